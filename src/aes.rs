@@ -0,0 +1,166 @@
+//! GF(2)-affine maps and the AES S-box.
+//!
+//! An Affine map sends a byte x to A x + c, where A is an 8 x 8 matrix
+//! over GF(2) and c a constant byte. A is stored by columns: columns[i] is
+//! the image of the bit 1 << i, so applying it xors the columns of the set
+//! bits of x.
+//!
+//! The AES S-box is the affine map AES_AFFINE applied to the inverse in
+//! the field of x^8 + x^4 + x^3 + x + 1 (0x11b), with 0 mapped to 0 before
+//! the affine step. sbox_table builds the same construction for any
+//! polynomial and affine map at compile time, SBOX and INV_SBOX are the
+//! AES tables.
+
+use crate::tables;
+
+/// x -> A x + c, see the module documentation
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Affine {
+	pub columns: [u8; 8],
+	pub constant: u8,
+}
+
+/// The affine step of the AES S-box. Bit i of the result is the xor of
+/// the bits i, i + 4, i + 5, i + 6 and i + 7 (mod 8) plus bit i of 0x63.
+pub const AES_AFFINE: Affine = {
+	let mut columns = [0u8; 8];
+	let mut j = 0;
+	while j < 8 {
+		columns[j] = 0x1fu8.rotate_left(j as u32);
+		j += 1;
+	}
+	Affine { columns, constant: 0x63 }
+};
+
+/// The AES S-box
+pub const SBOX: [u8; 256] = sbox_table(0x11b, &AES_AFFINE);
+
+/// The inverse of SBOX
+pub const INV_SBOX: [u8; 256] = invert_table(&SBOX);
+
+impl Affine {
+	/// The identity map
+	pub const IDENTITY: Affine = Affine {
+		columns: [1, 2, 4, 8, 16, 32, 64, 128],
+		constant: 0,
+	};
+
+	pub const fn new(columns: [u8; 8], constant: u8) -> Affine {
+		Affine { columns, constant }
+	}
+
+	/// A x + c
+	pub const fn apply(&self, x: u8) -> u8 {
+		let mut acc = self.constant;
+		let mut i = 0;
+		while i < 8 {
+			acc ^= self.columns[i] & 0u8.wrapping_sub(x >> i & 1);
+			i += 1;
+		}
+		acc
+	}
+
+	/// The map applying self first and then next
+	pub const fn then(&self, next: &Affine) -> Affine {
+		let mut columns = [0u8; 8];
+		let mut i = 0;
+		while i < 8 {
+			// apply adds the constant of next, which is not part of a column
+			columns[i] = next.apply(self.columns[i]) ^ next.constant;
+			i += 1;
+		}
+		Affine { columns, constant: next.apply(self.constant) }
+	}
+
+	/// The inverse map, None if the matrix is singular
+	pub const fn inverse(&self) -> Option<Affine> {
+		let linear = Affine { columns: self.columns, constant: 0 };
+		let mut columns = [0u8; 8];
+		let mut found = 0u8;
+		let mut x = 0;
+		while x < 256 {
+			let y = linear.apply(x as u8);
+			if y.count_ones() == 1 {
+				columns[y.trailing_zeros() as usize] = x as u8;
+				found |= y;
+			}
+			x += 1;
+		}
+		if found != 0xff {
+			return None;
+		}
+		let inv = Affine { columns, constant: 0 };
+		// x = A^-1 (y + c) = A^-1 y + A^-1 c
+		Some(Affine { columns, constant: inv.apply(self.constant) })
+	}
+}
+
+/// The S-box affine(x^-1) over the field of poly, with 0^-1 taken as 0
+pub const fn sbox_table(poly: u16, affine: &Affine) -> [u8; 256] {
+	let inv = tables::inv_table(poly);
+	let mut out = [0u8; 256];
+	let mut x = 0;
+	while x < 256 {
+		out[x] = affine.apply(inv[x]);
+		x += 1;
+	}
+	out
+}
+
+/// The inverse permutation of table. Fails const evaluation if table is
+/// not a permutation.
+pub const fn invert_table(table: &[u8; 256]) -> [u8; 256] {
+	let mut out = [0u8; 256];
+	let mut seen = [false; 256];
+	let mut x = 0;
+	while x < 256 {
+		let y = table[x] as usize;
+		assert!(!seen[y], "The table is not a permutation");
+		seen[y] = true;
+		out[y] = x as u8;
+		x += 1;
+	}
+	out
+}
+
+/// The AES S-box applied to b
+pub const fn aes_sbox(b: u8) -> u8 {
+	SBOX[b as usize]
+}
+
+/// The inverse AES S-box applied to b
+pub const fn aes_inv_sbox(b: u8) -> u8 {
+	INV_SBOX[b as usize]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sbox() {
+        // FIPS 197, figure 7 and section 5.1.1
+        assert_eq!(aes_sbox(0x00), 0x63);
+        assert_eq!(aes_sbox(0x01), 0x7c);
+        assert_eq!(aes_sbox(0x53), 0xed);
+        assert_eq!(aes_sbox(0xff), 0x16);
+        for b in 0..=255u8 {
+            assert_eq!(aes_inv_sbox(aes_sbox(b)), b);
+        }
+    }
+
+    #[test]
+    fn test_affine() {
+        let inv = AES_AFFINE.inverse().unwrap();
+        // The inverse affine step of the AES decryption
+        assert_eq!(inv.constant, 0x05);
+        assert_eq!(AES_AFFINE.then(&inv), Affine::IDENTITY);
+        for x in 0..=255u8 {
+            assert_eq!(inv.apply(AES_AFFINE.apply(x)), x);
+            assert_eq!(AES_AFFINE.then(&AES_AFFINE).apply(x), AES_AFFINE.apply(AES_AFFINE.apply(x)));
+        }
+        let singular = Affine::new([1, 1, 4, 8, 16, 32, 64, 128], 0);
+        assert_eq!(singular.inverse(), None);
+    }
+}
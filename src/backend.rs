@@ -0,0 +1,421 @@
+//! Interchangeable implementations of the bulk slice operations.
+//!
+//! The functions in bytes always pick the fastest strategy available.
+//! A Backend fixes one instead, for benchmarking strategies against each
+//! other or to force a particular code path:
+//!
+//! - Scalar multiplies bit by bit with masks instead of branches or
+//!   lookups, so its timing does not depend on the data. It is by far
+//!   the slowest; use it where table lookups could leak secrets through
+//!   the cache.
+//! - Table builds the 256 entry product table of the constant and looks
+//!   up every byte.
+//! - Nibble looks up both nibbles of every byte in two 16 entry tables,
+//!   the scalar counterpart of the vector kernels. It needs 32 products
+//!   per constant instead of 256 and keeps its tables in two cache lines.
+//! - Portable is the path of bytes on targets without a vector kernel:
+//!   it multiplies short slices byte by byte, uses the nibble tables from
+//!   32 bytes and the 256 entry table from 1024 on.
+//! - Clmul multiplies four bytes per carry-less multiplication, see
+//!   clmul::mul4_gf256. It is free of tables and, where the CPU has the
+//!   instruction, of data dependent timing, and faster than Scalar.
+//! - Simd uses the vector kernels of bytes and is only available when the
+//!   build or the CPU supports one of them. On x86_64 with GFNI those
+//!   multiply by an 8x8 bit matrix in one instruction; Simd::available
+//!   lists every kernel the CPU runs, to measure them against each other.
+//!
+//! best_available returns a backend that does what the bytes functions
+//! do: Simd with the detected kernel, or Portable. Matrix, RsEncoder and
+//! ErasureCode take a backend for their bulk loops, so a code can be
+//! pinned to e.g. Scalar.
+//!
+//! Which one is fastest varies with the slice length and, on virtualized
+//! or heterogeneous hosts, not always as the feature flags suggest. With
+//! std, calibrated times the candidates once per process and picks one
+//! per operation and size class.
+
+#[cfg(feature = "std")]
+use std::{ fmt, hint, sync::OnceLock, time::{ Duration, Instant } };
+
+use core::panic::RefUnwindSafe;
+
+use crate::bytes::{ self, Kernel };
+use crate::clmul::mul4_gf256;
+
+/// Bulk multiplication by a constant. Functions taking two slices panic
+/// if their lengths differ, as in bytes.
+pub trait Backend {
+	/// A short name for reports, e.g. "table" or "avx2"
+	fn name(&self) -> &'static str;
+
+	/// buf[i] = c * buf[i]
+	fn mul_scalar(&self, buf: &mut [u8], c: u8);
+
+	/// dst[i] = dst[i] + c * src[i]
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8);
+
+	/// dst[i] = c * src[i]
+	fn mul_scalar_into(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		dst.copy_from_slice(src);
+		self.mul_scalar(dst, c);
+	}
+}
+
+/// Branch-free multiplication without tables
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Scalar;
+
+/// Products of a byte with a constant from a 256 entry table
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Table;
+
+/// Products of a byte with a constant from two nibble tables
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Nibble;
+
+/// Table, Nibble or plain multiplication by slice length, as in bytes
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Portable;
+
+/// Carry-less multiplication of four bytes at a time
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Clmul;
+
+/// The vector kernels for the running CPU
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Simd {
+	kernel: Kernel,
+}
+
+/// a * b, running the same instructions for all inputs
+#[inline]
+pub fn mul_const_time(a: u8, b: u8) -> u8 {
+	let (mut a, mut b, mut acc) = (a, b, 0u8);
+	for _ in 0..8 {
+		acc ^= a & 0u8.wrapping_sub(b & 1);
+		// x^8 = x^4 + x^3 + x^2 + 1
+		a = (a << 1) ^ (0x1d & 0u8.wrapping_sub(a >> 7));
+		b >>= 1;
+	}
+	acc
+}
+
+impl Backend for Scalar {
+	fn name(&self) -> &'static str {
+		"scalar"
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		for b in buf.iter_mut() {
+			*b = mul_const_time(*b, c);
+		}
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		for (d, &s) in dst.iter_mut().zip(src) {
+			*d ^= mul_const_time(s, c);
+		}
+	}
+}
+
+impl Backend for Table {
+	fn name(&self) -> &'static str {
+		"table"
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		let table = bytes::mul_table(c);
+		for b in buf.iter_mut() {
+			*b = table[*b as usize];
+		}
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		let table = bytes::mul_table(c);
+		for (d, &s) in dst.iter_mut().zip(src) {
+			*d ^= table[s as usize];
+		}
+	}
+}
+
+impl Backend for Nibble {
+	fn name(&self) -> &'static str {
+		"nibble"
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		let (lo, hi) = bytes::nibble_tables(c);
+		for b in buf.iter_mut() {
+			*b = lo[(*b & 15) as usize] ^ hi[(*b >> 4) as usize];
+		}
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		let (lo, hi) = bytes::nibble_tables(c);
+		for (d, &s) in dst.iter_mut().zip(src) {
+			*d ^= lo[(s & 15) as usize] ^ hi[(s >> 4) as usize];
+		}
+	}
+}
+
+impl Backend for Portable {
+	fn name(&self) -> &'static str {
+		"portable"
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		bytes::portable_mul(buf, c);
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		bytes::portable_mul_add(dst, src, c);
+	}
+}
+
+/// Spreads up to four bytes into the 16 bit lanes of a word
+fn spread(bytes: &[u8]) -> u64 {
+	bytes.iter().rev().fold(0, |acc, &b| acc << 16 | b as u64)
+}
+
+fn gather(lanes: u64, bytes: &mut [u8]) {
+	for (i, b) in bytes.iter_mut().enumerate() {
+		*b = (lanes >> (16 * i)) as u8;
+	}
+}
+
+impl Backend for Clmul {
+	fn name(&self) -> &'static str {
+		"clmul"
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		for chunk in buf.chunks_mut(4) {
+			let p = mul4_gf256(spread(chunk), c);
+			gather(p, chunk);
+		}
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		for (d, s) in dst.chunks_mut(4).zip(src.chunks(4)) {
+			let p = mul4_gf256(spread(s), c) ^ spread(d);
+			gather(p, d);
+		}
+	}
+}
+
+impl Simd {
+	/// None if neither the build nor the CPU supports a vector kernel
+	pub fn detect() -> Option<Simd> {
+		Kernel::detect().map(|kernel| Simd { kernel })
+	}
+
+	/// Every kernel the build or the CPU supports, fastest first
+	pub fn available() -> impl Iterator<Item = Simd> {
+		Kernel::ALL.into_iter().filter(|k| k.is_supported()).map(|kernel| Simd { kernel })
+	}
+}
+
+impl Backend for Simd {
+	fn name(&self) -> &'static str {
+		self.kernel.name()
+	}
+
+	fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		let ptr = buf.as_mut_ptr();
+		// The kernel stays within the slice and came from detect
+		let done = unsafe { self.kernel.run::<false>(ptr, ptr, buf.len(), c) };
+		bytes::portable_mul(&mut buf[done..], c);
+	}
+
+	fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		let done = unsafe { self.kernel.run::<true>(dst.as_mut_ptr(), src.as_ptr(), dst.len(), c) };
+		bytes::portable_mul_add(&mut dst[done..], &src[done..], c);
+	}
+}
+
+/// The Simd backend of kernel as a static
+fn simd_static(kernel: Kernel) -> &'static Simd {
+	static GFNI_AVX2: Simd = Simd { kernel: Kernel::GfniAvx2 };
+	static GFNI: Simd = Simd { kernel: Kernel::Gfni };
+	static AVX2: Simd = Simd { kernel: Kernel::Avx2 };
+	static SSSE3: Simd = Simd { kernel: Kernel::Ssse3 };
+	static NEON: Simd = Simd { kernel: Kernel::Neon };
+	static SIMD128: Simd = Simd { kernel: Kernel::Simd128 };
+	match kernel {
+		Kernel::GfniAvx2 => &GFNI_AVX2,
+		Kernel::Avx2 => &AVX2,
+		Kernel::Gfni => &GFNI,
+		Kernel::Ssse3 => &SSSE3,
+		Kernel::Neon => &NEON,
+		Kernel::Simd128 => &SIMD128,
+	}
+}
+
+/// Simd where a kernel is available, Portable otherwise
+pub fn best_available() -> &'static (dyn Backend + Sync + RefUnwindSafe) {
+	match Kernel::detect() {
+		Some(kernel) => simd_static(kernel),
+		None => &Portable,
+	}
+}
+
+/// Upper ends of the size classes calibrate measures, in bytes. Longer
+/// slices fall into the last class.
+#[cfg(feature = "std")]
+pub const SIZE_CLASSES: [usize; 4] = [64, 1024, 16384, 262144];
+
+/// The fastest backend per operation and size class, as measured on the
+/// running machine
+#[cfg(feature = "std")]
+#[derive(Copy,Clone)]
+pub struct Calibration {
+	mul_scalar: [&'static (dyn Backend + Sync + RefUnwindSafe); SIZE_CLASSES.len()],
+	mul_add: [&'static (dyn Backend + Sync + RefUnwindSafe); SIZE_CLASSES.len()],
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for Calibration {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let names = |b: &[&(dyn Backend + Sync + RefUnwindSafe)]| b.iter().map(|b| b.name()).collect::<Vec<_>>();
+		f.debug_struct("Calibration")
+			.field("mul_scalar", &names(&self.mul_scalar))
+			.field("mul_add", &names(&self.mul_add))
+			.finish()
+	}
+}
+
+#[cfg(feature = "std")]
+fn size_class(len: usize) -> usize {
+	SIZE_CLASSES.iter().position(|&max| len <= max).unwrap_or(SIZE_CLASSES.len() - 1)
+}
+
+/// Fastest of three runs of op over about 1 MiB in slices of len bytes
+#[cfg(feature = "std")]
+fn time(len: usize, mut op: impl FnMut(&mut [u8], &[u8])) -> Duration {
+	let src: Vec<u8> = (0..len).map(|i| (i * 167 + 13) as u8).collect();
+	let mut dst = vec![0u8; len];
+	let reps = (1 << 20) / len;
+	(0..3).map(|_| {
+		let start = Instant::now();
+		for _ in 0..reps {
+			op(&mut dst, &src);
+			hint::black_box(&mut dst);
+		}
+		start.elapsed()
+	}).min().expect("Three runs")
+}
+
+#[cfg(feature = "std")]
+impl Calibration {
+	/// Times Portable, Table, Nibble and every kernel of Simd::available on each
+	/// size class. This takes some tens of milliseconds; calibrated runs
+	/// it once and keeps the result.
+	pub fn measure() -> Calibration {
+		let mut candidates: Vec<&'static (dyn Backend + Sync + RefUnwindSafe)> = vec![&Portable, &Table, &Nibble];
+		candidates.extend(Simd::available()
+			.map(|s| simd_static(s.kernel) as &(dyn Backend + Sync + RefUnwindSafe)));
+		let fastest = |len: usize, add: bool| {
+			candidates.iter().copied().min_by_key(|b| time(len, |dst, src| if add {
+				b.mul_add(dst, src, 0x8e)
+			} else {
+				b.mul_scalar(dst, 0x8e)
+			})).expect("Table is always a candidate")
+		};
+		let mut cal = Calibration { mul_scalar: [&Table; SIZE_CLASSES.len()], mul_add: [&Table; SIZE_CLASSES.len()] };
+		for (i, &len) in SIZE_CLASSES.iter().enumerate() {
+			cal.mul_scalar[i] = fastest(len, false);
+			cal.mul_add[i] = fastest(len, true);
+		}
+		cal
+	}
+
+	/// The backend for mul_scalar and mul_scalar_into on len bytes
+	pub fn mul_scalar(&self, len: usize) -> &'static dyn Backend {
+		self.mul_scalar[size_class(len)]
+	}
+
+	/// The backend for mul_add on len bytes
+	pub fn mul_add(&self, len: usize) -> &'static dyn Backend {
+		self.mul_add[size_class(len)]
+	}
+}
+
+/// Measures the backends on first use and returns the cached winners
+/// from then on
+#[cfg(feature = "std")]
+pub fn calibrated() -> &'static Calibration {
+	static CALIBRATION: OnceLock<Calibration> = OnceLock::new();
+	CALIBRATION.get_or_init(Calibration::measure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+
+    #[test]
+    fn test_const_time_mul() {
+        for a in 0..=255u8 {
+            for b in (0..=255u8).step_by(7) {
+                assert_eq!(mul_const_time(a, b), (Gf256::from_byte(a) * Gf256::from_byte(b)).to_byte());
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let src: Vec<u8> = (0..777).map(|i| (i * 89 + 3) as u8).collect();
+        let dst0: Vec<u8> = (0..777).map(|i| (i * 5) as u8).collect();
+        for c in [0u8, 1, 2, 0x8e, 0xff] {
+            let mut expected = dst0.clone();
+            bytes::mul_add(&mut expected, &src, c);
+            let mut scaled = src.clone();
+            bytes::mul_scalar(&mut scaled, c);
+            let simd: Vec<Simd> = Simd::available().collect();
+            let mut backends: Vec<&dyn Backend> = vec![&Scalar, &Table, &Nibble, &Clmul];
+            backends.extend(simd.iter().map(|s| s as &dyn Backend));
+            for b in backends {
+                let mut d = dst0.clone();
+                b.mul_add(&mut d, &src, c);
+                assert_eq!(d, expected, "{}", b.name());
+                let mut s = src.clone();
+                b.mul_scalar(&mut s, c);
+                assert_eq!(s, scaled, "{}", b.name());
+                b.mul_scalar_into(&mut d, &src, c);
+                assert_eq!(d, scaled);
+            }
+        }
+        if let Some(simd) = Simd::detect() {
+            assert_eq!(best_available().name(), simd.name());
+            assert_eq!(Simd::available().next(), Some(simd));
+        } else {
+            assert_eq!(best_available().name(), "portable");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_calibrated() {
+        let cal = calibrated();
+        assert!(core::ptr::eq(cal, calibrated()));
+        let names: Vec<&str> = ["portable", "table", "nibble"].into_iter()
+            .chain(Simd::available().map(|s| s.name())).collect();
+        for len in [0, 64, 65, 5000, 1 << 30] {
+            assert!(names.contains(&cal.mul_scalar(len).name()));
+            assert!(names.contains(&cal.mul_add(len).name()));
+        }
+        let src: Vec<u8> = (0..3000).map(|i| i as u8).collect();
+        let mut expected = vec![7u8; 3000];
+        bytes::mul_add(&mut expected, &src, 0x35);
+        let mut d = vec![7u8; 3000];
+        cal.mul_add(d.len()).mul_add(&mut d, &src, 0x35);
+        assert_eq!(d, expected);
+    }
+}
@@ -0,0 +1,295 @@
+//! Bases of GF(256) over GF(2).
+//!
+//! Gf256 stores an element by its coordinates in the polynomial basis
+//! 1, x, ..., x^7. Any other eight linearly independent elements form a
+//! basis as well; Basis converts coordinates in it to and from elements.
+//! Like the matrices of tower, the change of basis is kept as the images
+//! of the eight coordinate bits: to_polynomial()[i] is the byte of basis
+//! element i, and from_polynomial()[i] the coordinates of x^i.
+//!
+//! A normal basis consists of the conjugates b, b^2, b^4, ..., b^128 of
+//! one element b. Squaring maps each of them to the next, so in normal
+//! basis coordinates it is a rotation by one bit, which is why hardware
+//! likes them. NormalBasis multiplies in that representation with the
+//! Massey-Omura scheme: bit k of the product is one fixed bilinear form
+//! applied to both operands rotated by k bits, so a circuit for bit 0
+//! computes every bit.
+//!
+//! The dual of a basis b_i is the basis d_j with Tr(b_i d_j) = 1 if i = j
+//! and 0 otherwise. Coordinate j of an element a in the dual basis is
+//! then Tr(a b_j), the convention of Berlekamp's bit-serial encoders.
+
+use crate::Gf256;
+
+/// The GF(2)-linear map with the given column images, applied to x
+const fn apply(columns: &[u8; 8], x: u8) -> u8 {
+	let mut acc = 0;
+	let mut i = 0;
+	while i < 8 {
+		acc ^= columns[i] & 0u8.wrapping_sub(x >> i & 1);
+		i += 1;
+	}
+	acc
+}
+
+/// A basis of GF(256) over GF(2)
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Basis {
+	to_poly: [u8; 8],
+	from_poly: [u8; 8],
+}
+
+impl Basis {
+	/// 1, x, ..., x^7, the representation of Gf256 itself
+	pub const POLYNOMIAL: Basis = Basis {
+		to_poly: [1, 2, 4, 8, 16, 32, 64, 128],
+		from_poly: [1, 2, 4, 8, 16, 32, 64, 128],
+	};
+
+	/// None if the elements are linearly dependent
+	pub const fn new(elements: [Gf256; 8]) -> Option<Basis> {
+		let mut to_poly = [0u8; 8];
+		let mut i = 0;
+		while i < 8 {
+			to_poly[i] = elements[i].to_byte();
+			i += 1;
+		}
+		// Invert by finding the coordinates of every x^i
+		let mut from_poly = [0u8; 8];
+		let mut found = 0u8;
+		let mut c = 0;
+		while c < 256 {
+			let a = apply(&to_poly, c as u8);
+			if a.count_ones() == 1 {
+				from_poly[a.trailing_zeros() as usize] = c as u8;
+				found |= a;
+			}
+			c += 1;
+		}
+		if found != 0xff {
+			return None;
+		}
+		Some(Basis { to_poly, from_poly })
+	}
+
+	/// Basis element i
+	pub const fn element(&self, i: usize) -> Gf256 {
+		Gf256::from_byte(self.to_poly[i])
+	}
+
+	/// The elements of the basis as bytes, the columns of the matrix from
+	/// coordinates to the polynomial basis
+	pub const fn to_polynomial(&self) -> [u8; 8] {
+		self.to_poly
+	}
+
+	/// The coordinates of 1, x, ..., x^7, the columns of the matrix from
+	/// the polynomial basis to coordinates
+	pub const fn from_polynomial(&self) -> [u8; 8] {
+		self.from_poly
+	}
+
+	/// The coordinates of a, bit i for element i
+	pub const fn coordinates(&self, a: Gf256) -> u8 {
+		apply(&self.from_poly, a.to_byte())
+	}
+
+	/// The element with the given coordinates
+	pub const fn from_coordinates(&self, c: u8) -> Gf256 {
+		Gf256::from_byte(apply(&self.to_poly, c))
+	}
+
+	/// The trace-dual basis
+	pub const fn dual(&self) -> Basis {
+		let mut elements = [Gf256::ZERO; 8];
+		let mut d = 0;
+		while d < 256 {
+			// Tr(d b_i) for every i, a unit vector for dual elements
+			let e = Gf256::from_byte(d as u8);
+			let mut traces = 0u8;
+			let mut i = 0;
+			while i < 8 {
+				traces |= e.const_mul(self.element(i)).trace() << i;
+				i += 1;
+			}
+			if traces.count_ones() == 1 {
+				elements[traces.trailing_zeros() as usize] = e;
+			}
+			d += 1;
+		}
+		match Basis::new(elements) {
+			Some(b) => b,
+			None => panic!("The trace form is nondegenerate"),
+		}
+	}
+}
+
+/// A normal basis b, b^2, ..., b^128 with Massey-Omura multiplication
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct NormalBasis {
+	basis: Basis,
+	/// Bit j of form[i] is coordinate 0 of b^(2^i) * b^(2^j)
+	form: [u8; 8],
+}
+
+impl NormalBasis {
+	/// The normal basis of the smallest element generating one
+	pub const FIRST: NormalBasis = {
+		let mut b = 1;
+		loop {
+			if let Some(n) = NormalBasis::new(Gf256::from_byte(b)) {
+				break n;
+			}
+			b += 1;
+		}
+	};
+
+	/// None unless the conjugates of b are linearly independent
+	pub const fn new(b: Gf256) -> Option<NormalBasis> {
+		let mut elements = [Gf256::ZERO; 8];
+		let mut c = b;
+		let mut i = 0;
+		while i < 8 {
+			elements[i] = c;
+			c = c.square();
+			i += 1;
+		}
+		let basis = match Basis::new(elements) {
+			Some(basis) => basis,
+			None => return None,
+		};
+		let mut form = [0u8; 8];
+		let mut i = 0;
+		while i < 8 {
+			let mut j = 0;
+			while j < 8 {
+				let p = elements[i].const_mul(elements[j]);
+				form[i] |= (basis.coordinates(p) & 1) << j;
+				j += 1;
+			}
+			i += 1;
+		}
+		Some(NormalBasis { basis, form })
+	}
+
+	pub const fn basis(&self) -> &Basis {
+		&self.basis
+	}
+
+	/// b, whose conjugates form the basis
+	pub const fn normal_element(&self) -> Gf256 {
+		self.basis.element(0)
+	}
+
+	/// The products of the bilinear form, bit j of row i
+	pub const fn form(&self) -> [u8; 8] {
+		self.form
+	}
+
+	/// Number of ones in the form, the usual cost measure of a
+	/// Massey-Omura multiplier
+	pub const fn complexity(&self) -> u32 {
+		let mut n = 0;
+		let mut i = 0;
+		while i < 8 {
+			n += self.form[i].count_ones();
+			i += 1;
+		}
+		n
+	}
+
+	pub const fn coordinates(&self, a: Gf256) -> u8 {
+		self.basis.coordinates(a)
+	}
+
+	pub const fn from_coordinates(&self, c: u8) -> Gf256 {
+		self.basis.from_coordinates(c)
+	}
+
+	/// The coordinates of the square, a rotation
+	pub const fn square(c: u8) -> u8 {
+		c.rotate_left(1)
+	}
+
+	/// The coordinates of the square root
+	pub const fn sqrt(c: u8) -> u8 {
+		c.rotate_right(1)
+	}
+
+	/// The product of two elements given by their coordinates
+	pub const fn mul(&self, a: u8, b: u8) -> u8 {
+		let mut out = 0u8;
+		let mut k = 0;
+		while k < 8 {
+			let (x, y) = (a.rotate_right(k), b.rotate_right(k));
+			let mut bit = 0u32;
+			let mut i = 0;
+			while i < 8 {
+				if x >> i & 1 == 1 {
+					bit ^= (self.form[i] & y).count_ones();
+				}
+				i += 1;
+			}
+			out |= ((bit & 1) as u8) << k;
+			k += 1;
+		}
+		out
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basis() {
+        let p = Basis::POLYNOMIAL;
+        assert_eq!(Basis::new(core::array::from_fn(|i| Gf256::from_byte(1 << i))), Some(p));
+        for a in Gf256::all() {
+            assert_eq!(p.coordinates(a), a.to_byte());
+        }
+        // Powers of a generator form a basis, powers in GF(16) do not
+        let b = Basis::new(core::array::from_fn(|i| Gf256::xexp(3 * i as u8))).unwrap();
+        for a in Gf256::all() {
+            assert_eq!(b.from_coordinates(b.coordinates(a)), a);
+        }
+        assert_eq!(Basis::new(core::array::from_fn(|i| Gf256::xexp(17 * i as u8))), None);
+    }
+
+    #[test]
+    fn test_dual() {
+        let b = Basis::new(core::array::from_fn(|i| Gf256::xexp(i as u8 + 5))).unwrap();
+        let d = b.dual();
+        for i in 0..8 {
+            for j in 0..8 {
+                assert_eq!((b.element(i) * d.element(j)).trace(), (i == j) as u8);
+            }
+        }
+        assert_eq!(d.dual(), b);
+        for a in Gf256::all() {
+            let c = d.coordinates(a);
+            assert!((0..8).all(|j| c >> j & 1 == (a * b.element(j)).trace()));
+        }
+    }
+
+    #[test]
+    fn test_normal_basis() {
+        let n = NormalBasis::FIRST;
+        let b = n.normal_element();
+        assert!(Gf256::all().take(b.to_byte() as usize).all(|a| NormalBasis::new(a).is_none()));
+        // One is its own conjugate, so it never generates a normal basis
+        assert_eq!(NormalBasis::new(Gf256::one()), None);
+        for x in Gf256::all().step_by(3) {
+            let cx = n.coordinates(x);
+            assert_eq!(n.from_coordinates(NormalBasis::square(cx)), x.square());
+            assert_eq!(n.from_coordinates(NormalBasis::sqrt(cx)), x.sqrt());
+            for y in Gf256::all().step_by(7) {
+                assert_eq!(n.from_coordinates(n.mul(cx, n.coordinates(y))), x * y);
+            }
+        }
+        // One is the sum of all conjugates, so its coordinates are all set
+        assert_eq!(n.coordinates(Gf256::one()), 0xff);
+        assert!(n.complexity() >= 2 * 8 - 1);
+    }
+}
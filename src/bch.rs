@@ -0,0 +1,506 @@
+//! Binary BCH codes for NAND flash pages.
+//!
+//! A Bch corrects up to t bit errors in a block of data_len bytes plus
+//! its ECC bytes, like the codes of NAND controllers and the Linux bch
+//! library. It is a shortened narrow-sense binary BCH code over GF(2^m);
+//! m is the smallest value in 5..=15 for which 8 data_len + m t bits fit
+//! into a code of length 2^m - 1 (so 13 for 512 byte sectors). The
+//! primitive polynomials are the ones used by Linux.
+//!
+//! Controllers differ in the order in which they feed bits to the code:
+//! BitOrder::MsbFirst reads every byte from bit 7 down to bit 0, LsbFirst
+//! from bit 0 up. The same order applies to the ECC bytes, whose unused
+//! trailing bits are zero.
+//!
+//! For BCH codes of length 255 over Gf256 itself, cyclotomic_coset(s),
+//! minimal_polynomial and generator build the generator polynomial;
+//! CyclicCode::bch and cyclic::BchDecoder encode and decode with it.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+
+/// Primitive polynomials for m = 5..=15
+const PRIMITIVE: [u32; 11] = [
+	0x25, 0x43, 0x83, 0x11d, 0x211, 0x409, 0x805, 0x1053, 0x201b, 0x402b, 0x8003,
+];
+
+/// The exponents i, 2i, 4i, ... mod 255 of the conjugates of x^i in
+/// Gf256, the roots of its minimal polynomial
+pub fn cyclotomic_coset(i: usize) -> Vec<usize> {
+	let mut coset = vec![i % 255];
+	let mut e = 2 * i % 255;
+	while e != coset[0] {
+		coset.push(e);
+		e = 2 * e % 255;
+	}
+	coset
+}
+
+/// All cyclotomic cosets of 2 mod 255, each starting with its smallest
+/// member, in increasing order of that member. They partition 0..255
+/// into the exponent sets of the conjugacy classes.
+pub fn cyclotomic_cosets() -> Vec<Vec<usize>> {
+	let mut seen = [false; 255];
+	let mut cosets = Vec::new();
+	for i in 0..255 {
+		if !seen[i] {
+			let coset = cyclotomic_coset(i);
+			for &e in &coset {
+				seen[e] = true;
+			}
+			cosets.push(coset);
+		}
+	}
+	cosets
+}
+
+/// The product of x - x^e over the exponents e, lowest degree first
+fn roots_product<I: IntoIterator<Item = usize>>(exponents: I) -> Vec<Gf256> {
+	let mut p = vec![Gf256::one()];
+	for e in exponents {
+		let root = Gf256::xexp(e as u8);
+		p.insert(0, Gf256::zero());
+		for j in 0..p.len() - 1 {
+			let next = p[j + 1];
+			p[j] += next * root;
+		}
+	}
+	p
+}
+
+/// The minimal polynomial of x^i over GF(2), lowest degree first. All
+/// coefficients are 0 or 1.
+pub fn minimal_polynomial(i: usize) -> Vec<Gf256> {
+	roots_product(cyclotomic_coset(i))
+}
+
+/// The generator of the narrow-sense binary BCH code of length 255 with
+/// designed distance 2t + 1: the least common multiple of the minimal
+/// polynomials of x^1, ..., x^2t, lowest degree first
+pub fn generator(t: usize) -> Vec<Gf256> {
+	let mut seen = [false; 255];
+	for i in 1..=2 * t {
+		for e in cyclotomic_coset(i) {
+			seen[e] = true;
+		}
+	}
+	roots_product((0..255).filter(|&e| seen[e]))
+}
+
+/// Errors produced by Bch
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum BchError {
+	/// t is zero or the code would be longer than 2^15 - 1 bits
+	InvalidParameters,
+	/// A buffer has the wrong length
+	LengthMismatch,
+	/// There are more than t bit errors
+	Uncorrectable,
+}
+
+impl fmt::Display for BchError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			BchError::InvalidParameters => "invalid code parameters",
+			BchError::LengthMismatch => "buffer has the wrong length",
+			BchError::Uncorrectable => "too many bit errors",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for BchError {}
+
+/// Order in which the bits of a byte enter the code
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub enum BitOrder {
+	#[default]
+	MsbFirst,
+	LsbFirst,
+}
+
+impl BitOrder {
+	#[inline]
+	fn get(self, buf: &[u8], i: usize) -> bool {
+		let shift = match self {
+			BitOrder::MsbFirst => 7 - i % 8,
+			BitOrder::LsbFirst => i % 8,
+		};
+		buf[i / 8] >> shift & 1 == 1
+	}
+
+	#[inline]
+	fn flip(self, buf: &mut [u8], i: usize) {
+		let shift = match self {
+			BitOrder::MsbFirst => 7 - i % 8,
+			BitOrder::LsbFirst => i % 8,
+		};
+		buf[i / 8] ^= 1 << shift;
+	}
+}
+
+/// GF(2^m) with log tables
+#[derive(Clone,Debug)]
+struct Field {
+	/// 2^m - 1
+	n: usize,
+	exp: Vec<u16>,
+	log: Vec<u16>,
+}
+
+impl Field {
+	fn new(m: usize) -> Field {
+		let n = (1 << m) - 1;
+		let poly = PRIMITIVE[m - 5];
+		let mut exp = vec![0u16; 2 * n];
+		let mut log = vec![0u16; n + 1];
+		let mut x: u32 = 1;
+		for i in 0..n {
+			exp[i] = x as u16;
+			exp[i + n] = x as u16;
+			log[x as usize] = i as u16;
+			x <<= 1;
+			if x >> m & 1 == 1 {
+				x ^= poly;
+			}
+		}
+		Field { n, exp, log }
+	}
+
+	/// a^e for the primitive element a
+	#[inline]
+	fn alpha(&self, e: usize) -> u16 {
+		self.exp[e % self.n]
+	}
+
+	#[inline]
+	fn mul(&self, a: u16, b: u16) -> u16 {
+		if a == 0 || b == 0 {
+			0
+		} else {
+			self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+		}
+	}
+
+	#[inline]
+	fn div(&self, a: u16, b: u16) -> u16 {
+		if a == 0 {
+			0
+		} else {
+			let l = self.log[a as usize] as usize + self.n - self.log[b as usize] as usize;
+			self.exp[l % self.n]
+		}
+	}
+}
+
+/// A binary BCH code for blocks of data_len bytes
+#[derive(Clone,Debug)]
+pub struct Bch {
+	data_len: usize,
+	t: usize,
+	m: usize,
+	order: BitOrder,
+	field: Field,
+	/// Generator polynomial without its leading term, bit j of the
+	/// words standing for x^j
+	gen: Vec<u64>,
+	/// Degree of the generator, i.e. number of ECC bits
+	ecc_bits: usize,
+}
+
+impl Bch {
+	/// Creates a code correcting t bit errors in data_len bytes
+	pub fn new(data_len: usize, t: usize, order: BitOrder) -> Result<Bch, BchError> {
+		if t == 0 || data_len == 0 {
+			return Err(BchError::InvalidParameters);
+		}
+		let m = (5..=15).find(|&m| 8 * data_len + m * t < 1 << m)
+			.ok_or(BchError::InvalidParameters)?;
+		let field = Field::new(m);
+
+		// Product of the minimal polynomials of a^1, a^3, ..., a^(2t - 1).
+		// The even powers share the minimal polynomial of an odd one.
+		let mut gen = vec![1u16];
+		let mut seen = vec![false; field.n];
+		for i in (1..2 * t).step_by(2) {
+			let mut e = i;
+			while !seen[e] {
+				seen[e] = true;
+				// gen *= (x - a^e)
+				let root = field.alpha(e);
+				gen.push(0);
+				for j in (1..gen.len()).rev() {
+					gen[j] ^= field.mul(gen[j - 1], root);
+				}
+				e = e * 2 % field.n;
+			}
+		}
+		// gen is listed highest degree first and has binary coefficients
+		let ecc_bits = gen.len() - 1;
+		let mut words = vec![0u64; ecc_bits.div_ceil(64)];
+		for j in 0..ecc_bits {
+			if gen[ecc_bits - j] == 1 {
+				words[j / 64] |= 1 << (j % 64);
+			}
+		}
+		Ok(Bch { data_len, t, m, order, field, gen: words, ecc_bits })
+	}
+
+	pub fn data_len(&self) -> usize {
+		self.data_len
+	}
+
+	/// Number of correctable bit errors
+	pub fn t(&self) -> usize {
+		self.t
+	}
+
+	/// Degree of the underlying field GF(2^m)
+	pub fn m(&self) -> usize {
+		self.m
+	}
+
+	/// Number of ECC bits
+	pub fn ecc_bits(&self) -> usize {
+		self.ecc_bits
+	}
+
+	/// Number of ECC bytes
+	pub fn ecc_len(&self) -> usize {
+		self.ecc_bits.div_ceil(8)
+	}
+
+	fn check(&self, data: &[u8], ecc: &[u8]) -> Result<(), BchError> {
+		if data.len() != self.data_len || ecc.len() != self.ecc_len() {
+			return Err(BchError::LengthMismatch);
+		}
+		Ok(())
+	}
+
+	/// Computes the ECC bytes of data
+	pub fn encode(&self, data: &[u8], ecc: &mut [u8]) -> Result<(), BchError> {
+		self.check(data, ecc)?;
+		let r = self.ecc_bits;
+		let top = (r - 1) / 64;
+		let top_mask = if r.is_multiple_of(64) { !0 } else { (1u64 << (r % 64)) - 1 };
+		let mut rem = vec![0u64; self.gen.len()];
+		for i in 0..8 * self.data_len {
+			// One step of the division LFSR: rem = rem * x + bit * x^r mod g
+			let feedback = self.order.get(data, i) ^ (rem[top] >> ((r - 1) % 64) & 1 == 1);
+			for w in (0..rem.len()).rev() {
+				let carry = if w > 0 { rem[w - 1] >> 63 } else { 0 };
+				rem[w] = rem[w] << 1 | carry;
+			}
+			rem[top] &= top_mask;
+			if feedback {
+				for (r, g) in rem.iter_mut().zip(&self.gen) {
+					*r ^= g;
+				}
+			}
+		}
+		for b in ecc.iter_mut() {
+			*b = 0;
+		}
+		for i in 0..r {
+			let deg = r - 1 - i;
+			if rem[deg / 64] >> (deg % 64) & 1 == 1 {
+				self.order.flip(ecc, i);
+			}
+		}
+		Ok(())
+	}
+
+	/// Corrects up to t bit errors in data and ecc and returns the number
+	/// of flipped bits. Nothing is modified if the block is uncorrectable.
+	pub fn decode(&self, data: &mut [u8], ecc: &mut [u8]) -> Result<usize, BchError> {
+		self.check(data, ecc)?;
+		let f = &self.field;
+		let r = self.ecc_bits;
+		let k = 8 * self.data_len;
+		let n = k + r;
+
+		// S_j = c(a^j) for j = 1..=2t; S_2j = S_j^2 in characteristic 2
+		let mut syn = vec![0u16; 2 * self.t + 1];
+		let set_bits = (0..k).filter(|&i| self.order.get(data, i)).map(|i| n - 1 - i)
+			.chain((0..r).filter(|&i| self.order.get(ecc, i)).map(|i| r - 1 - i));
+		for deg in set_bits {
+			for j in (1..=2 * self.t).step_by(2) {
+				syn[j] ^= f.alpha(j * deg);
+			}
+		}
+		for j in (2..=2 * self.t).step_by(2) {
+			syn[j] = f.mul(syn[j / 2], syn[j / 2]);
+		}
+		if syn.iter().all(|&s| s == 0) {
+			return Ok(0);
+		}
+
+		// Berlekamp-Massey
+		let mut lambda = vec![0u16; self.t + 2];
+		let mut prev = vec![0u16; self.t + 2];
+		lambda[0] = 1;
+		prev[0] = 1;
+		let mut len = 0;
+		let mut last_disc = 1u16;
+		let mut shift = 1;
+		for step in 0..2 * self.t {
+			let mut disc = syn[step + 1];
+			for i in 1..=len {
+				disc ^= f.mul(lambda[i], syn[step + 1 - i]);
+			}
+			if disc == 0 {
+				shift += 1;
+				continue;
+			}
+			let scale = f.div(disc, last_disc);
+			let old = lambda.clone();
+			for i in shift..lambda.len() {
+				lambda[i] ^= f.mul(scale, prev[i - shift]);
+			}
+			if 2 * len <= step {
+				len = step + 1 - len;
+				if len > self.t {
+					return Err(BchError::Uncorrectable);
+				}
+				prev = old;
+				last_disc = disc;
+				shift = 1;
+			} else {
+				shift += 1;
+			}
+		}
+
+		// Chien search over the degrees of the shortened code
+		let mut errors = Vec::with_capacity(len);
+		for deg in 0..n {
+			let inv = f.n - deg % f.n;
+			let mut acc = 0;
+			for (i, &l) in lambda[..=len].iter().enumerate() {
+				acc ^= f.mul(l, f.alpha(inv * i));
+			}
+			if acc == 0 {
+				errors.push(deg);
+			}
+		}
+		if errors.len() != len {
+			return Err(BchError::Uncorrectable);
+		}
+		for &deg in &errors {
+			if deg >= r {
+				self.order.flip(data, n - 1 - deg);
+			} else {
+				self.order.flip(ecc, r - 1 - deg);
+			}
+		}
+		Ok(len)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corrupt::Corruptor;
+
+    fn page(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 89 + 17) as u8).collect()
+    }
+
+    #[test]
+    fn test_cosets_and_generator() {
+        assert_eq!(cyclotomic_coset(0), [0]);
+        assert_eq!(cyclotomic_coset(1), [1, 2, 4, 8, 16, 32, 64, 128]);
+        assert_eq!(cyclotomic_coset(17), [17, 34, 68, 136]);
+        assert_eq!(cyclotomic_coset(85), [85, 170]);
+        let cosets = cyclotomic_cosets();
+        // 1 of size 1, 1 of size 2, 3 of size 4 and 30 of size 8
+        assert_eq!(cosets.len(), 35);
+        assert_eq!(cosets.iter().map(Vec::len).sum::<usize>(), 255);
+        assert_eq!(cosets[..3], [vec![0], cyclotomic_coset(1), cyclotomic_coset(3)]);
+        assert!(cosets.iter().all(|c| c.iter().all(|&e| e >= c[0])));
+        // The field polynomial is the minimal polynomial of x
+        let bits: Vec<u8> = minimal_polynomial(1).iter().map(|c| c.to_byte()).collect();
+        assert_eq!(bits, [1, 0, 1, 1, 1, 0, 0, 0, 1]);
+        assert_eq!(minimal_polynomial(2), minimal_polynomial(1));
+        assert_eq!(generator(1), minimal_polynomial(1));
+        assert_eq!(generator(2).len(), 17);
+        for t in 1..=10 {
+            let g = generator(t);
+            assert!(g.iter().all(|c| c.to_byte() <= 1));
+            assert!(Gf256::powers_of(Gf256::GENERATOR).skip(1).take(2 * t).all(|x| {
+                g.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c) == Gf256::zero()
+            }));
+        }
+    }
+
+    #[test]
+    fn test_parameters() {
+        let bch = Bch::new(512, 4, BitOrder::MsbFirst).unwrap();
+        assert_eq!(bch.m(), 13);
+        assert_eq!(bch.ecc_bits(), 52);
+        assert_eq!(bch.ecc_len(), 7);
+        assert_eq!(Bch::new(512, 8, BitOrder::MsbFirst).unwrap().ecc_len(), 13);
+        assert_eq!(Bch::new(1024, 40, BitOrder::MsbFirst).unwrap().ecc_len(), 70);
+        assert_eq!(Bch::new(512, 0, BitOrder::MsbFirst).err(), Some(BchError::InvalidParameters));
+    }
+
+    #[test]
+    fn test_correct_bit_errors() {
+        let mut rng = Corruptor::new(11);
+        for &(len, t) in &[(512, 4), (516, 8), (64, 12)] {
+            let bch = Bch::new(len, t, BitOrder::MsbFirst).unwrap();
+            let data = page(len);
+            let mut ecc = vec![0u8; bch.ecc_len()];
+            bch.encode(&data, &mut ecc).unwrap();
+
+            for errors in 0..=t {
+                let mut block: Vec<u8> = data.iter().chain(&ecc).cloned().collect();
+                let bits = 8 * len + bch.ecc_bits();
+                for bit in rng.choose(bits, errors) {
+                    BitOrder::MsbFirst.flip(&mut block, bit);
+                }
+                let (d, e) = block.split_at_mut(len);
+                assert_eq!(bch.decode(d, e), Ok(errors));
+                assert_eq!(d, &data[..]);
+                assert_eq!(e, &ecc[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_many_errors() {
+        let bch = Bch::new(512, 4, BitOrder::MsbFirst).unwrap();
+        let data = page(512);
+        let mut ecc = vec![0u8; bch.ecc_len()];
+        bch.encode(&data, &mut ecc).unwrap();
+        let mut damaged = data.clone();
+        for bit in [3, 100, 1000, 2000, 4000] {
+            BitOrder::MsbFirst.flip(&mut damaged, bit);
+        }
+        let before = damaged.clone();
+        assert_eq!(bch.decode(&mut damaged, &mut ecc), Err(BchError::Uncorrectable));
+        assert_eq!(damaged, before);
+    }
+
+    #[test]
+    fn test_bit_order() {
+        let msb = Bch::new(32, 4, BitOrder::MsbFirst).unwrap();
+        let lsb = Bch::new(32, 4, BitOrder::LsbFirst).unwrap();
+        let data = page(32);
+        let reversed: Vec<u8> = data.iter().map(|b| b.reverse_bits()).collect();
+
+        let mut ecc_msb = vec![0u8; msb.ecc_len()];
+        let mut ecc_lsb = vec![0u8; lsb.ecc_len()];
+        msb.encode(&data, &mut ecc_msb).unwrap();
+        lsb.encode(&reversed, &mut ecc_lsb).unwrap();
+        let ecc_msb_reversed: Vec<u8> = ecc_msb.iter().map(|b| b.reverse_bits()).collect();
+        assert_eq!(ecc_lsb, ecc_msb_reversed);
+
+        // Decoding with the wrong order fails instead of "correcting"
+        let mut d = data.clone();
+        assert!(lsb.decode(&mut d, &mut ecc_msb.clone()) != Ok(0));
+    }
+}
@@ -0,0 +1,355 @@
+//! Polynomials over GF(2) of any degree.
+//!
+//! Gf2Poly packs the coefficients into u64 limbs, bit i of limb j being
+//! the coefficient of x^(64 j + i). Addition is XOR of the limbs and
+//! multiplication multiplies limb pairs with the carry-less product of
+//! clmul, which uses PCLMULQDQ or PMULL where the CPU has it.
+//!
+//! This is the arithmetic behind CRCs, field construction and minimal
+//! polynomials, for degrees beyond the 16 of the packed integers of
+//! gf2poly. Division goes bit by bit, shifting the divisor into place
+//! with whole limb XORs.
+
+use core::fmt;
+use core::ops::{ Add, AddAssign, Sub, Mul, Div, Rem };
+use alloc::{ vec, vec::Vec };
+
+use crate::clmul::clmul64;
+
+/// A polynomial with coefficients in GF(2)
+#[derive(Clone,PartialEq,Eq,Hash,Debug,Default)]
+pub struct Gf2Poly {
+	/// Lowest degree first, without trailing zero limbs
+	limbs: Vec<u64>
+}
+
+/// dst ^= src * x^shift, dst long enough
+fn xor_shifted(dst: &mut [u64], src: &[u64], shift: usize) {
+	let (words, bits) = (shift / 64, shift % 64);
+	for (i, &s) in src.iter().enumerate() {
+		dst[i + words] ^= s << bits;
+		if bits != 0 && s >> (64 - bits) != 0 {
+			dst[i + words + 1] ^= s >> (64 - bits);
+		}
+	}
+}
+
+impl Gf2Poly {
+	pub fn zero() -> Gf2Poly {
+		Gf2Poly { limbs: Vec::new() }
+	}
+
+	pub fn one() -> Gf2Poly {
+		Gf2Poly::from_u64(1)
+	}
+
+	/// x^k
+	pub fn monomial(k: usize) -> Gf2Poly {
+		let mut limbs = vec![0u64; k / 64 + 1];
+		limbs[k / 64] = 1 << (k % 64);
+		Gf2Poly { limbs }
+	}
+
+	/// Bit i of v is the coefficient of x^i, as in gf2poly
+	pub fn from_u64(v: u64) -> Gf2Poly {
+		Gf2Poly::from_limbs(vec![v])
+	}
+
+	/// Takes limbs lowest degree first, dropping high zeros
+	pub fn from_limbs(limbs: Vec<u64>) -> Gf2Poly {
+		let mut p = Gf2Poly { limbs };
+		p.normalize();
+		p
+	}
+
+	fn normalize(&mut self) {
+		while self.limbs.last() == Some(&0) {
+			self.limbs.pop();
+		}
+	}
+
+	/// Limbs, lowest degree first; empty for zero
+	pub fn limbs(&self) -> &[u64] {
+		&self.limbs
+	}
+
+	/// The packed coefficients, None for degree 64 and above
+	pub fn to_u64(&self) -> Option<u64> {
+		match self.limbs[..] {
+			[] => Some(0),
+			[v] => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Coefficient of x^i
+	pub fn coeff(&self, i: usize) -> bool {
+		self.limbs.get(i / 64).is_some_and(|l| l >> (i % 64) & 1 == 1)
+	}
+
+	/// Degree, None for the zero polynomial
+	pub fn degree(&self) -> Option<usize> {
+		let last = *self.limbs.last()?;
+		Some(64 * (self.limbs.len() - 1) + 63 - last.leading_zeros() as usize)
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.limbs.is_empty()
+	}
+
+	/// x^k * self
+	pub fn shift(&self, k: usize) -> Gf2Poly {
+		let Some(d) = self.degree() else {
+			return Gf2Poly::zero();
+		};
+		let mut limbs = vec![0u64; (d + k) / 64 + 1];
+		xor_shifted(&mut limbs, &self.limbs, k);
+		Gf2Poly { limbs }
+	}
+
+	/// Quotient and remainder of self / divisor, the remainder of degree
+	/// below the divisor's. Panics if divisor is zero.
+	pub fn div_rem(&self, divisor: &Gf2Poly) -> (Gf2Poly, Gf2Poly) {
+		let d = divisor.degree().expect("Division by zero polynomial");
+		let Some(n) = self.degree().filter(|&n| n >= d) else {
+			return (Gf2Poly::zero(), self.clone());
+		};
+		let mut rem = self.limbs.clone();
+		let mut quot = vec![0u64; (n - d) / 64 + 1];
+		for i in (0..=n - d).rev() {
+			if rem[(i + d) / 64] >> ((i + d) % 64) & 1 == 1 {
+				quot[i / 64] |= 1 << (i % 64);
+				xor_shifted(&mut rem, &divisor.limbs, i);
+			}
+		}
+		(Gf2Poly::from_limbs(quot), Gf2Poly::from_limbs(rem))
+	}
+
+	/// self mod divisor. Panics if divisor is zero.
+	pub fn rem(&self, divisor: &Gf2Poly) -> Gf2Poly {
+		self.div_rem(divisor).1
+	}
+
+	/// self * other mod modulus
+	pub fn mulmod(&self, other: &Gf2Poly, modulus: &Gf2Poly) -> Gf2Poly {
+		(self * other).rem(modulus)
+	}
+
+	/// self^e mod modulus, by square and multiply
+	pub fn powmod(&self, mut e: u64, modulus: &Gf2Poly) -> Gf2Poly {
+		let mut base = self.rem(modulus);
+		let mut acc = Gf2Poly::one().rem(modulus);
+		while e != 0 {
+			if e & 1 != 0 {
+				acc = acc.mulmod(&base, modulus);
+			}
+			base = base.mulmod(&base, modulus);
+			e >>= 1;
+		}
+		acc
+	}
+
+	/// The greatest common divisor, zero if both are zero
+	pub fn gcd(&self, other: &Gf2Poly) -> Gf2Poly {
+		let (mut a, mut b) = (self.clone(), other.clone());
+		while !b.is_zero() {
+			let r = a.rem(&b);
+			a = b;
+			b = r;
+		}
+		a
+	}
+
+	/// Whether self has degree at least 1 and no nontrivial factors. By
+	/// Ben-Or's test, x^(2^i) - x is coprime to self for all i up to half
+	/// the degree.
+	pub fn is_irreducible(&self) -> bool {
+		let Some(d) = self.degree().filter(|&d| d >= 1) else {
+			return false;
+		};
+		let x = Gf2Poly::monomial(1).rem(self);
+		let mut power = x.clone();
+		for _ in 0..d / 2 {
+			power = power.mulmod(&power, self);
+			if (&power + &x).gcd(self) != Gf2Poly::one() {
+				return false;
+			}
+		}
+		true
+	}
+}
+
+/// Prints like the alternate form of Gf256, e.g. x^8 + x^4 + x^3 + x^2 + 1
+impl fmt::Display for Gf2Poly {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let Some(d) = self.degree() else {
+			return f.write_str("0");
+		};
+		let mut first = true;
+		for i in (0..=d).rev().filter(|&i| self.coeff(i)) {
+			if !first {
+				f.write_str(" + ")?;
+			}
+			first = false;
+			match i {
+				0 => f.write_str("1")?,
+				1 => f.write_str("x")?,
+				_ => write!(f, "x^{}", i)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Add<&Gf2Poly> for &Gf2Poly {
+	type Output = Gf2Poly;
+	fn add(self, rhs: &Gf2Poly) -> Gf2Poly {
+		let mut out = self.clone();
+		out += rhs;
+		out
+	}
+}
+
+impl AddAssign<&Gf2Poly> for Gf2Poly {
+	fn add_assign(&mut self, rhs: &Gf2Poly) {
+		if self.limbs.len() < rhs.limbs.len() {
+			self.limbs.resize(rhs.limbs.len(), 0);
+		}
+		for (a, &b) in self.limbs.iter_mut().zip(&rhs.limbs) {
+			*a ^= b;
+		}
+		self.normalize();
+	}
+}
+
+impl Sub<&Gf2Poly> for &Gf2Poly {
+	type Output = Gf2Poly;
+	/// The same as addition in characteristic 2
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: &Gf2Poly) -> Gf2Poly {
+		self + rhs
+	}
+}
+
+impl Mul<&Gf2Poly> for &Gf2Poly {
+	type Output = Gf2Poly;
+	fn mul(self, rhs: &Gf2Poly) -> Gf2Poly {
+		if self.is_zero() || rhs.is_zero() {
+			return Gf2Poly::zero();
+		}
+		let mut limbs = vec![0u64; self.limbs.len() + rhs.limbs.len()];
+		for (i, &a) in self.limbs.iter().enumerate() {
+			for (j, &b) in rhs.limbs.iter().enumerate() {
+				let p = clmul64(a, b);
+				limbs[i + j] ^= p as u64;
+				limbs[i + j + 1] ^= (p >> 64) as u64;
+			}
+		}
+		Gf2Poly::from_limbs(limbs)
+	}
+}
+
+impl Div<&Gf2Poly> for &Gf2Poly {
+	type Output = Gf2Poly;
+	/// The quotient of div_rem
+	fn div(self, rhs: &Gf2Poly) -> Gf2Poly {
+		self.div_rem(rhs).0
+	}
+}
+
+impl Rem<&Gf2Poly> for &Gf2Poly {
+	type Output = Gf2Poly;
+	fn rem(self, rhs: &Gf2Poly) -> Gf2Poly {
+		self.div_rem(rhs).1
+	}
+}
+
+macro_rules! impl_owned_op {
+	($tr:ident, $method:ident) => {
+		impl $tr<Gf2Poly> for Gf2Poly {
+			type Output = Gf2Poly;
+			fn $method(self, rhs: Gf2Poly) -> Gf2Poly {
+				$tr::$method(&self, &rhs)
+			}
+		}
+
+		impl $tr<&Gf2Poly> for Gf2Poly {
+			type Output = Gf2Poly;
+			fn $method(self, rhs: &Gf2Poly) -> Gf2Poly {
+				$tr::$method(&self, rhs)
+			}
+		}
+	};
+}
+
+impl_owned_op!(Add, add);
+impl_owned_op!(Sub, sub);
+impl_owned_op!(Mul, mul);
+impl_owned_op!(Div, div);
+impl_owned_op!(Rem, rem);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gf2poly;
+
+    /// A pseudorandom polynomial of the given degree
+    fn poly(degree: usize, seed: u64) -> Gf2Poly {
+        let mut s = seed;
+        let mut limbs: Vec<u64> = (0..degree / 64 + 1).map(|_| {
+            s = s.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            s ^ s >> 29
+        }).collect();
+        let last = limbs.last_mut().unwrap();
+        *last &= u64::MAX >> (63 - degree % 64);
+        *last |= 1 << (degree % 64);
+        Gf2Poly::from_limbs(limbs)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Gf2Poly::from_u64(0b1011);
+        let b = Gf2Poly::from_u64(0b11);
+        assert_eq!(&a * &b, Gf2Poly::from_u64(0b11101));
+        assert_eq!(&a + &a, Gf2Poly::zero());
+        assert_eq!(Gf2Poly::monomial(70).degree(), Some(70));
+        assert_eq!(Gf2Poly::monomial(3).shift(130), Gf2Poly::monomial(133));
+        assert_eq!(Gf2Poly::one().shift(64).limbs(), [0, 1]);
+        assert_eq!(Gf2Poly::from_u64(0x11d).to_string(), "x^8 + x^4 + x^3 + x^2 + 1");
+        assert_eq!(Gf2Poly::zero().to_string(), "0");
+
+        for (da, db) in [(200, 70), (63, 64), (5, 300), (128, 1)] {
+            let (a, b) = (poly(da, da as u64), poly(db, 7 + db as u64));
+            let p = &a * &b;
+            assert_eq!(p.degree(), Some(da + db));
+            let (q, r) = p.div_rem(&b);
+            assert_eq!((q, r), (a.clone(), Gf2Poly::zero()));
+            let c = poly(db / 2, 3);
+            let (q, r) = (&p + &c).div_rem(&b);
+            assert_eq!(q, a);
+            assert_eq!(r, c);
+            assert_eq!((&a * &b).gcd(&(&b * &c)).rem(&b), Gf2Poly::zero());
+        }
+    }
+
+    #[test]
+    fn test_irreducible() {
+        for m in 2..1 << 10 {
+            let p = Gf2Poly::from_u64(m as u64);
+            assert_eq!(p.is_irreducible(), gf2poly::is_irreducible(m), "{:#x}", m);
+        }
+        // x^127 + x + 1 and x^64 + x^4 + x^3 + x + 1 are irreducible
+        let p = &Gf2Poly::monomial(127) + &Gf2Poly::from_u64(0b11);
+        assert!(p.is_irreducible());
+        assert!((&Gf2Poly::monomial(64) + &Gf2Poly::from_u64(0b11011)).is_irreducible());
+        assert!(!(&p * &p).is_irreducible());
+        // x^(2^127) = x mod p
+        let x = Gf2Poly::monomial(1);
+        let mut y = x.clone();
+        for _ in 0..127 {
+            y = y.mulmod(&y, &p);
+        }
+        assert_eq!(y, x);
+    }
+}
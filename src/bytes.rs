@@ -0,0 +1,759 @@
+//! Bulk field operations on plain byte slices.
+//!
+//! Every byte is interpreted as a field element (as by Gf256::from_byte),
+//! so callers with their own buffer types never need to touch Gf256.
+//! Functions taking two slices panic if their lengths differ.
+//!
+//! mul_scalar, mul_scalar_into and mul_add split every byte into two
+//! nibbles and look both products up in 16 entry tables, which is exactly
+//! one byte shuffle instruction per table. On x86_64 they process 32 bytes
+//! per step with AVX2 or 16 with SSSE3, on aarch64 16 with NEON table
+//! lookups, chosen from the build's target features or, with std, from
+//! what the CPU reports at runtime. WebAssembly has no runtime detection,
+//! so the 16 byte simd128 kernel is used when the build enables it (with
+//! RUSTFLAGS="-C target-feature=+simd128"). The tail and all other targets
+//! use the same two 16 entry tables with one lookup each per byte, which
+//! needs no unsafe code and only 32 products per constant. Runs too short
+//! for that are multiplied byte by byte, and long runs get a 256 entry
+//! table with a single lookup.
+//!
+//! Where the CPU has GFNI, x86_64 uses it instead. Multiplication by a
+//! constant is linear over GF(2), so GF2P8AFFINEQB does it in a single
+//! instruction per 32 (with AVX2) or 16 bytes, given the 8x8 bit matrix
+//! of the map from mul_matrix. That works for any field polynomial, and
+//! affine_transform applies any such matrix, e.g. a change of basis.
+//! GF2P8MULB multiplies two vectors but only modulo the AES polynomial
+//! 0x11B, so mul_elementwise maps both operands into that field through
+//! an isomorphism, multiplies there and maps the product back.
+
+use crate::Gf256;
+
+/// Returns the products c * x for every byte x
+pub fn mul_table(c: u8) -> [u8; 256] {
+	let c = Gf256::from_byte(c);
+	let mut table = [0u8; 256];
+	for (x, t) in table.iter_mut().enumerate() {
+		*t = (c * Gf256::from_byte(x as u8)).to_byte();
+	}
+	table
+}
+
+/// The products of c with every low nibble and with every high nibble;
+/// c * x is lo[x & 15] ^ hi[x >> 4]
+pub fn nibble_tables(c: u8) -> ([u8; 16], [u8; 16]) {
+	let c = Gf256::from_byte(c);
+	let mut lo = [0u8; 16];
+	let mut hi = [0u8; 16];
+	for i in 0..16u8 {
+		lo[i as usize] = (c * Gf256::from_byte(i)).to_byte();
+		hi[i as usize] = (c * Gf256::from_byte(i << 4)).to_byte();
+	}
+	(lo, hi)
+}
+
+/// The 8x8 bit matrix of a GF(2)-linear map of bytes in the layout of
+/// GF2P8AFFINEQB, from the images of the single bits: images[j] is the
+/// image of 1 << j. Byte 7 - i of the matrix holds the input bits that
+/// are summed up into output bit i.
+pub const fn affine_matrix(images: [u8; 8]) -> u64 {
+	let mut m = 0;
+	let mut i = 0;
+	while i < 8 {
+		let mut row = 0u8;
+		let mut j = 0;
+		while j < 8 {
+			row |= ((images[j] >> i) & 1) << j;
+			j += 1;
+		}
+		m |= (row as u64) << (8 * (7 - i));
+		i += 1;
+	}
+	m
+}
+
+/// Applies the matrix to x, as GF2P8AFFINEQB with a zero constant does
+pub const fn affine(matrix: u64, x: u8) -> u8 {
+	let mut y = 0;
+	let mut i = 0;
+	while i < 8 {
+		let row = (matrix >> (8 * (7 - i))) as u8;
+		y |= (((row & x).count_ones() & 1) as u8) << i;
+		i += 1;
+	}
+	y
+}
+
+/// The matrix of multiplication by c
+pub fn mul_matrix(c: u8) -> u64 {
+	let c = Gf256::from_byte(c);
+	affine_matrix(core::array::from_fn(|j| (c * Gf256::from_byte(1 << j)).to_byte()))
+}
+
+/// a * b modulo the AES polynomial, which GF2P8MULB uses
+#[allow(dead_code)]
+const fn aes_mul(mut a: u8, mut b: u8) -> u8 {
+	let mut r = 0;
+	while b != 0 {
+		if b & 1 == 1 {
+			r ^= a;
+		}
+		a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+		b >>= 1;
+	}
+	r
+}
+
+/// The isomorphism onto the AES field sending x to the smallest root of
+/// 0x11D there, and its inverse
+#[allow(dead_code)]
+const TO_AES: u64 = {
+	let mut root = 2u8;
+	loop {
+		// x^8 + x^4 + x^3 + x^2 + 1 at root
+		let mut powers = [1u8; 9];
+		let mut i = 1;
+		while i < 9 {
+			powers[i] = aes_mul(powers[i - 1], root);
+			i += 1;
+		}
+		if powers[8] ^ powers[4] ^ powers[3] ^ powers[2] ^ 1 == 0 {
+			let mut images = [0u8; 8];
+			let mut j = 0;
+			while j < 8 {
+				images[j] = powers[j];
+				j += 1;
+			}
+			break affine_matrix(images);
+		}
+		root += 1;
+	}
+};
+
+#[allow(dead_code)]
+const FROM_AES: u64 = {
+	let mut images = [0u8; 8];
+	let mut a = 1u8;
+	loop {
+		let b = affine(TO_AES, a);
+		if b.is_power_of_two() {
+			images[b.trailing_zeros() as usize] = a;
+		}
+		if a == 255 {
+			break;
+		}
+		a += 1;
+	}
+	affine_matrix(images)
+};
+
+#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+mod x86 {
+	use core::arch::x86_64::*;
+
+	/// Multiplies len / 16 * 16 bytes of src by the constant described by
+	/// the nibble tables into dst, adding to dst if ADD. dst and src may
+	/// be the same buffer.
+	#[target_feature(enable = "ssse3")]
+	pub unsafe fn mul_ssse3<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize,
+		lo: &[u8; 16], hi: &[u8; 16]) -> usize {
+		let tlo = _mm_loadu_si128(lo.as_ptr() as *const __m128i);
+		let thi = _mm_loadu_si128(hi.as_ptr() as *const __m128i);
+		let mask = _mm_set1_epi8(0x0f);
+		let mut i = 0;
+		while i + 16 <= len {
+			let x = _mm_loadu_si128(src.add(i) as *const __m128i);
+			let l = _mm_shuffle_epi8(tlo, _mm_and_si128(x, mask));
+			let h = _mm_shuffle_epi8(thi, _mm_and_si128(_mm_srli_epi16(x, 4), mask));
+			let mut p = _mm_xor_si128(l, h);
+			if ADD {
+				p = _mm_xor_si128(p, _mm_loadu_si128(dst.add(i) as *const __m128i));
+			}
+			_mm_storeu_si128(dst.add(i) as *mut __m128i, p);
+			i += 16;
+		}
+		i
+	}
+
+	/// mul_ssse3 with 32 bytes per step
+	#[target_feature(enable = "avx2")]
+	pub unsafe fn mul_avx2<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize,
+		lo: &[u8; 16], hi: &[u8; 16]) -> usize {
+		let tlo = _mm256_broadcastsi128_si256(_mm_loadu_si128(lo.as_ptr() as *const __m128i));
+		let thi = _mm256_broadcastsi128_si256(_mm_loadu_si128(hi.as_ptr() as *const __m128i));
+		let mask = _mm256_set1_epi8(0x0f);
+		let mut i = 0;
+		while i + 32 <= len {
+			let x = _mm256_loadu_si256(src.add(i) as *const __m256i);
+			let l = _mm256_shuffle_epi8(tlo, _mm256_and_si256(x, mask));
+			let h = _mm256_shuffle_epi8(thi, _mm256_and_si256(_mm256_srli_epi16(x, 4), mask));
+			let mut p = _mm256_xor_si256(l, h);
+			if ADD {
+				p = _mm256_xor_si256(p, _mm256_loadu_si256(dst.add(i) as *const __m256i));
+			}
+			_mm256_storeu_si256(dst.add(i) as *mut __m256i, p);
+			i += 32;
+		}
+		i
+	}
+
+	/// Applies the bit matrix to len / 16 * 16 bytes of src into dst,
+	/// adding to dst if ADD, like mul_ssse3
+	#[target_feature(enable = "gfni")]
+	pub unsafe fn affine_gfni<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize, matrix: u64) -> usize {
+		let m = _mm_set1_epi64x(matrix as i64);
+		let mut i = 0;
+		while i + 16 <= len {
+			let x = _mm_loadu_si128(src.add(i) as *const __m128i);
+			let mut p = _mm_gf2p8affine_epi64_epi8::<0>(x, m);
+			if ADD {
+				p = _mm_xor_si128(p, _mm_loadu_si128(dst.add(i) as *const __m128i));
+			}
+			_mm_storeu_si128(dst.add(i) as *mut __m128i, p);
+			i += 16;
+		}
+		i
+	}
+
+	/// affine_gfni with 32 bytes per step
+	#[target_feature(enable = "gfni,avx2")]
+	pub unsafe fn affine_gfni_avx2<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize, matrix: u64) -> usize {
+		let m = _mm256_set1_epi64x(matrix as i64);
+		let mut i = 0;
+		while i + 32 <= len {
+			let x = _mm256_loadu_si256(src.add(i) as *const __m256i);
+			let mut p = _mm256_gf2p8affine_epi64_epi8::<0>(x, m);
+			if ADD {
+				p = _mm256_xor_si256(p, _mm256_loadu_si256(dst.add(i) as *const __m256i));
+			}
+			_mm256_storeu_si256(dst.add(i) as *mut __m256i, p);
+			i += 32;
+		}
+		i
+	}
+
+	/// dst[i] *= src[i] for len / 16 * 16 bytes, with the product taken in
+	/// the AES field between the two isomorphisms
+	#[target_feature(enable = "gfni")]
+	pub unsafe fn mul_elementwise_gfni(dst: *mut u8, src: *const u8, len: usize) -> usize {
+		let to = _mm_set1_epi64x(super::TO_AES as i64);
+		let from = _mm_set1_epi64x(super::FROM_AES as i64);
+		let mut i = 0;
+		while i + 16 <= len {
+			let a = _mm_gf2p8affine_epi64_epi8::<0>(_mm_loadu_si128(dst.add(i) as *const __m128i), to);
+			let b = _mm_gf2p8affine_epi64_epi8::<0>(_mm_loadu_si128(src.add(i) as *const __m128i), to);
+			let p = _mm_gf2p8affine_epi64_epi8::<0>(_mm_gf2p8mul_epi8(a, b), from);
+			_mm_storeu_si128(dst.add(i) as *mut __m128i, p);
+			i += 16;
+		}
+		i
+	}
+
+	/// mul_elementwise_gfni with 32 bytes per step
+	#[target_feature(enable = "gfni,avx2")]
+	pub unsafe fn mul_elementwise_gfni_avx2(dst: *mut u8, src: *const u8, len: usize) -> usize {
+		let to = _mm256_set1_epi64x(super::TO_AES as i64);
+		let from = _mm256_set1_epi64x(super::FROM_AES as i64);
+		let mut i = 0;
+		while i + 32 <= len {
+			let a = _mm256_gf2p8affine_epi64_epi8::<0>(_mm256_loadu_si256(dst.add(i) as *const __m256i), to);
+			let b = _mm256_gf2p8affine_epi64_epi8::<0>(_mm256_loadu_si256(src.add(i) as *const __m256i), to);
+			let p = _mm256_gf2p8affine_epi64_epi8::<0>(_mm256_gf2p8mul_epi8(a, b), from);
+			_mm256_storeu_si256(dst.add(i) as *mut __m256i, p);
+			i += 32;
+		}
+		i
+	}
+}
+
+#[cfg(all(target_arch = "aarch64", any(feature = "std", target_feature = "neon")))]
+mod neon {
+	use core::arch::aarch64::*;
+
+	/// The NEON version of x86::mul_ssse3
+	#[target_feature(enable = "neon")]
+	pub unsafe fn mul_neon<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize,
+		lo: &[u8; 16], hi: &[u8; 16]) -> usize {
+		let tlo = vld1q_u8(lo.as_ptr());
+		let thi = vld1q_u8(hi.as_ptr());
+		let mask = vdupq_n_u8(0x0f);
+		let mut i = 0;
+		while i + 16 <= len {
+			let x = vld1q_u8(src.add(i));
+			let l = vqtbl1q_u8(tlo, vandq_u8(x, mask));
+			let h = vqtbl1q_u8(thi, vshrq_n_u8::<4>(x));
+			let mut p = veorq_u8(l, h);
+			if ADD {
+				p = veorq_u8(p, vld1q_u8(dst.add(i)));
+			}
+			vst1q_u8(dst.add(i), p);
+			i += 16;
+		}
+		i
+	}
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+	use core::arch::wasm32::*;
+
+	/// The simd128 version of x86::mul_ssse3. Swizzle yields 0 for
+	/// indices of 16 and above, and the masked nibbles stay below.
+	#[target_feature(enable = "simd128")]
+	pub unsafe fn mul_simd128<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize,
+		lo: &[u8; 16], hi: &[u8; 16]) -> usize {
+		let tlo = v128_load(lo.as_ptr() as *const v128);
+		let thi = v128_load(hi.as_ptr() as *const v128);
+		let mask = u8x16_splat(0x0f);
+		let mut i = 0;
+		while i + 16 <= len {
+			let x = v128_load(src.add(i) as *const v128);
+			let l = u8x16_swizzle(tlo, v128_and(x, mask));
+			let h = u8x16_swizzle(thi, u8x16_shr(x, 4));
+			let mut p = v128_xor(l, h);
+			if ADD {
+				p = v128_xor(p, v128_load(dst.add(i) as *const v128));
+			}
+			v128_store(dst.add(i) as *mut v128, p);
+			i += 16;
+		}
+		i
+	}
+}
+
+/// Whether the build or, with std, the CPU has the x86 feature
+#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+macro_rules! x86_feature {
+	($f:tt) => {{
+		#[cfg(target_feature = $f)]
+		let has = true;
+		#[cfg(all(feature = "std", not(target_feature = $f)))]
+		let has = std::is_x86_feature_detected!($f);
+		#[cfg(all(not(feature = "std"), not(target_feature = $f)))]
+		let has = false;
+		has
+	}};
+}
+
+/// A vector kernel for multiplication by a constant
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+#[allow(dead_code)]
+pub(crate) enum Kernel {
+	GfniAvx2,
+	Avx2,
+	Gfni,
+	Ssse3,
+	Neon,
+	Simd128,
+}
+
+impl Kernel {
+	/// Fastest first
+	pub(crate) const ALL: [Kernel; 6] = [
+		Kernel::GfniAvx2, Kernel::Avx2, Kernel::Gfni, Kernel::Ssse3, Kernel::Neon, Kernel::Simd128,
+	];
+
+	/// Whether the build or the CPU supports the kernel
+	pub(crate) fn is_supported(self) -> bool {
+		match self {
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::GfniAvx2 => x86_feature!("gfni") && x86_feature!("avx2"),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Avx2 => x86_feature!("avx2"),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Gfni => x86_feature!("gfni"),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Ssse3 => x86_feature!("ssse3"),
+			#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+			Kernel::Neon => true,
+			#[cfg(all(target_arch = "aarch64", feature = "std", not(target_feature = "neon")))]
+			Kernel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+			#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+			Kernel::Simd128 => true,
+			#[allow(unreachable_patterns)]
+			_ => false,
+		}
+	}
+
+	/// The fastest kernel the build or the CPU supports
+	pub(crate) fn detect() -> Option<Kernel> {
+		Kernel::ALL.into_iter().find(|k| k.is_supported())
+	}
+
+	pub(crate) fn name(self) -> &'static str {
+		match self {
+			Kernel::GfniAvx2 => "gfni-avx2",
+			Kernel::Avx2 => "avx2",
+			Kernel::Gfni => "gfni",
+			Kernel::Ssse3 => "ssse3",
+			Kernel::Neon => "neon",
+			Kernel::Simd128 => "simd128",
+		}
+	}
+
+	/// Runs the kernel over a prefix of the len bytes at dst and src and
+	/// returns the length of that prefix. dst and src have to be valid for
+	/// len bytes, they may be equal, and the kernel has to be supported.
+	#[allow(unused_variables)]
+	pub(crate) unsafe fn run<const ADD: bool>(self, dst: *mut u8, src: *const u8, len: usize, c: u8) -> usize {
+		match self {
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::GfniAvx2 => x86::affine_gfni_avx2::<ADD>(dst, src, len, mul_matrix(c)),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Avx2 => {
+				let (lo, hi) = nibble_tables(c);
+				x86::mul_avx2::<ADD>(dst, src, len, &lo, &hi)
+			},
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Gfni => x86::affine_gfni::<ADD>(dst, src, len, mul_matrix(c)),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Ssse3 => {
+				let (lo, hi) = nibble_tables(c);
+				x86::mul_ssse3::<ADD>(dst, src, len, &lo, &hi)
+			},
+			#[cfg(all(target_arch = "aarch64", any(feature = "std", target_feature = "neon")))]
+			Kernel::Neon => {
+				let (lo, hi) = nibble_tables(c);
+				neon::mul_neon::<ADD>(dst, src, len, &lo, &hi)
+			},
+			#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+			Kernel::Simd128 => {
+				let (lo, hi) = nibble_tables(c);
+				wasm::mul_simd128::<ADD>(dst, src, len, &lo, &hi)
+			},
+			#[allow(unreachable_patterns)]
+			_ => 0,
+		}
+	}
+
+	/// Like run, applying the bit matrix instead of multiplying. Only the
+	/// GFNI kernels do that; the others return 0.
+	#[allow(unused_variables)]
+	pub(crate) unsafe fn run_affine(self, dst: *mut u8, src: *const u8, len: usize, matrix: u64) -> usize {
+		match self {
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::GfniAvx2 => x86::affine_gfni_avx2::<false>(dst, src, len, matrix),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Gfni => x86::affine_gfni::<false>(dst, src, len, matrix),
+			_ => 0,
+		}
+	}
+
+	/// Like run for dst[i] *= src[i]. Only the GFNI kernels do that.
+	#[allow(unused_variables)]
+	pub(crate) unsafe fn run_elementwise(self, dst: *mut u8, src: *const u8, len: usize) -> usize {
+		match self {
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::GfniAvx2 => x86::mul_elementwise_gfni_avx2(dst, src, len),
+			#[cfg(all(target_arch = "x86_64", any(feature = "std", target_feature = "ssse3", target_feature = "gfni")))]
+			Kernel::Gfni => x86::mul_elementwise_gfni(dst, src, len),
+			_ => 0,
+		}
+	}
+}
+
+/// Shortest run worth the 32 products of the nibble tables; below it
+/// every byte is multiplied on its own
+const NIBBLE_MIN: usize = 32;
+
+/// Shortest run for which one lookup in a 256 entry table beats two in
+/// the nibble tables, building the table included
+const TABLE_MIN: usize = 1024;
+
+/// How the portable path finds c * x
+trait Products {
+	fn of(&self, x: u8) -> u8;
+}
+
+impl Products for Gf256 {
+	#[inline]
+	fn of(&self, x: u8) -> u8 {
+		(*self * Gf256::from_byte(x)).to_byte()
+	}
+}
+
+impl Products for ([u8; 16], [u8; 16]) {
+	#[inline]
+	fn of(&self, x: u8) -> u8 {
+		self.0[(x & 15) as usize] ^ self.1[(x >> 4) as usize]
+	}
+}
+
+impl Products for [u8; 256] {
+	#[inline]
+	fn of(&self, x: u8) -> u8 {
+		self[x as usize]
+	}
+}
+
+fn scale_by<P: Products>(p: P, buf: &mut [u8]) {
+	for b in buf.iter_mut() {
+		*b = p.of(*b);
+	}
+}
+
+fn add_scaled_by<P: Products>(p: P, dst: &mut [u8], src: &[u8]) {
+	for (d, s) in dst.iter_mut().zip(src) {
+		*d ^= p.of(*s);
+	}
+}
+
+/// buf[i] = c * buf[i] without vector instructions
+pub(crate) fn portable_mul(buf: &mut [u8], c: u8) {
+	match buf.len() {
+		n if n >= TABLE_MIN => scale_by(mul_table(c), buf),
+		n if n >= NIBBLE_MIN => scale_by(nibble_tables(c), buf),
+		_ => scale_by(Gf256::from_byte(c), buf),
+	}
+}
+
+/// dst[i] += c * src[i] without vector instructions
+pub(crate) fn portable_mul_add(dst: &mut [u8], src: &[u8], c: u8) {
+	match dst.len() {
+		n if n >= TABLE_MIN => add_scaled_by(mul_table(c), dst, src),
+		n if n >= NIBBLE_MIN => add_scaled_by(nibble_tables(c), dst, src),
+		_ => add_scaled_by(Gf256::from_byte(c), dst, src),
+	}
+}
+
+/// Runs the detected kernel (if any) over a prefix of the slices, see
+/// Kernel::run
+fn simd_prefix<const ADD: bool>(dst: *mut u8, src: *const u8, len: usize, c: u8) -> usize {
+	match Kernel::detect() {
+		// The kernel stays within len bytes and was detected
+		Some(k) if len >= 16 => unsafe { k.run::<ADD>(dst, src, len, c) },
+		_ => 0,
+	}
+}
+
+/// buf[i] = c * buf[i]
+pub fn mul_scalar(buf: &mut [u8], c: u8) {
+	match c {
+		0 => {
+			for b in buf.iter_mut() {
+				*b = 0;
+			}
+		},
+		1 => {},
+		_ => {
+			let ptr = buf.as_mut_ptr();
+			let done = simd_prefix::<false>(ptr, ptr, buf.len(), c);
+			portable_mul(&mut buf[done..], c);
+		},
+	}
+}
+
+/// dst[i] = c * src[i]
+pub fn mul_scalar_into(dst: &mut [u8], src: &[u8], c: u8) {
+	assert_eq!(dst.len(), src.len(), "Length mismatch");
+	dst.copy_from_slice(src);
+	mul_scalar(dst, c);
+}
+
+/// dst[i] = dst[i] + src[i]
+pub fn add_assign(dst: &mut [u8], src: &[u8]) {
+	assert_eq!(dst.len(), src.len(), "Length mismatch");
+	for (d, s) in dst.iter_mut().zip(src) {
+		*d ^= *s;
+	}
+}
+
+/// dst[i] = dst[i] + c * src[i]
+pub fn mul_add(dst: &mut [u8], src: &[u8], c: u8) {
+	assert_eq!(dst.len(), src.len(), "Length mismatch");
+	match c {
+		0 => {},
+		1 => add_assign(dst, src),
+		_ => {
+			let done = simd_prefix::<true>(dst.as_mut_ptr(), src.as_ptr(), dst.len(), c);
+			portable_mul_add(&mut dst[done..], &src[done..], c);
+		},
+	}
+}
+
+/// dst[i] = dst[i] * src[i]
+pub fn mul_elementwise(dst: &mut [u8], src: &[u8]) {
+	assert_eq!(dst.len(), src.len(), "Length mismatch");
+	let done = match Kernel::detect() {
+		// The kernel stays within the slices and is supported
+		Some(k) if dst.len() >= 16 => unsafe { k.run_elementwise(dst.as_mut_ptr(), src.as_ptr(), dst.len()) },
+		_ => 0,
+	};
+	for (d, s) in dst[done..].iter_mut().zip(&src[done..]) {
+		*d = (Gf256::from_byte(*d) * Gf256::from_byte(*s)).to_byte();
+	}
+}
+
+/// buf[i] = affine(matrix, buf[i]), e.g. with the matrix of a change of
+/// basis or of multiplication in another field
+pub fn affine_transform(buf: &mut [u8], matrix: u64) {
+	let ptr = buf.as_mut_ptr();
+	let done = match Kernel::detect() {
+		Some(k) if buf.len() >= 16 => unsafe { k.run_affine(ptr, ptr, buf.len(), matrix) },
+		_ => 0,
+	};
+	let mut lo = [0u8; 16];
+	let mut hi = [0u8; 16];
+	for i in 0..16u8 {
+		lo[i as usize] = affine(matrix, i);
+		hi[i as usize] = affine(matrix, i << 4);
+	}
+	scale_by((lo, hi), &mut buf[done..]);
+}
+
+/// Bytes of the products dot forms at once on the stack
+const DOT_BLOCK: usize = 256;
+
+/// Returns the sum of a[i] * b[i]. The products are formed by
+/// mul_elementwise in blocks on the stack and summed with XOR a word at
+/// a time.
+pub fn dot(a: &[u8], b: &[u8]) -> u8 {
+	assert_eq!(a.len(), b.len(), "Length mismatch");
+	let mut block = [0u8; DOT_BLOCK];
+	let mut words = 0u64;
+	let mut sum = 0u8;
+	for (x, y) in a.chunks(DOT_BLOCK).zip(b.chunks(DOT_BLOCK)) {
+		let products = &mut block[..x.len()];
+		products.copy_from_slice(x);
+		mul_elementwise(products, y);
+		let mut chunks = products.chunks_exact(8);
+		for w in &mut chunks {
+			words ^= u64::from_ne_bytes(w.try_into().expect("8 bytes"));
+		}
+		sum = chunks.remainder().iter().fold(sum, |acc, &p| acc ^ p);
+	}
+	words.to_ne_bytes().iter().fold(sum, |acc, &p| acc ^ p)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_scalar() {
+        let mut buf: Vec<u8> = (0..=255u8).collect();
+        mul_scalar(&mut buf, 0x8f);
+        for (x, &b) in buf.iter().enumerate() {
+            assert_eq!(Gf256::from_byte(b), Gf256::from_byte(x as u8) * Gf256::from_byte(0x8f));
+        }
+    }
+
+    #[test]
+    fn test_mul_add() {
+        let src = [1u8, 2, 3, 0, 255];
+        let mut dst = [9u8, 8, 7, 6, 5];
+        let expected: Vec<u8> = dst.iter().zip(&src)
+            .map(|(&d, &s)| (Gf256::from_byte(d) + Gf256::from_byte(s) * Gf256::from_byte(0x15)).to_byte())
+            .collect();
+        mul_add(&mut dst, &src, 0x15);
+        assert_eq!(&dst[..], &expected[..]);
+
+        // Multiplying by one is plain addition
+        let mut twice = src;
+        mul_add(&mut twice, &src, 1);
+        assert_eq!(twice, [0; 5]);
+    }
+
+    #[test]
+    fn test_vector_kernels() {
+        // Lengths around the 16 and 32 byte steps, so both the vector
+        // part and the scalar tail are exercised
+        for len in [0, 1, 15, 16, 17, 31, 32, 33, 64, 100, 1000] {
+            let src: Vec<u8> = (0..len).map(|i| (i * 73 + 11) as u8).collect();
+            for c in [2u8, 0x1d, 0x80, 0xff] {
+                let table = mul_table(c);
+                let mut buf = src.clone();
+                mul_scalar(&mut buf, c);
+                // dst = c * src + src
+                let mut acc = src.clone();
+                mul_add(&mut acc, &src, c);
+                for (i, &x) in src.iter().enumerate() {
+                    assert_eq!(buf[i], table[x as usize], "len {} c {}", len, c);
+                    assert_eq!(acc[i], table[x as usize] ^ x);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_affine() {
+        assert_eq!(affine_matrix([1, 2, 4, 8, 16, 32, 64, 128]), 0x0102040810204080);
+        for c in [0u8, 1, 2, 0x1d, 0xc3] {
+            let m = mul_matrix(c);
+            for x in 0..=255u8 {
+                assert_eq!(affine(m, x), (Gf256::from_byte(c) * Gf256::from_byte(x)).to_byte());
+            }
+        }
+        // TO_AES is a field isomorphism and FROM_AES its inverse
+        for a in (0..=255u8).step_by(5) {
+            assert_eq!(affine(FROM_AES, affine(TO_AES, a)), a);
+            for b in (0..=255u8).step_by(7) {
+                let p = (Gf256::from_byte(a) * Gf256::from_byte(b)).to_byte();
+                assert_eq!(aes_mul(affine(TO_AES, a), affine(TO_AES, b)), affine(TO_AES, p));
+            }
+        }
+
+        // Every supported kernel, not only the fastest
+        let src: Vec<u8> = (0..100).map(|i| (i * 53 + 1) as u8).collect();
+        let expected: Vec<u8> = src.iter().map(|&x| (Gf256::from_byte(x) * Gf256::from_byte(x)).to_byte()).collect();
+        for k in Kernel::ALL.into_iter().filter(|k| k.is_supported()) {
+            let mut sq = src.clone();
+            let done = unsafe { k.run_elementwise(sq.as_mut_ptr(), src.as_ptr(), 100) };
+            assert_eq!(sq[..done], expected[..done], "{}", k.name());
+            let mut buf = src.clone();
+            let done = unsafe { k.run_affine(buf.as_mut_ptr(), src.as_ptr(), 100, mul_matrix(0x35)) };
+            assert!(buf[..done].iter().zip(&src).all(|(&b, &x)| b == mul_table(0x35)[x as usize]));
+        }
+
+        // Multiplication in the AES field by 3, over every kernel length
+        let aes3 = affine_matrix(core::array::from_fn(|j| aes_mul(3, 1 << j)));
+        for len in [0, 15, 16, 33, 100] {
+            let src: Vec<u8> = (0..len).map(|i| (i * 37 + 5) as u8).collect();
+            let mut buf = src.clone();
+            affine_transform(&mut buf, aes3);
+            assert!(buf.iter().zip(&src).all(|(&b, &x)| b == aes_mul(3, x)));
+
+            let mut prod = src.clone();
+            mul_elementwise(&mut prod, &buf);
+            for i in 0..len {
+                assert_eq!(prod[i], (Gf256::from_byte(src[i]) * Gf256::from_byte(buf[i])).to_byte());
+            }
+        }
+    }
+
+    #[test]
+    fn test_portable_paths() {
+        // Each strategy of the portable path, on runs of its lengths
+        for len in [5, NIBBLE_MIN, 200, TABLE_MIN, 1500] {
+            let src: Vec<u8> = (0..len).map(|i| (i * 29 + 7) as u8).collect();
+            let c = 0x53;
+            let expected: Vec<u8> = src.iter().map(|&x| (Gf256::from_byte(x) * Gf256::from_byte(c)).to_byte()).collect();
+            let mut buf = src.clone();
+            portable_mul(&mut buf, c);
+            assert_eq!(buf, expected, "len {}", len);
+            portable_mul_add(&mut buf, &src, c);
+            assert!(buf.iter().all(|&b| b == 0));
+        }
+        let (lo, hi) = nibble_tables(0x1d);
+        assert_eq!(lo[3], 0x27);
+        assert_eq!(hi[1], mul_table(0x1d)[0x10]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let a = [3u8, 5];
+        let b = [7u8, 11];
+        let expected = Gf256::from_byte(3) * Gf256::from_byte(7)
+            + Gf256::from_byte(5) * Gf256::from_byte(11);
+        assert_eq!(dot(&a, &b), expected.to_byte());
+
+        // Several blocks and a ragged end
+        for len in [0, 7, 8, 255, 256, 257, 1000] {
+            let a: Vec<u8> = (0..len).map(|i| (i * 151 + 7) as u8).collect();
+            let b: Vec<u8> = (0..len).map(|i| (i * 29 + i / 5) as u8).collect();
+            let expected = a.iter().zip(&b)
+                .fold(Gf256::zero(), |acc, (&x, &y)| acc + Gf256::from_byte(x) * Gf256::from_byte(y));
+            assert_eq!(dot(&a, &b), expected.to_byte(), "{}", len);
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! The wide binary fields GF(2^32), GF(2^64) and GF(2^128).
+//!
+//! Tables are out of the question at these sizes, so elements are
+//! multiplied as polynomials with a carry-less multiplication and then
+//! reduced. On x86_64 the product uses PCLMULQDQ and on aarch64 PMULL when
+//! the build enables it, or (with std) when the CPU reports it at runtime;
+//! everywhere else a portable shift-and-xor loop computes the same product.
+//!
+//! mul_gf256 and mul4_gf256 use the same instruction for GF(256) itself,
+//! reducing by x^8 + x^4 + x^3 + x^2 + 1 in two folds. With the hardware
+//! instruction this involves neither tables nor branches; backend::Clmul
+//! builds the bulk operations on it.
+//!
+//! The moduli are
+//! - Gf2_32: x^32 + x^7 + x^3 + x^2 + 1
+//! - Gf2_64: x^64 + x^4 + x^3 + x + 1
+//! - Gf2_128: x^128 + x^7 + x^2 + x + 1, the field of GHASH
+//!
+//! In all of them bit i of the value is the coefficient of x^i. GHASH
+//! (GCM) and POLYVAL (AES-GCM-SIV, RFC 8452) write their blocks with other
+//! bit orders; Gf2_128 converts from and to both, and ghash and polyval
+//! compute the universal hashes.
+//!
+//! Inverses are computed as a^(2^k - 2), so inv and division cost about
+//! 2k multiplications.
+
+use core::fmt;
+use core::ops::{ Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign };
+
+/// Low bits of the GF(2^32) modulus
+const POLY32: u64 = 0x8d;
+/// Low bits of the GF(2^64) modulus
+const POLY64: u64 = 0x1b;
+/// Low bits of the GF(2^128) modulus
+const POLY128: u64 = 0x87;
+
+/// Carry-less product of a and b, shifting and xoring
+pub const fn clmul64_portable(a: u64, b: u64) -> u128 {
+	let (a, mut b, mut acc) = (a as u128, b, 0u128);
+	let mut i = 0;
+	while b != 0 {
+		if b & 1 != 0 {
+			acc ^= a << i;
+		}
+		b >>= 1;
+		i += 1;
+	}
+	acc
+}
+
+#[cfg(all(target_arch = "x86_64",
+	any(feature = "std", all(target_feature = "pclmulqdq", target_feature = "sse4.1"))))]
+mod x86 {
+	use core::arch::x86_64::{ _mm_clmulepi64_si128, _mm_cvtsi128_si64, _mm_extract_epi64, _mm_set_epi64x };
+
+	#[target_feature(enable = "pclmulqdq,sse4.1")]
+	pub unsafe fn clmul64(a: u64, b: u64) -> u128 {
+		let p = _mm_clmulepi64_si128(_mm_set_epi64x(0, a as i64), _mm_set_epi64x(0, b as i64), 0);
+		let lo = _mm_cvtsi128_si64(p) as u64 as u128;
+		let hi = _mm_extract_epi64(p, 1) as u64 as u128;
+		hi << 64 | lo
+	}
+}
+
+#[cfg(all(target_arch = "aarch64", any(feature = "std", target_feature = "aes")))]
+mod arm {
+	use core::arch::aarch64::vmull_p64;
+
+	#[target_feature(enable = "neon,aes")]
+	pub unsafe fn clmul64(a: u64, b: u64) -> u128 {
+		vmull_p64(a, b)
+	}
+}
+
+/// Carry-less product of a and b, with PCLMULQDQ or PMULL where available
+#[inline]
+pub fn clmul64(a: u64, b: u64) -> u128 {
+	#[cfg(all(target_arch = "x86_64", target_feature = "pclmulqdq", target_feature = "sse4.1"))]
+	{
+		// Enabled for the whole build
+		return unsafe { x86::clmul64(a, b) };
+	}
+	#[cfg(all(feature = "std", target_arch = "x86_64",
+		not(all(target_feature = "pclmulqdq", target_feature = "sse4.1"))))]
+	{
+		if std::is_x86_feature_detected!("pclmulqdq") && std::is_x86_feature_detected!("sse4.1") {
+			return unsafe { x86::clmul64(a, b) };
+		}
+	}
+	#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+	{
+		return unsafe { arm::clmul64(a, b) };
+	}
+	#[cfg(all(feature = "std", target_arch = "aarch64", not(target_feature = "aes")))]
+	{
+		if std::arch::is_aarch64_feature_detected!("pmull") {
+			return unsafe { arm::clmul64(a, b) };
+		}
+	}
+	#[allow(unreachable_code)]
+	clmul64_portable(a, b)
+}
+
+/// The low byte of every 16 bit lane
+const LANES: u64 = 0x00ff_00ff_00ff_00ff;
+
+/// Multiplies the four bytes in the low halves of the 16 bit lanes of a
+/// by c. The high halves must be zero and are zero in the result.
+#[inline]
+pub fn mul4_gf256(a: u64, c: u8) -> u64 {
+	// Lane products have at most 15 bits and stay in their lanes
+	let p = clmul64(a, c as u64) as u64;
+	// Bits 8 to 14 fold into at most 11 bits, the remaining 3 into 7
+	let p = (p & LANES) ^ clmul64(p >> 8 & LANES, crate::POLY as u64) as u64;
+	(p & LANES) ^ clmul64(p >> 8 & LANES, crate::POLY as u64) as u64
+}
+
+/// a * b in the field of Gf256
+#[inline]
+pub fn mul_gf256(a: u8, b: u8) -> u8 {
+	mul4_gf256(a as u64, b) as u8
+}
+
+fn reduce32(p: u64) -> u32 {
+	// x^32 = x^7 + x^3 + x^2 + 1; the high half times POLY32 has at most
+	// 39 bits, so folding twice is enough.
+	let t = clmul64(p >> 32, POLY32) as u64;
+	let t = (t & 0xffff_ffff) ^ clmul64(t >> 32, POLY32) as u64;
+	(p as u32) ^ (t as u32)
+}
+
+fn reduce64(p: u128) -> u64 {
+	let t = clmul64((p >> 64) as u64, POLY64);
+	let t = (t as u64) ^ clmul64((t >> 64) as u64, POLY64) as u64;
+	(p as u64) ^ t
+}
+
+/// Carry-less product of a and b as (high, low) halves
+fn clmul128(a: u128, b: u128) -> (u128, u128) {
+	let (a1, a0) = ((a >> 64) as u64, a as u64);
+	let (b1, b0) = ((b >> 64) as u64, b as u64);
+	let lo = clmul64(a0, b0);
+	let hi = clmul64(a1, b1);
+	let mid = clmul64(a0, b1) ^ clmul64(a1, b0);
+	(hi ^ (mid >> 64), lo ^ (mid << 64))
+}
+
+/// Multiplies by a low polynomial of at most 64 bits
+fn clmul128_low(a: u128, low: u64) -> (u128, u128) {
+	let lo = clmul64(a as u64, low);
+	let mid = clmul64((a >> 64) as u64, low);
+	(mid >> 64, lo ^ (mid << 64))
+}
+
+fn reduce128((hi, lo): (u128, u128)) -> u128 {
+	let (t1, t0) = clmul128_low(hi, POLY128);
+	let (_, u0) = clmul128_low(t1, POLY128);
+	lo ^ t0 ^ u0
+}
+
+macro_rules! wide_field {
+	($name:ident, $int:ty, $inv_exp:expr, $mul:expr) => {
+		/// An element of a wide binary field, see the module documentation
+		#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+		#[repr(transparent)]
+		pub struct $name {
+			pub poly: $int
+		}
+
+		impl $name {
+			#[inline]
+			pub const fn zero() -> $name {
+				$name { poly: 0 }
+			}
+
+			#[inline]
+			pub const fn one() -> $name {
+				$name { poly: 1 }
+			}
+
+			#[inline]
+			pub const fn new(v: $int) -> $name {
+				$name { poly: v }
+			}
+
+			#[inline]
+			pub const fn value(&self) -> $int {
+				self.poly
+			}
+
+			/// self ^ e by square and multiply
+			pub fn pow(&self, mut e: u128) -> $name {
+				let (mut base, mut acc) = (*self, $name::one());
+				while e != 0 {
+					if e & 1 != 0 {
+						acc *= base;
+					}
+					base *= base;
+					e >>= 1;
+				}
+				acc
+			}
+
+			/// None for zero
+			pub fn inv(&self) -> Option<$name> {
+				if self.poly == 0 {
+					None
+				} else {
+					Some(self.pow($inv_exp))
+				}
+			}
+
+			/// self / rhs, or None if rhs is zero
+			pub fn checked_div(self, rhs: $name) -> Option<$name> {
+				rhs.inv().map(|r| self * r)
+			}
+		}
+
+		impl fmt::Display for $name {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				fmt::Display::fmt(&self.poly, f)
+			}
+		}
+
+		impl fmt::LowerHex for $name {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				fmt::LowerHex::fmt(&self.poly, f)
+			}
+		}
+
+		impl Add for $name {
+			type Output = $name;
+			#[inline]
+			#[allow(clippy::suspicious_arithmetic_impl)]
+			fn add(self, rhs: $name) -> $name {
+				$name { poly: self.poly ^ rhs.poly }
+			}
+		}
+
+		impl Sub for $name {
+			type Output = $name;
+			#[inline]
+			#[allow(clippy::suspicious_arithmetic_impl)]
+			fn sub(self, rhs: $name) -> $name {
+				$name { poly: self.poly ^ rhs.poly }
+			}
+		}
+
+		impl Mul for $name {
+			type Output = $name;
+			#[inline]
+			fn mul(self, rhs: $name) -> $name {
+				$name { poly: $mul(self.poly, rhs.poly) }
+			}
+		}
+
+		impl Div for $name {
+			type Output = $name;
+			/// Panics if rhs is zero
+			fn div(self, rhs: $name) -> $name {
+				self.checked_div(rhs).expect("Division by zero")
+			}
+		}
+
+		impl AddAssign for $name {
+			#[inline]
+			fn add_assign(&mut self, rhs: $name) {
+				*self = *self + rhs;
+			}
+		}
+
+		impl SubAssign for $name {
+			#[inline]
+			fn sub_assign(&mut self, rhs: $name) {
+				*self = *self - rhs;
+			}
+		}
+
+		impl MulAssign for $name {
+			#[inline]
+			fn mul_assign(&mut self, rhs: $name) {
+				*self = *self * rhs;
+			}
+		}
+
+		impl DivAssign for $name {
+			#[inline]
+			fn div_assign(&mut self, rhs: $name) {
+				*self = *self / rhs;
+			}
+		}
+	};
+}
+
+wide_field!(Gf2_32, u32, (1u128 << 32) - 2, |a: u32, b: u32| reduce32(clmul64(a as u64, b as u64) as u64));
+wide_field!(Gf2_64, u64, (1u128 << 64) - 2, |a: u64, b: u64| reduce64(clmul64(a, b)));
+wide_field!(Gf2_128, u128, u128::MAX - 1, |a: u128, b: u128| reduce128(clmul128(a, b)));
+
+impl Gf2_128 {
+	/// Reads a GHASH block, whose first bit (the msb of byte 0) is the
+	/// coefficient of x^0
+	pub fn from_ghash(block: [u8; 16]) -> Gf2_128 {
+		Gf2_128::new(u128::from_be_bytes(block).reverse_bits())
+	}
+
+	pub fn to_ghash(&self) -> [u8; 16] {
+		self.poly.reverse_bits().to_be_bytes()
+	}
+
+	/// Reads a POLYVAL block (little endian, bit 0 of byte 0 is x^0 in
+	/// POLYVAL's field). The mapping is the byte reversal of RFC 8452,
+	/// appendix A; products differ from POLYVAL's by a factor x^-128.
+	pub fn from_polyval(block: [u8; 16]) -> Gf2_128 {
+		Gf2_128::new(u128::from_le_bytes(block).reverse_bits())
+	}
+
+	pub fn to_polyval(&self) -> [u8; 16] {
+		self.poly.reverse_bits().to_le_bytes()
+	}
+}
+
+/// GHASH_H of the 16 byte blocks, i.e. sum X_i H^(n - i + 1)
+pub fn ghash(h: [u8; 16], blocks: &[[u8; 16]]) -> [u8; 16] {
+	let h = Gf2_128::from_ghash(h);
+	blocks.iter().fold(Gf2_128::zero(), |acc, &b| (acc + Gf2_128::from_ghash(b)) * h).to_ghash()
+}
+
+/// POLYVAL_H of the 16 byte blocks, as in RFC 8452
+pub fn polyval(h: [u8; 16], blocks: &[[u8; 16]]) -> [u8; 16] {
+	// POLYVAL(H, X) = ByteReverse(GHASH(mulX_GHASH(ByteReverse(H)), ByteReverse(X)))
+	let h = Gf2_128::from_polyval(h) * Gf2_128::new(2);
+	blocks.iter().fold(Gf2_128::zero(), |acc, &b| (acc + Gf2_128::from_polyval(b)) * h).to_polyval()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+
+    #[test]
+    fn test_gf256() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(mul_gf256(a, b), (Gf256::from_byte(a) * Gf256::from_byte(b)).poly);
+            }
+        }
+        let lanes = 0x0012_00ff_0080_0001u64;
+        assert_eq!(mul4_gf256(lanes, 0x53), (mul_gf256(0x12, 0x53) as u64) << 48
+            | (mul_gf256(0xff, 0x53) as u64) << 32 | (mul_gf256(0x80, 0x53) as u64) << 16 | 0x53);
+    }
+
+    /// Bitwise multiplication modulo x^bits + low, as a reference
+    fn slow_mul(a: u64, b: u64, bits: u32, low: u64) -> u64 {
+        let top = 1u64 << (bits - 1);
+        let mask = if bits == 64 { u64::MAX } else { (1 << bits) - 1 };
+        let (mut a, mut b, mut acc) = (a, b, 0u64);
+        while b != 0 {
+            if b & 1 != 0 {
+                acc ^= a;
+            }
+            let carry = a & top != 0;
+            a = (a << 1) & mask;
+            if carry {
+                a ^= low;
+            }
+            b >>= 1;
+        }
+        acc
+    }
+
+    #[test]
+    fn test_clmul() {
+        let mut x = 0x0123_4567_89ab_cdefu64;
+        for _ in 0..100 {
+            let y = x.rotate_left(17) ^ 0x9e37_79b9_7f4a_7c15;
+            assert_eq!(clmul64(x, y), clmul64_portable(x, y));
+            x = x.wrapping_mul(0x5851_f42d_4c95_7f2d).wrapping_add(1);
+        }
+        assert_eq!(clmul64_portable(0b11, 0b11), 0b101);
+        assert_eq!(clmul64_portable(u64::MAX, 2), (u64::MAX as u128) << 1);
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut x = 0xdead_beef_0bad_f00du64;
+        for _ in 0..100 {
+            let y = x.rotate_left(29) ^ 0x1234_5678;
+            assert_eq!((Gf2_64::new(x) * Gf2_64::new(y)).value(), slow_mul(x, y, 64, POLY64));
+            let (a, b) = (x as u32, y as u32);
+            assert_eq!((Gf2_32::new(a) * Gf2_32::new(b)).value() as u64, slow_mul(a as u64, b as u64, 32, POLY32));
+            x = x.wrapping_mul(0x5851_f42d_4c95_7f2d).wrapping_add(3);
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        for v in [1u64, 2, 3, 0x8000_0000_0000_0000, 0x1234_5678_9abc_def0] {
+            let a = Gf2_64::new(v);
+            assert_eq!(a * a.inv().unwrap(), Gf2_64::one());
+            let b = Gf2_32::new(v as u32 | 1);
+            assert_eq!(b * b.inv().unwrap(), Gf2_32::one());
+            let mut c = Gf2_32::new(77);
+            c /= b;
+            assert_eq!(c * b, Gf2_32::new(77));
+        }
+        assert_eq!(Gf2_64::zero().inv(), None);
+    }
+
+    fn hex(s: &str) -> [u8; 16] {
+        let mut out = [0u8; 16];
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_gf2_128() {
+        let a = Gf2_128::new(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        assert_eq!(a * a.inv().unwrap(), Gf2_128::one());
+        // x^127 * x = x^7 + x^2 + x + 1
+        assert_eq!(Gf2_128::new(1 << 127) * Gf2_128::new(2), Gf2_128::new(0x87));
+        assert_eq!(Gf2_128::from_ghash(a.to_ghash()), a);
+        assert_eq!(Gf2_128::from_ghash(hex("80000000000000000000000000000000")), Gf2_128::one());
+    }
+
+    #[test]
+    fn test_ghash() {
+        // GCM specification, test case 2: the hash of the ciphertext and
+        // the length block
+        let h = hex("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let blocks = [hex("0388dace60b6a392f328c2b971b2fe78"), hex("00000000000000000000000000000080")];
+        assert_eq!(ghash(h, &blocks), hex("f38cbb1ad69223dcc3457ae5b6b0f885"));
+    }
+
+    #[test]
+    fn test_polyval() {
+        // RFC 8452, appendix A
+        let h = hex("25629347589242761d31f826ba4b757b");
+        let blocks = [hex("4f4f95668c83dfb6401762bb2d01a262"), hex("d1a24ddd2721d006bbe45f20d3c9f362")];
+        assert_eq!(polyval(h, &blocks), hex("f7a3b47b846119fae5b7866cf5e5b77e"));
+    }
+}
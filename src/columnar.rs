@@ -0,0 +1,167 @@
+//! Kernels over columnar buffers with validity bitmaps.
+//!
+//! These follow the Apache Arrow memory layout: a column of u8 (or fixed
+//! size binary) values is one contiguous byte buffer, and nullability is
+//! a separate bitmap with bit i, least significant bit first, set when
+//! slot i is valid. Arrow arrays hand out exactly these views through
+//! Buffer::as_slice and the null buffer's bytes and offset, so the
+//! functions here run on Arrow data in place without depending on the
+//! arrow crates.
+//!
+//! Null slots may contain anything. Transforms leave them untouched and
+//! reductions treat them as zero, so results never depend on garbage
+//! behind a null.
+//!
+//! Enabled with the `columnar` feature.
+
+use std::borrow::Cow;
+
+use crate::Gf256;
+use crate::bytes;
+use crate::rs::erasure::{ ErasureCode, ErasureError };
+
+/// A validity bitmap, starting at bit offset of bits
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Validity<'a> {
+	bits: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Validity<'a> {
+	pub fn new(bits: &'a [u8], offset: usize) -> Validity<'a> {
+		Validity { bits, offset }
+	}
+
+	/// Whether slot i is valid
+	#[inline]
+	pub fn is_valid(&self, i: usize) -> bool {
+		let bit = self.offset + i;
+		self.bits[bit / 8] & (1 << (bit % 8)) != 0
+	}
+
+	/// Number of null slots among the first len
+	pub fn null_count(&self, len: usize) -> usize {
+		(0..len).filter(|&i| !self.is_valid(i)).count()
+	}
+}
+
+/// A column of byte values, None validity meaning no nulls
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Column<'a> {
+	pub values: &'a [u8],
+	pub validity: Option<Validity<'a>>,
+}
+
+impl<'a> Column<'a> {
+	pub fn new(values: &'a [u8], validity: Option<Validity<'a>>) -> Column<'a> {
+		Column { values, validity }
+	}
+
+	#[inline]
+	fn is_valid(&self, i: usize) -> bool {
+		self.validity.is_none_or(|v| v.is_valid(i))
+	}
+
+	/// The values with null slots replaced by zero
+	pub fn to_dense(&self) -> Vec<u8> {
+		self.values.iter().enumerate()
+			.map(|(i, &v)| if self.is_valid(i) { v } else { 0 })
+			.collect()
+	}
+}
+
+/// values[i] = c * values[i] for every valid slot
+pub fn mul_scalar(values: &mut [u8], validity: Option<Validity>, c: u8) {
+	match validity {
+		None => bytes::mul_scalar(values, c),
+		Some(v) => {
+			let table = bytes::mul_table(c);
+			for (i, x) in values.iter_mut().enumerate() {
+				if v.is_valid(i) {
+					*x = table[*x as usize];
+				}
+			}
+		},
+	}
+}
+
+/// dst[i] = dst[i] + c * src[i] for every slot valid in src; dst keeps
+/// its value where src is null.
+pub fn mul_add(dst: &mut [u8], src: Column, c: u8) {
+	assert_eq!(dst.len(), src.values.len(), "Length mismatch");
+	match src.validity {
+		None => bytes::mul_add(dst, src.values, c),
+		Some(_) => bytes::mul_add(dst, &src.to_dense(), c),
+	}
+}
+
+/// Polynomial fingerprint sum values[i] key^(len - 1 - i), nulls as zero.
+/// Two columns with the same valid values collide only with probability
+/// len / 256 over the choice of key.
+pub fn fingerprint(column: Column, key: Gf256) -> Gf256 {
+	column.values.iter().enumerate().fold(Gf256::zero(), |acc, (i, &v)| {
+		let v = if column.is_valid(i) { v } else { 0 };
+		acc * key + Gf256::from_byte(v)
+	})
+}
+
+/// Computes erasure code parity over equally long columns, taking null
+/// slots as zero. Reconstructed columns therefore hold zero in slots
+/// that were null.
+pub fn encode_parity(code: &ErasureCode, columns: &[Column], parity: &mut [&mut [u8]])
+	-> Result<(), ErasureError> {
+	let dense: Vec<Cow<[u8]>> = columns.iter().map(|c| match c.validity {
+		None => c.values.into(),
+		Some(_) => c.to_dense().into(),
+	}).collect();
+	let refs: Vec<&[u8]> = dense.iter().map(|d| &d[..]).collect();
+	code.encode(&refs, parity)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validity() {
+        // Slots 0, 2 and 9 valid, starting at bit 1
+        let bits = [0b0000_1010, 0b0000_0100];
+        let v = Validity::new(&bits, 1);
+        assert!(v.is_valid(0) && !v.is_valid(1) && v.is_valid(2) && v.is_valid(9));
+        assert_eq!(v.null_count(10), 7);
+    }
+
+    #[test]
+    fn test_mul_skips_nulls() {
+        let bits = [0b101];
+        let mut values = [3u8, 0xff, 5];
+        mul_scalar(&mut values, Some(Validity::new(&bits, 0)), 2);
+        assert_eq!(values, [6, 0xff, 10]);
+
+        let mut dst = [1u8, 1, 1];
+        mul_add(&mut dst, Column::new(&[1, 9, 1], Some(Validity::new(&bits, 0))), 1);
+        assert_eq!(dst, [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_nulls_do_not_matter() {
+        let bits = [0b1101];
+        let key = Gf256::from_byte(0x35);
+        let a = Column::new(&[1, 2, 3, 4], Some(Validity::new(&bits, 0)));
+        let b = Column::new(&[1, 99, 3, 4], Some(Validity::new(&bits, 0)));
+        assert_eq!(fingerprint(a, key), fingerprint(b, key));
+        assert_eq!(fingerprint(a, key), fingerprint(Column::new(&[1, 0, 3, 4], None), key));
+
+        let code = ErasureCode::new(2, 1).unwrap();
+        let mut pa = [0u8; 4];
+        let mut pb = [0u8; 4];
+        encode_parity(&code, &[a, b], &mut [&mut pa]).unwrap();
+        encode_parity(&code, &[b, a], &mut [&mut pb]).unwrap();
+        let dense = a.to_dense();
+        let mut expected = [0u8; 4];
+        code.encode(&[&dense, &dense], &mut [&mut expected]).unwrap();
+        assert_eq!(pa, expected);
+        assert_eq!(pb, expected);
+    }
+}
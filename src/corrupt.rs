@@ -0,0 +1,200 @@
+//! Helpers which corrupt encoded shards or shares in controlled ways.
+//!
+//! These are meant for negative tests: damage a buffer (or a set of
+//! buffers), feed it to a decoder and check the outcome with one of the
+//! expect_* functions. The random variants are driven by a small
+//! deterministic generator so that a failing test can be reproduced from
+//! its seed.
+
+use core::fmt::Debug;
+use alloc::vec::Vec;
+
+/// Flips a single bit. Bit 0 is the least significant bit of buf[0].
+pub fn flip_bit(buf: &mut [u8], bit: usize) {
+	buf[bit / 8] ^= 1 << (bit % 8);
+}
+
+/// Flips every bit in bits
+pub fn flip_bits(buf: &mut [u8], bits: &[usize]) {
+	for &bit in bits {
+		flip_bit(buf, bit);
+	}
+}
+
+/// Replaces the byte at every position in positions by a different value
+pub fn corrupt_bytes(buf: &mut [u8], positions: &[usize]) {
+	for &pos in positions {
+		buf[pos] = !buf[pos];
+	}
+}
+
+/// Cuts buf down to len bytes. Does nothing if buf is already shorter.
+pub fn truncate(buf: &mut Vec<u8>, len: usize) {
+	buf.truncate(len);
+}
+
+/// Exchanges two shards, e.g. to simulate shards stored under the wrong
+/// index.
+pub fn swap_shards<T>(shards: &mut [T], a: usize, b: usize) {
+	shards.swap(a, b);
+}
+
+/// Removes the shards at the given indices, turning them into erasures
+pub fn erase_shards<T>(shards: &mut [Option<T>], indices: &[usize]) {
+	for &i in indices {
+		shards[i] = None;
+	}
+}
+
+/// Deterministic source of random corruption
+pub struct Corruptor {
+	state: u64
+}
+
+impl Corruptor {
+	/// Creates a corruptor. The same seed always produces the same damage.
+	pub fn new(seed: u64) -> Corruptor {
+		// xorshift must not start from zero, which the one seed equal to
+		// the constant would give
+		let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+		Corruptor { state: if state == 0 { 0x9e37_79b9_7f4a_7c15 } else { state } }
+	}
+
+	fn next(&mut self) -> u64 {
+		// xorshift64*
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+	}
+
+	fn below(&mut self, n: usize) -> usize {
+		(self.next() % (n as u64)) as usize
+	}
+
+	/// Picks count distinct indices below n, in increasing order
+	pub fn choose(&mut self, n: usize, count: usize) -> Vec<usize> {
+		assert!(count <= n, "Can not choose more indices than available");
+		let mut all: Vec<usize> = (0..n).collect();
+		for i in 0..count {
+			let j = i + self.below(n - i);
+			all.swap(i, j);
+		}
+		let mut chosen = all[..count].to_vec();
+		chosen.sort();
+		chosen
+	}
+
+	/// Flips count distinct random bits and returns their positions
+	pub fn flip_random_bits(&mut self, buf: &mut [u8], count: usize) -> Vec<usize> {
+		let bits = self.choose(buf.len() * 8, count);
+		flip_bits(buf, &bits);
+		bits
+	}
+
+	/// Corrupts count distinct random bytes and returns their positions.
+	/// Each byte is replaced by a different, random value.
+	pub fn corrupt_random_bytes(&mut self, buf: &mut [u8], count: usize) -> Vec<usize> {
+		let positions = self.choose(buf.len(), count);
+		for &pos in &positions {
+			let delta = 1 + self.below(255) as u8;
+			buf[pos] ^= delta;
+		}
+		positions
+	}
+
+	/// Erases count distinct random shards and returns their indices
+	pub fn erase_random_shards<T>(&mut self, shards: &mut [Option<T>], count: usize)
+		-> Vec<usize> {
+		let indices = self.choose(shards.len(), count);
+		erase_shards(shards, &indices);
+		indices
+	}
+}
+
+/// Panics unless the decoder restored the original data
+pub fn expect_corrected<T, E>(original: &[T], result: Result<Vec<T>, E>)
+	where T: PartialEq + Debug, E: Debug {
+	match result {
+		Ok(decoded) => assert_eq!(decoded.as_slice(), original,
+			"Decoder returned wrong data"),
+		Err(e) => panic!("Decoder failed to correct: {:?}", e),
+	}
+}
+
+/// Panics unless the decoder reported an error
+pub fn expect_detected<T, E>(result: Result<T, E>) where T: Debug {
+	if let Ok(decoded) = result {
+		panic!("Decoder accepted corrupted input: {:?}", decoded);
+	}
+}
+
+/// Panics if the decoder silently returned wrong data. Both a correct
+/// result and a reported error are accepted.
+pub fn expect_not_miscorrected<T, E>(original: &[T], result: Result<Vec<T>, E>)
+	where T: PartialEq + Debug {
+	if let Ok(decoded) = result {
+		assert_eq!(decoded.as_slice(), original, "Decoder returned wrong data");
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flip_bit() {
+        let mut buf = [0u8; 2];
+        flip_bit(&mut buf, 9);
+        assert_eq!(buf, [0, 2]);
+        flip_bit(&mut buf, 9);
+        assert_eq!(buf, [0, 0]);
+    }
+
+    #[test]
+    fn test_corruptor_deterministic() {
+        let mut a = vec![0u8; 32];
+        let mut b = vec![0u8; 32];
+        let pa = Corruptor::new(7).corrupt_random_bytes(&mut a, 5);
+        let pb = Corruptor::new(7).corrupt_random_bytes(&mut b, 5);
+        assert_eq!(pa, pb);
+        assert_eq!(a, b);
+
+        // Exactly the chosen bytes changed:
+        assert_eq!(pa.len(), 5);
+        for (i, &x) in a.iter().enumerate() {
+            assert_eq!(x != 0, pa.contains(&i));
+        }
+    }
+
+    #[test]
+    fn test_choose_distinct() {
+        let mut c = Corruptor::new(1);
+        let chosen = c.choose(10, 10);
+        assert_eq!(chosen, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_zero_state_seed() {
+        // This seed cancels the constant; it must not get stuck at zero
+        let mut c = Corruptor::new(0x9e37_79b9_7f4a_7c15);
+        assert_ne!(c.choose(100, 5), (0..5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_erase_random_shards() {
+        let mut shards: Vec<Option<u8>> = (0..6).map(Some).collect();
+        let erased = Corruptor::new(3).erase_random_shards(&mut shards, 2);
+        assert_eq!(shards.iter().filter(|s| s.is_none()).count(), 2);
+        for i in erased {
+            assert_eq!(shards[i], None);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_expect_detected_panics_on_ok() {
+        expect_detected::<u8, ()>(Ok(1));
+    }
+}
@@ -0,0 +1,201 @@
+//! Cyclic redundancy checks of any width up to 64 bits.
+//!
+//! A CRC is a polynomial remainder over GF(2): the message, read as a
+//! polynomial M(x) with the first bit as the highest coefficient, times
+//! x^width modulo the generator G(x). This is the same arithmetic as the
+//! field multiplication of this crate, with a reducible modulus. The
+//! Params follow the usual parameter model (width, poly, init, refin,
+//! refout, xorout; see the catalogue of parametrised CRC algorithms by
+//! Greg Cook), so the constants below can be checked against it with
+//! their check value, the CRC of "123456789".
+//!
+//! Crc::new computes the 256 entry table of the remainders of the bytes
+//! at compile time; Crc::checksum then processes a byte per lookup. For
+//! reflected CRCs the register is kept reflected instead of reflecting
+//! every input byte.
+
+/// The parameters of a CRC
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Params {
+	/// Degree of the generator, 1 to 64
+	pub width: u32,
+	/// The generator without its x^width term, highest degree in the
+	/// most significant bit
+	pub poly: u64,
+	/// Initial register value
+	pub init: u64,
+	/// Whether input bytes are processed least significant bit first
+	pub refin: bool,
+	/// Whether the register is reflected before xorout
+	pub refout: bool,
+	pub xorout: u64,
+	/// The CRC of the ASCII string "123456789"
+	pub check: u64,
+}
+
+pub const CRC_8_SMBUS: Params = Params { width: 8, poly: 0x07, init: 0, refin: false, refout: false, xorout: 0, check: 0xf4 };
+pub const CRC_16_ARC: Params = Params { width: 16, poly: 0x8005, init: 0, refin: true, refout: true, xorout: 0, check: 0xbb3d };
+pub const CRC_16_IBM_3740: Params = Params { width: 16, poly: 0x1021, init: 0xffff, refin: false, refout: false, xorout: 0, check: 0x29b1 };
+/// The CRC-32 of zip, PNG and Ethernet
+pub const CRC_32_ISO_HDLC: Params = Params {
+	width: 32, poly: 0x04c1_1db7, init: 0xffff_ffff, refin: true, refout: true, xorout: 0xffff_ffff, check: 0xcbf4_3926,
+};
+/// CRC-32C (Castagnoli), as used by iSCSI and ext4
+pub const CRC_32_ISCSI: Params = Params {
+	width: 32, poly: 0x1edc_6f41, init: 0xffff_ffff, refin: true, refout: true, xorout: 0xffff_ffff, check: 0xe306_9283,
+};
+pub const CRC_64_ECMA_182: Params = Params {
+	width: 64, poly: 0x42f0_e1eb_a9ea_3693, init: 0, refin: false, refout: false, xorout: 0, check: 0x6c40_df5f_0b49_7347,
+};
+/// The CRC-64 of xz
+pub const CRC_64_XZ: Params = Params {
+	width: 64, poly: 0x42f0_e1eb_a9ea_3693, init: u64::MAX, refin: true, refout: true, xorout: u64::MAX,
+	check: 0x995d_c9bb_df19_39fa,
+};
+
+/// The IEEE CRC-32
+pub static CRC32: Crc = Crc::new(CRC_32_ISO_HDLC);
+
+/// Reverses the low width bits of v
+const fn reflect(v: u64, width: u32) -> u64 {
+	v.reverse_bits() >> (64 - width)
+}
+
+/// A CRC with its lookup table
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Crc {
+	params: Params,
+	table: [u64; 256],
+}
+
+impl Crc {
+	/// Builds the table; fails const evaluation for widths outside 1 to
+	/// 64
+	pub const fn new(params: Params) -> Crc {
+		let w = params.width;
+		assert!(w >= 1 && w <= 64, "CRC width must be between 1 and 64");
+		let mut table = [0u64; 256];
+		let mut i = 0;
+		while i < 256 {
+			let mut c;
+			if params.refin {
+				// Reflected register: x^0 in the top bit of the width,
+				// shifting right
+				let poly = reflect(params.poly, w);
+				c = i as u64;
+				let mut bit = 0;
+				while bit < 8 {
+					c = (c >> 1) ^ (poly & 0u64.wrapping_sub(c & 1));
+					bit += 1;
+				}
+			} else {
+				// Register aligned to the top of the word, shifting left
+				let poly = params.poly << (64 - w);
+				c = (i as u64) << 56;
+				let mut bit = 0;
+				while bit < 8 {
+					c = (c << 1) ^ (poly & 0u64.wrapping_sub(c >> 63));
+					bit += 1;
+				}
+			}
+			table[i] = c;
+			i += 1;
+		}
+		Crc { params, table }
+	}
+
+	pub fn params(&self) -> &Params {
+		&self.params
+	}
+
+	/// The CRC of data
+	pub fn checksum(&self, data: &[u8]) -> u64 {
+		let mut d = self.digest();
+		d.update(data);
+		d.finalize()
+	}
+
+	/// Starts an incremental computation
+	pub fn digest(&self) -> Digest<'_> {
+		let Params { width, init, refin, .. } = self.params;
+		let reg = if refin { reflect(init, width) } else { init << (64 - width) };
+		Digest { crc: self, reg }
+	}
+}
+
+/// A CRC computed over data given in pieces
+#[derive(Clone,Debug)]
+pub struct Digest<'a> {
+	crc: &'a Crc,
+	reg: u64,
+}
+
+impl Digest<'_> {
+	pub fn update(&mut self, data: &[u8]) {
+		let table = &self.crc.table;
+		if self.crc.params.refin {
+			for &b in data {
+				self.reg = table[((self.reg ^ b as u64) & 0xff) as usize] ^ (self.reg >> 8);
+			}
+		} else {
+			for &b in data {
+				self.reg = table[((self.reg >> 56) ^ b as u64) as usize] ^ (self.reg << 8);
+			}
+		}
+	}
+
+	pub fn finalize(&self) -> u64 {
+		let Params { width, refin, refout, xorout, .. } = self.crc.params;
+		// The register in normal bit order
+		let reg = if refin { reflect(self.reg, width) } else { self.reg >> (64 - width) };
+		let out = if refout { reflect(reg, width) } else { reg };
+		out ^ xorout
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOGUE: [Params; 7] = [CRC_8_SMBUS, CRC_16_ARC, CRC_16_IBM_3740, CRC_32_ISO_HDLC, CRC_32_ISCSI,
+        CRC_64_ECMA_182, CRC_64_XZ];
+
+    /// M(x) x^width mod G(x), one bit at a time, with init added to the
+    /// first width bits of the message
+    fn remainder(params: &Params, data: &[u8]) -> u64 {
+        let w = params.width;
+        let mask = u64::MAX >> (64 - w);
+        let mut reg = params.init;
+        for &b in data {
+            let b = if params.refin { b.reverse_bits() } else { b };
+            for bit in (0..8).rev() {
+                let feedback = (reg >> (w - 1) ^ (b >> bit) as u64) & 1;
+                reg = (reg << 1 ^ params.poly & 0u64.wrapping_sub(feedback)) & mask;
+            }
+        }
+        (if params.refout { reflect(reg, w) } else { reg }) ^ params.xorout
+    }
+
+    #[test]
+    fn test_catalogue() {
+        for params in CATALOGUE {
+            let crc = Crc::new(params);
+            assert_eq!(crc.checksum(b"123456789"), params.check, "{:?}", params);
+            let mut d = crc.digest();
+            d.update(b"1234");
+            d.update(b"56789");
+            assert_eq!(d.finalize(), params.check);
+        }
+        assert_eq!(CRC32.checksum(b""), 0);
+    }
+
+    #[test]
+    fn test_polynomial_remainder() {
+        let data: Vec<u8> = (0..100).map(|i| (i * 37 + 11) as u8).collect();
+        for params in CATALOGUE {
+            assert_eq!(remainder(&params, b"123456789"), params.check, "{:?}", params);
+            assert_eq!(Crc::new(params).checksum(&data), remainder(&params, &data));
+        }
+    }
+}
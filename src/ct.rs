@@ -0,0 +1,214 @@
+//! Constant time arithmetic for secret data.
+//!
+//! The operators on Gf256 look up log and exp tables at indices derived
+//! from the operands, which leaks them through the cache to anyone timing
+//! the same machine. The functions here only use shifts, xors and masks:
+//! mul is the shift-and-add loop of backend::Scalar, inv raises to the
+//! power 254 with a fixed addition chain of four multiplications and seven
+//! squarings, and div multiplies with that inverse.
+//!
+//! Comparisons return a Choice instead of a bool, so that the result can
+//! be combined and used in select without branching on it. The same names
+//! as in the subtle crate are used, so code moves between the two easily.
+//!
+//! As with masked, the source is branch free but the compiler has the last
+//! word; check the generated code where it matters.
+
+use core::hint::black_box;
+use core::ops::{ BitAnd, BitOr, BitXor, Not };
+
+use crate::Gf256;
+use crate::backend::mul_const_time;
+
+/// A secret boolean, 1 for true and 0 for false
+#[derive(Copy,Clone,Debug)]
+pub struct Choice(u8);
+
+impl Choice {
+	/// Panics unless bit is 0 or 1
+	pub fn new(bit: u8) -> Choice {
+		assert!(bit <= 1, "A choice is 0 or 1");
+		Choice(black_box(bit))
+	}
+
+	pub fn unwrap_u8(&self) -> u8 {
+		self.0
+	}
+
+	/// 0xff for true, 0 for false
+	fn mask(&self) -> u8 {
+		0u8.wrapping_sub(self.0)
+	}
+}
+
+impl From<Choice> for bool {
+	/// Reveals the value; only for results that may become public
+	fn from(c: Choice) -> bool {
+		c.0 == 1
+	}
+}
+
+impl BitAnd for Choice {
+	type Output = Choice;
+	fn bitand(self, rhs: Choice) -> Choice {
+		Choice(self.0 & rhs.0)
+	}
+}
+
+impl BitOr for Choice {
+	type Output = Choice;
+	fn bitor(self, rhs: Choice) -> Choice {
+		Choice(self.0 | rhs.0)
+	}
+}
+
+impl BitXor for Choice {
+	type Output = Choice;
+	fn bitxor(self, rhs: Choice) -> Choice {
+		Choice(self.0 ^ rhs.0)
+	}
+}
+
+impl Not for Choice {
+	type Output = Choice;
+	fn not(self) -> Choice {
+		Choice(self.0 ^ 1)
+	}
+}
+
+/// Equality without data dependent branches
+pub trait ConstantTimeEq {
+	fn ct_eq(&self, other: &Self) -> Choice;
+
+	fn ct_ne(&self, other: &Self) -> Choice {
+		!self.ct_eq(other)
+	}
+}
+
+impl ConstantTimeEq for u8 {
+	fn ct_eq(&self, other: &u8) -> Choice {
+		let x = black_box(self ^ other);
+		// The top bit of x | -x is set exactly when x is nonzero
+		Choice(((x | x.wrapping_neg()) >> 7) ^ 1)
+	}
+}
+
+impl ConstantTimeEq for Gf256 {
+	fn ct_eq(&self, other: &Gf256) -> Choice {
+		self.poly.ct_eq(&other.poly)
+	}
+}
+
+/// Slices of different lengths are unequal. The lengths themselves are
+/// treated as public.
+impl<T: ConstantTimeEq> ConstantTimeEq for [T] {
+	fn ct_eq(&self, other: &[T]) -> Choice {
+		if self.len() != other.len() {
+			return Choice(0);
+		}
+		self.iter().zip(other).fold(Choice(1), |acc, (a, b)| acc & a.ct_eq(b))
+	}
+}
+
+/// a if choice is false, b if it is true
+pub fn select(a: Gf256, b: Gf256, choice: Choice) -> Gf256 {
+	Gf256::from_byte(a.poly ^ (choice.mask() & (a.poly ^ b.poly)))
+}
+
+/// Whether a is zero
+pub fn is_zero(a: Gf256) -> Choice {
+	a.ct_eq(&Gf256::zero())
+}
+
+/// a * b
+pub fn mul(a: Gf256, b: Gf256) -> Gf256 {
+	Gf256::from_byte(mul_const_time(a.poly, b.poly))
+}
+
+/// a^2
+pub fn square(a: Gf256) -> Gf256 {
+	mul(a, a)
+}
+
+/// a^e, running the same multiplications for every a and e
+pub fn pow(a: Gf256, e: u8) -> Gf256 {
+	let mut acc = Gf256::one();
+	for bit in (0..8).rev() {
+		acc = square(acc);
+		let choice = Choice(black_box(e >> bit & 1));
+		acc = select(acc, mul(acc, a), choice);
+	}
+	acc
+}
+
+/// a^254, which is the inverse of a nonzero a and zero for zero
+pub fn inv(a: Gf256) -> Gf256 {
+	let a2 = square(a);
+	let a3 = mul(a2, a);
+	let a12 = square(square(a3));
+	let a15 = mul(a12, a3);
+	let a240 = square(square(square(square(a15))));
+	let a252 = mul(a240, a12);
+	mul(a252, a2)
+}
+
+/// a / b. Division by zero gives zero, so check is_zero(b) first where
+/// that can happen.
+pub fn div(a: Gf256, b: Gf256) -> Gf256 {
+	mul(a, inv(b))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> impl Iterator<Item = Gf256> + Clone {
+        Gf256::all()
+    }
+
+    #[test]
+    fn test_matches_tables() {
+        for a in all() {
+            assert_eq!(inv(a), a.inv().unwrap_or(Gf256::zero()));
+            assert_eq!(pow(a, 254), inv(a));
+            assert_eq!(square(a), a * a);
+            for b in all().step_by(3) {
+                assert_eq!(mul(a, b), a * b);
+                if let Some(q) = a.checked_div(b) {
+                    assert_eq!(div(a, b), q);
+                }
+            }
+            // Gf256::exp takes 0^0 as 0
+            for e in [1u8, 2, 7, 128, 255] {
+                assert_eq!(pow(a, e), a.exp(e), "{:?}^{}", a, e);
+            }
+            assert_eq!(pow(a, 0), Gf256::one());
+        }
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(bool::from(a.ct_eq(&b)), a == b);
+            }
+        }
+        let x = [Gf256::from_byte(1), Gf256::from_byte(2)];
+        let y = [Gf256::from_byte(1), Gf256::from_byte(3)];
+        assert!(bool::from(x[..].ct_eq(&x[..])));
+        assert!(bool::from(x[..].ct_ne(&y[..])));
+        assert!(!bool::from(x[..].ct_eq(&x[..1])));
+        assert!(bool::from(is_zero(Gf256::zero())));
+    }
+
+    #[test]
+    fn test_select() {
+        let (a, b) = (Gf256::from_byte(0x12), Gf256::from_byte(0xa7));
+        assert_eq!(select(a, b, Choice::new(0)), a);
+        assert_eq!(select(a, b, Choice::new(1)), b);
+        let t = Choice::new(1);
+        assert_eq!((t & !t).unwrap_u8(), 0);
+        assert_eq!((t ^ !t).unwrap_u8(), 1);
+    }
+}
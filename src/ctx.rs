@@ -0,0 +1,306 @@
+//! Field contexts backed by caller-provided table memory.
+//!
+//! The arithmetic operators on Gf256 use tables owned by this crate.
+//! Environments that restrict where mutable statics may live (for
+//! example partitioned RTOS images, or tables shared between processes)
+//! can instead place a Tables value wherever they like, fill it once,
+//! and hand a reference to FieldCtx, which validates it before use.
+
+use core::fmt;
+use core::error;
+
+use crate::{ Gf256, get_tables };
+
+/// Number of bytes occupied by a Tables value
+pub const TABLES_LEN: usize = 3 * 256;
+
+/// Tables used for multiplication and division
+///
+/// The layout is fixed (exp, then log, then inv) so that the tables can
+/// be shared as plain bytes, see Tables::from_bytes.
+#[repr(C)]
+pub struct Tables {
+	pub exp: [u8; 256],
+	pub log: [u8; 256],
+	pub inv: [u8; 256]
+}
+
+impl Tables {
+	/// Tables with every entry set to zero. These do not describe a field
+	/// and have to be filled before they can be used.
+	pub const EMPTY: Tables = Tables {
+		exp: [0; 256],
+		log: [0; 256],
+		inv: [0; 256]
+	};
+
+	/// Computes the tables in place
+	pub fn fill(&mut self) {
+		self.fill_with(Gf256::GENERATOR);
+	}
+
+	/// Computes the tables with exp and log in base generator instead of
+	/// x, to match codecs with another convention. Panics unless
+	/// generator generates the multiplicative group (0x03, the AES
+	/// choice, does not in this field).
+	pub fn fill_with(&mut self, generator: Gf256) {
+		assert!(generator.is_generator(), "Not a generator of the multiplicative group");
+		let mut tmp = Gf256::one();
+		for power in 0..255usize {
+			self.exp[power] = tmp.poly;
+			self.log[tmp.poly as usize] = power as u8;
+			tmp *= generator;
+		}
+		self.exp[255] = 1;
+		self.log[0] = 0;
+		self.inv[0] = 0;
+		for x in 1..256usize {
+			let l = self.log[x];
+			let nl = if l == 0 { 0 } else { 255 - l };
+			self.inv[x] = self.exp[nl as usize];
+		}
+	}
+
+	/// Views raw table memory as Tables. The bytes are not checked here,
+	/// use FieldCtx::new to validate them.
+	pub fn from_bytes(bytes: &[u8; TABLES_LEN]) -> &Tables {
+		// Tables is repr(C) and consists only of u8 arrays, so it has
+		// the same size and alignment as the byte array.
+		unsafe { &*(bytes as *const [u8; TABLES_LEN] as *const Tables) }
+	}
+
+	/// Views the tables as raw bytes, e.g. for copying them into shared
+	/// memory.
+	pub fn as_bytes(&self) -> &[u8; TABLES_LEN] {
+		unsafe { &*(self as *const Tables as *const [u8; TABLES_LEN]) }
+	}
+
+	/// Checks that the tables describe this crate's field.
+	/// Returns the first inconsistent entry otherwise.
+	pub fn validate(&self) -> Result<(), TableError> {
+		self.validate_with(Gf256::GENERATOR)
+	}
+
+	/// Like validate, for tables made by fill_with(generator)
+	pub fn validate_with(&self, generator: Gf256) -> Result<(), TableError> {
+		let mut tmp = 1;
+		for power in 0..255usize {
+			if self.exp[power] != tmp {
+				return Err(TableError::Exp(power));
+			}
+			if self.log[tmp as usize] as usize != power {
+				return Err(TableError::Log(tmp as usize));
+			}
+			tmp = (Gf256::from_byte(tmp) * generator).poly;
+		}
+		if self.exp[255] != 1 {
+			return Err(TableError::Exp(255));
+		}
+		if self.log[0] != 0 {
+			return Err(TableError::Log(0));
+		}
+		if self.inv[0] != 0 {
+			return Err(TableError::Inv(0));
+		}
+		for x in 1..256usize {
+			let i = self.inv[x];
+			if i == 0 {
+				return Err(TableError::Inv(x));
+			}
+			let l = (self.log[x] as usize + self.log[i as usize] as usize) % 255;
+			if self.exp[l] != 1 {
+				return Err(TableError::Inv(x));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Describes the first entry of a Tables value which is inconsistent
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum TableError {
+	/// exp[i] is wrong
+	Exp(usize),
+	/// log[i] is wrong
+	Log(usize),
+	/// inv[i] is wrong
+	Inv(usize),
+}
+
+impl fmt::Display for TableError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TableError::Exp(i) => write!(f, "invalid exp table entry at {}", i),
+			TableError::Log(i) => write!(f, "invalid log table entry at {}", i),
+			TableError::Inv(i) => write!(f, "invalid inv table entry at {}", i),
+		}
+	}
+}
+
+impl error::Error for TableError {}
+
+/// Field arithmetic over a validated set of tables
+#[derive(Copy,Clone)]
+pub struct FieldCtx<'a> {
+	tables: &'a Tables,
+	generator: Gf256,
+}
+
+impl FieldCtx<'static> {
+	/// Returns a context over the tables used by the Gf256 operators.
+	/// The context is Copy, Send and Sync, so it can be handed to any
+	/// number of threads.
+	pub fn builtin() -> FieldCtx<'static> {
+		FieldCtx { tables: get_tables(), generator: Gf256::GENERATOR }
+	}
+}
+
+impl<'a> FieldCtx<'a> {
+	/// Creates a context after checking the given tables
+	pub fn new(tables: &'a Tables) -> Result<FieldCtx<'a>, TableError> {
+		FieldCtx::with_generator(tables, Gf256::GENERATOR)
+	}
+
+	/// Creates a context over tables in base generator, see
+	/// Tables::fill_with. log, xexp and exp then refer to generator;
+	/// products and inverses are the same as with x.
+	pub fn with_generator(tables: &'a Tables, generator: Gf256) -> Result<FieldCtx<'a>, TableError> {
+		tables.validate_with(generator)?;
+		Ok(FieldCtx { tables, generator })
+	}
+
+	/// Creates a context from raw table memory, see Tables::from_bytes
+	pub fn from_bytes(bytes: &'a [u8; TABLES_LEN]) -> Result<FieldCtx<'a>, TableError> {
+		FieldCtx::new(Tables::from_bytes(bytes))
+	}
+
+	/// Returns the tables used by this context
+	pub fn tables(&self) -> &'a Tables {
+		self.tables
+	}
+
+	/// The base of log and xexp
+	pub fn generator(&self) -> Gf256 {
+		self.generator
+	}
+
+	/// Find the logarithm of a in base generator()
+	pub fn log(&self, a: Gf256) -> Option<u8> {
+		if a.poly == 0 {
+			None
+		} else {
+			Some(self.tables.log[a.poly as usize])
+		}
+	}
+
+	/// Calculate generator() ^ power
+	pub fn xexp(&self, power: u8) -> Gf256 {
+		Gf256 { poly: self.tables.exp[power as usize] }
+	}
+
+	/// Calculate a ^ power
+	pub fn exp(&self, a: Gf256, power: u8) -> Gf256 {
+		match self.log(a) {
+			None => Gf256::zero(),
+			Some(i) => self.xexp((((i as u16) * (power as u16)) % 255) as u8),
+		}
+	}
+
+	/// Find the inverse of a
+	pub fn inv(&self, a: Gf256) -> Option<Gf256> {
+		if a.poly == 0 {
+			None
+		} else {
+			Some(Gf256 { poly: self.tables.inv[a.poly as usize] })
+		}
+	}
+
+	pub fn mul(&self, a: Gf256, b: Gf256) -> Gf256 {
+		if let (Some(l1), Some(l2)) = (self.log(a), self.log(b)) {
+			let tmp = ((l1 as u16) + (l2 as u16)) % 255;
+			self.xexp(tmp as u8)
+		} else {
+			Gf256::zero()
+		}
+	}
+
+	/// Divides a by b. Panics if b is zero, like the Div operator.
+	pub fn div(&self, a: Gf256, b: Gf256) -> Gf256 {
+		let l2 = self.log(b).expect("Division by zero");
+		if let Some(l1) = self.log(a) {
+			let tmp = ((l1 as u16) + 255 - (l2 as u16)) % 255;
+			self.xexp(tmp as u8)
+		} else {
+			Gf256::zero()
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_validates() {
+        let mut tabs = Tables::EMPTY;
+        assert!(tabs.validate().is_err());
+        tabs.fill();
+        assert_eq!(tabs.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_matches_operators() {
+        let mut tabs = Tables::EMPTY;
+        tabs.fill();
+        let ctx = FieldCtx::new(&tabs).unwrap();
+
+        let a = Gf256::from_byte(0x8f);
+        let b = Gf256::from_byte(0x15);
+        assert_eq!(ctx.mul(a, b), a * b);
+        assert_eq!(ctx.div(a, b), a / b);
+        assert_eq!(ctx.inv(a), a.inv());
+        assert_eq!(ctx.exp(a, 7), a.exp(7));
+        assert_eq!(ctx.inv(Gf256::zero()), None);
+    }
+
+    #[test]
+    fn test_builtin_threads() {
+        let ctx = FieldCtx::builtin();
+        let handles: Vec<_> = (1..5u8).map(|i| {
+            std::thread::spawn(move || ctx.mul(Gf256::from_byte(i), Gf256::from_byte(0x53)))
+        }).collect();
+        for (i, h) in (1..5u8).zip(handles) {
+            assert_eq!(h.join().unwrap(), Gf256::from_byte(i) * Gf256::from_byte(0x53));
+        }
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let mut tabs = Tables::EMPTY;
+        tabs.fill();
+        let mut bytes = *tabs.as_bytes();
+        assert!(FieldCtx::from_bytes(&bytes).is_ok());
+
+        // Corrupt a log entry
+        bytes[256 + 3] ^= 1;
+        assert_eq!(FieldCtx::from_bytes(&bytes).err(), Some(TableError::Log(3)));
+    }
+
+    #[test]
+    fn test_with_generator() {
+        let three = Gf256::from_byte(3);
+        let (mut tabs, mut other) = (Tables::EMPTY, Tables::EMPTY);
+        tabs.fill_with(Gf256::from_byte(4));
+        other.fill();
+        assert_eq!(FieldCtx::new(&tabs).err(), Some(TableError::Exp(1)));
+        let ctx = FieldCtx::with_generator(&tabs, Gf256::from_byte(4)).unwrap();
+        assert_eq!(ctx.generator(), Gf256::from_byte(4));
+        assert_eq!(ctx.xexp(1), Gf256::from_byte(4));
+        assert_eq!(ctx.log(Gf256::from_byte(16)), Some(2));
+        assert_eq!(ctx.log(three), three.log_wrt(Gf256::from_byte(4)));
+        assert_eq!(ctx.mul(three, Gf256::from_byte(0x8f)), three * Gf256::from_byte(0x8f));
+        assert_eq!(ctx.div(three, Gf256::from_byte(0x8f)), three / Gf256::from_byte(0x8f));
+        assert_eq!(tabs.inv, other.inv);
+    }
+}
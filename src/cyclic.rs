@@ -0,0 +1,672 @@
+//! The quotient ring Gf256[x] / (x^n - 1).
+//!
+//! Elements are polynomials of degree below n. Multiplication by x is a
+//! cyclic rotation of the coefficients, and the product of two elements
+//! is their cyclic convolution. This is the algebraic setting of cyclic
+//! codes: a codeword rotated by any amount is again a codeword.
+//!
+//! CyclicCode describes a cyclic code by its generator polynomial and
+//! provides encoding and syndrome checks for any such code. Decoding is
+//! specific to the family of codes and is plugged in through the
+//! CyclicDecoder trait; RsDecoder handles Reed-Solomon codes and
+//! BchDecoder binary BCH codes.
+//!
+//! Codewords of a CyclicCode are coefficient vectors, lowest degree
+//! first. In systematic form the n - k parity symbols come first and the
+//! message occupies the k highest degree coefficients.
+
+use core::fmt;
+use core::error;
+use core::ops::{ Add, Sub, Mul, Shl, Shr };
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::bch;
+use crate::rs;
+
+/// An element of Gf256[x] / (x^n - 1)
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct CyclicPoly {
+	/// Coefficients, lowest degree first. Always exactly n of them.
+	coeffs: Vec<Gf256>
+}
+
+impl CyclicPoly {
+	/// Returns the zero element of the ring with modulus x^n - 1
+	pub fn zero(n: usize) -> CyclicPoly {
+		assert!(n > 0, "Ring modulus must have positive degree");
+		CyclicPoly { coeffs: vec![Gf256::zero(); n] }
+	}
+
+	/// Returns the multiplicative neutral element
+	pub fn one(n: usize) -> CyclicPoly {
+		let mut p = CyclicPoly::zero(n);
+		p.coeffs[0] = Gf256::one();
+		p
+	}
+
+	/// Returns c * x^k
+	pub fn monomial(n: usize, c: Gf256, k: usize) -> CyclicPoly {
+		let mut p = CyclicPoly::zero(n);
+		p.coeffs[k % n] = c;
+		p
+	}
+
+	/// Reduces a polynomial (lowest degree first, any length) modulo
+	/// x^n - 1. Since x^n = 1, the coefficient of x^i lands on x^(i mod n).
+	pub fn from_coeffs(n: usize, coeffs: &[Gf256]) -> CyclicPoly {
+		let mut p = CyclicPoly::zero(n);
+		for (i, &c) in coeffs.iter().enumerate() {
+			p.coeffs[i % n] += c;
+		}
+		p
+	}
+
+	/// The degree n of the modulus x^n - 1
+	pub fn modulus_degree(&self) -> usize {
+		self.coeffs.len()
+	}
+
+	/// Coefficients, lowest degree first
+	pub fn coeffs(&self) -> &[Gf256] {
+		&self.coeffs
+	}
+
+	pub fn coeff(&self, i: usize) -> Gf256 {
+		self.coeffs[i % self.coeffs.len()]
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.coeffs.iter().all(|c| c.poly == 0)
+	}
+
+	/// Evaluates the representative of degree below n at x
+	pub fn eval(&self, x: Gf256) -> Gf256 {
+		self.coeffs.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c)
+	}
+
+	/// Returns x^k * self, i.e. the coefficients rotated up by k
+	pub fn rotate(&self, k: usize) -> CyclicPoly {
+		let mut coeffs = self.coeffs.clone();
+		let n = coeffs.len();
+		coeffs.rotate_right(k % n);
+		CyclicPoly { coeffs }
+	}
+
+	/// Returns x^-k * self, i.e. the coefficients rotated down by k
+	pub fn rotate_back(&self, k: usize) -> CyclicPoly {
+		let mut coeffs = self.coeffs.clone();
+		let n = coeffs.len();
+		coeffs.rotate_left(k % n);
+		CyclicPoly { coeffs }
+	}
+
+	/// Multiplies every coefficient by c
+	pub fn scale(&self, c: Gf256) -> CyclicPoly {
+		CyclicPoly { coeffs: self.coeffs.iter().map(|&a| a * c).collect() }
+	}
+
+	fn check_same_ring(&self, other: &CyclicPoly) {
+		assert_eq!(self.coeffs.len(), other.coeffs.len(), "Elements of different rings");
+	}
+}
+
+impl<'a> Add<&'a CyclicPoly> for &'a CyclicPoly {
+	type Output = CyclicPoly;
+	fn add(self, rhs: &'a CyclicPoly) -> CyclicPoly {
+		self.check_same_ring(rhs);
+		CyclicPoly {
+			coeffs: self.coeffs.iter().zip(&rhs.coeffs).map(|(&a, &b)| a + b).collect()
+		}
+	}
+}
+
+impl<'a> Sub<&'a CyclicPoly> for &'a CyclicPoly {
+	type Output = CyclicPoly;
+	fn sub(self, rhs: &'a CyclicPoly) -> CyclicPoly {
+		self.check_same_ring(rhs);
+		CyclicPoly {
+			coeffs: self.coeffs.iter().zip(&rhs.coeffs).map(|(&a, &b)| a - b).collect()
+		}
+	}
+}
+
+impl<'a> Mul<&'a CyclicPoly> for &'a CyclicPoly {
+	type Output = CyclicPoly;
+	/// Cyclic convolution of the coefficients
+	fn mul(self, rhs: &'a CyclicPoly) -> CyclicPoly {
+		self.check_same_ring(rhs);
+		let n = self.coeffs.len();
+		let mut res = CyclicPoly::zero(n);
+		for (i, &a) in self.coeffs.iter().enumerate() {
+			if a.poly == 0 {
+				continue;
+			}
+			for (j, &b) in rhs.coeffs.iter().enumerate() {
+				let k = (i + j) % n;
+				res.coeffs[k] += a * b;
+			}
+		}
+		res
+	}
+}
+
+impl Mul<Gf256> for &CyclicPoly {
+	type Output = CyclicPoly;
+	fn mul(self, rhs: Gf256) -> CyclicPoly {
+		self.scale(rhs)
+	}
+}
+
+impl Shl<usize> for &CyclicPoly {
+	type Output = CyclicPoly;
+	/// Rotation by multiplication with x^k
+	fn shl(self, k: usize) -> CyclicPoly {
+		self.rotate(k)
+	}
+}
+
+impl Shr<usize> for &CyclicPoly {
+	type Output = CyclicPoly;
+	/// Rotation by multiplication with x^-k
+	fn shr(self, k: usize) -> CyclicPoly {
+		self.rotate_back(k)
+	}
+}
+
+
+/// Errors produced by CyclicCode
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum CodeError {
+	/// The generator polynomial is not monic, or its degree is not below n
+	InvalidGenerator,
+	/// The generator polynomial does not divide x^n - 1
+	NotCyclic,
+	/// A message or word has the wrong length
+	LengthMismatch,
+	/// The decoder could not correct the word
+	Uncorrectable,
+}
+
+impl fmt::Display for CodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			CodeError::InvalidGenerator => "invalid generator polynomial",
+			CodeError::NotCyclic => "generator does not divide x^n - 1",
+			CodeError::LengthMismatch => "length mismatch",
+			CodeError::Uncorrectable => "word can not be corrected",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for CodeError {}
+
+/// Remainder of the division of dividend by a monic divisor, both lowest
+/// degree first. The result has exactly divisor.len() - 1 coefficients.
+fn rem_monic(dividend: &[Gf256], divisor: &[Gf256]) -> Vec<Gf256> {
+	let d = divisor.len() - 1;
+	let mut r = dividend.to_vec();
+	if r.len() < d {
+		r.resize(d, Gf256::zero());
+		return r;
+	}
+	for i in (d..r.len()).rev() {
+		let q = r[i];
+		if q.poly == 0 {
+			continue;
+		}
+		for j in 0..=d {
+			r[i - d + j] -= q * divisor[j];
+		}
+	}
+	r.truncate(d);
+	r
+}
+
+/// A cyclic code of length n, given by its generator polynomial
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct CyclicCode {
+	n: usize,
+	/// Monic generator, lowest degree first
+	generator: Vec<Gf256>
+}
+
+impl CyclicCode {
+	/// Creates the cyclic code of length n generated by generator (lowest
+	/// degree first). The generator has to be monic and divide x^n - 1.
+	pub fn new(n: usize, generator: Vec<Gf256>) -> Result<CyclicCode, CodeError> {
+		let deg = generator.len().checked_sub(1).ok_or(CodeError::InvalidGenerator)?;
+		if deg >= n || generator[deg] != Gf256::one() {
+			return Err(CodeError::InvalidGenerator);
+		}
+		// x^n - 1 = x^n + 1 in characteristic 2
+		let mut xn = vec![Gf256::zero(); n + 1];
+		xn[0] = Gf256::one();
+		xn[n] = Gf256::one();
+		if rem_monic(&xn, &generator).iter().any(|c| c.poly != 0) {
+			return Err(CodeError::NotCyclic);
+		}
+		Ok(CyclicCode { n, generator })
+	}
+
+	/// The Reed-Solomon code of length 255 with k message symbols, using
+	/// the same generator as rs::RsCodec. Decode it with RsDecoder.
+	pub fn reed_solomon(k: usize) -> Result<CyclicCode, CodeError> {
+		if k == 0 || k >= 255 {
+			return Err(CodeError::InvalidGenerator);
+		}
+		let nsym = 255 - k;
+		let mut gen = vec![Gf256::zero(); nsym + 1];
+		rs::generator_into(nsym, 0, &mut gen);
+		gen.reverse();
+		CyclicCode::new(255, gen)
+	}
+
+	/// The narrow-sense binary BCH code of length 255 correcting t bit
+	/// errors, see bch::generator. Its codewords have coefficients 0 and
+	/// 1; decode it with BchDecoder.
+	pub fn bch(t: usize) -> Result<CyclicCode, CodeError> {
+		if t == 0 {
+			return Err(CodeError::InvalidGenerator);
+		}
+		CyclicCode::new(255, bch::generator(t))
+	}
+
+	/// Code length
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	/// Number of message symbols
+	pub fn k(&self) -> usize {
+		self.n + 1 - self.generator.len()
+	}
+
+	/// The generator polynomial, lowest degree first
+	pub fn generator(&self) -> &[Gf256] {
+		&self.generator
+	}
+
+	/// Generator matrix of the non-systematic encoding: row i holds the
+	/// coefficients of x^i g(x), lowest degree first.
+	pub fn generator_matrix(&self) -> Vec<Vec<Gf256>> {
+		(0..self.k()).map(|i| {
+			let mut row = vec![Gf256::zero(); self.n];
+			row[i..i + self.generator.len()].copy_from_slice(&self.generator);
+			row
+		}).collect()
+	}
+
+	/// Systematic encoding: c(x) = x^(n-k) m(x) + (x^(n-k) m(x) mod g(x))
+	pub fn encode_systematic(&self, msg: &[Gf256]) -> Result<Vec<Gf256>, CodeError> {
+		if msg.len() != self.k() {
+			return Err(CodeError::LengthMismatch);
+		}
+		let nsym = self.n - self.k();
+		let mut word = vec![Gf256::zero(); nsym];
+		word.extend_from_slice(msg);
+		let parity = rem_monic(&word, &self.generator);
+		word[..nsym].copy_from_slice(&parity);
+		Ok(word)
+	}
+
+	/// Non-systematic encoding: c(x) = m(x) g(x)
+	pub fn encode_nonsystematic(&self, msg: &[Gf256]) -> Result<Vec<Gf256>, CodeError> {
+		if msg.len() != self.k() {
+			return Err(CodeError::LengthMismatch);
+		}
+		let mut word = vec![Gf256::zero(); self.n];
+		for (i, &m) in msg.iter().enumerate() {
+			for (j, &g) in self.generator.iter().enumerate() {
+				word[i + j] += m * g;
+			}
+		}
+		Ok(word)
+	}
+
+	/// Returns the message of a systematically encoded codeword
+	pub fn message<'a>(&self, codeword: &'a [Gf256]) -> &'a [Gf256] {
+		&codeword[self.n - self.k()..]
+	}
+
+	/// Returns the syndrome polynomial w(x) mod g(x), which is zero
+	/// exactly for codewords.
+	pub fn syndrome(&self, word: &[Gf256]) -> Result<Vec<Gf256>, CodeError> {
+		if word.len() != self.n {
+			return Err(CodeError::LengthMismatch);
+		}
+		Ok(rem_monic(word, &self.generator))
+	}
+
+	pub fn is_codeword(&self, word: &[Gf256]) -> bool {
+		self.syndrome(word).map(|s| s.iter().all(|c| c.poly == 0)).unwrap_or(false)
+	}
+
+	/// Corrects word in place with the given decoder and returns the
+	/// number of corrected symbols.
+	pub fn decode<D: CyclicDecoder>(&self, decoder: &D, word: &mut [Gf256])
+		-> Result<usize, CodeError> {
+		if word.len() != self.n {
+			return Err(CodeError::LengthMismatch);
+		}
+		decoder.decode(self, word)
+	}
+}
+
+/// Streaming systematic encoder emulating the polynomial division shift
+/// register found in hardware encoders.
+///
+/// Message symbols are fed highest degree first, one per clock. After all
+/// k symbols have been pushed, the register holds the parity, i.e.
+/// x^(n-k) m(x) mod g(x). The register contents can be inspected after
+/// every step to compare against a hardware implementation cycle by cycle.
+#[derive(Clone,Debug)]
+pub struct LfsrEncoder<'a> {
+	code: &'a CyclicCode,
+	reg: Vec<Gf256>,
+	count: usize,
+}
+
+impl<'a> LfsrEncoder<'a> {
+	pub fn new(code: &'a CyclicCode) -> LfsrEncoder<'a> {
+		let nsym = code.n() - code.k();
+		LfsrEncoder { code, reg: vec![Gf256::zero(); nsym], count: 0 }
+	}
+
+	/// Clocks one message symbol into the register and returns it, since
+	/// a systematic encoder passes message symbols through unchanged.
+	pub fn push(&mut self, sym: Gf256) -> Gf256 {
+		let g = &self.code.generator;
+		let nsym = self.reg.len();
+		if nsym > 0 {
+			let feedback = sym + self.reg[nsym - 1];
+			for j in (1..nsym).rev() {
+				self.reg[j] = self.reg[j - 1] + feedback * g[j];
+			}
+			self.reg[0] = feedback * g[0];
+		}
+		self.count += 1;
+		sym
+	}
+
+	/// Clocks in several symbols, highest degree first
+	pub fn push_slice(&mut self, syms: &[Gf256]) {
+		for &s in syms {
+			self.push(s);
+		}
+	}
+
+	/// Number of symbols pushed since the last reset
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	/// Current register contents, lowest degree cell first. Once k symbols
+	/// have been pushed this is the parity, in the same order as the first
+	/// n - k coefficients of CyclicCode::encode_systematic.
+	pub fn register(&self) -> &[Gf256] {
+		&self.reg
+	}
+
+	/// Returns the parity in the order a hardware encoder shifts it out
+	/// (highest degree first) and clears the register.
+	pub fn finish(&mut self) -> Vec<Gf256> {
+		let parity = self.reg.iter().rev().cloned().collect();
+		self.reset();
+		parity
+	}
+
+	pub fn reset(&mut self) {
+		for r in self.reg.iter_mut() {
+			*r = Gf256::zero();
+		}
+		self.count = 0;
+	}
+}
+
+/// A decoding algorithm for a family of cyclic codes
+pub trait CyclicDecoder {
+	/// Corrects word (of length code.n()) in place and returns the number
+	/// of corrected symbols.
+	fn decode(&self, code: &CyclicCode, word: &mut [Gf256]) -> Result<usize, CodeError>;
+}
+
+/// Berlekamp-Massey decoder for codes built by CyclicCode::reed_solomon
+#[derive(Copy,Clone,Debug,Default)]
+pub struct RsDecoder;
+
+impl CyclicDecoder for RsDecoder {
+	fn decode(&self, code: &CyclicCode, word: &mut [Gf256]) -> Result<usize, CodeError> {
+		let n = code.n();
+		let nsym = n - code.k();
+		// The rs module stores the highest degree coefficient first
+		let mut bytes: Vec<u8> = word.iter().rev().map(|c| c.to_byte()).collect();
+		let mut syn = vec![Gf256::zero(); nsym + 1];
+		let mut lambda = vec![Gf256::zero(); nsym + 1];
+		let mut prev = vec![Gf256::zero(); nsym + 1];
+		let mut tmp = vec![Gf256::zero(); nsym + 1];
+		let mut omega = vec![Gf256::zero(); nsym + 1];
+		let mut positions = vec![0; nsym + 1];
+		let bufs = rs::Buffers {
+			syn: &mut syn,
+			lambda: &mut lambda,
+			prev: &mut prev,
+			tmp: &mut tmp,
+			omega: &mut omega,
+			positions: &mut positions,
+		};
+		let count = rs::decode_with(&mut bytes, nsym, 0, bufs)
+			.map_err(|_| CodeError::Uncorrectable)?;
+		for (w, &b) in word.iter_mut().zip(bytes.iter().rev()) {
+			*w = Gf256::from_byte(b);
+		}
+		Ok(count)
+	}
+}
+
+/// Decoder for binary BCH codes built by CyclicCode::bch.
+///
+/// Such a code is a subcode of the Reed-Solomon code with the roots x^1
+/// .. x^2t, so the Reed-Solomon decoder corrects up to t errors; a
+/// correction that is not binary means more than t bit errors.
+#[derive(Copy,Clone,Debug,Default)]
+pub struct BchDecoder;
+
+impl BchDecoder {
+	/// Half the number of consecutive roots x^1, x^2, ... of the generator
+	pub fn designed_t(code: &CyclicCode) -> usize {
+		let roots = Gf256::powers_of(Gf256::GENERATOR).skip(1).take(254)
+			.take_while(|&x| code.generator.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c).poly == 0)
+			.count();
+		roots / 2
+	}
+}
+
+impl CyclicDecoder for BchDecoder {
+	fn decode(&self, code: &CyclicCode, word: &mut [Gf256]) -> Result<usize, CodeError> {
+		if word.iter().any(|c| c.poly > 1) {
+			return Err(CodeError::Uncorrectable);
+		}
+		let nsym = 2 * BchDecoder::designed_t(code);
+		let mut bytes: Vec<u8> = word.iter().rev().map(|c| c.to_byte()).collect();
+		let mut syn = vec![Gf256::zero(); nsym + 1];
+		let mut lambda = vec![Gf256::zero(); nsym + 1];
+		let mut prev = vec![Gf256::zero(); nsym + 1];
+		let mut tmp = vec![Gf256::zero(); nsym + 1];
+		let mut omega = vec![Gf256::zero(); nsym + 1];
+		let mut positions = vec![0; nsym + 1];
+		let bufs = rs::Buffers {
+			syn: &mut syn,
+			lambda: &mut lambda,
+			prev: &mut prev,
+			tmp: &mut tmp,
+			omega: &mut omega,
+			positions: &mut positions,
+		};
+		let count = rs::decode_with(&mut bytes, nsym, 1, bufs)
+			.map_err(|_| CodeError::Uncorrectable)?;
+		if bytes.iter().any(|&b| b > 1) {
+			return Err(CodeError::Uncorrectable);
+		}
+		for (w, &b) in word.iter_mut().zip(bytes.iter().rev()) {
+			*w = Gf256::from_byte(b);
+		}
+		Ok(count)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(n: usize, seed: u8) -> CyclicPoly {
+        let coeffs: Vec<Gf256> = (0..n)
+            .map(|i| Gf256::from_byte(seed.wrapping_mul(31).wrapping_add(i as u8 * 17)))
+            .collect();
+        CyclicPoly::from_coeffs(n, &coeffs)
+    }
+
+    #[test]
+    fn test_x_to_the_n_is_one() {
+        let x = CyclicPoly::monomial(7, Gf256::one(), 1);
+        let mut p = CyclicPoly::one(7);
+        for _ in 0..7 {
+            p = &p * &x;
+        }
+        assert_eq!(p, CyclicPoly::one(7));
+    }
+
+    #[test]
+    fn test_rotation_is_multiplication_by_x() {
+        let p = sample(9, 3);
+        let x3 = CyclicPoly::monomial(9, Gf256::one(), 3);
+        assert_eq!(&p << 3, &p * &x3);
+        assert_eq!(&(&p << 3) >> 3, p);
+        assert_eq!(&p << 9, p);
+    }
+
+    #[test]
+    fn test_mul_matches_reduced_product() {
+        let n = 5;
+        let a = sample(n, 1);
+        let b = sample(n, 2);
+
+        // Plain polynomial product, then reduction
+        let mut full = vec![Gf256::zero(); 2 * n - 1];
+        for i in 0..n {
+            for j in 0..n {
+                full[i + j] += a.coeff(i) * b.coeff(j);
+            }
+        }
+        assert_eq!(&a * &b, CyclicPoly::from_coeffs(n, &full));
+        assert_eq!(&a * &b, &b * &a);
+    }
+
+    fn bytes(v: &[u8]) -> Vec<Gf256> {
+        v.iter().map(|&b| Gf256::from_byte(b)).collect()
+    }
+
+    #[test]
+    fn test_generator_must_divide() {
+        // x + 1 divides x^n - 1 for every n
+        assert!(CyclicCode::new(7, bytes(&[1, 1])).is_ok());
+        // x + 2 is a factor only if 2^n = 1
+        assert_eq!(CyclicCode::new(7, bytes(&[2, 1])).err(), Some(CodeError::NotCyclic));
+        assert_eq!(CyclicCode::new(7, bytes(&[1, 2])).err(), Some(CodeError::InvalidGenerator));
+    }
+
+    #[test]
+    fn test_encodings_are_codewords() {
+        let code = CyclicCode::new(255, bytes(&[2, 1])).unwrap();
+        let msg: Vec<Gf256> = (0..254).map(|i| Gf256::from_byte(i as u8)).collect();
+
+        let sys = code.encode_systematic(&msg).unwrap();
+        assert!(code.is_codeword(&sys));
+        assert_eq!(code.message(&sys), &msg[..]);
+
+        let nonsys = code.encode_nonsystematic(&msg).unwrap();
+        assert!(code.is_codeword(&nonsys));
+
+        // Rotations of codewords are codewords
+        let rotated = CyclicPoly::from_coeffs(255, &sys).rotate(17);
+        assert!(code.is_codeword(rotated.coeffs()));
+    }
+
+    #[test]
+    fn test_reed_solomon_instance() {
+        let code = CyclicCode::reed_solomon(239).unwrap();
+        let msg: Vec<Gf256> = (0..239).map(|i| Gf256::from_byte((i * 3) as u8)).collect();
+        let original = code.encode_systematic(&msg).unwrap();
+
+        // Same code as RsCodec, which stores the highest degree first
+        let mut cw = [0u8; 255];
+        for (c, m) in cw.iter_mut().zip(msg.iter().rev()) {
+            *c = m.to_byte();
+        }
+        rs::RsCodec::<255, 239>::new().encode(&mut cw);
+        assert!(cw.iter().eq(original.iter().rev().map(|c| &c.poly)));
+
+        let mut word = original.clone();
+        for &p in &[0, 50, 100, 200, 254] {
+            word[p] += Gf256::from_byte(0x33);
+        }
+        assert!(!code.is_codeword(&word));
+        assert_eq!(code.decode(&RsDecoder, &mut word), Ok(5));
+        assert_eq!(word, original);
+    }
+
+    #[test]
+    fn test_bch_instance() {
+        // BCH(255, 223) corrects 4 bit errors
+        let code = CyclicCode::bch(4).unwrap();
+        assert_eq!(code.k(), 223);
+        assert_eq!(BchDecoder::designed_t(&code), 4);
+        let msg: Vec<Gf256> = (0..223).map(|i| Gf256::from_byte((i * 7 % 3 == 0) as u8)).collect();
+        let original = code.encode_systematic(&msg).unwrap();
+        assert!(original.iter().all(|c| c.to_byte() <= 1));
+
+        let mut word = original.clone();
+        for &p in &[3, 80, 81, 254] {
+            word[p] += Gf256::one();
+        }
+        assert_eq!(code.decode(&BchDecoder, &mut word), Ok(4));
+        assert_eq!(word, original);
+
+        for &p in &[3, 80, 81, 200, 254] {
+            word[p] += Gf256::one();
+        }
+        assert_ne!(code.decode(&BchDecoder, &mut word.clone()), Ok(5));
+        word[0] = Gf256::from_byte(2);
+        assert_eq!(code.decode(&BchDecoder, &mut word), Err(CodeError::Uncorrectable));
+        assert_eq!(CyclicCode::bch(0).err(), Some(CodeError::InvalidGenerator));
+    }
+
+    #[test]
+    fn test_lfsr_matches_division() {
+        let code = CyclicCode::reed_solomon(223).unwrap();
+        let msg: Vec<Gf256> = (0..223).map(|i| Gf256::from_byte((i * 11 + 5) as u8)).collect();
+        let word = code.encode_systematic(&msg).unwrap();
+
+        let mut enc = LfsrEncoder::new(&code);
+        for &m in msg.iter().rev() {
+            assert_eq!(enc.push(m), m);
+        }
+        assert_eq!(enc.count(), 223);
+        assert_eq!(enc.register(), &word[..32]);
+
+        let parity = enc.finish();
+        assert!(parity.iter().eq(word[..32].iter().rev()));
+        assert_eq!(enc.count(), 0);
+        assert!(enc.register().iter().all(|r| r.poly == 0));
+    }
+
+    #[test]
+    fn test_distributive() {
+        let a = sample(6, 4);
+        let b = sample(6, 5);
+        let c = sample(6, 6);
+        assert_eq!(&a * &(&b + &c), &(&a * &b) + &(&a * &c));
+    }
+}
@@ -0,0 +1,193 @@
+//! The length 255 discrete Fourier transform over Gf256.
+//!
+//! The DFT with respect to a generator g maps the coefficients a_0 ..
+//! a_254 of a polynomial to its values A(g^0), ..., A(g^254) at all
+//! nonzero elements. Since 255 = 1 in characteristic 2, the inverse is
+//! the same transform with respect to g^-1, without any scaling.
+//!
+//! 255 = 3 * 5 * 17 with coprime factors, so the Good-Thomas prime factor
+//! algorithm applies: with the input permuted by n = 85 n1 + 51 n2 + 15 n3
+//! and the output read back in CRT order, the transform splits into
+//! independent DFTs of lengths 3, 5 and 17 along the axes of a 3 x 5 x 17
+//! array, without twiddle factors in between. That is 255 * (3 + 5 + 17)
+//! multiplications instead of 255^2.
+//!
+//! For the additive FFT over subspaces, as used for erasure coding, see
+//! rs::fft.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::poly::Poly;
+
+/// The transform length, the order of the multiplicative group
+pub const N: usize = 255;
+
+/// Errors produced by the transforms
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum DftError {
+	/// The inverse transform needs exactly N values
+	LengthMismatch(usize),
+	/// The root does not have order 255
+	NotAGenerator(Gf256),
+}
+
+impl fmt::Display for DftError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DftError::LengthMismatch(n) => write!(f, "expected {} values, got {}", N, n),
+			DftError::NotAGenerator(g) => write!(f, "{:#x} does not have order 255", g.to_byte()),
+		}
+	}
+}
+
+impl error::Error for DftError {}
+
+/// The factor lengths and the strides of their axes in the work array
+const AXES: [(usize, usize); 3] = [(3, 85), (5, 17), (17, 1)];
+
+/// Runs the length len DFT with root w along every line of the axis
+fn axis_dft(work: &mut [Gf256], len: usize, stride: usize, w: Gf256) {
+	let powers: Vec<Gf256> = Gf256::powers_of(w).take(len).collect();
+	let mut line = [Gf256::zero(); 17];
+	for start in (0..N).filter(|&i| (i / stride).is_multiple_of(len)) {
+		for (j, l) in line[..len].iter_mut().enumerate() {
+			*l = work[start + j * stride];
+		}
+		for k in 0..len {
+			let mut acc = Gf256::zero();
+			for (j, &l) in line[..len].iter().enumerate() {
+				acc += l * powers[j * k % len];
+			}
+			work[start + k * stride] = acc;
+		}
+	}
+}
+
+/// The forward transform with root g, for any g of order 255
+fn transform(input: &[Gf256], g: Gf256) -> Vec<Gf256> {
+	// work[85 n1 + 17 n2 + n3] = input[(85 n1 + 51 n2 + 15 n3) mod 255]
+	let mut work = vec![Gf256::zero(); N];
+	for n1 in 0..3 {
+		for n2 in 0..5 {
+			for n3 in 0..17 {
+				work[85 * n1 + 17 * n2 + n3] = input[(85 * n1 + 51 * n2 + 15 * n3) % N];
+			}
+		}
+	}
+	// The root of the length n_i DFT is g^(255 / n_i)
+	for (len, stride) in AXES {
+		axis_dft(&mut work, len, stride, g.pow((N / len) as u64));
+	}
+	// Output index k = 85 k1 + 51 k2 + 120 k3, the CRT of (k1, k2, k3):
+	// 85 = 1 mod 3, 51 = 1 mod 5 and 120 = 15 * 8 = 1 mod 17
+	let mut out = vec![Gf256::zero(); N];
+	for k1 in 0..3 {
+		for k2 in 0..5 {
+			for k3 in 0..17 {
+				out[(85 * k1 + 51 * k2 + 120 * k3) % N] = work[85 * k1 + 17 * k2 + k3];
+			}
+		}
+	}
+	out
+}
+
+/// Folds any number of coefficients to N, using g^255 = 1
+fn fold(coeffs: &[Gf256]) -> Vec<Gf256> {
+	let mut out = vec![Gf256::zero(); N];
+	for (i, &c) in coeffs.iter().enumerate() {
+		out[i % N] += c;
+	}
+	out
+}
+
+/// The values of the polynomial with these coefficients (lowest degree
+/// first, any number) at x^0, x^1, ..., x^254
+pub fn dft(coeffs: &[Gf256]) -> Vec<Gf256> {
+	transform(&fold(coeffs), Gf256::GENERATOR)
+}
+
+/// The 255 coefficients of the polynomial of degree below 255 taking
+/// values[j] at x^j
+pub fn idft(values: &[Gf256]) -> Result<Vec<Gf256>, DftError> {
+	idft_with(values, Gf256::GENERATOR)
+}
+
+/// dft with respect to another generator g: the values at g^0, ..., g^254
+pub fn dft_with(coeffs: &[Gf256], g: Gf256) -> Result<Vec<Gf256>, DftError> {
+	if !g.is_generator() {
+		return Err(DftError::NotAGenerator(g));
+	}
+	Ok(transform(&fold(coeffs), g))
+}
+
+/// The inverse of dft_with
+pub fn idft_with(values: &[Gf256], g: Gf256) -> Result<Vec<Gf256>, DftError> {
+	if values.len() != N {
+		return Err(DftError::LengthMismatch(values.len()));
+	}
+	let inv = g.inv().filter(Gf256::is_generator).ok_or(DftError::NotAGenerator(g))?;
+	Ok(transform(values, inv))
+}
+
+/// The values of p at all 256 field elements in byte order, like
+/// eval_many over every element but with a tenth of the multiplications
+pub fn eval_all(p: &Poly) -> Vec<Gf256> {
+	let values = dft(p.coeffs());
+	let mut out = vec![p.coeff(0); 256];
+	for (j, v) in values.into_iter().enumerate() {
+		out[Gf256::xexp(j as u8).to_byte() as usize] = v;
+	}
+	out
+}
+
+/// The polynomial of degree below 255 with the given values at x^0 ..
+/// x^254
+pub fn interpolate_nonzero(values: &[Gf256]) -> Result<Poly, DftError> {
+	Ok(Poly::from_vec(idft(values)?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(n: usize) -> Vec<Gf256> {
+        (0..n).map(|i| Gf256::from_byte((i * 29 + 3) as u8)).collect()
+    }
+
+    #[test]
+    fn test_matches_evaluation() {
+        let coeffs = sample(N);
+        let p = Poly::from_coeffs(&coeffs);
+        let values = dft(&coeffs);
+        for (j, &v) in values.iter().enumerate() {
+            assert_eq!(v, p.eval(Gf256::xexp(j as u8)), "point {}", j);
+        }
+        let all = eval_all(&p);
+        assert!(Gf256::all().all(|x| all[x.to_byte() as usize] == p.eval(x)));
+        // Higher degrees fold onto the nonzero points
+        let long = Poly::from_coeffs(&sample(600));
+        assert_eq!(eval_all(&long), Gf256::all().map(|x| long.eval(x)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let coeffs = sample(N);
+        assert_eq!(idft(&dft(&coeffs)).unwrap(), coeffs);
+        let four = Gf256::from_byte(4);
+        assert_eq!(idft_with(&dft_with(&coeffs, four).unwrap(), four).unwrap(), coeffs);
+        let short = Poly::from_coeffs(&sample(10));
+        assert_eq!(interpolate_nonzero(&dft(short.coeffs())).unwrap(), short);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(idft(&sample(10)), Err(DftError::LengthMismatch(10)));
+        let three = Gf256::from_byte(3);
+        assert_eq!(dft_with(&sample(4), three), Err(DftError::NotAGenerator(three)));
+        assert_eq!(idft_with(&sample(N), Gf256::zero()), Err(DftError::NotAGenerator(Gf256::zero())));
+    }
+}
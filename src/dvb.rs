@@ -0,0 +1,283 @@
+//! The DVB outer coding chain (EN 300 421, section 4.4).
+//!
+//! Transport stream packets of 188 bytes go through energy dispersal
+//! (a PRBS with polynomial 1 + x^14 + x^15, restarted every 8 packets),
+//! the shortened RS(204, 188) code and the convolutional interleaver with
+//! I = 12 and M = 17. DvbEncoder and DvbDecoder run the whole chain on one
+//! packet at a time.
+//!
+//! The interleaver delays the stream by exactly 11 coded packets, so the
+//! decoder yields nothing for the first 11 packets it is fed.
+
+use core::fmt;
+use core::error;
+
+use crate::interleave::{ Interleaver, Deinterleaver };
+use crate::rs::{ RsCodec, RsError, Scratch };
+
+/// Length of a transport stream packet
+pub const TS_PACKET_LEN: usize = 188;
+/// Length of a coded packet
+pub const CODED_PACKET_LEN: usize = 204;
+/// Transport stream sync byte
+pub const SYNC: u8 = 0x47;
+/// Sync byte of the first packet of every group of 8, marking the PRBS
+/// restart
+pub const SYNC_INVERTED: u8 = 0xB8;
+
+/// Interleaver depth (I)
+const DEPTH: usize = 12;
+/// Interleaver delay step (M)
+const STEP: usize = CODED_PACKET_LEN / DEPTH;
+/// Packets between two PRBS restarts
+const GROUP: usize = 8;
+/// Number of packets the decoder lags behind
+pub const LATENCY_PACKETS: usize = DEPTH - 1;
+
+/// Errors produced by the DVB chain
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum DvbError {
+	/// The packet does not start with the sync byte
+	MissingSync,
+	/// The coded packet had too many errors
+	Uncorrectable(RsError),
+}
+
+impl fmt::Display for DvbError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DvbError::MissingSync => f.write_str("packet does not start with the sync byte"),
+			DvbError::Uncorrectable(e) => write!(f, "uncorrectable packet: {}", e),
+		}
+	}
+}
+
+impl error::Error for DvbError {}
+
+/// The energy dispersal randomizer. The same object derandomizes.
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Randomizer {
+	/// Bits 1 to 15 of the shift register, bit 1 in the lowest position
+	reg: u16,
+	/// Index of the next packet in its group
+	packet: usize,
+}
+
+impl Randomizer {
+	/// The initialization sequence 100101010000000 of bits 1 to 15
+	const INIT: u16 = 0b000_0000_1010_1001;
+
+	pub fn new() -> Randomizer {
+		Randomizer { reg: Randomizer::INIT, packet: 0 }
+	}
+
+	/// Returns the next 8 PRBS bits, first bit in the MSB
+	fn next_byte(&mut self) -> u8 {
+		let mut byte = 0;
+		for _ in 0..8 {
+			let bit = ((self.reg >> 13) ^ (self.reg >> 14)) & 1;
+			self.reg = ((self.reg << 1) | bit) & 0x7fff;
+			byte = (byte << 1) | bit as u8;
+		}
+		byte
+	}
+
+	fn apply(&mut self, packet: &mut [u8; TS_PACKET_LEN]) {
+		if self.packet == 0 {
+			self.reg = Randomizer::INIT;
+		} else {
+			// The PRBS keeps running during the sync bytes, which are
+			// left as they are
+			self.next_byte();
+		}
+		for b in packet[1..].iter_mut() {
+			*b ^= self.next_byte();
+		}
+		self.packet = (self.packet + 1) % GROUP;
+	}
+
+	/// Randomizes a packet for transmission. The sync byte of every 8th
+	/// packet is inverted.
+	pub fn randomize(&mut self, packet: &mut [u8; TS_PACKET_LEN]) {
+		if self.packet == 0 {
+			packet[0] = !packet[0];
+		}
+		self.apply(packet);
+	}
+
+	/// Undoes randomize. An inverted sync byte restarts the group, so
+	/// the receiver locks on to the transmitter's groups.
+	pub fn derandomize(&mut self, packet: &mut [u8; TS_PACKET_LEN]) {
+		if packet[0] == SYNC_INVERTED {
+			self.packet = 0;
+			packet[0] = SYNC;
+		}
+		self.apply(packet);
+	}
+}
+
+impl Default for Randomizer {
+	fn default() -> Randomizer {
+		Randomizer::new()
+	}
+}
+
+/// Turns transport stream packets into the interleaved coded stream
+pub struct DvbEncoder {
+	randomizer: Randomizer,
+	codec: RsCodec<CODED_PACKET_LEN, TS_PACKET_LEN>,
+	interleaver: Interleaver,
+}
+
+impl DvbEncoder {
+	pub fn new() -> DvbEncoder {
+		DvbEncoder {
+			randomizer: Randomizer::new(),
+			codec: RsCodec::new(),
+			interleaver: Interleaver::new(DEPTH, STEP),
+		}
+	}
+
+	/// Encodes the next packet and returns the next 204 stream bytes
+	pub fn encode(&mut self, packet: &[u8; TS_PACKET_LEN])
+		-> Result<[u8; CODED_PACKET_LEN], DvbError> {
+		if packet[0] != SYNC {
+			return Err(DvbError::MissingSync);
+		}
+		let mut ts = *packet;
+		self.randomizer.randomize(&mut ts);
+		let mut coded = [0u8; CODED_PACKET_LEN];
+		coded[..TS_PACKET_LEN].copy_from_slice(&ts);
+		self.codec.encode(&mut coded);
+		self.interleaver.process(&mut coded);
+		Ok(coded)
+	}
+}
+
+impl Default for DvbEncoder {
+	fn default() -> DvbEncoder {
+		DvbEncoder::new()
+	}
+}
+
+/// Recovers transport stream packets from the coded stream
+pub struct DvbDecoder {
+	deinterleaver: Deinterleaver,
+	codec: RsCodec<CODED_PACKET_LEN, TS_PACKET_LEN>,
+	scratch: Scratch<CODED_PACKET_LEN, TS_PACKET_LEN>,
+	randomizer: Randomizer,
+	received: usize,
+}
+
+impl DvbDecoder {
+	pub fn new() -> DvbDecoder {
+		DvbDecoder {
+			deinterleaver: Deinterleaver::new(DEPTH, STEP),
+			codec: RsCodec::new(),
+			scratch: Scratch::new(),
+			randomizer: Randomizer::new(),
+			received: 0,
+		}
+	}
+
+	/// Feeds the next 204 stream bytes. Returns the packet fed to the
+	/// encoder LATENCY_PACKETS packets earlier, or None while the
+	/// deinterleaver is still filling up.
+	pub fn decode(&mut self, block: &[u8; CODED_PACKET_LEN])
+		-> Option<Result<[u8; TS_PACKET_LEN], DvbError>> {
+		let mut coded = *block;
+		self.deinterleaver.process(&mut coded);
+		self.received += 1;
+		if self.received <= LATENCY_PACKETS {
+			return None;
+		}
+		if let Err(e) = self.codec.decode(&mut coded, &mut self.scratch) {
+			return Some(Err(DvbError::Uncorrectable(e)));
+		}
+		let mut ts = [0u8; TS_PACKET_LEN];
+		ts.copy_from_slice(&coded[..TS_PACKET_LEN]);
+		self.randomizer.derandomize(&mut ts);
+		Some(Ok(ts))
+	}
+}
+
+impl Default for DvbDecoder {
+	fn default() -> DvbDecoder {
+		DvbDecoder::new()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(i: usize) -> [u8; TS_PACKET_LEN] {
+        let mut p = [0u8; TS_PACKET_LEN];
+        p[0] = SYNC;
+        for (j, b) in p[1..].iter_mut().enumerate() {
+            *b = (i * 13 + j) as u8;
+        }
+        p
+    }
+
+    #[test]
+    fn test_prbs_sequence() {
+        let mut r = Randomizer::new();
+        let mut p = [0u8; TS_PACKET_LEN];
+        p[0] = SYNC;
+        r.randomize(&mut p);
+        assert_eq!(&p[..4], &[SYNC_INVERTED, 0x03, 0xf6, 0x08]);
+
+        // The register restarts with every group of 8 packets
+        let first = p;
+        for _ in 1..GROUP {
+            let mut q = [0u8; TS_PACKET_LEN];
+            q[0] = SYNC;
+            r.randomize(&mut q);
+            assert_eq!(q[0], SYNC);
+            assert_ne!(q, first);
+        }
+        let mut q = [0u8; TS_PACKET_LEN];
+        q[0] = SYNC;
+        r.randomize(&mut q);
+        assert_eq!(q, first);
+    }
+
+    #[test]
+    fn test_randomizer_roundtrip() {
+        let mut tx = Randomizer::new();
+        let mut rx = Randomizer::new();
+        for i in 0..20 {
+            let mut p = packet(i);
+            tx.randomize(&mut p);
+            assert_eq!(p[0], if i % 8 == 0 { SYNC_INVERTED } else { SYNC });
+            rx.derandomize(&mut p);
+            assert_eq!(p, packet(i));
+        }
+    }
+
+    #[test]
+    fn test_pipeline_with_burst() {
+        let mut enc = DvbEncoder::new();
+        let mut dec = DvbDecoder::new();
+        let mut out = Vec::new();
+        for i in 0..40 {
+            let mut block = enc.encode(&packet(i)).unwrap();
+            if i == 20 {
+                // A burst of 96 bytes, 8 per branch
+                for b in &mut block[50..146] {
+                    *b ^= 0x5a;
+                }
+            }
+            if let Some(res) = dec.decode(&block) {
+                out.push(res.unwrap());
+            }
+        }
+        assert_eq!(out.len(), 40 - LATENCY_PACKETS);
+        for (i, p) in out.iter().enumerate() {
+            assert_eq!(*p, packet(i));
+        }
+        assert_eq!(enc.encode(&[0u8; TS_PACKET_LEN]), Err(DvbError::MissingSync));
+    }
+}
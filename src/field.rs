@@ -0,0 +1,235 @@
+//! A trait over the field types of the crate.
+//!
+//! FiniteField lets decoders and other algorithms be written once for
+//! every symbol field: Gf256, the other representations of GF(256) in
+//! modular and tower, the small fields of small and GF(2^16) of wide. The
+//! operators come from the usual traits; the rest is what the types
+//! already provide as inherent methods, under one name.
+//!
+//! Repr is the integer holding the bits of an element. from_repr returns
+//! None for integers that are no element, like 0x10 in GF(16).
+
+use core::fmt::Debug;
+use core::ops::{ Add, Sub, Mul };
+
+use crate::Gf256;
+use crate::modular::Gf256Mod;
+use crate::small::Gf2k;
+use crate::tower::Tower;
+use crate::wide::Gf65536;
+
+/// A finite field of characteristic 2
+pub trait FiniteField: Copy + Eq + Debug
+	+ Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> {
+	/// The integer representation of elements
+	type Repr: Copy + Eq + Debug;
+
+	/// Number of elements
+	const SIZE: usize;
+
+	fn zero() -> Self;
+
+	fn one() -> Self;
+
+	/// None for zero
+	fn inv(&self) -> Option<Self>;
+
+	fn from_repr(r: Self::Repr) -> Option<Self>;
+
+	fn to_repr(&self) -> Self::Repr;
+
+	fn is_zero(&self) -> bool {
+		*self == Self::zero()
+	}
+
+	fn square(&self) -> Self {
+		*self * *self
+	}
+
+	/// self ^ e, where 0^0 is 1
+	fn pow(&self, e: u64) -> Self {
+		let (mut acc, mut base, mut e) = (Self::one(), *self, e);
+		while e > 0 {
+			if e & 1 == 1 {
+				acc = acc * base;
+			}
+			base = base.square();
+			e >>= 1;
+		}
+		acc
+	}
+
+	/// self / rhs, None if rhs is zero
+	fn checked_div(&self, rhs: Self) -> Option<Self> {
+		rhs.inv().map(|r| *self * r)
+	}
+}
+
+impl FiniteField for Gf256 {
+	type Repr = u8;
+	const SIZE: usize = 256;
+
+	fn zero() -> Gf256 {
+		Gf256::ZERO
+	}
+
+	fn one() -> Gf256 {
+		Gf256::ONE
+	}
+
+	fn inv(&self) -> Option<Gf256> {
+		Gf256::inv(self)
+	}
+
+	fn from_repr(r: u8) -> Option<Gf256> {
+		Some(Gf256::from_byte(r))
+	}
+
+	fn to_repr(&self) -> u8 {
+		self.to_byte()
+	}
+
+	fn pow(&self, e: u64) -> Gf256 {
+		Gf256::pow(self, e)
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> FiniteField for Gf256Mod<POLY, GENERATOR> {
+	type Repr = u8;
+	const SIZE: usize = 256;
+
+	fn zero() -> Self {
+		Gf256Mod::zero()
+	}
+
+	fn one() -> Self {
+		Gf256Mod::one()
+	}
+
+	fn inv(&self) -> Option<Self> {
+		Gf256Mod::inv(self)
+	}
+
+	fn from_repr(r: u8) -> Option<Self> {
+		Some(Gf256Mod::from_byte(r))
+	}
+
+	fn to_repr(&self) -> u8 {
+		self.to_byte()
+	}
+}
+
+impl<const K: usize> FiniteField for Gf2k<K> {
+	type Repr = u8;
+	const SIZE: usize = Gf2k::<K>::SIZE;
+
+	fn zero() -> Self {
+		Gf2k::zero()
+	}
+
+	fn one() -> Self {
+		Gf2k::one()
+	}
+
+	fn inv(&self) -> Option<Self> {
+		Gf2k::inv(self)
+	}
+
+	fn from_repr(r: u8) -> Option<Self> {
+		Gf2k::new(r)
+	}
+
+	fn to_repr(&self) -> u8 {
+		self.to_byte()
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> FiniteField for Gf65536<POLY, GENERATOR> {
+	type Repr = u16;
+	const SIZE: usize = 65536;
+
+	fn zero() -> Self {
+		Gf65536::zero()
+	}
+
+	fn one() -> Self {
+		Gf65536::one()
+	}
+
+	fn inv(&self) -> Option<Self> {
+		Gf65536::inv(self)
+	}
+
+	fn from_repr(r: u16) -> Option<Self> {
+		Some(Gf65536::from_u16(r))
+	}
+
+	fn to_repr(&self) -> u16 {
+		self.to_u16()
+	}
+}
+
+impl FiniteField for Tower {
+	type Repr = u8;
+	const SIZE: usize = 256;
+
+	fn zero() -> Tower {
+		Tower::zero()
+	}
+
+	fn one() -> Tower {
+		Tower::one()
+	}
+
+	fn inv(&self) -> Option<Tower> {
+		Tower::inv(self)
+	}
+
+	fn from_repr(r: u8) -> Option<Tower> {
+		Some(Tower::from_byte(r))
+	}
+
+	fn to_repr(&self) -> u8 {
+		self.to_byte()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modular::Aes;
+    use crate::small::{ Gf2, Gf16 };
+
+    /// Field axioms on the given elements
+    fn check<F: FiniteField>(elements: &[F]) {
+        for &a in elements {
+            assert_eq!(a + F::zero(), a);
+            assert_eq!(a * F::one(), a);
+            assert_eq!(a - a * F::one(), F::zero());
+            assert_eq!(F::from_repr(a.to_repr()), Some(a));
+            assert_eq!(a.pow(F::SIZE as u64), a);
+            match a.inv() {
+                Some(i) => assert_eq!(a * i, F::one()),
+                None => assert!(a.is_zero()),
+            }
+            for &b in elements.iter().step_by(3) {
+                assert_eq!(a * b, b * a);
+                assert_eq!(a.checked_div(b).map(|q| q * b), if b.is_zero() { None } else { Some(a) });
+            }
+        }
+        assert_eq!(F::zero().pow(0), F::one());
+    }
+
+    #[test]
+    fn test_fields() {
+        check(&Gf256::all().collect::<Vec<_>>());
+        check(&(0..=255).map(Aes::from_byte).collect::<Vec<_>>());
+        check(&(0..=255).map(Tower::from_byte).collect::<Vec<_>>());
+        check(&(0..16).map(Gf16::from_byte).collect::<Vec<_>>());
+        check(&[Gf2::zero(), Gf2::one()]);
+        check(&(0..65535).step_by(257).map(<Gf65536>::from_u16).collect::<Vec<_>>());
+        assert_eq!(Gf16::from_repr(0x10), None);
+        assert_eq!(<Gf65536 as FiniteField>::SIZE, 65536);
+    }
+}
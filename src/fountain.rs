@@ -0,0 +1,409 @@
+//! LT codes, a rateless erasure code.
+//!
+//! The data is cut into k source blocks of symbol_size bytes (the last
+//! one padded with zeros). An encoder produces as many coded symbols as
+//! wanted: symbol id is a linear combination of d source blocks, with d
+//! drawn from the robust soliton distribution and the blocks and their
+//! nonzero GF(256) coefficients drawn from a generator seeded by the code's
+//! seed and id. The decoder derives the same combination from the id, so
+//! only the id travels with the symbol, and any large enough set of
+//! symbols recovers the data no matter which ones were lost.
+//!
+//! Decoding peels first: a symbol with one unknown block left gives that
+//! block, which is then substituted into all other symbols. When peeling
+//! stalls although there are as many equations as unknown blocks, the
+//! rest is solved by Gaussian elimination. Thanks to the GF(256)
+//! coefficients a set of k + a few symbols almost always suffices, while
+//! plain XOR based LT codes need several percent more.
+//!
+//! The distribution follows Luby, "LT codes" (2002): c and delta trade the
+//! expected overhead against the chance of a stalled peeling decoder.
+
+use std::fmt;
+use std::error;
+
+use crate::Gf256;
+use crate::bytes;
+
+/// Errors produced by the LT code
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum LtError {
+	/// The data or symbol size is zero, or the distribution parameters
+	/// are out of range
+	InvalidParameters,
+	/// The data given to the encoder has a different length than the code
+	DataLengthMismatch,
+	/// A coded symbol has the wrong size
+	SymbolSizeMismatch,
+}
+
+impl fmt::Display for LtError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			LtError::InvalidParameters => "invalid LT code parameters",
+			LtError::DataLengthMismatch => "data length does not match the code",
+			LtError::SymbolSizeMismatch => "coded symbol has the wrong size",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for LtError {}
+
+/// A coded symbol and the id it was generated from
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct EncodedSymbol {
+	pub id: u32,
+	pub data: Vec<u8>,
+}
+
+/// splitmix64
+fn next_random(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+/// The parameters shared by encoder and decoder
+#[derive(Clone,PartialEq,Debug)]
+pub struct LtCode {
+	len: usize,
+	symbol_size: usize,
+	k: usize,
+	seed: u64,
+	/// cdf[d - 1] is the probability of a degree up to d
+	cdf: Vec<f64>,
+}
+
+impl LtCode {
+	/// A code for len bytes of data in blocks of symbol_size bytes, with
+	/// c = 0.1 and delta = 0.5
+	pub fn new(len: usize, symbol_size: usize, seed: u64) -> Result<LtCode, LtError> {
+		if len == 0 || symbol_size == 0 {
+			return Err(LtError::InvalidParameters);
+		}
+		let k = len.div_ceil(symbol_size);
+		LtCode { len, symbol_size, k, seed, cdf: Vec::new() }.with_distribution(0.1, 0.5)
+	}
+
+	/// Replaces the robust soliton parameters; c must be positive and
+	/// delta between 0 and 1
+	pub fn with_distribution(mut self, c: f64, delta: f64) -> Result<LtCode, LtError> {
+		if c.is_nan() || c <= 0.0 || delta.is_nan() || delta <= 0.0 || delta >= 1.0 {
+			return Err(LtError::InvalidParameters);
+		}
+		let k = self.k as f64;
+		let r = c * (k / delta).ln() * k.sqrt();
+		let spike = ((k / r) as usize).clamp(1, self.k);
+		let mut weights: Vec<f64> = (1..=self.k).map(|d| {
+			let rho = if d == 1 { 1.0 / k } else { 1.0 / (d * (d - 1)) as f64 };
+			let tau = if d < spike {
+				r / (d as f64 * k)
+			} else if d == spike {
+				r * (r / delta).ln().max(0.0) / k
+			} else {
+				0.0
+			};
+			rho + tau
+		}).collect();
+		let total: f64 = weights.iter().sum();
+		let mut acc = 0.0;
+		for w in weights.iter_mut() {
+			acc += *w / total;
+			*w = acc;
+		}
+		self.cdf = weights;
+		Ok(self)
+	}
+
+	/// The number of source blocks k
+	pub fn source_symbols(&self) -> usize {
+		self.k
+	}
+
+	pub fn symbol_size(&self) -> usize {
+		self.symbol_size
+	}
+
+	pub fn data_len(&self) -> usize {
+		self.len
+	}
+
+	/// The source blocks and coefficients combined into symbol id
+	fn neighbors(&self, id: u32) -> Vec<(usize, u8)> {
+		let mut state = self.seed ^ (id as u64).wrapping_mul(0xd6e8_feb8_6659_fd93);
+		let u = (next_random(&mut state) >> 11) as f64 / (1u64 << 53) as f64;
+		let degree = (self.cdf.partition_point(|&p| p < u) + 1).min(self.k);
+		let mut out: Vec<(usize, u8)> = Vec::with_capacity(degree);
+		while out.len() < degree {
+			let r = next_random(&mut state);
+			let block = (r % self.k as u64) as usize;
+			if out.iter().all(|&(b, _)| b != block) {
+				out.push((block, (r >> 56) as u8 % 255 + 1));
+			}
+		}
+		out
+	}
+
+	/// An encoder for data, which must be data_len() bytes long
+	pub fn encoder<'a>(&'a self, data: &[u8]) -> Result<LtEncoder<'a>, LtError> {
+		if data.len() != self.len {
+			return Err(LtError::DataLengthMismatch);
+		}
+		let blocks = data.chunks(self.symbol_size).map(|c| {
+			let mut b = c.to_vec();
+			b.resize(self.symbol_size, 0);
+			b
+		}).collect();
+		Ok(LtEncoder { code: self, blocks, next: 0 })
+	}
+
+	pub fn decoder(&self) -> LtDecoder<'_> {
+		LtDecoder { code: self, blocks: vec![None; self.k], known: 0, pending: Vec::new(), received: 0 }
+	}
+}
+
+/// Produces coded symbols; as an iterator it yields ids 0, 1, 2, ...
+#[derive(Clone,Debug)]
+pub struct LtEncoder<'a> {
+	code: &'a LtCode,
+	blocks: Vec<Vec<u8>>,
+	next: u32,
+}
+
+impl LtEncoder<'_> {
+	pub fn symbol(&self, id: u32) -> EncodedSymbol {
+		let mut data = vec![0u8; self.code.symbol_size];
+		for (b, c) in self.code.neighbors(id) {
+			bytes::mul_add(&mut data, &self.blocks[b], c);
+		}
+		EncodedSymbol { id, data }
+	}
+}
+
+impl Iterator for LtEncoder<'_> {
+	type Item = EncodedSymbol;
+
+	fn next(&mut self) -> Option<EncodedSymbol> {
+		let s = self.symbol(self.next);
+		self.next = self.next.wrapping_add(1);
+		Some(s)
+	}
+}
+
+/// A received symbol with the known blocks substituted
+#[derive(Clone,Debug)]
+struct Equation {
+	terms: Vec<(usize, u8)>,
+	data: Vec<u8>,
+}
+
+/// Collects coded symbols until the data is recovered
+#[derive(Clone,Debug)]
+pub struct LtDecoder<'a> {
+	code: &'a LtCode,
+	blocks: Vec<Option<Vec<u8>>>,
+	known: usize,
+	pending: Vec<Equation>,
+	received: usize,
+}
+
+impl LtDecoder<'_> {
+	/// Adds a symbol and returns whether the data is complete. Symbols
+	/// after that are ignored.
+	pub fn add(&mut self, symbol: &EncodedSymbol) -> Result<bool, LtError> {
+		if symbol.data.len() != self.code.symbol_size {
+			return Err(LtError::SymbolSizeMismatch);
+		}
+		if self.is_complete() {
+			return Ok(true);
+		}
+		self.received += 1;
+		let mut eq = Equation { terms: self.code.neighbors(symbol.id), data: symbol.data.clone() };
+		self.substitute(&mut eq);
+		if !eq.terms.is_empty() {
+			self.pending.push(eq);
+		}
+		self.peel();
+		if !self.is_complete() && self.pending.len() >= self.code.k - self.known {
+			self.eliminate();
+		}
+		Ok(self.is_complete())
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.known == self.code.k
+	}
+
+	/// Number of symbols added so far
+	pub fn received(&self) -> usize {
+		self.received
+	}
+
+	/// Number of source blocks recovered so far
+	pub fn recovered_blocks(&self) -> usize {
+		self.known
+	}
+
+	/// The data, once complete
+	pub fn data(&self) -> Option<Vec<u8>> {
+		if !self.is_complete() {
+			return None;
+		}
+		let mut out: Vec<u8> = self.blocks.iter().flat_map(|b| b.as_ref().expect("Complete").iter().copied()).collect();
+		out.truncate(self.code.len);
+		Some(out)
+	}
+
+	/// Removes the known blocks from eq
+	fn substitute(&self, eq: &mut Equation) {
+		let blocks = &self.blocks;
+		let data = &mut eq.data;
+		eq.terms.retain(|&(b, c)| match blocks[b] {
+			Some(ref block) => {
+				bytes::mul_add(data, block, c);
+				false
+			},
+			None => true,
+		});
+	}
+
+	fn solve(&mut self, block: usize, data: Vec<u8>) {
+		if self.blocks[block].is_none() {
+			self.blocks[block] = Some(data);
+			self.known += 1;
+		}
+	}
+
+	fn peel(&mut self) {
+		while let Some(i) = self.pending.iter().position(|eq| eq.terms.len() == 1) {
+			let Equation { terms, mut data } = self.pending.swap_remove(i);
+			let (b, c) = terms[0];
+			let inv = Gf256::from_byte(c).inv().expect("Coefficients are nonzero");
+			bytes::mul_scalar(&mut data, inv.to_byte());
+			self.solve(b, data);
+			let mut pending = std::mem::take(&mut self.pending);
+			for eq in pending.iter_mut() {
+				self.substitute(eq);
+			}
+			pending.retain(|eq| !eq.terms.is_empty());
+			self.pending = pending;
+		}
+	}
+
+	/// Gauss-Jordan elimination over the unknown blocks. Leaves the
+	/// pending equations alone if they do not determine all of them.
+	fn eliminate(&mut self) {
+		let unknown: Vec<usize> = (0..self.code.k).filter(|&b| self.blocks[b].is_none()).collect();
+		let mut column = vec![usize::MAX; self.code.k];
+		for (i, &b) in unknown.iter().enumerate() {
+			column[b] = i;
+		}
+		let mut rows: Vec<(Vec<Gf256>, Vec<u8>)> = self.pending.iter().map(|eq| {
+			let mut coeffs = vec![Gf256::zero(); unknown.len()];
+			for &(b, c) in &eq.terms {
+				coeffs[column[b]] = Gf256::from_byte(c);
+			}
+			(coeffs, eq.data.clone())
+		}).collect();
+		for col in 0..unknown.len() {
+			let Some(p) = (col..rows.len()).find(|&r| rows[r].0[col] != Gf256::zero()) else {
+				return;
+			};
+			rows.swap(col, p);
+			let (pivot, rest) = rows.split_at_mut(col + 1);
+			let pivot = &mut pivot[col];
+			let inv = pivot.0[col].inv().expect("Nonzero pivot");
+			for c in pivot.0.iter_mut() {
+				*c *= inv;
+			}
+			bytes::mul_scalar(&mut pivot.1, inv.to_byte());
+			for row in rest.iter_mut() {
+				let f = row.0[col];
+				if f != Gf256::zero() {
+					for (a, &b) in row.0.iter_mut().zip(&pivot.0) {
+						*a += f * b;
+					}
+					bytes::mul_add(&mut row.1, &pivot.1, f.to_byte());
+				}
+			}
+		}
+		// Back substitution, from the last pivot up
+		for col in (0..unknown.len()).rev() {
+			let (above, below) = rows.split_at_mut(col);
+			let pivot = &below[0];
+			for row in above.iter_mut() {
+				let f = row.0[col];
+				if f != Gf256::zero() {
+					row.0[col] = Gf256::zero();
+					bytes::mul_add(&mut row.1, &pivot.1, f.to_byte());
+				}
+			}
+		}
+		for (i, (_, data)) in rows.into_iter().take(unknown.len()).enumerate() {
+			self.solve(unknown[i], data);
+		}
+		self.pending.clear();
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 131 + 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_decode_with_losses() {
+        let data = data(1000);
+        let code = LtCode::new(data.len(), 10, 42).unwrap();
+        assert_eq!(code.source_symbols(), 100);
+        let encoder = code.encoder(&data).unwrap();
+        let mut decoder = code.decoder();
+        // Lose two symbols out of three
+        for symbol in encoder.filter(|s| s.id % 3 == 0) {
+            if decoder.add(&symbol).unwrap() {
+                break;
+            }
+            assert!(decoder.received() < 150, "overhead too large");
+        }
+        assert!(decoder.received() >= 100);
+        assert_eq!(decoder.data().unwrap(), data);
+    }
+
+    #[test]
+    fn test_decoders_agree() {
+        let data = data(77);
+        let code = LtCode::new(data.len(), 4, 7).unwrap().with_distribution(0.2, 0.1).unwrap();
+        let encoder = code.encoder(&data).unwrap();
+        assert_eq!(encoder.symbol(5), encoder.symbol(5));
+        for skip in [0, 13, 1000] {
+            let mut decoder = code.decoder();
+            assert_eq!(decoder.data(), None);
+            let mut ids = skip..;
+            while !decoder.add(&encoder.symbol(ids.next().unwrap())).unwrap() {}
+            assert_eq!(decoder.recovered_blocks(), 20);
+            assert_eq!(decoder.data().unwrap(), data);
+        }
+        let single = LtCode::new(3, 8, 0).unwrap();
+        let mut decoder = single.decoder();
+        assert!(decoder.add(&single.encoder(b"abc").unwrap().symbol(9)).unwrap());
+        assert_eq!(decoder.data().unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(LtCode::new(0, 4, 0), Err(LtError::InvalidParameters));
+        assert_eq!(LtCode::new(4, 0, 0), Err(LtError::InvalidParameters));
+        let code = LtCode::new(16, 4, 0).unwrap();
+        assert_eq!(code.clone().with_distribution(0.1, 1.0), Err(LtError::InvalidParameters));
+        assert_eq!(code.encoder(&[0; 15]).err(), Some(LtError::DataLengthMismatch));
+        let symbol = EncodedSymbol { id: 0, data: vec![0; 5] };
+        assert_eq!(code.decoder().add(&symbol), Err(LtError::SymbolSizeMismatch));
+    }
+}
@@ -0,0 +1,185 @@
+//! Polynomials over GF(2) of degree up to 16, packed into integers.
+//!
+//! Bit i of the integer is the coefficient of x^i, so 0x11d is
+//! x^8 + x^4 + x^3 + x^2 + 1. These are the reduction polynomials of the
+//! binary fields in this crate; is_irreducible and is_primitive check a
+//! custom modulus before tables are built from it.
+
+/// Largest supported degree
+pub const MAX_DEGREE: u32 = 16;
+
+/// Degree of a, None for zero
+pub const fn degree(a: u32) -> Option<u32> {
+	if a == 0 {
+		None
+	} else {
+		Some(31 - a.leading_zeros())
+	}
+}
+
+/// a * b mod m, for a and b of degree below deg(m) <= 16
+const fn mul_mod(a: u32, b: u32, m: u32, d: u32) -> u32 {
+	let (mut a, mut b, mut acc) = (a, b, 0u32);
+	while b != 0 {
+		if b & 1 != 0 {
+			acc ^= a;
+		}
+		a <<= 1;
+		if a >> d != 0 {
+			a ^= m;
+		}
+		b >>= 1;
+	}
+	acc
+}
+
+/// x mod m
+const fn x_mod(m: u32, d: u32) -> u32 {
+	if d == 1 { m ^ 2 } else { 2 }
+}
+
+/// x^(2^k) mod m by k squarings
+const fn x_pow_2k(k: u32, m: u32, d: u32) -> u32 {
+	let mut r = x_mod(m, d);
+	let mut i = 0;
+	while i < k {
+		r = mul_mod(r, r, m, d);
+		i += 1;
+	}
+	r
+}
+
+/// x^e mod m
+const fn x_pow(mut e: u32, m: u32, d: u32) -> u32 {
+	let (mut base, mut acc) = (x_mod(m, d), 1u32);
+	while e != 0 {
+		if e & 1 != 0 {
+			acc = mul_mod(acc, base, m, d);
+		}
+		base = mul_mod(base, base, m, d);
+		e >>= 1;
+	}
+	acc
+}
+
+const fn gcd(mut a: u32, mut b: u32) -> u32 {
+	while b != 0 {
+		// a mod b
+		let db = 31 - b.leading_zeros();
+		while a != 0 && 31 - a.leading_zeros() >= db {
+			a ^= b << (31 - a.leading_zeros() - db);
+		}
+		let t = a;
+		a = b;
+		b = t;
+	}
+	a
+}
+
+/// Whether a, of degree 1 to MAX_DEGREE, has no nontrivial factors.
+/// Uses Rabin's test: x^(2^d) = x mod a, and x^(2^(d/q)) - x is coprime
+/// to a for every prime q dividing d.
+pub const fn is_irreducible(a: u32) -> bool {
+	let d = match degree(a) {
+		Some(d) if d >= 1 && d <= MAX_DEGREE => d,
+		_ => return false,
+	};
+	if d == 1 {
+		return true;
+	}
+	if x_pow_2k(d, a, d) != 2 {
+		return false;
+	}
+	let mut q = 2;
+	while q <= d {
+		if d.is_multiple_of(q) && is_prime(q) && gcd(a, x_pow_2k(d / q, a, d) ^ 2) != 1 {
+			return false;
+		}
+		q += 1;
+	}
+	true
+}
+
+const fn is_prime(n: u32) -> bool {
+	let mut i = 2;
+	while i * i <= n {
+		if n.is_multiple_of(i) {
+			return false;
+		}
+		i += 1;
+	}
+	n >= 2
+}
+
+/// Whether a is irreducible and x generates the multiplicative group of
+/// GF(2)[x] / a, i.e. has order 2^d - 1. Only primitive polynomials give
+/// log and exp tables with generator x.
+pub const fn is_primitive(a: u32) -> bool {
+	if !is_irreducible(a) {
+		return false;
+	}
+	let d = 31 - a.leading_zeros();
+	let order = (1u32 << d) - 1;
+	if x_pow(order, a, d) != 1 {
+		return false;
+	}
+	// x^(order / q) != 1 for every prime factor q of the order
+	let (mut rest, mut q) = (order, 2);
+	while rest > 1 {
+		if rest.is_multiple_of(q) {
+			if x_pow(order / q, a, d) == 1 {
+				return false;
+			}
+			while rest.is_multiple_of(q) {
+				rest /= q;
+			}
+		}
+		q += 1;
+	}
+	true
+}
+
+/// All primitive polynomials of the given degree (1 to MAX_DEGREE), in
+/// increasing order. For degree 8 there are 16, starting with 0x11d.
+pub fn primitive_polynomials(degree: u32) -> impl Iterator<Item = u32> {
+	assert!((1..=MAX_DEGREE).contains(&degree), "Unsupported degree");
+	(1u32 << degree..1u32 << (degree + 1)).filter(|&a| is_primitive(a))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::small::POLYNOMIALS;
+    use crate::wide::PAR2_POLY;
+
+    #[test]
+    fn test_irreducible() {
+        assert!(is_irreducible(0x11b));
+        assert!(is_irreducible(0x11d));
+        // x^8 + 1 = (x + 1)^8
+        assert!(!is_irreducible(0x101));
+        // (x^2 + x + 1)(x^3 + x + 1), no roots but reducible
+        assert!(!is_irreducible(0b110001));
+        // (x^3 + x + 1)^2, no roots and no factor of degree 1 or 2
+        assert!(!is_irreducible(0b1000101));
+        assert!(is_irreducible(0b11) && is_irreducible(0b10));
+        assert!(!is_irreducible(1) && !is_irreducible(0));
+        assert!(!is_irreducible(0x3_0000));
+    }
+
+    #[test]
+    fn test_primitive() {
+        assert!(is_primitive(0x11d));
+        // The AES polynomial is irreducible, but x has order 51
+        assert!(!is_primitive(0x11b));
+        assert!(is_primitive(PAR2_POLY));
+        for &p in &POLYNOMIALS {
+            assert!(is_primitive(p as u32));
+        }
+        let all: Vec<u32> = primitive_polynomials(8).collect();
+        assert_eq!(all.len(), 16);
+        assert_eq!(all[0], 0x11d);
+        assert_eq!(primitive_polynomials(4).collect::<Vec<_>>(), [0x13, 0x19]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Binary Goppa codes over GF(2^8).
+//!
+//! A Goppa code is given by a polynomial g over Gf256 of degree t and a
+//! support L_0, ..., L_(n-1) of distinct elements that are not roots of
+//! g. The binary word c is a codeword if its syndrome, the sum of
+//! c_j / (x - L_j) mod g(x), is zero. For irreducible g, as required here, the code corrects t
+//! errors with Patterson's algorithm, twice the t / 2 that the designed
+//! distance of the Gf256 parity checks suggests. This is the code family
+//! of the McEliece cryptosystem.
+//!
+//! Words have one bit per byte, 0 or 1, in support order. Nothing here
+//! runs in constant time, so keep secret Goppa polynomials and supports
+//! away from untrusted timing observers.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::matrix::Matrix;
+use crate::poly::Poly;
+
+/// Errors produced by Goppa codes
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum GoppaError {
+	/// The Goppa polynomial is constant or not irreducible
+	InvalidPolynomial,
+	/// The support is empty, repeats an element or contains a root of g
+	InvalidSupport,
+	/// A word or message has the wrong length or is not binary
+	LengthMismatch,
+	/// There are more than t errors
+	Uncorrectable,
+}
+
+impl fmt::Display for GoppaError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			GoppaError::InvalidPolynomial => "invalid Goppa polynomial",
+			GoppaError::InvalidSupport => "invalid support",
+			GoppaError::LengthMismatch => "length mismatch",
+			GoppaError::Uncorrectable => "too many errors",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for GoppaError {}
+
+/// a^-1 mod g, None if they share a factor
+fn inv_mod(a: &Poly, g: &Poly) -> Option<Poly> {
+	let (d, s, _) = a.xgcd(g);
+	(d == Poly::one()).then(|| s.rem(g))
+}
+
+/// A binary Goppa code
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct GoppaCode {
+	/// Monic Goppa polynomial
+	g: Poly,
+	support: Vec<Gf256>,
+	/// (x - L_j)^-1 mod g for every support element
+	inverses: Vec<Poly>,
+}
+
+impl GoppaCode {
+	/// The code of the irreducible polynomial g over the support
+	pub fn new(g: &Poly, support: &[Gf256]) -> Result<GoppaCode, GoppaError> {
+		if !g.is_irreducible() {
+			return Err(GoppaError::InvalidPolynomial);
+		}
+		let mut seen = [false; 256];
+		for &a in support {
+			if core::mem::replace(&mut seen[a.to_byte() as usize], true) {
+				return Err(GoppaError::InvalidSupport);
+			}
+		}
+		// An irreducible g of degree above one has no roots at all
+		if support.is_empty() || support.iter().any(|&a| g.eval(a) == Gf256::zero()) {
+			return Err(GoppaError::InvalidSupport);
+		}
+		let g = g.monic();
+		let inverses = support.iter()
+			.map(|&a| inv_mod(&Poly::from_coeffs(&[a, Gf256::one()]), &g).expect("g(a) is nonzero"))
+			.collect();
+		Ok(GoppaCode { g, support: support.to_vec(), inverses })
+	}
+
+	/// The Goppa polynomial, made monic
+	pub fn polynomial(&self) -> &Poly {
+		&self.g
+	}
+
+	pub fn support(&self) -> &[Gf256] {
+		&self.support
+	}
+
+	/// Code length
+	pub fn n(&self) -> usize {
+		self.support.len()
+	}
+
+	/// Number of correctable errors, the degree of g
+	pub fn t(&self) -> usize {
+		self.g.degree().expect("Nonconstant")
+	}
+
+	/// The t x n parity check matrix over Gf256, H_ij = L_j^i / g(L_j). A
+	/// binary word c is a codeword iff H c = 0.
+	pub fn parity_check_matrix(&self) -> Matrix {
+		let t = self.t();
+		let mut h = Matrix::zero(t, self.n());
+		for (j, &a) in self.support.iter().enumerate() {
+			let mut v = self.g.eval(a).inv().expect("g(a) is nonzero");
+			for i in 0..t {
+				h[(i, j)] = v;
+				v *= a;
+			}
+		}
+		h
+	}
+
+	/// The 8t x n binary parity check matrix: row 8i + b holds bit b of
+	/// row i of parity_check_matrix. Its entries are 0 and 1.
+	pub fn binary_parity_check_matrix(&self) -> Matrix {
+		let h = self.parity_check_matrix();
+		let rows: Vec<Vec<Gf256>> = (0..h.rows())
+			.flat_map(|i| Matrix::from_bit_columns(h.row(i)).to_rows())
+			.collect();
+		Matrix::from_rows(&rows).expect("Rows have n entries")
+	}
+
+	/// A k x n binary generator matrix, k = n - rank of the binary parity
+	/// checks, at least n - 8t
+	pub fn generator_matrix(&self) -> Matrix {
+		// The rref of a 0/1 matrix has 0/1 entries, so its null space over
+		// Gf256 is spanned by binary vectors
+		let h = self.binary_parity_check_matrix().rref();
+		let n = self.n();
+		let pivots: Vec<usize> = (0..h.rows())
+			.filter_map(|i| h.row(i).iter().position(|&c| c != Gf256::zero()))
+			.collect();
+		let free: Vec<usize> = (0..n).filter(|j| !pivots.contains(j)).collect();
+		let mut gen = Matrix::zero(free.len(), n);
+		for (r, &f) in free.iter().enumerate() {
+			gen[(r, f)] = Gf256::one();
+			for (i, &p) in pivots.iter().enumerate() {
+				gen[(r, p)] = h[(i, f)];
+			}
+		}
+		gen
+	}
+
+	fn check(&self, word: &[u8]) -> Result<(), GoppaError> {
+		if word.len() != self.n() || word.iter().any(|&b| b > 1) {
+			return Err(GoppaError::LengthMismatch);
+		}
+		Ok(())
+	}
+
+	/// The codeword msg * G for a message of generator_matrix().rows() bits
+	pub fn encode(&self, msg: &[u8]) -> Result<Vec<u8>, GoppaError> {
+		let gen = self.generator_matrix();
+		if msg.len() != gen.rows() || msg.iter().any(|&b| b > 1) {
+			return Err(GoppaError::LengthMismatch);
+		}
+		let mut word = vec![0u8; self.n()];
+		for (i, _) in msg.iter().enumerate().filter(|&(_, &b)| b == 1) {
+			for (w, c) in word.iter_mut().zip(gen.row(i)) {
+				*w ^= c.to_byte();
+			}
+		}
+		Ok(word)
+	}
+
+	/// The syndrome polynomial S(x), of degree below t
+	pub fn syndrome(&self, word: &[u8]) -> Result<Poly, GoppaError> {
+		self.check(word)?;
+		let mut s = Poly::zero();
+		for (_, inv) in word.iter().zip(&self.inverses).filter(|&(&b, _)| b == 1) {
+			s += inv;
+		}
+		Ok(s)
+	}
+
+	/// The error locator sigma(x) = prod (x - L_j) over the error positions
+	/// j, by Patterson's algorithm
+	pub fn error_locator(&self, syndrome: &Poly) -> Result<Poly, GoppaError> {
+		let g = &self.g;
+		let t = self.t();
+		if syndrome.is_zero() {
+			return Ok(Poly::one());
+		}
+		let x = Poly::monomial(Gf256::one(), 1).rem(g);
+		let inv = inv_mod(syndrome, g).ok_or(GoppaError::Uncorrectable)?;
+		// sigma = a^2 + x b^2 with a^2 = x b^2 (S^-1 + x) mod g
+		let sum = &inv + &x;
+		if sum.is_zero() {
+			return Ok(Poly::monomial(Gf256::one(), 1));
+		}
+		// Squaring is a bijection on Gf256[x] / g, of order 2^(8t), so
+		// the square root is the 2^(8t - 1)-th power
+		let mut root = sum;
+		for _ in 0..8 * t - 1 {
+			root = root.mulmod(&root, g);
+		}
+		// a = b root mod g with deg a <= t / 2 and deg b <= (t - 1) / 2
+		let (a, _, b) = g.xgcd_until(&root, t / 2 + 1);
+		Ok((&(&a * &a) + &(&x * &(&b * &b))).monic())
+	}
+
+	/// Corrects up to t errors in word and returns the positions flipped.
+	/// word is left unchanged if it is uncorrectable.
+	pub fn decode(&self, word: &mut [u8]) -> Result<Vec<usize>, GoppaError> {
+		let sigma = self.error_locator(&self.syndrome(word)?)?;
+		let positions: Vec<usize> = (0..self.n())
+			.filter(|&j| sigma.eval(self.support[j]) == Gf256::zero())
+			.collect();
+		if Some(positions.len()) != sigma.degree() {
+			return Err(GoppaError::Uncorrectable);
+		}
+		for &j in &positions {
+			word[j] ^= 1;
+		}
+		Ok(positions)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first irreducible monic polynomial of degree t in a sequence
+    /// of candidates depending on seed
+    fn irreducible(t: usize, seed: u8) -> Poly {
+        (0usize..).map(|i| {
+            let mut coeffs: Vec<Gf256> = (0..t)
+                .map(|j| Gf256::from_byte((i * 73 + j * 29 + seed as usize) as u8))
+                .collect();
+            coeffs.push(Gf256::one());
+            Poly::from_vec(coeffs)
+        }).find(Poly::is_irreducible).unwrap()
+    }
+
+    fn support(n: usize) -> Vec<Gf256> {
+        Gf256::all().take(n).collect()
+    }
+
+    #[test]
+    fn test_parity_checks() {
+        let code = GoppaCode::new(&irreducible(4, 2), &support(60)).unwrap();
+        let gen = code.generator_matrix();
+        assert!(gen.rows() >= 60 - 32);
+        let msg: Vec<u8> = (0..gen.rows()).map(|i| (i * 5 % 3 == 0) as u8).collect();
+        let word = code.encode(&msg).unwrap();
+        assert!(code.syndrome(&word).unwrap().is_zero());
+        let as_field: Vec<Gf256> = word.iter().map(|&b| Gf256::from_byte(b)).collect();
+        assert!(code.parity_check_matrix().mul_vec(&as_field).iter().all(|&c| c == Gf256::zero()));
+        assert!(code.binary_parity_check_matrix().mul_vec(&as_field).iter().all(|&c| c == Gf256::zero()));
+    }
+
+    #[test]
+    fn test_patterson() {
+        for (t, n) in [(2, 40), (5, 120), (8, 256)] {
+            let code = GoppaCode::new(&irreducible(t, t as u8), &support(n)).unwrap();
+            let msg: Vec<u8> = (0..code.generator_matrix().rows()).map(|i| (i * 7 % 5 < 2) as u8).collect();
+            let original = code.encode(&msg).unwrap();
+            for errors in 0..=t {
+                let mut positions: Vec<usize> = (0..errors).map(|e| (e * 37 + t) % n).collect();
+                positions.sort_unstable();
+                let mut word = original.clone();
+                for &p in &positions {
+                    word[p] ^= 1;
+                }
+                assert_eq!(code.decode(&mut word), Ok(positions), "t = {}, {} errors", t, errors);
+                assert_eq!(word, original);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let g = irreducible(3, 5);
+        assert_eq!(GoppaCode::new(&Poly::one(), &support(10)), Err(GoppaError::InvalidPolynomial));
+        let doubled = [Gf256::one(), Gf256::one()];
+        assert_eq!(GoppaCode::new(&g, &doubled), Err(GoppaError::InvalidSupport));
+        assert_eq!(GoppaCode::new(&g, &[]), Err(GoppaError::InvalidSupport));
+        let linear = Poly::from_bytes(&[7, 1]);
+        assert_eq!(GoppaCode::new(&linear, &support(10)), Err(GoppaError::InvalidSupport));
+        let code = GoppaCode::new(&g, &support(10)).unwrap();
+        assert_eq!(code.syndrome(&[0; 9]), Err(GoppaError::LengthMismatch));
+        assert_eq!(code.syndrome(&[2; 10]), Err(GoppaError::LengthMismatch));
+    }
+}
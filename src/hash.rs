@@ -0,0 +1,234 @@
+//! Deterministic hashing to field elements.
+//!
+//! Elements are derived with expand_message_xmd of RFC 9380, section
+//! 5.3.1, over SHA-256, so any implementation of that RFC reproduces
+//! them. The domain is the domain separation tag (DST) of the RFC; tags
+//! longer than 255 bytes are first hashed to "H2C-OVERSIZE-DST-" || DST
+//! as the RFC prescribes.
+//!
+//! Every element of GF(256) is a byte, so hash_to_field takes the first
+//! byte of a one byte expansion and is exactly uniform.
+//!
+//! hash_to_distinct picks n distinct nonzero elements with a partial
+//! Fisher-Yates shuffle of 1, 2, ..., 255 in this order. It always
+//! expands to 8 * 255 bytes, so fewer elements are a prefix of more; step
+//! i swaps entry i with entry i + (r mod (255 - i)), where r is the
+//! big-endian 64-bit integer in bytes 8 i to 8 i + 7, and outputs entry i.
+//! The bias of the reduction is below 2^-56.
+
+use crate::Gf256;
+
+/// Most input to expand_message_xmd: 255 blocks of a SHA-256 digest
+pub const MAX_EXPAND_LEN: usize = 255 * 32;
+
+const K: [u32; 64] = [
+	0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+	0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+	0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+	0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+	0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+	0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+	0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+	0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Incremental SHA-256 (FIPS 180-4)
+#[derive(Clone,Debug)]
+pub struct Sha256 {
+	state: [u32; 8],
+	block: [u8; 64],
+	filled: usize,
+	len: u64,
+}
+
+impl Sha256 {
+	pub const fn new() -> Sha256 {
+		Sha256 {
+			state: [
+				0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+				0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+			],
+			block: [0; 64],
+			filled: 0,
+			len: 0,
+		}
+	}
+
+	fn compress(&mut self) {
+		let mut w = [0u32; 64];
+		for (i, c) in self.block.chunks_exact(4).enumerate() {
+			w[i] = u32::from_be_bytes([c[0], c[1], c[2], c[3]]);
+		}
+		for i in 16..64 {
+			let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+			let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+			w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+		}
+		let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+		for i in 0..64 {
+			let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+			let ch = (e & f) ^ (!e & g);
+			let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+			let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+			let maj = (a & b) ^ (a & c) ^ (b & c);
+			let t2 = s0.wrapping_add(maj);
+			h = g;
+			g = f;
+			f = e;
+			e = d.wrapping_add(t1);
+			d = c;
+			c = b;
+			b = a;
+			a = t1.wrapping_add(t2);
+		}
+		for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+			*s = s.wrapping_add(v);
+		}
+	}
+
+	pub fn update(&mut self, mut data: &[u8]) {
+		self.len += data.len() as u64;
+		while !data.is_empty() {
+			let n = (64 - self.filled).min(data.len());
+			self.block[self.filled..self.filled + n].copy_from_slice(&data[..n]);
+			self.filled += n;
+			data = &data[n..];
+			if self.filled == 64 {
+				self.compress();
+				self.filled = 0;
+			}
+		}
+	}
+
+	pub fn finalize(mut self) -> [u8; 32] {
+		let bits = self.len * 8;
+		self.update(&[0x80]);
+		while self.filled != 56 {
+			self.update(&[0]);
+		}
+		self.update(&bits.to_be_bytes());
+		let mut out = [0u8; 32];
+		for (o, s) in out.chunks_exact_mut(4).zip(self.state) {
+			o.copy_from_slice(&s.to_be_bytes());
+		}
+		out
+	}
+}
+
+impl Default for Sha256 {
+	fn default() -> Sha256 {
+		Sha256::new()
+	}
+}
+
+/// SHA-256 of the concatenation of parts
+pub fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+	let mut h = Sha256::new();
+	for p in parts {
+		h.update(p);
+	}
+	h.finalize()
+}
+
+/// Fills out with expand_message_xmd(msg, dst, out.len()) over SHA-256.
+/// Panics if out is longer than MAX_EXPAND_LEN.
+pub fn expand_message_xmd(dst: &[u8], msg: &[u8], out: &mut [u8]) {
+	assert!(out.len() <= MAX_EXPAND_LEN, "Output too long for expand_message_xmd");
+	let hashed;
+	let dst = if dst.len() > 255 {
+		hashed = sha256(&[b"H2C-OVERSIZE-DST-", dst]);
+		&hashed[..]
+	} else {
+		dst
+	};
+	let dst_len = [dst.len() as u8];
+	let len = (out.len() as u16).to_be_bytes();
+	let b0 = sha256(&[&[0u8; 64], msg, &len, &[0], dst, &dst_len]);
+	let mut b = sha256(&[&b0, &[1], dst, &dst_len]);
+	for (i, chunk) in out.chunks_mut(32).enumerate() {
+		if i > 0 {
+			let mut x = b0;
+			for (x, y) in x.iter_mut().zip(&b) {
+				*x ^= y;
+			}
+			b = sha256(&[&x, &[i as u8 + 1], dst, &dst_len]);
+		}
+		chunk.copy_from_slice(&b[..chunk.len()]);
+	}
+}
+
+/// The element derived from msg in the given domain
+pub fn hash_to_field(domain: &[u8], msg: &[u8]) -> Gf256 {
+	let mut out = [0u8; 1];
+	expand_message_xmd(domain, msg, &mut out);
+	Gf256::from_byte(out[0])
+}
+
+/// Fills out with distinct nonzero elements derived from msg in the
+/// given domain. Panics if out is longer than 255.
+pub fn hash_to_distinct(domain: &[u8], msg: &[u8], out: &mut [Gf256]) {
+	assert!(out.len() <= 255, "GF(256) has only 255 nonzero elements");
+	let mut bytes = [0u8; 8 * 255];
+	expand_message_xmd(domain, msg, &mut bytes);
+	let mut pool: [u8; 255] = core::array::from_fn(|i| i as u8 + 1);
+	for (i, (o, r)) in out.iter_mut().zip(bytes.chunks_exact(8)).enumerate() {
+		let r = u64::from_be_bytes(r.try_into().expect("8 bytes"));
+		pool.swap(i, i + (r % (255 - i) as u64) as usize);
+		*o = Gf256::from_byte(pool[i]);
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(hex(&sha256(&[])), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(&sha256(&[b"abc"])), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let long = [b'a'; 1000];
+        let mut h = Sha256::new();
+        for chunk in long.chunks(7) {
+            h.update(chunk);
+        }
+        assert_eq!(h.finalize(), sha256(&[&long[..500], &long[500..]]));
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        // RFC 9380, appendix K.1
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let mut out = [0u8; 32];
+        expand_message_xmd(dst, b"", &mut out);
+        assert_eq!(hex(&out), "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235");
+        expand_message_xmd(dst, b"abc", &mut out);
+        assert_eq!(hex(&out), "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615");
+    }
+
+    #[test]
+    fn test_hash_to_field() {
+        let a = hash_to_field(b"shards", b"id-1");
+        assert_eq!(a, hash_to_field(b"shards", b"id-1"));
+        let mut first = [0u8; 1];
+        expand_message_xmd(b"shards", b"id-1", &mut first);
+        assert_eq!(a.to_byte(), first[0]);
+
+        let mut all = [Gf256::ZERO; 255];
+        hash_to_distinct(b"points", b"set", &mut all);
+        let mut seen = [false; 256];
+        for p in all {
+            assert!(!seen[p.to_byte() as usize] && p != Gf256::ZERO);
+            seen[p.to_byte() as usize] = true;
+        }
+        let mut some = [Gf256::ZERO; 10];
+        hash_to_distinct(b"points", b"set", &mut some);
+        assert_eq!(some, all[..10]);
+        hash_to_distinct(b"other", b"set", &mut some);
+        assert_ne!(some, all[..10]);
+    }
+}
@@ -0,0 +1,351 @@
+//! Forney convolutional interleaving.
+//!
+//! Bytes are fed in turn to I branches; branch i delays its bytes by
+//! i * M positions of that branch. The deinterleaver uses the opposite
+//! delays, (I - 1 - i) * M, so every byte comes out after the same total
+//! latency of I * (I - 1) * M bytes. A burst of B corrupted bytes on the
+//! channel is spread so that consecutive errors end up I bytes apart,
+//! and a code block of I * M bytes starting on branch 0 sees at most
+//! ceil(B / I) of them.
+//!
+//! DVB (EN 300 421) uses I = 12 and M = 17 in front of RS(204, 188).
+//! Both sides start with zero-filled delay lines and have to stay
+//! aligned: byte 0 of the stream enters branch 0.
+//!
+//! The block interleaver is the frame based alternative: a frame holds
+//! depth codewords of n bytes written row by row and sent column by
+//! column, so byte j of codeword i goes out at j * depth + i. A burst of
+//! up to depth * (n - k) / 2 bytes then leaves every codeword with at
+//! most (n - k) / 2 errors. InterleavedRs does this around an RsEncoder.
+
+use alloc::{ vec, vec::Vec };
+
+use crate::rs::RsError;
+use crate::rs::encoder::RsEncoder;
+
+/// A set of I delay lines of lengths delay(0), ..., delay(I - 1)
+#[derive(Clone,PartialEq,Eq,Debug)]
+struct Branches {
+	/// Delay lines stored back to back
+	cells: Vec<u8>,
+	/// Start of the delay line of every branch in cells, and its end
+	starts: Vec<usize>,
+	/// Next cell to read (and overwrite) in every branch
+	heads: Vec<usize>,
+	/// Branch receiving the next byte
+	next: usize,
+}
+
+impl Branches {
+	fn new(branches: usize, delay: impl Fn(usize) -> usize) -> Branches {
+		assert!(branches > 0, "At least one branch is required");
+		let mut starts = Vec::with_capacity(branches + 1);
+		let mut total = 0;
+		for i in 0..branches {
+			starts.push(total);
+			total += delay(i);
+		}
+		starts.push(total);
+		Branches { cells: vec![0; total], heads: starts[..branches].to_vec(), starts, next: 0 }
+	}
+
+	fn push(&mut self, byte: u8) -> u8 {
+		let i = self.next;
+		self.next = (i + 1) % self.heads.len();
+		let (start, end) = (self.starts[i], self.starts[i + 1]);
+		if start == end {
+			return byte;
+		}
+		let head = self.heads[i];
+		let out = core::mem::replace(&mut self.cells[head], byte);
+		self.heads[i] = if head + 1 == end { start } else { head + 1 };
+		out
+	}
+
+	fn reset(&mut self) {
+		for c in self.cells.iter_mut() {
+			*c = 0;
+		}
+		let n = self.heads.len();
+		self.heads.copy_from_slice(&self.starts[..n]);
+		self.next = 0;
+	}
+}
+
+/// Convolutional interleaver with I branches and a delay step of M
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Interleaver {
+	branches: Branches,
+	depth: usize,
+}
+
+/// The matching deinterleaver
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Deinterleaver {
+	branches: Branches,
+	depth: usize,
+}
+
+macro_rules! impl_stream {
+	($ty:ident) => {
+		impl $ty {
+			/// Number of branches (I)
+			pub fn depth(&self) -> usize {
+				self.depth
+			}
+
+			/// Bytes between a byte entering the interleaver and leaving
+			/// the deinterleaver
+			pub fn latency(&self) -> usize {
+				self.branches.cells.len() * 2
+			}
+
+			/// Feeds one byte and returns the byte leaving the delay lines
+			pub fn push(&mut self, byte: u8) -> u8 {
+				self.branches.push(byte)
+			}
+
+			/// Processes buf in place; may be called with pieces of any
+			/// size
+			pub fn process(&mut self, buf: &mut [u8]) {
+				for b in buf.iter_mut() {
+					*b = self.branches.push(*b);
+				}
+			}
+
+			/// Clears the delay lines and returns to branch 0
+			pub fn reset(&mut self) {
+				self.branches.reset();
+			}
+		}
+	};
+}
+
+impl Interleaver {
+	/// Creates an interleaver with depth branches and delay step m
+	pub fn new(depth: usize, m: usize) -> Interleaver {
+		Interleaver { branches: Branches::new(depth, |i| i * m), depth }
+	}
+}
+
+impl Deinterleaver {
+	/// Creates the deinterleaver for Interleaver::new(depth, m)
+	pub fn new(depth: usize, m: usize) -> Deinterleaver {
+		Deinterleaver { branches: Branches::new(depth, |i| (depth - 1 - i) * m), depth }
+	}
+}
+
+impl_stream!(Interleaver);
+impl_stream!(Deinterleaver);
+
+/// Sends depth rows of data.len() / depth bytes column by column. Panics
+/// if depth does not divide the length.
+pub fn block_interleave(data: &[u8], depth: usize) -> Vec<u8> {
+	assert!(depth > 0 && data.len().is_multiple_of(depth), "Length must be a multiple of the depth");
+	let row = data.len() / depth;
+	let mut out = vec![0u8; data.len()];
+	for (i, r) in data.chunks(row.max(1)).enumerate() {
+		for (j, &b) in r.iter().enumerate() {
+			out[j * depth + i] = b;
+		}
+	}
+	out
+}
+
+/// The inverse of block_interleave
+pub fn block_deinterleave(data: &[u8], depth: usize) -> Vec<u8> {
+	assert!(depth > 0 && data.len().is_multiple_of(depth), "Length must be a multiple of the depth");
+	let row = data.len() / depth;
+	let mut out = vec![0u8; data.len()];
+	for (j, column) in data.chunks(depth).enumerate() {
+		for (i, &b) in column.iter().enumerate() {
+			out[i * row + j] = b;
+		}
+	}
+	out
+}
+
+/// depth RS codewords, block interleaved into one frame
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct InterleavedRs {
+	code: RsEncoder,
+	depth: usize,
+}
+
+impl InterleavedRs {
+	/// Panics if depth is zero
+	pub fn new(code: RsEncoder, depth: usize) -> InterleavedRs {
+		assert!(depth > 0, "Depth must be positive");
+		InterleavedRs { code, depth }
+	}
+
+	pub fn code(&self) -> &RsEncoder {
+		&self.code
+	}
+
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// depth * k data bytes per frame
+	pub fn data_len(&self) -> usize {
+		self.depth * self.code.k()
+	}
+
+	/// depth * n bytes per frame
+	pub fn frame_len(&self) -> usize {
+		self.depth * self.code.n()
+	}
+
+	/// The longest burst of errors every frame survives
+	pub fn burst_capacity(&self) -> usize {
+		self.depth * (self.code.parity_len() / 2)
+	}
+
+	/// Encodes data_len bytes, codeword i taking the i-th k bytes, into
+	/// an interleaved frame
+	pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, RsError> {
+		if data.len() != self.data_len() {
+			return Err(RsError::InvalidLength);
+		}
+		let rows: Vec<u8> = data.chunks(self.code.k()).flat_map(|m| self.code.encode(m)).collect();
+		Ok(block_interleave(&rows, self.depth))
+	}
+
+	/// Corrects a frame in place and returns the number of corrected
+	/// bytes. Every codeword is decoded on its own; if one fails the
+	/// others are still corrected and the error is returned.
+	pub fn decode(&self, frame: &mut [u8]) -> Result<usize, RsError> {
+		if frame.len() != self.frame_len() {
+			return Err(RsError::InvalidLength);
+		}
+		let mut rows = block_deinterleave(frame, self.depth);
+		let mut total = Ok(0);
+		for row in rows.chunks_mut(self.code.n()) {
+			let mut corrected = row.to_vec();
+			match self.code.decode(&mut corrected) {
+				Ok(count) => {
+					row.copy_from_slice(&corrected);
+					total = total.map(|t| t + count);
+				},
+				Err(e) => total = total.and(Err(e)),
+			}
+		}
+		frame.copy_from_slice(&block_interleave(&rows, self.depth));
+		total
+	}
+
+	/// The data bytes of a (corrected) frame
+	pub fn data(&self, frame: &[u8]) -> Result<Vec<u8>, RsError> {
+		if frame.len() != self.frame_len() {
+			return Err(RsError::InvalidLength);
+		}
+		let rows = block_deinterleave(frame, self.depth);
+		Ok(rows.chunks(self.code.n()).flat_map(|r| r[..self.code.k()].iter().copied()).collect())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 1) as u8).collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut il = Interleaver::new(12, 17);
+        let mut de = Deinterleaver::new(12, 17);
+        assert_eq!(il.latency(), 12 * 11 * 17);
+
+        let input = data(5000);
+        let mut buf = input.clone();
+        // Odd chunk sizes must not matter
+        for chunk in buf.chunks_mut(37) {
+            il.process(chunk);
+        }
+        assert_ne!(buf, input);
+        for chunk in buf.chunks_mut(100) {
+            de.process(chunk);
+        }
+        let lat = il.latency();
+        assert!(buf[..lat].iter().all(|&b| b == 0));
+        assert_eq!(&buf[lat..], &input[..input.len() - lat]);
+    }
+
+    #[test]
+    fn test_burst_spreading() {
+        let (depth, m) = (12, 17);
+        let mut il = Interleaver::new(depth, m);
+        let mut de = Deinterleaver::new(depth, m);
+        let input = data(10000);
+        let mut buf = input.clone();
+        il.process(&mut buf);
+        for b in &mut buf[4000..4000 + 96] {
+            *b ^= 0xff;
+        }
+        de.process(&mut buf);
+
+        let lat = il.latency();
+        let errors: Vec<usize> = (lat..buf.len())
+            .filter(|&i| buf[i] != input[i - lat])
+            .collect();
+        assert_eq!(errors.len(), 96);
+        // At most 96 / 12 errors in every block of I * M bytes
+        for block in (lat..buf.len()).step_by(depth * m) {
+            let hits = errors.iter().filter(|&&e| e >= block && e < block + depth * m).count();
+            assert!(hits <= 8, "{} errors in block at {}", hits, block);
+        }
+    }
+
+    #[test]
+    fn test_block_interleave() {
+        let rows = data(24);
+        let sent = block_interleave(&rows, 4);
+        assert_eq!(sent[..4], [rows[0], rows[6], rows[12], rows[18]]);
+        assert_eq!(block_deinterleave(&sent, 4), rows);
+    }
+
+    #[test]
+    fn test_interleaved_rs() {
+        let irs = InterleavedRs::new(RsEncoder::new(40, 30).unwrap(), 8);
+        assert_eq!(irs.burst_capacity(), 40);
+        let input = data(irs.data_len());
+        let original = irs.encode(&input).unwrap();
+        assert_eq!(original.len(), 320);
+
+        // A burst of 40 bytes costs every codeword 5 errors
+        let mut frame = original.clone();
+        for b in &mut frame[100..140] {
+            *b ^= 0x5a;
+        }
+        assert_eq!(irs.decode(&mut frame), Ok(40));
+        assert_eq!(frame, original);
+        assert_eq!(irs.data(&frame).unwrap(), input);
+
+        // One byte more is too much for one of them, not for the others
+        for b in &mut frame[100..141] {
+            *b ^= 0x5a;
+        }
+        assert_eq!(irs.decode(&mut frame), Err(RsError::TooManyErrors));
+        let rows = block_deinterleave(&frame, 8);
+        let clean = block_deinterleave(&original, 8);
+        let bad = rows.chunks(40).zip(clean.chunks(40)).filter(|(a, b)| a != b).count();
+        assert_eq!(bad, 1);
+        assert_eq!(irs.encode(&input[1..]), Err(RsError::InvalidLength));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut il = Interleaver::new(3, 2);
+        let mut fresh = il.clone();
+        il.process(&mut data(11));
+        il.reset();
+        let (mut a, mut b) = (data(20), data(20));
+        il.process(&mut a);
+        fresh.process(&mut b);
+        assert_eq!(a, b);
+    }
+}
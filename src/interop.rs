@@ -0,0 +1,592 @@
+//! Coding matrices of Jerasure and Intel ISA-L.
+//!
+//! Both libraries work over GF(256) with the polynomial 0x11D and treat a
+//! shard as a sequence of bytes, each its own field element, which is the
+//! field and element order of this crate. What differs between codes is
+//! the coding matrix, so shards written by either library are read back
+//! by rebuilding its matrix and running matrix_encode or matrix_decode.
+//!
+//! Layouts follow the C interfaces. Jerasure passes the m x k coding
+//! matrix as row-major ints; ISA-L passes the full (k + m) x k encoding
+//! matrix as row-major bytes with the identity on top, and encodes from
+//! the expanded tables of ec_init_tables, 32 bytes per coefficient: the
+//! products with 0 to 15 followed by the products with 0x00, 0x10, ...,
+//! 0xf0. Shard lists are data shards first, then coding shards, as in
+//! jerasure_matrix_decode and ec_encode_data.
+//!
+//! gf_gen_rs_matrix is not MDS for every k and m; ISA-L recommends the
+//! Cauchy matrix for general use, and matrix_decode reports a singular
+//! survivor set instead of producing garbage.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::{ Gf256, as_byte_slice, bytes };
+use crate::matrix::Matrix;
+
+/// Errors produced by the interop helpers
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum InteropError {
+	/// k must be positive and k + m at most 256
+	InvalidParameters,
+	/// A matrix has the wrong shape or an int entry is not a byte
+	InvalidMatrix,
+	/// The number of shards does not match the matrix
+	ShardCountMismatch,
+	/// Shards have different sizes
+	ShardSizeMismatch,
+	/// Fewer than k shards survived
+	TooFewShards,
+	/// The first k surviving rows of the encoding matrix are dependent
+	Singular,
+}
+
+impl fmt::Display for InteropError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			InteropError::InvalidParameters => "invalid code parameters",
+			InteropError::InvalidMatrix => "invalid coding matrix",
+			InteropError::ShardCountMismatch => "wrong number of shards",
+			InteropError::ShardSizeMismatch => "shards have different sizes",
+			InteropError::TooFewShards => "too few shards to decode",
+			InteropError::Singular => "surviving rows are not independent",
+		})
+	}
+}
+
+impl error::Error for InteropError {}
+
+fn check(k: usize, m: usize) -> Result<(), InteropError> {
+	if k == 0 || k + m > 256 {
+		return Err(InteropError::InvalidParameters);
+	}
+	Ok(())
+}
+
+#[inline]
+fn el(i: usize) -> Gf256 {
+	Gf256::from_byte(i as u8)
+}
+
+/// The matrix of reed_sol_vandermonde_coding_matrix(k, m, 8): m x k,
+/// first row and first column all ones
+pub fn jerasure_vandermonde(k: usize, m: usize) -> Result<Matrix, InteropError> {
+	check(k, m)?;
+	if m == 0 {
+		return Ok(Matrix::zero(0, k));
+	}
+	let rows = k + m;
+	// reed_sol_extended_vandermonde_matrix: rows 1 to rows - 2 hold the
+	// powers of their index, the first and last rows are unit vectors
+	let mut d = Matrix::zero(rows, k);
+	d[(0, 0)] = Gf256::one();
+	d[(rows - 1, k - 1)] = Gf256::one();
+	for i in 1..rows - 1 {
+		let mut acc = Gf256::one();
+		for j in 0..k {
+			d[(i, j)] = acc;
+			acc *= el(i);
+		}
+	}
+
+	// reed_sol_big_vandermonde_distribution_matrix: column operations
+	// until the top is the identity
+	for i in 1..k {
+		let pivot = (i..rows).find(|&r| d[(r, i)] != Gf256::zero()).expect("Vandermonde rows are independent");
+		if pivot != i {
+			for j in 0..k {
+				let t = d[(pivot, j)];
+				d[(pivot, j)] = d[(i, j)];
+				d[(i, j)] = t;
+			}
+		}
+		if d[(i, i)] != Gf256::one() {
+			let s = d[(i, i)].inv().expect("Nonzero");
+			for r in 0..rows {
+				d[(r, i)] *= s;
+			}
+		}
+		for j in 0..k {
+			let e = d[(i, j)];
+			if j != i && e != Gf256::zero() {
+				for r in 0..rows {
+					let v = d[(r, i)];
+					d[(r, j)] += e * v;
+				}
+			}
+		}
+	}
+	// Row k all ones by scaling the coding part of each column, then
+	// column 0 all ones by scaling the later rows
+	for j in 0..k {
+		let s = d[(k, j)].inv().expect("Nonzero");
+		for r in k..rows {
+			d[(r, j)] *= s;
+		}
+	}
+	for r in k + 1..rows {
+		let s = d[(r, 0)].inv().expect("Nonzero");
+		for a in d.row_mut(r) {
+			*a *= s;
+		}
+	}
+	Ok(Matrix::from_slice(m, k, &d.as_slice()[k * k..]).expect("Sizes match"))
+}
+
+/// The matrix of reed_sol_r6_coding_matrix(k, 8): all ones, then the
+/// powers of 2
+pub fn jerasure_r6(k: usize) -> Result<Matrix, InteropError> {
+	check(k, 2)?;
+	let mut c = Matrix::zero(2, k);
+	for j in 0..k {
+		c[(0, j)] = Gf256::one();
+		c[(1, j)] = Gf256::GENERATOR.pow(j as u64);
+	}
+	Ok(c)
+}
+
+/// The matrix of cauchy_original_coding_matrix(k, m, 8): entry (i, j) is
+/// 1 / (i + (m + j))
+pub fn jerasure_cauchy(k: usize, m: usize) -> Result<Matrix, InteropError> {
+	check(k, m)?;
+	let xs: Vec<Gf256> = (0..m).map(el).collect();
+	let ys: Vec<Gf256> = (m..m + k).map(el).collect();
+	Ok(Matrix::cauchy(&xs, &ys).expect("Points are distinct"))
+}
+
+/// The ints Jerasure takes as its coding matrix
+pub fn to_jerasure(coding: &Matrix) -> Vec<i32> {
+	coding.as_slice().iter().map(|a| a.to_byte() as i32).collect()
+}
+
+/// Reads an m x k Jerasure coding matrix
+pub fn from_jerasure(m: usize, k: usize, matrix: &[i32]) -> Result<Matrix, InteropError> {
+	let bytes: Option<Vec<u8>> = matrix.iter().map(|&a| u8::try_from(a).ok()).collect();
+	let bytes = bytes.ok_or(InteropError::InvalidMatrix)?;
+	Matrix::from_bytes(m, k, &bytes).map_err(|_| InteropError::InvalidMatrix)
+}
+
+/// A (k + m) x k zero matrix with the identity on top
+fn identity_top(k: usize, m: usize) -> Matrix {
+	let mut a = Matrix::zero(k + m, k);
+	for i in 0..k {
+		a[(i, i)] = Gf256::one();
+	}
+	a
+}
+
+/// The encoding matrix of gf_gen_rs_matrix(a, k + m, k): the identity,
+/// then rows of the powers of 1, 2, 4, ...
+pub fn isal_rs(k: usize, m: usize) -> Result<Matrix, InteropError> {
+	check(k, m)?;
+	let mut a = identity_top(k, m);
+	let mut g = Gf256::one();
+	for i in k..k + m {
+		let mut p = Gf256::one();
+		for j in 0..k {
+			a[(i, j)] = p;
+			p *= g;
+		}
+		g *= Gf256::GENERATOR;
+	}
+	Ok(a)
+}
+
+/// The encoding matrix of gf_gen_cauchy1_matrix(a, k + m, k): the
+/// identity, then entry (i, j) is 1 / (i + j)
+pub fn isal_cauchy(k: usize, m: usize) -> Result<Matrix, InteropError> {
+	check(k, m)?;
+	let mut a = identity_top(k, m);
+	for i in k..k + m {
+		for j in 0..k {
+			a[(i, j)] = (el(i) + el(j)).inv().expect("Nonzero");
+		}
+	}
+	Ok(a)
+}
+
+/// The bytes ISA-L takes as its encoding matrix
+pub fn to_isal(encoding: &Matrix) -> Vec<u8> {
+	as_byte_slice(encoding.as_slice()).to_vec()
+}
+
+/// Reads a (k + m) x k ISA-L encoding matrix; the top k rows are
+/// expected to be the identity
+pub fn from_isal(k: usize, m: usize, a: &[u8]) -> Result<Matrix, InteropError> {
+	let a = Matrix::from_bytes(k + m, k, a).map_err(|_| InteropError::InvalidMatrix)?;
+	if a.as_slice()[..k * k] != *Matrix::identity(k).as_slice() {
+		return Err(InteropError::InvalidMatrix);
+	}
+	Ok(Matrix::from_slice(m, k, &a.as_slice()[k * k..]).expect("Sizes match"))
+}
+
+/// The tables ec_init_tables(k, m, coding, g_tbls) builds for an m x k
+/// coding matrix, 32 bytes per entry
+pub fn isal_tables(coding: &Matrix) -> Vec<u8> {
+	let mut t = Vec::with_capacity(coding.rows() * coding.cols() * 32);
+	for &c in coding.as_slice() {
+		t.extend((0..16).map(|i| (c * el(i)).to_byte()));
+		t.extend((0..16).map(|i| (c * el(i << 4)).to_byte()));
+	}
+	t
+}
+
+/// ec_encode_data: computes the coding shards from the data shards with
+/// tables from isal_tables, one nibble lookup per byte
+pub fn isal_encode(tables: &[u8], data: &[&[u8]], coding: &mut [&mut [u8]]) -> Result<(), InteropError> {
+	let k = data.len();
+	if k == 0 {
+		return Err(InteropError::InvalidParameters);
+	}
+	if tables.len() != coding.len() * k * 32 {
+		return Err(InteropError::InvalidMatrix);
+	}
+	let size = check_sizes(data, coding)?;
+	for (out, rows) in coding.iter_mut().zip(tables.chunks(k * 32)) {
+		out[..size].fill(0);
+		for (input, t) in data.iter().zip(rows.chunks(32)) {
+			for (o, &x) in out.iter_mut().zip(input.iter()) {
+				*o ^= t[(x & 15) as usize] ^ t[16 + (x >> 4) as usize];
+			}
+		}
+	}
+	Ok(())
+}
+
+fn check_sizes(data: &[&[u8]], coding: &[&mut [u8]]) -> Result<usize, InteropError> {
+	let size = data.first().map_or(0, |d| d.len());
+	if data.iter().any(|d| d.len() != size) || coding.iter().any(|c| c.len() != size) {
+		return Err(InteropError::ShardSizeMismatch);
+	}
+	Ok(size)
+}
+
+/// jerasure_matrix_encode: coding shard i is row i of the m x k coding
+/// matrix applied to the data shards
+pub fn matrix_encode(coding: &Matrix, data: &[&[u8]], out: &mut [&mut [u8]]) -> Result<(), InteropError> {
+	if data.len() != coding.cols() || out.len() != coding.rows() {
+		return Err(InteropError::ShardCountMismatch);
+	}
+	check_sizes(data, out)?;
+	for (i, o) in out.iter_mut().enumerate() {
+		o.fill(0);
+		for (input, c) in data.iter().zip(coding.row(i)) {
+			bytes::mul_add(o, input, c.to_byte());
+		}
+	}
+	Ok(())
+}
+
+/// jerasure_matrix_decode: fills in the missing shards, data and coding,
+/// from the first k survivors
+pub fn matrix_decode(coding: &Matrix, shards: &mut [Option<Vec<u8>>]) -> Result<(), InteropError> {
+	let (k, m) = (coding.cols(), coding.rows());
+	if shards.len() != k + m {
+		return Err(InteropError::ShardCountMismatch);
+	}
+	let survivors: Vec<usize> = (0..k + m).filter(|&i| shards[i].is_some()).take(k).collect();
+	if survivors.len() < k {
+		return Err(InteropError::TooFewShards);
+	}
+	let size = shards[survivors[0]].as_ref().expect("Survivor").len();
+	if shards.iter().flatten().any(|s| s.len() != size) {
+		return Err(InteropError::ShardSizeMismatch);
+	}
+
+	if (0..k).any(|i| shards[i].is_none()) {
+		let row = |i: usize| if i < k { Matrix::identity(k).row(i).to_vec() } else { coding.row(i - k).to_vec() };
+		let rows: Vec<Vec<Gf256>> = survivors.iter().map(|&i| row(i)).collect();
+		let inv = Matrix::from_rows(&rows).expect("Rows have k entries").inverse().ok_or(InteropError::Singular)?;
+		for i in 0..k {
+			if shards[i].is_some() {
+				continue;
+			}
+			let mut out = vec![0u8; size];
+			for (&s, c) in survivors.iter().zip(inv.row(i)) {
+				bytes::mul_add(&mut out, shards[s].as_ref().expect("Survivor"), c.to_byte());
+			}
+			shards[i] = Some(out);
+		}
+	}
+	for i in 0..m {
+		if shards[k + i].is_none() {
+			let mut out = vec![0u8; size];
+			for (j, c) in coding.row(i).iter().enumerate() {
+				bytes::mul_add(&mut out, shards[j].as_ref().expect("Decoded"), c.to_byte());
+			}
+			shards[k + i] = Some(out);
+		}
+	}
+	Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes_of(m: &Matrix) -> Vec<u8> {
+        as_byte_slice(m.as_slice()).to_vec()
+    }
+
+    #[test]
+    fn test_jerasure_matrices() {
+        // Hand evaluation of the distribution matrix for k = m = 2
+        assert_eq!(bytes_of(&jerasure_vandermonde(2, 2).unwrap()), [1, 1, 1, 0x8f]);
+        for (k, m) in [(3, 3), (6, 3), (10, 4), (200, 56)] {
+            let c = jerasure_vandermonde(k, m).unwrap();
+            assert!(c.row(0).iter().all(|&a| a == Gf256::one()));
+            assert!((0..m).all(|i| c[(i, 0)] == Gf256::one()));
+            assert_eq!(from_jerasure(m, k, &to_jerasure(&c)).unwrap(), c);
+        }
+        let r6 = jerasure_r6(4).unwrap();
+        assert_eq!(bytes_of(&r6), [1, 1, 1, 1, 1, 2, 4, 8]);
+        // 1 / (0 + 2), 1 / (0 + 3), 1 / (1 + 2), 1 / (1 + 3)
+        let c = jerasure_cauchy(2, 2).unwrap();
+        assert_eq!(bytes_of(&c), [0x8e, 0xf4, 0xf4, 0x8e]);
+        assert_eq!(jerasure_vandermonde(200, 57), Err(InteropError::InvalidParameters));
+        // No coding shards
+        for k in [3, 1] {
+            assert_eq!(jerasure_vandermonde(k, 0), Ok(Matrix::zero(0, k)));
+            assert_eq!(jerasure_cauchy(k, 0), Ok(Matrix::zero(0, k)));
+            assert_eq!(isal_rs(k, 0), Ok(Matrix::identity(k)));
+        }
+        assert_eq!(from_jerasure(1, 2, &[1, 256]), Err(InteropError::InvalidMatrix));
+    }
+
+    #[test]
+    fn test_isal_matrices() {
+        let a = isal_rs(4, 3).unwrap();
+        assert_eq!(to_isal(&a)[16..], [1, 1, 1, 1, 1, 2, 4, 8, 1, 4, 16, 64]);
+        let c = isal_cauchy(4, 2).unwrap();
+        // 1 / 4 is 0x47 and 1 / 5 is 0xa7
+        assert_eq!(to_isal(&c)[16..18], [0x47, 0xa7]);
+        let coding = from_isal(4, 2, &to_isal(&c)).unwrap();
+        assert_eq!(coding.row(1)[0], Gf256::from_byte(5).inv().expect("Nonzero"));
+        let mut bad = to_isal(&c);
+        bad[1] = 1;
+        assert_eq!(from_isal(4, 2, &bad), Err(InteropError::InvalidMatrix));
+
+        // The tables of 0x1d: products with 0..15, then with 0x00..0xf0
+        let t = isal_tables(&Matrix::from_bytes(1, 1, &[0x1d]).unwrap());
+        assert_eq!(t[..4], [0, 0x1d, 0x3a, 0x27]);
+        assert_eq!(t[16 + 1], (Gf256::from_byte(0x1d) * Gf256::from_byte(0x10)).to_byte());
+    }
+
+    // Line by line transcriptions of the C sources, Jerasure 2.0
+    // reed_sol.c and ISA-L erasure_code/ec_base.c, with the field
+    // arithmetic of galois.c (w = 8, prim_poly 0435) done bit by bit. No
+    // output of either library is available to compare with, so these
+    // pin the matrices and parity to what the C code computes instead.
+
+    fn galois_single_multiply(a: i32, b: i32) -> i32 {
+        let (mut a, mut b, mut p) = (a, b, 0);
+        while b != 0 {
+            if b & 1 != 0 {
+                p ^= a;
+            }
+            a <<= 1;
+            if a & 0x100 != 0 {
+                a ^= 0o435;
+            }
+            b >>= 1;
+        }
+        p
+    }
+
+    fn galois_single_divide(a: i32, b: i32) -> i32 {
+        let inv = (1..256).find(|&x| galois_single_multiply(b, x) == 1).unwrap();
+        galois_single_multiply(a, inv)
+    }
+
+    fn reed_sol_extended_vandermonde_matrix(rows: usize, cols: usize) -> Vec<i32> {
+        let mut vdm = vec![0i32; rows * cols];
+        vdm[0] = 1;
+        let i = (rows - 1) * cols;
+        vdm[i + cols - 1] = 1;
+        for i in 1..rows - 1 {
+            let mut k = 1;
+            for j in 0..cols {
+                vdm[i * cols + j] = k;
+                k = galois_single_multiply(k, i as i32);
+            }
+        }
+        vdm
+    }
+
+    fn reed_sol_big_vandermonde_distribution_matrix(rows: usize, cols: usize) -> Vec<i32> {
+        let mut dist = reed_sol_extended_vandermonde_matrix(rows, cols);
+        let mut sindex = 0;
+        for i in 1..cols {
+            sindex += cols;
+            let mut srindex = sindex + i;
+            let mut j = i;
+            while j < rows && dist[srindex] == 0 {
+                srindex += cols;
+                j += 1;
+            }
+            assert!(j < rows);
+            if j != i {
+                srindex -= i;
+                for k in 0..cols {
+                    dist.swap(srindex + k, sindex + k);
+                }
+            }
+            if dist[sindex + i] != 1 {
+                let tmp = galois_single_divide(1, dist[sindex + i]);
+                let mut srindex = i;
+                for _ in 0..rows {
+                    dist[srindex] = galois_single_multiply(tmp, dist[srindex]);
+                    srindex += cols;
+                }
+            }
+            for j in 0..cols {
+                let tmp = dist[sindex + j];
+                if j != i && tmp != 0 {
+                    let (mut srindex, mut siindex) = (j, i);
+                    for _ in 0..rows {
+                        dist[srindex] ^= galois_single_multiply(tmp, dist[siindex]);
+                        srindex += cols;
+                        siindex += cols;
+                    }
+                }
+            }
+        }
+        for sindex in cols * cols..cols * cols + cols {
+            let tmp = dist[sindex];
+            if tmp != 1 {
+                let tmp = galois_single_divide(1, tmp);
+                let mut srindex = sindex;
+                for _ in cols..rows {
+                    dist[srindex] = galois_single_multiply(tmp, dist[srindex]);
+                    srindex += cols;
+                }
+            }
+        }
+        let mut sindex = cols * (cols + 1);
+        for _ in cols + 1..rows {
+            let tmp = dist[sindex];
+            if tmp != 1 {
+                let tmp = galois_single_divide(1, tmp);
+                for j in 0..cols {
+                    dist[sindex + j] = galois_single_multiply(dist[sindex + j], tmp);
+                }
+            }
+            sindex += cols;
+        }
+        dist
+    }
+
+    fn reed_sol_vandermonde_coding_matrix(k: usize, m: usize) -> Vec<i32> {
+        reed_sol_big_vandermonde_distribution_matrix(k + m, k)[k * k..].to_vec()
+    }
+
+    fn gf_mul(a: u8, b: u8) -> u8 {
+        galois_single_multiply(a as i32, b as i32) as u8
+    }
+
+    fn gf_inv(a: u8) -> u8 {
+        galois_single_divide(1, a as i32) as u8
+    }
+
+    fn gf_gen_rs_matrix(m: usize, k: usize) -> Vec<u8> {
+        let mut a = vec![0u8; k * m];
+        for i in 0..k {
+            a[k * i + i] = 1;
+        }
+        let mut gen = 1;
+        for i in k..m {
+            let mut p = 1;
+            for j in 0..k {
+                a[k * i + j] = p;
+                p = gf_mul(p, gen);
+            }
+            gen = gf_mul(gen, 2);
+        }
+        a
+    }
+
+    fn gf_gen_cauchy1_matrix(m: usize, k: usize) -> Vec<u8> {
+        let mut a = vec![0u8; k * m];
+        for i in 0..k {
+            a[k * i + i] = 1;
+        }
+        let mut p = k * k;
+        for i in k..m {
+            for j in 0..k {
+                a[p] = gf_inv((i ^ j) as u8);
+                p += 1;
+            }
+        }
+        a
+    }
+
+    /// ec_encode_data_base, which reads the coefficient back from entry 1
+    /// of its table
+    fn ec_encode_data_base(len: usize, srcs: usize, dests: usize, v: &[u8], src: &[&[u8]], dest: &mut [Vec<u8>]) {
+        for l in 0..dests {
+            for i in 0..len {
+                let mut s = 0;
+                for j in 0..srcs {
+                    s ^= gf_mul(src[j][i], v[j * 32 + l * srcs * 32 + 1]);
+                }
+                dest[l][i] = s;
+            }
+        }
+    }
+
+    #[test]
+    fn test_c_transcriptions() {
+        for (k, m) in [(2, 2), (3, 3), (4, 2), (6, 3), (10, 4), (12, 12), (17, 5), (100, 100), (255, 1)] {
+            let c = jerasure_vandermonde(k, m).unwrap();
+            assert_eq!(to_jerasure(&c), reed_sol_vandermonde_coding_matrix(k, m), "{} {}", k, m);
+            assert_eq!(to_isal(&isal_rs(k, m).unwrap()), gf_gen_rs_matrix(k + m, k), "{} {}", k, m);
+            assert_eq!(to_isal(&isal_cauchy(k, m).unwrap()), gf_gen_cauchy1_matrix(k + m, k), "{} {}", k, m);
+        }
+
+        // Parity of ec_encode_data_base from the tables of ec_init_tables
+        let (k, m) = (6, 3);
+        let data: Vec<Vec<u8>> = (0..k).map(|i| (0..50).map(|j| (i * 97 + j * 13 + 1) as u8).collect()).collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        for encoding in [gf_gen_rs_matrix(k + m, k), gf_gen_cauchy1_matrix(k + m, k)] {
+            let coding = from_isal(k, m, &encoding).unwrap();
+            let tables = isal_tables(&coding);
+            let mut expected = vec![vec![0u8; 50]; m];
+            ec_encode_data_base(50, k, m, &tables, &refs, &mut expected);
+            let mut parity = vec![vec![0u8; 50]; m];
+            let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+            isal_encode(&tables, &refs, &mut out).unwrap();
+            assert_eq!(parity, expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let data: Vec<Vec<u8>> = (0..5).map(|i| (0..32).map(|j| (i * 41 + j * 3) as u8).collect()).collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let coding = from_isal(5, 3, &to_isal(&isal_cauchy(5, 3).unwrap())).unwrap();
+
+        let mut parity = vec![vec![0u8; 32]; 3];
+        let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+        matrix_encode(&coding, &refs, &mut out).unwrap();
+        let mut tabled = vec![vec![0u8; 32]; 3];
+        let mut out: Vec<&mut [u8]> = tabled.iter_mut().map(|p| &mut p[..]).collect();
+        isal_encode(&isal_tables(&coding), &refs, &mut out).unwrap();
+        assert_eq!(isal_encode(&[], &[], &mut out), Err(InteropError::InvalidParameters));
+        assert_eq!(tabled, parity);
+
+        let all: Vec<Vec<u8>> = data.iter().chain(&parity).cloned().collect();
+        let mut shards: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        shards[1] = None;
+        shards[3] = None;
+        shards[6] = None;
+        matrix_decode(&coding, &mut shards).unwrap();
+        assert!(shards.iter().zip(&all).all(|(s, a)| s.as_ref() == Some(a)));
+        shards[0] = None;
+        shards[1] = None;
+        shards[2] = None;
+        shards[3] = None;
+        assert_eq!(matrix_decode(&coding, &mut shards), Err(InteropError::TooFewShards));
+    }
+}
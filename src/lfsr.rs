@@ -0,0 +1,128 @@
+//! Linear feedback shift registers in Galois form.
+//!
+//! The register holds a polynomial of degree below n and every step
+//! multiplies it by x modulo the feedback polynomial, exactly like xtimes
+//! does for Gf256: the bit shifted out at the top is the output bit, and
+//! when it is set the feedback taps are xored in. With a primitive
+//! feedback polynomial (see gf2poly::is_primitive) every nonzero state
+//! runs through all 2^n - 1 states before repeating.
+//!
+//! scramble xors the output bytes into a buffer. This is additive
+//! scrambling as in DVB or IEEE 802.11: running it again from the same
+//! seed restores the data. The Fibonacci form used by those standards
+//! (dvb::Randomizer) gives the same sequence from a different seed.
+
+/// A Galois LFSR, see the module documentation
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Lfsr {
+	/// The feedback polynomial including the x^n term
+	poly: u64,
+	degree: u32,
+	state: u64,
+}
+
+impl Lfsr {
+	/// A register with feedback polynomial poly (e.g. 0x11d for x^8 + x^4 +
+	/// x^3 + x^2 + 1) and the seed as initial state. Panics unless poly
+	/// has a degree from 1 to 63 and the seed is nonzero and of lower
+	/// degree.
+	pub const fn new(poly: u64, seed: u64) -> Lfsr {
+		assert!(poly > 1, "The feedback polynomial needs a degree of at least 1");
+		let degree = 63 - poly.leading_zeros();
+		assert!(seed != 0 && seed >> degree == 0, "The seed must be nonzero and below x^degree");
+		Lfsr { poly, degree, state: seed }
+	}
+
+	pub const fn degree(&self) -> u32 {
+		self.degree
+	}
+
+	/// The current state, a polynomial of degree below degree()
+	pub const fn state(&self) -> u64 {
+		self.state
+	}
+
+	/// Multiplies the state by x and returns the bit shifted out
+	#[inline]
+	pub fn next_bit(&mut self) -> u8 {
+		let out = self.state >> (self.degree - 1) & 1;
+		// The x^n term of poly clears the bit shifted out
+		self.state = (self.state << 1) ^ (self.poly & 0u64.wrapping_sub(out));
+		out as u8
+	}
+
+	/// The next 8 output bits, the first in the most significant bit
+	pub fn next_byte(&mut self) -> u8 {
+		(0..8).fold(0, |byte, _| byte << 1 | self.next_bit())
+	}
+
+	/// Overwrites buf with output bytes
+	pub fn fill(&mut self, buf: &mut [u8]) {
+		for b in buf.iter_mut() {
+			*b = self.next_byte();
+		}
+	}
+
+	/// Xors output bytes into buf. The same call runs the other way.
+	pub fn scramble(&mut self, buf: &mut [u8]) {
+		for b in buf.iter_mut() {
+			*b ^= self.next_byte();
+		}
+	}
+}
+
+/// The output bytes, without end
+impl Iterator for Lfsr {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		Some(self.next_byte())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+
+    #[test]
+    fn test_matches_field() {
+        let mut lfsr = Lfsr::new(0x11d, 1);
+        let mut x = Gf256::one();
+        for _ in 0..300 {
+            assert_eq!(lfsr.state(), x.poly as u64);
+            lfsr.next_bit();
+            x *= Gf256::from_byte(2);
+        }
+    }
+
+    #[test]
+    fn test_period() {
+        for (poly, period) in [(0x13u64, 15usize), (0x11d, 255), (0xc001, 32767)] {
+            let mut lfsr = Lfsr::new(poly, 1);
+            let mut steps = 0;
+            loop {
+                lfsr.next_bit();
+                steps += 1;
+                if lfsr.state() == 1 {
+                    break;
+                }
+            }
+            assert_eq!(steps, period, "{:#x}", poly);
+        }
+    }
+
+    #[test]
+    fn test_scramble() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut buf = data.clone();
+        Lfsr::new(0x11d, 0x5a).scramble(&mut buf);
+        assert_ne!(buf, data);
+        Lfsr::new(0x11d, 0x5a).scramble(&mut buf);
+        assert_eq!(buf, data);
+        let mut stream = [0u8; 4];
+        Lfsr::new(0x13, 8).fill(&mut stream);
+        assert_eq!(Lfsr::new(0x13, 8).take(4).collect::<Vec<u8>>(), stream);
+    }
+}
@@ -3,91 +3,256 @@
 //! Based on 
 //! https://github.com/sellibitze/secretshare/blob/
 //!     master/src/gf256.rs
+//!
+//! The crate comes in three tiers, selected by features:
+//!
+//! - without features: the field arithmetic, tables and contexts, the
+//!   FiniteField trait over all field types, normal and dual bases,
+//!   hashing to field elements, RsCodec with presets of standard codes and
+//!   the CCSDS code, shamir, constant time arithmetic, masking and
+//!   verification. No allocator is needed.
+//! - `alloc`: polynomials, matrices and vectors over the field, GF(2)
+//!   polynomials of any degree, the cyclic, BCH, Goppa and MSR codes, RS
+//!   product codes, Shamir sharing over GF(2^16), QR code error
+//!   correction, PAR2 recovery slices, Jerasure and ISA-L coding
+//!   matrices, random linear network coding and the rest of what only
+//!   needs heap buffers.
+//! - `std` (default): erasure coding and everything built on it, LT
+//!   fountain codes, packet recovery, test vectors and the command line
+//!   tool.
+//!
+//! `parallel` adds multithreaded bulk operations and erasure coding.
+//! Independently of the tier, `mul-table` makes multiplication a single
+//! lookup in a 64 KiB table of all products.
 
-use std::num::Wrapping;
-use std::ops::{ Add, Sub, Mul, Div };
-use std::sync::{ Once, ONCE_INIT };
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::fmt;
+use core::error;
+use core::iter::{ Sum, Product };
+use core::num::IntErrorKind;
+use core::str::FromStr;
+use core::ops::{ Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, MulAssign, DivAssign };
+
+pub mod aes;
+pub mod backend;
+pub mod basis;
+pub mod bitslice;
+pub mod bytes;
+pub mod clmul;
+pub mod crc;
+pub mod ct;
+pub mod ctx;
+pub mod field;
+pub mod gf2poly;
+pub mod hash;
+pub mod log;
+pub mod lfsr;
+pub mod masked;
+pub mod modular;
+pub mod nonzero;
+pub mod pow;
+pub mod raid6;
+pub mod rs;
+pub mod shamir;
+pub mod small;
+pub mod tables;
+pub mod tower;
+pub mod trace;
+pub mod verify;
+pub mod wide;
+
+#[cfg(feature = "alloc")]
+pub mod bch;
+#[cfg(feature = "alloc")]
+pub mod bitpoly;
+#[cfg(feature = "alloc")]
+pub mod corrupt;
+#[cfg(feature = "alloc")]
+pub mod cyclic;
+#[cfg(feature = "alloc")]
+pub mod dft;
+#[cfg(feature = "alloc")]
+pub mod dvb;
+#[cfg(feature = "alloc")]
+pub mod goppa;
+#[cfg(feature = "alloc")]
+pub mod interleave;
+#[cfg(feature = "alloc")]
+pub mod interop;
+#[cfg(feature = "alloc")]
+pub mod matrix;
+#[cfg(feature = "alloc")]
+pub mod msr;
+#[cfg(feature = "alloc")]
+pub mod par2;
+#[cfg(feature = "alloc")]
+pub mod poly;
+#[cfg(feature = "alloc")]
+pub mod qr;
+#[cfg(feature = "alloc")]
+pub mod rlnc;
+#[cfg(feature = "alloc")]
+pub mod uep;
+#[cfg(feature = "alloc")]
+pub mod vec;
+#[cfg(feature = "alloc")]
+pub mod weight;
+
+#[cfg(feature = "columnar")]
+pub mod columnar;
+#[cfg(feature = "std")]
+pub mod fountain;
+#[cfg(feature = "std")]
+pub mod packet;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod recovery;
+#[cfg(feature = "std")]
+pub mod shares;
+#[cfg(feature = "std")]
+pub mod square;
+#[cfg(feature = "std")]
+pub mod vectors;
+
+use ctx::Tables;
+pub use log::GfLog;
+pub use nonzero::NonZeroGf256;
 
 const POLY: u8 = 0x1D; // represents x^8 + x^4 + x^3 + x^2 + 1
 
+/// The reduction polynomial x^8 + x^4 + x^3 + x^2 + 1 of Gf256, with
+/// the x^8 term
+pub const POLYNOMIAL: u16 = 0x100 | POLY as u16;
+
+const fn gcd(mut a: u32, mut b: u32) -> u32 {
+	while b != 0 {
+		let t = a % b;
+		a = b;
+		b = t;
+	}
+	a
+}
+
 /// replicates the least significant bit to every other bit
 #[inline]
-fn mask(bit: u8) -> u8 {
-    (Wrapping(0u8) - Wrapping(bit & 1)).0
+const fn mask(bit: u8) -> u8 {
+    0u8.wrapping_sub(bit & 1)
 }
 
 /// multiplies a polynomial with x and returns the residual
 /// of the polynomial division with POLY as divisor
 #[inline]
-fn xtimes(poly: u8) -> u8 {
+const fn xtimes(poly: u8) -> u8 {
 	(poly << 1) ^ (mask(poly >> 7) & POLY)
 }
 
-/// Tables used for multiplication and division
-struct Tables {
-	exp: [u8; 256],
-	log: [u8; 256],
-	inv: [u8; 256]
-}
+/// Computed at compile time: no lazy initialization, and the arithmetic
+/// on Gf256 can be const fn
+static TABLES: Tables = tables::tables(POLYNOMIAL, 0x02);
 
-static INIT: Once = ONCE_INIT;
-static mut TABLES: Tables = Tables {
-	exp: [0; 256],
-	log: [0; 256],
-	inv: [0; 256]
+/// All products, for the mul-table feature. One load per multiplication
+/// instead of two log lookups, a sum and an exp lookup, at the price of
+/// 64 KiB of memory and cache.
+#[cfg(feature = "mul-table")]
+static MUL_TABLE: [[u8; 256]; 256] = tables::mul_table(POLYNOMIAL);
+
+/// The exp table twice over, so that multiplication and division need no
+/// reduction mod 255
+static EXP2: [u8; 512] = tables::exp_table_doubled(POLYNOMIAL, 0x02);
+
+/// SQUARES[i] = (x^i)^2. Squaring is linear in characteristic 2, so these
+/// eight values determine it.
+const SQUARES: [u8; 8] = {
+	let mut out = [0u8; 8];
+	let mut x = 1u8;
+	let mut i = 0;
+	while i < 8 {
+		out[i] = x;
+		x = xtimes(xtimes(x));
+		i += 1;
+	}
+	out
 };
 
-fn get_tables() -> &'static Tables {
-	INIT.call_once(|| {
-		// mutable access is fine because of synchronization via INIT
-		let tabs = unsafe { &mut TABLES };
-		let mut tmp = 1;
-		for power in 0..255usize {
-			tabs.exp[power] = tmp;
-			tabs.log[tmp as usize] = power as u8;
-			tmp = xtimes(tmp);
+/// The bits i for which x^i has trace 1. The trace is linear as well, so
+/// trace(a) is the parity of a & TRACE_MASK.
+const TRACE_MASK: u8 = {
+	let mut out = 0u8;
+	let mut i = 0;
+	while i < 8 {
+		let mut c = Gf256::from_byte(1 << i);
+		let mut sum = 0u8;
+		let mut j = 0;
+		while j < 8 {
+			sum ^= c.poly;
+			c = c.square();
+			j += 1;
 		}
-		tabs.exp[255] = 1;
-		for x in 1..256usize {
-			let l = tabs.log[x];
-			let nl = if l == 0 { 0 } else { 255 - l };
-			let i = tabs.exp[nl as usize];
-			tabs.inv[x] = i;
-		}
-	});
-	// We're guaranteed to have TABLES initialized by now
-	return unsafe { &TABLES };
+		// The sum of the conjugates is 0 or 1
+		out |= sum << i;
+		i += 1;
+	}
+	out
+};
+
+#[inline]
+const fn get_tables() -> &'static Tables {
+	&TABLES
 }
 
 /// Type for elements of a finite field with 256 elements
-#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+///
+/// The representation is exactly one byte, so byte buffers can be viewed
+/// as slices of Gf256 and back without copying (see as_gf_slice and
+/// vec::GfSlice).
+///
+/// Ord compares the byte values. The field has no ordering compatible
+/// with its arithmetic; this one only serves sorting and ordered maps, e.g.
+/// of evaluation points.
+#[derive(Copy,Clone,PartialEq,Eq,PartialOrd,Ord,Hash,Debug)]
+#[repr(transparent)]
 pub struct Gf256 {
 	pub poly: u8
 }
 
 impl Gf256 {
+	pub const ZERO: Gf256 = Gf256 { poly: 0 };
+	pub const ONE: Gf256 = Gf256 { poly: 1 };
+	/// x, the generator all logarithms and exponentials refer to
+	pub const GENERATOR: Gf256 = Gf256 { poly: 2 };
+
 	/// returns the additive neutral element of the field
 	#[inline]
-	pub fn zero() -> Gf256 {
+	pub const fn zero() -> Gf256 {
 		Gf256 { poly: 0 }
 	}
 	/// returns the multiplicative neutral element of the field
 	#[inline]
-	pub fn one() -> Gf256 {
+	pub const fn one() -> Gf256 {
 		Gf256 { poly: 1 }
 	}
+	/// The element with the bits of b, like from_byte
 	#[inline]
-	pub fn from_byte(b: u8) -> Gf256 {
+	pub const fn new(b: u8) -> Gf256 {
 		Gf256 { poly: b }
 	}
 	#[inline]
-	pub fn to_byte(&self) -> u8 {
+	pub const fn from_byte(b: u8) -> Gf256 {
+		Gf256 { poly: b }
+	}
+	#[inline]
+	pub const fn to_byte(&self) -> u8 {
 		self.poly
 	}
 
     /// Find the logrithm of self in base x.
     /// In other words, find a value i such that x ^ i == self
-	pub fn log(&self) -> Option<u8> {
+	pub const fn log(&self) -> Option<u8> {
 		if self.poly == 0 {
 			None
 		} else {
@@ -96,14 +261,62 @@ impl Gf256 {
 		}
 	}
 
+	/// Find the smallest i with base ^ i == self, e.g. for codecs built
+	/// on another generator than x. Unless base is a generator, only
+	/// the powers of base (the subgroup of order base.order()) have a
+	/// logarithm; for everything else, and for zero, this returns None.
+	pub const fn log_base(&self, base: Gf256) -> Option<u8> {
+		let (s, b) = match (self.log(), base.log()) {
+			(Some(s), Some(b)) => (s as u16, b as u16),
+			_ => return None,
+		};
+		// Solve i * b = s mod 255; i < order(base) suffices
+		let n = match base.order() {
+			Some(n) => n as u16,
+			None => return None,
+		};
+		let mut i = 0;
+		while i < n {
+			if i * b % 255 == s {
+				return Some(i as u8);
+			}
+			i += 1;
+		}
+		None
+	}
+
+	/// The logarithm in base generator, for log conventions other than x.
+	/// None for zero and unless generator generates the multiplicative
+	/// group; see log_base for other bases.
+	pub const fn log_wrt(&self, generator: Gf256) -> Option<u8> {
+		let (s, g) = match (self.log(), generator.log()) {
+			(Some(s), Some(g)) => (s as u32, g as u32),
+			_ => return None,
+		};
+		// generator = x^g, so self = generator^(s / g) mod 255. g is
+		// invertible mod 255 exactly for generators; g^-1 = g^(phi(255) - 1).
+		if gcd(g, 255) != 1 {
+			return None;
+		}
+		let (mut inv, mut base, mut e) = (1u32, g, 127u32);
+		while e > 0 {
+			if e & 1 == 1 {
+				inv = inv * base % 255;
+			}
+			base = base * base % 255;
+			e >>= 1;
+		}
+		Some((s * inv % 255) as u8)
+	}
+
     /// Calculate x ^ power
-	pub fn xexp(power: u8) -> Gf256 {
+	pub const fn xexp(power: u8) -> Gf256 {
 		let tabs = get_tables();
 		Gf256 { poly: tabs.exp[power as usize] }
 	}
 
     /// Calculate self ^ power
-	pub fn exp(&self, power: u8) -> Gf256 {
+	pub const fn exp(&self, power: u8) -> Gf256 {
         match self.log() {
             None => Self::zero(),
             Some(i) => { 
@@ -113,15 +326,472 @@ impl Gf256 {
         }
 	}
 
-    /// Find the inverse of self: A number y such that self * y == 1
-	pub fn inv(&self) -> Option<Gf256> {
-		self.log().map(|l| Gf256::xexp(255 - l))
+	/// self ^ e for any exponent, reduced mod 255. Unlike exp, 0^0 is 1
+	/// (the empty product); 0^e is 0 for every other e.
+	pub const fn pow(&self, e: u64) -> Gf256 {
+		match self.log() {
+			None if e == 0 => Gf256::one(),
+			None => Gf256::zero(),
+			Some(l) => Gf256::xexp(((l as u64) * (e % 255) % 255) as u8),
+		}
+	}
+
+	/// self ^ e, where negative exponents are powers of the inverse.
+	/// Returns None for zero to a negative power, which has no value.
+	pub const fn pow_signed(&self, e: i64) -> Option<Gf256> {
+		if e >= 0 {
+			return Some(self.pow(e as u64));
+		}
+		match self.log() {
+			None => None,
+			// x^(-l |e|) = x^(l (255 - |e| mod 255))
+			Some(l) => {
+				let neg = 255 - e.unsigned_abs() % 255;
+				Some(Gf256::xexp(((l as u64) * neg % 255) as u8))
+			},
+		}
+	}
+
+    /// Find the inverse of self: A number y such that self * y == 1.
+    /// Returns None for zero, exactly when checked_div(one, self) does.
+	pub const fn inv(&self) -> Option<Gf256> {
+		match self.log() {
+			Some(l) => Some(Gf256::xexp(255 - l)),
+			None => None,
+		}
+	}
+
+	/// The multiplicative order: the smallest n > 0 with self^n == 1. It
+	/// divides 255; zero has none.
+	pub const fn order(&self) -> Option<u8> {
+		let l = match self.log() {
+			Some(l) => l,
+			None => return None,
+		};
+		// x^l has order 255 / gcd(l, 255)
+		let (mut a, mut b) = (l, 255u8);
+		while a != 0 {
+			(a, b) = (b % a, a);
+		}
+		Some(255 / b)
+	}
+
+	/// Whether self generates the multiplicative group, i.e. has order 255
+	pub const fn is_generator(&self) -> bool {
+		matches!(self.order(), Some(255))
+	}
+
+	/// All 256 elements in increasing byte order
+	pub fn all() -> impl ExactSizeIterator<Item = Gf256> + DoubleEndedIterator + Clone {
+		(0..=255u8).map(Gf256::from_byte)
+	}
+
+	/// The 255 nonzero elements in increasing byte order
+	pub fn nonzero() -> impl ExactSizeIterator<Item = Gf256> + DoubleEndedIterator + Clone {
+		(1..=255u8).map(Gf256::from_byte)
+	}
+
+	/// g^0, g^1, g^2, ... without end; the powers repeat after
+	/// g.order() steps
+	pub fn powers_of(g: Gf256) -> impl Iterator<Item = Gf256> + Clone {
+		core::iter::successors(Some(Gf256::one()), move |&x| Some(x * g))
+	}
+
+	/// The 128 generators of the multiplicative group in increasing byte
+	/// order, starting with 2
+	pub fn generators() -> impl Iterator<Item = Gf256> + Clone {
+		Gf256::nonzero().filter(Gf256::is_generator)
+	}
+
+	/// self * self, by the linear map instead of the log tables
+	pub const fn square(&self) -> Gf256 {
+		let mut acc = 0u8;
+		let mut i = 0;
+		while i < 8 {
+			acc ^= SQUARES[i] & mask(self.poly >> i);
+			i += 1;
+		}
+		Gf256 { poly: acc }
+	}
+
+	/// self^(2^k), the k-th power of the Frobenius map. It is the
+	/// identity for k = 8, so only k mod 8 matters.
+	pub const fn frobenius(&self, k: u32) -> Gf256 {
+		let mut a = *self;
+		let mut i = 0;
+		while i < k % 8 {
+			a = a.square();
+			i += 1;
+		}
+		a
+	}
+
+	/// Whether self lies in the subfield GF(16), i.e. self^16 == self.
+	/// tower::restrict gives the Gf16 element.
+	pub const fn is_in_subfield_gf16(&self) -> bool {
+		self.frobenius(4).poly == self.poly
+	}
+
+	/// The square root. Squaring is a bijection in characteristic 2, so
+	/// every element has exactly one: a^128, since (a^128)^2 = a^256 = a.
+	pub const fn sqrt(&self) -> Gf256 {
+		self.frobenius(7)
+	}
+
+	/// The distinct conjugates self, self^2, self^4, ... in that order.
+	/// Their number is the degree of the minimal polynomial.
+	pub fn conjugates(&self) -> impl Iterator<Item = Gf256> + Clone {
+		let a = *self;
+		core::iter::successors(Some(a), move |c| Some(c.square()).filter(|&s| s != a))
+	}
+
+	/// The monic polynomial over GF(2) of least degree with self as a
+	/// root, packed as in gf2poly (bit i is the coefficient of x^i). It is
+	/// the product of x - c over the distinct conjugates c = self^(2^k),
+	/// so its degree divides 8; zero gives x.
+	pub const fn minimal_polynomial(&self) -> u32 {
+		// coeffs[i] of x^i, times x - c for every conjugate c in turn
+		let mut coeffs = [Gf256::ZERO; 9];
+		coeffs[0] = Gf256::ONE;
+		let mut deg = 0;
+		let mut c = *self;
+		loop {
+			let mut i = deg + 1;
+			while i > 0 {
+				coeffs[i] = Gf256 { poly: coeffs[i - 1].poly ^ coeffs[i].const_mul(c).poly };
+				i -= 1;
+			}
+			coeffs[0] = coeffs[0].const_mul(c);
+			deg += 1;
+			c = c.square();
+			if c.poly == self.poly {
+				break;
+			}
+		}
+		// The coefficients are 0 or 1
+		let mut packed = 0u32;
+		let mut i = 0;
+		while i <= deg {
+			packed |= (coeffs[i].poly as u32) << i;
+			i += 1;
+		}
+		packed
+	}
+
+	/// The absolute trace a + a^2 + a^4 + ... + a^128, which lies in GF(2),
+	/// as 0 or 1
+	pub const fn trace(&self) -> u8 {
+		((self.poly & TRACE_MASK).count_ones() & 1) as u8
+	}
+
+	/// The absolute norm, the product of the conjugates a a^2 ... a^128 =
+	/// a^255, as 0 or 1. Over GF(2) it only tells zero from nonzero.
+	pub const fn norm(&self) -> u8 {
+		(self.poly != 0) as u8
+	}
+
+	/// self * rhs; the same as the * operator, but usable in const contexts
+	#[cfg(feature = "mul-table")]
+	#[inline]
+	pub const fn const_mul(self, rhs: Gf256) -> Gf256 {
+		Gf256 { poly: MUL_TABLE[self.poly as usize][rhs.poly as usize] }
+	}
+
+	/// self * rhs; the same as the * operator, but usable in const contexts
+	#[cfg(not(feature = "mul-table"))]
+	#[inline]
+	pub const fn const_mul(self, rhs: Gf256) -> Gf256 {
+		// log[0] is 0 like log[1]; the mask clears the product instead of
+		// branching on zero operands
+		let log = &get_tables().log;
+		let p = EXP2[log[self.poly as usize] as usize + log[rhs.poly as usize] as usize];
+		let nonzero = mask((self.poly != 0) as u8) & mask((rhs.poly != 0) as u8);
+		Gf256 { poly: p & nonzero }
+	}
+
+	/// self / rhs, or None if rhs is zero
+	pub const fn checked_div(self, rhs: Gf256) -> Option<Gf256> {
+		let l2 = match rhs.log() {
+			Some(l2) => l2,
+			None => return None,
+		};
+		Some(match self.log() {
+			Some(l1) => Gf256 { poly: EXP2[l1 as usize + 255 - l2 as usize] },
+			None => Gf256::zero(),
+		})
+	}
+
+	/// self / rhs as a Result, for propagating with ?
+	pub fn try_div(self, rhs: Gf256) -> Result<Gf256, Gf256Error> {
+		self.checked_div(rhs).ok_or(Gf256Error::DivisionByZero)
+	}
+
+	/// Samples a uniform element. As in shamir, rng has to fill the buffer
+	/// with uniformly random bytes.
+	pub fn random<R: FnMut(&mut [u8])>(mut rng: R) -> Gf256 {
+		let mut b = [0u8; 1];
+		rng(&mut b);
+		Gf256::from_byte(b[0])
+	}
+
+	/// Samples a uniform nonzero element by rejecting zero bytes
+	pub fn random_nonzero<R: FnMut(&mut [u8])>(mut rng: R) -> Gf256 {
+		let mut b = [0u8; 1];
+		loop {
+			rng(&mut b);
+			if b[0] != 0 {
+				return Gf256::from_byte(b[0]);
+			}
+		}
+	}
+
+	/// Renders self as a polynomial in x, e.g. "x^7 + x^2 + 1"
+	#[cfg(feature = "alloc")]
+	pub fn to_poly_string(&self) -> alloc::string::String {
+		alloc::format!("{:#}", self)
+	}
+
+	/// The coefficient of x^i, i.e. bit i of the byte. Powers from x^8 on
+	/// have coefficient 0.
+	pub const fn coefficient(&self, i: u32) -> bool {
+		i < 8 && self.poly >> i & 1 == 1
+	}
+
+	/// The coefficients of 1, x, ..., x^7
+	pub const fn to_bits(&self) -> [bool; 8] {
+		let mut bits = [false; 8];
+		let mut i = 0;
+		while i < 8 {
+			bits[i] = self.poly >> i & 1 == 1;
+			i += 1;
+		}
+		bits
+	}
+
+	/// The element with coefficients bits[i] of x^i
+	pub const fn from_bits(bits: [bool; 8]) -> Gf256 {
+		let mut poly = 0u8;
+		let mut i = 0;
+		while i < 8 {
+			poly |= (bits[i] as u8) << i;
+			i += 1;
+		}
+		Gf256 { poly }
+	}
+
+	/// Sets the coefficient of x^i; panics unless i < 8
+	pub const fn set_coefficient(&mut self, i: u32, bit: bool) {
+		assert!(i < 8, "Elements have degree below 8");
+		self.poly = self.poly & !(1 << i) | (bit as u8) << i;
+	}
+
+	/// A reproducible element derived from msg, see hash
+	pub fn hash_to_field(domain: &[u8], msg: &[u8]) -> Gf256 {
+		hash::hash_to_field(domain, msg)
+	}
+
+	/// Fills out with distinct nonzero elements derived from msg, e.g.
+	/// evaluation points for shard IDs; panics for more than 255
+	pub fn hash_to_distinct(domain: &[u8], msg: &[u8], out: &mut [Gf256]) {
+		hash::hash_to_distinct(domain, msg, out)
+	}
+}
+
+/// Views bytes as field elements without copying
+pub const fn as_gf_slice(bytes: &[u8]) -> &[Gf256] {
+	// Gf256 is repr(transparent) over u8, and every byte is a valid element
+	unsafe { core::slice::from_raw_parts(bytes.as_ptr() as *const Gf256, bytes.len()) }
+}
+
+pub const fn as_gf_slice_mut(bytes: &mut [u8]) -> &mut [Gf256] {
+	unsafe { core::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut Gf256, bytes.len()) }
+}
+
+/// Views field elements as their bytes without copying
+pub const fn as_byte_slice(elements: &[Gf256]) -> &[u8] {
+	unsafe { core::slice::from_raw_parts(elements.as_ptr() as *const u8, elements.len()) }
+}
+
+pub const fn as_byte_slice_mut(elements: &mut [Gf256]) -> &mut [u8] {
+	unsafe { core::slice::from_raw_parts_mut(elements.as_mut_ptr() as *mut u8, elements.len()) }
+}
+
+/// The sum of a[i] * b[i]; panics if the lengths differ
+pub fn dot(a: &[Gf256], b: &[Gf256]) -> Gf256 {
+	Gf256::from_byte(bytes::dot(as_byte_slice(a), as_byte_slice(b)))
+}
+
+/// Errors of fallible field operations
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Gf256Error {
+	/// The divisor (or the element to invert) is zero
+	DivisionByZero,
+	/// An integer above 255 was converted to a field element
+	OutOfRange,
+	/// A nonzero element was expected
+	Zero,
+}
+
+impl fmt::Display for Gf256Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Gf256Error::DivisionByZero => f.write_str("division by zero"),
+			Gf256Error::OutOfRange => f.write_str("value out of range for a field element"),
+			Gf256Error::Zero => f.write_str("zero where a nonzero element was expected"),
+		}
+	}
+}
+
+impl error::Error for Gf256Error {}
+
+/// A field element built at compile time, usable in consts and statics:
+///
+///     use gf256::{ gf, Gf256 };
+///     use gf256::small::Gf16;
+///     static GENERATOR: [Gf256; 3] = [gf!(1), gf!(0x03), gf!(0x02)];
+///     assert_eq!(GENERATOR[1], Gf256::from_byte(3));
+///     assert_eq!(gf!(Gf16, 0xa).to_byte(), 0xa);
+///
+/// gf!(v) is a Gf256 and fails to compile unless v is below 256.
+/// gf!(T, v) calls the const from_byte of T, so gf!(Gf16, 0x10) fails to
+/// compile as well.
+#[macro_export]
+macro_rules! gf {
+	($v:expr) => {
+		const {
+			let v = ($v) as u64;
+			assert!(v < 256, "Field element out of range");
+			$crate::Gf256::new(v as u8)
+		}
+	};
+	($t:ty, $v:expr) => {
+		const { <$t>::from_byte($v) }
+	};
+}
+
+impl From<u8> for Gf256 {
+	#[inline]
+	fn from(b: u8) -> Gf256 {
+		Gf256::from_byte(b)
+	}
+}
+
+impl From<Gf256> for u8 {
+	#[inline]
+	fn from(x: Gf256) -> u8 {
+		x.to_byte()
+	}
+}
+
+macro_rules! impl_try_from {
+	($($t:ty),*) => {
+		$(
+			/// Fails for values above 255
+			impl TryFrom<$t> for Gf256 {
+				type Error = Gf256Error;
+
+				fn try_from(v: $t) -> Result<Gf256, Gf256Error> {
+					u8::try_from(v).map(Gf256::from_byte).map_err(|_| Gf256Error::OutOfRange)
+				}
+			}
+		)*
+	};
+}
+
+impl_try_from!(u16, u32);
+
+/// Errors produced when parsing a Gf256 from a string
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum ParseGf256Error {
+	/// No digits, possibly after a prefix
+	Empty,
+	InvalidDigit,
+	/// The value does not fit into a byte
+	OutOfRange,
+}
+
+impl fmt::Display for ParseGf256Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			ParseGf256Error::Empty => "no digits in field element",
+			ParseGf256Error::InvalidDigit => "invalid digit in field element",
+			ParseGf256Error::OutOfRange => "field element out of range",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for ParseGf256Error {}
+
+/// Parses the byte value in decimal, or in hex or binary with a 0x or 0b
+/// prefix ("133", "0x85", "0b10000101"). Signs are not accepted.
+impl FromStr for Gf256 {
+	type Err = ParseGf256Error;
+
+	fn from_str(s: &str) -> Result<Gf256, ParseGf256Error> {
+		let (digits, radix) = if let Some(d) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+			(d, 16)
+		} else if let Some(d) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+			(d, 2)
+		} else {
+			(s, 10)
+		};
+		if digits.starts_with(['+', '-']) {
+			return Err(ParseGf256Error::InvalidDigit);
+		}
+		u8::from_str_radix(digits, radix).map(Gf256::from_byte).map_err(|e| match e.kind() {
+			IntErrorKind::Empty => ParseGf256Error::Empty,
+			IntErrorKind::PosOverflow => ParseGf256Error::OutOfRange,
+			_ => ParseGf256Error::InvalidDigit,
+		})
+	}
+}
+
+/// Prints the byte value; the alternate form `{:#}` prints the polynomial
+impl fmt::Display for Gf256 {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if !f.alternate() {
+			return fmt::Display::fmt(&self.poly, f);
+		}
+		if self.poly == 0 {
+			return f.write_str("0");
+		}
+		let mut first = true;
+		for bit in (0..8).rev().filter(|&bit| self.poly >> bit & 1 == 1) {
+			if !first {
+				f.write_str(" + ")?;
+			}
+			first = false;
+			match bit {
+				0 => f.write_str("1")?,
+				1 => f.write_str("x")?,
+				_ => write!(f, "x^{}", bit)?,
+			}
+		}
+		Ok(())
 	}
 }
 
+// Hex and binary formatting print the bits of the polynomial, with the
+// same flags as for u8
+macro_rules! impl_fmt_bits {
+	($($tr:ident),*) => {
+		$(
+			impl fmt::$tr for Gf256 {
+				fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+					fmt::$tr::fmt(&self.poly, f)
+				}
+			}
+		)*
+	};
+}
+
+impl_fmt_bits!(LowerHex, UpperHex, Binary);
+
 impl Add<Gf256> for Gf256 {
 	type Output = Gf256;
 	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn add(self, rhs: Gf256) -> Gf256 {
 		Gf256::from_byte(self.poly ^ rhs.poly)
 	}
@@ -130,6 +800,7 @@ impl Add<Gf256> for Gf256 {
 impl Sub<Gf256> for Gf256 {
 	type Output = Gf256;
 	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn sub(self, rhs: Gf256) -> Gf256 {
 		Gf256::from_byte(self.poly ^ rhs.poly)
 	}
@@ -138,24 +809,192 @@ impl Sub<Gf256> for Gf256 {
 impl Mul<Gf256> for Gf256 {
 	type Output = Gf256;
 	fn mul(self, rhs: Gf256) -> Gf256 {
-		if let (Some(l1), Some(l2)) = (self.log(), rhs.log()) {
-			let tmp = ((l1 as u16) + (l2 as u16)) % 255;
-			Gf256::xexp(tmp as u8)
-		} else {
-			Gf256 { poly: 0 }
-		}
+		self.const_mul(rhs)
 	}
 }
 
 impl Div<Gf256> for Gf256 {
 	type Output = Gf256;
+	/// Panics if rhs is zero, see Gf256::checked_div
 	fn div(self, rhs: Gf256) -> Gf256 {
-		let l2 = rhs.log().expect("Division by zero");
-		if let Some(l1) = self.log() {
-			let tmp = ((l1 as u16) + 255 - (l2 as u16)) % 255;
-			Gf256::xexp(tmp as u8)
-		} else {
-			Gf256 { poly: 0 }
+		self.checked_div(rhs).expect("Division by zero")
+	}
+}
+
+// Mixed operations with raw bytes. The byte is interpreted as a field
+// element, exactly as Gf256::from_byte does; the in place versions on a
+// byte store the result back as a byte.
+macro_rules! impl_byte_op {
+	($tr:ident, $method:ident, $assign:ident, $assign_method:ident) => {
+		impl $tr<u8> for Gf256 {
+			type Output = Gf256;
+			#[inline]
+			fn $method(self, rhs: u8) -> Gf256 {
+				$tr::$method(self, Gf256::from_byte(rhs))
+			}
+		}
+
+		impl $tr<Gf256> for u8 {
+			type Output = Gf256;
+			#[inline]
+			fn $method(self, rhs: Gf256) -> Gf256 {
+				$tr::$method(Gf256::from_byte(self), rhs)
+			}
+		}
+
+		impl $assign<u8> for Gf256 {
+			#[inline]
+			fn $assign_method(&mut self, rhs: u8) {
+				*self = $tr::$method(*self, Gf256::from_byte(rhs));
+			}
+		}
+
+		impl $assign<Gf256> for u8 {
+			#[inline]
+			fn $assign_method(&mut self, rhs: Gf256) {
+				*self = $tr::$method(Gf256::from_byte(*self), rhs).to_byte();
+			}
+		}
+	};
+}
+
+impl_byte_op!(Add, add, AddAssign, add_assign);
+impl_byte_op!(Sub, sub, SubAssign, sub_assign);
+impl_byte_op!(Mul, mul, MulAssign, mul_assign);
+impl_byte_op!(Div, div, DivAssign, div_assign);
+
+// Borrowed operands, so generic code bounded on &T: Add<&T> etc. works
+macro_rules! impl_ref_op {
+	($tr:ident, $method:ident) => {
+		impl $tr<&Gf256> for Gf256 {
+			type Output = Gf256;
+			#[inline]
+			fn $method(self, rhs: &Gf256) -> Gf256 {
+				$tr::$method(self, *rhs)
+			}
+		}
+
+		impl $tr<Gf256> for &Gf256 {
+			type Output = Gf256;
+			#[inline]
+			fn $method(self, rhs: Gf256) -> Gf256 {
+				$tr::$method(*self, rhs)
+			}
+		}
+
+		impl $tr<&Gf256> for &Gf256 {
+			type Output = Gf256;
+			#[inline]
+			fn $method(self, rhs: &Gf256) -> Gf256 {
+				$tr::$method(*self, *rhs)
+			}
+		}
+	};
+}
+
+impl_ref_op!(Add, add);
+impl_ref_op!(Sub, sub);
+impl_ref_op!(Mul, mul);
+impl_ref_op!(Div, div);
+
+/// Every element is its own additive inverse in characteristic 2
+impl Neg for Gf256 {
+	type Output = Gf256;
+	#[inline]
+	fn neg(self) -> Gf256 {
+		self
+	}
+}
+
+impl Neg for &Gf256 {
+	type Output = Gf256;
+	#[inline]
+	fn neg(self) -> Gf256 {
+		*self
+	}
+}
+
+// In place versions of the operators, taking the right hand side by value
+// or by reference
+macro_rules! impl_assign_op {
+	($tr:ident, $method:ident, $op:ident, $op_method:ident) => {
+		impl $tr<Gf256> for Gf256 {
+			#[inline]
+			fn $method(&mut self, rhs: Gf256) {
+				*self = $op::$op_method(*self, rhs);
+			}
+		}
+
+		impl $tr<&Gf256> for Gf256 {
+			#[inline]
+			fn $method(&mut self, rhs: &Gf256) {
+				*self = $op::$op_method(*self, *rhs);
+			}
+		}
+	};
+}
+
+impl_assign_op!(AddAssign, add_assign, Add, add);
+impl_assign_op!(SubAssign, sub_assign, Sub, sub);
+impl_assign_op!(MulAssign, mul_assign, Mul, mul);
+impl_assign_op!(DivAssign, div_assign, Div, div);
+
+impl Sum<Gf256> for Gf256 {
+	fn sum<I: Iterator<Item = Gf256>>(iter: I) -> Gf256 {
+		iter.fold(Gf256::zero(), Add::add)
+	}
+}
+
+impl<'a> Sum<&'a Gf256> for Gf256 {
+	fn sum<I: Iterator<Item = &'a Gf256>>(iter: I) -> Gf256 {
+		iter.copied().sum()
+	}
+}
+
+impl Product<Gf256> for Gf256 {
+	fn product<I: Iterator<Item = Gf256>>(iter: I) -> Gf256 {
+		iter.fold(Gf256::one(), Mul::mul)
+	}
+}
+
+impl<'a> Product<&'a Gf256> for Gf256 {
+	fn product<I: Iterator<Item = &'a Gf256>>(iter: I) -> Gf256 {
+		iter.copied().product()
+	}
+}
+
+
+// Codecs, contexts and tables are meant to be configured once and shared
+// between threads (by reference or through an Arc). Make sure none of them
+// loses Send + Sync by accident.
+const _: () = {
+	const fn assert_send_sync<T: Send + Sync>() {}
+	assert_send_sync::<Gf256>();
+	assert_send_sync::<GfLog>();
+	assert_send_sync::<ctx::Tables>();
+	assert_send_sync::<ctx::FieldCtx<'static>>();
+	assert_send_sync::<rs::RsCodec<255, 223>>();
+	assert_send_sync::<rs::Scratch<255, 223>>();
+	assert_send_sync::<shamir::Share<32>>();
+};
+
+// ErasureCode holds its backend as a trait object, which only keeps the
+// unwind safety of the code if the object type asks for it.
+#[cfg(feature = "std")]
+const _: () = {
+	const fn assert_unwind_safe<T: Send + Sync + core::panic::RefUnwindSafe + core::panic::UnwindSafe>() {}
+	assert_unwind_safe::<rs::erasure::ErasureCode>();
+};
+
+/// A small deterministic stand-in for a random number generator, for
+/// tests of the functions that take one
+#[cfg(test)]
+pub(crate) fn test_rng(seed: u8) -> impl FnMut(&mut [u8]) {
+	let mut state = seed;
+	move |buf: &mut [u8]| {
+		for b in buf.iter_mut() {
+			state = state.wrapping_mul(167).wrapping_add(13);
+			*b = state;
 		}
 	}
 }
@@ -251,6 +1090,30 @@ mod tests {
         assert_eq!(res, None);
     }
 
+    #[test]
+    fn test_byte_ops() {
+        let a = Gf256::from_byte(0x8f);
+        let b = Gf256::from_byte(0x15);
+
+        assert_eq!(a + 0x15, a + b);
+        assert_eq!(0x8f + b, a + b);
+        assert_eq!(a - 0x15, a - b);
+        assert_eq!(a * 0x15, a * b);
+        assert_eq!(0x8f * b, a * b);
+        assert_eq!(a / 0x15, a / b);
+        assert_eq!(0x8f / b, a / b);
+
+        let mut c = a;
+        c *= 0x15;
+        c += 0x01;
+        assert_eq!(c, a * b + Gf256::one());
+        let mut buf = [0x8fu8, 0x15];
+        buf[0] *= b;
+        buf[1] /= b;
+        buf[1] -= a;
+        assert_eq!(buf, [(a * b).to_byte(), (Gf256::one() - a).to_byte()]);
+    }
+
     #[test]
     fn test_exp() {
         let a = Gf256::from_byte(0x8f);
@@ -263,5 +1126,391 @@ mod tests {
         // Fermat's little theorem:
         assert_eq!(a.exp(255), Gf256::one());
     }
-}
 
+    #[test]
+    fn test_display() {
+        assert_eq!(Gf256::from_byte(0x85).to_poly_string(), "x^7 + x^2 + 1");
+        assert_eq!(format!("{:#}", Gf256::from_byte(2)), "x");
+        assert_eq!(format!("{:#}", Gf256::from_byte(3)), "x + 1");
+        assert_eq!(format!("{:#}", Gf256::zero()), "0");
+        assert_eq!(format!("{}", Gf256::from_byte(0x85)), "133");
+        assert_eq!(format!("{:02x}", Gf256::from_byte(0x0b)), "0b");
+        assert_eq!(format!("{:#X}", Gf256::from_byte(0xab)), "0xAB");
+        assert_eq!(format!("{:08b}", Gf256::from_byte(0x85)), "10000101");
+    }
+
+    #[test]
+    fn test_conjugates() {
+        assert_eq!(Gf256::zero().conjugates().collect::<Vec<_>>(), [Gf256::zero()]);
+        let x: Vec<u8> = Gf256::GENERATOR.conjugates().map(|c| c.log().unwrap()).collect();
+        assert_eq!(x, [1, 2, 4, 8, 16, 32, 64, 128]);
+        assert_eq!(Gf256::xexp(85).conjugates().count(), 2);
+        for a in Gf256::all() {
+            let degree = gf2poly::degree(a.minimal_polynomial()).unwrap() as usize;
+            assert_eq!(a.conjugates().count(), degree);
+        }
+    }
+
+    #[test]
+    fn test_minimal_polynomial() {
+        assert_eq!(Gf256::zero().minimal_polynomial(), 0b10);
+        assert_eq!(Gf256::one().minimal_polynomial(), 0b11);
+        // The generator x has the reduction polynomial
+        assert_eq!(Gf256::GENERATOR.minimal_polynomial(), POLYNOMIAL as u32);
+        for a in Gf256::all() {
+            let m = a.minimal_polynomial();
+            let value = (0..=8).filter(|&i| m >> i & 1 == 1).map(|i| a.pow(i)).sum::<Gf256>();
+            assert_eq!(value, Gf256::zero(), "{}", a);
+            assert!(gf2poly::is_irreducible(m));
+            assert_eq!(8 % gf2poly::degree(m).unwrap(), 0);
+            // Conjugates share it
+            assert_eq!(a.square().minimal_polynomial(), m);
+        }
+        // x^17 lies in GF(16), x^85 in GF(4)
+        assert_eq!(gf2poly::degree(Gf256::xexp(17).minimal_polynomial()), Some(4));
+        assert_eq!(Gf256::xexp(85).minimal_polynomial(), 0b111);
+        let via_bch: u32 = bch::minimal_polynomial(3).iter().enumerate()
+            .map(|(i, c)| (c.to_byte() as u32) << i).sum();
+        assert_eq!(Gf256::xexp(3).minimal_polynomial(), via_bch);
+    }
+
+    #[test]
+    fn test_gf_macro() {
+        const G: [Gf256; 3] = [gf!(0x01), gf!(0x53), gf!(255)];
+        static S: Gf256 = gf!(0b110);
+        assert_eq!(G.map(|g| g.to_byte()), [1, 0x53, 255]);
+        assert_eq!(S, Gf256::new(6));
+        const B: u8 = 0xca;
+        assert_eq!(gf!(B), Gf256::from_byte(B));
+        assert_eq!(gf!(small::Gf16, 7), small::Gf16::from_byte(7));
+        assert_eq!(gf!(modular::Aes, 0x57) * gf!(modular::Aes, 0x83), gf!(modular::Aes, 0xc1));
+    }
+
+    #[test]
+    fn test_coefficients() {
+        let a = Gf256::from_byte(0x85);
+        let set: Vec<u32> = (0..10).filter(|&i| a.coefficient(i)).collect();
+        assert_eq!(set, [0, 2, 7]);
+        // x^4 + x + 1, built term by term
+        let mut b = Gf256::zero();
+        for i in [4, 1, 0] {
+            b.set_coefficient(i, true);
+        }
+        assert_eq!(b.to_poly_string(), "x^4 + x + 1");
+        b.set_coefficient(1, false);
+        b.set_coefficient(4, true);
+        assert_eq!(b, Gf256::from_byte(0x11));
+        assert_eq!(Gf256::from_byte(0x85).to_bits(), [true, false, true, false, false, false, false, true]);
+        for a in Gf256::all() {
+            assert_eq!(Gf256::from_bits(a.to_bits()), a);
+        }
+    }
+
+    #[test]
+    fn test_static_tables() {
+        let mut filled = Tables::EMPTY;
+        filled.fill();
+        assert_eq!(get_tables().as_bytes(), filled.as_bytes());
+    }
+
+    #[cfg(feature = "mul-table")]
+    #[test]
+    fn test_mul_table() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                let expected = match (Gf256::from_byte(a).log(), Gf256::from_byte(b).log()) {
+                    (Some(l1), Some(l2)) => Gf256::xexp(((l1 as u16 + l2 as u16) % 255) as u8),
+                    _ => Gf256::zero(),
+                };
+                assert_eq!(Gf256::from_byte(a) * Gf256::from_byte(b), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        let coeffs = [Gf256::from_byte(3), Gf256::from_byte(0x51), Gf256::from_byte(0xe0)];
+        let x = Gf256::from_byte(0x17);
+        let mut acc = Gf256::zero();
+        for c in &coeffs {
+            acc *= x;
+            acc += c;
+        }
+        assert_eq!(acc, (coeffs[0] * x + coeffs[1]) * x + coeffs[2]);
+
+        let mut y = acc;
+        y -= &coeffs[2];
+        y /= x;
+        assert_eq!(y, coeffs[0] * x + coeffs[1]);
+        y *= &x;
+        y /= &x;
+        assert_eq!(y, coeffs[0] * x + coeffs[1]);
+    }
+
+    #[test]
+    fn test_checked_div() {
+        let a = Gf256::from_byte(0x9c);
+        let b = Gf256::from_byte(0x21);
+        assert_eq!(a.checked_div(b), Some(a / b));
+        assert_eq!(Gf256::zero().checked_div(b), Some(Gf256::zero()));
+        assert_eq!(a.checked_div(Gf256::zero()), None);
+        assert_eq!(a.try_div(Gf256::zero()), Err(Gf256Error::DivisionByZero));
+        for x in 0..=255u8 {
+            let x = Gf256::from_byte(x);
+            assert_eq!(x.inv(), Gf256::one().checked_div(x));
+        }
+    }
+
+    fn poly_eval<T>(coeffs: &[T], x: &T, zero: T) -> T
+        where for<'a> &'a T: Mul<&'a T, Output = T> + Add<&'a T, Output = T> {
+        coeffs.iter().fold(zero, |acc, c| &(&acc * x) + c)
+    }
+
+    #[test]
+    fn test_ref_ops() {
+        let a = Gf256::from_byte(0x3a);
+        let b = Gf256::from_byte(0xc5);
+        let (ra, rb) = (&a, &b);
+        assert_eq!(ra + rb, a + b);
+        assert_eq!(a - rb, a - b);
+        assert_eq!(ra * b, a * b);
+        assert_eq!(ra / rb, a / b);
+        assert_eq!(-a, a);
+        assert_eq!(-ra + a, Gf256::zero());
+        assert_eq!(poly_eval(&[a, b], &b, Gf256::zero()), a * b + b);
+    }
+
+    #[test]
+    fn test_sum_product() {
+        let xs: Vec<Gf256> = [3u8, 0x80, 0x47, 0x47].iter().map(|&b| Gf256::from_byte(b)).collect();
+        assert_eq!(xs.iter().sum::<Gf256>(), Gf256::from_byte(3 ^ 0x80));
+        assert_eq!(xs.iter().copied().sum::<Gf256>(), Gf256::from_byte(3 ^ 0x80));
+        assert_eq!(xs.iter().product::<Gf256>(), xs[0] * xs[1] * xs[2] * xs[3]);
+        assert_eq!(xs.into_iter().skip(4).product::<Gf256>(), Gf256::one());
+        assert_eq!(std::iter::empty::<Gf256>().sum::<Gf256>(), Gf256::zero());
+    }
+
+    #[test]
+    fn test_parse() {
+        let x = Gf256::from_byte(0x85);
+        assert_eq!("133".parse(), Ok(x));
+        assert_eq!("0x85".parse(), Ok(x));
+        assert_eq!("0X85".parse(), Ok(x));
+        assert_eq!("0b10000101".parse(), Ok(x));
+        assert_eq!("".parse::<Gf256>(), Err(ParseGf256Error::Empty));
+        assert_eq!("0x".parse::<Gf256>(), Err(ParseGf256Error::Empty));
+        assert_eq!("256".parse::<Gf256>(), Err(ParseGf256Error::OutOfRange));
+        assert_eq!("0b102".parse::<Gf256>(), Err(ParseGf256Error::InvalidDigit));
+        assert_eq!("+1".parse::<Gf256>(), Err(ParseGf256Error::InvalidDigit));
+        assert_eq!("0x+1".parse::<Gf256>(), Err(ParseGf256Error::InvalidDigit));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let x: Gf256 = 0x85u8.into();
+        assert_eq!(x, Gf256::from_byte(0x85));
+        assert_eq!(u8::from(x), 0x85);
+        assert_eq!(Gf256::try_from(255u16), Ok(Gf256::from_byte(255)));
+        assert_eq!(Gf256::try_from(256u16), Err(Gf256Error::OutOfRange));
+        assert_eq!(Gf256::try_from(7u32), Ok(Gf256::from_byte(7)));
+        assert_eq!(Gf256::try_from(u32::MAX), Err(Gf256Error::OutOfRange));
+    }
+
+    #[test]
+    fn test_random() {
+        let mut stream = [0u8, 0, 0x42, 7].into_iter();
+        let mut rng = |buf: &mut [u8]| buf.fill(stream.next().unwrap());
+        assert_eq!(Gf256::random(&mut rng), Gf256::zero());
+        assert_eq!(Gf256::random_nonzero(&mut rng), Gf256::from_byte(0x42));
+        assert_eq!(Gf256::random_nonzero(&mut rng), Gf256::from_byte(7));
+    }
+
+    #[test]
+    fn test_frobenius() {
+        let mut ones = 0;
+        for a in Gf256::all() {
+            assert_eq!(a.square(), a * a);
+            assert_eq!(a.frobenius(0), a);
+            assert_eq!(a.frobenius(3), a.exp(8));
+            assert_eq!(a.frobenius(8), a);
+            let conjugates: Gf256 = (0..8).map(|k| a.frobenius(k)).sum();
+            assert_eq!(conjugates, Gf256::from_byte(a.trace()));
+            assert_eq!(a.square().trace(), a.trace());
+            let norm: Gf256 = (0..8).map(|k| a.frobenius(k)).product();
+            assert_eq!(norm, Gf256::from_byte(a.norm()));
+            ones += a.trace() as usize;
+        }
+        // The trace maps onto GF(2), hitting 0 and 1 equally often
+        assert_eq!(ones, 128);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let mut roots = [false; 256];
+        for a in Gf256::all() {
+            let r = a.sqrt();
+            assert_eq!(r * r, a);
+            assert_eq!(a.square().sqrt(), a);
+            roots[r.poly as usize] = true;
+        }
+        assert!(roots.iter().all(|&r| r));
+    }
+
+    #[test]
+    fn test_pow() {
+        let a = Gf256::from_byte(0x35);
+        let inv = a.inv().unwrap();
+        assert_eq!(a.pow(0), Gf256::one());
+        assert_eq!(a.pow(255), Gf256::one());
+        assert_eq!(a.pow(1000), a.exp((1000 % 255) as u8));
+        assert_eq!(a.pow(u64::MAX), a.exp((u64::MAX % 255) as u8));
+        assert_eq!(a.pow_signed(-1), Some(inv));
+        assert_eq!(a.pow_signed(-3), Some(inv * inv * inv));
+        assert_eq!(a.pow_signed(-255), Some(Gf256::one()));
+        assert_eq!(a.pow_signed(i64::MIN).unwrap() * a.pow(i64::MIN.unsigned_abs()), Gf256::one());
+        assert_eq!(a.pow_signed(7), Some(a.pow(7)));
+        let zero = Gf256::zero();
+        assert_eq!(zero.pow(0), Gf256::one());
+        assert_eq!(zero.pow(300), zero);
+        assert_eq!(zero.pow_signed(0), Some(Gf256::one()));
+        assert_eq!(zero.pow_signed(-2), None);
+    }
+
+    #[test]
+    fn test_log_base() {
+        // 3 is not a generator of this field, but 4 is
+        let three = Gf256::from_byte(3);
+        let four = Gf256::from_byte(4);
+        assert_eq!(Gf256::generators().nth(1), Some(four));
+        for a in Gf256::nonzero() {
+            let i = a.log_base(four).unwrap();
+            assert_eq!(four.exp(i), a);
+            assert_eq!(a.log_base(three).is_some(), a.exp(three.order().unwrap()) == Gf256::one());
+            assert_eq!(a.log_base(Gf256::from_byte(2)), a.log());
+        }
+        // x^5 generates the 51 powers x^(5i)
+        let base = Gf256::xexp(5);
+        assert_eq!(Gf256::xexp(20).log_base(base), Some(4));
+        assert_eq!(Gf256::xexp(21).log_base(base), None);
+        assert_eq!(Gf256::one().log_base(base), Some(0));
+        assert_eq!(Gf256::zero().log_base(three), None);
+        assert_eq!(three.log_base(Gf256::zero()), None);
+    }
+
+    #[test]
+    fn test_order() {
+        assert_eq!(Gf256::zero().order(), None);
+        assert_eq!(Gf256::one().order(), Some(1));
+        for a in Gf256::nonzero() {
+            let n = a.order().unwrap();
+            assert_eq!(255 % n, 0);
+            assert_eq!(a.exp(n), Gf256::one());
+            assert!((1..n).all(|m| a.exp(m) != Gf256::one()));
+        }
+        let gens: Vec<Gf256> = Gf256::generators().collect();
+        assert_eq!(gens.len(), 128);
+        assert_eq!(gens[0], Gf256::from_byte(2));
+        assert!(gens.iter().all(|g| g.is_generator()));
+        // x^3 generates the subgroup of order 85
+        assert!(!Gf256::xexp(3).is_generator());
+        assert_eq!(Gf256::xexp(3).order(), Some(85));
+    }
+
+    const CUBES: [Gf256; 4] = {
+        let mut out = [Gf256::zero(); 4];
+        let mut i = 0;
+        while i < 4 {
+            let x = Gf256::from_byte(i as u8 + 2);
+            out[i] = x.const_mul(x).const_mul(x);
+            i += 1;
+        }
+        out
+    };
+
+    #[test]
+    fn test_const_arithmetic() {
+        for (i, &c) in CUBES.iter().enumerate() {
+            let x = Gf256::from_byte(i as u8 + 2);
+            assert_eq!(c, x * x * x);
+            assert_eq!(c, x.exp(3));
+        }
+        const INV: Option<Gf256> = Gf256::from_byte(0x53).inv();
+        assert_eq!(INV.unwrap() * Gf256::from_byte(0x53), Gf256::one());
+        const LOG: Option<u8> = Gf256::from_byte(2).log();
+        assert_eq!(LOG, Some(1));
+    }
+
+    #[test]
+    fn test_ordering_and_hash() {
+        use std::collections::{ BTreeSet, HashSet };
+        let mut points: Vec<Gf256> = [9u8, 0, 200, 3].iter().map(|&b| Gf256::from_byte(b)).collect();
+        points.sort();
+        assert_eq!(points.iter().map(|x| x.to_byte()).collect::<Vec<u8>>(), [0, 3, 9, 200]);
+        assert!(Gf256::one() < Gf256::from_byte(2));
+        let tree: BTreeSet<Gf256> = points.iter().copied().chain(points.iter().copied()).collect();
+        let hashed: HashSet<Gf256> = points.iter().copied().collect();
+        assert_eq!(tree.len(), 4);
+        assert!(hashed.contains(&Gf256::from_byte(200)));
+    }
+
+    #[test]
+    fn test_slice_casts() {
+        let mut bytes = [1u8, 2, 0x53];
+        assert_eq!(as_gf_slice(&bytes)[2], Gf256::from_byte(0x53));
+        as_gf_slice_mut(&mut bytes)[0] *= Gf256::from_byte(2);
+        assert_eq!(bytes[0], 2);
+        let mut elements = [Gf256::zero(), Gf256::one()];
+        assert_eq!(as_byte_slice(&elements), [0, 1]);
+        as_byte_slice_mut(&mut elements)[0] = 7;
+        assert_eq!(elements[0], Gf256::from_byte(7));
+        assert_eq!(core::mem::size_of::<Gf256>(), 1);
+    }
+
+    #[test]
+    fn test_dot() {
+        let a: Vec<Gf256> = (1..=40u8).map(Gf256::from_byte).collect();
+        let b: Vec<Gf256> = (1..=40u8).map(|i| Gf256::from_byte(i.wrapping_mul(37))).collect();
+        let expected: Gf256 = a.iter().zip(&b).map(|(&x, &y)| x * y).sum();
+        assert_eq!(dot(&a, &b), expected);
+        assert_eq!(dot(&[], &[]), Gf256::zero());
+    }
+
+    #[test]
+    fn test_element_iterators() {
+        assert_eq!(Gf256::all().len(), 256);
+        assert_eq!(Gf256::nonzero().len(), 255);
+        assert_eq!(Gf256::all().next_back(), Some(Gf256::from_byte(255)));
+        assert!(Gf256::all().zip(0..=255u8).all(|(a, b)| a.to_byte() == b));
+        assert_eq!(Gf256::nonzero().next(), Some(Gf256::one()));
+        let x = Gf256::from_byte(2);
+        assert!(Gf256::powers_of(x).take(300).enumerate().all(|(i, p)| p == Gf256::xexp((i % 255) as u8)));
+        let g = Gf256::from_byte(0x1d);
+        let n = g.order().unwrap() as usize;
+        assert_eq!(Gf256::powers_of(g).nth(n), Some(Gf256::one()));
+        assert_eq!(Gf256::powers_of(Gf256::zero()).take(3).collect::<Vec<_>>(), [Gf256::one(), Gf256::zero(), Gf256::zero()]);
+    }
+
+    #[test]
+    fn test_constants() {
+        static POINTS: [Gf256; 2] = [Gf256::ZERO, Gf256::ONE];
+        assert_eq!(POINTS, [Gf256::zero(), Gf256::one()]);
+        assert!(Gf256::GENERATOR.is_generator());
+        assert_eq!(Gf256::GENERATOR, Gf256::xexp(1));
+        assert_eq!(POLYNOMIAL, 0x11d);
+        assert_eq!(Gf256::xexp(7) * Gf256::GENERATOR, Gf256::from_byte((POLYNOMIAL & 0xff) as u8));
+    }
+
+    #[test]
+    fn test_log_wrt() {
+        for g in Gf256::generators() {
+            for a in Gf256::nonzero() {
+                let l = a.log_wrt(g).unwrap();
+                assert_eq!(g.pow(l as u64), a);
+                assert_eq!(Some(l), a.log_base(g));
+            }
+        }
+        assert_eq!(Gf256::from_byte(9).log_wrt(Gf256::from_byte(2)), Gf256::from_byte(9).log());
+        assert_eq!(Gf256::zero().log_wrt(Gf256::from_byte(4)), None);
+        assert_eq!(Gf256::from_byte(9).log_wrt(Gf256::from_byte(3)), None);
+    }
+}
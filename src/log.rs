@@ -0,0 +1,166 @@
+//! Field elements stored as discrete logarithms.
+//!
+//! Multiplying two GfLog values is an addition modulo 255 without any
+//! table lookup, and multiplying a Gf256 by a GfLog needs one lookup
+//! less than a plain product. Loops which keep multiplying by the same
+//! constants (syndromes, Chien search) can convert them once up front.
+
+use core::ops::{ Mul, Div };
+
+use crate::Gf256;
+
+/// A field element x^log, with log = 255 standing for zero
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Hash)]
+pub struct GfLog {
+	log: u8
+}
+
+impl GfLog {
+	/// The zero element
+	pub const ZERO: GfLog = GfLog { log: 255 };
+	/// The one element, x^0
+	pub const ONE: GfLog = GfLog { log: 0 };
+
+	/// Returns x^log; log is taken modulo 255
+	#[inline]
+	pub const fn from_log(log: u8) -> GfLog {
+		GfLog { log: log % 255 }
+	}
+
+	/// Returns the logarithm, None for zero
+	#[inline]
+	pub fn log(&self) -> Option<u8> {
+		if self.is_zero() { None } else { Some(self.log) }
+	}
+
+	#[inline]
+	pub fn is_zero(&self) -> bool {
+		self.log == 255
+	}
+
+	pub fn from_gf256(x: Gf256) -> GfLog {
+		x.log().map_or(GfLog::ZERO, |log| GfLog { log })
+	}
+
+	pub fn to_gf256(&self) -> Gf256 {
+		self.log().map_or(Gf256::zero(), Gf256::xexp)
+	}
+
+	/// Returns self ^ power
+	pub fn pow(&self, power: u32) -> GfLog {
+		match self.log() {
+			None if power == 0 => GfLog::ONE,
+			None => GfLog::ZERO,
+			Some(l) => GfLog { log: ((l as u64 * power as u64) % 255) as u8 },
+		}
+	}
+
+	/// Returns the inverse, None for zero
+	pub fn inv(&self) -> Option<GfLog> {
+		self.log().map(|l| GfLog { log: (255 - l as u16) as u8 % 255 })
+	}
+}
+
+impl From<Gf256> for GfLog {
+	fn from(x: Gf256) -> GfLog {
+		GfLog::from_gf256(x)
+	}
+}
+
+impl From<GfLog> for Gf256 {
+	fn from(x: GfLog) -> Gf256 {
+		x.to_gf256()
+	}
+}
+
+impl Mul<GfLog> for GfLog {
+	type Output = GfLog;
+	#[inline]
+	fn mul(self, rhs: GfLog) -> GfLog {
+		if self.is_zero() || rhs.is_zero() {
+			GfLog::ZERO
+		} else {
+			GfLog { log: ((self.log as u16 + rhs.log as u16) % 255) as u8 }
+		}
+	}
+}
+
+impl Div<GfLog> for GfLog {
+	type Output = GfLog;
+	#[inline]
+	fn div(self, rhs: GfLog) -> GfLog {
+		assert!(!rhs.is_zero(), "Division by zero");
+		if self.is_zero() {
+			GfLog::ZERO
+		} else {
+			GfLog { log: ((self.log as u16 + 255 - rhs.log as u16) % 255) as u8 }
+		}
+	}
+}
+
+impl Mul<GfLog> for Gf256 {
+	type Output = Gf256;
+	#[inline]
+	fn mul(self, rhs: GfLog) -> Gf256 {
+		match (self.log(), rhs.log()) {
+			(Some(a), Some(b)) => Gf256::xexp(((a as u16 + b as u16) % 255) as u8),
+			_ => Gf256::zero(),
+		}
+	}
+}
+
+impl Mul<Gf256> for GfLog {
+	type Output = Gf256;
+	#[inline]
+	fn mul(self, rhs: Gf256) -> Gf256 {
+		rhs * self
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for b in 0..=255u8 {
+            let x = Gf256::from_byte(b);
+            assert_eq!(GfLog::from(x).to_gf256(), x);
+        }
+        assert!(GfLog::from(Gf256::zero()).is_zero());
+        assert_eq!(GfLog::from_log(255), GfLog::ONE);
+    }
+
+    #[test]
+    fn test_matches_gf256() {
+        for a in (0..=255u8).step_by(7) {
+            for b in (0..=255u8).step_by(5) {
+                let (x, y) = (Gf256::from_byte(a), Gf256::from_byte(b));
+                let (lx, ly) = (GfLog::from(x), GfLog::from(y));
+                assert_eq!((lx * ly).to_gf256(), x * y);
+                assert_eq!(x * ly, x * y);
+                if b != 0 {
+                    assert_eq!((lx / ly).to_gf256(), x / y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_inv() {
+        let g = GfLog::from(Gf256::from_byte(0x53));
+        assert_eq!(g.pow(255), GfLog::ONE);
+        assert_eq!(g.pow(3).to_gf256(), Gf256::from_byte(0x53).exp(3));
+        assert_eq!(g * g.inv().unwrap(), GfLog::ONE);
+        assert_eq!(GfLog::ONE.inv(), Some(GfLog::ONE));
+        assert_eq!(GfLog::ZERO.inv(), None);
+        assert_eq!(GfLog::ZERO.pow(0), GfLog::ONE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_div_by_zero() {
+        let _ = GfLog::ONE / GfLog::ZERO;
+    }
+}
@@ -0,0 +1,309 @@
+//! Command line tool.
+//!
+//!     gf256 encode [--data K] [--parity M] FILE
+//!     gf256 verify FILE
+//!     gf256 repair [--output PATH] FILE
+//!     gf256 split [--threshold K] [--shares N] [--text] [--output PREFIX] [FILE]
+//!     gf256 combine [--output PATH] SHARE ...
+//!     gf256 vectors [--seed N] [--count N] [--len N] [codec ...]
+//!
+//! encode splits FILE into K data shards (10 by default) and adds M
+//! parity shards (4 by default), written next to it as FILE.000,
+//! FILE.001, ... Each shard file is framed with a rs::header header. The
+//! data shards hold the file length as a little endian u64, the file and
+//! zero padding.
+//!
+//! verify checks the shard files and exits with 1 if any is missing or
+//! damaged. repair rebuilds those from at least K intact shards and
+//! writes the original file back to FILE or PATH.
+//!
+//! split cuts a secret read from FILE or stdin into N Shamir shares (3 by
+//! default), any K of which (2 by default) recover it. The shares are
+//! written in the shares format to PREFIX.share1, PREFIX.share2, ...,
+//! where PREFIX defaults to FILE or "secret"; --text writes them armored
+//! for printing instead. More than 255 shares are made over GF(2^16), up
+//! to 65535. combine takes binary or armored share files and
+//! writes the secret to PATH or stdout. The randomness comes from
+//! /dev/urandom.
+//!
+//! vectors prints golden test vectors of the given codecs (all by
+//! default) as JSON lines on stdout.
+
+use std::env;
+use std::fs;
+use std::io::{ self, Read, Write };
+use std::process;
+
+use gf256::rs::erasure::ErasureCode;
+use gf256::rs::header::ShardHeader;
+use gf256::shamir;
+use gf256::shares::{ self, EncodedShare, FieldShare };
+use gf256::vectors::{ self, Codec, Params };
+
+const USAGE: &str = "usage: gf256 encode [--data K] [--parity M] FILE
+       gf256 verify FILE
+       gf256 repair [--output PATH] FILE
+       gf256 split [--threshold K] [--shares N] [--text] [--output PREFIX] [FILE]
+       gf256 combine [--output PATH] SHARE ...
+       gf256 vectors [--seed N] [--count N] [--len N] [rs|shamir|crc32|scrambler ...]";
+
+/// The most shards a file can be split into
+const MAX_SHARDS: usize = 256;
+
+fn fail(msg: &str) -> ! {
+	eprintln!("{}\n{}", msg, USAGE);
+	process::exit(2);
+}
+
+/// Reports an error which is not a usage problem
+fn die(msg: &str) -> ! {
+	eprintln!("gf256: {}", msg);
+	process::exit(1);
+}
+
+fn number<T: std::str::FromStr>(value: Option<String>, flag: &str) -> T {
+	value.and_then(|v| v.parse().ok()).unwrap_or_else(|| fail(&format!("{} needs a number", flag)))
+}
+
+fn shard_path(file: &str, i: usize) -> String {
+	format!("{}.{:03}", file, i)
+}
+
+fn encode(mut args: impl Iterator<Item = String>) {
+	let (mut k, mut m, mut file) = (10usize, 4usize, None);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--data" => k = number(args.next(), "--data"),
+			"--parity" => m = number(args.next(), "--parity"),
+			_ if file.is_none() => file = Some(arg),
+			_ => fail("more than one file given"),
+		}
+	}
+	let file = file.unwrap_or_else(|| fail("no file given"));
+	let code = ErasureCode::new(k, m).unwrap_or_else(|e| fail(&e.to_string()));
+	let data = fs::read(&file).unwrap_or_else(|e| die(&format!("{}: {}", file, e)));
+	let mut stream = (data.len() as u64).to_le_bytes().to_vec();
+	stream.extend_from_slice(&data);
+	let size = stream.len().div_ceil(k);
+	stream.resize(size * k, 0);
+	let shards: Vec<&[u8]> = stream.chunks(size).collect();
+	let framed = code.encode_framed(&shards).unwrap_or_else(|e| die(&e.to_string()));
+	for (i, shard) in framed.iter().enumerate() {
+		let path = shard_path(&file, i);
+		fs::write(&path, shard).unwrap_or_else(|e| die(&format!("{}: {}", path, e)));
+	}
+	println!("{}: {} data and {} parity shards of {} bytes", file, k, m, size);
+}
+
+/// The shard files of a file as found on disk
+struct Scan {
+	code: ErasureCode,
+	/// The payloads of the intact shards
+	shards: Vec<Option<Vec<u8>>>,
+	/// Whether a file exists for each shard
+	exists: Vec<bool>,
+}
+
+impl Scan {
+	fn new(file: &str) -> Scan {
+		let mut exists = vec![false; MAX_SHARDS];
+		let mut found: Vec<(ShardHeader, Vec<u8>)> = Vec::new();
+		for (i, e) in exists.iter_mut().enumerate() {
+			let Ok(bytes) = fs::read(shard_path(file, i)) else {
+				continue;
+			};
+			*e = true;
+			if let Ok((header, payload)) = ShardHeader::parse(&bytes) {
+				if header.index as usize == i {
+					found.push((header, payload.to_vec()));
+				}
+			}
+		}
+		// The geometry most intact shards agree on
+		let geometry = |h: &ShardHeader| (h.data_shards, h.parity_shards, h.stripe_size);
+		let (k, m, size) = found.iter().map(|(h, _)| geometry(h))
+			.max_by_key(|&g| found.iter().filter(|(h, _)| geometry(h) == g).count())
+			.unwrap_or_else(|| die(&format!("{}: no intact shards found", file)));
+		let code = ErasureCode::new(k as usize, m as usize).unwrap_or_else(|e| die(&e.to_string()));
+		let mut shards = vec![None; code.total_shards()];
+		for (h, payload) in found {
+			if geometry(&h) == (k, m, size) {
+				shards[h.index as usize] = Some(payload);
+			}
+		}
+		exists.truncate(code.total_shards());
+		Scan { code, shards, exists }
+	}
+
+	/// The indices of shards which are missing or damaged
+	fn bad(&self) -> Vec<usize> {
+		(0..self.shards.len()).filter(|&i| self.shards[i].is_none()).collect()
+	}
+
+	fn report(&self, file: &str) {
+		for i in self.bad() {
+			let state = if self.exists[i] { "damaged" } else { "missing" };
+			println!("{}: {}", shard_path(file, i), state);
+		}
+		let bad = self.bad().len();
+		let verdict = if bad == 0 {
+			"intact"
+		} else if bad <= self.code.parity_shards() {
+			"repairable"
+		} else {
+			"not repairable"
+		};
+		println!("{}: {} of {} shards intact, {}", file, self.shards.len() - bad, self.shards.len(), verdict);
+	}
+}
+
+/// The file argument, and the --output path if with_output allows it
+fn file_args(mut args: impl Iterator<Item = String>, with_output: bool) -> (String, Option<String>) {
+	let (mut file, mut output) = (None, None);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--output" if with_output => output = Some(args.next().unwrap_or_else(|| fail("--output needs a path"))),
+			_ if file.is_none() => file = Some(arg),
+			_ => fail("more than one file given"),
+		}
+	}
+	(file.unwrap_or_else(|| fail("no file given")), output)
+}
+
+fn verify(args: impl Iterator<Item = String>) {
+	let (file, _) = file_args(args, false);
+	let scan = Scan::new(&file);
+	scan.report(&file);
+	if !scan.bad().is_empty() {
+		process::exit(1);
+	}
+}
+
+fn repair(args: impl Iterator<Item = String>) {
+	let (file, output) = file_args(args, true);
+	let output = output.unwrap_or_else(|| file.clone());
+	let mut scan = Scan::new(&file);
+	scan.report(&file);
+	let bad = scan.bad();
+	scan.code.reconstruct(&mut scan.shards).unwrap_or_else(|e| die(&format!("{}: {}", file, e)));
+	let (k, m) = (scan.code.data_shards() as u16, scan.code.parity_shards() as u16);
+	for i in bad {
+		let payload = scan.shards[i].as_ref().expect("Reconstructed");
+		let path = shard_path(&file, i);
+		fs::write(&path, ShardHeader::frame(k, m, i as u16, payload))
+			.unwrap_or_else(|e| die(&format!("{}: {}", path, e)));
+		println!("{}: rebuilt", path);
+	}
+	let stream: Vec<u8> = scan.shards[..k as usize].iter().flat_map(|s| s.as_ref().expect("Reconstructed").iter().copied()).collect();
+	let len = stream.get(..8).map(|b| u64::from_le_bytes(b.try_into().expect("8 bytes")));
+	let data = len.and_then(|l| stream.get(8..8usize.checked_add(usize::try_from(l).ok()?)?))
+		.unwrap_or_else(|| die(&format!("{}: invalid file length in the shards", file)));
+	fs::write(&output, data).unwrap_or_else(|e| die(&format!("{}: {}", output, e)));
+	println!("{}: {} bytes written", output, data.len());
+}
+
+/// Fills buf from the operating system's random number generator
+fn os_random(buf: &mut [u8]) {
+	fs::File::open("/dev/urandom").and_then(|mut f| f.read_exact(buf))
+		.unwrap_or_else(|e| die(&format!("/dev/urandom: {}", e)));
+}
+
+fn split(mut args: impl Iterator<Item = String>) {
+	let (mut k, mut n, mut text, mut prefix, mut file) = (2u16, 3u16, false, None, None);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--threshold" => k = number(args.next(), "--threshold"),
+			"--shares" => n = number(args.next(), "--shares"),
+			"--text" => text = true,
+			"--output" => prefix = Some(args.next().unwrap_or_else(|| fail("--output needs a prefix"))),
+			_ if file.is_none() => file = Some(arg),
+			_ => fail("more than one file given"),
+		}
+	}
+	let secret = match file.as_deref() {
+		None | Some("-") => {
+			let mut buf = Vec::new();
+			io::stdin().read_to_end(&mut buf).unwrap_or_else(|e| die(&format!("stdin: {}", e)));
+			buf
+		},
+		Some(f) => fs::read(f).unwrap_or_else(|e| die(&format!("{}: {}", f, e))),
+	};
+	let prefix = prefix.or(file.filter(|f| f != "-")).unwrap_or_else(|| "secret".to_string());
+	let split: Result<Vec<FieldShare>, _> = match (u8::try_from(k), u8::try_from(n)) {
+		(Ok(k), Ok(n)) => shamir::split(&secret, k, n, os_random).map(|s| s.into_iter().map(FieldShare::from).collect()),
+		_ => shamir::wide::split(&secret, k, n, os_random).map(|s| s.into_iter().map(FieldShare::from).collect()),
+	};
+	let split = split.unwrap_or_else(|e| fail(&e.to_string()));
+	for share in shares::encode_set(split, os_random) {
+		let path = format!("{}.share{}", prefix, share.share.x());
+		let bytes = if text { share.to_armored().into_bytes() } else { share.to_bytes() };
+		fs::write(&path, bytes).unwrap_or_else(|e| die(&format!("{}: {}", path, e)));
+	}
+	println!("{}: {} shares, any {} of which recover {} bytes", prefix, n, k, secret.len());
+}
+
+fn combine(mut args: impl Iterator<Item = String>) {
+	let (mut output, mut files) = (None, Vec::new());
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--output" => output = Some(args.next().unwrap_or_else(|| fail("--output needs a path"))),
+			_ => files.push(arg),
+		}
+	}
+	if files.is_empty() {
+		fail("no share files given");
+	}
+	let shares: Vec<EncodedShare> = files.iter().map(|f| {
+		let bytes = fs::read(f).unwrap_or_else(|e| die(&format!("{}: {}", f, e)));
+		let share = match std::str::from_utf8(&bytes) {
+			Ok(text) if !bytes.starts_with(&shares::MAGIC) => EncodedShare::from_armored(text),
+			_ => EncodedShare::from_bytes(&bytes),
+		};
+		share.unwrap_or_else(|e| die(&format!("{}: {}", f, e)))
+	}).collect();
+	let secret = shares::recover(&shares).unwrap_or_else(|e| die(&e.to_string()));
+	match output {
+		Some(path) => fs::write(&path, &secret).unwrap_or_else(|e| die(&format!("{}: {}", path, e))),
+		None => io::stdout().write_all(&secret).unwrap_or_else(|e| die(&format!("stdout: {}", e))),
+	}
+}
+
+fn generate_vectors(mut args: impl Iterator<Item = String>) {
+	let mut seed = 0u64;
+	let mut params = Params::default();
+	let mut codecs = Vec::new();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--seed" => seed = number(args.next(), "--seed"),
+			"--count" => params.count = number(args.next(), "--count"),
+			"--len" => params.len = number(args.next(), "--len"),
+			name => codecs.push(name.parse::<Codec>().unwrap_or_else(|e| fail(&format!("{}: {}", e, name)))),
+		}
+	}
+	if codecs.is_empty() {
+		codecs.extend(Codec::ALL);
+	}
+	for codec in codecs {
+		match vectors::generate(codec, &params, seed) {
+			Ok(vs) => {
+				for v in vs {
+					println!("{}", v.to_json());
+				}
+			},
+			Err(e) => fail(&format!("{}: {}", codec, e)),
+		}
+	}
+}
+
+fn main() {
+	let mut args = env::args().skip(1);
+	match args.next().as_deref() {
+		Some("encode") => encode(args),
+		Some("verify") => verify(args),
+		Some("repair") => repair(args),
+		Some("split") => split(args),
+		Some("combine") => combine(args),
+		Some("vectors") => generate_vectors(args),
+		_ => fail("unknown command"),
+	}
+}
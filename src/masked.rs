@@ -0,0 +1,189 @@
+//! Masked arithmetic for side channel resistant prototypes.
+//!
+//! A Masked<S> holds a value as S = d + 1 Boolean shares whose sum is the
+//! value, with any d of them uniformly random. Linear operations (adding,
+//! squaring, multiplying by a public constant) work share by share.
+//! Multiplication of two masked values uses the ISW scheme, which needs
+//! S (S - 1) / 2 fresh random bytes and keeps every intermediate value
+//! independent of the secret as long as at most d of them leak.
+//!
+//! inv follows Rivain and Prouff: x^254 with four ISW products. Note that
+//! this only models the algorithm; whether compiled code actually keeps
+//! the shares apart is up to the target and compiler.
+//!
+//! Randomness is supplied as in shamir: rng has to fill the buffer with
+//! uniformly random bytes from a cryptographically secure source.
+
+use core::ops::Add;
+
+use crate::Gf256;
+
+/// A field element split into S additive shares
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Masked<const S: usize> {
+	shares: [Gf256; S],
+}
+
+impl<const S: usize> Masked<S> {
+	const VALID: () = assert!(S >= 1 && S <= 32, "Between 1 and 32 shares are supported");
+
+	/// Splits x into S shares using S - 1 random bytes
+	pub fn mask<R: FnMut(&mut [u8])>(x: Gf256, mut rng: R) -> Masked<S> {
+		let () = Self::VALID;
+		let mut random = [0u8; S];
+		rng(&mut random[1..]);
+		let mut shares = [Gf256::zero(); S];
+		let mut first = x;
+		for (s, &r) in shares.iter_mut().zip(&random).skip(1) {
+			*s = Gf256::from_byte(r);
+			first += *s;
+		}
+		shares[0] = first;
+		Masked { shares }
+	}
+
+	/// Builds a masked value from given shares
+	pub fn from_shares(shares: [Gf256; S]) -> Masked<S> {
+		Masked { shares }
+	}
+
+	pub fn shares(&self) -> &[Gf256; S] {
+		&self.shares
+	}
+
+	/// Recombines the shares into the plain value
+	pub fn unmask(&self) -> Gf256 {
+		self.shares.iter().fold(Gf256::zero(), |acc, &s| acc + s)
+	}
+
+	/// Adds a public constant, touching only the first share
+	pub fn add_const(&self, c: Gf256) -> Masked<S> {
+		let mut out = *self;
+		out.shares[0] += c;
+		out
+	}
+
+	/// Multiplies by a public constant
+	pub fn mul_const(&self, c: Gf256) -> Masked<S> {
+		let mut out = *self;
+		for s in out.shares.iter_mut() {
+			*s *= c;
+		}
+		out
+	}
+
+	/// Squares, which is linear in characteristic 2
+	pub fn square(&self) -> Masked<S> {
+		let mut out = *self;
+		for s in out.shares.iter_mut() {
+			*s = *s * *s;
+		}
+		out
+	}
+
+	/// Re-randomizes the shares without changing the value
+	pub fn refresh<R: FnMut(&mut [u8])>(&self, mut rng: R) -> Masked<S> {
+		let mut out = *self;
+		let mut random = [0u8; 1];
+		for i in 0..S {
+			for j in i + 1..S {
+				rng(&mut random);
+				let r = Gf256::from_byte(random[0]);
+				out.shares[i] += r;
+				out.shares[j] += r;
+			}
+		}
+		out
+	}
+
+	/// ISW multiplication
+	pub fn mul<R: FnMut(&mut [u8])>(&self, other: &Masked<S>, mut rng: R) -> Masked<S> {
+		let (a, b) = (&self.shares, &other.shares);
+		let mut c = [Gf256::zero(); S];
+		for (i, ci) in c.iter_mut().enumerate() {
+			*ci = a[i] * b[i];
+		}
+		let mut random = [0u8; 1];
+		for i in 0..S {
+			for j in i + 1..S {
+				rng(&mut random);
+				let r = Gf256::from_byte(random[0]);
+				// r_ji = (r_ij + a_i b_j) + a_j b_i, bracketed in that order
+				let rji = (r + a[i] * b[j]) + a[j] * b[i];
+				c[i] += r;
+				c[j] += rji;
+			}
+		}
+		Masked { shares: c }
+	}
+
+	/// Masked inverse x^254 (0 for 0), by the Rivain-Prouff chain
+	pub fn inv<R: FnMut(&mut [u8])>(&self, mut rng: R) -> Masked<S> {
+		let x2 = self.square().refresh(&mut rng);
+		let x3 = x2.mul(self, &mut rng);
+		let x12 = x3.square().square();
+		let x15 = x12.refresh(&mut rng).mul(&x3, &mut rng);
+		let x240 = x15.square().square().square().square();
+		let x252 = x240.mul(&x12, &mut rng);
+		x252.mul(&x2, &mut rng)
+	}
+}
+
+impl<const S: usize> Add<Masked<S>> for Masked<S> {
+	type Output = Masked<S>;
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Masked<S>) -> Masked<S> {
+		let mut out = self;
+		for (s, r) in out.shares.iter_mut().zip(rhs.shares) {
+			*s += r;
+		}
+		out
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+
+    #[test]
+    fn test_mask_unmask() {
+        let mut rng = test_rng(1);
+        let x = Masked::<4>::mask(Gf256::from_byte(0x5a), &mut rng);
+        assert_eq!(x.unmask(), Gf256::from_byte(0x5a));
+        assert_ne!(x.shares()[0], Gf256::from_byte(0x5a));
+        let fresh = x.refresh(&mut rng);
+        assert_ne!(fresh, x);
+        assert_eq!(fresh.unmask(), x.unmask());
+    }
+
+    #[test]
+    fn test_operations() {
+        let mut rng = test_rng(2);
+        for a in (0..=255u8).step_by(7) {
+            for b in (0..=255u8).step_by(13) {
+                let (ga, gb) = (Gf256::from_byte(a), Gf256::from_byte(b));
+                let ma = Masked::<3>::mask(ga, &mut rng);
+                let mb = Masked::<3>::mask(gb, &mut rng);
+                assert_eq!((ma + mb).unmask(), ga + gb);
+                assert_eq!(ma.mul(&mb, &mut rng).unmask(), ga * gb);
+                assert_eq!(ma.mul_const(gb).add_const(ga).unmask(), ga * gb + ga);
+                assert_eq!(ma.square().unmask(), ga * ga);
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut rng = test_rng(3);
+        for x in 0..=255u8 {
+            let m = Masked::<2>::mask(Gf256::from_byte(x), &mut rng);
+            let expected = Gf256::from_byte(x).inv().unwrap_or(Gf256::zero());
+            assert_eq!(m.inv(&mut rng).unmask(), expected);
+        }
+        // A single share is unmasked arithmetic
+        let one = Masked::<1>::mask(Gf256::from_byte(9), &mut rng);
+        assert_eq!(one.shares(), &[Gf256::from_byte(9)]);
+    }
+}
@@ -0,0 +1,665 @@
+//! Dense matrices over Gf256.
+//!
+//! Matrix stores its elements row by row in one buffer. Sizes are checked
+//! when a matrix is built from outside data, which reports a MatrixError;
+//! the arithmetic operators panic on mismatched shapes instead, like
+//! slice indexing does.
+//!
+//! rref, rank and inverse share one Gauss-Jordan elimination. Every
+//! nonzero element is invertible, so the first nonzero entry of a column
+//! serves as pivot; there is no rounding to care about. solve_many runs
+//! the same elimination on [A | B] and reads the solution off the right.
+//!
+//! The Vandermonde, Cauchy and systematic constructors give the usual
+//! generator matrices of MDS erasure codes.
+
+use core::fmt;
+use core::error;
+use core::ops::{ Add, Sub, Mul, Index, IndexMut };
+use alloc::{ vec, vec::Vec };
+
+use crate::{ Gf256, as_byte_slice, as_byte_slice_mut };
+use crate::backend::{ self, Backend };
+
+/// Errors produced by matrix operations
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum MatrixError {
+	/// The data does not fit the requested shape, or rows differ in length
+	DimensionMismatch,
+	/// The linear system has no solution
+	Inconsistent,
+	/// The linear system has more than one solution
+	Underdetermined,
+	/// The points of a Cauchy matrix are not all distinct
+	DuplicatePoint,
+	/// A matrix over GF(2) has an entry other than 0 and 1
+	NotBinary,
+}
+
+impl fmt::Display for MatrixError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			MatrixError::DimensionMismatch => "dimension mismatch",
+			MatrixError::Inconsistent => "inconsistent system",
+			MatrixError::Underdetermined => "underdetermined system",
+			MatrixError::DuplicatePoint => "duplicate point",
+			MatrixError::NotBinary => "entry is not 0 or 1",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for MatrixError {}
+
+/// A rows x cols matrix of field elements
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct Matrix {
+	rows: usize,
+	cols: usize,
+	/// Row-major
+	data: Vec<Gf256>
+}
+
+impl Matrix {
+	pub fn zero(rows: usize, cols: usize) -> Matrix {
+		Matrix { rows, cols, data: vec![Gf256::zero(); rows * cols] }
+	}
+
+	pub fn identity(n: usize) -> Matrix {
+		let mut m = Matrix::zero(n, n);
+		for i in 0..n {
+			m[(i, i)] = Gf256::one();
+		}
+		m
+	}
+
+	/// Entry (i, j) is i^j, reading i as a field element. Any cols rows
+	/// are independent since the points are distinct, so rows is at most
+	/// 256.
+	pub fn vandermonde(rows: usize, cols: usize) -> Matrix {
+		assert!(rows <= 256, "Too many rows");
+		let mut m = Matrix::zero(rows, cols);
+		for i in 0..rows {
+			let x = Gf256::from_byte(i as u8);
+			let mut acc = Gf256::one();
+			for a in m.row_mut(i) {
+				*a = acc;
+				acc *= x;
+			}
+		}
+		m
+	}
+
+	/// Entry (i, j) is 1 / (xs[i] + ys[j]). All points must be distinct,
+	/// and then every square submatrix is invertible.
+	pub fn cauchy(xs: &[Gf256], ys: &[Gf256]) -> Result<Matrix, MatrixError> {
+		let all: Vec<Gf256> = xs.iter().chain(ys).copied().collect();
+		if (1..all.len()).any(|i| all[..i].contains(&all[i])) {
+			return Err(MatrixError::DuplicatePoint);
+		}
+		let data = xs.iter()
+			.flat_map(|&x| ys.iter().map(move |&y| (x + y).inv().expect("Points are distinct")))
+			.collect();
+		Ok(Matrix { rows: xs.len(), cols: ys.len(), data })
+	}
+
+	/// An encoding matrix with data + parity rows and data columns whose
+	/// top rows are the identity, so the data shards pass through
+	/// unchanged. It is the Vandermonde matrix times the inverse of its top
+	/// square, and keeps the property that any data rows are independent.
+	pub fn systematic(data: usize, parity: usize) -> Matrix {
+		let v = Matrix::vandermonde(data + parity, data);
+		let top = Matrix::from_slice(data, data, &v.data[..data * data]).expect("Sizes match");
+		let inv = top.inverse().expect("Vandermonde squares are invertible");
+		&v * &inv
+	}
+
+	/// The 8 x n matrix over GF(2) whose column j holds the coefficients
+	/// of elements[j], the one of x^i in row i
+	pub fn from_bit_columns(elements: &[Gf256]) -> Matrix {
+		let mut m = Matrix::zero(8, elements.len());
+		for (j, e) in elements.iter().enumerate() {
+			for (i, &bit) in e.to_bits().iter().enumerate() {
+				m[(i, j)] = Gf256::from_byte(bit as u8);
+			}
+		}
+		m
+	}
+
+	/// The elements of the columns of an 8 row matrix over GF(2), the
+	/// inverse of from_bit_columns
+	pub fn to_bit_columns(&self) -> Result<Vec<Gf256>, MatrixError> {
+		if self.rows != 8 {
+			return Err(MatrixError::DimensionMismatch);
+		}
+		if self.data.iter().any(|a| a.to_byte() > 1) {
+			return Err(MatrixError::NotBinary);
+		}
+		Ok((0..self.cols).map(|j| Gf256::from_bits(core::array::from_fn(|i| self[(i, j)] == Gf256::one()))).collect())
+	}
+
+	/// The matrix over GF(2) of the same linear map on bit vectors: entry
+	/// c becomes the 8 x 8 block of multiplication by c, whose column x
+	/// holds the bits of c * x^x. Products and inverses carry over block
+	/// by block.
+	pub fn binary_expansion(&self) -> Matrix {
+		let mut out = Matrix::zero(8 * self.rows, 8 * self.cols);
+		for i in 0..self.rows {
+			for j in 0..self.cols {
+				let mut e = self[(i, j)];
+				for x in 0..8 {
+					for (l, &bit) in e.to_bits().iter().enumerate() {
+						out[(8 * i + l, 8 * j + x)] = Gf256::from_byte(bit as u8);
+					}
+					e *= Gf256::GENERATOR;
+				}
+			}
+		}
+		out
+	}
+
+	/// Takes rows * cols elements in row-major order
+	pub fn from_slice(rows: usize, cols: usize, data: &[Gf256]) -> Result<Matrix, MatrixError> {
+		if data.len() != rows * cols {
+			return Err(MatrixError::DimensionMismatch);
+		}
+		Ok(Matrix { rows, cols, data: data.to_vec() })
+	}
+
+	/// Like from_slice, for raw bytes
+	pub fn from_bytes(rows: usize, cols: usize, data: &[u8]) -> Result<Matrix, MatrixError> {
+		let data: Vec<Gf256> = data.iter().map(|&b| Gf256::from_byte(b)).collect();
+		Matrix::from_slice(rows, cols, &data)
+	}
+
+	/// Builds a matrix from rows of equal length
+	pub fn from_rows<R: AsRef<[Gf256]>>(rows: &[R]) -> Result<Matrix, MatrixError> {
+		let cols = rows.first().map_or(0, |r| r.as_ref().len());
+		let mut data = Vec::with_capacity(rows.len() * cols);
+		for r in rows {
+			if r.as_ref().len() != cols {
+				return Err(MatrixError::DimensionMismatch);
+			}
+			data.extend_from_slice(r.as_ref());
+		}
+		Ok(Matrix { rows: rows.len(), cols, data })
+	}
+
+	#[inline]
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+
+	#[inline]
+	pub fn cols(&self) -> usize {
+		self.cols
+	}
+
+	pub fn is_square(&self) -> bool {
+		self.rows == self.cols
+	}
+
+	pub fn row(&self, i: usize) -> &[Gf256] {
+		&self.data[i * self.cols..(i + 1) * self.cols]
+	}
+
+	pub fn row_mut(&mut self, i: usize) -> &mut [Gf256] {
+		&mut self.data[i * self.cols..(i + 1) * self.cols]
+	}
+
+	/// All elements, row-major
+	pub fn as_slice(&self) -> &[Gf256] {
+		&self.data
+	}
+
+	/// All elements, row-major, for filling rows in parallel
+	pub fn as_mut_slice(&mut self) -> &mut [Gf256] {
+		&mut self.data
+	}
+
+	/// Copies the rows into separate vectors, the layout used by msr and
+	/// CyclicCode::generator_matrix
+	pub fn to_rows(&self) -> Vec<Vec<Gf256>> {
+		(0..self.rows).map(|i| self.row(i).to_vec()).collect()
+	}
+
+	pub fn transpose(&self) -> Matrix {
+		let mut t = Matrix::zero(self.cols, self.rows);
+		for i in 0..self.rows {
+			for j in 0..self.cols {
+				t[(j, i)] = self[(i, j)];
+			}
+		}
+		t
+	}
+
+	/// self * v for a column vector v with cols elements, one bytes::dot
+	/// of a row and v per element, so rows are multiplied by
+	/// mul_elementwise.
+	pub fn mul_vec(&self, v: &[Gf256]) -> Vec<Gf256> {
+		assert_eq!(v.len(), self.cols, "Dimension mismatch");
+		(0..self.rows).map(|i| crate::dot(self.row(i), v)).collect()
+	}
+
+	/// outputs[i] = sum over j of self[(i, j)] * inputs[j], for byte
+	/// slices of one length such as shards, with the bulk kernels of
+	/// bytes::mul_add. Panics if the counts do not match the shape.
+	pub fn mul_slice_rows<I: AsRef<[u8]>, O: AsMut<[u8]>>(&self, inputs: &[I], outputs: &mut [O]) {
+		self.mul_slice_rows_with(backend::best_available(), inputs, outputs);
+	}
+
+	/// mul_slice_rows with the multiplications done by backend
+	pub fn mul_slice_rows_with<I: AsRef<[u8]>, O: AsMut<[u8]>>(&self, backend: &dyn Backend,
+		inputs: &[I], outputs: &mut [O]) {
+		assert_eq!(inputs.len(), self.cols, "Dimension mismatch");
+		assert_eq!(outputs.len(), self.rows, "Dimension mismatch");
+		for (i, out) in outputs.iter_mut().enumerate() {
+			let out = out.as_mut();
+			out.fill(0);
+			for (input, c) in inputs.iter().zip(self.row(i)) {
+				backend.mul_add(out, input.as_ref(), c.to_byte());
+			}
+		}
+	}
+
+	/// self * rhs with the rows of the product accumulated by backend,
+	/// whatever the shape. Panics if self.cols() != rhs.rows().
+	pub fn mul_with(&self, rhs: &Matrix, backend: &dyn Backend) -> Matrix {
+		assert_eq!(self.cols, rhs.rows, "Dimension mismatch");
+		let mut out = Matrix::zero(self.rows, rhs.cols);
+		if rhs.cols == 0 {
+			return out;
+		}
+		let inputs: Vec<&[u8]> = (0..rhs.rows).map(|k| as_byte_slice(rhs.row(k))).collect();
+		let mut outputs: Vec<&mut [u8]> = out.data.chunks_mut(rhs.cols).map(as_byte_slice_mut).collect();
+		self.mul_slice_rows_with(backend, &inputs, &mut outputs);
+		out
+	}
+
+	/// [self | other], the columns of other appended to those of self
+	pub fn augment(&self, other: &Matrix) -> Matrix {
+		assert_eq!(self.rows, other.rows, "Dimension mismatch");
+		let mut data = Vec::with_capacity(self.data.len() + other.data.len());
+		for i in 0..self.rows {
+			data.extend_from_slice(self.row(i));
+			data.extend_from_slice(other.row(i));
+		}
+		Matrix { rows: self.rows, cols: self.cols + other.cols, data }
+	}
+
+	/// The columns from start to end (exclusive)
+	pub fn columns(&self, start: usize, end: usize) -> Matrix {
+		assert!(start <= end && end <= self.cols, "Index out of range");
+		let mut data = Vec::with_capacity(self.rows * (end - start));
+		for i in 0..self.rows {
+			data.extend_from_slice(&self.row(i)[start..end]);
+		}
+		Matrix { rows: self.rows, cols: end - start, data }
+	}
+
+	fn swap_rows(&mut self, a: usize, b: usize) {
+		if a != b {
+			for j in 0..self.cols {
+				self.data.swap(a * self.cols + j, b * self.cols + j);
+			}
+		}
+	}
+
+	/// Brings the first limit columns into reduced row echelon form and
+	/// returns the pivot column of each nonzero row. The remaining columns
+	/// undergo the same row operations.
+	fn eliminate(&mut self, limit: usize) -> Vec<usize> {
+		let mut pivots = Vec::new();
+		for col in 0..limit {
+			let r = pivots.len();
+			let pivot = match (r..self.rows).find(|&i| self[(i, col)] != Gf256::zero()) {
+				Some(p) => p,
+				None => continue,
+			};
+			self.swap_rows(r, pivot);
+			let scale = self[(r, col)].inv().expect("Pivot is nonzero");
+			for a in self.row_mut(r) {
+				*a *= scale;
+			}
+			let pivot_row = self.row(r).to_vec();
+			for i in 0..self.rows {
+				let f = self[(i, col)];
+				if i == r || f == Gf256::zero() {
+					continue;
+				}
+				for (a, &b) in self.row_mut(i).iter_mut().zip(&pivot_row) {
+					*a -= f * b;
+				}
+			}
+			pivots.push(col);
+			if pivots.len() == self.rows {
+				break;
+			}
+		}
+		pivots
+	}
+
+	/// The reduced row echelon form
+	pub fn rref(&self) -> Matrix {
+		let mut m = self.clone();
+		m.eliminate(m.cols);
+		m
+	}
+
+	pub fn rank(&self) -> usize {
+		self.clone().eliminate(self.cols).len()
+	}
+
+	/// None if the matrix is not square or singular
+	pub fn inverse(&self) -> Option<Matrix> {
+		if !self.is_square() {
+			return None;
+		}
+		let n = self.rows;
+		let mut m = self.augment(&Matrix::identity(n));
+		if m.eliminate(n).len() < n {
+			return None;
+		}
+		Some(m.columns(n, 2 * n))
+	}
+
+	/// Solves self * x = b, None unless there is exactly one solution
+	pub fn solve(&self, b: &[Gf256]) -> Option<Vec<Gf256>> {
+		let b = Matrix::from_slice(b.len(), 1, b).ok()?;
+		self.solve_many(&b).ok().map(|x| x.data)
+	}
+
+	/// Solves self * X = B for all columns of B at once. Rows beyond the
+	/// rank must be consistent, so an overdetermined system with a
+	/// solution is fine.
+	pub fn solve_many(&self, b: &Matrix) -> Result<Matrix, MatrixError> {
+		if b.rows != self.rows {
+			return Err(MatrixError::DimensionMismatch);
+		}
+		let mut m = self.augment(b);
+		let rank = m.eliminate(self.cols).len();
+		if (rank..m.rows).any(|i| m.row(i)[self.cols..].iter().any(|&a| a != Gf256::zero())) {
+			return Err(MatrixError::Inconsistent);
+		}
+		if rank < self.cols {
+			return Err(MatrixError::Underdetermined);
+		}
+		// Full column rank, so row k has its pivot in column k
+		m.rows = self.cols;
+		m.data.truncate(self.cols * m.cols);
+		Ok(m.columns(self.cols, m.cols))
+	}
+
+	pub fn scale(&self, c: Gf256) -> Matrix {
+		let data = self.data.iter().map(|&a| a * c).collect();
+		Matrix { rows: self.rows, cols: self.cols, data }
+	}
+}
+
+impl Index<(usize, usize)> for Matrix {
+	type Output = Gf256;
+	fn index(&self, (i, j): (usize, usize)) -> &Gf256 {
+		assert!(i < self.rows && j < self.cols, "Index out of range");
+		&self.data[i * self.cols + j]
+	}
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+	fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Gf256 {
+		assert!(i < self.rows && j < self.cols, "Index out of range");
+		&mut self.data[i * self.cols + j]
+	}
+}
+
+impl Add<&Matrix> for &Matrix {
+	type Output = Matrix;
+	/// Panics if the shapes differ
+	fn add(self, rhs: &Matrix) -> Matrix {
+		assert!(self.rows == rhs.rows && self.cols == rhs.cols, "Dimension mismatch");
+		let data = self.data.iter().zip(&rhs.data).map(|(&a, &b)| a + b).collect();
+		Matrix { rows: self.rows, cols: self.cols, data }
+	}
+}
+
+impl Sub<&Matrix> for &Matrix {
+	type Output = Matrix;
+	/// The same as addition in characteristic 2
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: &Matrix) -> Matrix {
+		self + rhs
+	}
+}
+
+/// Rows at least this long are multiplied by the bulk kernels; shorter
+/// ones do not pay for the kernel setup
+const KERNEL_MIN_COLS: usize = 64;
+
+impl Mul<&Matrix> for &Matrix {
+	type Output = Matrix;
+	/// Panics if self.cols() != rhs.rows()
+	fn mul(self, rhs: &Matrix) -> Matrix {
+		assert_eq!(self.cols, rhs.rows, "Dimension mismatch");
+		if rhs.cols >= KERNEL_MIN_COLS {
+			return self.mul_with(rhs, backend::best_available());
+		}
+		let mut out = Matrix::zero(self.rows, rhs.cols);
+		for i in 0..self.rows {
+			for (k, &a) in self.row(i).iter().enumerate() {
+				if a == Gf256::zero() {
+					continue;
+				}
+				for (c, &b) in out.row_mut(i).iter_mut().zip(rhs.row(k)) {
+					*c += a * b;
+				}
+			}
+		}
+		out
+	}
+}
+
+impl Mul<Gf256> for &Matrix {
+	type Output = Matrix;
+	fn mul(self, rhs: Gf256) -> Matrix {
+		self.scale(rhs)
+	}
+}
+
+macro_rules! impl_owned_op {
+	($tr:ident, $method:ident) => {
+		impl $tr<Matrix> for Matrix {
+			type Output = Matrix;
+			fn $method(self, rhs: Matrix) -> Matrix {
+				$tr::$method(&self, &rhs)
+			}
+		}
+
+		impl $tr<&Matrix> for Matrix {
+			type Output = Matrix;
+			fn $method(self, rhs: &Matrix) -> Matrix {
+				$tr::$method(&self, rhs)
+			}
+		}
+	};
+}
+
+impl_owned_op!(Add, add);
+impl_owned_op!(Sub, sub);
+impl_owned_op!(Mul, mul);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(rows: usize, cols: usize, bytes: &[u8]) -> Matrix {
+        Matrix::from_bytes(rows, cols, bytes).unwrap()
+    }
+
+    #[test]
+    fn test_construction() {
+        let a = m(2, 3, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(a[(1, 0)], Gf256::from_byte(4));
+        assert_eq!(a.row(0), &a.as_slice()[..3]);
+        assert_eq!(Matrix::from_rows(&a.to_rows()), Ok(a.clone()));
+        assert_eq!(Matrix::from_bytes(2, 2, &[1, 2, 3]), Err(MatrixError::DimensionMismatch));
+        let ragged = [vec![Gf256::one()], vec![]];
+        assert_eq!(Matrix::from_rows(&ragged), Err(MatrixError::DimensionMismatch));
+        let t = a.transpose();
+        assert_eq!((t.rows(), t.cols()), (3, 2));
+        assert_eq!(t[(2, 1)], Gf256::from_byte(6));
+        assert_eq!(t.transpose(), a);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = m(2, 2, &[1, 2, 3, 4]);
+        let b = m(2, 2, &[5, 6, 7, 8]);
+        let i = Matrix::identity(2);
+        assert_eq!(&a * &i, a);
+        assert_eq!(&a + &a, Matrix::zero(2, 2));
+        assert_eq!(&a - &b, &a + &b);
+        // (ab)^T = b^T a^T
+        assert_eq!((&a * &b).transpose(), b.transpose() * a.transpose());
+        let c = m(2, 3, &[9, 10, 11, 12, 13, 14]);
+        // Distributive over addition
+        assert_eq!(&(&a + &b) * &c, &a * &c + &b * &c);
+        let g = Gf256::from_byte(2);
+        assert_eq!(a.mul_vec(&[g, g]), (&a * g).mul_vec(&[Gf256::one(); 2]));
+        assert_eq!((&a * &c).row(1)[2], a[(1, 0)] * c[(0, 2)] + a[(1, 1)] * c[(1, 2)]);
+    }
+
+    #[test]
+    fn test_kernel_products() {
+        let a = Matrix::from_bytes(3, 4, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        let b = Matrix::from_bytes(4, 100, &(0..400).map(|i| (i * 7 + i / 3) as u8).collect::<Vec<_>>()).unwrap();
+        // The kernel product agrees with column by column products
+        let p = &a * &b;
+        for j in 0..100 {
+            let col: Vec<Gf256> = (0..4).map(|k| b[(k, j)]).collect();
+            assert_eq!(a.mul_vec(&col), (0..3).map(|i| p[(i, j)]).collect::<Vec<_>>());
+        }
+        let inputs: Vec<&[u8]> = (0..4).map(|k| as_byte_slice(b.row(k))).collect();
+        let mut outputs = vec![vec![0xffu8; 100]; 3];
+        a.mul_slice_rows(&inputs, &mut outputs);
+        for (i, out) in outputs.iter().enumerate() {
+            assert_eq!(out[..], *as_byte_slice(p.row(i)));
+        }
+        // Any backend, also below the kernel threshold
+        assert_eq!(a.mul_with(&b, &backend::Scalar), p);
+        let narrow = b.columns(0, 5);
+        assert_eq!(a.mul_with(&narrow, &backend::Clmul), &a * &narrow);
+        assert_eq!(a.mul_with(&b.columns(0, 0), &backend::Scalar), Matrix::zero(3, 0));
+    }
+
+    #[test]
+    fn test_bit_matrices() {
+        let elements = [Gf256::from_byte(0x85), Gf256::from_byte(0x02), Gf256::zero()];
+        let b = Matrix::from_bit_columns(&elements);
+        assert_eq!(b.rows(), 8);
+        assert_eq!((0..8).map(|i| b[(i, 0)].to_byte()).collect::<Vec<_>>(), [1, 0, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(b.to_bit_columns().unwrap(), elements);
+        assert_eq!(Matrix::zero(7, 2).to_bit_columns(), Err(MatrixError::DimensionMismatch));
+        assert_eq!(Matrix::from_bytes(8, 1, &[2; 8]).unwrap().to_bit_columns(), Err(MatrixError::NotBinary));
+
+        // The expansion acts on bit columns as the matrix on elements
+        let a = m(2, 3, &[1, 0x53, 7, 0x8e, 2, 0xff]);
+        let v = [Gf256::from_byte(9), Gf256::from_byte(0xca), Gf256::from_byte(0x31)];
+        let bits: Vec<Gf256> = v.iter().flat_map(|e| e.to_bits().map(|b| Gf256::from_byte(b as u8))).collect();
+        let out = a.binary_expansion().mul_vec(&bits);
+        let expected = a.mul_vec(&v);
+        for (i, e) in expected.iter().enumerate() {
+            let got: [bool; 8] = core::array::from_fn(|l| out[8 * i + l] == Gf256::one());
+            assert_eq!(Gf256::from_bits(got), *e);
+        }
+        let sq = m(2, 2, &[3, 1, 4, 1]);
+        assert_eq!(sq.inverse().unwrap().binary_expansion(), sq.binary_expansion().inverse().unwrap());
+    }
+
+    #[test]
+    fn test_rref_rank() {
+        let a = m(3, 3, &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let r = a.rref();
+        assert_eq!(r.rank(), a.rank());
+        assert_eq!(a.rank() == 3, a.inverse().is_some());
+        // The second row is twice the first
+        let d = m(3, 4, &[1, 2, 3, 4, 2, 4, 6, 8, 0, 1, 0, 1]);
+        assert_eq!(d.rank(), 2);
+        assert_eq!(d.rref(), m(3, 4, &[1, 0, 3, 6, 0, 1, 0, 1, 0, 0, 0, 0]));
+        assert_eq!(Matrix::zero(2, 5).rank(), 0);
+        assert_eq!(Matrix::identity(4).rref(), Matrix::identity(4));
+        assert_eq!(d.transpose().rank(), 2);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let a = m(3, 3, &[1, 2, 3, 0, 1, 4, 5, 6, 0]);
+        let inv = a.inverse().unwrap();
+        assert_eq!(&a * &inv, Matrix::identity(3));
+        assert_eq!(&inv * &a, Matrix::identity(3));
+        assert_eq!(inv.inverse(), Some(a));
+        assert_eq!(m(2, 2, &[1, 1, 1, 1]).inverse(), None);
+        assert_eq!(Matrix::zero(2, 3).inverse(), None);
+        // Requires a row swap: the first pivot candidate is zero
+        let s = m(2, 2, &[0, 1, 1, 0]);
+        assert_eq!(s.inverse(), Some(s));
+    }
+
+    /// Every size-square selection of rows is invertible
+    fn check_mds(g: &Matrix, size: usize) {
+        let n = g.rows();
+        for mask in 0u32..1 << n {
+            if mask.count_ones() as usize != size {
+                continue;
+            }
+            let rows: Vec<&[Gf256]> = (0..n).filter(|&i| mask >> i & 1 != 0).map(|i| g.row(i)).collect();
+            let sub = Matrix::from_rows(&rows).unwrap();
+            assert!(sub.inverse().is_some(), "rows {:b}", mask);
+        }
+    }
+
+    #[test]
+    fn test_constructors() {
+        let v = Matrix::vandermonde(4, 3);
+        assert_eq!(v.row(0), &[Gf256::one(), Gf256::zero(), Gf256::zero()]);
+        assert_eq!(v[(3, 2)], Gf256::from_byte(3) * Gf256::from_byte(3));
+        check_mds(&v, 3);
+
+        let g = Matrix::systematic(4, 3);
+        assert_eq!(Matrix::from_slice(4, 4, &g.as_slice()[..16]), Ok(Matrix::identity(4)));
+        check_mds(&g, 4);
+
+        let xs: Vec<Gf256> = (0..3).map(Gf256::from_byte).collect();
+        let ys: Vec<Gf256> = (3..7).map(Gf256::from_byte).collect();
+        let c = Matrix::cauchy(&xs, &ys).unwrap();
+        assert_eq!(c[(1, 2)] * (xs[1] + ys[2]), Gf256::one());
+        check_mds(&c.transpose(), 3);
+        assert_eq!(Matrix::cauchy(&xs, &xs[..1]), Err(MatrixError::DuplicatePoint));
+        assert_eq!(Matrix::cauchy(&[xs[0], xs[0]], &ys), Err(MatrixError::DuplicatePoint));
+    }
+
+    #[test]
+    fn test_solve() {
+        let a = m(3, 3, &[1, 2, 3, 0, 1, 4, 5, 6, 0]);
+        let x: Vec<Gf256> = [7, 8, 9].iter().map(|&b| Gf256::from_byte(b)).collect();
+        let b = a.mul_vec(&x);
+        assert_eq!(a.solve(&b), Some(x.clone()));
+        assert_eq!(a.solve(&b[..2]), None);
+
+        // Several right-hand sides: a * a^-1 = I
+        assert_eq!(a.solve_many(&Matrix::identity(3)), Ok(a.inverse().unwrap()));
+
+        // Overdetermined but consistent
+        let tall = Matrix::from_rows(&[a.row(0), a.row(1), a.row(2), a.row(0)]).unwrap();
+        let mut tb = b.clone();
+        tb.push(b[0]);
+        assert_eq!(tall.solve(&tb), Some(x));
+        tb[3] += Gf256::one();
+        let tb = Matrix::from_slice(4, 1, &tb).unwrap();
+        assert_eq!(tall.solve_many(&tb), Err(MatrixError::Inconsistent));
+
+        let wide = m(2, 3, &[1, 2, 3, 4, 5, 6]);
+        let wb = Matrix::from_bytes(2, 1, &[1, 1]).unwrap();
+        assert_eq!(wide.solve_many(&wb), Err(MatrixError::Underdetermined));
+        assert_eq!(wide.solve_many(&Matrix::zero(3, 1)), Err(MatrixError::DimensionMismatch));
+    }
+}
@@ -0,0 +1,193 @@
+//! GF(256) over other reduction polynomials.
+//!
+//! Gf256 is fixed to x^8 + x^4 + x^3 + x^2 + 1. Gf256Mod<POLY, GENERATOR>
+//! is the same field constructed with a different degree 8 polynomial
+//! (and primitive element for its log tables), e.g. the AES field:
+//!
+//!     use gf256::modular::Aes;
+//!     assert_eq!(Aes::from_byte(0x57) * Aes::from_byte(0x83), Aes::from_byte(0xc1));
+//!
+//! Every choice of polynomial is its own type with its own tables, built
+//! at compile time by the tables module, so elements of different
+//! representations cannot be mixed by accident. An invalid polynomial or
+//! generator fails the build on first use.
+
+use core::fmt;
+use core::ops::{ Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign };
+
+use crate::ctx::Tables;
+use crate::tables;
+
+/// The field of FIPS 197 (AES), x^8 + x^4 + x^3 + x + 1 with generator x + 1
+pub type Aes = Gf256Mod<0x11b, 0x03>;
+
+/// An element of GF(256) represented modulo POLY
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+#[repr(transparent)]
+pub struct Gf256Mod<const POLY: u16, const GENERATOR: u8> {
+	pub poly: u8
+}
+
+impl<const POLY: u16, const GENERATOR: u8> Gf256Mod<POLY, GENERATOR> {
+	const TABLES: &'static Tables = &tables::tables(POLY, GENERATOR);
+
+	pub const POLY: u16 = POLY;
+	pub const GENERATOR: u8 = GENERATOR;
+
+	#[inline]
+	pub const fn zero() -> Self {
+		Gf256Mod { poly: 0 }
+	}
+
+	#[inline]
+	pub const fn one() -> Self {
+		Gf256Mod { poly: 1 }
+	}
+
+	#[inline]
+	pub const fn from_byte(b: u8) -> Self {
+		Gf256Mod { poly: b }
+	}
+
+	#[inline]
+	pub const fn to_byte(&self) -> u8 {
+		self.poly
+	}
+
+	/// Logarithm to the base GENERATOR, None for zero
+	pub const fn log(&self) -> Option<u8> {
+		if self.poly == 0 {
+			None
+		} else {
+			Some(Self::TABLES.log[self.poly as usize])
+		}
+	}
+
+	/// GENERATOR ^ power
+	pub const fn xexp(power: u8) -> Self {
+		Gf256Mod { poly: Self::TABLES.exp[power as usize] }
+	}
+
+	pub const fn exp(&self, power: u8) -> Self {
+		match self.log() {
+			None => Self::zero(),
+			Some(i) => Self::xexp((((i as u16) * (power as u16)) % 255) as u8),
+		}
+	}
+
+	/// None for zero
+	pub const fn inv(&self) -> Option<Self> {
+		if self.poly == 0 {
+			None
+		} else {
+			Some(Gf256Mod { poly: Self::TABLES.inv[self.poly as usize] })
+		}
+	}
+
+	pub const fn const_mul(self, rhs: Self) -> Self {
+		match (self.log(), rhs.log()) {
+			(Some(l1), Some(l2)) => Self::xexp((((l1 as u16) + (l2 as u16)) % 255) as u8),
+			_ => Self::zero(),
+		}
+	}
+
+	/// self / rhs, or None if rhs is zero
+	pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+		match rhs.inv() {
+			Some(r) => Some(self.const_mul(r)),
+			None => None,
+		}
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> fmt::Display for Gf256Mod<POLY, GENERATOR> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(&self.poly, f)
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> fmt::LowerHex for Gf256Mod<POLY, GENERATOR> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::LowerHex::fmt(&self.poly, f)
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> Add for Gf256Mod<POLY, GENERATOR> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Self) -> Self {
+		Gf256Mod { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> Sub for Gf256Mod<POLY, GENERATOR> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: Self) -> Self {
+		Gf256Mod { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> Mul for Gf256Mod<POLY, GENERATOR> {
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self {
+		self.const_mul(rhs)
+	}
+}
+
+impl<const POLY: u16, const GENERATOR: u8> Div for Gf256Mod<POLY, GENERATOR> {
+	type Output = Self;
+	/// Panics if rhs is zero
+	fn div(self, rhs: Self) -> Self {
+		self.checked_div(rhs).expect("Division by zero")
+	}
+}
+
+macro_rules! impl_assign_op {
+	($tr:ident, $method:ident, $op:ident, $op_method:ident) => {
+		impl<const POLY: u16, const GENERATOR: u8> $tr for Gf256Mod<POLY, GENERATOR> {
+			#[inline]
+			fn $method(&mut self, rhs: Self) {
+				*self = $op::$op_method(*self, rhs);
+			}
+		}
+	};
+}
+
+impl_assign_op!(AddAssign, add_assign, Add, add);
+impl_assign_op!(SubAssign, sub_assign, Sub, sub);
+impl_assign_op!(MulAssign, mul_assign, Mul, mul);
+impl_assign_op!(DivAssign, div_assign, Div, div);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+
+    type Builtin = Gf256Mod<0x11d, 0x02>;
+
+    #[test]
+    fn test_aes() {
+        // FIPS 197, 4.2
+        assert_eq!(Aes::from_byte(0x57) * Aes::from_byte(0x13), Aes::from_byte(0xfe));
+        assert_eq!(Aes::from_byte(0x53).inv(), Some(Aes::from_byte(0xca)));
+        assert_eq!(Aes::xexp(1), Aes::from_byte(0x03));
+        let mut x = Aes::from_byte(0x57);
+        x /= Aes::from_byte(0x83);
+        assert_eq!(x * Aes::from_byte(0x83), Aes::from_byte(0x57));
+    }
+
+    #[test]
+    fn test_matches_gf256() {
+        for a in (0..=255u8).step_by(7) {
+            for b in 0..=255u8 {
+                let p = Builtin::from_byte(a) * Builtin::from_byte(b);
+                assert_eq!(p.to_byte(), (Gf256::from_byte(a) * Gf256::from_byte(b)).to_byte());
+            }
+            assert_eq!(Builtin::from_byte(a).log(), Gf256::from_byte(a).log());
+        }
+    }
+}
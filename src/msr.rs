@@ -0,0 +1,385 @@
+//! Minimum storage regenerating codes (product-matrix construction).
+//!
+//! Follows Rashmi, Shah and Kumar with d = 2k - 2 helpers. Every node
+//! stores alpha = k - 1 symbols and the file consists of B = k * alpha
+//! symbols, so storage is the same as for an (n, k) MDS code. When a node
+//! fails, each of d helpers sends a single symbol (beta = 1) computed from
+//! its own content, and the newcomer rebuilds exactly the lost content
+//! from those d symbols. Plain RS repair would read k whole shards, i.e.
+//! k * alpha symbols.
+//!
+//! A symbol here is a run of bytes, so nodes store alpha equally long
+//! sub-shards and every operation works bytewise across them.
+//!
+//! The message is laid out as two symmetric alpha x alpha matrices S1, S2
+//! stacked into M. Node i stores psi_i^T M with psi_i = (1, x_i, ..,
+//! x_i^(d-1)), which splits as phi_i = (1, .., x_i^(alpha-1)) and
+//! lambda_i = x_i^alpha.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::bytes;
+
+/// Errors produced by the regenerating code
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum MsrError {
+	/// k must be at least 2 and n at least d + 1, with n points available
+	InvalidParameters,
+	/// Data or node content has the wrong length
+	LengthMismatch,
+	/// Fewer nodes than required were given
+	NotEnoughNodes,
+	/// A node index is out of range or appears twice
+	InvalidNode(usize),
+}
+
+impl fmt::Display for MsrError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MsrError::InvalidParameters => f.write_str("invalid code parameters"),
+			MsrError::LengthMismatch => f.write_str("length mismatch"),
+			MsrError::NotEnoughNodes => f.write_str("not enough nodes"),
+			MsrError::InvalidNode(i) => write!(f, "invalid node {}", i),
+		}
+	}
+}
+
+impl error::Error for MsrError {}
+
+/// Inverts a square matrix by Gauss-Jordan elimination
+fn invert(mut m: Vec<Vec<Gf256>>) -> Option<Vec<Vec<Gf256>>> {
+	let n = m.len();
+	let mut inv: Vec<Vec<Gf256>> = (0..n).map(|i| {
+		(0..n).map(|j| if i == j { Gf256::one() } else { Gf256::zero() }).collect()
+	}).collect();
+	for col in 0..n {
+		let pivot = (col..n).find(|&r| m[r][col] != Gf256::zero())?;
+		m.swap(col, pivot);
+		inv.swap(col, pivot);
+		let scale = m[col][col].inv()?;
+		for j in 0..n {
+			m[col][j] *= scale;
+			inv[col][j] *= scale;
+		}
+		for r in 0..n {
+			let f = m[r][col];
+			if r == col || f == Gf256::zero() {
+				continue;
+			}
+			for j in 0..n {
+				let (a, b) = (m[col][j], inv[col][j]);
+				m[r][j] -= f * a;
+				inv[r][j] -= f * b;
+			}
+		}
+	}
+	Some(inv)
+}
+
+/// Returns sum coeffs[i] * inputs[i]
+fn combine<S: AsRef<[u8]>>(coeffs: &[Gf256], inputs: &[S], len: usize) -> Vec<u8> {
+	let mut out = vec![0u8; len];
+	for (c, input) in coeffs.iter().zip(inputs) {
+		bytes::mul_add(&mut out, input.as_ref(), c.to_byte());
+	}
+	out
+}
+
+fn powers(x: Gf256, count: usize) -> Vec<Gf256> {
+	let mut p = Vec::with_capacity(count);
+	let mut acc = Gf256::one();
+	for _ in 0..count {
+		p.push(acc);
+		acc *= x;
+	}
+	p
+}
+
+/// A product-matrix MSR code with n nodes, k of which hold the file
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Msr {
+	k: usize,
+	/// Evaluation point of every node, with distinct x^alpha
+	points: Vec<Gf256>,
+}
+
+impl Msr {
+	pub fn new(n: usize, k: usize) -> Result<Msr, MsrError> {
+		if k < 2 || n < 2 * k - 1 {
+			return Err(MsrError::InvalidParameters);
+		}
+		let alpha = k - 1;
+		let mut points = Vec::with_capacity(n);
+		let mut lambdas = Vec::with_capacity(n);
+		for b in 0..=255u8 {
+			if points.len() == n {
+				break;
+			}
+			let x = Gf256::from_byte(b);
+			let lambda = powers(x, alpha + 1)[alpha];
+			if !lambdas.contains(&lambda) {
+				points.push(x);
+				lambdas.push(lambda);
+			}
+		}
+		if points.len() < n {
+			return Err(MsrError::InvalidParameters);
+		}
+		Ok(Msr { k, points })
+	}
+
+	/// Number of nodes
+	pub fn n(&self) -> usize {
+		self.points.len()
+	}
+
+	/// Number of nodes needed to rebuild the file
+	pub fn k(&self) -> usize {
+		self.k
+	}
+
+	/// Number of helpers needed to repair a node
+	pub fn d(&self) -> usize {
+		2 * self.k - 2
+	}
+
+	/// Symbols stored per node
+	pub fn alpha(&self) -> usize {
+		self.k - 1
+	}
+
+	/// Symbols in a file
+	pub fn file_symbols(&self) -> usize {
+		self.k * self.alpha()
+	}
+
+	fn psi(&self, node: usize) -> Vec<Gf256> {
+		powers(self.points[node], self.d())
+	}
+
+	fn phi(&self, node: usize) -> Vec<Gf256> {
+		powers(self.points[node], self.alpha())
+	}
+
+	fn lambda(&self, node: usize) -> Gf256 {
+		powers(self.points[node], self.alpha() + 1)[self.alpha()]
+	}
+
+	/// Checks node indices and returns the symbol length
+	fn check_nodes(&self, nodes: &[(usize, &[u8])], needed: usize, per_node: usize)
+		-> Result<usize, MsrError>
+	{
+		if nodes.len() < needed {
+			return Err(MsrError::NotEnoughNodes);
+		}
+		let nodes = &nodes[..needed];
+		for (i, &(idx, _)) in nodes.iter().enumerate() {
+			if idx >= self.n() || nodes[..i].iter().any(|&(j, _)| j == idx) {
+				return Err(MsrError::InvalidNode(idx));
+			}
+		}
+		let len = nodes[0].1.len();
+		if nodes.iter().any(|(_, c)| c.len() != len) || !len.is_multiple_of(per_node) {
+			return Err(MsrError::LengthMismatch);
+		}
+		Ok(len / per_node)
+	}
+
+	/// Encodes a file into the content of all n nodes. The file length
+	/// must be a multiple of file_symbols(); every node gets alpha symbols
+	/// of file.len() / file_symbols() bytes, concatenated.
+	pub fn encode(&self, file: &[u8]) -> Result<Vec<Vec<u8>>, MsrError> {
+		let b = self.file_symbols();
+		if file.is_empty() || !file.len().is_multiple_of(b) {
+			return Err(MsrError::LengthMismatch);
+		}
+		let len = file.len() / b;
+		let alpha = self.alpha();
+		let mut symbols = file.chunks(len);
+		// Rows 0..alpha hold S1, rows alpha..d hold S2, both filled from
+		// their upper triangles.
+		let mut m = vec![vec![&[][..]; alpha]; self.d()];
+		for half in 0..2 {
+			for i in 0..alpha {
+				for j in i..alpha {
+					let s = symbols.next().expect("File has B symbols");
+					m[half * alpha + i][j] = s;
+					m[half * alpha + j][i] = s;
+				}
+			}
+		}
+		Ok((0..self.n()).map(|node| {
+			let psi = self.psi(node);
+			let mut content = Vec::with_capacity(alpha * len);
+			for a in 0..alpha {
+				let column: Vec<&[u8]> = m.iter().map(|row| row[a]).collect();
+				content.extend(combine(&psi, &column, len));
+			}
+			content
+		}).collect())
+	}
+
+	/// Runs on a helper: computes the single symbol it sends for the
+	/// repair of node failed.
+	pub fn helper_symbol(&self, content: &[u8], failed: usize) -> Result<Vec<u8>, MsrError> {
+		if failed >= self.n() {
+			return Err(MsrError::InvalidNode(failed));
+		}
+		if content.is_empty() || !content.len().is_multiple_of(self.alpha()) {
+			return Err(MsrError::LengthMismatch);
+		}
+		let len = content.len() / self.alpha();
+		let subs: Vec<&[u8]> = content.chunks(len).collect();
+		Ok(combine(&self.phi(failed), &subs, len))
+	}
+
+	/// Rebuilds the content of node failed from the helper symbols of d
+	/// other nodes, given as (helper index, symbol).
+	pub fn repair(&self, failed: usize, helpers: &[(usize, &[u8])]) -> Result<Vec<u8>, MsrError> {
+		let d = self.d();
+		let len = self.check_nodes(helpers, d, 1)?;
+		if failed >= self.n() || helpers[..d].iter().any(|&(i, _)| i == failed) {
+			return Err(MsrError::InvalidNode(failed));
+		}
+		// Psi_rep (M phi_f) = received symbols
+		let psi_rep = helpers[..d].iter().map(|&(i, _)| self.psi(i)).collect();
+		let inv = invert(psi_rep).expect("Any d rows of Psi are independent");
+		let received: Vec<&[u8]> = helpers[..d].iter().map(|&(_, s)| s).collect();
+		let w: Vec<Vec<u8>> = inv.iter().map(|row| combine(row, &received, len)).collect();
+		// By symmetry, phi_f^T S1 = (S1 phi_f)^T and likewise for S2
+		let lambda = self.lambda(failed);
+		let mut content = Vec::with_capacity(self.alpha() * len);
+		for a in 0..self.alpha() {
+			let mut sym = w[a].clone();
+			bytes::mul_add(&mut sym, &w[self.alpha() + a], lambda.to_byte());
+			content.extend(sym);
+		}
+		Ok(content)
+	}
+
+	/// Rebuilds the file from the content of any k nodes
+	pub fn reconstruct(&self, nodes: &[(usize, &[u8])]) -> Result<Vec<u8>, MsrError> {
+		let (k, alpha) = (self.k, self.alpha());
+		let len = self.check_nodes(nodes, k, alpha)?;
+		let nodes = &nodes[..k];
+		let idx: Vec<usize> = nodes.iter().map(|&(i, _)| i).collect();
+		let y: Vec<Vec<&[u8]>> = nodes.iter().map(|(_, c)| c.chunks(len).collect()).collect();
+		let phis: Vec<Vec<Gf256>> = idx.iter().map(|&i| self.phi(i)).collect();
+		let lambdas: Vec<Gf256> = idx.iter().map(|&i| self.lambda(i)).collect();
+
+		// C = Y Phi^T = P + Lambda Q with P, Q symmetric
+		let c: Vec<Vec<Vec<u8>>> = y.iter().map(|row| {
+			phis.iter().map(|phi| combine(phi, row, len)).collect()
+		}).collect();
+		let mut p = vec![vec![Vec::new(); k]; k];
+		let mut q = vec![vec![Vec::new(); k]; k];
+		for i in 0..k {
+			for j in i + 1..k {
+				let mut diff = c[i][j].clone();
+				bytes::add_assign(&mut diff, &c[j][i]);
+				let scale = (lambdas[i] - lambdas[j]).inv().expect("Distinct lambdas");
+				bytes::mul_scalar(&mut diff, scale.to_byte());
+				let mut pij = c[i][j].clone();
+				bytes::mul_add(&mut pij, &diff, lambdas[i].to_byte());
+				q[i][j] = diff.clone();
+				q[j][i] = diff;
+				p[i][j] = pij.clone();
+				p[j][i] = pij;
+			}
+		}
+
+		// From P: phi_i^T S1 Phi_others^T = off diagonal row i of P
+		let solve = |m: &Vec<Vec<Vec<u8>>>| -> Vec<Vec<Vec<u8>>> {
+			let rows: Vec<Vec<Vec<u8>>> = (0..alpha).map(|i| {
+				let others: Vec<usize> = (0..k).filter(|&j| j != i).collect();
+				let g = invert(others.iter().map(|&j| phis[j].clone()).collect())
+					.expect("Any alpha rows of Phi are independent");
+				let rhs: Vec<&[u8]> = others.iter().map(|&j| &m[i][j][..]).collect();
+				g.iter().map(|row| combine(row, &rhs, len)).collect()
+			}).collect();
+			// Phi_first S = rows
+			let h = invert(phis[..alpha].to_vec()).expect("Any alpha rows of Phi are independent");
+			h.iter().map(|hrow| {
+				(0..alpha).map(|a| {
+					let column: Vec<&[u8]> = rows.iter().map(|r| &r[a][..]).collect();
+					combine(hrow, &column, len)
+				}).collect()
+			}).collect()
+		};
+
+		let mut file = Vec::with_capacity(self.file_symbols() * len);
+		for s in [solve(&p), solve(&q)] {
+			for i in 0..alpha {
+				for sym in &s[i][i..] {
+					file.extend_from_slice(sym);
+				}
+			}
+		}
+		Ok(file)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(code: &Msr, len: usize) -> Vec<u8> {
+        (0..code.file_symbols() * len).map(|i| (i * 73 + 11) as u8).collect()
+    }
+
+    #[test]
+    fn test_invert() {
+        let m: Vec<Vec<Gf256>> = (1..4u8).map(|x| powers(Gf256::from_byte(x), 3)).collect();
+        let inv = invert(m.clone()).unwrap();
+        for (i, row) in m.iter().enumerate() {
+            for j in 0..3 {
+                let dot = row.iter().zip(&inv).fold(Gf256::zero(), |acc, (&a, r)| acc + a * r[j]);
+                assert_eq!(dot, if i == j { Gf256::one() } else { Gf256::zero() });
+            }
+        }
+        assert_eq!(invert(vec![vec![Gf256::one(); 2]; 2]), None);
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let code = Msr::new(8, 4).unwrap();
+        assert_eq!((code.d(), code.alpha(), code.file_symbols()), (6, 3, 12));
+        let input = file(&code, 5);
+        let nodes = code.encode(&input).unwrap();
+        assert!(nodes.iter().all(|n| n.len() == 15));
+        for subset in [[0, 1, 2, 3], [4, 5, 6, 7], [7, 2, 5, 0]] {
+            let given: Vec<(usize, &[u8])> = subset.iter().map(|&i| (i, &nodes[i][..])).collect();
+            assert_eq!(code.reconstruct(&given).unwrap(), input);
+        }
+        let given: Vec<(usize, &[u8])> = (0..3).map(|i| (i, &nodes[i][..])).collect();
+        assert_eq!(code.reconstruct(&given), Err(MsrError::NotEnoughNodes));
+    }
+
+    #[test]
+    fn test_repair() {
+        let code = Msr::new(7, 4).unwrap();
+        let nodes = code.encode(&file(&code, 3)).unwrap();
+        for failed in 0..7 {
+            let symbols: Vec<(usize, Vec<u8>)> = (0..7).filter(|&i| i != failed)
+                .map(|i| (i, code.helper_symbol(&nodes[i], failed).unwrap()))
+                .collect();
+            // Each helper sends a third of its content
+            assert!(symbols.iter().all(|(_, s)| s.len() == 3));
+            let helpers: Vec<(usize, &[u8])> = symbols.iter().map(|(i, s)| (*i, &s[..])).collect();
+            assert_eq!(code.repair(failed, &helpers).unwrap(), nodes[failed]);
+        }
+        assert_eq!(code.repair(0, &[(1, &[0][..])]), Err(MsrError::NotEnoughNodes));
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert_eq!(Msr::new(4, 3), Err(MsrError::InvalidParameters));
+        assert_eq!(Msr::new(3, 1), Err(MsrError::InvalidParameters));
+        let code = Msr::new(5, 3).unwrap();
+        assert_eq!(code.encode(&[0; 7]), Err(MsrError::LengthMismatch));
+    }
+}
@@ -0,0 +1,170 @@
+//! Nonzero field elements.
+//!
+//! The nonzero elements form a group under multiplication, so products,
+//! quotients and inverses of NonZeroGf256 values are total and stay
+//! nonzero. Dividing a Gf256 by a NonZeroGf256 cannot fail either; code
+//! that checks a divisor once (interpolation denominators, pivots) can
+//! carry the result of that check in the type.
+
+use core::ops::{ Mul, Div, MulAssign, DivAssign };
+
+use crate::{ Gf256, Gf256Error };
+
+/// A Gf256 which is not zero
+#[derive(Copy,Clone,PartialEq,Eq,PartialOrd,Ord,Hash,Debug)]
+#[repr(transparent)]
+pub struct NonZeroGf256(Gf256);
+
+impl NonZeroGf256 {
+	pub const ONE: NonZeroGf256 = NonZeroGf256(Gf256::one());
+
+	/// None for zero
+	#[inline]
+	pub const fn new(x: Gf256) -> Option<NonZeroGf256> {
+		if x.poly == 0 { None } else { Some(NonZeroGf256(x)) }
+	}
+
+	#[inline]
+	pub const fn from_byte(b: u8) -> Option<NonZeroGf256> {
+		NonZeroGf256::new(Gf256::from_byte(b))
+	}
+
+	/// x^power, which is never zero
+	#[inline]
+	pub const fn xexp(power: u8) -> NonZeroGf256 {
+		NonZeroGf256(Gf256::xexp(power))
+	}
+
+	#[inline]
+	pub const fn get(self) -> Gf256 {
+		self.0
+	}
+
+	/// The logarithm in base x, which always exists
+	#[inline]
+	pub const fn log(self) -> u8 {
+		match self.0.log() {
+			Some(l) => l,
+			None => unreachable!(),
+		}
+	}
+
+	pub const fn inv(self) -> NonZeroGf256 {
+		NonZeroGf256::xexp(255 - self.log())
+	}
+
+	/// self ^ e, with negative exponents as powers of the inverse
+	pub const fn pow(self, e: i64) -> NonZeroGf256 {
+		let e = e.rem_euclid(255) as u64;
+		NonZeroGf256(self.0.pow(e))
+	}
+}
+
+impl From<NonZeroGf256> for Gf256 {
+	#[inline]
+	fn from(x: NonZeroGf256) -> Gf256 {
+		x.0
+	}
+}
+
+impl TryFrom<Gf256> for NonZeroGf256 {
+	type Error = Gf256Error;
+
+	fn try_from(x: Gf256) -> Result<NonZeroGf256, Gf256Error> {
+		NonZeroGf256::new(x).ok_or(Gf256Error::Zero)
+	}
+}
+
+impl Mul<NonZeroGf256> for NonZeroGf256 {
+	type Output = NonZeroGf256;
+	fn mul(self, rhs: NonZeroGf256) -> NonZeroGf256 {
+		NonZeroGf256(self.0 * rhs.0)
+	}
+}
+
+impl Div<NonZeroGf256> for NonZeroGf256 {
+	type Output = NonZeroGf256;
+	fn div(self, rhs: NonZeroGf256) -> NonZeroGf256 {
+		NonZeroGf256::xexp(((self.log() as u16 + 255 - rhs.log() as u16) % 255) as u8)
+	}
+}
+
+impl Mul<NonZeroGf256> for Gf256 {
+	type Output = Gf256;
+	fn mul(self, rhs: NonZeroGf256) -> Gf256 {
+		self * rhs.0
+	}
+}
+
+impl Div<NonZeroGf256> for Gf256 {
+	type Output = Gf256;
+	/// Never panics
+	fn div(self, rhs: NonZeroGf256) -> Gf256 {
+		match NonZeroGf256::new(self) {
+			Some(x) => (x / rhs).0,
+			None => Gf256::zero(),
+		}
+	}
+}
+
+impl MulAssign<NonZeroGf256> for NonZeroGf256 {
+	fn mul_assign(&mut self, rhs: NonZeroGf256) {
+		*self = *self * rhs;
+	}
+}
+
+impl DivAssign<NonZeroGf256> for NonZeroGf256 {
+	fn div_assign(&mut self, rhs: NonZeroGf256) {
+		*self = *self / rhs;
+	}
+}
+
+impl MulAssign<NonZeroGf256> for Gf256 {
+	fn mul_assign(&mut self, rhs: NonZeroGf256) {
+		*self = *self * rhs;
+	}
+}
+
+impl DivAssign<NonZeroGf256> for Gf256 {
+	fn div_assign(&mut self, rhs: NonZeroGf256) {
+		*self = *self / rhs;
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> impl Iterator<Item = NonZeroGf256> {
+        (1..=255u8).map(|b| NonZeroGf256::from_byte(b).unwrap())
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(NonZeroGf256::new(Gf256::zero()), None);
+        assert_eq!(NonZeroGf256::try_from(Gf256::zero()), Err(Gf256Error::Zero));
+        let x = NonZeroGf256::try_from(Gf256::from_byte(0x53)).unwrap();
+        assert_eq!(Gf256::from(x), Gf256::from_byte(0x53));
+        assert_eq!(NonZeroGf256::xexp(1).get(), Gf256::from_byte(2));
+        assert_eq!(NonZeroGf256::ONE.log(), 0);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let y = NonZeroGf256::from_byte(0xa7).unwrap();
+        for x in all() {
+            assert_eq!(x.inv().get(), x.get().inv().unwrap());
+            assert_eq!(x * x.inv(), NonZeroGf256::ONE);
+            assert_eq!((x / y).get(), x.get() / y.get());
+            assert_eq!(x.get() / y, x.get() / y.get());
+            assert_eq!(x.pow(-3), x.inv().pow(3));
+            assert_eq!(x.pow(255), NonZeroGf256::ONE);
+        }
+        assert_eq!(Gf256::zero() / y, Gf256::zero());
+        let mut z = Gf256::from_byte(7);
+        z *= y;
+        z /= y;
+        assert_eq!(z, Gf256::from_byte(7));
+    }
+}
@@ -0,0 +1,402 @@
+//! Packet-level forward error correction for datagram streams.
+//!
+//! The sender groups consecutive payloads into generations of up to N
+//! source packets. Every source packet is sent right away with a small
+//! header; when a generation is complete (or flushed), M repair packets
+//! follow. The receiver hands out source payloads as they arrive and
+//! recovers lost ones as soon as any N packets of their generation are
+//! in, whichever they are.
+//!
+//! Payloads may differ in length. For the erasure code every payload is
+//! framed as a 2 byte big endian length followed by the payload and zero
+//! padding up to the longest payload of the generation; repair packets
+//! carry shards of this framed size.
+//!
+//! Header layout (8 bytes):
+//!
+//! | bytes | field                                                  |
+//! |-------|--------------------------------------------------------|
+//! | 0..4  | generation number, big endian                          |
+//! | 4     | kind: 0 for source, 1 for repair                       |
+//! | 5     | index of the packet in its generation                  |
+//! | 6     | number of source packets (repair packets only, else 0) |
+//! | 7     | number of repair packets (repair packets only, else 0) |
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt;
+use std::error;
+
+use crate::rs::erasure::{ ErasureCode, ErasureError };
+
+/// Length of the packet header
+pub const HEADER_LEN: usize = 8;
+/// Largest payload which can be protected
+pub const MAX_PAYLOAD: usize = u16::MAX as usize;
+/// Generations kept by a decoder before the oldest is dropped
+const WINDOW: usize = 16;
+
+const KIND_SOURCE: u8 = 0;
+const KIND_REPAIR: u8 = 1;
+
+/// Errors produced by the packet FEC layer
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum PacketError {
+	/// Invalid number of source or repair packets per generation
+	InvalidParameters,
+	/// The payload exceeds MAX_PAYLOAD bytes
+	PayloadTooLarge,
+	/// The packet is not a valid FEC packet
+	Malformed,
+	/// Recovery failed
+	Erasure(ErasureError),
+}
+
+impl fmt::Display for PacketError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PacketError::InvalidParameters => f.write_str("invalid generation size"),
+			PacketError::PayloadTooLarge => f.write_str("payload too large"),
+			PacketError::Malformed => f.write_str("malformed packet"),
+			PacketError::Erasure(e) => write!(f, "recovery failed: {}", e),
+		}
+	}
+}
+
+impl error::Error for PacketError {}
+
+impl From<ErasureError> for PacketError {
+	fn from(e: ErasureError) -> PacketError {
+		PacketError::Erasure(e)
+	}
+}
+
+/// A parsed packet header
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Header {
+	pub generation: u32,
+	pub repair: bool,
+	pub index: u8,
+	pub source_count: u8,
+	pub repair_count: u8,
+}
+
+impl Header {
+	pub fn parse(packet: &[u8]) -> Result<Header, PacketError> {
+		if packet.len() < HEADER_LEN {
+			return Err(PacketError::Malformed);
+		}
+		let generation = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+		let repair = match packet[4] {
+			KIND_SOURCE => false,
+			KIND_REPAIR => true,
+			_ => return Err(PacketError::Malformed),
+		};
+		let header = Header {
+			generation,
+			repair,
+			index: packet[5],
+			source_count: packet[6],
+			repair_count: packet[7],
+		};
+		if repair {
+			let (k, m, i) = (header.source_count as usize, header.repair_count as usize, header.index as usize);
+			if k == 0 || m == 0 || i < k || i >= k + m {
+				return Err(PacketError::Malformed);
+			}
+		}
+		Ok(header)
+	}
+
+	fn write(&self, out: &mut Vec<u8>) {
+		out.extend_from_slice(&self.generation.to_be_bytes());
+		out.push(if self.repair { KIND_REPAIR } else { KIND_SOURCE });
+		out.push(self.index);
+		out.push(self.source_count);
+		out.push(self.repair_count);
+	}
+}
+
+/// Frames payload as length, payload, zero padding up to size bytes
+fn frame(payload: &[u8], size: usize) -> Vec<u8> {
+	let mut shard = Vec::with_capacity(size);
+	shard.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+	shard.extend_from_slice(payload);
+	shard.resize(size, 0);
+	shard
+}
+
+/// Extracts the payload from a framed shard
+fn unframe(shard: &[u8]) -> Result<Vec<u8>, PacketError> {
+	if shard.len() < 2 {
+		return Err(PacketError::Malformed);
+	}
+	let len = u16::from_be_bytes([shard[0], shard[1]]) as usize;
+	shard.get(2..2 + len).map(|p| p.to_vec()).ok_or(PacketError::Malformed)
+}
+
+/// The sending side
+pub struct PacketEncoder {
+	source: usize,
+	repair: usize,
+	generation: u32,
+	pending: Vec<Vec<u8>>,
+}
+
+impl PacketEncoder {
+	/// Creates an encoder with generations of source packets followed by
+	/// repair packets; source + repair must not exceed 256.
+	pub fn new(source: usize, repair: usize) -> Result<PacketEncoder, PacketError> {
+		if source == 0 || repair == 0 || source + repair > 256 || source > 255 || repair > 255 {
+			return Err(PacketError::InvalidParameters);
+		}
+		Ok(PacketEncoder { source, repair, generation: 0, pending: Vec::with_capacity(source) })
+	}
+
+	/// Number of the generation the next payload belongs to
+	pub fn generation(&self) -> u32 {
+		self.generation
+	}
+
+	/// Returns the packets to send for payload: its source packet,
+	/// followed by the repair packets if it completes a generation.
+	pub fn send(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+		if payload.len() > MAX_PAYLOAD {
+			return Err(PacketError::PayloadTooLarge);
+		}
+		let header = Header {
+			generation: self.generation,
+			repair: false,
+			index: self.pending.len() as u8,
+			source_count: 0,
+			repair_count: 0,
+		};
+		let mut packet = Vec::with_capacity(HEADER_LEN + payload.len());
+		header.write(&mut packet);
+		packet.extend_from_slice(payload);
+		self.pending.push(payload.to_vec());
+
+		let mut out = vec![packet];
+		if self.pending.len() == self.source {
+			out.extend(self.flush()?);
+		}
+		Ok(out)
+	}
+
+	/// Ends the current generation early and returns its repair packets
+	/// (none if the generation is empty).
+	pub fn flush(&mut self) -> Result<Vec<Vec<u8>>, PacketError> {
+		if self.pending.is_empty() {
+			return Ok(Vec::new());
+		}
+		let k = self.pending.len();
+		let code = ErasureCode::new(k, self.repair)?;
+		let size = 2 + self.pending.iter().map(|p| p.len()).max().unwrap_or(0);
+		let data: Vec<Vec<u8>> = self.pending.iter().map(|p| frame(p, size)).collect();
+		let mut parity = vec![vec![0u8; size]; self.repair];
+		{
+			let data_refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+			let mut parity_refs: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+			code.encode(&data_refs, &mut parity_refs)?;
+		}
+
+		let packets = parity.into_iter().enumerate().map(|(j, shard)| {
+			let header = Header {
+				generation: self.generation,
+				repair: true,
+				index: (k + j) as u8,
+				source_count: k as u8,
+				repair_count: self.repair as u8,
+			};
+			let mut packet = Vec::with_capacity(HEADER_LEN + size);
+			header.write(&mut packet);
+			packet.extend_from_slice(&shard);
+			packet
+		}).collect();
+		self.pending.clear();
+		self.generation = self.generation.wrapping_add(1);
+		Ok(packets)
+	}
+}
+
+/// What a decoder knows about one generation
+#[derive(Default)]
+struct Generation {
+	/// Source payloads by index
+	sources: BTreeMap<u8, Vec<u8>>,
+	/// Repair shards by index
+	repairs: BTreeMap<u8, Vec<u8>>,
+	/// (source count, repair count) once a repair packet arrived
+	shape: Option<(usize, usize)>,
+	done: bool,
+}
+
+/// A payload handed out by the decoder
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Delivered {
+	pub generation: u32,
+	pub index: u8,
+	pub payload: Vec<u8>,
+	/// Whether the payload was recovered rather than received
+	pub recovered: bool,
+}
+
+/// The receiving side
+#[derive(Default)]
+pub struct PacketDecoder {
+	generations: BTreeMap<u32, Generation>,
+}
+
+impl PacketDecoder {
+	pub fn new() -> PacketDecoder {
+		PacketDecoder { generations: BTreeMap::new() }
+	}
+
+	/// Processes a received packet and returns the payloads which became
+	/// available: the packet's own payload for a source packet, and all
+	/// missing payloads of its generation once it can be recovered.
+	/// Duplicates and packets of completed generations yield nothing.
+	pub fn receive(&mut self, packet: &[u8]) -> Result<Vec<Delivered>, PacketError> {
+		let header = Header::parse(packet)?;
+		let body = &packet[HEADER_LEN..];
+		let gen = self.generations.entry(header.generation).or_default();
+		if gen.done {
+			return Ok(Vec::new());
+		}
+
+		let mut out = Vec::new();
+		if header.repair {
+			let shape = (header.source_count as usize, header.repair_count as usize);
+			if gen.shape.is_some_and(|s| s != shape) {
+				return Err(PacketError::Malformed);
+			}
+			gen.shape = Some(shape);
+			gen.repairs.entry(header.index).or_insert_with(|| body.to_vec());
+		} else if let Entry::Vacant(slot) = gen.sources.entry(header.index) {
+			slot.insert(body.to_vec());
+			out.push(Delivered {
+				generation: header.generation,
+				index: header.index,
+				payload: body.to_vec(),
+				recovered: false,
+			});
+		}
+
+		if let Some((k, m)) = gen.shape {
+			if gen.sources.len() >= k {
+				gen.done = true;
+			} else if gen.sources.len() + gen.repairs.len() >= k {
+				out.extend(PacketDecoder::recover(header.generation, gen, k, m)?);
+				gen.done = true;
+			}
+		}
+
+		while self.generations.len() > WINDOW {
+			self.generations.pop_first();
+		}
+		Ok(out)
+	}
+
+	fn recover(generation: u32, gen: &Generation, k: usize, m: usize)
+		-> Result<Vec<Delivered>, PacketError> {
+		let code = ErasureCode::new(k, m)?;
+		let size = gen.repairs.values().next().map_or(0, |r| r.len());
+		let mut shards: Vec<Option<Vec<u8>>> = vec![None; k + m];
+		for (&i, payload) in &gen.sources {
+			if payload.len() + 2 > size || i as usize >= k {
+				return Err(PacketError::Malformed);
+			}
+			shards[i as usize] = Some(frame(payload, size));
+		}
+		for (&i, shard) in &gen.repairs {
+			if shard.len() != size {
+				return Err(PacketError::Malformed);
+			}
+			shards[i as usize] = Some(shard.clone());
+		}
+		code.reconstruct(&mut shards)?;
+
+		let mut out = Vec::new();
+		for (i, shard) in shards.iter().enumerate().take(k) {
+			if gen.sources.contains_key(&(i as u8)) {
+				continue;
+			}
+			let shard = shard.as_ref().expect("Reconstructed");
+			out.push(Delivered {
+				generation,
+				index: i as u8,
+				payload: unframe(shard)?,
+				recovered: true,
+			});
+		}
+		Ok(out)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(i: usize) -> Vec<u8> {
+        (0..(i * 37) % 90).map(|j| (i + j) as u8).collect()
+    }
+
+    #[test]
+    fn test_recover_losses() {
+        let mut enc = PacketEncoder::new(5, 2).unwrap();
+        let mut dec = PacketDecoder::new();
+        let mut wire = Vec::new();
+        for i in 0..15 {
+            wire.extend(enc.send(&payload(i)).unwrap());
+        }
+        assert_eq!(wire.len(), 15 + 3 * 2);
+
+        // Lose two packets of every generation of 7
+        let mut got: Vec<Delivered> = Vec::new();
+        for (n, packet) in wire.iter().enumerate() {
+            if n % 7 == 1 || n % 7 == 4 {
+                continue;
+            }
+            got.extend(dec.receive(packet).unwrap());
+        }
+        got.sort_by_key(|d| (d.generation, d.index));
+        let payloads: Vec<Vec<u8>> = got.iter().map(|d| d.payload.clone()).collect();
+        assert_eq!(payloads, (0..15).map(payload).collect::<Vec<_>>());
+        assert_eq!(got.iter().filter(|d| d.recovered).count(), 6);
+    }
+
+    #[test]
+    fn test_flush_and_reorder() {
+        let mut enc = PacketEncoder::new(10, 3).unwrap();
+        let mut wire = Vec::new();
+        for i in 0..4 {
+            wire.extend(enc.send(&payload(i)).unwrap());
+        }
+        wire.extend(enc.flush().unwrap());
+        assert_eq!(wire.len(), 7);
+        assert_eq!(enc.generation(), 1);
+
+        // Repair packets first, then only one source packet
+        let mut dec = PacketDecoder::new();
+        let mut got = Vec::new();
+        for packet in wire[4..].iter().chain(&wire[2..3]) {
+            got.extend(dec.receive(packet).unwrap());
+        }
+        got.sort_by_key(|d| d.index);
+        assert_eq!(got.len(), 4);
+        assert!(got.iter().enumerate().all(|(i, d)| d.payload == payload(i)));
+        // Late arrivals are not delivered twice
+        assert!(dec.receive(&wire[0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_malformed() {
+        let mut dec = PacketDecoder::new();
+        assert_eq!(dec.receive(&[0, 0, 0]), Err(PacketError::Malformed));
+        assert_eq!(dec.receive(&[0, 0, 0, 0, 7, 0, 0, 0]), Err(PacketError::Malformed));
+        // Repair index inside the source range
+        assert_eq!(dec.receive(&[0, 0, 0, 0, 1, 2, 4, 2, 0, 0]), Err(PacketError::Malformed));
+        assert!(PacketEncoder::new(200, 57).is_err());
+    }
+}
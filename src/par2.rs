@@ -0,0 +1,274 @@
+//! PAR2 recovery slices.
+//!
+//! PAR2 splits the input files into slices of a fixed size and reads each
+//! slice as little-endian 16-bit words in GF(2^16) with the polynomial
+//! 0x1100b, the default of Gf65536. Input slice i gets the constant
+//! c_i = 2^n_i, where n_0, n_1, ... are the exponents coprime to 65535 in
+//! increasing order, that is those not divisible by 3, 5, 17 or 257.
+//! This leaves 32768 possible input slices.
+//!
+//! The recovery slice with exponent e is the sum of c_i^e * input_i over
+//! all input slices, so the recovery slices form a Vandermonde matrix
+//! over the input constants. Recovery slice 0 is the XOR of the inputs.
+//!
+//! The PAR2 matrix is not always invertible for an arbitrary choice of
+//! recovery exponents; reconstruct uses the recovery slices it is given
+//! until the missing inputs are determined and fails only if all of them
+//! together are not enough.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::wide::Gf65536;
+
+/// The most input slices of a PAR2 set
+pub const MAX_INPUT_SLICES: usize = 32768;
+
+/// Errors produced by Par2Coder
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Par2Error {
+	/// No input slices or more than MAX_INPUT_SLICES
+	InvalidInputCount,
+	/// The slice size is not a positive multiple of 4
+	InvalidSliceSize,
+	/// The number of input slices does not match the coder
+	InputCountMismatch,
+	/// A slice does not have the slice size
+	SliceSizeMismatch,
+	/// The recovery slices do not determine the missing input slices
+	TooFewRecoverySlices,
+}
+
+impl fmt::Display for Par2Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			Par2Error::InvalidInputCount => "invalid number of input slices",
+			Par2Error::InvalidSliceSize => "slice size is not a positive multiple of 4",
+			Par2Error::InputCountMismatch => "wrong number of input slices",
+			Par2Error::SliceSizeMismatch => "slices differ in size",
+			Par2Error::TooFewRecoverySlices => "not enough recovery slices",
+		})
+	}
+}
+
+impl error::Error for Par2Error {}
+
+/// The constants of the first count input slices
+pub fn input_constants(count: usize) -> Result<Vec<Gf65536>, Par2Error> {
+	if count == 0 || count > MAX_INPUT_SLICES {
+		return Err(Par2Error::InvalidInputCount);
+	}
+	Ok((1u32..)
+		.filter(|n| [3, 5, 17, 257].iter().all(|p| !n.is_multiple_of(*p)))
+		.take(count)
+		.map(Gf65536::xexp)
+		.collect())
+}
+
+/// dst += c * src, word by word
+fn mul_acc(dst: &mut [u8], src: &[u8], c: Gf65536) {
+	for (d, s) in dst.chunks_exact_mut(2).zip(src.chunks_exact(2)) {
+		let w = Gf65536::from_u16(u16::from_le_bytes([s[0], s[1]])) * c;
+		let v = u16::from_le_bytes([d[0], d[1]]) ^ w.to_u16();
+		d.copy_from_slice(&v.to_le_bytes());
+	}
+}
+
+/// Computes recovery slices and reconstructs input slices of one PAR2
+/// recovery set
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Par2Coder {
+	constants: Vec<Gf65536>,
+	slice_size: usize,
+}
+
+impl Par2Coder {
+	pub fn new(input_slices: usize, slice_size: usize) -> Result<Par2Coder, Par2Error> {
+		if slice_size == 0 || !slice_size.is_multiple_of(4) {
+			return Err(Par2Error::InvalidSliceSize);
+		}
+		Ok(Par2Coder { constants: input_constants(input_slices)?, slice_size })
+	}
+
+	pub fn input_slices(&self) -> usize {
+		self.constants.len()
+	}
+
+	pub fn slice_size(&self) -> usize {
+		self.slice_size
+	}
+
+	/// The constant of input slice i
+	pub fn constant(&self, i: usize) -> Gf65536 {
+		self.constants[i]
+	}
+
+	fn check_inputs<T: AsRef<[u8]>>(&self, inputs: &[T]) -> Result<(), Par2Error> {
+		if inputs.len() != self.input_slices() {
+			return Err(Par2Error::InputCountMismatch);
+		}
+		if inputs.iter().any(|s| s.as_ref().len() != self.slice_size) {
+			return Err(Par2Error::SliceSizeMismatch);
+		}
+		Ok(())
+	}
+
+	/// Writes the recovery slice with the given exponent to out
+	pub fn recovery_slice<T: AsRef<[u8]>>(&self, exponent: u16, inputs: &[T], out: &mut [u8]) -> Result<(), Par2Error> {
+		self.check_inputs(inputs)?;
+		if out.len() != self.slice_size {
+			return Err(Par2Error::SliceSizeMismatch);
+		}
+		out.fill(0);
+		for (input, c) in inputs.iter().zip(&self.constants) {
+			mul_acc(out, input.as_ref(), c.exp(exponent as u32));
+		}
+		Ok(())
+	}
+
+	/// The recovery slices of the given exponents
+	pub fn recovery_slices<T: AsRef<[u8]>>(&self, exponents: &[u16], inputs: &[T]) -> Result<Vec<Vec<u8>>, Par2Error> {
+		exponents.iter().map(|&e| {
+			let mut out = vec![0u8; self.slice_size];
+			self.recovery_slice(e, inputs, &mut out)?;
+			Ok(out)
+		}).collect()
+	}
+
+	/// Whether recovery matches the recovery slice of the inputs
+	pub fn verify<T: AsRef<[u8]>>(&self, exponent: u16, inputs: &[T], recovery: &[u8]) -> Result<bool, Par2Error> {
+		let mut expected = vec![0u8; self.slice_size];
+		self.recovery_slice(exponent, inputs, &mut expected)?;
+		Ok(expected == recovery)
+	}
+
+	/// Fills in the missing input slices, given as None, from recovery
+	/// slices given as (exponent, slice)
+	pub fn reconstruct(&self, inputs: &mut [Option<Vec<u8>>], recovery: &[(u16, &[u8])]) -> Result<(), Par2Error> {
+		if inputs.len() != self.input_slices() {
+			return Err(Par2Error::InputCountMismatch);
+		}
+		let size = self.slice_size;
+		if inputs.iter().flatten().map(|s| s.len()).chain(recovery.iter().map(|r| r.1.len())).any(|l| l != size) {
+			return Err(Par2Error::SliceSizeMismatch);
+		}
+		let missing: Vec<usize> = (0..inputs.len()).filter(|&i| inputs[i].is_none()).collect();
+		let (m, k) = (missing.len(), recovery.len());
+		if m == 0 {
+			return Ok(());
+		}
+		if k < m {
+			return Err(Par2Error::TooFewRecoverySlices);
+		}
+
+		// Row r: the coefficients of the missing inputs in recovery slice
+		// r, followed by the combination of recovery slices it stands for
+		let mut rows: Vec<Vec<Gf65536>> = recovery.iter().enumerate().map(|(r, &(e, _))| {
+			let mut row: Vec<Gf65536> = missing.iter().map(|&i| self.constants[i].exp(e as u32)).collect();
+			row.extend((0..k).map(|j| if j == r { Gf65536::one() } else { Gf65536::zero() }));
+			row
+		}).collect();
+		for col in 0..m {
+			let pivot = (col..k).find(|&r| rows[r][col] != Gf65536::zero())
+				.ok_or(Par2Error::TooFewRecoverySlices)?;
+			rows.swap(col, pivot);
+			let inv = rows[col][col].inv().expect("Nonzero pivot");
+			for v in rows[col].iter_mut() {
+				*v *= inv;
+			}
+			let pivot_row = rows[col].clone();
+			for (r, row) in rows.iter_mut().enumerate() {
+				let f = row[col];
+				if r != col && f != Gf65536::zero() {
+					for (v, &p) in row.iter_mut().zip(&pivot_row) {
+						*v += f * p;
+					}
+				}
+			}
+		}
+
+		// The recovery slices without the contribution of present inputs
+		let mut rhs: Vec<Vec<u8>> = recovery.iter().map(|r| r.1.to_vec()).collect();
+		for (out, &(e, _)) in rhs.iter_mut().zip(recovery) {
+			for (input, c) in inputs.iter().zip(&self.constants) {
+				if let Some(input) = input {
+					mul_acc(out, input, c.exp(e as u32));
+				}
+			}
+		}
+		for (row, &i) in rows.iter().zip(&missing) {
+			let mut out = vec![0u8; size];
+			for (src, &c) in rhs.iter().zip(&row[m..]) {
+				if c != Gf65536::zero() {
+					mul_acc(&mut out, src, c);
+				}
+			}
+			inputs[i] = Some(out);
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(count: usize, size: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| (0..size).map(|j| (i * 31 + j * 7 + j / 13) as u8).collect()).collect()
+    }
+
+    #[test]
+    fn test_constants() {
+        let logs: Vec<u16> = input_constants(12).unwrap().iter().map(|c| c.log().unwrap()).collect();
+        assert_eq!(logs, [1, 2, 4, 7, 8, 11, 13, 14, 16, 19, 22, 23]);
+        let all = input_constants(MAX_INPUT_SLICES).unwrap();
+        assert_eq!(all.last().unwrap().log(), Some(65534));
+        assert_eq!(input_constants(MAX_INPUT_SLICES + 1), Err(Par2Error::InvalidInputCount));
+        assert_eq!(input_constants(0), Err(Par2Error::InvalidInputCount));
+    }
+
+    #[test]
+    fn test_recovery_slices() {
+        let coder = Par2Coder::new(5, 8).unwrap();
+        let data = inputs(5, 8);
+        let rec = coder.recovery_slices(&[0, 1], &data).unwrap();
+        let xor: Vec<u8> = (0..8).map(|j| data.iter().fold(0, |a, s| a ^ s[j])).collect();
+        assert_eq!(rec[0], xor);
+        // Word j of recovery slice 1: sum of 2^n_i * word j of input i
+        let w = |s: &[u8], j: usize| u16::from_le_bytes([s[2 * j], s[2 * j + 1]]);
+        for j in 0..4 {
+            let expected = (0..5).fold(Gf65536::zero(), |a, i| {
+                a + coder.constant(i) * Gf65536::from_u16(w(&data[i], j))
+            });
+            assert_eq!(w(&rec[1], j), expected.to_u16());
+        }
+        assert_eq!(coder.verify(1, &data, &rec[1]), Ok(true));
+        assert_eq!(coder.verify(2, &data, &rec[1]), Ok(false));
+        assert_eq!(Par2Coder::new(5, 6), Err(Par2Error::InvalidSliceSize));
+        assert_eq!(coder.recovery_slices(&[0], &data[1..]), Err(Par2Error::InputCountMismatch));
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let coder = Par2Coder::new(10, 16).unwrap();
+        let data = inputs(10, 16);
+        let exponents = [0, 1, 5, 9];
+        let rec = coder.recovery_slices(&exponents, &data).unwrap();
+        let rec: Vec<(u16, &[u8])> = exponents.iter().copied().zip(rec.iter().map(|r| &r[..])).collect();
+
+        let mut damaged: Vec<Option<Vec<u8>>> = data.iter().cloned().map(Some).collect();
+        damaged[0] = None;
+        damaged[3] = None;
+        damaged[9] = None;
+        coder.reconstruct(&mut damaged, &rec[1..]).unwrap();
+        assert!(damaged.iter().zip(&data).all(|(d, a)| d.as_ref() == Some(a)));
+
+        let mut damaged: Vec<Option<Vec<u8>>> = data.iter().cloned().map(Some).collect();
+        damaged[2..6].fill(None);
+        assert_eq!(coder.reconstruct(&mut damaged, &rec[..3]), Err(Par2Error::TooFewRecoverySlices));
+        coder.reconstruct(&mut damaged, &rec).unwrap();
+        assert!(damaged.iter().zip(&data).all(|(d, a)| d.as_ref() == Some(a)));
+    }
+}
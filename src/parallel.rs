@@ -0,0 +1,249 @@
+//! Bulk operations spread over several threads.
+//!
+//! Multiplying and accumulating buffers is independent per byte, so a
+//! buffer is cut into chunks of a configurable size which are handed out
+//! to scoped threads (round robin, so the threads get equal shares). The
+//! same goes for the rows of a matrix product and for the byte ranges of
+//! an erasure encoding: Parallel implements Offload, so passing it to
+//! ErasureCode::encode_offload or reconstruct_offload encodes all shards
+//! on every core.
+//!
+//! The crate has no dependencies, so std::thread::scope is used rather
+//! than a thread pool. Starting the threads costs some microseconds per
+//! call; inputs of a single chunk run on the calling thread.
+//!
+//! Enabled with the `parallel` feature.
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::{ Gf256, as_byte_slice, as_byte_slice_mut };
+use crate::bytes;
+use crate::matrix::{ Matrix, MatrixError };
+use crate::rs::offload::{ Cpu, Job, Offload, OffloadError };
+
+/// Thread count and chunk size for the parallel operations
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Parallel {
+	threads: usize,
+	chunk: usize,
+	row_chunk: Option<usize>,
+}
+
+impl Default for Parallel {
+	fn default() -> Parallel {
+		Parallel::new()
+	}
+}
+
+impl Parallel {
+	/// One thread per core and chunks of 64 KiB
+	pub fn new() -> Parallel {
+		let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+		Parallel { threads, chunk: 64 * 1024, row_chunk: None }
+	}
+
+	/// Panics if threads is zero
+	pub fn with_threads(self, threads: usize) -> Parallel {
+		assert!(threads > 0, "At least one thread is needed");
+		Parallel { threads, ..self }
+	}
+
+	/// Bytes per unit of work for the slice operations. Panics if chunk
+	/// is zero.
+	pub fn with_chunk(self, chunk: usize) -> Parallel {
+		assert!(chunk > 0, "Chunks must not be empty");
+		Parallel { chunk, ..self }
+	}
+
+	/// Rows per unit of work for matmul. By default the rows are split
+	/// into one block per thread. Panics if rows is zero.
+	pub fn with_row_chunk(self, rows: usize) -> Parallel {
+		assert!(rows > 0, "Chunks must not be empty");
+		Parallel { row_chunk: Some(rows), ..self }
+	}
+
+	pub fn threads(&self) -> usize {
+		self.threads
+	}
+
+	pub fn chunk(&self) -> usize {
+		self.chunk
+	}
+
+	/// Rows per matmul block for a product with the given number of rows
+	pub fn row_chunk(&self, rows: usize) -> usize {
+		self.row_chunk.unwrap_or_else(|| rows.div_ceil(self.threads).max(1))
+	}
+
+	/// Calls f on every item, spreading the items over the threads
+	fn run<T: Send, F: Fn(T) + Sync>(&self, items: Vec<T>, f: F) {
+		let threads = self.threads.min(items.len());
+		if threads <= 1 {
+			items.into_iter().for_each(f);
+			return;
+		}
+		let mut shares: Vec<Vec<T>> = (0..threads).map(|_| Vec::new()).collect();
+		for (i, item) in items.into_iter().enumerate() {
+			shares[i % threads].push(item);
+		}
+		let f = &f;
+		thread::scope(|s| {
+			for share in shares {
+				s.spawn(move || share.into_iter().for_each(f));
+			}
+		});
+	}
+
+	/// buf[i] = c * buf[i]
+	pub fn mul_scalar(&self, buf: &mut [u8], c: u8) {
+		self.run(buf.chunks_mut(self.chunk).collect(), |b| bytes::mul_scalar(b, c));
+	}
+
+	/// dst[i] = dst[i] + c * src[i]. Panics if the lengths differ.
+	pub fn mul_add(&self, dst: &mut [u8], src: &[u8], c: u8) {
+		assert_eq!(dst.len(), src.len(), "Length mismatch");
+		let pairs = dst.chunks_mut(self.chunk).zip(src.chunks(self.chunk)).collect();
+		self.run(pairs, |(d, s)| bytes::mul_add(d, s, c));
+	}
+
+	/// a * b, computing blocks of row_chunk rows in parallel. Every row
+	/// is accumulated with bytes::mul_add, as in Matrix multiplication.
+	pub fn matmul(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+		if a.cols() != b.rows() {
+			return Err(MatrixError::DimensionMismatch);
+		}
+		let mut out = Matrix::zero(a.rows(), b.cols());
+		if b.cols() == 0 {
+			return Ok(out);
+		}
+		let mut rows: Vec<&mut [Gf256]> = Vec::with_capacity(a.rows());
+		let mut rest = out.as_mut_slice();
+		while !rest.is_empty() {
+			let (row, tail) = rest.split_at_mut(b.cols());
+			rows.push(row);
+			rest = tail;
+		}
+		let per_block = self.row_chunk(a.rows());
+		let mut blocks: Vec<(usize, Vec<&mut [Gf256]>)> = Vec::new();
+		for (i, row) in rows.into_iter().enumerate() {
+			if i % per_block == 0 {
+				blocks.push((i, Vec::with_capacity(per_block)));
+			}
+			blocks.last_mut().expect("Pushed above").1.push(row);
+		}
+		self.run(blocks, |(first, rows)| {
+			for (i, out) in rows.into_iter().enumerate() {
+				let out = as_byte_slice_mut(out);
+				for (k, &x) in a.row(first + i).iter().enumerate() {
+					bytes::mul_add(out, as_byte_slice(b.row(k)), x.to_byte());
+				}
+			}
+		});
+		Ok(out)
+	}
+}
+
+/// Splits every job into byte ranges of chunk bytes and runs the ranges of
+/// all jobs concurrently
+impl Offload for Parallel {
+	fn submit(&self, jobs: &mut [Job]) -> Result<(), OffloadError> {
+		let chunk = self.chunk;
+		let mut work: Vec<Job> = Vec::new();
+		for job in jobs.iter_mut() {
+			match job {
+				Job::Axpy { dst, src, c } => {
+					for (d, s) in dst.chunks_mut(chunk).zip(src.chunks(chunk)) {
+						work.push(Job::Axpy { dst: d, src: s, c: *c });
+					}
+				},
+				Job::Matrix { matrix, inputs, outputs } => {
+					let len = inputs.first().map_or(0, |i| i.len());
+					let mut ranges: Vec<Vec<&mut [u8]>> = (0..len.div_ceil(chunk)).map(|_| Vec::new()).collect();
+					for out in outputs.iter_mut() {
+						for (range, piece) in ranges.iter_mut().zip(out.chunks_mut(chunk)) {
+							range.push(piece);
+						}
+					}
+					for (i, outputs) in ranges.into_iter().enumerate() {
+						let start = i * chunk;
+						let end = (start + chunk).min(len);
+						let inputs = inputs.iter().map(|input| &input[start..end]).collect();
+						work.push(Job::Matrix { matrix, inputs, outputs });
+					}
+				},
+			}
+		}
+		self.run(work, |mut job| {
+			Cpu.submit(std::slice::from_mut(&mut job)).expect("Cpu does not fail");
+		});
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::erasure::ErasureCode;
+
+    fn bytes_of(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 + seed * 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_slices() {
+        let par = Parallel::new().with_threads(3).with_chunk(100);
+        let src = bytes_of(1234, 1);
+        let mut dst = bytes_of(1234, 2);
+        let mut expected = dst.clone();
+        bytes::mul_add(&mut expected, &src, 0x53);
+        par.mul_add(&mut dst, &src, 0x53);
+        assert_eq!(dst, expected);
+        bytes::mul_scalar(&mut expected, 7);
+        par.mul_scalar(&mut dst, 7);
+        assert_eq!(dst, expected);
+    }
+
+    #[test]
+    fn test_matmul() {
+        let a = Matrix::from_bytes(5, 3, &bytes_of(15, 3)).unwrap();
+        let b = Matrix::from_bytes(3, 4, &bytes_of(12, 4)).unwrap();
+        for rows in [1, 2, 10] {
+            let par = Parallel::new().with_threads(4).with_row_chunk(rows);
+            assert_eq!(par.matmul(&a, &b), Ok(&a * &b));
+        }
+        // By default one block per thread, whatever the byte chunk
+        let par = Parallel::new().with_threads(4);
+        assert_eq!(par.row_chunk(5), 2);
+        assert_eq!(par.row_chunk(1000), 250);
+        assert_eq!(par.with_row_chunk(3).row_chunk(1000), 3);
+        assert_eq!(par.matmul(&a, &b), Ok(&a * &b));
+        let wide = Matrix::from_bytes(3, 200, &bytes_of(600, 5)).unwrap();
+        assert_eq!(par.matmul(&a, &wide), Ok(&a * &wide));
+        assert_eq!(Parallel::new().matmul(&a, &a), Err(MatrixError::DimensionMismatch));
+    }
+
+    #[test]
+    fn test_erasure_offload() {
+        let code = ErasureCode::new(4, 2).unwrap();
+        let data: Vec<Vec<u8>> = (0..4).map(|i| bytes_of(1000, i)).collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let mut expected = vec![vec![0u8; 1000]; 2];
+        let mut out: Vec<&mut [u8]> = expected.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode(&refs, &mut out).unwrap();
+
+        let par = Parallel::new().with_threads(4).with_chunk(128);
+        let mut parity = vec![vec![0u8; 1000]; 2];
+        let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode_offload(&refs, &mut out, &par).unwrap();
+        assert_eq!(parity, expected);
+
+        let mut shards: Vec<Option<Vec<u8>>> = data.into_iter().chain(parity).map(Some).collect();
+        let original = shards.clone();
+        shards[1] = None;
+        shards[4] = None;
+        code.reconstruct_offload(&mut shards, &par).unwrap();
+        assert_eq!(shards, original);
+    }
+}
@@ -0,0 +1,1028 @@
+//! Polynomials over Gf256.
+//!
+//! Poly stores its coefficients lowest degree first, as cyclic and
+//! rs::locator do, and is kept normalized: the highest stored coefficient
+//! is never zero, so the zero polynomial has no coefficients at all and
+//! two equal polynomials always compare equal.
+//!
+//! Note that rs::generator_poly lists coefficients the other way round,
+//! highest degree first, matching the codeword layout of RsCodec; use
+//! Poly::from_high_first to convert.
+//!
+//! interpolate and interpolate_at recover a polynomial (or one of its
+//! values) from points, the decoding step of shamir and erasure codes.
+//!
+//! factor splits a polynomial into irreducible factors. Repeated factors
+//! are separated first by gcds with the derivative; in characteristic 2 a
+//! polynomial with zero derivative is a square, whose root is taken
+//! coefficient by coefficient. Each square-free part is then split by
+//! Berlekamp's algorithm: the v with v^256 = v mod f form a space with
+//! one dimension per irreducible factor, found as the kernel of a matrix
+//! over GF(256), and gcd(f, v - s) over all s in the field separates the
+//! factors.
+//!
+//! Multiplication picks an algorithm by the length of the shorter factor:
+//! the schoolbook product, one bulk multiply-add per coefficient, below
+//! KARATSUBA_MIN coefficients and Karatsuba above, down to schoolbook
+//! products of that size. mul_dft goes through the length 255 DFT
+//! instead: both factors are cut into blocks of 128 coefficients, whose
+//! products fit into 255 coefficients and can be summed up in the
+//! frequency domain. That takes about a sixtieth of the multiplications,
+//! but scalar ones, and loses to the bulk kernels used by the others at
+//! every size measured, so * never picks it.
+
+use core::fmt;
+use core::error;
+use core::ops::{ Add, Sub, Mul, Div, Rem, AddAssign };
+use alloc::{ vec, vec::Vec };
+
+use crate::{ Gf256, as_byte_slice, as_byte_slice_mut };
+use crate::bytes;
+use crate::dft;
+use crate::matrix::Matrix;
+
+/// Errors produced by polynomial operations
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum PolyError {
+	/// Two interpolation points share an x coordinate
+	DuplicatePoint,
+}
+
+impl fmt::Display for PolyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			PolyError::DuplicatePoint => "duplicate interpolation point",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for PolyError {}
+
+/// The shorter factor length from which Karatsuba is used
+pub const KARATSUBA_MIN: usize = 512;
+
+/// Coefficients per DFT block; two blocks multiply to 255 coefficients
+const BLOCK: usize = 128;
+
+/// out[i + j] += a[i] * b[j]
+fn mul_schoolbook(a: &[Gf256], b: &[Gf256], out: &mut [Gf256]) {
+	for (i, &x) in a.iter().enumerate() {
+		bytes::mul_add(as_byte_slice_mut(&mut out[i..i + b.len()]), as_byte_slice(b), x.to_byte());
+	}
+}
+
+/// mul_schoolbook by Karatsuba's method, down to KARATSUBA_MIN
+fn mul_karatsuba(a: &[Gf256], b: &[Gf256], out: &mut [Gf256]) {
+	let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+	if b.len() < KARATSUBA_MIN {
+		return mul_schoolbook(a, b, out);
+	}
+	let m = a.len().div_ceil(2);
+	let (a0, a1) = a.split_at(m);
+	if b.len() <= m {
+		// Too unbalanced to split b as well
+		mul_karatsuba(a0, b, out);
+		return mul_karatsuba(a1, b, &mut out[m..]);
+	}
+	let (b0, b1) = b.split_at(m);
+	let mut z0 = vec![Gf256::zero(); 2 * m - 1];
+	let mut z2 = vec![Gf256::zero(); a1.len() + b1.len() - 1];
+	mul_karatsuba(a0, b0, &mut z0);
+	mul_karatsuba(a1, b1, &mut z2);
+	// z1 = (a0 + a1)(b0 + b1) - z0 - z2
+	let mut sa = a0.to_vec();
+	let mut sb = b0.to_vec();
+	bytes::add_assign(as_byte_slice_mut(&mut sa[..a1.len()]), as_byte_slice(a1));
+	bytes::add_assign(as_byte_slice_mut(&mut sb[..b1.len()]), as_byte_slice(b1));
+	let mut z1 = vec![Gf256::zero(); 2 * m - 1];
+	mul_karatsuba(&sa, &sb, &mut z1);
+	bytes::add_assign(as_byte_slice_mut(&mut z1), as_byte_slice(&z0));
+	bytes::add_assign(as_byte_slice_mut(&mut z1[..z2.len()]), as_byte_slice(&z2));
+	bytes::add_assign(as_byte_slice_mut(&mut out[..z0.len()]), as_byte_slice(&z0));
+	bytes::add_assign(as_byte_slice_mut(&mut out[m..m + z1.len()]), as_byte_slice(&z1));
+	bytes::add_assign(as_byte_slice_mut(&mut out[2 * m..2 * m + z2.len()]), as_byte_slice(&z2));
+}
+
+/// mul_schoolbook through the DFT, see the module documentation
+fn mul_dft(a: &[Gf256], b: &[Gf256], out: &mut [Gf256]) {
+	let fa: Vec<Vec<Gf256>> = a.chunks(BLOCK).map(dft::dft).collect();
+	let fb: Vec<Vec<Gf256>> = b.chunks(BLOCK).map(dft::dft).collect();
+	for k in 0..fa.len() + fb.len() - 1 {
+		let mut acc = vec![Gf256::zero(); dft::N];
+		for (i, x) in fa.iter().enumerate() {
+			if let Some(y) = k.checked_sub(i).and_then(|j| fb.get(j)) {
+				for ((c, &u), &v) in acc.iter_mut().zip(x).zip(y) {
+					*c += u * v;
+				}
+			}
+		}
+		let block = dft::idft(&acc).expect("N values");
+		// Beyond the end of the product the coefficients are zero
+		for (o, c) in out[k * BLOCK..].iter_mut().zip(block) {
+			*o += c;
+		}
+	}
+}
+
+/// A polynomial with Gf256 coefficients
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct Poly {
+	/// Lowest degree first, without trailing zeros
+	coeffs: Vec<Gf256>
+}
+
+impl Poly {
+	pub fn zero() -> Poly {
+		Poly { coeffs: Vec::new() }
+	}
+
+	pub fn one() -> Poly {
+		Poly::constant(Gf256::one())
+	}
+
+	pub fn constant(c: Gf256) -> Poly {
+		Poly::from_vec(vec![c])
+	}
+
+	/// c * x^k
+	pub fn monomial(c: Gf256, k: usize) -> Poly {
+		let mut coeffs = vec![Gf256::zero(); k + 1];
+		coeffs[k] = c;
+		Poly::from_vec(coeffs)
+	}
+
+	/// Takes coefficients lowest degree first, dropping high zeros
+	pub fn from_vec(coeffs: Vec<Gf256>) -> Poly {
+		let mut p = Poly { coeffs };
+		p.normalize();
+		p
+	}
+
+	pub fn from_coeffs(coeffs: &[Gf256]) -> Poly {
+		Poly::from_vec(coeffs.to_vec())
+	}
+
+	/// Takes coefficients highest degree first, as rs::generator_poly
+	/// returns them
+	pub fn from_high_first(coeffs: &[Gf256]) -> Poly {
+		Poly::from_vec(coeffs.iter().rev().copied().collect())
+	}
+
+	/// A polynomial of degree at most degree with constant term secret
+	/// and the other coefficients uniformly random, as in Shamir's scheme.
+	/// rng fills the given buffer with random bytes; it is called once.
+	/// The leading coefficient is zero with probability 1/256, so the
+	/// degree can come out lower.
+	pub fn random_with_secret<R>(secret: Gf256, degree: usize, mut rng: R) -> Poly
+		where R: FnMut(&mut [u8]) {
+		let mut coeffs = vec![secret; degree + 1];
+		rng(as_byte_slice_mut(&mut coeffs[1..]));
+		Poly::from_vec(coeffs)
+	}
+
+	/// Interprets bytes as coefficients, lowest degree first
+	pub fn from_bytes(bytes: &[u8]) -> Poly {
+		Poly::from_vec(bytes.iter().map(|&b| Gf256::from_byte(b)).collect())
+	}
+
+	/// Drops zero coefficients above the degree
+	fn normalize(&mut self) {
+		while self.coeffs.last().is_some_and(|c| c.poly == 0) {
+			self.coeffs.pop();
+		}
+	}
+
+	/// Coefficients, lowest degree first; empty for zero
+	pub fn coeffs(&self) -> &[Gf256] {
+		&self.coeffs
+	}
+
+	pub fn into_coeffs(self) -> Vec<Gf256> {
+		self.coeffs
+	}
+
+	/// Coefficient of x^i, zero above the degree
+	pub fn coeff(&self, i: usize) -> Gf256 {
+		self.coeffs.get(i).copied().unwrap_or(Gf256::zero())
+	}
+
+	/// Degree, None for the zero polynomial
+	pub fn degree(&self) -> Option<usize> {
+		self.coeffs.len().checked_sub(1)
+	}
+
+	/// Coefficient of the highest power, zero for the zero polynomial
+	pub fn leading(&self) -> Gf256 {
+		self.coeffs.last().copied().unwrap_or(Gf256::zero())
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.coeffs.is_empty()
+	}
+
+	/// Multiplies every coefficient by c
+	pub fn scale(&self, c: Gf256) -> Poly {
+		Poly::from_vec(self.coeffs.iter().map(|&a| a * c).collect())
+	}
+
+	/// Evaluates self at x by Horner's rule
+	pub fn eval(&self, x: Gf256) -> Gf256 {
+		self.coeffs.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c)
+	}
+
+	/// Evaluates self at every point. Horner's rule runs on all points at
+	/// once, one bulk multiply per coefficient.
+	pub fn eval_many(&self, points: &[Gf256]) -> Vec<Gf256> {
+		let xs: Vec<u8> = points.iter().map(|x| x.to_byte()).collect();
+		let mut acc = vec![0u8; points.len()];
+		for &c in self.coeffs.iter().rev() {
+			bytes::mul_elementwise(&mut acc, &xs);
+			for a in acc.iter_mut() {
+				*a ^= c.to_byte();
+			}
+		}
+		acc.into_iter().map(Gf256::from_byte).collect()
+	}
+
+	/// Every x in Gf256 with self(x) = 0, in increasing byte order, found by
+	/// evaluating at all 256 elements. The zero polynomial vanishes
+	/// everywhere and returns all of them.
+	pub fn roots(&self) -> Vec<Gf256> {
+		let all: Vec<Gf256> = Gf256::all().collect();
+		let values = self.eval_many(&all);
+		all.into_iter().zip(values).filter(|(_, v)| v.poly == 0).map(|(x, _)| x).collect()
+	}
+
+	/// Reads self as an RS error locator for codewords of length n (n at
+	/// most 255): position p, with locator x^(n - 1 - p), is in error if
+	/// the inverse of its locator is a root. Returns the positions in
+	/// increasing order, or None if the locator does not have deg(self)
+	/// distinct roots among them, i.e. the errors are not correctable.
+	pub fn error_positions(&self, n: usize) -> Option<Vec<usize>> {
+		assert!(n <= 255, "Codeword too long");
+		let positions: Vec<usize> = (0..n)
+			.filter(|&p| self.eval(Gf256::xexp((n - 1 - p) as u8).inv().expect("Nonzero")).poly == 0)
+			.collect();
+		(Some(positions.len()) == self.degree()).then_some(positions)
+	}
+
+	/// Quotient and remainder of self / divisor, the remainder of degree
+	/// below the divisor's. Panics if divisor is zero.
+	pub fn div_rem(&self, divisor: &Poly) -> (Poly, Poly) {
+		let d = divisor.degree().expect("Division by zero polynomial");
+		let lead_inv = divisor.leading().inv().expect("Normalized");
+		let mut rem = self.coeffs.clone();
+		if rem.len() <= d {
+			return (Poly::zero(), self.clone());
+		}
+		let mut quot = vec![Gf256::zero(); rem.len() - d];
+		for i in (0..quot.len()).rev() {
+			let q = rem[i + d] * lead_inv;
+			quot[i] = q;
+			if q.poly != 0 {
+				for (r, &c) in rem[i..].iter_mut().zip(&divisor.coeffs) {
+					*r -= q * c;
+				}
+			}
+		}
+		rem.truncate(d);
+		(Poly::from_vec(quot), Poly::from_vec(rem))
+	}
+
+	/// self mod divisor. Panics if divisor is zero.
+	///
+	/// For an RS generator g of degree n - k, (m * x^(n-k)).rem(g) is the
+	/// systematic parity of the message m.
+	pub fn rem(&self, divisor: &Poly) -> Poly {
+		self.div_rem(divisor).1
+	}
+
+	/// self * other mod modulus
+	pub fn mulmod(&self, other: &Poly, modulus: &Poly) -> Poly {
+		(self * other).rem(modulus)
+	}
+
+	/// The product by the schoolbook method, whatever the sizes
+	pub fn mul_schoolbook(&self, other: &Poly) -> Poly {
+		self.mul_with(other, mul_schoolbook)
+	}
+
+	/// The product by Karatsuba's method, whatever the sizes
+	pub fn mul_karatsuba(&self, other: &Poly) -> Poly {
+		self.mul_with(other, mul_karatsuba)
+	}
+
+	/// The product through the DFT, whatever the sizes
+	pub fn mul_dft(&self, other: &Poly) -> Poly {
+		self.mul_with(other, mul_dft)
+	}
+
+	fn mul_with(&self, other: &Poly, f: fn(&[Gf256], &[Gf256], &mut [Gf256])) -> Poly {
+		if self.is_zero() || other.is_zero() {
+			return Poly::zero();
+		}
+		let mut coeffs = vec![Gf256::zero(); self.coeffs.len() + other.coeffs.len() - 1];
+		f(&self.coeffs, &other.coeffs, &mut coeffs);
+		// The product of the leading coefficients is nonzero
+		Poly { coeffs }
+	}
+
+	/// self^e mod modulus, by square and multiply
+	pub fn powmod(&self, mut e: u64, modulus: &Poly) -> Poly {
+		let mut base = self.rem(modulus);
+		let mut acc = Poly::one().rem(modulus);
+		while e != 0 {
+			if e & 1 != 0 {
+				acc = acc.mulmod(&base, modulus);
+			}
+			base = base.mulmod(&base, modulus);
+			e >>= 1;
+		}
+		acc
+	}
+
+	/// self scaled so that its leading coefficient is one; zero stays zero
+	pub fn monic(&self) -> Poly {
+		match self.leading().inv() {
+			Some(inv) => self.scale(inv),
+			None => Poly::zero(),
+		}
+	}
+
+	/// The monic greatest common divisor, zero if both are zero
+	pub fn gcd(&self, other: &Poly) -> Poly {
+		self.xgcd(other).0
+	}
+
+	/// Returns (g, s, t) with g = gcd(self, other) monic and
+	/// s * self + t * other = g
+	pub fn xgcd(&self, other: &Poly) -> (Poly, Poly, Poly) {
+		let (g, s, t) = self.xgcd_until(other, 0);
+		match g.leading().inv() {
+			Some(inv) => (g.scale(inv), s.scale(inv), t.scale(inv)),
+			None => (g, s, t),
+		}
+	}
+
+	/// Runs the extended Euclidean algorithm on self and other until the
+	/// remainder r has degree below `degree` (or is the gcd), and returns
+	/// (r, s, t) with s * self + t * other = r. r is not made monic.
+	///
+	/// With self = x^2t and other the syndrome polynomial, stopping at
+	/// degree t gives the error evaluator r and the error locator t of the
+	/// Sugiyama decoder, up to a constant factor.
+	pub fn xgcd_until(&self, other: &Poly, degree: usize) -> (Poly, Poly, Poly) {
+		let (mut r0, mut r1) = (self.clone(), other.clone());
+		let (mut s0, mut s1) = (Poly::one(), Poly::zero());
+		let (mut t0, mut t1) = (Poly::zero(), Poly::one());
+		while r0.degree().is_some_and(|d| d >= degree) && !r1.is_zero() {
+			let (q, r) = r0.div_rem(&r1);
+			let s = &s0 - &(&q * &s1);
+			let t = &t0 - &(&q * &t1);
+			(r0, r1) = (r1, r);
+			(s0, s1) = (s1, s);
+			(t0, t1) = (t1, t);
+		}
+		(r0, s0, t0)
+	}
+
+	/// x^k * self
+	pub fn shift(&self, k: usize) -> Poly {
+		if self.is_zero() {
+			return Poly::zero();
+		}
+		let mut coeffs = vec![Gf256::zero(); k];
+		coeffs.extend_from_slice(&self.coeffs);
+		Poly { coeffs }
+	}
+
+	/// The formal derivative. In characteristic 2 the terms of even
+	/// degree drop out.
+	pub fn derivative(&self) -> Poly {
+		Poly::from_vec(self.coeffs.iter().enumerate().skip(1)
+			.map(|(i, &c)| if i % 2 == 1 { c } else { Gf256::zero() })
+			.collect())
+	}
+
+	/// The monic irreducible factors of self with their multiplicities,
+	/// ordered by degree and then by coefficients. Their product, times
+	/// self.leading(), is self; constants have no factors. Panics if self
+	/// is zero.
+	pub fn factor(&self) -> Vec<(Poly, usize)> {
+		assert!(!self.is_zero(), "Factoring the zero polynomial");
+		let mut factors: Vec<(Poly, usize)> = square_free(&self.monic()).into_iter()
+			.flat_map(|(f, e)| berlekamp(&f).into_iter().map(move |g| (g, e)))
+			.collect();
+		factors.sort_by(|(a, _), (b, _)| {
+			(a.degree(), as_byte_slice(&a.coeffs)).cmp(&(b.degree(), as_byte_slice(&b.coeffs)))
+		});
+		factors
+	}
+
+	/// Whether self has positive degree and no factors of lower positive
+	/// degree, by Rabin's test: self of degree t is irreducible iff
+	/// x^(256^t) = x mod self and gcd(x^(256^(t/p)) - x, self) = 1 for
+	/// every prime p dividing t. Cheaper than factor.
+	pub fn is_irreducible(&self) -> bool {
+		let t = match self.degree() {
+			Some(t) if t > 0 => t,
+			_ => return false,
+		};
+		let x = Poly::monomial(Gf256::one(), 1);
+		// frobenius[i] = x^(256^i) mod self
+		let x_mod = Poly::rem(&x, self);
+		let mut frobenius = vec![x_mod.clone()];
+		for i in 0..t {
+			let next = frobenius[i].powmod(256, self);
+			frobenius.push(next);
+		}
+		if frobenius[t] != x_mod {
+			return false;
+		}
+		let primes = (2..=t).filter(|&p| t.is_multiple_of(p) && (2..p).all(|q| !p.is_multiple_of(q)));
+		for p in primes {
+			if self.gcd(&(&frobenius[t / p] - &x)).degree() != Some(0) {
+				return false;
+			}
+		}
+		true
+	}
+}
+
+/// The square-free parts of a monic f: pairs (g, e) of square-free,
+/// pairwise coprime monic g with f the product of all g^e
+fn square_free(f: &Poly) -> Vec<(Poly, usize)> {
+	let mut out = vec![];
+	if f.degree().is_none_or(|d| d == 0) {
+		return out;
+	}
+	let mut c = f.gcd(&f.derivative());
+	let mut w = f.div_rem(&c).0;
+	let mut e = 1;
+	while w.degree() != Some(0) {
+		let y = w.gcd(&c);
+		let part = w.div_rem(&y).0;
+		if part.degree() != Some(0) {
+			out.push((part, e));
+		}
+		c = c.div_rem(&y).0;
+		w = y;
+		e += 1;
+	}
+	if c.degree() != Some(0) {
+		// Only even powers remain: c is the square of its root
+		let root = Poly::from_vec(c.coeffs.iter().step_by(2).map(|a| a.sqrt()).collect());
+		out.extend(square_free(&root).into_iter().map(|(g, e)| (g, 2 * e)));
+	}
+	out
+}
+
+/// The irreducible factors of a square-free monic f of positive degree
+fn berlekamp(f: &Poly) -> Vec<Poly> {
+	let n = f.degree().expect("Nonzero");
+	if n == 1 {
+		return vec![f.clone()];
+	}
+	// Column i holds x^(256 i) mod f minus x^i
+	let step = Poly::monomial(Gf256::one(), 256).rem(f);
+	let mut q = Matrix::zero(n, n);
+	let mut power = Poly::one();
+	for i in 0..n {
+		for j in 0..n {
+			q[(j, i)] = power.coeff(j);
+		}
+		q[(i, i)] -= Gf256::one();
+		power = power.mulmod(&step, f);
+	}
+	let kernel = kernel(&q);
+	let mut factors = vec![f.clone()];
+	for v in kernel.iter().filter(|v| v.degree().is_some_and(|d| d > 0)) {
+		if factors.len() == kernel.len() {
+			break;
+		}
+		let mut split = vec![];
+		for mut u in factors {
+			for s in Gf256::all() {
+				if u.degree() == Some(1) {
+					break;
+				}
+				let g = u.gcd(&(v - &Poly::constant(s)));
+				if g.degree().is_some_and(|d| d > 0) && g.degree() < u.degree() {
+					u = u.div_rem(&g).0;
+					split.push(g);
+				}
+			}
+			split.push(u);
+		}
+		factors = split;
+	}
+	factors
+}
+
+/// A basis of the vectors v with m v = 0, as polynomials with the vector
+/// entries as coefficients
+fn kernel(m: &Matrix) -> Vec<Poly> {
+	let r = m.rref();
+	let pivots: Vec<(usize, usize)> = (0..r.rows())
+		.filter_map(|i| r.row(i).iter().position(|c| c.poly != 0).map(|j| (i, j)))
+		.collect();
+	(0..r.cols())
+		.filter(|&j| pivots.iter().all(|&(_, p)| p != j))
+		.map(|free| {
+			let mut v = vec![Gf256::zero(); r.cols()];
+			v[free] = Gf256::one();
+			for &(i, p) in &pivots {
+				v[p] = r[(i, free)];
+			}
+			Poly::from_vec(v)
+		})
+		.collect()
+}
+
+/// One random polynomial per secret byte, all of degree at most degree,
+/// as random_with_secret would make them. The coefficients are stored
+/// by power: the coefficients of x^j of all polynomials are contiguous,
+/// so evaluation runs on all of them with one bulk multiply-add per
+/// power. They are overwritten with zeros on drop.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct PolyBatch {
+	degree: usize,
+	len: usize,
+	/// coeffs[j * len + i]: coefficient of x^j of polynomial i
+	coeffs: Vec<u8>,
+}
+
+impl PolyBatch {
+	/// Polynomial i has the constant term secrets[i]. rng is called once,
+	/// for degree * secrets.len() bytes.
+	pub fn random<R>(secrets: &[u8], degree: usize, mut rng: R) -> PolyBatch
+		where R: FnMut(&mut [u8]) {
+		let len = secrets.len();
+		let mut coeffs = vec![0u8; (degree + 1) * len];
+		coeffs[..len].copy_from_slice(secrets);
+		rng(&mut coeffs[len..]);
+		PolyBatch { degree, len, coeffs }
+	}
+
+	/// Number of polynomials
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Bound on the degree of every polynomial
+	pub fn degree(&self) -> usize {
+		self.degree
+	}
+
+	/// The coefficients of x^j of all polynomials
+	pub fn coefficient(&self, j: usize) -> &[u8] {
+		&self.coeffs[j * self.len..][..self.len]
+	}
+
+	/// Polynomial i on its own
+	pub fn poly(&self, i: usize) -> Poly {
+		Poly::from_vec((0..=self.degree).map(|j| Gf256::from_byte(self.coefficient(j)[i])).collect())
+	}
+
+	/// Writes the value of every polynomial at x to out, which has to hold
+	/// len bytes
+	pub fn eval_into(&self, x: Gf256, out: &mut [u8]) {
+		assert_eq!(out.len(), self.len, "Output length differs from the batch");
+		out.copy_from_slice(self.coefficient(self.degree));
+		for j in (0..self.degree).rev() {
+			bytes::mul_scalar(out, x.to_byte());
+			bytes::add_assign(out, self.coefficient(j));
+		}
+	}
+
+	pub fn eval(&self, x: Gf256) -> Vec<u8> {
+		let mut out = vec![0u8; self.len];
+		self.eval_into(x, &mut out);
+		out
+	}
+}
+
+impl Drop for PolyBatch {
+	fn drop(&mut self) {
+		for c in self.coeffs.iter_mut() {
+			// Volatile so that the dead stores are not optimized away
+			unsafe { core::ptr::write_volatile(c, 0) };
+		}
+		core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+	}
+}
+
+fn check_points(points: &[(Gf256, Gf256)]) -> Result<(), PolyError> {
+	for (i, &(x, _)) in points.iter().enumerate() {
+		if points[..i].iter().any(|&(y, _)| y == x) {
+			return Err(PolyError::DuplicatePoint);
+		}
+	}
+	Ok(())
+}
+
+/// The polynomial of degree below points.len() through all (x, y) points
+pub fn interpolate(points: &[(Gf256, Gf256)]) -> Result<Poly, PolyError> {
+	check_points(points)?;
+	// m = prod (x - x_j), lowest degree first
+	let mut m = vec![Gf256::one()];
+	for &(xj, _) in points {
+		m.insert(0, Gf256::zero());
+		for i in 0..m.len() - 1 {
+			let next = m[i + 1];
+			m[i] += next * xj;
+		}
+	}
+	let mut coeffs = vec![Gf256::zero(); points.len()];
+	let mut q = vec![Gf256::zero(); points.len()];
+	for &(xj, yj) in points {
+		// q = m / (x - x_j) by synthetic division, highest degree down
+		let mut carry = Gf256::zero();
+		for i in (0..q.len()).rev() {
+			carry = m[i + 1] + carry * xj;
+			q[i] = carry;
+		}
+		let denom = q.iter().rev().fold(Gf256::zero(), |acc, &c| acc * xj + c);
+		let w = yj / denom;
+		for (c, &qi) in coeffs.iter_mut().zip(&q) {
+			*c += w * qi;
+		}
+	}
+	Ok(Poly::from_vec(coeffs))
+}
+
+/// The value at x of the polynomial through points, without building it
+pub fn interpolate_at(points: &[(Gf256, Gf256)], x: Gf256) -> Result<Gf256, PolyError> {
+	check_points(points)?;
+	if let Some(&(_, y)) = points.iter().find(|&&(xj, _)| xj == x) {
+		return Ok(y);
+	}
+	// sum y_j prod_{m != j} (x - x_m) / (x_j - x_m)
+	Ok(points.iter().enumerate().map(|(j, &(xj, yj))| {
+		let (num, den) = points.iter().enumerate().filter(|&(m, _)| m != j)
+			.fold((Gf256::one(), Gf256::one()), |(n, d), (_, &(xm, _))| (n * (x - xm), d * (xj - xm)));
+		yj * num / den
+	}).sum())
+}
+
+impl Add<&Poly> for &Poly {
+	type Output = Poly;
+	fn add(self, rhs: &Poly) -> Poly {
+		let mut out = self.clone();
+		out += rhs;
+		out
+	}
+}
+
+impl AddAssign<&Poly> for Poly {
+	fn add_assign(&mut self, rhs: &Poly) {
+		if self.coeffs.len() < rhs.coeffs.len() {
+			self.coeffs.resize(rhs.coeffs.len(), Gf256::zero());
+		}
+		for (a, &b) in self.coeffs.iter_mut().zip(&rhs.coeffs) {
+			*a += b;
+		}
+		self.normalize();
+	}
+}
+
+impl Sub<&Poly> for &Poly {
+	type Output = Poly;
+	/// The same as addition in characteristic 2
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: &Poly) -> Poly {
+		self + rhs
+	}
+}
+
+impl Mul<&Poly> for &Poly {
+	type Output = Poly;
+	/// Picks the algorithm by the shorter factor, see the module
+	/// documentation
+	fn mul(self, rhs: &Poly) -> Poly {
+		if self.coeffs.len().min(rhs.coeffs.len()) >= KARATSUBA_MIN {
+			self.mul_karatsuba(rhs)
+		} else {
+			self.mul_schoolbook(rhs)
+		}
+	}
+}
+
+impl Mul<Gf256> for &Poly {
+	type Output = Poly;
+	fn mul(self, rhs: Gf256) -> Poly {
+		self.scale(rhs)
+	}
+}
+
+impl Div<&Poly> for &Poly {
+	type Output = Poly;
+	/// The quotient of div_rem
+	fn div(self, rhs: &Poly) -> Poly {
+		self.div_rem(rhs).0
+	}
+}
+
+impl Rem<&Poly> for &Poly {
+	type Output = Poly;
+	fn rem(self, rhs: &Poly) -> Poly {
+		self.div_rem(rhs).1
+	}
+}
+
+macro_rules! impl_owned_op {
+	($tr:ident, $method:ident) => {
+		impl $tr<Poly> for Poly {
+			type Output = Poly;
+			fn $method(self, rhs: Poly) -> Poly {
+				$tr::$method(&self, &rhs)
+			}
+		}
+
+		impl $tr<&Poly> for Poly {
+			type Output = Poly;
+			fn $method(self, rhs: &Poly) -> Poly {
+				$tr::$method(&self, rhs)
+			}
+		}
+	};
+}
+
+impl_owned_op!(Add, add);
+impl_owned_op!(Sub, sub);
+impl_owned_op!(Mul, mul);
+impl_owned_op!(Div, div);
+impl_owned_op!(Rem, rem);
+
+impl Mul<Gf256> for Poly {
+	type Output = Poly;
+	fn mul(self, rhs: Gf256) -> Poly {
+		self.scale(rhs)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs;
+
+    fn p(bytes: &[u8]) -> Poly {
+        Poly::from_bytes(bytes)
+    }
+
+    #[test]
+    fn test_normalized() {
+        assert_eq!(p(&[1, 2, 0, 0]), p(&[1, 2]));
+        assert_eq!(p(&[0, 0]), Poly::zero());
+        assert_eq!(Poly::zero().degree(), None);
+        assert_eq!(p(&[1, 2, 3]).degree(), Some(2));
+        assert_eq!(p(&[3, 4]) + p(&[1, 4]), p(&[2]));
+        assert_eq!(p(&[3, 4]) - p(&[3, 4]), Poly::zero());
+        assert_eq!(Poly::monomial(Gf256::from_byte(5), 3).coeff(3), Gf256::from_byte(5));
+        assert_eq!(p(&[1, 2]).scale(Gf256::zero()), Poly::zero());
+    }
+
+    #[test]
+    fn test_mul() {
+        // (x + 1)(x + 1) = x^2 + 1
+        assert_eq!(p(&[1, 1]) * p(&[1, 1]), p(&[1, 0, 1]));
+        let a = p(&[3, 0x10, 7]);
+        let b = p(&[0x55, 1]);
+        assert_eq!(&a * &b, &b * &a);
+        assert_eq!((&a * &b).degree(), Some(3));
+        assert_eq!(&a * &Poly::one(), a);
+        assert_eq!(a.shift(2), &a * &Poly::monomial(Gf256::one(), 2));
+        assert_eq!(a.clone() * Gf256::from_byte(2), a.scale(Gf256::from_byte(2)));
+    }
+
+    #[test]
+    fn test_mul_algorithms() {
+        let long = |n: usize, seed: usize| -> Poly {
+            Poly::from_vec((0..n).map(|i| Gf256::from_byte((i * seed + 1) as u8 | 1)).collect())
+        };
+        for (n, m) in [(1, 1), (5, 300), (50, 49), (100, 64), (128, 128), (129, 255), (400, 700), (1000, 3), (1100, 1500), (600, 3000)] {
+            let (a, b) = (long(n, 7), long(m, 13));
+            let expected = a.mul_schoolbook(&b);
+            assert_eq!(expected.degree(), Some(n + m - 2));
+            assert_eq!(a.mul_karatsuba(&b), expected, "{} x {}", n, m);
+            assert_eq!(a.mul_dft(&b), expected, "{} x {}", n, m);
+            assert_eq!(&a * &b, expected);
+        }
+        assert_eq!(Poly::zero().mul_dft(&long(300, 3)), Poly::zero());
+    }
+
+    #[test]
+    fn test_generator() {
+        // The RS generator is the product of (x - alpha^i)
+        let gen = Poly::from_high_first(&rs::generator_poly(10, 6, 0, Gf256::from_byte(2)).unwrap());
+        let product = (0..4u8).fold(Poly::one(), |acc, i| acc * Poly::from_vec(vec![Gf256::xexp(i), Gf256::one()]));
+        assert_eq!(gen, product);
+    }
+
+    #[test]
+    fn test_eval() {
+        let a = p(&[7, 0, 3, 1]);
+        let x = Gf256::from_byte(0x21);
+        assert_eq!(a.eval(x), Gf256::from_byte(7) + Gf256::from_byte(3) * x * x + x * x * x);
+        assert_eq!(Poly::zero().eval(x), Gf256::zero());
+        let points: Vec<Gf256> = Gf256::all().collect();
+        let many = a.eval_many(&points);
+        assert!(points.iter().zip(&many).all(|(&x, &y)| a.eval(x) == y));
+        assert!(Poly::zero().eval_many(&points).iter().all(|y| *y == Gf256::zero()));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let a = p(&[0x42, 9, 0, 0xd0, 1]);
+        let points: Vec<(Gf256, Gf256)> = [1u8, 7, 0, 0x80, 0x33]
+            .iter().map(|&x| (Gf256::from_byte(x), a.eval(Gf256::from_byte(x)))).collect();
+        assert_eq!(interpolate(&points), Ok(a.clone()));
+        assert_eq!(interpolate(&points[..1]), Ok(Poly::constant(points[0].1)));
+        assert_eq!(interpolate(&[]), Ok(Poly::zero()));
+        for x in [0u8, 1, 2, 0x99] {
+            let x = Gf256::from_byte(x);
+            assert_eq!(interpolate_at(&points, x), Ok(a.eval(x)));
+        }
+        let dup = [points[0], points[1], points[0]];
+        assert_eq!(interpolate(&dup), Err(PolyError::DuplicatePoint));
+        assert_eq!(interpolate_at(&dup, Gf256::zero()), Err(PolyError::DuplicatePoint));
+    }
+
+    #[test]
+    fn test_gcd() {
+        let common = p(&[5, 1]) * p(&[0x10, 0, 1]);
+        let a = &common * &p(&[3, 1]);
+        let b = &common * &p(&[7, 9, 1]);
+        let (g, s, t) = a.xgcd(&b);
+        assert_eq!(g, common.monic());
+        assert_eq!(&(&s * &a) + &(&t * &b), g);
+        assert_eq!(a.gcd(&Poly::zero()), a.monic());
+        assert_eq!(Poly::zero().gcd(&Poly::zero()), Poly::zero());
+        // Coprime polynomials
+        assert_eq!(p(&[1, 1]).gcd(&p(&[0, 1])), Poly::one());
+    }
+
+    #[test]
+    fn test_xgcd_until() {
+        let a = Poly::monomial(Gf256::one(), 8);
+        let b = p(&[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0]);
+        let (r, s, t) = a.xgcd_until(&b, 4);
+        assert!(r.degree().is_none_or(|d| d < 4));
+        assert_eq!(&(&s * &a) + &(&t * &b), r);
+        assert!(t.degree().unwrap() <= 4);
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let a = p(&[9, 8, 7, 6, 5, 4, 3]);
+        let b = p(&[0x11, 0, 0x22, 1]);
+        let (q, r) = a.div_rem(&b);
+        assert_eq!(&(&q * &b) + &r, a);
+        assert!(r.degree().unwrap() < 3);
+        assert_eq!(&a / &b, q);
+        assert_eq!(&a % &b, r);
+        assert_eq!(b.div_rem(&a), (Poly::zero(), b.clone()));
+    }
+
+    #[test]
+    fn test_systematic_parity() {
+        let (n, k) = (12, 8);
+        let gen_high = rs::generator_poly(n, k, 0, Gf256::from_byte(2)).unwrap();
+        let msg: Vec<u8> = (0..k as u8).map(|i| i * 29 + 1).collect();
+        let mut parity = vec![0u8; n - k];
+        rs::encode_parity(&gen_high, &msg, &mut parity);
+
+        let m = Poly::from_high_first(&msg.iter().map(|&b| Gf256::from_byte(b)).collect::<Vec<_>>());
+        let r = m.shift(n - k).rem(&Poly::from_high_first(&gen_high));
+        let expected: Vec<u8> = (0..n - k).rev().map(|i| r.coeff(i).to_byte()).collect();
+        assert_eq!(parity, expected);
+    }
+
+    #[test]
+    fn test_powmod() {
+        // In GF(256)[x] / (x^2 + x + 1) the class of x has order 3
+        let modulus = p(&[1, 1, 1]);
+        let x = p(&[0, 1]);
+        assert_eq!(x.powmod(3, &modulus), Poly::one());
+        assert_eq!(x.powmod(4, &modulus), x);
+        let a = p(&[3, 5, 7, 9]);
+        assert_eq!(a.powmod(2, &modulus), a.mulmod(&a, &modulus));
+        assert_eq!(a.powmod(0, &Poly::one()), Poly::zero());
+    }
+
+    #[test]
+    fn test_roots() {
+        let roots = [Gf256::from_byte(0), Gf256::from_byte(3), Gf256::from_byte(0xf0)];
+        let a = roots.iter().fold(p(&[5]), |acc, &r| acc * Poly::from_vec(vec![r, Gf256::one()]));
+        assert_eq!(a.roots(), roots);
+        // x^3 + x + 1 splits over GF(8), which is not a subfield
+        assert_eq!(p(&[1, 1, 0, 1]).roots(), []);
+        assert_eq!(Poly::zero().roots().len(), 256);
+    }
+
+    #[test]
+    fn test_error_positions() {
+        // Errors at positions 2 and 9 of a length 15 word: the locator is
+        // (1 - X_2 x)(1 - X_9 x) with X_p = alpha^(14 - p)
+        let n = 15;
+        let loc = [2usize, 9].iter().fold(Poly::one(), |acc, &pos| {
+            acc * Poly::from_vec(vec![Gf256::one(), Gf256::xexp((n - 1 - pos) as u8)])
+        });
+        assert_eq!(loc.error_positions(n), Some(vec![2, 9]));
+        assert_eq!(p(&[1, 1, 0, 1]).error_positions(n), None);
+        assert_eq!(loc.error_positions(8), None);
+    }
+
+    #[test]
+    fn test_random_with_secret() {
+        let mut rng = crate::test_rng(7);
+        let s = Gf256::from_byte(0x42);
+        let a = Poly::random_with_secret(s, 4, &mut rng);
+        assert_eq!(a.eval(Gf256::zero()), s);
+        assert!(a.degree().unwrap() <= 4);
+        assert_ne!(a, Poly::random_with_secret(s, 4, &mut rng));
+        assert_eq!(Poly::random_with_secret(s, 0, |b: &mut [u8]| assert!(b.is_empty())), Poly::constant(s));
+
+        let secrets = b"batched secret";
+        let mut calls = 0;
+        let batch = PolyBatch::random(secrets, 3, |buf: &mut [u8]| {
+            calls += 1;
+            assert_eq!(buf.len(), 3 * secrets.len());
+            rng(buf);
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(batch.len(), secrets.len());
+        assert_eq!(batch.coefficient(0), secrets);
+        for x in [0u8, 1, 9, 200] {
+            let x = Gf256::from_byte(x);
+            let values = batch.eval(x);
+            for (i, &v) in values.iter().enumerate() {
+                assert_eq!(batch.poly(i).eval(x).to_byte(), v);
+            }
+        }
+        assert_eq!(batch.eval(Gf256::zero()), secrets);
+    }
+
+    #[test]
+    fn test_factor() {
+        let x = |c: u8| p(&[c, 1]);
+        // x^2 + x + c is irreducible exactly if c has trace one
+        let c = Gf256::all().find(|c| c.trace() == 1).unwrap();
+        let quad = p(&[c.to_byte(), 1, 1]);
+        assert!(quad.is_irreducible());
+        assert!(quad.roots().is_empty());
+
+        let f = &(&(&x(3) * &x(3)) * &(&x(3) * &quad)) * &(&(&quad * &x(5)) * &Poly::constant(Gf256::from_byte(7)));
+        let factors = f.factor();
+        assert_eq!(factors, vec![(x(3), 3), (x(5), 1), (quad.clone(), 2)]);
+        let product = factors.iter()
+            .fold(Poly::constant(f.leading()), |acc, (g, e)| (0..*e).fold(acc, |acc, _| &acc * g));
+        assert_eq!(product, f);
+
+        // A square: zero derivative
+        let sq = &quad * &quad;
+        assert_eq!(sq.derivative(), Poly::zero());
+        assert_eq!(sq.factor(), vec![(quad, 2)]);
+
+        // 256 has order 3 mod 7, so x^7 + 1 is x + 1 times two cubics,
+        // while 15 divides 255 and x^15 + 1 splits into linear factors
+        let x7 = p(&[1, 0, 0, 0, 0, 0, 0, 1]).factor();
+        assert_eq!(x7.iter().map(|(g, e)| (g.degree().unwrap(), *e)).collect::<Vec<_>>(), [(1, 1), (3, 1), (3, 1)]);
+        assert!(x7.iter().all(|(g, _)| g.is_irreducible() && g.roots().len() == (g.degree() == Some(1)) as usize));
+        let x15 = Poly::monomial(Gf256::one(), 15) + Poly::one();
+        assert_eq!(x15.factor().len(), 15);
+        assert!(x15.factor().iter().all(|(g, e)| g.degree() == Some(1) && *e == 1));
+
+        assert_eq!(Poly::constant(Gf256::from_byte(9)).factor(), vec![]);
+        assert!(!Poly::one().is_irreducible());
+        assert!(!p(&[0, 0, 1]).is_irreducible());
+    }
+
+    #[test]
+    fn test_irreducible() {
+        for a in Gf256::nonzero() {
+            let q = Poly::from_coeffs(&[a, Gf256::one(), Gf256::one()]);
+            assert_eq!(q.is_irreducible(), a.trace() == 1);
+        }
+        assert!(p(&[5, 1]).is_irreducible());
+        assert!(!Poly::zero().is_irreducible());
+        // Rabin's test agrees with factoring, also for reducible
+        // polynomials without roots
+        for seed in 0..40usize {
+            let f = Poly::from_vec((0..6).map(|j| Gf256::from_byte((seed * 53 + j * 17 + 1) as u8)).collect());
+            assert_eq!(f.is_irreducible(), matches!(&f.factor()[..], [(_, 1)]), "{:?}", f);
+        }
+        let cubic = (0u8..).map(|c| p(&[c, 1, 0, 1])).find(Poly::is_irreducible).unwrap();
+        assert!(!(&cubic * &cubic).is_irreducible());
+        let quads: Vec<Poly> = Gf256::all().filter(|c| c.trace() == 1).take(2)
+            .map(|c| p(&[c.to_byte(), 1, 1])).collect();
+        let quartic = &quads[0] * &quads[1];
+        assert!(quartic.roots().is_empty() && !quartic.is_irreducible());
+    }
+}
@@ -0,0 +1,102 @@
+//! Sliding-window exponentiation.
+//!
+//! Gf256::exp only needs a table lookup, but elements of fields without
+//! log tables (such as GF(2^64) or GF(2^128), where inversion is done by
+//! raising to 2^n - 2) need a square-and-multiply loop. The functions here
+//! work for any type with an associative multiplication.
+
+use core::ops::Mul;
+
+/// Window width used by pow
+pub const DEFAULT_WINDOW: u32 = 4;
+
+/// Returns base ^ exponent, with one the neutral element
+pub fn pow<T>(base: T, one: T, exponent: u128) -> T
+	where T: Copy + Mul<Output = T> {
+	pow_window(base, one, exponent, DEFAULT_WINDOW)
+}
+
+/// Returns base ^ exponent using windows of up to window bits (1 to 8).
+///
+/// The odd powers base^1, base^3, ..., base^(2^window - 1) are computed
+/// first; afterwards every window costs one multiplication on top of the
+/// squarings, instead of one per set bit.
+pub fn pow_window<T>(base: T, one: T, exponent: u128, window: u32) -> T
+	where T: Copy + Mul<Output = T> {
+	assert!((1..=8).contains(&window), "Window must have 1 to 8 bits");
+	if exponent == 0 {
+		return one;
+	}
+
+	let mut odd = [base; 128];
+	let square = base * base;
+	for i in 1..1usize << (window - 1) {
+		odd[i] = odd[i - 1] * square;
+	}
+
+	let mut acc = one;
+	let mut started = false;
+	let mut i = 127 - exponent.leading_zeros() as i32;
+	while i >= 0 {
+		if exponent >> i & 1 == 0 {
+			if started {
+				acc = acc * acc;
+			}
+			i -= 1;
+			continue;
+		}
+		// Longest window i..=j of at most window bits ending in a one
+		let mut j = (i - window as i32 + 1).max(0);
+		while exponent >> j & 1 == 0 {
+			j += 1;
+		}
+		let bits = ((exponent >> j) & ((1u128 << (i - j + 1)) - 1)) as usize;
+		if started {
+			for _ in j..=i {
+				acc = acc * acc;
+			}
+			acc = acc * odd[bits >> 1];
+		} else {
+			acc = odd[bits >> 1];
+			started = true;
+		}
+		i = j - 1;
+	}
+	acc
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::Wrapping;
+    use crate::Gf256;
+
+    #[test]
+    fn test_gf256() {
+        let x = Gf256::from_byte(0x53);
+        for e in 0..600u128 {
+            assert_eq!(pow(x, Gf256::one(), e), x.exp((e % 255) as u8));
+        }
+        // Fermat inversion
+        assert_eq!(pow(x, Gf256::one(), 254) * x, Gf256::one());
+    }
+
+    #[test]
+    fn test_all_windows() {
+        let base = Wrapping(0x9e37_79b9_7f4a_7c15u64);
+        for window in 1..=8 {
+            for &e in &[1u32, 2, 3, 255, 256, 0xdead_beef, u32::MAX] {
+                assert_eq!(pow_window(base, Wrapping(1), e as u128, window),
+                    Wrapping(base.0.wrapping_pow(e)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_exponent() {
+        // 3 has order dividing 2^62 modulo 2^64, so 3^(2^127 + 5) = 3^5
+        let e = (1u128 << 127) + 5;
+        assert_eq!(pow(Wrapping(3u64), Wrapping(1), e), Wrapping(243));
+    }
+}
@@ -0,0 +1,253 @@
+//! Error correction for QR codes (ISO/IEC 18004, section 7.5 and 7.6).
+//!
+//! QR codes use Reed-Solomon codes over GF(256) with the polynomial 0x11D
+//! and the generator 2, the field of this crate, with first consecutive
+//! root a^0 like RsCodec. A symbol of a given version and error
+//! correction level splits its data codewords into up to two groups of
+//! blocks, where the blocks of the second group are one codeword longer,
+//! and every block gets the same number of EC codewords.
+//!
+//! The final sequence takes the first data codeword of every block, then
+//! the second of every block and so on, skipping the short blocks once
+//! they run out, followed by the EC codewords interleaved the same way.
+//!
+//! Block counts and EC lengths are the ones of table 9 of the standard;
+//! the number of codewords of a version follows from its module layout.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::rs::{ generator_poly, ConfigError };
+use crate::rs::encoder::RsEncoder;
+
+/// Errors produced by the QR helpers
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum QrError {
+	/// Versions run from 1 to 40
+	InvalidVersion(u8),
+	/// The data does not have the data capacity of the symbol
+	DataLength { expected: usize, got: usize },
+}
+
+impl fmt::Display for QrError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			QrError::InvalidVersion(v) => write!(f, "invalid QR version {}", v),
+			QrError::DataLength { expected, got } =>
+				write!(f, "expected {} data codewords, got {}", expected, got),
+		}
+	}
+}
+
+impl error::Error for QrError {}
+
+/// Error correction level
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum EcLevel {
+	L,
+	M,
+	Q,
+	H,
+}
+
+/// EC codewords per block, by level and version (index 0 unused)
+const EC_PER_BLOCK: [[u8; 41]; 4] = [
+	[0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28,
+		28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+	[0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26,
+		26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+	[0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30,
+		28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+	[0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28,
+		30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+/// Number of blocks, by level and version (index 0 unused)
+const BLOCKS: [[u8; 41]; 4] = [
+	[0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8,
+		8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+	[0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16,
+		17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+	[0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20,
+		23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+	[0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25,
+		25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// Total codewords of a version: the modules left after the function
+/// patterns and the format and version information, divided by 8
+const fn total_codewords(version: usize) -> usize {
+	let mut modules = (16 * version + 128) * version + 64;
+	if version >= 2 {
+		let align = version / 7 + 2;
+		modules -= (25 * align - 10) * align - 55;
+		if version >= 7 {
+			modules -= 36;
+		}
+	}
+	modules / 8
+}
+
+/// The QR generator polynomial with ec_len EC codewords, highest degree
+/// first; ec_len runs up to 254
+pub fn generator(ec_len: usize) -> Result<Vec<Gf256>, ConfigError> {
+	generator_poly(ec_len + 1, 1, 0, Gf256::GENERATOR)
+}
+
+/// The generator as the exponents of its coefficients, in the form the
+/// standard lists them in annex A
+pub fn generator_exponents(ec_len: usize) -> Result<Vec<u8>, ConfigError> {
+	Ok(generator(ec_len)?.into_iter().map(|c| c.log().expect("Nonzero coefficient")).collect())
+}
+
+/// The data and EC codewords of one block
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Block {
+	pub data: Vec<u8>,
+	pub ec: Vec<u8>,
+}
+
+/// The block structure of one version and level
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct BlockStructure {
+	/// EC codewords of every block
+	pub ec_per_block: usize,
+	/// Number of blocks and data codewords per block in each group; the
+	/// second group has one data codeword more and may be empty
+	pub groups: [(usize, usize); 2],
+}
+
+impl BlockStructure {
+	pub fn new(version: u8, level: EcLevel) -> Result<BlockStructure, QrError> {
+		if !(1..=40).contains(&version) {
+			return Err(QrError::InvalidVersion(version));
+		}
+		let v = version as usize;
+		let ec_per_block = EC_PER_BLOCK[level as usize][v] as usize;
+		let blocks = BLOCKS[level as usize][v] as usize;
+		let total = total_codewords(v);
+		let long = total % blocks;
+		let short_len = total / blocks - ec_per_block;
+		Ok(BlockStructure { ec_per_block, groups: [(blocks - long, short_len), (long, short_len + 1)] })
+	}
+
+	pub fn block_count(&self) -> usize {
+		self.groups[0].0 + self.groups[1].0
+	}
+
+	/// Data codewords of every block, in order
+	pub fn block_lengths(&self) -> impl Iterator<Item = usize> {
+		let [(n1, l1), (n2, l2)] = self.groups;
+		core::iter::repeat_n(l1, n1).chain(core::iter::repeat_n(l2, n2))
+	}
+
+	/// Data capacity of the symbol in codewords
+	pub fn data_codewords(&self) -> usize {
+		self.block_lengths().sum()
+	}
+
+	/// Data and EC codewords of the symbol
+	pub fn total_codewords(&self) -> usize {
+		self.data_codewords() + self.block_count() * self.ec_per_block
+	}
+
+	/// Splits data into blocks and computes their EC codewords
+	pub fn blocks(&self, data: &[u8]) -> Result<Vec<Block>, QrError> {
+		let expected = self.data_codewords();
+		if data.len() != expected {
+			return Err(QrError::DataLength { expected, got: data.len() });
+		}
+		let mut codes: [Option<RsEncoder>; 2] = [None, None];
+		let mut rest = data;
+		let mut out = Vec::with_capacity(self.block_count());
+		for len in self.block_lengths() {
+			let (block, tail) = rest.split_at(len);
+			rest = tail;
+			let code = codes[len - self.groups[0].1].get_or_insert_with(|| {
+				RsEncoder::new(len + self.ec_per_block, len).expect("QR blocks fit RS codes")
+			});
+			out.push(Block { data: block.to_vec(), ec: code.parity(block) });
+		}
+		Ok(out)
+	}
+
+	/// The final codeword sequence of the symbol for its data codewords,
+	/// before the remainder bits
+	pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, QrError> {
+		let blocks = self.blocks(data)?;
+		let mut out = vec![];
+		// The long block length, even if there are no long blocks
+		for i in 0..self.groups[1].1 {
+			out.extend(blocks.iter().filter_map(|b| b.data.get(i)));
+		}
+		for i in 0..self.ec_per_block {
+			out.extend(blocks.iter().map(|b| b.ec[i]));
+		}
+		Ok(out)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generators() {
+        assert_eq!(generator_exponents(7).unwrap(), [0, 87, 229, 146, 149, 238, 102, 21]);
+        assert_eq!(generator_exponents(10).unwrap(), [0, 251, 67, 46, 61, 118, 70, 64, 94, 32, 45]);
+        assert_eq!(generator_exponents(13).unwrap(),
+            [0, 74, 152, 176, 100, 86, 100, 106, 104, 130, 218, 206, 140, 78]);
+        assert_eq!(generator(10).unwrap(), RsEncoder::new(26, 16).unwrap().generator());
+        assert!(generator(255).is_err());
+    }
+
+    #[test]
+    fn test_capacities() {
+        // Data codewords of versions 1, 10, 20 and 40 at levels L, M, Q, H
+        let expected = [
+            (1, [19, 16, 13, 9]),
+            (10, [274, 216, 154, 122]),
+            (20, [861, 669, 485, 385]),
+            (40, [2956, 2334, 1666, 1276]),
+        ];
+        let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for (version, capacities) in expected {
+            for (level, capacity) in levels.into_iter().zip(capacities) {
+                let s = BlockStructure::new(version, level).unwrap();
+                assert_eq!(s.data_codewords(), capacity, "{}-{:?}", version, level);
+            }
+        }
+        for version in 1..=40 {
+            let total = total_codewords(version as usize);
+            for level in levels {
+                assert_eq!(BlockStructure::new(version, level).unwrap().total_codewords(), total);
+            }
+        }
+        assert_eq!(BlockStructure::new(0, EcLevel::L), Err(QrError::InvalidVersion(0)));
+    }
+
+    #[test]
+    fn test_encode() {
+        // "HELLO WORLD" as a version 1-M symbol
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        let s = BlockStructure::new(1, EcLevel::M).unwrap();
+        let out = s.encode(&data).unwrap();
+        assert_eq!(out[..16], data);
+        assert_eq!(out[16..], [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+
+        // 5-Q: two blocks of 15 and two of 16 data codewords, 18 EC each
+        let s = BlockStructure::new(5, EcLevel::Q).unwrap();
+        assert_eq!(s.groups, [(2, 15), (2, 16)]);
+        let data: Vec<u8> = (0..62).collect();
+        let out = s.encode(&data).unwrap();
+        assert_eq!(out[..8], [0, 15, 30, 46, 1, 16, 31, 47]);
+        assert_eq!(out[56..62], [14, 29, 44, 60, 45, 61]);
+        let blocks = s.blocks(&data).unwrap();
+        assert_eq!(out[62..66], [blocks[0].ec[0], blocks[1].ec[0], blocks[2].ec[0], blocks[3].ec[0]]);
+        assert_eq!(out.len(), 134);
+        assert_eq!(s.encode(&data[1..]), Err(QrError::DataLength { expected: 62, got: 61 }));
+    }
+}
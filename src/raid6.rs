@@ -0,0 +1,247 @@
+//! RAID-6 parity as in Linux md.
+//!
+//! An array of n data disks D_0 .. D_(n-1) carries two parity disks:
+//!
+//! ```text
+//! P = D_0 + D_1 + ... + D_(n-1)
+//! Q = g^0 D_0 + g^1 D_1 + ... + g^(n-1) D_(n-1)
+//! ```
+//!
+//! with g = 2 (x) in this crate's field, which is the field of the Linux
+//! RAID-6 code (H. Peter Anvin, "The mathematics of RAID-6"). Any two
+//! lost disks can be rebuilt; n can be at most 255, since g has order
+//! 255.
+//!
+//! All functions take the whole array as one slice of n + 2 equally long
+//! shards, data first, then P and Q, and rebuild lost shards in place, so
+//! no allocator is needed. The old contents of lost shards do not matter.
+
+use core::fmt;
+use core::error;
+use core::mem;
+
+use crate::Gf256;
+use crate::bytes;
+
+/// The most data shards an array can have
+pub const MAX_DATA_SHARDS: usize = 255;
+
+/// Errors of the RAID-6 functions
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Raid6Error {
+	/// Fewer than one data shard plus P and Q, or too many data shards
+	InvalidShardCount(usize),
+	/// The shards differ in length
+	ShardSizeMismatch,
+	/// A failed index is outside the array, or the same twice
+	InvalidIndex(usize),
+	/// More than two shards are lost
+	TooManyFailures,
+}
+
+impl fmt::Display for Raid6Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Raid6Error::InvalidShardCount(n) => write!(f, "invalid shard count {}", n),
+			Raid6Error::ShardSizeMismatch => f.write_str("shards differ in size"),
+			Raid6Error::InvalidIndex(i) => write!(f, "invalid failed shard index {}", i),
+			Raid6Error::TooManyFailures => f.write_str("more than two shards lost"),
+		}
+	}
+}
+
+impl error::Error for Raid6Error {}
+
+/// The number of data shards
+fn check(shards: &[&mut [u8]]) -> Result<usize, Raid6Error> {
+	let n = shards.len().wrapping_sub(2);
+	if shards.len() < 3 || n > MAX_DATA_SHARDS {
+		return Err(Raid6Error::InvalidShardCount(shards.len()));
+	}
+	if shards.iter().any(|s| s.len() != shards[0].len()) {
+		return Err(Raid6Error::ShardSizeMismatch);
+	}
+	Ok(n)
+}
+
+/// The coefficient of data shard i in Q
+fn coeff(i: usize) -> u8 {
+	Gf256::xexp(i as u8).to_byte()
+}
+
+/// Computes P and Q from the data shards
+pub fn compute_parity(shards: &mut [&mut [u8]]) -> Result<(), Raid6Error> {
+	let n = check(shards)?;
+	let p = mem::take(&mut shards[n]);
+	let q = mem::take(&mut shards[n + 1]);
+	p.copy_from_slice(shards[0]);
+	q.copy_from_slice(shards[0]);
+	for (i, d) in shards[..n].iter().enumerate().skip(1) {
+		bytes::add_assign(p, d);
+		bytes::mul_add(q, d, coeff(i));
+	}
+	shards[n] = p;
+	shards[n + 1] = q;
+	Ok(())
+}
+
+/// Rebuilds data shard x from the other data shards and P
+pub fn recover_data(shards: &mut [&mut [u8]], x: usize) -> Result<(), Raid6Error> {
+	let n = check(shards)?;
+	if x >= n {
+		return Err(Raid6Error::InvalidIndex(x));
+	}
+	let dx = mem::take(&mut shards[x]);
+	dx.copy_from_slice(shards[n]);
+	for (i, d) in shards[..n].iter().enumerate() {
+		if i != x {
+			bytes::add_assign(dx, d);
+		}
+	}
+	shards[x] = dx;
+	Ok(())
+}
+
+/// Rebuilds data shard x and P from the other data shards and Q
+pub fn recover_data_and_p(shards: &mut [&mut [u8]], x: usize) -> Result<(), Raid6Error> {
+	let n = check(shards)?;
+	if x >= n {
+		return Err(Raid6Error::InvalidIndex(x));
+	}
+	// Q + the other terms of Q = g^x D_x
+	let dx = mem::take(&mut shards[x]);
+	dx.copy_from_slice(shards[n + 1]);
+	for (i, d) in shards[..n].iter().enumerate() {
+		if i != x {
+			bytes::mul_add(dx, d, coeff(i));
+		}
+	}
+	bytes::mul_scalar(dx, Gf256::xexp(255 - x as u8).to_byte());
+	shards[x] = dx;
+	compute_parity(shards)
+}
+
+/// Rebuilds the data shards x and y from the other data shards, P and Q
+pub fn recover_two_data(shards: &mut [&mut [u8]], x: usize, y: usize) -> Result<(), Raid6Error> {
+	let n = check(shards)?;
+	for i in [x, y] {
+		if i >= n {
+			return Err(Raid6Error::InvalidIndex(i));
+		}
+	}
+	if x == y {
+		return Err(Raid6Error::InvalidIndex(y));
+	}
+	let dx = mem::take(&mut shards[x]);
+	let dy = mem::take(&mut shards[y]);
+	// dx = D_x + D_y and dy = g^x D_x + g^y D_y
+	dx.copy_from_slice(shards[n]);
+	dy.copy_from_slice(shards[n + 1]);
+	for (i, d) in shards[..n].iter().enumerate() {
+		if i != x && i != y {
+			bytes::add_assign(dx, d);
+			bytes::mul_add(dy, d, coeff(i));
+		}
+	}
+	// dy + g^y dx = (g^x + g^y) D_x
+	bytes::mul_add(dy, dx, coeff(y));
+	let denom = Gf256::from_byte(coeff(x) ^ coeff(y));
+	bytes::mul_scalar(dy, denom.inv().expect("g^x != g^y for x != y < 255").to_byte());
+	bytes::add_assign(dx, dy);
+	// Now dy holds D_x and dx holds D_y
+	dx.swap_with_slice(dy);
+	shards[x] = dx;
+	shards[y] = dy;
+	Ok(())
+}
+
+/// Rebuilds up to two lost shards of any kind, naming them by index:
+/// 0 to n - 1 for data, n for P and n + 1 for Q
+pub fn recover(shards: &mut [&mut [u8]], failed: &[usize]) -> Result<(), Raid6Error> {
+	let n = check(shards)?;
+	let (mut data, mut count, mut p_lost) = ([0usize; 2], 0, false);
+	for (i, &f) in failed.iter().enumerate() {
+		if f >= n + 2 || failed[..i].contains(&f) {
+			return Err(Raid6Error::InvalidIndex(f));
+		}
+		if i >= 2 {
+			return Err(Raid6Error::TooManyFailures);
+		}
+		if f < n {
+			data[count] = f;
+			count += 1;
+		}
+		p_lost |= f == n;
+	}
+	match (count, p_lost) {
+		(0, _) if failed.is_empty() => Ok(()),
+		(0, _) => compute_parity(shards),
+		(1, true) => recover_data_and_p(shards, data[0]),
+		(1, false) => {
+			recover_data(shards, data[0])?;
+			if failed.len() == 2 {
+				// The other one is Q
+				compute_parity(shards)?;
+			}
+			Ok(())
+		},
+		_ => recover_two_data(shards, data[0], data[1]),
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(n: usize) -> Vec<Vec<u8>> {
+        let mut shards: Vec<Vec<u8>> = (0..n).map(|i| (0..40).map(|j| (i * 41 + j * 7 + 1) as u8).collect()).collect();
+        shards.push(vec![0; 40]);
+        shards.push(vec![0; 40]);
+        let mut refs: Vec<&mut [u8]> = shards.iter_mut().map(|s| &mut s[..]).collect();
+        compute_parity(&mut refs).unwrap();
+        shards
+    }
+
+    #[test]
+    fn test_parity() {
+        let shards = array(3);
+        for j in 0..40 {
+            let d: Vec<Gf256> = shards[..3].iter().map(|s| Gf256::from_byte(s[j])).collect();
+            assert_eq!(shards[3][j], (d[0] + d[1] + d[2]).to_byte());
+            assert_eq!(shards[4][j], (d[0] + d[1] * Gf256::from_byte(2) + d[2] * Gf256::from_byte(4)).to_byte());
+        }
+    }
+
+    #[test]
+    fn test_recover_all_pairs() {
+        let n = 6;
+        let good = array(n);
+        for a in 0..n + 2 {
+            for b in a..n + 2 {
+                let failed: Vec<usize> = if a == b { vec![a] } else { vec![b, a] };
+                let mut shards = good.clone();
+                for &f in &failed {
+                    shards[f].fill(0xee);
+                }
+                let mut refs: Vec<&mut [u8]> = shards.iter_mut().map(|s| &mut s[..]).collect();
+                recover(&mut refs, &failed).unwrap();
+                assert_eq!(shards, good, "lost {:?}", failed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut shards = array(2);
+        let mut refs: Vec<&mut [u8]> = shards.iter_mut().map(|s| &mut s[..]).collect();
+        assert_eq!(recover(&mut refs, &[0, 1, 2]), Err(Raid6Error::TooManyFailures));
+        assert_eq!(recover(&mut refs, &[4]), Err(Raid6Error::InvalidIndex(4)));
+        assert_eq!(recover(&mut refs, &[1, 1]), Err(Raid6Error::InvalidIndex(1)));
+        assert_eq!(recover_two_data(&mut refs, 0, 0), Err(Raid6Error::InvalidIndex(0)));
+        assert_eq!(recover_data(&mut refs[..2], 0), Err(Raid6Error::InvalidShardCount(2)));
+        let mut short = [0u8; 3];
+        refs[1] = &mut short;
+        assert_eq!(compute_parity(&mut refs), Err(Raid6Error::ShardSizeMismatch));
+    }
+}
@@ -0,0 +1,398 @@
+//! Recovery records for archives.
+//!
+//! A recovery record is appended to an existing file. It holds a CRC-32
+//! of every sector of the file and erasure-coded recovery sectors, so a
+//! damaged copy can later be checked and, as long as not too many
+//! sectors of the same group were hit, repaired.
+//!
+//! The file is cut into sectors of sector_size bytes (the last one zero
+//! padded). With S sectors and k data sectors per group there are
+//! G = ceil(S / k) groups; group g holds the sectors g, g + G, g + 2G, ...
+//! and gets m recovery sectors. Spreading groups over the whole file
+//! means a single run of up to m * G damaged sectors is repairable.
+//!
+//! Record layout, all integers little endian:
+//!
+//! | bytes     | content                                          |
+//! |-----------|--------------------------------------------------|
+//! | 32        | header: magic "GFRR", version, reserved, sector  |
+//! |           | size (u32), file length (u64), k (u16), m (u16), |
+//! |           | CRC of the checksum table (u32), header CRC (u32)|
+//! | 4 (S + R) | CRC-32 of every data and recovery sector         |
+//! | R sectors | recovery sectors, R = G * m, group by group      |
+//! | 16        | footer: record length (u64), "GFRRtail"          |
+
+use std::fmt;
+use std::error;
+
+use crate::rs::erasure::ErasureCode;
+
+const MAGIC: [u8; 4] = *b"GFRR";
+const TAIL_MAGIC: [u8; 8] = *b"GFRRtail";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 32;
+const FOOTER_LEN: usize = 16;
+
+/// Errors produced while creating or using a recovery record
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum RecoveryError {
+	/// Sector size or group parameters are not usable
+	InvalidParameters,
+	/// No recovery record was found at the end of the file
+	NotFound,
+	/// The record's own header or checksum table is damaged
+	CorruptRecord,
+	/// The record was written by an incompatible version
+	UnsupportedVersion(u8),
+}
+
+impl fmt::Display for RecoveryError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			RecoveryError::InvalidParameters => f.write_str("invalid recovery record parameters"),
+			RecoveryError::NotFound => f.write_str("no recovery record found"),
+			RecoveryError::CorruptRecord => f.write_str("the recovery record itself is damaged"),
+			RecoveryError::UnsupportedVersion(v) => write!(f, "unsupported record version {}", v),
+		}
+	}
+}
+
+impl error::Error for RecoveryError {}
+
+/// CRC-32 (IEEE 802.3, as used by zip and PNG)
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+	crate::crc::CRC32.checksum(data) as u32
+}
+
+/// Parameters of a recovery record
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Options {
+	/// Bytes per sector
+	pub sector_size: usize,
+	/// Data sectors per group (k)
+	pub data_sectors: usize,
+	/// Recovery sectors per group (m)
+	pub recovery_sectors: usize,
+}
+
+impl Default for Options {
+	/// 512 byte sectors with 10% redundancy
+	fn default() -> Options {
+		Options { sector_size: 512, data_sectors: 200, recovery_sectors: 20 }
+	}
+}
+
+impl Options {
+	fn check(&self) -> Result<(), RecoveryError> {
+		let (k, m) = (self.data_sectors, self.recovery_sectors);
+		if self.sector_size == 0 || self.sector_size > u32::MAX as usize || k == 0 || m == 0
+			|| k + m > 256 {
+			return Err(RecoveryError::InvalidParameters);
+		}
+		Ok(())
+	}
+}
+
+/// Geometry of a file's sectors and groups
+struct Layout {
+	opts: Options,
+	data_len: usize,
+	sectors: usize,
+	groups: usize,
+}
+
+impl Layout {
+	fn new(opts: Options, data_len: usize) -> Layout {
+		let sectors = data_len.div_ceil(opts.sector_size);
+		let groups = sectors.div_ceil(opts.data_sectors);
+		Layout { opts, data_len, sectors, groups }
+	}
+
+	fn recovery_count(&self) -> usize {
+		self.groups * self.opts.recovery_sectors
+	}
+
+	fn record_len(&self) -> usize {
+		HEADER_LEN + 4 * (self.sectors + self.recovery_count())
+			+ self.recovery_count() * self.opts.sector_size + FOOTER_LEN
+	}
+
+	/// Sector number of slot j of group g, None for padding
+	fn sector(&self, g: usize, j: usize) -> Option<usize> {
+		Some(g + j * self.groups).filter(|&s| s < self.sectors)
+	}
+
+	/// Sector s of data, zero padded
+	fn read(&self, data: &[u8], s: usize) -> Vec<u8> {
+		let size = self.opts.sector_size;
+		let start = s * size;
+		let end = (start + size).min(self.data_len);
+		let mut sector = data[start..end].to_vec();
+		sector.resize(size, 0);
+		sector
+	}
+}
+
+/// Computes the recovery record for data. Appending it to data gives a
+/// protected file.
+pub fn create(data: &[u8], opts: Options) -> Result<Vec<u8>, RecoveryError> {
+	opts.check()?;
+	let layout = Layout::new(opts, data.len());
+	let (k, m, size) = (opts.data_sectors, opts.recovery_sectors, opts.sector_size);
+
+	let mut table = Vec::with_capacity(4 * (layout.sectors + layout.recovery_count()));
+	for s in 0..layout.sectors {
+		table.extend_from_slice(&crc32(&layout.read(data, s)).to_le_bytes());
+	}
+
+	let code = ErasureCode::new(k, m).map_err(|_| RecoveryError::InvalidParameters)?;
+	let mut recovery = Vec::with_capacity(layout.recovery_count() * size);
+	for g in 0..layout.groups {
+		let group: Vec<Vec<u8>> = (0..k)
+			.map(|j| layout.sector(g, j).map_or_else(|| vec![0; size], |s| layout.read(data, s)))
+			.collect();
+		let mut parity = vec![vec![0u8; size]; m];
+		let group_refs: Vec<&[u8]> = group.iter().map(|s| &s[..]).collect();
+		let mut parity_refs: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+		code.encode(&group_refs, &mut parity_refs).expect("Shards have the right shape");
+		for p in &parity {
+			table.extend_from_slice(&crc32(p).to_le_bytes());
+			recovery.extend_from_slice(p);
+		}
+	}
+
+	let mut record = Vec::with_capacity(layout.record_len());
+	record.extend_from_slice(&MAGIC);
+	record.extend_from_slice(&[VERSION, 0, 0, 0]);
+	record.extend_from_slice(&(size as u32).to_le_bytes());
+	record.extend_from_slice(&(data.len() as u64).to_le_bytes());
+	record.extend_from_slice(&(k as u16).to_le_bytes());
+	record.extend_from_slice(&(m as u16).to_le_bytes());
+	record.extend_from_slice(&crc32(&table).to_le_bytes());
+	let header_crc = crc32(&record);
+	record.extend_from_slice(&header_crc.to_le_bytes());
+	record.extend_from_slice(&table);
+	record.extend_from_slice(&recovery);
+	record.extend_from_slice(&((layout.record_len()) as u64).to_le_bytes());
+	record.extend_from_slice(&TAIL_MAGIC);
+	debug_assert_eq!(record.len(), layout.record_len());
+	Ok(record)
+}
+
+/// Appends a recovery record to file
+pub fn append(file: &mut Vec<u8>, opts: Options) -> Result<(), RecoveryError> {
+	let record = create(file, opts)?;
+	file.extend_from_slice(&record);
+	Ok(())
+}
+
+/// Result of checking or repairing a protected file
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Report {
+	/// Data sectors whose checksum did not match
+	pub damaged: Vec<usize>,
+	/// Damaged data sectors which could not be repaired
+	pub unrecoverable: Vec<usize>,
+	/// Number of damaged recovery sectors
+	pub damaged_recovery: usize,
+}
+
+impl Report {
+	/// Whether the data is intact (after repair, if any)
+	pub fn is_ok(&self) -> bool {
+		self.unrecoverable.is_empty()
+	}
+}
+
+fn u32_at(b: &[u8], pos: usize) -> u32 {
+	u32::from_le_bytes([b[pos], b[pos + 1], b[pos + 2], b[pos + 3]])
+}
+
+/// A parsed record
+struct Parsed {
+	layout: Layout,
+	/// Offset of the record in the file
+	start: usize,
+	crcs: Vec<u32>,
+}
+
+fn parse(file: &[u8]) -> Result<Parsed, RecoveryError> {
+	if file.len() < FOOTER_LEN || file[file.len() - 8..] != TAIL_MAGIC {
+		return Err(RecoveryError::NotFound);
+	}
+	let mut len = [0u8; 8];
+	len.copy_from_slice(&file[file.len() - FOOTER_LEN..file.len() - 8]);
+	let record_len = u64::from_le_bytes(len) as usize;
+	if record_len < HEADER_LEN + FOOTER_LEN || record_len > file.len() {
+		return Err(RecoveryError::CorruptRecord);
+	}
+	let start = file.len() - record_len;
+	let rec = &file[start..];
+	if rec[..4] != MAGIC || crc32(&rec[..HEADER_LEN - 4]) != u32_at(rec, HEADER_LEN - 4) {
+		return Err(RecoveryError::CorruptRecord);
+	}
+	if rec[4] != VERSION {
+		return Err(RecoveryError::UnsupportedVersion(rec[4]));
+	}
+	let mut data_len = [0u8; 8];
+	data_len.copy_from_slice(&rec[12..20]);
+	let opts = Options {
+		sector_size: u32_at(rec, 8) as usize,
+		data_sectors: u16::from_le_bytes([rec[20], rec[21]]) as usize,
+		recovery_sectors: u16::from_le_bytes([rec[22], rec[23]]) as usize,
+	};
+	opts.check().map_err(|_| RecoveryError::CorruptRecord)?;
+	let layout = Layout::new(opts, u64::from_le_bytes(data_len) as usize);
+	if layout.record_len() != record_len || layout.data_len != start {
+		return Err(RecoveryError::CorruptRecord);
+	}
+
+	let count = layout.sectors + layout.recovery_count();
+	let table = &rec[HEADER_LEN..HEADER_LEN + 4 * count];
+	if crc32(table) != u32_at(rec, 24) {
+		return Err(RecoveryError::CorruptRecord);
+	}
+	let crcs = (0..count).map(|i| u32_at(table, 4 * i)).collect();
+	Ok(Parsed { layout, start, crcs })
+}
+
+/// Checks a protected file (data followed by its record), and repairs
+/// the data sectors in place if repair is set.
+fn check(file: &mut [u8], repair: bool) -> Result<Report, RecoveryError> {
+	let Parsed { layout, start, crcs } = parse(file)?;
+	let opts = layout.opts;
+	let (k, m, size) = (opts.data_sectors, opts.recovery_sectors, opts.sector_size);
+	let recovery_start = start + HEADER_LEN + 4 * crcs.len();
+
+	let damaged: Vec<usize> = (0..layout.sectors)
+		.filter(|&s| crc32(&layout.read(file, s)) != crcs[s])
+		.collect();
+	let recovery_sector = |file: &[u8], r: usize| -> Option<Vec<u8>> {
+		let sector = &file[recovery_start + r * size..recovery_start + (r + 1) * size];
+		Some(sector.to_vec()).filter(|s| crc32(s) == crcs[layout.sectors + r])
+	};
+	let mut report = Report { damaged: damaged.clone(), unrecoverable: Vec::new(), damaged_recovery: 0 };
+	report.damaged_recovery = (0..layout.recovery_count())
+		.filter(|&r| recovery_sector(file, r).is_none())
+		.count();
+
+	let code = ErasureCode::new(k, m).map_err(|_| RecoveryError::CorruptRecord)?;
+	let mut groups: Vec<usize> = damaged.iter().map(|&s| s % layout.groups).collect();
+	groups.sort_unstable();
+	groups.dedup();
+	for g in groups {
+		let mut shards: Vec<Option<Vec<u8>>> = (0..k).map(|j| match layout.sector(g, j) {
+			None => Some(vec![0; size]),
+			Some(s) if damaged.binary_search(&s).is_ok() => None,
+			Some(s) => Some(layout.read(file, s)),
+		}).collect();
+		shards.extend((0..m).map(|j| recovery_sector(file, g * m + j)));
+
+		let members = (0..k).filter_map(|j| layout.sector(g, j));
+		if shards.iter().filter(|s| s.is_some()).count() < k {
+			report.unrecoverable.extend(members.filter(|s| damaged.binary_search(s).is_ok()));
+			continue;
+		}
+		if !repair {
+			continue;
+		}
+		code.reconstruct(&mut shards).expect("Enough shards are present");
+		for (j, s) in (0..k).filter_map(|j| layout.sector(g, j).map(|s| (j, s))) {
+			if damaged.binary_search(&s).is_err() {
+				continue;
+			}
+			let shard = shards[j].as_ref().expect("Reconstructed");
+			let begin = s * size;
+			let end = (begin + size).min(layout.data_len);
+			file[begin..end].copy_from_slice(&shard[..end - begin]);
+		}
+	}
+	report.unrecoverable.sort_unstable();
+	Ok(report)
+}
+
+/// Checks a protected file without modifying it
+pub fn verify(file: &[u8]) -> Result<Report, RecoveryError> {
+	let mut copy = file.to_vec();
+	check(&mut copy, false)
+}
+
+/// Repairs the damaged data sectors of a protected file in place. The
+/// report lists what was damaged and what could not be repaired.
+pub fn repair(file: &mut [u8]) -> Result<Report, RecoveryError> {
+	check(file, true)
+}
+
+/// Returns the data part of a protected file
+pub fn data(file: &[u8]) -> Result<&[u8], RecoveryError> {
+	let parsed = parse(file)?;
+	Ok(&file[..parsed.start])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTS: Options = Options { sector_size: 64, data_sectors: 10, recovery_sectors: 2 };
+
+    fn protected(len: usize) -> (Vec<u8>, Vec<u8>) {
+        let data: Vec<u8> = (0..len).map(|i| (i * 29 + i / 7) as u8).collect();
+        let mut file = data.clone();
+        append(&mut file, OPTS).unwrap();
+        (data, file)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_intact() {
+        let (data, file) = protected(3000);
+        let report = verify(&file).unwrap();
+        assert!(report.damaged.is_empty() && report.is_ok());
+        assert_eq!(super::data(&file).unwrap(), &data[..]);
+    }
+
+    #[test]
+    fn test_repair_burst() {
+        let (data, mut file) = protected(3000);
+        // 47 sectors in 5 groups: a run of 10 sectors hits each group twice
+        for b in &mut file[640..1280] {
+            *b = 0;
+        }
+        let report = verify(&file).unwrap();
+        assert_eq!(report.damaged, (10..20).collect::<Vec<_>>());
+        assert!(report.is_ok());
+
+        let report = repair(&mut file).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(&file[..3000], &data[..]);
+        assert!(verify(&file).unwrap().damaged.is_empty());
+    }
+
+    #[test]
+    fn test_unrecoverable_and_partial_sector() {
+        let (data, mut file) = protected(3000);
+        // Three sectors of group 2 plus the partial last sector (group 1)
+        for s in [2, 7, 12] {
+            file[s * 64 + 3] ^= 1;
+        }
+        file[2999] ^= 0x80;
+        let report = repair(&mut file).unwrap();
+        assert_eq!(report.unrecoverable, vec![2, 7, 12]);
+        assert_eq!(report.damaged, vec![2, 7, 12, 46]);
+        assert_eq!(file[2999], data[2999]);
+    }
+
+    #[test]
+    fn test_damaged_record() {
+        let (_, mut file) = protected(500);
+        let len = file.len();
+        assert_eq!(verify(&file[..len - 1]).err(), Some(RecoveryError::NotFound));
+        file[500 + 9] ^= 1;
+        assert_eq!(verify(&file).err(), Some(RecoveryError::CorruptRecord));
+    }
+}
@@ -0,0 +1,251 @@
+//! Random linear network coding.
+//!
+//! A generation of n equally long payloads is sent as coded packets, each
+//! carrying a coefficient vector c and the payload c_0 P_0 + ... +
+//! c_(n-1) P_(n-1). A node that has collected some coded packets can
+//! recode them into fresh random combinations without decoding first,
+//! which is what makes the scheme work in meshes. A receiver decodes once
+//! its coefficient vectors have rank n, by solving the linear system with
+//! Matrix::solve_many.
+//!
+//! The coefficients travel with every packet; framing them is left to the
+//! caller. Random coefficients come from an rng filling a buffer with
+//! uniform bytes, as in shamir.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::bytes;
+use crate::matrix::Matrix;
+
+/// Errors produced by network coding
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum RlncError {
+	/// An empty generation, or payloads of different lengths
+	InvalidGeneration,
+	/// A coefficient vector or payload does not fit the generation
+	DimensionMismatch,
+	/// Fewer independent packets than the generation size
+	RankDeficient,
+}
+
+impl fmt::Display for RlncError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			RlncError::InvalidGeneration => "invalid generation",
+			RlncError::DimensionMismatch => "dimension mismatch",
+			RlncError::RankDeficient => "not enough independent packets",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for RlncError {}
+
+/// A linear combination of the payloads of a generation
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct CodedPacket {
+	pub coefficients: Vec<Gf256>,
+	pub payload: Vec<u8>,
+}
+
+/// n uniformly random coefficients
+pub fn random_coefficients<R: FnMut(&mut [u8])>(n: usize, mut rng: R) -> Vec<Gf256> {
+	let mut buf = vec![0u8; n];
+	rng(&mut buf);
+	buf.into_iter().map(Gf256::from_byte).collect()
+}
+
+/// The sum of coefficients[i] times payloads[i]
+pub fn combine<P: AsRef<[u8]>>(coefficients: &[Gf256], payloads: &[P]) -> Result<Vec<u8>, RlncError> {
+	if coefficients.len() != payloads.len() {
+		return Err(RlncError::DimensionMismatch);
+	}
+	let len = payloads.first().map_or(0, |p| p.as_ref().len());
+	if payloads.iter().any(|p| p.as_ref().len() != len) {
+		return Err(RlncError::InvalidGeneration);
+	}
+	let mut out = vec![0u8; len];
+	for (&c, p) in coefficients.iter().zip(payloads) {
+		bytes::mul_add(&mut out, p.as_ref(), c.to_byte());
+	}
+	Ok(out)
+}
+
+/// The source side of a generation
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Encoder {
+	payloads: Vec<Vec<u8>>,
+}
+
+impl Encoder {
+	pub fn new(payloads: Vec<Vec<u8>>) -> Result<Encoder, RlncError> {
+		if payloads.is_empty() || payloads.iter().any(|p| p.len() != payloads[0].len()) {
+			return Err(RlncError::InvalidGeneration);
+		}
+		Ok(Encoder { payloads })
+	}
+
+	pub fn generation_size(&self) -> usize {
+		self.payloads.len()
+	}
+
+	pub fn payload_len(&self) -> usize {
+		self.payloads[0].len()
+	}
+
+	/// The packet with the given coefficients
+	pub fn encode_with(&self, coefficients: &[Gf256]) -> Result<CodedPacket, RlncError> {
+		let payload = combine(coefficients, &self.payloads)?;
+		Ok(CodedPacket { coefficients: coefficients.to_vec(), payload })
+	}
+
+	/// A packet with random coefficients
+	pub fn encode<R: FnMut(&mut [u8])>(&self, rng: R) -> CodedPacket {
+		let c = random_coefficients(self.payloads.len(), rng);
+		self.encode_with(&c).expect("Coefficients match the generation")
+	}
+}
+
+/// Collects coded packets of one generation, for decoding or recoding
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Decoder {
+	n: usize,
+	len: usize,
+	/// Only innovative packets are kept, so there are at most n
+	packets: Vec<CodedPacket>,
+}
+
+impl Decoder {
+	/// A decoder for n payloads of len bytes each
+	pub fn new(n: usize, len: usize) -> Result<Decoder, RlncError> {
+		if n == 0 {
+			return Err(RlncError::InvalidGeneration);
+		}
+		Ok(Decoder { n, len, packets: Vec::new() })
+	}
+
+	pub fn generation_size(&self) -> usize {
+		self.n
+	}
+
+	/// The rank of the coefficient matrix, the number of packets kept
+	pub fn rank(&self) -> usize {
+		self.packets.len()
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.rank() == self.n
+	}
+
+	/// The coefficient vectors of the packets kept, one per row
+	pub fn coefficient_matrix(&self) -> Matrix {
+		let rows: Vec<&[Gf256]> = self.packets.iter().map(|p| &p.coefficients[..]).collect();
+		Matrix::from_rows(&rows).unwrap_or_else(|_| Matrix::zero(0, self.n))
+	}
+
+	/// Adds a packet and returns whether it raised the rank. Packets which
+	/// do not are dropped.
+	pub fn add(&mut self, packet: CodedPacket) -> Result<bool, RlncError> {
+		if packet.coefficients.len() != self.n || packet.payload.len() != self.len {
+			return Err(RlncError::DimensionMismatch);
+		}
+		if self.is_complete() {
+			return Ok(false);
+		}
+		self.packets.push(packet);
+		if self.coefficient_matrix().rank() < self.packets.len() {
+			self.packets.pop();
+			return Ok(false);
+		}
+		Ok(true)
+	}
+
+	/// A random combination of the packets kept so far, with its
+	/// coefficients over the original payloads
+	pub fn recode<R: FnMut(&mut [u8])>(&self, rng: R) -> CodedPacket {
+		let mix = random_coefficients(self.packets.len(), rng);
+		let mut out = CodedPacket { coefficients: vec![Gf256::zero(); self.n], payload: vec![0u8; self.len] };
+		for (&m, p) in mix.iter().zip(&self.packets) {
+			for (a, &c) in out.coefficients.iter_mut().zip(&p.coefficients) {
+				*a += m * c;
+			}
+			bytes::mul_add(&mut out.payload, &p.payload, m.to_byte());
+		}
+		out
+	}
+
+	/// The original payloads, once the rank reaches the generation size
+	pub fn decode(&self) -> Result<Vec<Vec<u8>>, RlncError> {
+		if !self.is_complete() {
+			return Err(RlncError::RankDeficient);
+		}
+		let data: Vec<u8> = self.packets.iter().flat_map(|p| p.payload.iter().copied()).collect();
+		let b = Matrix::from_bytes(self.n, self.len, &data).map_err(|_| RlncError::DimensionMismatch)?;
+		let x = self.coefficient_matrix().solve_many(&b).map_err(|_| RlncError::RankDeficient)?;
+		Ok((0..self.n).map(|i| x.row(i).iter().map(|a| a.to_byte()).collect()).collect())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+
+    fn generation() -> Vec<Vec<u8>> {
+        (0..5).map(|i| (0..32).map(|j| (i * 51 + j * 3) as u8).collect()).collect()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let encoder = Encoder::new(generation()).unwrap();
+        let mut decoder = Decoder::new(5, 32).unwrap();
+        let mut rng = test_rng(3);
+        while !decoder.is_complete() {
+            decoder.add(encoder.encode(&mut rng)).unwrap();
+        }
+        assert_eq!(decoder.decode().unwrap(), generation());
+        assert_eq!(decoder.coefficient_matrix().rank(), 5);
+        assert_eq!(decoder.add(encoder.encode(&mut rng)), Ok(false));
+    }
+
+    #[test]
+    fn test_dependent_packets_and_recoding() {
+        let encoder = Encoder::new(generation()).unwrap();
+        let one = Gf256::one();
+        let zero = Gf256::zero();
+        let a = encoder.encode_with(&[one, zero, zero, zero, zero]).unwrap();
+        let b = encoder.encode_with(&[zero, one, zero, zero, zero]).unwrap();
+        let sum = encoder.encode_with(&[one, one, zero, zero, zero]).unwrap();
+        let mut relay = Decoder::new(5, 32).unwrap();
+        assert_eq!(relay.add(a), Ok(true));
+        assert_eq!(relay.add(b), Ok(true));
+        assert_eq!(relay.add(sum), Ok(false));
+        assert_eq!(relay.decode(), Err(RlncError::RankDeficient));
+        let recoded = relay.recode(test_rng(9));
+        assert_eq!(recoded.coefficients[2..], [zero; 3]);
+        assert_eq!(encoder.encode_with(&recoded.coefficients).unwrap(), recoded);
+        // A sink fed by the relay and the source
+        let mut sink = Decoder::new(5, 32).unwrap();
+        let mut rng = test_rng(1);
+        sink.add(relay.recode(&mut rng)).unwrap();
+        while !sink.is_complete() {
+            sink.add(encoder.encode(&mut rng)).unwrap();
+        }
+        assert_eq!(sink.decode().unwrap(), generation());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Encoder::new(vec![]), Err(RlncError::InvalidGeneration));
+        assert_eq!(Encoder::new(vec![vec![1], vec![1, 2]]), Err(RlncError::InvalidGeneration));
+        assert_eq!(combine(&[Gf256::one()], &[[1u8], [2]]), Err(RlncError::DimensionMismatch));
+        let mut decoder = Decoder::new(2, 4).unwrap();
+        let packet = CodedPacket { coefficients: vec![Gf256::one(); 2], payload: vec![0; 3] };
+        assert_eq!(decoder.add(packet), Err(RlncError::DimensionMismatch));
+        assert_eq!(Decoder::new(0, 4), Err(RlncError::InvalidGeneration));
+    }
+}
@@ -0,0 +1,1079 @@
+//! Reed-Solomon codes over GF(256).
+//!
+//! Codewords are stored with the highest degree coefficient first: the
+//! K message symbols come first, followed by N - K parity symbols. The
+//! generator polynomial is (x - a^0)(x - a^1)...(x - a^(N-K-1)) with
+//! a = x, which is the convention used by QR codes and DVB.
+//!
+//! RsCodec and Scratch do not allocate, so a receiver can decode frames
+//! on targets without a heap. Shortened codes (N < 255) are supported
+//! directly by choosing a smaller N.
+//!
+//! An RsCodec is immutable after construction, so a single instance can
+//! be shared between threads (e.g. in an Arc); only the Scratch is per
+//! thread.
+
+use core::fmt;
+use core::error;
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::Gf256;
+use crate::trace::{ Event, NoTrace, Sink };
+
+pub mod ccsds;
+pub mod presets;
+#[cfg(feature = "alloc")]
+pub mod encoder;
+#[cfg(feature = "alloc")]
+pub mod locator;
+#[cfg(feature = "alloc")]
+pub mod product;
+
+#[cfg(feature = "std")]
+pub mod cauchy;
+#[cfg(feature = "std")]
+pub mod chunked;
+#[cfg(feature = "std")]
+pub mod erasure;
+#[cfg(feature = "std")]
+pub mod fft;
+#[cfg(feature = "std")]
+pub mod header;
+#[cfg(feature = "std")]
+pub mod offload;
+#[cfg(feature = "std")]
+pub mod repair;
+#[cfg(feature = "std")]
+pub mod shard;
+#[cfg(feature = "std")]
+pub mod stream;
+
+/// Errors produced while decoding
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum RsError {
+	/// The codeword contains more errors than the code can correct
+	TooManyErrors,
+	/// The codeword does not have the length of the code
+	InvalidLength,
+	/// An erasure position is out of range or given twice
+	InvalidPosition(usize),
+}
+
+impl fmt::Display for RsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			RsError::TooManyErrors => f.write_str("too many errors to correct"),
+			RsError::InvalidLength => f.write_str("invalid codeword length"),
+			RsError::InvalidPosition(p) => write!(f, "invalid erasure position {}", p),
+		}
+	}
+}
+
+impl error::Error for RsError {}
+
+/// Errors produced while restoring a saved codec state
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum StateError {
+	/// The output buffer is too small, or the input is truncated
+	BufferTooSmall,
+	/// The bytes do not start with the state magic
+	BadMagic,
+	/// The state was written by an unsupported format version
+	UnsupportedVersion,
+	/// The state belongs to a code with different parameters
+	ParameterMismatch,
+	/// The state fails its checksum or does not describe a valid code
+	Corrupt,
+}
+
+impl fmt::Display for StateError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			StateError::BufferTooSmall => "buffer too small",
+			StateError::BadMagic => "not a saved codec state",
+			StateError::UnsupportedVersion => "unsupported codec state version",
+			StateError::ParameterMismatch => "codec state has different parameters",
+			StateError::Corrupt => "codec state is corrupt",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for StateError {}
+
+const STATE_MAGIC: [u8; 4] = *b"GFRS";
+const STATE_VERSION: u8 = 2;
+const STATE_HEADER_LEN: usize = 8;
+
+/// Writes the state of the RS(n, k) code with generator gen to out,
+/// which has to hold STATE_HEADER_LEN + n - k + 5 bytes
+fn write_state(n: usize, k: usize, gen: &[Gf256], out: &mut [u8]) -> usize {
+	let end = STATE_HEADER_LEN + gen.len();
+	out[..4].copy_from_slice(&STATE_MAGIC);
+	out[4] = STATE_VERSION;
+	out[5] = n as u8;
+	out[6] = k as u8;
+	out[7] = 0;
+	for (o, g) in out[STATE_HEADER_LEN..end].iter_mut().zip(gen) {
+		*o = g.to_byte();
+	}
+	let crc = crate::crc::CRC32.checksum(&out[..end]) as u32;
+	out[end..end + 4].copy_from_slice(&crc.to_be_bytes());
+	end + 4
+}
+
+/// Checks a saved state and returns n, k and the generator, highest
+/// degree first. The generator has to match the one new builds.
+pub(crate) fn read_state(bytes: &[u8]) -> Result<(usize, usize, &[u8]), StateError> {
+	if bytes.len() < STATE_HEADER_LEN {
+		return Err(StateError::BufferTooSmall);
+	}
+	if bytes[..4] != STATE_MAGIC {
+		return Err(StateError::BadMagic);
+	}
+	if bytes[4] != STATE_VERSION {
+		return Err(StateError::UnsupportedVersion);
+	}
+	let (n, k) = (bytes[5] as usize, bytes[6] as usize);
+	if k == 0 || k >= n || bytes[7] != 0 {
+		return Err(StateError::Corrupt);
+	}
+	let end = STATE_HEADER_LEN + n - k + 1;
+	if bytes.len() < end + 4 {
+		return Err(StateError::BufferTooSmall);
+	}
+	let crc = crate::crc::CRC32.checksum(&bytes[..end]) as u32;
+	if crc.to_be_bytes() != bytes[end..end + 4] {
+		return Err(StateError::Corrupt);
+	}
+	// A CRC-valid state can still carry the generator of another code
+	let mut gen = [Gf256::zero(); 256];
+	generator_into(n - k, 0, &mut gen);
+	let saved = &bytes[STATE_HEADER_LEN..end];
+	if saved.iter().zip(&gen).any(|(&b, g)| b != g.to_byte()) {
+		return Err(StateError::Corrupt);
+	}
+	Ok((n, k, saved))
+}
+
+/// Calculate a ^ e for the primitive element a = x and any exponent
+#[inline]
+fn alpha_pow(e: usize) -> Gf256 {
+	Gf256::xexp((e % 255) as u8)
+}
+
+/// Computes the generator polynomial with nsym consecutive roots starting
+/// at a^fcr. out receives nsym + 1 coefficients, highest degree first.
+pub(crate) fn generator_into(nsym: usize, fcr: usize, out: &mut [Gf256]) {
+	out[0] = Gf256::one();
+	for c in out[1..=nsym].iter_mut() {
+		*c = Gf256::zero();
+	}
+	for i in 0..nsym {
+		// Multiply by (x - a^(fcr + i))
+		let root = alpha_pow(fcr + i);
+		for j in (1..=i + 1).rev() {
+			out[j] += out[j - 1] * root;
+		}
+	}
+}
+
+/// Returns the generator polynomial of the RS(n, k) code whose n - k
+/// consecutive roots are alpha^fcr, ..., alpha^(fcr + n - k - 1). The
+/// n - k + 1 coefficients are listed highest degree first; the leading
+/// one is always 1.
+///
+/// alpha has to have order at least n; RsCodec uses fcr = 0 and alpha = x.
+#[cfg(feature = "alloc")]
+pub fn generator_poly(n: usize, k: usize, fcr: usize, alpha: Gf256)
+	-> Result<Vec<Gf256>, ConfigError> {
+	if k == 0 {
+		return Err(ConfigError::ZeroDataShards);
+	}
+	if n <= k {
+		return Err(ConfigError::ZeroParityShards);
+	}
+	let max = alpha.order().map_or(0, usize::from);
+	if n > max {
+		return Err(ConfigError::TooManyShards { total: n, max });
+	}
+	let nsym = n - k;
+	let mut gen = alloc::vec![Gf256::zero(); nsym + 1];
+	gen[0] = Gf256::one();
+	for i in 0..nsym {
+		let root = alpha.exp(((fcr + i) % 255) as u8);
+		for j in (1..=i + 1).rev() {
+			gen[j] = gen[j] + gen[j - 1] * root;
+		}
+	}
+	Ok(gen)
+}
+
+/// Computes the parity symbols of msg for the given generator polynomial
+/// (highest degree first, monic) by polynomial division.
+pub(crate) fn encode_parity(gen: &[Gf256], msg: &[u8], parity: &mut [u8]) {
+	let nsym = gen.len() - 1;
+	for p in parity.iter_mut() {
+		*p = 0;
+	}
+	for &m in msg {
+		let feedback = Gf256::from_byte(m ^ parity[0]);
+		for j in 0..nsym - 1 {
+			parity[j] = parity[j + 1] ^ (feedback * gen[j + 1]).to_byte();
+		}
+		parity[nsym - 1] = (feedback * gen[nsym]).to_byte();
+	}
+}
+
+/// Evaluates the codeword at a^(fcr + i) for every i below syn.len().
+/// Returns true if all syndromes are zero.
+pub(crate) fn syndromes_into(codeword: &[u8], fcr: usize, syn: &mut [Gf256]) -> bool {
+	let mut clean = true;
+	for (i, s) in syn.iter_mut().enumerate() {
+		let x = alpha_pow(fcr + i);
+		*s = codeword.iter().fold(Gf256::zero(), |acc, &c| acc * x + Gf256::from_byte(c));
+		clean &= s.poly == 0;
+	}
+	clean
+}
+
+/// Working memory for decode_with. Every buffer has to hold at least
+/// nsym + 1 elements, positions at least nsym / 2.
+pub(crate) struct Buffers<'a> {
+	pub syn: &'a mut [Gf256],
+	pub lambda: &'a mut [Gf256],
+	pub prev: &'a mut [Gf256],
+	pub tmp: &'a mut [Gf256],
+	pub omega: &'a mut [Gf256],
+	pub positions: &'a mut [usize],
+}
+
+/// Berlekamp-Massey: finds the error locator (lowest degree first) for
+/// the syndromes and returns its degree.
+fn berlekamp_massey(syn: &[Gf256], lambda: &mut [Gf256], prev: &mut [Gf256],
+	tmp: &mut [Gf256], sink: &mut dyn Sink) -> usize {
+	let len = syn.len() + 1;
+	for i in 0..len {
+		lambda[i] = Gf256::zero();
+		prev[i] = Gf256::zero();
+	}
+	lambda[0] = Gf256::one();
+	prev[0] = Gf256::one();
+	let mut l = 0;
+	let mut m = 1;
+	let mut b = Gf256::one();
+
+	for n in 0..syn.len() {
+		let mut d = syn[n];
+		for i in 1..=l {
+			d += lambda[i] * syn[n - i];
+		}
+		sink.event(&Event::Discrepancy { step: n, value: d });
+		if d.poly == 0 {
+			m += 1;
+			continue;
+		}
+		let coef = d / b;
+		let grow = 2 * l <= n;
+		if grow {
+			tmp[..len].copy_from_slice(&lambda[..len]);
+		}
+		for i in m..len {
+			lambda[i] += coef * prev[i - m];
+		}
+		if grow {
+			l = n + 1 - l;
+			prev[..len].copy_from_slice(&tmp[..len]);
+			b = d;
+			m = 1;
+		} else {
+			m += 1;
+		}
+		sink.event(&Event::Locator { step: n, lambda: &lambda[..l + 1] });
+	}
+	l
+}
+
+/// Degree of p, None for zero
+fn degree(p: &[Gf256]) -> Option<usize> {
+	p.iter().rposition(|c| c.poly != 0)
+}
+
+/// Sugiyama's algorithm: runs the extended Euclidean algorithm on x^nsym
+/// and the syndrome polynomial until the remainder has degree below
+/// nsym / 2. The cofactor of the syndromes is then a multiple of the
+/// error locator, which is left in lambda scaled to lambda[0] = 1.
+/// Returns its degree like berlekamp_massey; more than nsym / 2 means
+/// failure. omega is used as scratch.
+fn euclid(syn: &[Gf256], lambda: &mut [Gf256], prev: &mut [Gf256],
+	tmp: &mut [Gf256], omega: &mut [Gf256], sink: &mut dyn Sink) -> usize {
+	let nsym = syn.len();
+	let len = nsym + 1;
+	// r0 = x^nsym and r1 = S, with t_i S = r_i mod x^nsym throughout
+	let (r0, r1) = (&mut prev[..len], &mut tmp[..len]);
+	let (t0, t1) = (&mut omega[..len], &mut lambda[..len]);
+	for i in 0..len {
+		r0[i] = Gf256::zero();
+		r1[i] = if i < nsym { syn[i] } else { Gf256::zero() };
+		t0[i] = Gf256::zero();
+		t1[i] = Gf256::zero();
+	}
+	r0[nsym] = Gf256::one();
+	t1[0] = Gf256::one();
+	let mut step = 0;
+	while let Some(d1) = degree(r1).filter(|&d1| 2 * d1 >= nsym) {
+		// One division r0 = q r1 + r, a leading term of q at a time
+		while let Some(d0) = degree(r0).filter(|&d0| d0 >= d1) {
+			let c = r0[d0] / r1[d1];
+			let k = d0 - d1;
+			for i in 0..=d1 {
+				r0[i + k] += c * r1[i];
+			}
+			for i in 0..len - k {
+				t0[i + k] += c * t1[i];
+			}
+		}
+		r0.swap_with_slice(r1);
+		t0.swap_with_slice(t1);
+		let l = degree(t1).unwrap_or(0);
+		sink.event(&Event::Locator { step, lambda: &t1[..l + 1] });
+		step += 1;
+	}
+	let l = degree(t1).unwrap_or(0);
+	let Some(scale) = t1[0].inv() else {
+		return nsym;
+	};
+	for c in t1[..l + 1].iter_mut() {
+		*c *= scale;
+	}
+	l
+}
+
+/// The algorithm solving the key equation for the error locator
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub enum KeySolver {
+	#[default]
+	BerlekampMassey,
+	/// Sugiyama's extended Euclidean algorithm
+	Euclid,
+}
+
+/// Corrects up to nsym / 2 symbol errors in codeword in place and returns
+/// the number of corrected symbols.
+pub(crate) fn decode_with(codeword: &mut [u8], nsym: usize, fcr: usize,
+	bufs: Buffers) -> Result<usize, RsError> {
+	decode_using(codeword, nsym, fcr, bufs, KeySolver::BerlekampMassey)
+}
+
+/// decode_with, solving the key equation with solver
+pub(crate) fn decode_using(codeword: &mut [u8], nsym: usize, fcr: usize,
+	bufs: Buffers, solver: KeySolver) -> Result<usize, RsError> {
+	decode_traced_with(codeword, nsym, fcr, bufs, solver, &mut NoTrace)
+}
+
+/// decode_using, reporting every step to sink
+fn decode_traced_with(codeword: &mut [u8], nsym: usize, fcr: usize,
+	bufs: Buffers, solver: KeySolver, sink: &mut dyn Sink) -> Result<usize, RsError> {
+	let res = decode_steps(codeword, nsym, fcr, bufs, solver, sink);
+	match res {
+		Ok(count) => sink.event(&Event::Corrected(count)),
+		Err(_) => sink.event(&Event::Failed),
+	}
+	res
+}
+
+fn decode_steps(codeword: &mut [u8], nsym: usize, fcr: usize,
+	bufs: Buffers, solver: KeySolver, sink: &mut dyn Sink) -> Result<usize, RsError> {
+
+	let n = codeword.len();
+	let syn = &mut bufs.syn[..nsym];
+	let clean = syndromes_into(codeword, fcr, syn);
+	sink.event(&Event::Syndromes(syn));
+	if clean {
+		return Ok(0);
+	}
+
+	let lambda = &mut bufs.lambda[..nsym + 1];
+	let l = match solver {
+		KeySolver::BerlekampMassey => berlekamp_massey(syn, lambda, &mut bufs.prev[..nsym + 1],
+			&mut bufs.tmp[..nsym + 1], sink),
+		KeySolver::Euclid => euclid(syn, lambda, &mut bufs.prev[..nsym + 1],
+			&mut bufs.tmp[..nsym + 1], &mut bufs.omega[..nsym + 1], sink),
+	};
+	if 2 * l > nsym {
+		return Err(RsError::TooManyErrors);
+	}
+
+	// Chien search: an error at index p has locator a^(n - 1 - p), which
+	// is the inverse of a root of lambda.
+	let mut count = 0;
+	for p in 0..n {
+		let xinv = alpha_pow(255 - (n - 1 - p) % 255);
+		let v = lambda[..l + 1].iter().rev().fold(Gf256::zero(), |acc, &c| acc * xinv + c);
+		if v.poly == 0 {
+			if count == l {
+				return Err(RsError::TooManyErrors);
+			}
+			sink.event(&Event::Root { position: p });
+			bufs.positions[count] = p;
+			count += 1;
+		}
+	}
+	if count != l {
+		return Err(RsError::TooManyErrors);
+	}
+
+	// Error evaluator omega = syn * lambda mod x^nsym
+	let omega = &mut bufs.omega[..nsym];
+	for i in 0..nsym {
+		let mut acc = Gf256::zero();
+		for j in 0..=i.min(l) {
+			acc += lambda[j] * syn[i - j];
+		}
+		omega[i] = acc;
+	}
+	sink.event(&Event::Evaluator(omega));
+
+	// Forney's formula
+	for &p in bufs.positions[..count].iter() {
+		let e = (n - 1 - p) % 255;
+		let xinv = alpha_pow(255 - e);
+		let num = omega.iter().rev().fold(Gf256::zero(), |acc, &c| acc * xinv + c);
+		let mut den = Gf256::zero();
+		let mut i = 1;
+		while i <= l {
+			den += lambda[i] * xinv.exp(((i - 1) % 255) as u8);
+			i += 2;
+		}
+		if den.poly == 0 {
+			return Err(RsError::TooManyErrors);
+		}
+		// magnitude = X^(1 - fcr) * omega(X^-1) / lambda'(X^-1)
+		let scale = alpha_pow(e * ((1 + 255 - fcr % 255) % 255));
+		let value = scale * num / den;
+		sink.event(&Event::Magnitude { position: p, value });
+		codeword[p] ^= value.to_byte();
+	}
+
+	if !syndromes_into(codeword, fcr, syn) {
+		return Err(RsError::TooManyErrors);
+	}
+	Ok(count)
+}
+
+/// Working memory for RsCodec::decode
+pub struct Scratch<const N: usize, const K: usize> {
+	syn: [Gf256; N],
+	lambda: [Gf256; N],
+	prev: [Gf256; N],
+	tmp: [Gf256; N],
+	omega: [Gf256; N],
+	positions: [usize; N],
+}
+
+impl<const N: usize, const K: usize> Scratch<N, K> {
+	pub const fn new() -> Scratch<N, K> {
+		let z = Gf256::zero();
+		Scratch {
+			syn: [z; N],
+			lambda: [z; N],
+			prev: [z; N],
+			tmp: [z; N],
+			omega: [z; N],
+			positions: [0; N],
+		}
+	}
+}
+
+impl<const N: usize, const K: usize> Default for Scratch<N, K> {
+	fn default() -> Scratch<N, K> {
+		Scratch::new()
+	}
+}
+
+/// A systematic RS(N, K) code with N - K parity symbols
+pub struct RsCodec<const N: usize, const K: usize> {
+	gen: [Gf256; N]
+}
+
+impl<const N: usize, const K: usize> RsCodec<N, K> {
+	/// Number of parity symbols
+	pub const PARITY: usize = N - K;
+
+	// Evaluated at compile time whenever a codec is constructed, so
+	// invalid parameters are rejected by the compiler.
+	const VALID: () = assert!(K > 0 && K < N && N <= 255, "Invalid code parameters");
+
+	/// Creates the codec. Requires 0 < K < N <= 255, which is checked at
+	/// compile time.
+	pub fn new() -> RsCodec<N, K> {
+		let () = Self::VALID;
+		let mut gen = [Gf256::zero(); N];
+		generator_into(N - K, 0, &mut gen);
+		RsCodec { gen }
+	}
+
+	/// Describes the code as a runtime configuration
+	pub fn config() -> CodecConfig {
+		CodecConfig { data_shards: K, parity_shards: N - K, symbol_bits: 8 }
+	}
+
+	/// Returns the generator polynomial, highest degree first
+	pub fn generator(&self) -> &[Gf256] {
+		&self.gen[..N - K + 1]
+	}
+
+	/// Number of bytes written by save
+	pub const STATE_LEN: usize = STATE_HEADER_LEN + N - K + 5;
+
+	/// Writes the precomputed state of the codec to out and returns the
+	/// number of bytes written (STATE_LEN).
+	///
+	/// The layout is: magic "GFRS", format version, N, K, a reserved
+	/// zero byte, the generator polynomial (highest degree first) and a
+	/// big endian CRC-32 of all that. RsEncoder reads and writes the
+	/// same format.
+	pub fn save(&self, out: &mut [u8]) -> Result<usize, StateError> {
+		if out.len() < Self::STATE_LEN {
+			return Err(StateError::BufferTooSmall);
+		}
+		Ok(write_state(N, K, self.generator(), out))
+	}
+
+	/// Restores a codec saved with save. The CRC is checked and the
+	/// generator compared with a rebuilt one, so a state from another
+	/// build or a damaged one is refused with StateError::Corrupt.
+	pub fn restore(bytes: &[u8]) -> Result<RsCodec<N, K>, StateError> {
+		let () = Self::VALID;
+		let (n, k, saved) = read_state(bytes)?;
+		if n != N || k != K {
+			return Err(StateError::ParameterMismatch);
+		}
+		let mut gen = [Gf256::zero(); N];
+		for (g, &b) in gen.iter_mut().zip(saved) {
+			*g = Gf256::from_byte(b);
+		}
+		Ok(RsCodec { gen })
+	}
+
+	/// Computes the parity of codeword[..K] into codeword[K..]
+	pub fn encode(&self, codeword: &mut [u8; N]) {
+		let (msg, parity) = codeword.split_at_mut(K);
+		encode_parity(self.generator(), msg, parity);
+	}
+
+	/// Checks whether codeword is a valid codeword
+	pub fn is_valid(&self, codeword: &[u8; N], scratch: &mut Scratch<N, K>) -> bool {
+		syndromes_into(codeword, 0, &mut scratch.syn[..N - K])
+	}
+
+	/// Corrects up to (N - K) / 2 symbol errors in place and returns the
+	/// number of corrected symbols. On error the codeword is left in an
+	/// unspecified state.
+	pub fn decode(&self, codeword: &mut [u8; N], scratch: &mut Scratch<N, K>)
+		-> Result<usize, RsError> {
+		let bufs = Buffers {
+			syn: &mut scratch.syn,
+			lambda: &mut scratch.lambda,
+			prev: &mut scratch.prev,
+			tmp: &mut scratch.tmp,
+			omega: &mut scratch.omega,
+			positions: &mut scratch.positions,
+		};
+		decode_with(codeword, N - K, 0, bufs)
+	}
+
+	/// Like decode, explaining every step of the decoder to sink
+	pub fn decode_traced(&self, codeword: &mut [u8; N], scratch: &mut Scratch<N, K>,
+		sink: &mut dyn Sink) -> Result<usize, RsError> {
+		let bufs = Buffers {
+			syn: &mut scratch.syn,
+			lambda: &mut scratch.lambda,
+			prev: &mut scratch.prev,
+			tmp: &mut scratch.tmp,
+			omega: &mut scratch.omega,
+			positions: &mut scratch.positions,
+		};
+		decode_traced_with(codeword, N - K, 0, bufs, KeySolver::BerlekampMassey, sink)
+	}
+
+	/// Like decode, solving the key equation with solver. Both solvers
+	/// correct the same error patterns.
+	pub fn decode_using(&self, codeword: &mut [u8; N], scratch: &mut Scratch<N, K>,
+		solver: KeySolver) -> Result<usize, RsError> {
+		let bufs = Buffers {
+			syn: &mut scratch.syn,
+			lambda: &mut scratch.lambda,
+			prev: &mut scratch.prev,
+			tmp: &mut scratch.tmp,
+			omega: &mut scratch.omega,
+			positions: &mut scratch.positions,
+		};
+		decode_using(codeword, N - K, 0, bufs, solver)
+	}
+
+	/// Like encode, for a codeword laid out according to conv
+	pub fn encode_as(&self, codeword: &mut [u8; N], conv: Convention) {
+		conv.convert(codeword);
+		self.encode(codeword);
+		conv.convert(codeword);
+	}
+
+	/// Like decode, for a codeword laid out according to conv
+	pub fn decode_as(&self, codeword: &mut [u8; N], scratch: &mut Scratch<N, K>, conv: Convention)
+		-> Result<usize, RsError> {
+		conv.convert(codeword);
+		let res = self.decode(codeword, scratch);
+		conv.convert(codeword);
+		res
+	}
+}
+
+impl<const N: usize, const K: usize> Default for RsCodec<N, K> {
+	fn default() -> RsCodec<N, K> {
+		RsCodec::new()
+	}
+}
+
+
+/// How field elements map to the bits of a byte
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub enum BitOrder {
+	/// Bit i holds the coefficient of x^i, as in Gf256::from_byte
+	#[default]
+	Standard,
+	/// Bit i holds the coefficient of x^(7 - i)
+	Reversed,
+}
+
+/// Order of the symbols in a codeword
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub enum SymbolOrder {
+	/// Byte 0 is the highest degree coefficient: message, then parity
+	#[default]
+	HighestFirst,
+	/// Byte 0 is the constant coefficient: parity, then the message
+	/// (itself lowest degree first)
+	LowestFirst,
+}
+
+/// The layout of codewords exchanged with other implementations.
+///
+/// Hardware and legacy libraries disagree on both orders, and mixing them
+/// up just produces words that fail to decode.
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Convention {
+	pub bits: BitOrder,
+	pub symbols: SymbolOrder,
+}
+
+impl Convention {
+	/// The layout used by RsCodec::encode and decode
+	pub const STANDARD: Convention = Convention {
+		bits: BitOrder::Standard,
+		symbols: SymbolOrder::HighestFirst,
+	};
+
+	/// Converts a codeword between this layout and the standard one. The
+	/// conversion is its own inverse.
+	pub fn convert(&self, codeword: &mut [u8]) {
+		if self.bits == BitOrder::Reversed {
+			for b in codeword.iter_mut() {
+				*b = b.reverse_bits();
+			}
+		}
+		if self.symbols == SymbolOrder::LowestFirst {
+			codeword.reverse();
+		}
+	}
+}
+
+/// Errors produced by CodecBuilder::build
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum ConfigError {
+	/// At least one data shard is required
+	ZeroDataShards,
+	/// At least one parity shard is required
+	ZeroParityShards,
+	/// Only the given symbol widths (in bits) are supported
+	UnsupportedSymbolWidth(u32),
+	/// Data and parity shards together exceed the maximal code length
+	TooManyShards { total: usize, max: usize },
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ConfigError::ZeroDataShards => f.write_str("no data shards"),
+			ConfigError::ZeroParityShards => f.write_str("no parity shards"),
+			ConfigError::UnsupportedSymbolWidth(bits) =>
+				write!(f, "unsupported symbol width of {} bits", bits),
+			ConfigError::TooManyShards { total, max } =>
+				write!(f, "{} shards exceed the maximum of {}", total, max),
+		}
+	}
+}
+
+impl error::Error for ConfigError {}
+
+/// Most shards a code over symbol_bits wide symbols can have, one per
+/// field element. CodecBuilder and ErasureCode::new both check this;
+/// RS codewords, which are indexed by powers of alpha, are one shorter.
+pub const fn max_shards(symbol_bits: u32) -> usize {
+	1 << symbol_bits
+}
+
+/// A validated code configuration
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct CodecConfig {
+	data_shards: usize,
+	parity_shards: usize,
+	symbol_bits: u32,
+}
+
+impl CodecConfig {
+	/// Starts building a configuration
+	pub fn builder() -> CodecBuilder<Unset, Unset, Unset> {
+		CodecBuilder::new()
+	}
+
+	pub fn data_shards(&self) -> usize {
+		self.data_shards
+	}
+
+	pub fn parity_shards(&self) -> usize {
+		self.parity_shards
+	}
+
+	pub fn total_shards(&self) -> usize {
+		self.data_shards + self.parity_shards
+	}
+
+	pub fn symbol_bits(&self) -> u32 {
+		self.symbol_bits
+	}
+}
+
+/// Marks a CodecBuilder parameter which has not been given yet
+pub struct Unset;
+/// Marks a CodecBuilder parameter which has been given
+pub struct Set;
+
+/// Builder for CodecConfig.
+///
+/// The type parameters record which of data shards, parity shards and
+/// symbol width have been set. build is only available once all three
+/// are, and each can only be set once, so forgetting or repeating a
+/// parameter is a compile error. Constraints between the values are
+/// checked by build.
+pub struct CodecBuilder<D, P, W> {
+	data_shards: usize,
+	parity_shards: usize,
+	symbol_bits: u32,
+	marker: PhantomData<(D, P, W)>,
+}
+
+impl CodecBuilder<Unset, Unset, Unset> {
+	pub fn new() -> CodecBuilder<Unset, Unset, Unset> {
+		CodecBuilder { data_shards: 0, parity_shards: 0, symbol_bits: 0, marker: PhantomData }
+	}
+}
+
+impl Default for CodecBuilder<Unset, Unset, Unset> {
+	fn default() -> CodecBuilder<Unset, Unset, Unset> {
+		CodecBuilder::new()
+	}
+}
+
+impl<D, P, W> CodecBuilder<D, P, W> {
+	fn cast<D2, P2, W2>(self) -> CodecBuilder<D2, P2, W2> {
+		CodecBuilder {
+			data_shards: self.data_shards,
+			parity_shards: self.parity_shards,
+			symbol_bits: self.symbol_bits,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl<P, W> CodecBuilder<Unset, P, W> {
+	/// Sets the number of data shards (k)
+	pub fn data_shards(mut self, k: usize) -> CodecBuilder<Set, P, W> {
+		self.data_shards = k;
+		self.cast()
+	}
+}
+
+impl<D, W> CodecBuilder<D, Unset, W> {
+	/// Sets the number of parity shards (m)
+	pub fn parity_shards(mut self, m: usize) -> CodecBuilder<D, Set, W> {
+		self.parity_shards = m;
+		self.cast()
+	}
+}
+
+impl<D, P> CodecBuilder<D, P, Unset> {
+	/// Sets the symbol width in bits
+	pub fn symbol_bits(mut self, bits: u32) -> CodecBuilder<D, P, Set> {
+		self.symbol_bits = bits;
+		self.cast()
+	}
+}
+
+impl CodecBuilder<Set, Set, Set> {
+	/// Validates the parameters
+	pub fn build(self) -> Result<CodecConfig, ConfigError> {
+		if self.symbol_bits != 8 {
+			return Err(ConfigError::UnsupportedSymbolWidth(self.symbol_bits));
+		}
+		if self.data_shards == 0 {
+			return Err(ConfigError::ZeroDataShards);
+		}
+		if self.parity_shards == 0 {
+			return Err(ConfigError::ZeroParityShards);
+		}
+		let max = max_shards(self.symbol_bits);
+		let total = self.data_shards + self.parity_shards;
+		if total > max {
+			return Err(ConfigError::TooManyShards { total, max });
+		}
+		Ok(CodecConfig {
+			data_shards: self.data_shards,
+			parity_shards: self.parity_shards,
+			symbol_bits: self.symbol_bits,
+		})
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corrupt::Corruptor;
+
+    #[test]
+    fn test_qr_vector() {
+        // "HELLO WORLD" as a version 1-M QR code
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        let parity = [196, 35, 39, 119, 235, 215, 231, 226, 93, 23];
+        let codec = RsCodec::<26, 16>::new();
+        let mut cw = [0u8; 26];
+        cw[..16].copy_from_slice(&data);
+        codec.encode(&mut cw);
+        assert_eq!(&cw[16..], &parity[..]);
+    }
+
+    #[test]
+    fn test_generator_poly() {
+        // Exponents of the QR code generators with 7 and 10 EC codewords
+        let exponents = |gen: Vec<Gf256>| -> Vec<u8> { gen.iter().map(|c| c.log().unwrap()).collect() };
+        let x = Gf256::from_byte(2);
+        assert_eq!(exponents(generator_poly(26, 19, 0, x).unwrap()),
+            [0, 87, 229, 146, 149, 238, 102, 21]);
+        assert_eq!(exponents(generator_poly(26, 16, 0, x).unwrap()),
+            [0, 251, 67, 46, 61, 118, 70, 64, 94, 32, 45]);
+        assert_eq!(&generator_poly(30, 20, 0, x).unwrap()[..], RsCodec::<30, 20>::new().generator());
+
+        // The roots are alpha^fcr, alpha^(fcr + 1), ...
+        let alpha = Gf256::from_byte(0x8e);
+        let gen = generator_poly(40, 32, 3, alpha).unwrap();
+        for i in 3..11 {
+            let root = alpha.exp(i);
+            assert_eq!(gen.iter().fold(Gf256::zero(), |acc, &c| acc * root + c), Gf256::zero());
+        }
+
+        // 0x10 = x^4 has order 255 / gcd(4, 255) = 255, but x^5 only 51
+        assert!(generator_poly(255, 223, 0, Gf256::from_byte(0x10)).is_ok());
+        assert_eq!(generator_poly(60, 50, 0, x.exp(5)),
+            Err(ConfigError::TooManyShards { total: 60, max: 51 }));
+        assert_eq!(generator_poly(10, 10, 0, x), Err(ConfigError::ZeroParityShards));
+    }
+
+    #[test]
+    fn test_conventions() {
+        let codec = RsCodec::<26, 16>::new();
+        let mut standard = [0u8; 26];
+        for (i, b) in standard[..16].iter_mut().enumerate() {
+            *b = (i * 29 + 3) as u8;
+        }
+        let mut other = standard;
+        codec.encode(&mut standard);
+
+        let conv = Convention { bits: BitOrder::Reversed, symbols: SymbolOrder::LowestFirst };
+        conv.convert(&mut other);
+        codec.encode_as(&mut other, conv);
+        conv.convert(&mut other);
+        assert_eq!(other, standard);
+
+        // Errors are corrected in the foreign layout, too
+        conv.convert(&mut other);
+        let expected = other;
+        other[0] ^= 0x01;
+        other[20] ^= 0x80;
+        let mut scratch = Scratch::new();
+        assert_eq!(codec.decode_as(&mut other, &mut scratch, conv), Ok(2));
+        assert_eq!(other, expected);
+
+        // A mismatched convention fails instead of silently decoding
+        let mut mixed = standard;
+        Convention { bits: BitOrder::Reversed, symbols: SymbolOrder::HighestFirst }.convert(&mut mixed);
+        assert!(!codec.is_valid(&mixed, &mut scratch));
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let codec = RsCodec::<30, 20>::new();
+        let mut state = [0u8; RsCodec::<30, 20>::STATE_LEN];
+        assert_eq!(codec.save(&mut state), Ok(state.len()));
+
+        let restored = RsCodec::<30, 20>::restore(&state).unwrap();
+        assert_eq!(restored.generator(), codec.generator());
+
+        // Different parameters
+        assert_eq!(RsCodec::<30, 19>::restore(&state).err(),
+            Some(StateError::ParameterMismatch));
+
+        // Damaged generator coefficient
+        state[STATE_HEADER_LEN + 3] ^= 0x10;
+        assert_eq!(RsCodec::<30, 20>::restore(&state).err(), Some(StateError::Corrupt));
+        state[STATE_HEADER_LEN + 3] ^= 0x10;
+        state[state.len() - 1] ^= 1;
+        assert_eq!(RsCodec::<30, 20>::restore(&state).err(), Some(StateError::Corrupt));
+
+        assert_eq!(RsCodec::<30, 20>::restore(&state[..5]).err(),
+            Some(StateError::BufferTooSmall));
+    }
+
+    #[test]
+    fn test_correct_errors() {
+        let codec = RsCodec::<255, 223>::new();
+        let mut scratch = Scratch::new();
+        let mut original = [0u8; 255];
+        for (i, b) in original[..223].iter_mut().enumerate() {
+            *b = (i * 7 + 3) as u8;
+        }
+        codec.encode(&mut original);
+        assert!(codec.is_valid(&original, &mut scratch));
+
+        for errors in 0..=16 {
+            let mut cw = original;
+            Corruptor::new(errors as u64).corrupt_random_bytes(&mut cw, errors);
+            assert_eq!(codec.decode(&mut cw, &mut scratch), Ok(errors));
+            assert_eq!(&cw[..], &original[..]);
+        }
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        fn check<const N: usize, const K: usize>(max_errors: usize) {
+            let codec = RsCodec::<N, K>::new();
+            let mut scratch = Scratch::new();
+            let mut original = [0u8; N];
+            for (i, b) in original[..K].iter_mut().enumerate() {
+                *b = (i * 13 + 1) as u8;
+            }
+            codec.encode(&mut original);
+            for errors in 0..=max_errors {
+                for seed in 0..8 {
+                    let mut bm = original;
+                    Corruptor::new((errors * 8 + seed) as u64).corrupt_random_bytes(&mut bm, errors);
+                    let mut euclid = bm;
+                    let a = codec.decode_using(&mut bm, &mut scratch, KeySolver::BerlekampMassey);
+                    let b = codec.decode_using(&mut euclid, &mut scratch, KeySolver::Euclid);
+                    assert_eq!(a, b, "{} errors, seed {}", errors, seed);
+                    if a.is_ok() {
+                        assert_eq!(bm, euclid);
+                    }
+                    if errors <= (N - K) / 2 {
+                        assert_eq!(b, Ok(errors));
+                        assert_eq!(euclid, original);
+                    }
+                }
+            }
+        }
+        check::<255, 223>(20);
+        check::<31, 20>(8);
+        check::<10, 9>(2);
+    }
+
+    #[test]
+    fn test_shared_codec() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let codec = Arc::new(RsCodec::<40, 30>::new());
+        let handles: Vec<_> = (0..4u8).map(|t| {
+            let codec = codec.clone();
+            thread::spawn(move || {
+                let mut scratch = Scratch::new();
+                let mut original = [t; 40];
+                codec.encode(&mut original);
+                let mut cw = original;
+                cw[t as usize] ^= 0x5a;
+                assert_eq!(codec.decode(&mut cw, &mut scratch), Ok(1));
+                assert_eq!(cw, original);
+            })
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = CodecConfig::builder()
+            .data_shards(10)
+            .symbol_bits(8)
+            .parity_shards(4)
+            .build()
+            .unwrap();
+        assert_eq!(config.total_shards(), 14);
+        assert_eq!(RsCodec::<14, 10>::config(), config);
+
+        let err = CodecBuilder::new().data_shards(200).parity_shards(57).symbol_bits(8).build();
+        assert_eq!(err, Err(ConfigError::TooManyShards { total: 257, max: 256 }));
+        assert!(CodecBuilder::new().data_shards(200).parity_shards(56).symbol_bits(8).build().is_ok());
+        let err = CodecBuilder::new().data_shards(2).parity_shards(0).symbol_bits(8).build();
+        assert_eq!(err, Err(ConfigError::ZeroParityShards));
+        let err = CodecBuilder::new().data_shards(2).parity_shards(1).symbol_bits(16).build();
+        assert_eq!(err, Err(ConfigError::UnsupportedSymbolWidth(16)));
+    }
+
+    #[test]
+    fn test_shortened() {
+        let codec = RsCodec::<20, 12>::new();
+        let mut scratch = Scratch::new();
+        let mut original = [0u8; 20];
+        original[..12].copy_from_slice(b"telemetry!!!");
+        codec.encode(&mut original);
+
+        let mut cw = original;
+        cw[0] ^= 0xff;
+        cw[11] ^= 0x01;
+        cw[13] ^= 0x80;
+        cw[19] ^= 0x42;
+        assert_eq!(codec.decode(&mut cw, &mut scratch), Ok(4));
+        assert_eq!(cw, original);
+    }
+
+    #[test]
+    fn test_too_many_errors() {
+        let codec = RsCodec::<10, 6>::new();
+        let mut scratch = Scratch::new();
+        let mut cw = [0u8; 10];
+        cw[..6].copy_from_slice(b"frame!");
+        codec.encode(&mut cw);
+
+        // 3 errors with a capacity of 2: never silently accept the word
+        let original = cw;
+        cw[0] ^= 1;
+        cw[3] ^= 2;
+        cw[7] ^= 3;
+        match codec.decode(&mut cw, &mut scratch) {
+            Err(e) => assert_eq!(e, RsError::TooManyErrors),
+            Ok(_) => assert_ne!(cw, original),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Cauchy Reed-Solomon coding with XORs only.
+//!
+//! Multiplication by a constant c is linear over GF(2), so it is an 8 x 8
+//! bit matrix whose column x holds the bits of c * 2^x. Expanding every
+//! entry of the Cauchy coding matrix this way turns each parity shard
+//! into XORs of whole regions of the data shards: a shard is cut into
+//! blocks of 8 packets, and packet l of a parity block is the XOR of the
+//! data packets x of every shard j whose bit (l, x) in the expansion of
+//! coefficient j is set. No multiplication happens per byte, which wins
+//! where there is no byte shuffle for the nibble tables of bytes.
+//!
+//! The coding matrix is the one of Jerasure's cauchy_original_coding_matrix
+//! and packets follow its bit matrix layout, so the shards are the ones of
+//! jerasure_bitmatrix_encode with w = 8 and the same packet size. They are
+//! not those of ErasureCode, which codes byte by byte.
+//!
+//! Decoding inverts the surviving rows over GF(256) and expands the
+//! inverse; the expansion of a product is the product of the expansions.
+
+use crate::Gf256;
+use crate::interop::jerasure_cauchy;
+use crate::matrix::Matrix;
+use super::erasure::ErasureError;
+
+/// For every output packet, the input packets XORed into it; packet x of
+/// shard j is number 8 * j + x
+type Schedule = Vec<Vec<usize>>;
+
+fn schedule(m: &Matrix) -> Schedule {
+	let bits = m.binary_expansion();
+	(0..bits.rows())
+		.map(|r| (0..bits.cols()).filter(|&p| bits[(r, p)] == Gf256::one()).collect())
+		.collect()
+}
+
+fn xor_into(dst: &mut [u8], src: &[u8]) {
+	for (d, s) in dst.chunks_exact_mut(8).zip(src.chunks_exact(8)) {
+		let v = u64::from_ne_bytes(d.try_into().unwrap()) ^ u64::from_ne_bytes(s.try_into().unwrap());
+		d.copy_from_slice(&v.to_ne_bytes());
+	}
+}
+
+/// A systematic Cauchy Reed-Solomon code coded by XORs of packets
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct CauchyXor {
+	coding: Matrix,
+	encode: Schedule,
+	packet_size: usize,
+}
+
+impl CauchyXor {
+	/// data must be positive and data + parity at most 256; packet_size
+	/// must be a positive multiple of 8
+	pub fn new(data: usize, parity: usize, packet_size: usize) -> Result<CauchyXor, ErasureError> {
+		if packet_size == 0 || !packet_size.is_multiple_of(8) {
+			return Err(ErasureError::InvalidParameters);
+		}
+		let coding = jerasure_cauchy(data, parity).map_err(|_| ErasureError::InvalidParameters)?;
+		let encode = schedule(&coding);
+		Ok(CauchyXor { coding, encode, packet_size })
+	}
+
+	pub fn data_shards(&self) -> usize {
+		self.coding.cols()
+	}
+
+	pub fn parity_shards(&self) -> usize {
+		self.coding.rows()
+	}
+
+	pub fn total_shards(&self) -> usize {
+		self.data_shards() + self.parity_shards()
+	}
+
+	pub fn packet_size(&self) -> usize {
+		self.packet_size
+	}
+
+	/// Shard sizes have to be multiples of this
+	pub fn block_size(&self) -> usize {
+		8 * self.packet_size
+	}
+
+	/// The parity x data Cauchy matrix over GF(256)
+	pub fn coding_matrix(&self) -> &Matrix {
+		&self.coding
+	}
+
+	/// XORs of one packet per block needed to compute all parity shards
+	pub fn xor_count(&self) -> usize {
+		self.encode.iter().map(|r| r.len().saturating_sub(1)).sum()
+	}
+
+	fn apply(&self, schedule: &Schedule, inputs: &[&[u8]], outputs: &mut [&mut [u8]]) {
+		let ps = self.packet_size;
+		let size = inputs[0].len();
+		for block in (0..size).step_by(self.block_size()) {
+			for (r, row) in schedule.iter().enumerate() {
+				let dst = &mut outputs[r / 8][block + r % 8 * ps..][..ps];
+				dst.fill(0);
+				for &p in row {
+					xor_into(dst, &inputs[p / 8][block + p % 8 * ps..][..ps]);
+				}
+			}
+		}
+	}
+
+	fn check_size(&self, size: usize) -> Result<(), ErasureError> {
+		if !size.is_multiple_of(self.block_size()) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		Ok(())
+	}
+
+	/// Computes the parity shards from the data shards, all of one size
+	/// which is a multiple of block_size
+	pub fn encode(&self, data: &[&[u8]], parity: &mut [&mut [u8]]) -> Result<(), ErasureError> {
+		if data.len() != self.data_shards() || parity.len() != self.parity_shards() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let size = data[0].len();
+		if data.iter().any(|d| d.len() != size) || parity.iter().any(|p| p.len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		self.check_size(size)?;
+		self.apply(&self.encode, data, parity);
+		Ok(())
+	}
+
+	/// Fills in every missing shard, data and parity, from the first
+	/// data_shards present ones
+	pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ErasureError> {
+		let (k, n) = (self.data_shards(), self.total_shards());
+		if shards.len() != n {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let survivors: Vec<usize> = (0..n).filter(|&i| shards[i].is_some()).take(k).collect();
+		if survivors.len() < k {
+			return Err(ErasureError::TooFewShards);
+		}
+		let size = shards[survivors[0]].as_ref().expect("Survivor").len();
+		if shards.iter().flatten().any(|s| s.len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		self.check_size(size)?;
+
+		let missing_data: Vec<usize> = (0..k).filter(|&i| shards[i].is_none()).collect();
+		if !missing_data.is_empty() {
+			let rows: Vec<Vec<Gf256>> = survivors.iter().map(|&i| if i < k {
+				Matrix::identity(k).row(i).to_vec()
+			} else {
+				self.coding.row(i - k).to_vec()
+			}).collect();
+			let inv = Matrix::from_rows(&rows).expect("Rows have k entries").inverse()
+				.expect("Cauchy generators are MDS");
+			let wanted: Vec<Vec<Gf256>> = missing_data.iter().map(|&i| inv.row(i).to_vec()).collect();
+			let plan = schedule(&Matrix::from_rows(&wanted).expect("Rows have k entries"));
+			let mut out = vec![vec![0u8; size]; missing_data.len()];
+			{
+				let inputs: Vec<&[u8]> = survivors.iter().map(|&i| &shards[i].as_ref().expect("Survivor")[..]).collect();
+				let mut refs: Vec<&mut [u8]> = out.iter_mut().map(|o| &mut o[..]).collect();
+				self.apply(&plan, &inputs, &mut refs);
+			}
+			for (&i, o) in missing_data.iter().zip(out) {
+				shards[i] = Some(o);
+			}
+		}
+
+		let missing_parity: Vec<usize> = (0..self.parity_shards()).filter(|&i| shards[k + i].is_none()).collect();
+		if !missing_parity.is_empty() {
+			let rows: Vec<Vec<Gf256>> = missing_parity.iter().map(|&i| self.coding.row(i).to_vec()).collect();
+			let plan = schedule(&Matrix::from_rows(&rows).expect("Rows have k entries"));
+			let mut out = vec![vec![0u8; size]; missing_parity.len()];
+			{
+				let inputs: Vec<&[u8]> = shards[..k].iter().map(|s| &s.as_ref().expect("Decoded")[..]).collect();
+				let mut refs: Vec<&mut [u8]> = out.iter_mut().map(|o| &mut o[..]).collect();
+				self.apply(&plan, &inputs, &mut refs);
+			}
+			for (&i, o) in missing_parity.iter().zip(out) {
+				shards[k + i] = Some(o);
+			}
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(k: usize, size: usize) -> Vec<Vec<u8>> {
+        (0..k).map(|i| (0..size).map(|j| (i * 59 + j * 11 + j / 7) as u8).collect()).collect()
+    }
+
+    fn encode(code: &CauchyXor, data: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let size = data[0].len();
+        let mut parity = vec![vec![0u8; size]; code.parity_shards()];
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode(&refs, &mut out).unwrap();
+        parity
+    }
+
+    /// Bit q of byte t of the 8 packets of a block, as a field element
+    fn element(shard: &[u8], ps: usize, t: usize, q: usize) -> Gf256 {
+        Gf256::from_byte((0..8).map(|x| (shard[x * ps + t] >> q & 1) << x).sum())
+    }
+
+    #[test]
+    fn test_encode_is_field_coding() {
+        let code = CauchyXor::new(3, 2, 8).unwrap();
+        let d = data(3, 64);
+        let parity = encode(&code, &d);
+        for (i, p) in parity.iter().enumerate() {
+            for t in 0..8 {
+                for q in 0..8 {
+                    let expected: Gf256 = (0..3).map(|j| code.coding_matrix()[(i, j)] * element(&d[j], 8, t, q)).sum();
+                    assert_eq!(element(p, 8, t, q), expected);
+                }
+            }
+        }
+        assert!(code.xor_count() > 0);
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let code = CauchyXor::new(5, 3, 16).unwrap();
+        let d = data(5, 256);
+        let all: Vec<Vec<u8>> = d.iter().cloned().chain(encode(&code, &d)).collect();
+        for lost in [[0, 1, 2], [1, 4, 6], [5, 6, 7], [0, 4, 7]] {
+            let mut shards: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+            for i in lost {
+                shards[i] = None;
+            }
+            code.reconstruct(&mut shards).unwrap();
+            assert!(shards.iter().zip(&all).all(|(s, a)| s.as_ref() == Some(a)), "lost {:?}", lost);
+        }
+        let mut shards: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        shards[..4].fill(None);
+        assert_eq!(code.reconstruct(&mut shards), Err(ErasureError::TooFewShards));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(CauchyXor::new(4, 2, 12), Err(ErasureError::InvalidParameters));
+        assert_eq!(CauchyXor::new(200, 57, 8), Err(ErasureError::InvalidParameters));
+        let code = CauchyXor::new(2, 1, 8).unwrap();
+        let d = [vec![0u8; 60], vec![0u8; 60]];
+        let refs: Vec<&[u8]> = d.iter().map(|x| &x[..]).collect();
+        let mut p = [0u8; 60];
+        assert_eq!(code.encode(&refs, &mut [&mut p[..]]), Err(ErasureError::ShardSizeMismatch));
+    }
+}
@@ -0,0 +1,330 @@
+//! The CCSDS telemetry Reed-Solomon code (CCSDS 131.0-B, section 4).
+//!
+//! The code is RS(255, 223) over the field with the polynomial
+//! x^8 + x^7 + x^2 + x + 1 (0x187). For a root a of it the generator is
+//! the product of x - a^(11 j) for j = 112, ..., 143; the roots lie
+//! symmetrically around a^(11 * 127.5), so the generator equals its own
+//! reciprocal and a hardware encoder needs only half the multipliers.
+//! Symbols go on the wire in Berlekamp's dual basis representation, which
+//! the standard defines by the matrix T_al.
+//!
+//! Any two fields with 256 elements are isomorphic. Sending a to ALPHA,
+//! a root of 0x187 in Gf256, maps the CCSDS field onto Gf256; the
+//! conventional CCSDS byte of an element is its coordinates in the basis
+//! CONVENTIONAL of the powers of ALPHA. With l = a^117, bit 7 - k of
+//! the Berlekamp byte of z is Tr(l^k z), so BERLEKAMP is the trace-dual
+//! of the powers of l, and the bytes of T_al are its coordinates.
+//!
+//! ALPHA^11 is x^s for an s coprime to 255. Moving the coefficient of
+//! degree e of a codeword to degree s e mod 255 turns the CCSDS roots
+//! into x^112, ..., x^143, so decoding reuses the decoder of RsCodec with
+//! first consecutive root 112.
+
+use crate::Gf256;
+use crate::basis::Basis;
+use super::{ decode_with, encode_parity, syndromes_into, Buffers, RsError, Scratch };
+
+/// Codeword length
+pub const N: usize = 255;
+/// Message length
+pub const K: usize = 223;
+/// Number of parity symbols
+pub const PARITY: usize = N - K;
+/// The field polynomial x^8 + x^7 + x^2 + x + 1
+pub const FIELD_POLYNOMIAL: u16 = 0x187;
+/// The first root of the generator is a^(ROOT_SPACING * FIRST_ROOT)
+pub const FIRST_ROOT: usize = 112;
+/// The roots are consecutive powers of a^ROOT_SPACING
+pub const ROOT_SPACING: usize = 11;
+
+/// The image of a in Gf256, the smallest root of FIELD_POLYNOMIAL
+pub const ALPHA: Gf256 = {
+	let mut b = 2;
+	loop {
+		let a = Gf256::from_byte(b);
+		if a.minimal_polynomial() == FIELD_POLYNOMIAL as u32 {
+			break a;
+		}
+		b += 1;
+	}
+};
+
+const fn powers(base: Gf256, reversed: bool) -> [Gf256; 8] {
+	let mut out = [Gf256::ZERO; 8];
+	let mut p = Gf256::ONE;
+	let mut i = 0;
+	while i < 8 {
+		out[if reversed { 7 - i } else { i }] = p;
+		p = p.const_mul(base);
+		i += 1;
+	}
+	out
+}
+
+/// 1, a, ..., a^7, whose coordinates are conventional CCSDS bytes
+pub const CONVENTIONAL: Basis = match Basis::new(powers(ALPHA, false)) {
+	Some(b) => b,
+	None => panic!("a has degree 8"),
+};
+
+/// Berlekamp's dual basis, whose coordinates are the bytes on the wire
+pub const BERLEKAMP: Basis = match Basis::new(powers(ALPHA.exp(117), true)) {
+	Some(b) => b.dual(),
+	None => panic!("a^117 has degree 8"),
+};
+
+/// The matrix T_al of the standard, in the layout of the tables of
+/// Phil Karn's widely used implementation: row i is the Berlekamp byte
+/// of a^(7 - i)
+pub const T_AL: [u8; 8] = [0x8d, 0xef, 0xec, 0x86, 0xfa, 0x99, 0xaf, 0x7b];
+
+/// Berlekamp byte of every conventional byte, by T_al
+pub const TO_DUAL: [u8; 256] = {
+	let mut t = [0u8; 256];
+	let mut c = 0;
+	while c < 256 {
+		let mut k = 0;
+		while k < 8 {
+			if c >> k & 1 == 1 {
+				t[c] ^= T_AL[7 - k];
+			}
+			k += 1;
+		}
+		c += 1;
+	}
+	t
+};
+
+/// Conventional byte of every Berlekamp byte
+pub const FROM_DUAL: [u8; 256] = {
+	let mut t = [0u8; 256];
+	let mut c = 0;
+	while c < 256 {
+		t[TO_DUAL[c] as usize] = c as u8;
+		c += 1;
+	}
+	t
+};
+
+/// The element of Gf256 a Berlekamp byte stands for
+pub const fn to_field(b: u8) -> Gf256 {
+	BERLEKAMP.from_coordinates(b)
+}
+
+/// The Berlekamp byte of an element of Gf256
+pub const fn from_field(a: Gf256) -> u8 {
+	BERLEKAMP.coordinates(a)
+}
+
+const fn field_table(to_field: bool) -> [u8; 256] {
+	let mut t = [0u8; 256];
+	let mut b = 0;
+	while b < 256 {
+		t[b] = if to_field {
+			BERLEKAMP.from_coordinates(b as u8).to_byte()
+		} else {
+			BERLEKAMP.coordinates(Gf256::from_byte(b as u8))
+		};
+		b += 1;
+	}
+	t
+}
+
+const TO_FIELD: [u8; 256] = field_table(true);
+const FROM_FIELD: [u8; 256] = field_table(false);
+
+/// The generator polynomial in Gf256, highest degree first
+pub const GENERATOR: [Gf256; PARITY + 1] = {
+	let root = ALPHA.exp(ROOT_SPACING as u8);
+	let mut gen = [Gf256::ZERO; PARITY + 1];
+	gen[0] = Gf256::ONE;
+	let mut i = 0;
+	while i < PARITY {
+		let r = root.exp(((FIRST_ROOT + i) % 255) as u8);
+		let mut j = i + 1;
+		while j > 0 {
+			gen[j] = Gf256::from_byte(gen[j].to_byte() ^ gen[j - 1].const_mul(r).to_byte());
+			j -= 1;
+		}
+		i += 1;
+	}
+	gen
+};
+
+/// Position in the permuted codeword of every position of a codeword
+const POSITIONS: [u8; N] = {
+	let s = match ALPHA.exp(ROOT_SPACING as u8).log() {
+		Some(s) => s as usize,
+		None => panic!("a is nonzero"),
+	};
+	let mut t = [0u8; N];
+	let mut p = 0;
+	while p < N {
+		t[p] = (N - 1 - s * (N - 1 - p) % 255) as u8;
+		p += 1;
+	}
+	t
+};
+
+/// The codeword in Gf256 with the roots of RsCodec
+fn permuted(codeword: &[u8; N]) -> [u8; N] {
+	let mut w = [0u8; N];
+	for (&p, &b) in POSITIONS.iter().zip(codeword) {
+		w[p as usize] = TO_FIELD[b as usize];
+	}
+	w
+}
+
+/// Computes the parity of codeword[..K] into codeword[K..], all in
+/// Berlekamp bytes
+pub fn encode(codeword: &mut [u8; N]) {
+	let mut msg = [0u8; K];
+	for (m, &b) in msg.iter_mut().zip(&codeword[..K]) {
+		*m = TO_FIELD[b as usize];
+	}
+	let parity = &mut codeword[K..];
+	encode_parity(&GENERATOR, &msg, parity);
+	for p in parity.iter_mut() {
+		*p = FROM_FIELD[*p as usize];
+	}
+}
+
+/// Checks whether codeword is a valid codeword
+pub fn is_valid(codeword: &[u8; N], scratch: &mut Scratch<N, K>) -> bool {
+	syndromes_into(&permuted(codeword), FIRST_ROOT, &mut scratch.syn[..PARITY])
+}
+
+/// Corrects up to 16 symbol errors in place and returns the number of
+/// corrected symbols. On error the codeword is left unchanged.
+pub fn decode(codeword: &mut [u8; N], scratch: &mut Scratch<N, K>) -> Result<usize, RsError> {
+	let mut w = permuted(codeword);
+	let bufs = Buffers {
+		syn: &mut scratch.syn,
+		lambda: &mut scratch.lambda,
+		prev: &mut scratch.prev,
+		tmp: &mut scratch.tmp,
+		omega: &mut scratch.omega,
+		positions: &mut scratch.positions,
+	};
+	let count = decode_with(&mut w, PARITY, FIRST_ROOT, bufs)?;
+	for (b, &p) in codeword.iter_mut().zip(&POSITIONS) {
+		*b = FROM_FIELD[w[p as usize] as usize];
+	}
+	Ok(count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Multiplication in the CCSDS field itself
+    fn mul(mut a: u8, mut b: u8) -> u8 {
+        let mut r = 0;
+        while b != 0 {
+            if b & 1 == 1 {
+                r ^= a;
+            }
+            b >>= 1;
+            a = if a & 0x80 != 0 { (a << 1) ^ FIELD_POLYNOMIAL as u8 } else { a << 1 };
+        }
+        r
+    }
+
+    fn power(a: u8, e: usize) -> u8 {
+        (0..e).fold(1, |p, _| mul(p, a))
+    }
+
+    /// Parity computed the way dual basis encoders do: convert the data
+    /// to conventional bytes, divide in the CCSDS field, convert back
+    fn reference_parity(data: &[u8]) -> [u8; PARITY] {
+        let mut gen = [0u8; PARITY + 1];
+        gen[0] = 1;
+        for i in 0..PARITY {
+            let r = power(2, ROOT_SPACING * (FIRST_ROOT + i));
+            for j in (1..=i + 1).rev() {
+                gen[j] ^= mul(gen[j - 1], r);
+            }
+        }
+        let mut parity = [0u8; PARITY];
+        for &d in data {
+            let feedback = FROM_DUAL[d as usize] ^ parity[0];
+            for j in 0..PARITY - 1 {
+                parity[j] = parity[j + 1] ^ mul(feedback, gen[j + 1]);
+            }
+            parity[PARITY - 1] = mul(feedback, gen[PARITY]);
+        }
+        parity.map(|p| TO_DUAL[p as usize])
+    }
+
+    fn codeword(seed: usize) -> [u8; N] {
+        let mut c = [0u8; N];
+        for (i, b) in c[..K].iter_mut().enumerate() {
+            *b = (i * 37 + seed * 101 + i / 5) as u8;
+        }
+        encode(&mut c);
+        c
+    }
+
+    #[test]
+    fn test_dual_basis() {
+        for c in 0..=255u8 {
+            let a = CONVENTIONAL.from_coordinates(c);
+            assert_eq!(TO_DUAL[c as usize], from_field(a));
+            assert_eq!(to_field(TO_DUAL[c as usize]), a);
+            assert_eq!(FROM_DUAL[TO_DUAL[c as usize] as usize], c);
+            for d in (0..=255u8).step_by(17) {
+                let b = CONVENTIONAL.from_coordinates(d);
+                assert_eq!(CONVENTIONAL.coordinates(a * b), mul(c, d));
+            }
+        }
+        assert_eq!(TO_DUAL[0], 0);
+        assert_eq!(T_AL.map(|r| FROM_DUAL[r as usize]), [128, 64, 32, 16, 8, 4, 2, 1]);
+    }
+
+    #[test]
+    fn test_generator() {
+        let conventional = GENERATOR.map(|g| CONVENTIONAL.coordinates(g));
+        let mut reversed = conventional;
+        reversed.reverse();
+        assert_eq!(conventional, reversed);
+        assert_eq!(conventional[0], 1);
+        for (r, c) in POSITIONS.iter().enumerate() {
+            assert!(POSITIONS[..r].iter().all(|p| p != c));
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        for seed in 0..4 {
+            let c = codeword(seed);
+            assert_eq!(c[K..], reference_parity(&c[..K]));
+            assert!(is_valid(&c, &mut Scratch::new()));
+        }
+        let mut zero = [0u8; N];
+        encode(&mut zero);
+        assert_eq!(zero, [0u8; N]);
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut scratch = Scratch::new();
+        let c = codeword(7);
+        let mut r = c;
+        for i in 0..16 {
+            r[i * 15 + 3] ^= (i as u8).wrapping_mul(29) | 1;
+        }
+        assert!(!is_valid(&r, &mut scratch));
+        assert_eq!(decode(&mut r, &mut scratch), Ok(16));
+        assert_eq!(r, c);
+        assert_eq!(decode(&mut r, &mut scratch), Ok(0));
+
+        let mut r = c;
+        for i in 0..17 {
+            r[i * 13] ^= 0x5a;
+        }
+        let damaged = r;
+        assert_eq!(decode(&mut r, &mut scratch), Err(RsError::TooManyErrors));
+        assert_eq!(r, damaged);
+    }
+}
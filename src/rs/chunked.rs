@@ -0,0 +1,240 @@
+//! Erasure coding of streams, a chunk at a time.
+//!
+//! ChunkedCoder reads data_shards * chunk_size bytes from the input, cuts
+//! them into one chunk per data shard, adds the parity chunks and appends
+//! each chunk to its shard stream. At no point does it hold more than one
+//! chunk per shard, so the memory use is fixed by the chunk size and not
+//! by the length of the input. The last stripe is cut into chunks of
+//! ceil(rest / data_shards) bytes, padded with zeros, so all shard streams
+//! have the same length.
+//!
+//! The shard streams do not record the input length; decode needs it to
+//! drop the padding of the last stripe. Codec errors surface as
+//! io::ErrorKind::InvalidData with the ErasureError as the inner error.
+
+use std::io::{ self, Read, Write };
+
+use super::erasure::ErasureError;
+use super::shard::ReedSolomon;
+
+fn invalid(e: ErasureError) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Reads until buf is full or the reader is exhausted
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut len = 0;
+	while len < buf.len() {
+		match r.read(&mut buf[len..]) {
+			Ok(0) => break,
+			Ok(m) => len += m,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(len)
+}
+
+/// Streams data through a ReedSolomon code in fixed size chunks
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ChunkedCoder {
+	rs: ReedSolomon,
+	chunk_size: usize,
+}
+
+impl ChunkedCoder {
+	/// chunk_size bytes per shard and stripe; it has to be positive
+	pub fn new(rs: ReedSolomon, chunk_size: usize) -> Result<ChunkedCoder, ErasureError> {
+		if chunk_size == 0 {
+			return Err(ErasureError::InvalidParameters);
+		}
+		Ok(ChunkedCoder { rs, chunk_size })
+	}
+
+	/// The largest chunk size whose stripe buffers fit in limit bytes
+	pub fn with_memory_limit(rs: ReedSolomon, limit: usize) -> Result<ChunkedCoder, ErasureError> {
+		let chunk_size = limit / rs.total_shard_count();
+		ChunkedCoder::new(rs, chunk_size)
+	}
+
+	pub fn code(&self) -> &ReedSolomon {
+		&self.rs
+	}
+
+	pub fn chunk_size(&self) -> usize {
+		self.chunk_size
+	}
+
+	/// Bytes buffered while coding, one chunk per shard
+	pub fn buffer_size(&self) -> usize {
+		self.chunk_size * self.rs.total_shard_count()
+	}
+
+	/// The length of every shard stream for an input of len bytes
+	pub fn shard_len(&self, len: u64) -> u64 {
+		let stripe = (self.chunk_size * self.rs.data_shard_count()) as u64;
+		let rest = len % stripe;
+		len / stripe * self.chunk_size as u64 + rest.div_ceil(self.rs.data_shard_count() as u64)
+	}
+
+	/// Encodes input into one stream per shard, data shards first, and
+	/// returns the number of input bytes
+	pub fn encode<R: Read, W: Write>(&self, mut input: R, outputs: &mut [W]) -> io::Result<u64> {
+		let (d, total) = (self.rs.data_shard_count(), self.rs.total_shard_count());
+		if outputs.len() != total {
+			return Err(invalid(ErasureError::ShardCountMismatch));
+		}
+		let mut stripe = vec![0u8; self.buffer_size()];
+		let mut len = 0u64;
+		loop {
+			let got = read_full(&mut input, &mut stripe[..d * self.chunk_size])?;
+			if got == 0 {
+				break;
+			}
+			len += got as u64;
+			let chunk = got.div_ceil(d);
+			stripe[got..d * chunk].fill(0);
+			let mut shards: Vec<&mut [u8]> = stripe.chunks_mut(chunk).take(total).collect();
+			self.rs.encode(&mut shards).map_err(invalid)?;
+			for (out, shard) in outputs.iter_mut().zip(&shards) {
+				out.write_all(shard)?;
+			}
+			if got < d * self.chunk_size {
+				break;
+			}
+		}
+		Ok(len)
+	}
+
+	/// Reads the next chunk of every present input. Returns None at the
+	/// end of all of them.
+	fn read_stripe<R: Read>(&self, inputs: &mut [Option<R>]) -> io::Result<Option<Vec<Option<Vec<u8>>>>> {
+		if inputs.len() != self.rs.total_shard_count() {
+			return Err(invalid(ErasureError::ShardCountMismatch));
+		}
+		let mut size = None;
+		let mut shards = Vec::with_capacity(inputs.len());
+		for input in inputs.iter_mut() {
+			let Some(r) = input else {
+				shards.push(None);
+				continue;
+			};
+			let mut chunk = vec![0u8; self.chunk_size];
+			let got = read_full(r, &mut chunk)?;
+			if *size.get_or_insert(got) != got {
+				return Err(invalid(ErasureError::ShardSizeMismatch));
+			}
+			chunk.truncate(got);
+			shards.push(Some(chunk));
+		}
+		match size {
+			None => Err(invalid(ErasureError::TooFewShards)),
+			Some(0) => Ok(None),
+			Some(_) => Ok(Some(shards)),
+		}
+	}
+
+	/// Recovers len bytes of input from the shard streams, with None for
+	/// lost shards, and writes them to output
+	pub fn decode<R: Read, W: Write>(&self, inputs: &mut [Option<R>], mut output: W, len: u64) -> io::Result<()> {
+		let d = self.rs.data_shard_count();
+		let mut left = len;
+		while left > 0 {
+			let Some(mut shards) = self.read_stripe(inputs)? else {
+				return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "shard streams too short"));
+			};
+			self.rs.reconstruct_data(&mut shards).map_err(invalid)?;
+			for shard in shards.iter().take(d) {
+				let shard = shard.as_ref().expect("Reconstructed");
+				let take = (shard.len() as u64).min(left) as usize;
+				output.write_all(&shard[..take])?;
+				left -= take as u64;
+			}
+		}
+		output.flush()
+	}
+
+	/// Regenerates lost shard streams: every output which is Some and
+	/// whose input is None receives the full stream of that shard
+	pub fn repair<R: Read, W: Write>(&self, inputs: &mut [Option<R>], outputs: &mut [Option<W>]) -> io::Result<()> {
+		if outputs.len() != self.rs.total_shard_count() {
+			return Err(invalid(ErasureError::ShardCountMismatch));
+		}
+		while let Some(mut shards) = self.read_stripe(inputs)? {
+			let lost: Vec<bool> = shards.iter().map(Option::is_none).collect();
+			self.rs.reconstruct(&mut shards).map_err(invalid)?;
+			for ((out, shard), lost) in outputs.iter_mut().zip(&shards).zip(lost) {
+				if let (Some(w), true) = (out, lost) {
+					w.write_all(shard.as_ref().expect("Reconstructed"))?;
+				}
+			}
+		}
+		for w in outputs.iter_mut().flatten() {
+			w.flush()?;
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    fn encode(coder: &ChunkedCoder, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut outputs = vec![Vec::new(); coder.code().total_shard_count()];
+        assert_eq!(coder.encode(data, &mut outputs).unwrap(), data.len() as u64);
+        outputs
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let coder = ChunkedCoder::new(ReedSolomon::new(4, 2).unwrap(), 16).unwrap();
+        for len in [0, 1, 63, 64, 65, 1000] {
+            let data = input(len);
+            let shards = encode(&coder, &data);
+            assert!(shards.iter().all(|s| s.len() as u64 == coder.shard_len(len as u64)));
+            let mut inputs: Vec<Option<&[u8]>> = shards.iter().map(|s| Some(&s[..])).collect();
+            inputs[1] = None;
+            inputs[4] = None;
+            let mut out = Vec::new();
+            coder.decode(&mut inputs, &mut out, len as u64).unwrap();
+            assert_eq!(out, data, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_repair() {
+        let coder = ChunkedCoder::with_memory_limit(ReedSolomon::new(3, 3).unwrap(), 60).unwrap();
+        assert_eq!(coder.chunk_size(), 10);
+        assert!(coder.buffer_size() <= 60);
+        let shards = encode(&coder, &input(500));
+        let mut inputs: Vec<Option<&[u8]>> = shards.iter().map(|s| Some(&s[..])).collect();
+        inputs[0] = None;
+        inputs[5] = None;
+        let mut outputs: Vec<Option<Vec<u8>>> = vec![None; 6];
+        outputs[0] = Some(Vec::new());
+        outputs[5] = Some(Vec::new());
+        coder.repair(&mut inputs, &mut outputs).unwrap();
+        assert_eq!(outputs[0].as_ref(), Some(&shards[0]));
+        assert_eq!(outputs[5].as_ref(), Some(&shards[5]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let rs = ReedSolomon::new(2, 1).unwrap();
+        assert_eq!(ChunkedCoder::with_memory_limit(rs.clone(), 2), Err(ErasureError::InvalidParameters));
+        let coder = ChunkedCoder::new(rs, 4).unwrap();
+        let shards = encode(&coder, &input(20));
+        let mut inputs: Vec<Option<&[u8]>> = vec![None, None, Some(&shards[2])];
+        let err = coder.decode(&mut inputs, Vec::new(), 20).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut inputs: Vec<Option<&[u8]>> = shards.iter().map(|s| Some(&s[..])).collect();
+        let err = coder.decode(&mut inputs, Vec::new(), 21).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,362 @@
+//! Reed-Solomon codes with parameters chosen at runtime.
+//!
+//! RsEncoder is the heap-backed counterpart of RsCodec for when n and k
+//! come from a file header or command line rather than the type. It uses
+//! the same generator polynomial and codeword layout, so the two produce
+//! identical parity.
+//!
+//! Parity can be computed either by polynomial division or by multiplying
+//! the message with the systematic generator matrix; the results agree
+//! and the matrix form is what erasure-style decoders start from.
+//!
+//! decode_erasures restores symbols at known positions. With e erasures
+//! the erasure locator is known up front, so Forney's formula gives the
+//! missing values directly; up to n - k erasures can be filled, twice as
+//! many as unknown errors could be corrected. decode handles errors at
+//! unknown positions with the decoder of RsCodec: syndromes,
+//! Berlekamp-Massey for the error locator, Chien search for its roots and
+//! Forney's formula for the magnitudes.
+
+use alloc::{ vec, vec::Vec };
+
+use crate::{ Gf256, as_byte_slice };
+use crate::backend::Backend;
+use crate::matrix::Matrix;
+use super::{ decode_using, encode_parity, generator_poly, read_state, write_state, Buffers, CodecConfig,
+	ConfigError, KeySolver, RsError, StateError, STATE_HEADER_LEN };
+use super::locator;
+
+/// A systematic RS(n, k) code over GF(256)
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct RsEncoder {
+	n: usize,
+	k: usize,
+	/// Highest degree first
+	gen: Vec<Gf256>,
+}
+
+impl RsEncoder {
+	/// Requires 0 < k < n <= 255
+	pub fn new(n: usize, k: usize) -> Result<RsEncoder, ConfigError> {
+		let gen = generator_poly(n, k, 0, Gf256::from_byte(2))?;
+		Ok(RsEncoder { n, k, gen })
+	}
+
+	/// The RS code with config's data and parity shards as k and n - k.
+	/// Fails with TooManyShards for 256 shards, which ErasureCode allows
+	/// but an RS codeword cannot have.
+	pub fn from_config(config: &CodecConfig) -> Result<RsEncoder, ConfigError> {
+		RsEncoder::new(config.total_shards(), config.data_shards())
+	}
+
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	pub fn k(&self) -> usize {
+		self.k
+	}
+
+	/// Number of parity symbols, n - k
+	pub fn parity_len(&self) -> usize {
+		self.n - self.k
+	}
+
+	/// Returns the generator polynomial, highest degree first
+	pub fn generator(&self) -> &[Gf256] {
+		&self.gen
+	}
+
+	/// Computes the n - k parity symbols of msg into parity by polynomial
+	/// division. Panics if msg is not k bytes long or parity not n - k.
+	pub fn encode_into(&self, msg: &[u8], parity: &mut [u8]) {
+		assert_eq!(msg.len(), self.k, "Message must have k symbols");
+		assert_eq!(parity.len(), self.n - self.k, "Parity must have n - k symbols");
+		encode_parity(&self.gen, msg, parity);
+	}
+
+	/// encode_into with the shift register updated by backend, one
+	/// mul_add of the generator per message symbol
+	pub fn encode_into_with(&self, backend: &dyn Backend, msg: &[u8], parity: &mut [u8]) {
+		assert_eq!(msg.len(), self.k, "Message must have k symbols");
+		assert_eq!(parity.len(), self.n - self.k, "Parity must have n - k symbols");
+		parity.fill(0);
+		let gen = as_byte_slice(&self.gen[1..]);
+		for &m in msg {
+			let feedback = m ^ parity[0];
+			parity.copy_within(1.., 0);
+			parity[self.n - self.k - 1] = 0;
+			backend.mul_add(parity, gen, feedback);
+		}
+	}
+
+	/// Returns the parity symbols of msg
+	pub fn parity(&self, msg: &[u8]) -> Vec<u8> {
+		let mut parity = vec![0u8; self.n - self.k];
+		self.encode_into(msg, &mut parity);
+		parity
+	}
+
+	/// Returns the codeword msg followed by its parity
+	pub fn encode(&self, msg: &[u8]) -> Vec<u8> {
+		let mut codeword = msg.to_vec();
+		codeword.extend_from_slice(&self.parity(msg));
+		codeword
+	}
+
+	/// The k x n systematic generator matrix: row i is the codeword of
+	/// the i-th unit message, so the left k columns are the identity
+	pub fn generator_matrix(&self) -> Matrix {
+		let mut g = Matrix::zero(self.k, self.n);
+		let mut msg = vec![0u8; self.k];
+		for i in 0..self.k {
+			msg[i] = 1;
+			let parity = self.parity(&msg);
+			msg[i] = 0;
+			let row = g.row_mut(i);
+			row[i] = Gf256::one();
+			for (r, &p) in row[self.k..].iter_mut().zip(&parity) {
+				*r = Gf256::from_byte(p);
+			}
+		}
+		g
+	}
+
+	/// Computes the parity of msg as the right n - k entries of
+	/// msg * generator_matrix(). Slower than parity, but shows that the
+	/// code is linear; the output is identical.
+	pub fn parity_by_matrix(&self, msg: &[u8]) -> Vec<u8> {
+		assert_eq!(msg.len(), self.k, "Message must have k symbols");
+		let g = self.generator_matrix();
+		let msg: Vec<Gf256> = msg.iter().map(|&b| Gf256::from_byte(b)).collect();
+		let codeword = g.transpose().mul_vec(&msg);
+		codeword[self.k..].iter().map(|c| c.to_byte()).collect()
+	}
+
+	/// The state of the code in the format of RsCodec::save, so either
+	/// type restores what the other saved
+	pub fn save(&self) -> Vec<u8> {
+		let mut out = vec![0u8; STATE_HEADER_LEN + self.gen.len() + 4];
+		write_state(self.n, self.k, &self.gen, &mut out);
+		out
+	}
+
+	/// Restores a code saved with save, checking the CRC and that the
+	/// generator is the one new would build
+	pub fn restore(bytes: &[u8]) -> Result<RsEncoder, StateError> {
+		let (n, k, gen) = read_state(bytes)?;
+		Ok(RsEncoder { n, k, gen: gen.iter().map(|&b| Gf256::from_byte(b)).collect() })
+	}
+
+	/// Checks whether codeword is a valid codeword
+	pub fn is_valid(&self, codeword: &[u8]) -> bool {
+		codeword.len() == self.n
+			&& locator::syndromes(codeword, self.n - self.k).iter().all(|s| s.poly == 0)
+	}
+
+	/// Corrects up to (n - k) / 2 symbol errors in place and returns the
+	/// number of corrected symbols. On error the codeword is left in an
+	/// unspecified state.
+	pub fn decode(&self, codeword: &mut [u8]) -> Result<usize, RsError> {
+		self.decode_using(codeword, KeySolver::BerlekampMassey)
+	}
+
+	/// Like decode, solving the key equation with solver
+	pub fn decode_using(&self, codeword: &mut [u8], solver: KeySolver) -> Result<usize, RsError> {
+		if codeword.len() != self.n {
+			return Err(RsError::InvalidLength);
+		}
+		let len = self.n - self.k + 1;
+		let mut work = vec![Gf256::zero(); 5 * len];
+		let mut positions = vec![0usize; len];
+		let (syn, rest) = work.split_at_mut(len);
+		let (lambda, rest) = rest.split_at_mut(len);
+		let (prev, rest) = rest.split_at_mut(len);
+		let (tmp, omega) = rest.split_at_mut(len);
+		let bufs = Buffers { syn, lambda, prev, tmp, omega, positions: &mut positions };
+		decode_using(codeword, self.n - self.k, 0, bufs, solver)
+	}
+
+	/// Rewrites the symbols at the erased positions (counted from the
+	/// start of the codeword) so that codeword becomes valid again. The
+	/// old contents of those positions do not matter. Fails if there are
+	/// more than n - k erasures. With fewer, corruption of other symbols
+	/// is detected as well, in which case the erased positions may have
+	/// been overwritten. With exactly n - k erasures no check is left:
+	/// any other symbols give a valid codeword, right or not.
+	pub fn decode_erasures(&self, codeword: &mut [u8], erasures: &[usize]) -> Result<(), RsError> {
+		let (n, nsym) = (self.n, self.n - self.k);
+		if codeword.len() != n {
+			return Err(RsError::InvalidLength);
+		}
+		for (i, &p) in erasures.iter().enumerate() {
+			if p >= n || erasures[..i].contains(&p) {
+				return Err(RsError::InvalidPosition(p));
+			}
+		}
+		if erasures.len() > nsym {
+			return Err(RsError::TooManyErrors);
+		}
+		let syn = locator::syndromes(codeword, nsym);
+		if syn.iter().all(|s| s.poly == 0) {
+			return Ok(());
+		}
+		let loc = locator::erasure_locator(erasures, n);
+		let omega = locator::error_evaluator(&syn, &loc);
+		let magnitudes = locator::forney(&omega, &loc, erasures, n).ok_or(RsError::TooManyErrors)?;
+		for (&p, m) in erasures.iter().zip(magnitudes) {
+			codeword[p] ^= m.to_byte();
+		}
+		if locator::syndromes(codeword, nsym).iter().any(|s| s.poly != 0) {
+			return Err(RsError::TooManyErrors);
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::RsCodec;
+
+    #[test]
+    fn test_matches_rscodec() {
+        let enc = RsEncoder::new(15, 11).unwrap();
+        let codec = RsCodec::<15, 11>::new();
+        assert_eq!(enc.generator(), codec.generator());
+        let msg: Vec<u8> = (1..=11).collect();
+        let mut cw = [0u8; 15];
+        cw[..11].copy_from_slice(&msg);
+        codec.encode(&mut cw);
+        assert_eq!(enc.encode(&msg), cw.to_vec());
+    }
+
+    #[test]
+    fn test_backend() {
+        let enc = RsEncoder::new(60, 40).unwrap();
+        let msg: Vec<u8> = (0..40).map(|i| (i * 29 + 7) as u8).collect();
+        let mut parity = [0u8; 20];
+        for b in [&crate::backend::Scalar as &dyn Backend, &crate::backend::Table, crate::backend::best_available()] {
+            enc.encode_into_with(b, &msg, &mut parity);
+            assert_eq!(parity[..], enc.parity(&msg)[..], "{}", b.name());
+        }
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let enc = RsEncoder::new(40, 28).unwrap();
+        let state = enc.save();
+        assert_eq!(RsEncoder::restore(&state), Ok(enc));
+
+        // Interchangeable with RsCodec states
+        let codec = RsCodec::<40, 28>::new();
+        let mut fixed = [0u8; RsCodec::<40, 28>::STATE_LEN];
+        codec.save(&mut fixed).unwrap();
+        assert_eq!(fixed[..], state[..]);
+        assert!(RsCodec::<40, 28>::restore(&state).is_ok());
+
+        let mut bad = state.clone();
+        bad[10] ^= 4;
+        assert_eq!(RsEncoder::restore(&bad), Err(StateError::Corrupt));
+        assert_eq!(RsEncoder::restore(&state[..state.len() - 1]), Err(StateError::BufferTooSmall));
+
+        // A valid CRC over the generator of another code
+        let other = generator_poly(40, 28, 1, Gf256::from_byte(2)).unwrap();
+        let mut bad = vec![0u8; state.len()];
+        write_state(40, 28, &other, &mut bad);
+        assert_eq!(RsEncoder::restore(&bad), Err(StateError::Corrupt));
+        assert_eq!(RsCodec::<40, 28>::restore(&bad).err(), Some(StateError::Corrupt));
+    }
+
+    #[test]
+    fn test_matrix_style() {
+        let enc = RsEncoder::new(20, 12).unwrap();
+        let msg: Vec<u8> = (0..12).map(|i| (i * 37 + 5) as u8).collect();
+        assert_eq!(enc.parity_by_matrix(&msg), enc.parity(&msg));
+        let g = enc.generator_matrix();
+        assert_eq!((g.rows(), g.cols()), (12, 20));
+        assert_eq!(g.rank(), 12);
+    }
+
+    #[test]
+    fn test_decode_erasures() {
+        let enc = RsEncoder::new(30, 20).unwrap();
+        let msg: Vec<u8> = (0..20).map(|i| (i * 13 + 1) as u8).collect();
+        let good = enc.encode(&msg);
+        // Up to n - k erasures anywhere, data or parity
+        for erasures in [vec![], vec![0], vec![3, 29, 17], (10..20).collect(), (0..30).step_by(3).collect()] {
+            let mut cw = good.clone();
+            for &p in &erasures {
+                cw[p] = 0;
+            }
+            assert_eq!(enc.decode_erasures(&mut cw, &erasures), Ok(()));
+            assert_eq!(cw, good);
+        }
+        let mut cw = good.clone();
+        let eleven: Vec<usize> = (0..11).collect();
+        assert_eq!(enc.decode_erasures(&mut cw, &eleven), Err(RsError::TooManyErrors));
+        // An error outside the erased positions is detected
+        cw[25] ^= 1;
+        cw[0] ^= 1;
+        assert_eq!(enc.decode_erasures(&mut cw, &[0, 1]), Err(RsError::TooManyErrors));
+        assert_eq!(enc.decode_erasures(&mut cw, &[1, 1]), Err(RsError::InvalidPosition(1)));
+        assert_eq!(enc.decode_erasures(&mut cw, &[30]), Err(RsError::InvalidPosition(30)));
+        assert_eq!(enc.decode_erasures(&mut cw[1..], &[]), Err(RsError::InvalidLength));
+    }
+
+    #[test]
+    fn test_full_erasures_undetected() {
+        let enc = RsEncoder::new(10, 6).unwrap();
+        let good = enc.encode(&[1, 2, 3, 4, 5, 6]);
+        let mut cw = good.clone();
+        cw[9] ^= 1;
+        // Four erasures use up all the parity, so the flipped byte goes
+        // unnoticed
+        assert_eq!(enc.decode_erasures(&mut cw, &[0, 1, 2, 3]), Ok(()));
+        assert!(enc.is_valid(&cw));
+        assert_ne!(cw, good);
+        // With one erasure fewer it is caught
+        let mut cw = good.clone();
+        cw[9] ^= 1;
+        assert_eq!(enc.decode_erasures(&mut cw, &[0, 1, 2]), Err(RsError::TooManyErrors));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let enc = RsEncoder::new(40, 30).unwrap();
+        let msg: Vec<u8> = (0..30).map(|i| (i * 71 + 3) as u8).collect();
+        let good = enc.encode(&msg);
+        assert!(enc.is_valid(&good));
+        let mut cw = good.clone();
+        assert_eq!(enc.decode(&mut cw), Ok(0));
+        // Five errors are correctable with ten parity symbols
+        for (i, &p) in [0, 7, 22, 31, 39].iter().enumerate() {
+            cw[p] ^= 0x11 * (i as u8 + 1);
+        }
+        assert!(!enc.is_valid(&cw));
+        assert_eq!(enc.decode(&mut cw), Ok(5));
+        assert_eq!(cw, good);
+        // Six are not
+        for p in [1, 5, 9, 13, 17, 21] {
+            cw[p] ^= 0xa5;
+        }
+        assert!(enc.decode(&mut cw).is_err());
+        assert_eq!(enc.decode(&mut cw[..39]), Err(RsError::InvalidLength));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(RsEncoder::new(10, 10), Err(ConfigError::ZeroParityShards));
+        assert_eq!(RsEncoder::new(10, 0), Err(ConfigError::ZeroDataShards));
+        assert!(RsEncoder::new(256, 200).is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = CodecConfig::builder().data_shards(12).parity_shards(4).symbol_bits(8).build().unwrap();
+        assert_eq!(RsEncoder::from_config(&config), RsEncoder::new(16, 12));
+        let wide = CodecConfig::builder().data_shards(200).parity_shards(56).symbol_bits(8).build().unwrap();
+        assert_eq!(RsEncoder::from_config(&wide), Err(ConfigError::TooManyShards { total: 256, max: 255 }));
+    }
+}
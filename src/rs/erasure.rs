@@ -0,0 +1,720 @@
+//! Systematic erasure coding of whole shards.
+//!
+//! The data shards are viewed, byte position by byte position, as the
+//! values of a polynomial of degree below k at the points 0, 1, ..., k-1;
+//! parity shard j holds the values at k + j. Any k shards determine the
+//! polynomial, so any k surviving shards recover the others.
+//!
+//! Because the generator has this Vandermonde structure, the decoding
+//! coefficients are Lagrange coefficients. In barycentric form they cost
+//! O(k^2) for the shared weights and O(k) per missing shard, instead of
+//! the O(k^3) of inverting a k x k matrix by Gaussian elimination.
+//!
+//! save writes the encoding coefficients and the cached reconstruction
+//! plans, so a short-lived process can pick up a warm code with restore
+//! and every machine of a fleet uses the very same coefficients. The
+//! state is checked by a CRC-32, not recomputed:
+//!
+//! ```text
+//! 0  magic "GFEC"
+//! 4  version (1)
+//! 5  number of cached plans
+//! 6  data shards k, parity shards m, 16 bits each
+//! 10 the encoding plan, then the cached plans, most recent first
+//! .. CRC-32 (IEEE) of everything before
+//! ```
+//!
+//! A plan is its 32 byte key (the mask of present shards, big endian
+//! words; zero for the encoding plan), the number of survivors and of
+//! targets as 16 bit integers, one byte per survivor and target index,
+//! and the coefficients, one row per target.
+
+use std::fmt;
+use std::error;
+use std::panic::RefUnwindSafe;
+use std::sync::{ Arc, Mutex };
+
+use crate::Gf256;
+use crate::backend::{ self, Backend };
+use crate::recovery::crc32;
+use super::{ max_shards, CodecConfig, StateError };
+use super::header::{ HeaderError, ShardHeader };
+use super::offload::{ Job, Offload, OffloadError };
+
+/// Errors produced by ErasureCode
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum ErasureError {
+	/// data must be positive and data + parity at most 256
+	InvalidParameters,
+	/// The number of shards does not match the code
+	ShardCountMismatch,
+	/// Shards have different sizes
+	ShardSizeMismatch,
+	/// Fewer than data shards survived
+	TooFewShards,
+	/// The offload backend failed
+	Offload(OffloadError),
+	/// A framed shard has an unusable header, or belongs to a different
+	/// code (reported as InvalidGeometry)
+	InvalidHeader(HeaderError),
+	/// Two intact framed shards claim this index with different payloads
+	DuplicateShard(usize),
+}
+
+impl fmt::Display for ErasureError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ErasureError::InvalidParameters => f.write_str("invalid code parameters"),
+			ErasureError::ShardCountMismatch => f.write_str("wrong number of shards"),
+			ErasureError::ShardSizeMismatch => f.write_str("shards have different sizes"),
+			ErasureError::TooFewShards => f.write_str("too few shards to reconstruct"),
+			ErasureError::Offload(e) => write!(f, "offload failed: {}", e),
+			ErasureError::InvalidHeader(e) => write!(f, "invalid shard header: {}", e),
+			ErasureError::DuplicateShard(i) => write!(f, "conflicting copies of shard {}", i),
+		}
+	}
+}
+
+impl error::Error for ErasureError {}
+
+#[inline]
+fn point(i: usize) -> Gf256 {
+	Gf256::from_byte(i as u8)
+}
+
+/// Computes the coefficients expressing the interpolating polynomial's
+/// value at every target point as a combination of its values at the
+/// (distinct) source points. Result is targets.len() rows of
+/// sources.len() coefficients. No target may equal a source point.
+pub(crate) fn lagrange_coefficients(sources: &[Gf256], targets: &[Gf256]) -> Vec<Vec<Gf256>> {
+	// Barycentric weights w_i = 1 / prod_{m != i} (x_i - x_m)
+	let weights: Vec<Gf256> = sources.iter().enumerate().map(|(i, &xi)| {
+		let prod = sources.iter().enumerate()
+			.filter(|&(m, _)| m != i)
+			.fold(Gf256::one(), |acc, (_, &xm)| acc * (xi - xm));
+		prod.inv().expect("Source points must be distinct")
+	}).collect();
+
+	targets.iter().map(|&t| {
+		// l(t) = prod (t - x_m), coefficient i = l(t) w_i / (t - x_i)
+		let l = sources.iter().fold(Gf256::one(), |acc, &xm| acc * (t - xm));
+		sources.iter().zip(&weights).map(|(&xi, &wi)| l * wi / (t - xi)).collect()
+	}).collect()
+}
+
+/// Precomputed coefficients recovering a set of shards from survivors
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Plan {
+	survivors: Vec<usize>,
+	targets: Vec<usize>,
+	/// coeffs[t][s]: weight of survivors[s] in targets[t]
+	coeffs: Vec<Vec<Gf256>>,
+}
+
+impl Plan {
+	/// Indices of the shards read by the plan
+	pub fn survivors(&self) -> &[usize] {
+		&self.survivors
+	}
+
+	/// Indices of the shards produced by the plan
+	pub fn targets(&self) -> &[usize] {
+		&self.targets
+	}
+
+	/// Computes target shard number t (an index into targets) from the
+	/// survivor shards, given in the order of survivors.
+	pub fn apply(&self, t: usize, inputs: &[&[u8]], out: &mut [u8]) {
+		self.apply_with(backend::best_available(), t, inputs, out);
+	}
+
+	/// apply with the multiplications done by backend
+	pub fn apply_with(&self, backend: &dyn Backend, t: usize, inputs: &[&[u8]], out: &mut [u8]) {
+		assert_eq!(inputs.len(), self.survivors.len(), "Wrong number of inputs");
+		for o in out.iter_mut() {
+			*o = 0;
+		}
+		for (input, c) in inputs.iter().zip(&self.coeffs[t]) {
+			backend.mul_add(out, input, c.to_byte());
+		}
+	}
+}
+
+/// Number of reconstruction plans remembered by each ErasureCode
+const PLAN_CACHE_SIZE: usize = 16;
+
+/// Set of shard indices, bit i of word i / 64 standing for shard i
+type ShardMask = [u64; 4];
+
+fn shard_mask(indices: &[usize]) -> ShardMask {
+	let mut mask = [0u64; 4];
+	for &i in indices {
+		mask[i / 64] |= 1 << (i % 64);
+	}
+	mask
+}
+
+/// Least recently used plans, most recent first. During a rebuild the
+/// same failure pattern repeats for every stripe, so this is tiny and
+/// a linear scan is cheaper than hashing.
+#[derive(Default)]
+struct PlanCache {
+	entries: Vec<(ShardMask, Arc<Plan>)>,
+}
+
+impl PlanCache {
+	fn get(&mut self, key: &ShardMask) -> Option<Arc<Plan>> {
+		let pos = self.entries.iter().position(|(k, _)| k == key)?;
+		let entry = self.entries.remove(pos);
+		let plan = entry.1.clone();
+		self.entries.insert(0, entry);
+		Some(plan)
+	}
+
+	fn insert(&mut self, key: ShardMask, plan: Arc<Plan>) {
+		self.entries.truncate(PLAN_CACHE_SIZE - 1);
+		self.entries.insert(0, (key, plan));
+	}
+}
+
+const STATE_MAGIC: [u8; 4] = *b"GFEC";
+const STATE_VERSION: u8 = 1;
+
+/// Reads one plan of a saved state for a code of n shards, returning its
+/// key, the plan and the bytes after it
+fn read_plan(bytes: &[u8], n: usize) -> Option<(ShardMask, Plan, &[u8])> {
+	let mut key = [0u64; 4];
+	for (k, word) in key.iter_mut().zip(bytes.get(..32)?.chunks_exact(8)) {
+		*k = u64::from_be_bytes(word.try_into().ok()?);
+	}
+	let count = |at: usize| bytes.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize);
+	let (s, t) = (count(32)?, count(34)?);
+	let indices = bytes.get(36..36 + s + t)?;
+	let coeffs = bytes.get(36 + s + t..36 + s + t + s * t)?;
+	if indices.iter().any(|&i| i as usize >= n) {
+		return None;
+	}
+	let plan = Plan {
+		survivors: indices[..s].iter().map(|&i| i as usize).collect(),
+		targets: indices[s..].iter().map(|&i| i as usize).collect(),
+		coeffs: coeffs.chunks(s.max(1)).take(t).map(|row| row.iter().map(|&c| Gf256::from_byte(c)).collect()).collect(),
+	};
+	Some((key, plan, &bytes[36 + s + t + s * t..]))
+}
+
+/// A systematic erasure code with data + parity shards
+pub struct ErasureCode {
+	data: usize,
+	parity: usize,
+	encode_plan: Plan,
+	cache: Mutex<PlanCache>,
+	backend: &'static (dyn Backend + Sync + RefUnwindSafe),
+}
+
+impl Clone for ErasureCode {
+	fn clone(&self) -> ErasureCode {
+		ErasureCode {
+			data: self.data,
+			parity: self.parity,
+			encode_plan: self.encode_plan.clone(),
+			cache: Mutex::new(PlanCache::default()),
+			backend: self.backend,
+		}
+	}
+}
+
+impl PartialEq for ErasureCode {
+	fn eq(&self, other: &ErasureCode) -> bool {
+		self.data == other.data && self.parity == other.parity
+	}
+}
+
+impl Eq for ErasureCode {}
+
+impl fmt::Debug for ErasureCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasureCode")
+			.field("data", &self.data)
+			.field("parity", &self.parity)
+			.field("backend", &self.backend.name())
+			.finish()
+	}
+}
+
+impl ErasureCode {
+	pub fn new(data: usize, parity: usize) -> Result<ErasureCode, ErasureError> {
+		if data == 0 || data + parity > max_shards(8) {
+			return Err(ErasureError::InvalidParameters);
+		}
+		let survivors: Vec<usize> = (0..data).collect();
+		let targets: Vec<usize> = (data..data + parity).collect();
+		let encode_plan = ErasureCode::make_plan(survivors, targets);
+		Ok(ErasureCode { data, parity, encode_plan, cache: Mutex::new(PlanCache::default()),
+			backend: backend::best_available() })
+	}
+
+	/// Runs encode and reconstruct on backend instead of the one the
+	/// bytes functions would pick, e.g. Scalar to keep table lookups off
+	/// secret data. The output does not change.
+	pub fn with_backend(mut self, backend: &'static (dyn Backend + Sync + RefUnwindSafe)) -> ErasureCode {
+		self.backend = backend;
+		self
+	}
+
+	pub fn backend(&self) -> &'static (dyn Backend + Sync + RefUnwindSafe) {
+		self.backend
+	}
+
+	/// The code described by config. CodecBuilder checks the same limits
+	/// as new, so this cannot fail.
+	pub fn from_config(config: &CodecConfig) -> ErasureCode {
+		ErasureCode::new(config.data_shards(), config.parity_shards())
+			.expect("CodecConfig is validated")
+	}
+
+	pub fn data_shards(&self) -> usize {
+		self.data
+	}
+
+	pub fn parity_shards(&self) -> usize {
+		self.parity
+	}
+
+	pub fn total_shards(&self) -> usize {
+		self.data + self.parity
+	}
+
+	fn make_plan(survivors: Vec<usize>, targets: Vec<usize>) -> Plan {
+		let src: Vec<Gf256> = survivors.iter().map(|&i| point(i)).collect();
+		let dst: Vec<Gf256> = targets.iter().map(|&i| point(i)).collect();
+		let coeffs = lagrange_coefficients(&src, &dst);
+		Plan { survivors, targets, coeffs }
+	}
+
+	/// Computes a plan which recovers targets from survivors. The first
+	/// data survivors are used; survivors and targets have to be disjoint
+	/// shard indices.
+	pub fn plan(&self, survivors: &[usize], targets: &[usize]) -> Result<Plan, ErasureError> {
+		if survivors.len() < self.data {
+			return Err(ErasureError::TooFewShards);
+		}
+		let n = self.total_shards();
+		if survivors.iter().chain(targets).any(|&i| i >= n) {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		Ok(ErasureCode::make_plan(survivors[..self.data].to_vec(), targets.to_vec()))
+	}
+
+	/// The coefficients and cached plans in the format described above
+	pub fn save(&self) -> Vec<u8> {
+		let cache = self.cache.lock().unwrap();
+		let mut out = STATE_MAGIC.to_vec();
+		out.extend_from_slice(&[STATE_VERSION, cache.entries.len() as u8]);
+		out.extend_from_slice(&(self.data as u16).to_be_bytes());
+		out.extend_from_slice(&(self.parity as u16).to_be_bytes());
+		let plans = core::iter::once((&[0u64; 4], &self.encode_plan))
+			.chain(cache.entries.iter().map(|(key, plan)| (key, &**plan)));
+		for (key, plan) in plans {
+			for word in key {
+				out.extend_from_slice(&word.to_be_bytes());
+			}
+			out.extend_from_slice(&(plan.survivors.len() as u16).to_be_bytes());
+			out.extend_from_slice(&(plan.targets.len() as u16).to_be_bytes());
+			out.extend(plan.survivors.iter().chain(&plan.targets).map(|&i| i as u8));
+			out.extend(plan.coeffs.iter().flatten().map(|c| c.to_byte()));
+		}
+		let crc = crc32(&out);
+		out.extend_from_slice(&crc.to_be_bytes());
+		out
+	}
+
+	/// Restores a code written by save, with its cached plans, after
+	/// checking the CRC and that every plan fits the code and its key
+	pub fn restore(bytes: &[u8]) -> Result<ErasureCode, StateError> {
+		if bytes.len() < 14 {
+			return Err(StateError::BufferTooSmall);
+		}
+		if bytes[..4] != STATE_MAGIC {
+			return Err(StateError::BadMagic);
+		}
+		if bytes[4] != STATE_VERSION {
+			return Err(StateError::UnsupportedVersion);
+		}
+		let (body, crc) = bytes.split_at(bytes.len() - 4);
+		if crc32(body).to_be_bytes() != crc {
+			return Err(StateError::Corrupt);
+		}
+		let data = u16::from_be_bytes([body[6], body[7]]) as usize;
+		let parity = u16::from_be_bytes([body[8], body[9]]) as usize;
+		if data == 0 || data + parity > max_shards(8) || bytes[5] as usize > PLAN_CACHE_SIZE {
+			return Err(StateError::Corrupt);
+		}
+		let mut rest = &body[10..];
+		let mut plans = Vec::with_capacity(bytes[5] as usize + 1);
+		for _ in 0..=bytes[5] {
+			let (key, plan, tail) = read_plan(rest, data + parity).ok_or(StateError::Corrupt)?;
+			// A cached plan reads the first data present shards of its key
+			// and rebuilds exactly the missing ones
+			if !plans.is_empty() {
+				let (present, missing): (Vec<usize>, Vec<usize>) = (0..data + parity)
+					.partition(|&i| key[i / 64] >> (i % 64) & 1 == 1);
+				if key != shard_mask(&present) || present.len() < data
+					|| plan.survivors[..] != present[..data] || plan.targets != missing {
+					return Err(StateError::Corrupt);
+				}
+			}
+			if plan.survivors.len() != data {
+				return Err(StateError::Corrupt);
+			}
+			plans.push((key, plan));
+			rest = tail;
+		}
+		if !rest.is_empty() {
+			return Err(StateError::Corrupt);
+		}
+		let (_, encode_plan) = plans.remove(0);
+		if encode_plan.survivors != (0..data).collect::<Vec<_>>()
+			|| encode_plan.targets != (data..data + parity).collect::<Vec<_>>() {
+			return Err(StateError::Corrupt);
+		}
+		let entries = plans.into_iter().map(|(key, plan)| (key, Arc::new(plan))).collect();
+		Ok(ErasureCode { data, parity, encode_plan, cache: Mutex::new(PlanCache { entries }),
+			backend: backend::best_available() })
+	}
+
+	/// Computes the parity shards from the data shards
+	pub fn encode(&self, data: &[&[u8]], parity: &mut [&mut [u8]]) -> Result<(), ErasureError> {
+		if data.len() != self.data || parity.len() != self.parity {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let size = data[0].len();
+		if data.iter().any(|d| d.len() != size) || parity.iter().any(|p| p.len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		for (t, out) in parity.iter_mut().enumerate() {
+			self.encode_plan.apply_with(self.backend, t, data, out);
+		}
+		Ok(())
+	}
+
+	/// Encodes the data shards and returns all shards, data first, each
+	/// framed with a header (see header)
+	pub fn encode_framed(&self, data: &[&[u8]]) -> Result<Vec<Vec<u8>>, ErasureError> {
+		let size = data.first().map_or(0, |d| d.len());
+		let mut parity = vec![vec![0u8; size]; self.parity];
+		{
+			let mut refs: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+			self.encode(data, &mut refs)?;
+		}
+		let (k, m) = (self.data as u16, self.parity as u16);
+		Ok(data.iter().copied().chain(parity.iter().map(|p| &p[..])).enumerate()
+			.map(|(i, payload)| ShardHeader::frame(k, m, i as u16, payload))
+			.collect())
+	}
+
+	/// Recovers the data shards from framed shards in any order. Shards
+	/// failing their checksum are treated as missing; any other header
+	/// problem is an error. Identical copies of a shard are skipped, while
+	/// copies that differ fail with DuplicateShard.
+	pub fn decode_framed(&self, framed: &[&[u8]]) -> Result<Vec<Vec<u8>>, ErasureError> {
+		let mut shards: Vec<Option<Vec<u8>>> = vec![None; self.total_shards()];
+		for bytes in framed {
+			let (header, payload) = match ShardHeader::parse(bytes) {
+				Ok(parsed) => parsed,
+				Err(HeaderError::ChecksumMismatch) => continue,
+				Err(e) => return Err(ErasureError::InvalidHeader(e)),
+			};
+			if header.data_shards as usize != self.data || header.parity_shards as usize != self.parity {
+				return Err(ErasureError::InvalidHeader(HeaderError::InvalidGeometry));
+			}
+			match &shards[header.index as usize] {
+				Some(first) if first[..] == *payload => {},
+				Some(_) => return Err(ErasureError::DuplicateShard(header.index as usize)),
+				None => shards[header.index as usize] = Some(payload.to_vec()),
+			}
+		}
+		self.reconstruct(&mut shards)?;
+		shards.truncate(self.data);
+		Ok(shards.into_iter().map(|s| s.expect("Reconstructed")).collect())
+	}
+
+	/// Like encode, running the arithmetic on backend
+	pub fn encode_offload(&self, data: &[&[u8]], parity: &mut [&mut [u8]], backend: &dyn Offload)
+		-> Result<(), ErasureError> {
+		if data.len() != self.data || parity.len() != self.parity {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let size = data[0].len();
+		if data.iter().any(|d| d.len() != size) || parity.iter().any(|p| p.len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		let outputs = parity.iter_mut().map(|p| &mut p[..]).collect();
+		let job = Job::Matrix { matrix: &self.encode_plan.coeffs, inputs: data.to_vec(), outputs };
+		backend.submit(&mut [job]).map_err(ErasureError::Offload)
+	}
+
+	/// Like reconstruct, running the arithmetic on backend
+	pub fn reconstruct_offload(&self, shards: &mut [Option<Vec<u8>>], backend: &dyn Offload)
+		-> Result<(), ErasureError> {
+		if shards.len() != self.total_shards() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let present: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_some()).collect();
+		let missing: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_none()).collect();
+		if missing.is_empty() {
+			return Ok(());
+		}
+		let plan = self.cached_plan(&present, &missing)?;
+		let size = shards[plan.survivors[0]].as_ref().map_or(0, |s| s.len());
+		let mut recovered = vec![vec![0u8; size]; plan.targets.len()];
+		{
+			let mut inputs: Vec<&[u8]> = Vec::with_capacity(plan.survivors.len());
+			for &s in &plan.survivors {
+				match shards[s] {
+					Some(ref shard) if shard.len() == size => inputs.push(shard),
+					_ => return Err(ErasureError::ShardSizeMismatch),
+				}
+			}
+			let outputs = recovered.iter_mut().map(|r| &mut r[..]).collect();
+			let job = Job::Matrix { matrix: &plan.coeffs, inputs, outputs };
+			backend.submit(&mut [job]).map_err(ErasureError::Offload)?;
+		}
+		for (&t, shard) in plan.targets.iter().zip(recovered) {
+			shards[t] = Some(shard);
+		}
+		Ok(())
+	}
+
+	/// Fills in every missing (None) shard, given at least data present
+	/// shards. Plans for recently seen sets of present shards are reused.
+	pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ErasureError> {
+		if shards.len() != self.total_shards() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let present: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_some()).collect();
+		let missing: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_none()).collect();
+		if missing.is_empty() {
+			return Ok(());
+		}
+		let plan = self.cached_plan(&present, &missing)?;
+		self.reconstruct_with(&plan, shards)
+	}
+
+	fn cached_plan(&self, present: &[usize], missing: &[usize]) -> Result<Arc<Plan>, ErasureError> {
+		let key = shard_mask(present);
+		if let Some(plan) = self.cache.lock().unwrap().get(&key) {
+			return Ok(plan);
+		}
+		// Computed without holding the lock; a concurrent miss on the same
+		// key just inserts an equal plan twice.
+		let plan = Arc::new(self.plan(present, missing)?);
+		self.cache.lock().unwrap().insert(key, plan.clone());
+		Ok(plan)
+	}
+
+	/// Like reconstruct, but uses a previously computed plan. The plan's
+	/// survivors have to be present.
+	pub fn reconstruct_with(&self, plan: &Plan, shards: &mut [Option<Vec<u8>>])
+		-> Result<(), ErasureError> {
+		let size = match shards[plan.survivors[0]] {
+			Some(ref s) => s.len(),
+			None => return Err(ErasureError::TooFewShards),
+		};
+		let mut recovered = Vec::with_capacity(plan.targets.len());
+		{
+			let mut inputs: Vec<&[u8]> = Vec::with_capacity(plan.survivors.len());
+			for &s in &plan.survivors {
+				match shards[s] {
+					Some(ref shard) if shard.len() == size => inputs.push(shard),
+					Some(_) => return Err(ErasureError::ShardSizeMismatch),
+					None => return Err(ErasureError::TooFewShards),
+				}
+			}
+			for t in 0..plan.targets.len() {
+				let mut out = vec![0u8; size];
+				plan.apply_with(self.backend, t, &inputs, &mut out);
+				recovered.push(out);
+			}
+		}
+		for (&t, shard) in plan.targets.iter().zip(recovered) {
+			shards[t] = Some(shard);
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shards(code: &ErasureCode, size: usize) -> Vec<Vec<u8>> {
+        let data: Vec<Vec<u8>> = (0..code.data_shards())
+            .map(|i| (0..size).map(|j| (i * 31 + j * 7) as u8).collect())
+            .collect();
+        let mut parity = vec![vec![0u8; size]; code.parity_shards()];
+        {
+            let data_refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+            let mut parity_refs: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+            code.encode(&data_refs, &mut parity_refs).unwrap();
+        }
+        data.into_iter().chain(parity).collect()
+    }
+
+    #[test]
+    fn test_lagrange_identity() {
+        // Interpolating a constant yields the constant everywhere, so the
+        // coefficients of every target sum to one.
+        let src = [point(1), point(2), point(3)];
+        let coeffs = lagrange_coefficients(&src, &[point(9)]);
+        let sum = coeffs[0].iter().fold(Gf256::zero(), |acc, &c| acc + c);
+        assert_eq!(sum, Gf256::one());
+    }
+
+    #[test]
+    fn test_reconstruct_any_subset() {
+        let code = ErasureCode::new(4, 3).unwrap();
+        let all = shards(&code, 16);
+        for a in 0..7 {
+            for b in a + 1..7 {
+                for c in b + 1..7 {
+                    let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+                    damaged[a] = None;
+                    damaged[b] = None;
+                    damaged[c] = None;
+                    code.reconstruct(&mut damaged).unwrap();
+                    let restored: Vec<Vec<u8>> = damaged.into_iter().map(|s| s.unwrap()).collect();
+                    assert_eq!(restored, all);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_few_shards() {
+        let code = ErasureCode::new(3, 2).unwrap();
+        let all = shards(&code, 4);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.into_iter().map(Some).collect();
+        damaged[0] = None;
+        damaged[2] = None;
+        damaged[4] = None;
+        assert_eq!(code.reconstruct(&mut damaged), Err(ErasureError::TooFewShards));
+    }
+
+    #[test]
+    fn test_plan_cache() {
+        let code = ErasureCode::new(3, 3).unwrap();
+        let all = shards(&code, 4);
+        for round in 0..2 {
+            for lost in 0..PLAN_CACHE_SIZE + 4 {
+                let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+                damaged[lost % 6] = None;
+                damaged[(lost / 6 + lost + 1) % 6] = None;
+                code.reconstruct(&mut damaged).unwrap();
+                assert!(damaged.iter().zip(&all).all(|(d, a)| d.as_ref() == Some(a)), "round {}", round);
+            }
+        }
+        let cache = code.cache.lock().unwrap();
+        assert!(cache.entries.len() <= PLAN_CACHE_SIZE);
+        // The last pattern is the most recently used
+        let expected = shard_mask(&[0, 2, 3, 4]);
+        assert_eq!(cache.entries[0].0, expected);
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let code = ErasureCode::new(4, 3).unwrap();
+        let all = shards(&code, 6);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        damaged[1] = None;
+        damaged[5] = None;
+        code.reconstruct(&mut damaged).unwrap();
+
+        let state = code.save();
+        let restored = ErasureCode::restore(&state).unwrap();
+        assert_eq!(restored, code);
+        assert_eq!(restored.encode_plan, code.encode_plan);
+        // The plan comes from the cache
+        {
+            let cache = restored.cache.lock().unwrap();
+            assert_eq!(cache.entries.len(), 1);
+            assert_eq!(cache.entries[0].0, shard_mask(&[0, 2, 3, 4, 6]));
+        }
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        damaged[1] = None;
+        damaged[5] = None;
+        restored.reconstruct(&mut damaged).unwrap();
+        assert!(damaged.iter().zip(&all).all(|(d, a)| d.as_ref() == Some(a)));
+        assert_eq!(restored.save(), state);
+
+        let mut bad = state.clone();
+        bad[20] ^= 1;
+        assert_eq!(ErasureCode::restore(&bad).err(), Some(StateError::Corrupt));
+        assert_eq!(ErasureCode::restore(&state[..8]).err(), Some(StateError::BufferTooSmall));
+        assert_eq!(ErasureCode::restore(b"GFRS\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00").err(), Some(StateError::BadMagic));
+    }
+
+    #[test]
+    fn test_restore_mismatched_plan() {
+        let code = ErasureCode::new(2, 1).unwrap();
+        let all = shards(&code, 4);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        damaged[0] = None;
+        code.reconstruct(&mut damaged).unwrap();
+        let state = code.save();
+
+        // Aim the plan for {1, 2} at shard 1 instead of shard 0, the CRC
+        // still matching
+        let target = 10 + (32 + 4 + 3 + 2) + 32 + 4 + 2;
+        assert_eq!(state[target], 0);
+        let mut bad = state[..state.len() - 4].to_vec();
+        bad[target] = 1;
+        let crc = crc32(&bad);
+        bad.extend_from_slice(&crc.to_be_bytes());
+        assert_eq!(ErasureCode::restore(&bad).err(), Some(StateError::Corrupt));
+
+        // Or at both missing and present shards
+        let mut bad = state[..state.len() - 4].to_vec();
+        bad[target - 2] = 2;
+        let crc = crc32(&bad);
+        bad.extend_from_slice(&crc.to_be_bytes());
+        assert_eq!(ErasureCode::restore(&bad).err(), Some(StateError::Corrupt));
+    }
+
+    #[test]
+    fn test_wide_code() {
+        let code = ErasureCode::new(200, 56).unwrap();
+        let all = shards(&code, 8);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        for i in (0..256).step_by(5).take(56) {
+            damaged[i] = None;
+        }
+        code.reconstruct(&mut damaged).unwrap();
+        assert!(damaged.iter().zip(&all).all(|(d, a)| d.as_ref() == Some(a)));
+    }
+
+    #[test]
+    fn test_backend() {
+        let code = ErasureCode::new(6, 3).unwrap();
+        let all = shards(&code, 100);
+        let scalar = code.clone().with_backend(&backend::Scalar);
+        assert_eq!(scalar.backend().name(), "scalar");
+        assert_eq!(shards(&scalar, 100), all);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        damaged[0] = None;
+        damaged[7] = None;
+        scalar.reconstruct(&mut damaged).unwrap();
+        assert!(damaged.iter().zip(&all).all(|(d, a)| d.as_ref() == Some(a)));
+        assert_eq!(scalar.clone().backend().name(), "scalar");
+    }
+
+    #[test]
+    fn test_from_config() {
+        // The builder accepts exactly what new accepts
+        let config = CodecConfig::builder().data_shards(200).parity_shards(56).symbol_bits(8).build().unwrap();
+        let code = ErasureCode::from_config(&config);
+        assert_eq!((code.data_shards(), code.parity_shards()), (200, 56));
+        assert!(CodecConfig::builder().data_shards(200).parity_shards(57).symbol_bits(8).build().is_err());
+        assert_eq!(ErasureCode::new(200, 57).err(), Some(ErasureError::InvalidParameters));
+    }
+}
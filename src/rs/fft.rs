@@ -0,0 +1,221 @@
+//! Erasure encoding with the additive FFT.
+//!
+//! This follows Lin, Chung and Han ("Novel polynomial basis and its
+//! application to Reed-Solomon erasure codes", 2014). The field elements
+//! are enumerated as w_i = i, which spans GF(256) with the basis
+//! v_j = 2^j. Polynomials are written in the novel basis
+//! X_i(x) = prod_j W_j(x)^(bit j of i), where W_j is the normalized
+//! vanishing polynomial of span(v_0, ..., v_(j-1)). In that basis a
+//! polynomial of degree below 2^m can be evaluated on any coset of
+//! span(v_0, ..., v_(m-1)) with O(2^m m) additions and multiplications.
+//!
+//! Every butterfly works on whole shards through the bulk kernels, so a
+//! stripe with k data shards and p parity shards costs O((k + p) log k)
+//! shard operations instead of the O(k p) of the matrix path. The
+//! evaluation points are those of ErasureCode, so the output is
+//! identical and ErasureCode decodes it.
+//!
+//! The data shards have to fill whole cosets of span(v_0, ..., v_(m-1)),
+//! so their number must be a power of two; FftEncoder::new rejects
+//! anything else. Pad other stripes with zero shards or use ErasureCode.
+//! The gain shows at wide stripes: the ignored test_timing compares both
+//! encoders with `cargo test --release -- --ignored --nocapture`.
+
+use crate::Gf256;
+use crate::bytes;
+use super::max_shards;
+use super::erasure::ErasureError;
+
+/// skew[j][x] = W_j(x) / W_j(v_j), the normalized vanishing polynomial of
+/// span(v_0, ..., v_(j-1))
+fn skew_table() -> [[u8; 256]; 8] {
+	let mut w = [[0u8; 256]; 8];
+	for (x, v) in w[0].iter_mut().enumerate() {
+		*v = x as u8;
+	}
+	for j in 0..7 {
+		// W_(j+1)(x) = W_j(x) W_j(x + v_j) = W_j(x) (W_j(x) + W_j(v_j))
+		let (lo, hi) = w.split_at_mut(j + 1);
+		let at_vj = Gf256::from_byte(lo[j][1 << j]);
+		for (next, &cur) in hi[0].iter_mut().zip(lo[j].iter()) {
+			let wx = Gf256::from_byte(cur);
+			*next = (wx * (wx + at_vj)).to_byte();
+		}
+	}
+	for (j, row) in w.iter_mut().enumerate() {
+		let norm = Gf256::from_byte(row[1 << j]);
+		for v in row.iter_mut() {
+			*v = (Gf256::from_byte(*v) / norm).to_byte();
+		}
+	}
+	w
+}
+
+/// Evaluates the polynomial with novel basis coefficients shards at the
+/// points beta + i, i < shards.len(), in place.
+fn fft(skew: &[[u8; 256]; 8], shards: &mut [Vec<u8>], beta: usize) {
+	let len = shards.len();
+	if len <= 1 {
+		return;
+	}
+	let half = len / 2;
+	let j = half.trailing_zeros() as usize;
+	let c = skew[j][beta];
+	{
+		let (lo, hi) = shards.split_at_mut(half);
+		for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+			bytes::mul_add(a, b, c);
+			bytes::add_assign(b, a);
+		}
+	}
+	let (lo, hi) = shards.split_at_mut(half);
+	fft(skew, lo, beta);
+	fft(skew, hi, beta ^ half);
+}
+
+/// Inverse of fft: turns values at beta + i into novel basis coefficients
+fn ifft(skew: &[[u8; 256]; 8], shards: &mut [Vec<u8>], beta: usize) {
+	let len = shards.len();
+	if len <= 1 {
+		return;
+	}
+	let half = len / 2;
+	let j = half.trailing_zeros() as usize;
+	let c = skew[j][beta];
+	let (lo, hi) = shards.split_at_mut(half);
+	ifft(skew, lo, beta);
+	ifft(skew, hi, beta ^ half);
+	for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+		bytes::add_assign(b, a);
+		bytes::mul_add(a, b, c);
+	}
+}
+
+/// Systematic encoder producing the same parity as ErasureCode with
+/// O(n log n) shard operations. The number of data shards has to be a
+/// power of two.
+pub struct FftEncoder {
+	data: usize,
+	parity: usize,
+	skew: [[u8; 256]; 8],
+}
+
+impl FftEncoder {
+	pub fn new(data: usize, parity: usize) -> Result<FftEncoder, ErasureError> {
+		if !data.is_power_of_two() || data + parity > max_shards(8) {
+			return Err(ErasureError::InvalidParameters);
+		}
+		Ok(FftEncoder { data, parity, skew: skew_table() })
+	}
+
+	pub fn data_shards(&self) -> usize {
+		self.data
+	}
+
+	pub fn parity_shards(&self) -> usize {
+		self.parity
+	}
+
+	/// Computes the parity shards from the data shards
+	pub fn encode(&self, data: &[&[u8]], parity: &mut [&mut [u8]]) -> Result<(), ErasureError> {
+		if data.len() != self.data || parity.len() != self.parity {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let size = data[0].len();
+		if data.iter().any(|d| d.len() != size) || parity.iter().any(|p| p.len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+
+		let mut coeffs: Vec<Vec<u8>> = data.iter().map(|d| d.to_vec()).collect();
+		ifft(&self.skew, &mut coeffs, 0);
+
+		// Parity shard j sits at point data + j, i.e. in the cosets
+		// data * c + span(v_0, ..., v_(m-1)) for c >= 1.
+		let mut done = 0;
+		let mut coset = 1;
+		while done < self.parity {
+			let mut values = coeffs.clone();
+			fft(&self.skew, &mut values, coset * self.data);
+			for v in values.iter().take(self.parity - done) {
+				parity[done].copy_from_slice(v);
+				done += 1;
+			}
+			coset += 1;
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::erasure::ErasureCode;
+
+    fn data(k: usize, size: usize) -> Vec<Vec<u8>> {
+        (0..k).map(|i| (0..size).map(|j| (i * 37 + j * 11 + 5) as u8).collect()).collect()
+    }
+
+    fn compare(k: usize, m: usize) {
+        let data = data(k, 6);
+        let data_refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+
+        let mut fft_parity = vec![vec![0u8; 6]; m];
+        let mut matrix_parity = vec![vec![0u8; 6]; m];
+        {
+            let mut refs: Vec<&mut [u8]> = fft_parity.iter_mut().map(|p| &mut p[..]).collect();
+            FftEncoder::new(k, m).unwrap().encode(&data_refs, &mut refs).unwrap();
+        }
+        {
+            let mut refs: Vec<&mut [u8]> = matrix_parity.iter_mut().map(|p| &mut p[..]).collect();
+            ErasureCode::new(k, m).unwrap().encode(&data_refs, &mut refs).unwrap();
+        }
+        assert_eq!(fft_parity, matrix_parity);
+    }
+
+    #[test]
+    fn test_matches_matrix_path() {
+        compare(1, 3);
+        compare(4, 5);
+        compare(16, 16);
+        compare(128, 128);
+    }
+
+    #[test]
+    fn test_fft_inverts_ifft() {
+        let skew = skew_table();
+        let original = data(32, 3);
+        let mut shards = original.clone();
+        ifft(&skew, &mut shards, 64);
+        fft(&skew, &mut shards, 64);
+        assert_eq!(shards, original);
+    }
+
+    #[test]
+    fn test_requires_power_of_two() {
+        assert!(FftEncoder::new(6, 2).is_err());
+        assert!(FftEncoder::new(8, 249).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_timing() {
+        use std::time::Instant;
+        for (k, m) in [(16, 16), (64, 64), (128, 128)] {
+            let data = data(k, 16384);
+            let data_refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+            let mut parity = vec![vec![0u8; 16384]; m];
+            let mut refs: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+
+            let fft = FftEncoder::new(k, m).unwrap();
+            let start = Instant::now();
+            fft.encode(&data_refs, &mut refs).unwrap();
+            let fft_time = start.elapsed();
+            let code = ErasureCode::new(k, m).unwrap();
+            let start = Instant::now();
+            code.encode(&data_refs, &mut refs).unwrap();
+            let matrix_time = start.elapsed();
+            println!("{}+{} x 16 KiB: fft {:?}, matrix {:?}", k, m, fft_time, matrix_time);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Self-describing headers for stored shards.
+//!
+//! Every framed shard starts with a 24 byte header, all integers big
+//! endian:
+//!
+//! ```text
+//! 0  magic "GFSH"
+//! 4  version (1)
+//! 5  reserved, zero
+//! 6  field polynomial (0x011d)
+//! 8  data shards k
+//! 10 parity shards m
+//! 12 shard index
+//! 14 reserved, zero
+//! 16 stripe size: payload bytes following the header
+//! 20 CRC-32 (IEEE) of bytes 0..20 and the payload
+//! ```
+//!
+//! The shard with index i holds the values at point i described in
+//! erasure, so shards are interchangeable with any implementation of the
+//! same polynomial and points. Readers reject versions they don't know
+//! and reserved bytes that are not zero, so the layout can grow later
+//! without old readers misparsing it.
+
+use std::fmt;
+use std::error;
+
+use crate::recovery::crc32;
+
+pub const MAGIC: [u8; 4] = *b"GFSH";
+pub const VERSION: u8 = 1;
+/// x^8 + x^4 + x^3 + x^2 + 1, the polynomial of Gf256
+pub const FIELD_POLY: u16 = crate::POLYNOMIAL;
+pub const HEADER_LEN: usize = 24;
+
+/// Problems found while parsing a header
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum HeaderError {
+	/// Fewer bytes than the header and its stripe size announce
+	Truncated,
+	BadMagic,
+	UnsupportedVersion(u8),
+	/// The shard was coded over a different field
+	UnsupportedField(u16),
+	/// The index is not below k + m, or k is zero
+	InvalidGeometry,
+	/// The header or payload are damaged
+	ChecksumMismatch,
+	/// A reserved byte is set, by a newer writer
+	ReservedNotZero,
+}
+
+impl fmt::Display for HeaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			HeaderError::Truncated => f.write_str("truncated shard"),
+			HeaderError::BadMagic => f.write_str("not a shard header"),
+			HeaderError::UnsupportedVersion(v) => write!(f, "unsupported header version {}", v),
+			HeaderError::UnsupportedField(p) => write!(f, "unsupported field polynomial {:#06x}", p),
+			HeaderError::InvalidGeometry => f.write_str("invalid shard geometry"),
+			HeaderError::ChecksumMismatch => f.write_str("shard checksum mismatch"),
+			HeaderError::ReservedNotZero => f.write_str("reserved header bytes are set"),
+		}
+	}
+}
+
+impl error::Error for HeaderError {}
+
+/// The decoded fields of a header
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct ShardHeader {
+	pub data_shards: u16,
+	pub parity_shards: u16,
+	pub index: u16,
+	pub stripe_size: u32,
+}
+
+fn be16(b: &[u8]) -> u16 {
+	u16::from_be_bytes([b[0], b[1]])
+}
+
+impl ShardHeader {
+	/// Writes the header followed by payload. stripe_size is taken from
+	/// the payload.
+	pub fn frame(data_shards: u16, parity_shards: u16, index: u16, payload: &[u8]) -> Vec<u8> {
+		let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+		out.extend_from_slice(&MAGIC);
+		out.extend_from_slice(&[VERSION, 0]);
+		out.extend_from_slice(&FIELD_POLY.to_be_bytes());
+		out.extend_from_slice(&data_shards.to_be_bytes());
+		out.extend_from_slice(&parity_shards.to_be_bytes());
+		out.extend_from_slice(&index.to_be_bytes());
+		out.extend_from_slice(&[0, 0]);
+		out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+		out.extend_from_slice(payload);
+		let crc = crc32(&out);
+		out.splice(20..20, crc.to_be_bytes());
+		out
+	}
+
+	/// Parses and checks a framed shard, returning the header and payload.
+	/// Bytes after the payload are ignored.
+	pub fn parse(bytes: &[u8]) -> Result<(ShardHeader, &[u8]), HeaderError> {
+		if bytes.len() < 6 {
+			return Err(HeaderError::Truncated);
+		}
+		if bytes[..4] != MAGIC {
+			return Err(HeaderError::BadMagic);
+		}
+		if bytes[4] != VERSION {
+			return Err(HeaderError::UnsupportedVersion(bytes[4]));
+		}
+		if bytes.len() < HEADER_LEN {
+			return Err(HeaderError::Truncated);
+		}
+		let header = ShardHeader {
+			data_shards: be16(&bytes[8..]),
+			parity_shards: be16(&bytes[10..]),
+			index: be16(&bytes[12..]),
+			stripe_size: u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]),
+		};
+		let end = HEADER_LEN.checked_add(header.stripe_size as usize)
+			.filter(|&end| end <= bytes.len())
+			.ok_or(HeaderError::Truncated)?;
+		let mut covered = bytes[..20].to_vec();
+		covered.extend_from_slice(&bytes[HEADER_LEN..end]);
+		if crc32(&covered).to_be_bytes() != bytes[20..24] {
+			return Err(HeaderError::ChecksumMismatch);
+		}
+		if bytes[5] != 0 || bytes[14..16] != [0, 0] {
+			return Err(HeaderError::ReservedNotZero);
+		}
+		let poly = be16(&bytes[6..]);
+		if poly != FIELD_POLY {
+			return Err(HeaderError::UnsupportedField(poly));
+		}
+		if header.data_shards == 0 || header.index as u32 >= header.data_shards as u32 + header.parity_shards as u32 {
+			return Err(HeaderError::InvalidGeometry);
+		}
+		Ok((header, &bytes[HEADER_LEN..end]))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::erasure::{ ErasureCode, ErasureError };
+
+    #[test]
+    fn test_roundtrip() {
+        let framed = ShardHeader::frame(4, 2, 5, b"payload");
+        assert_eq!(framed.len(), HEADER_LEN + 7);
+        assert_eq!(&framed[..4], b"GFSH");
+        let (header, payload) = ShardHeader::parse(&framed).unwrap();
+        assert_eq!(header, ShardHeader { data_shards: 4, parity_shards: 2, index: 5, stripe_size: 7 });
+        assert_eq!(payload, b"payload");
+    }
+
+    #[test]
+    fn test_rejects() {
+        let framed = ShardHeader::frame(4, 2, 1, b"abc");
+        let mut bad = framed.clone();
+        bad[4] = 2;
+        assert_eq!(ShardHeader::parse(&bad), Err(HeaderError::UnsupportedVersion(2)));
+        let mut bad = framed.clone();
+        bad[HEADER_LEN + 1] ^= 1;
+        assert_eq!(ShardHeader::parse(&bad), Err(HeaderError::ChecksumMismatch));
+        assert_eq!(ShardHeader::parse(&framed[..HEADER_LEN + 2]), Err(HeaderError::Truncated));
+        assert_eq!(ShardHeader::parse(b"GFRR\x01\x00"), Err(HeaderError::BadMagic));
+        let invalid = ShardHeader::frame(4, 2, 6, b"abc");
+        assert_eq!(ShardHeader::parse(&invalid), Err(HeaderError::InvalidGeometry));
+
+        // Reserved bytes set, with a checksum that matches
+        for pos in [5, 14, 15] {
+            let mut bad = framed.clone();
+            bad[pos] = 1;
+            let mut covered = bad[..20].to_vec();
+            covered.extend_from_slice(&bad[HEADER_LEN..]);
+            bad[20..24].copy_from_slice(&crc32(&covered).to_be_bytes());
+            assert_eq!(ShardHeader::parse(&bad), Err(HeaderError::ReservedNotZero));
+        }
+    }
+
+    #[test]
+    fn test_framed_coding() {
+        let code = ErasureCode::new(3, 2).unwrap();
+        let data: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i * 7; 10]).collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let mut framed = code.encode_framed(&refs).unwrap();
+        assert_eq!(framed.len(), 5);
+
+        // A damaged shard counts as missing
+        framed[0][HEADER_LEN] ^= 0xff;
+        let given: Vec<&[u8]> = framed.iter().map(|f| &f[..]).collect();
+        assert_eq!(code.decode_framed(&given).unwrap(), data);
+        let given: Vec<&[u8]> = framed.iter().take(3).map(|f| &f[..]).collect();
+        assert_eq!(code.decode_framed(&given), Err(ErasureError::TooFewShards));
+
+        // Copies of a shard are harmless, conflicting copies are not
+        let mut given: Vec<&[u8]> = framed.iter().map(|f| &f[..]).collect();
+        given.push(&framed[1]);
+        assert_eq!(code.decode_framed(&given).unwrap(), data);
+        let forged = ShardHeader::frame(3, 2, 1, &[0xaa; 10]);
+        given.push(&forged);
+        assert_eq!(code.decode_framed(&given), Err(ErasureError::DuplicateShard(1)));
+
+        let other = ErasureCode::new(2, 3).unwrap();
+        let given: Vec<&[u8]> = framed.iter().map(|f| &f[..]).collect();
+        assert_eq!(other.decode_framed(&given), Err(ErasureError::InvalidHeader(HeaderError::InvalidGeometry)));
+    }
+}
@@ -0,0 +1,255 @@
+//! Building blocks for custom Reed-Solomon decoding flows.
+//!
+//! These expose the individual steps of errors-and-erasures decoding
+//! for codes built like rs::RsCodec (first consecutive root a^0).
+//! Codeword positions count from the first (highest degree) symbol, as
+//! everywhere in the rs module; the locator of position p in a codeword
+//! of length n is a^(n - 1 - p). Polynomials are returned lowest degree
+//! first.
+
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::bytes;
+use crate::trace::NoTrace;
+use super::{ alpha_pow, berlekamp_massey, euclid, syndromes_into };
+
+/// Multiplies two polynomials (lowest degree first)
+fn mul(a: &[Gf256], b: &[Gf256]) -> Vec<Gf256> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+	let mut res = vec![Gf256::zero(); a.len() + b.len() - 1];
+	for (i, &x) in a.iter().enumerate() {
+		for (j, &y) in b.iter().enumerate() {
+			res[i + j] += x * y;
+		}
+	}
+	res
+}
+
+fn eval(p: &[Gf256], x: Gf256) -> Gf256 {
+	p.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c)
+}
+
+/// Returns the locator a^(n - 1 - p) of position p
+pub fn position_locator(p: usize, n: usize) -> Gf256 {
+	alpha_pow(n - 1 - p)
+}
+
+/// Computes the nsym syndromes of codeword
+pub fn syndromes(codeword: &[u8], nsym: usize) -> Vec<Gf256> {
+	let mut syn = vec![Gf256::zero(); nsym];
+	syndromes_into(codeword, 0, &mut syn);
+	syn
+}
+
+/// The erasure locator prod (1 - X_i x) over the locators X_i of the
+/// erased positions
+pub fn erasure_locator(positions: &[usize], n: usize) -> Vec<Gf256> {
+	let mut loc = vec![Gf256::one()];
+	for &p in positions {
+		loc = mul(&loc, &[Gf256::one(), position_locator(p, n)]);
+	}
+	loc
+}
+
+/// Combines an erasure and an error locator into the errata locator
+pub fn combine_locators(erasure: &[Gf256], error: &[Gf256]) -> Vec<Gf256> {
+	mul(erasure, error)
+}
+
+/// Modified (Forney) syndromes: the coefficients rho..nsym of
+/// S(x) * erasure(x) mod x^nsym, where rho is the number of erasures.
+/// Running Berlekamp-Massey on them yields the locator of the remaining
+/// errors only.
+pub fn modified_syndromes(syndromes: &[Gf256], erasure: &[Gf256]) -> Vec<Gf256> {
+	let nsym = syndromes.len();
+	let rho = erasure.len() - 1;
+	let mut t = mul(syndromes, erasure);
+	t.truncate(nsym);
+	if rho >= t.len() {
+		return Vec::new();
+	}
+	t.split_off(rho)
+}
+
+/// Finds the error locator for the given (possibly modified) syndromes
+/// with Berlekamp-Massey
+pub fn error_locator(syndromes: &[Gf256]) -> Vec<Gf256> {
+	let len = syndromes.len() + 1;
+	let mut lambda = vec![Gf256::zero(); len];
+	let mut prev = vec![Gf256::zero(); len];
+	let mut tmp = vec![Gf256::zero(); len];
+	let l = berlekamp_massey(syndromes, &mut lambda, &mut prev, &mut tmp, &mut NoTrace);
+	lambda.truncate(l + 1);
+	lambda
+}
+
+/// error_locator by Sugiyama's extended Euclidean algorithm instead, for
+/// cross-checking. Both return the same locator whenever the errors are
+/// correctable.
+pub fn error_locator_euclid(syndromes: &[Gf256]) -> Vec<Gf256> {
+	let len = syndromes.len() + 1;
+	let mut lambda = vec![Gf256::zero(); len];
+	let mut prev = vec![Gf256::zero(); len];
+	let mut tmp = vec![Gf256::zero(); len];
+	let mut omega = vec![Gf256::zero(); len];
+	let l = euclid(syndromes, &mut lambda, &mut prev, &mut tmp, &mut omega, &mut NoTrace);
+	lambda.truncate(l + 1);
+	lambda
+}
+
+/// The errata evaluator S(x) * locator(x) mod x^nsym
+pub fn error_evaluator(syndromes: &[Gf256], locator: &[Gf256]) -> Vec<Gf256> {
+	let mut omega = mul(syndromes, locator);
+	omega.truncate(syndromes.len());
+	omega
+}
+
+/// Returns the positions below n whose locator inverse is a root of
+/// locator
+pub fn chien_search(locator: &[Gf256], n: usize) -> Vec<usize> {
+	(0..n).filter(|&p| {
+		let xinv = alpha_pow(255 - (n - 1 - p) % 255);
+		eval(locator, xinv).poly == 0
+	}).collect()
+}
+
+/// Chien search over all positions at once, built on the bulk kernels.
+///
+/// For a code of length n, row j of the plan holds X_p^-j for every
+/// position p. Evaluating a locator then takes one fused multiply-add
+/// pass over n bytes per coefficient instead of a Horner loop per
+/// position, so the work runs through bytes::mul_add and benefits from
+/// whatever vector implementation backs it.
+#[derive(Clone,Debug)]
+pub struct ChienSearch {
+	n: usize,
+	/// rows[j - 1][p] = X_p^-j
+	rows: Vec<Vec<u8>>,
+}
+
+impl ChienSearch {
+	/// Prepares a search for locators of degree up to max_degree in
+	/// codewords of length n
+	pub fn new(n: usize, max_degree: usize) -> ChienSearch {
+		assert!(n <= 255, "Codeword too long");
+		let first: Vec<u8> = (0..n).map(|p| alpha_pow(255 - (n - 1 - p) % 255).to_byte()).collect();
+		let mut rows = Vec::with_capacity(max_degree);
+		if max_degree > 0 {
+			rows.push(first.clone());
+		}
+		for j in 1..max_degree {
+			let mut row = rows[j - 1].clone();
+			bytes::mul_elementwise(&mut row, &first);
+			rows.push(row);
+		}
+		ChienSearch { n, rows }
+	}
+
+	/// Returns the positions whose locator inverse is a root of locator,
+	/// like chien_search. Panics if the locator's degree exceeds the
+	/// prepared maximum.
+	pub fn roots(&self, locator: &[Gf256]) -> Vec<usize> {
+		let mut acc = vec![0u8; self.n];
+		self.evaluate(locator, &mut acc);
+		acc.iter().enumerate().filter(|&(_, &v)| v == 0).map(|(p, _)| p).collect()
+	}
+
+	/// Evaluates locator at X_p^-1 for every position p into out
+	pub fn evaluate(&self, locator: &[Gf256], out: &mut [u8]) {
+		assert!(locator.len() <= self.rows.len() + 1, "Locator degree too large");
+		let c0 = locator.first().map(|c| c.to_byte()).unwrap_or(0);
+		for o in out.iter_mut() {
+			*o = c0;
+		}
+		for (row, c) in self.rows.iter().zip(locator.iter().skip(1)) {
+			bytes::mul_add(out, row, c.to_byte());
+		}
+	}
+}
+
+/// Forney's formula: the errata magnitudes at the given positions, or
+/// None if the locator's derivative vanishes at one of them (which means
+/// the positions are not roots of a valid locator).
+pub fn forney(evaluator: &[Gf256], locator: &[Gf256], positions: &[usize], n: usize)
+	-> Option<Vec<Gf256>> {
+	// Formal derivative: only odd powers survive in characteristic 2
+	let deriv: Vec<Gf256> = locator.iter().enumerate().skip(1)
+		.map(|(i, &c)| if i % 2 == 1 { c } else { Gf256::zero() })
+		.collect();
+	positions.iter().map(|&p| {
+		let x = position_locator(p, n);
+		let xinv = x.inv().unwrap();
+		let den = eval(&deriv, xinv);
+		if den.poly == 0 {
+			None
+		} else {
+			Some(x * eval(evaluator, xinv) / den)
+		}
+	}).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::RsCodec;
+
+    #[test]
+    fn test_erasure_locator_roots() {
+        let loc = erasure_locator(&[3, 7, 19], 20);
+        assert_eq!(loc.len(), 4);
+        assert_eq!(chien_search(&loc, 20), vec![3, 7, 19]);
+    }
+
+    #[test]
+    fn test_batched_chien() {
+        let positions = [0, 2, 100, 200, 254];
+        let loc = erasure_locator(&positions, 255);
+        let search = ChienSearch::new(255, 8);
+        assert_eq!(search.roots(&loc), chien_search(&loc, 255));
+        assert_eq!(search.roots(&loc), positions.to_vec());
+
+        // Shortened code
+        let loc = erasure_locator(&[4, 9], 12);
+        assert_eq!(ChienSearch::new(12, 2).roots(&loc), vec![4, 9]);
+    }
+
+    #[test]
+    fn test_errors_and_erasures_flow() {
+        let codec = RsCodec::<40, 30>::new();
+        let mut original = [0u8; 40];
+        for (i, b) in original[..30].iter_mut().enumerate() {
+            *b = (i * 13 + 1) as u8;
+        }
+        codec.encode(&mut original);
+
+        // 4 erasures (known positions) and 3 errors: 4 + 2 * 3 <= 10
+        let erasures = [1, 8, 22, 39];
+        let mut cw = original;
+        for &p in &erasures {
+            cw[p] = 0;
+        }
+        cw[5] ^= 0x11;
+        cw[17] ^= 0x22;
+        cw[33] ^= 0x44;
+
+        let syn = syndromes(&cw, 10);
+        let gamma = erasure_locator(&erasures, 40);
+        let lambda = error_locator(&modified_syndromes(&syn, &gamma));
+        assert_eq!(chien_search(&lambda, 40), vec![5, 17, 33]);
+        assert_eq!(error_locator_euclid(&modified_syndromes(&syn, &gamma)), lambda);
+
+        let psi = combine_locators(&gamma, &lambda);
+        let positions = chien_search(&psi, 40);
+        assert_eq!(positions.len(), 7);
+        let omega = error_evaluator(&syn, &psi);
+        let magnitudes = forney(&omega, &psi, &positions, 40).unwrap();
+        for (&p, e) in positions.iter().zip(magnitudes) {
+            cw[p] ^= e.to_byte();
+        }
+        assert_eq!(cw, original);
+    }
+}
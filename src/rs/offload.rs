@@ -0,0 +1,137 @@
+//! Hook for running the bulk work of erasure coding elsewhere.
+//!
+//! Everything ErasureCode does per byte is a linear combination of shards,
+//! so it is handed to an Offload backend as a batch of Jobs. Cpu runs them
+//! with the bytes kernels; a GPU or FPGA backend implements the same trait
+//! in its own crate and is passed to ErasureCode::encode_offload or
+//! reconstruct_offload. Plans, shard bookkeeping and error checking stay
+//! in this crate.
+
+use std::fmt;
+use std::error;
+
+use crate::Gf256;
+use crate::bytes;
+
+/// Errors a backend may report
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum OffloadError {
+	/// The backend cannot run a job of this kind or size
+	Unsupported,
+	/// The device failed, with a backend specific code
+	Device(u32),
+}
+
+impl fmt::Display for OffloadError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			OffloadError::Unsupported => f.write_str("job not supported by the backend"),
+			OffloadError::Device(code) => write!(f, "device error {}", code),
+		}
+	}
+}
+
+impl error::Error for OffloadError {}
+
+/// One unit of work. All slices of a job have the same length.
+#[derive(Debug)]
+pub enum Job<'a> {
+	/// dst = dst + c * src
+	Axpy { dst: &'a mut [u8], src: &'a [u8], c: Gf256 },
+	/// outputs[r] = sum_i matrix[r][i] * inputs[i], overwriting outputs
+	Matrix { matrix: &'a [Vec<Gf256>], inputs: Vec<&'a [u8]>, outputs: Vec<&'a mut [u8]> },
+}
+
+/// A backend executing batches of jobs
+pub trait Offload {
+	/// Runs every job of the batch. Jobs of one batch touch disjoint
+	/// outputs, so they may run in any order or concurrently.
+	fn submit(&self, jobs: &mut [Job]) -> Result<(), OffloadError>;
+}
+
+/// Reference backend running on the calling thread
+#[derive(Copy,Clone,PartialEq,Eq,Debug,Default)]
+pub struct Cpu;
+
+impl Offload for Cpu {
+	fn submit(&self, jobs: &mut [Job]) -> Result<(), OffloadError> {
+		for job in jobs.iter_mut() {
+			match job {
+				Job::Axpy { dst, src, c } => bytes::mul_add(dst, src, c.to_byte()),
+				Job::Matrix { matrix, inputs, outputs } => {
+					for (row, out) in matrix.iter().zip(outputs.iter_mut()) {
+						bytes::mul_scalar(out, 0);
+						for (c, input) in row.iter().zip(inputs.iter()) {
+							bytes::mul_add(out, input, c.to_byte());
+						}
+					}
+				},
+			}
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use crate::rs::erasure::{ ErasureCode, ErasureError };
+
+    /// Counts the submitted jobs and delegates to Cpu
+    struct Counting {
+        jobs: Cell<usize>,
+        fail: bool,
+    }
+
+    impl Offload for Counting {
+        fn submit(&self, jobs: &mut [Job]) -> Result<(), OffloadError> {
+            if self.fail {
+                return Err(OffloadError::Device(7));
+            }
+            self.jobs.set(self.jobs.get() + jobs.len());
+            Cpu.submit(jobs)
+        }
+    }
+
+    fn data(k: usize) -> Vec<Vec<u8>> {
+        (0..k).map(|i| (0..32).map(|j| (i * 29 + j * 3) as u8).collect()).collect()
+    }
+
+    #[test]
+    fn test_axpy() {
+        let mut dst = [1u8, 2];
+        Cpu.submit(&mut [Job::Axpy { dst: &mut dst, src: &[3, 0], c: Gf256::from_byte(2) }]).unwrap();
+        assert_eq!(dst, [1 ^ 6, 2]);
+    }
+
+    #[test]
+    fn test_encode_matches() {
+        let code = ErasureCode::new(5, 3).unwrap();
+        let data = data(5);
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let mut expected = vec![vec![0u8; 32]; 3];
+        let mut out: Vec<&mut [u8]> = expected.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode(&refs, &mut out).unwrap();
+
+        let backend = Counting { jobs: Cell::new(0), fail: false };
+        let mut parity = vec![vec![0u8; 32]; 3];
+        let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode_offload(&refs, &mut out, &backend).unwrap();
+        assert_eq!(parity, expected);
+        assert_eq!(backend.jobs.get(), 1);
+
+        let mut shards: Vec<Option<Vec<u8>>> = data.into_iter().chain(parity).map(Some).collect();
+        let original = shards.clone();
+        shards[0] = None;
+        shards[6] = None;
+        code.reconstruct_offload(&mut shards, &backend).unwrap();
+        assert_eq!(shards, original);
+
+        shards[1] = None;
+        let broken = Counting { jobs: Cell::new(0), fail: true };
+        assert_eq!(code.reconstruct_offload(&mut shards, &broken),
+            Err(ErasureError::Offload(OffloadError::Device(7))));
+    }
+}
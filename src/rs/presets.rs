@@ -0,0 +1,187 @@
+//! Parameters of standard Reed-Solomon codes.
+//!
+//! Every code here is a shortened RS(255, 255 - (n - k)) code: the
+//! leading 255 - n message symbols are taken to be zero and not sent.
+//! The generator has the n - k roots a^(root_spacing (fcr + i)), where a
+//! is x in the field of the given polynomial.
+//!
+//! - CCSDS: RS(255, 223) over 0x187 with roots a^(11 j) for j from 112,
+//!   symbols in Berlekamp's dual basis; see ccsds.
+//! - DVB (EN 300 421): RS(204, 188), shortened by 51 from RS(255, 239).
+//! - OTN (ITU-T G.709 annex A): RS(255, 239).
+//! - CD CIRC (IEC 60908): C1 is RS(32, 28) and C2 RS(28, 24). C2 puts
+//!   its four Q parity symbols between the two halves of the data,
+//!   which encode_circ_c2 handles. The delays and interleaving of CIRC
+//!   are not part of the codes.
+//!
+//! All but CCSDS use the field, generator and first consecutive root of
+//! RsCodec, so the aliases below are the codes themselves.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::POLYNOMIAL;
+use crate::rs::RsCodec;
+use crate::rs::ccsds;
+
+/// The parameters of one code
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Preset {
+	pub name: &'static str,
+	pub n: usize,
+	pub k: usize,
+	/// The field polynomial, bit i for x^i
+	pub field_polynomial: u16,
+	/// First consecutive root, as a power of a^root_spacing
+	pub fcr: usize,
+	pub root_spacing: usize,
+	/// Whether symbols are sent in Berlekamp's dual basis
+	pub dual_basis: bool,
+	/// Index of the first parity symbol in a codeword
+	pub parity_offset: usize,
+}
+
+impl Preset {
+	pub const fn parity_symbols(&self) -> usize {
+		self.n - self.k
+	}
+
+	/// Number of zero symbols left out of the full length code
+	pub const fn shortening(&self) -> usize {
+		255 - self.n
+	}
+
+	/// Whether RsCodec<n, k> codes the preset as it is
+	pub const fn is_native(&self) -> bool {
+		self.field_polynomial == POLYNOMIAL && self.fcr == 0 && self.root_spacing == 1
+			&& !self.dual_basis && self.parity_offset == self.k
+	}
+
+	/// The generator polynomial, highest degree first, with coefficients
+	/// in the field of the preset in its polynomial basis
+	#[cfg(feature = "alloc")]
+	pub fn generator(&self) -> Vec<u8> {
+		let mul = |mut a: u8, mut b: u8| {
+			let mut r = 0;
+			while b != 0 {
+				if b & 1 == 1 {
+					r ^= a;
+				}
+				b >>= 1;
+				a = (a << 1) ^ if a & 0x80 != 0 { self.field_polynomial as u8 } else { 0 };
+			}
+			r
+		};
+		let nsym = self.parity_symbols();
+		let mut gen = alloc::vec![0u8; nsym + 1];
+		gen[0] = 1;
+		for i in 0..nsym {
+			let e = self.root_spacing * (self.fcr + i) % 255;
+			let root = (0..e).fold(1, |p, _| mul(p, 2));
+			for j in (1..=i + 1).rev() {
+				gen[j] ^= mul(gen[j - 1], root);
+			}
+		}
+		gen
+	}
+}
+
+pub const CCSDS: Preset = Preset {
+	name: "CCSDS", n: ccsds::N, k: ccsds::K,
+	field_polynomial: ccsds::FIELD_POLYNOMIAL, fcr: ccsds::FIRST_ROOT, root_spacing: ccsds::ROOT_SPACING,
+	dual_basis: true, parity_offset: ccsds::K,
+};
+
+pub const DVB: Preset = Preset {
+	name: "DVB", n: 204, k: 188,
+	field_polynomial: POLYNOMIAL, fcr: 0, root_spacing: 1,
+	dual_basis: false, parity_offset: 188,
+};
+
+pub const OTN: Preset = Preset {
+	name: "OTN", n: 255, k: 239,
+	field_polynomial: POLYNOMIAL, fcr: 0, root_spacing: 1,
+	dual_basis: false, parity_offset: 239,
+};
+
+pub const CIRC_C1: Preset = Preset {
+	name: "CIRC C1", n: 32, k: 28,
+	field_polynomial: POLYNOMIAL, fcr: 0, root_spacing: 1,
+	dual_basis: false, parity_offset: 28,
+};
+
+pub const CIRC_C2: Preset = Preset {
+	name: "CIRC C2", n: 28, k: 24,
+	field_polynomial: POLYNOMIAL, fcr: 0, root_spacing: 1,
+	dual_basis: false, parity_offset: 12,
+};
+
+pub const ALL: [Preset; 5] = [CCSDS, DVB, OTN, CIRC_C1, CIRC_C2];
+
+pub type DvbCodec = RsCodec<204, 188>;
+pub type OtnCodec = RsCodec<255, 239>;
+pub type CircC1Codec = RsCodec<32, 28>;
+/// Decodes C2 blocks in their order on the disc; encode with
+/// encode_circ_c2
+pub type CircC2Codec = RsCodec<28, 24>;
+
+/// Computes the Q parity of a C2 block into block[12..16] from the data
+/// in block[..12] and block[16..]
+pub fn encode_circ_c2(block: &mut [u8; 28]) {
+	// The full length code is cyclic. Rotating the last 12 data symbols
+	// to the front of the unshortened codeword leaves the parity last.
+	let mut word = [0u8; 255];
+	word[..12].copy_from_slice(&block[16..]);
+	word[239..251].copy_from_slice(&block[..12]);
+	RsCodec::<255, 251>::new().encode(&mut word);
+	block[12..16].copy_from_slice(&word[251..]);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::Scratch;
+
+    #[test]
+    fn test_presets() {
+        for p in ALL {
+            assert_eq!(p.parity_symbols() + p.k, p.n, "{}", p.name);
+            assert_eq!(p.is_native(), !matches!(p.name, "CCSDS" | "CIRC C2"));
+        }
+        assert_eq!(DVB.shortening(), 51);
+        assert_eq!(OTN.shortening(), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_generators() {
+        use crate::rs::encoder::RsEncoder;
+        for p in [DVB, OTN, CIRC_C1, CIRC_C2] {
+            let expected: Vec<u8> = RsEncoder::new(p.n, p.k).unwrap().generator().iter().map(|g| g.to_byte()).collect();
+            assert_eq!(p.generator(), expected, "{}", p.name);
+        }
+        let ccsds: Vec<u8> = ccsds::GENERATOR.iter().map(|&g| ccsds::CONVENTIONAL.coordinates(g)).collect();
+        assert_eq!(CCSDS.generator(), ccsds);
+    }
+
+    #[test]
+    fn test_circ_c2() {
+        let mut block = [0u8; 28];
+        for (i, b) in block.iter_mut().enumerate() {
+            *b = (i * 41 + 7) as u8;
+        }
+        let data = block;
+        encode_circ_c2(&mut block);
+        assert_eq!(block[..12], data[..12]);
+        assert_eq!(block[16..], data[16..]);
+        let mut scratch = Scratch::new();
+        let codec = CircC2Codec::new();
+        assert!(codec.is_valid(&block, &mut scratch));
+        let good = block;
+        block[3] ^= 0x40;
+        block[13] ^= 0x11;
+        assert_eq!(codec.decode(&mut block, &mut scratch), Ok(2));
+        assert_eq!(block, good);
+    }
+}
@@ -0,0 +1,220 @@
+//! Product codes of two Reed-Solomon codes.
+//!
+//! The data is a k2 x k1 array. Every row is extended to n1 symbols with
+//! the row code RS(n1, k1), then every one of the n1 columns to n2 symbols
+//! with the column code RS(n2, k2). The parity rows at the bottom are
+//! row codewords as well, so every row and every column of the n2 x n1
+//! block is a codeword; the minimum distance is the product of those of
+//! the two codes. Blocks are stored row by row.
+//!
+//! decode alternates error decoding of the rows and of the columns, so
+//! each pass works on what the previous one left: a burst that wipes out
+//! part of a row is spread over many columns, and columns fix what
+//! rows could not. When the errors-only passes get stuck, the rows that
+//! are still invalid are erased in every column, which fills in up to
+//! n2 - k2 bad rows as long as all other rows are right.
+
+use alloc::{ vec, vec::Vec };
+
+use super::{ ConfigError, RsError };
+use super::encoder::RsEncoder;
+
+/// An RS(n1, k1) x RS(n2, k2) product code
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ProductCode {
+	row: RsEncoder,
+	col: RsEncoder,
+}
+
+impl ProductCode {
+	/// Rows use RS(row_n, row_k) and columns RS(col_n, col_k)
+	pub fn new(row_n: usize, row_k: usize, col_n: usize, col_k: usize) -> Result<ProductCode, ConfigError> {
+		Ok(ProductCode { row: RsEncoder::new(row_n, row_k)?, col: RsEncoder::new(col_n, col_k)? })
+	}
+
+	pub fn row_code(&self) -> &RsEncoder {
+		&self.row
+	}
+
+	pub fn column_code(&self) -> &RsEncoder {
+		&self.col
+	}
+
+	/// Data symbols of a block, k1 * k2
+	pub fn data_len(&self) -> usize {
+		self.row.k() * self.col.k()
+	}
+
+	/// Symbols of a block, n1 * n2
+	pub fn block_len(&self) -> usize {
+		self.row.n() * self.col.n()
+	}
+
+	fn column(&self, block: &[u8], c: usize) -> Vec<u8> {
+		block.iter().skip(c).step_by(self.row.n()).copied().collect()
+	}
+
+	fn set_column(&self, block: &mut [u8], c: usize, col: &[u8]) {
+		for (b, &v) in block.iter_mut().skip(c).step_by(self.row.n()).zip(col) {
+			*b = v;
+		}
+	}
+
+	/// Encodes k1 * k2 data symbols, given row by row, into a block
+	pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, RsError> {
+		if data.len() != self.data_len() {
+			return Err(RsError::InvalidLength);
+		}
+		let (n1, k1) = (self.row.n(), self.row.k());
+		let mut block = vec![0u8; self.block_len()];
+		for (row, msg) in block.chunks_exact_mut(n1).zip(data.chunks_exact(k1)) {
+			row[..k1].copy_from_slice(msg);
+			self.row.encode_into(msg, &mut row[k1..]);
+		}
+		let k2 = self.col.k();
+		for c in 0..n1 {
+			let mut col = self.column(&block, c);
+			let (msg, parity) = col.split_at_mut(k2);
+			self.col.encode_into(msg, parity);
+			self.set_column(&mut block, c, &col);
+		}
+		Ok(block)
+	}
+
+	/// The data symbols of a block, row by row
+	pub fn data(&self, block: &[u8]) -> Vec<u8> {
+		let (n1, k1) = (self.row.n(), self.row.k());
+		block.chunks_exact(n1).take(self.col.k()).flat_map(|r| &r[..k1]).copied().collect()
+	}
+
+	/// Whether every row and column is a codeword
+	pub fn is_valid(&self, block: &[u8]) -> bool {
+		block.len() == self.block_len()
+			&& block.chunks_exact(self.row.n()).all(|r| self.row.is_valid(r))
+			&& (0..self.row.n()).all(|c| self.col.is_valid(&self.column(block, c)))
+	}
+
+	/// One errors-only pass over the rows or the columns. Returns the
+	/// symbols corrected and the lines that could not be decoded, leaving
+	/// those unchanged.
+	fn pass(&self, block: &mut [u8], rows: bool) -> (usize, Vec<usize>) {
+		let (mut corrected, mut failed) = (0, vec![]);
+		let lines = if rows { self.col.n() } else { self.row.n() };
+		for i in 0..lines {
+			let mut line = if rows {
+				block[i * self.row.n()..][..self.row.n()].to_vec()
+			} else {
+				self.column(block, i)
+			};
+			let code = if rows { &self.row } else { &self.col };
+			match code.decode(&mut line) {
+				Ok(0) => {}
+				Ok(n) => {
+					corrected += n;
+					if rows {
+						block[i * self.row.n()..][..self.row.n()].copy_from_slice(&line);
+					} else {
+						self.set_column(block, i, &line);
+					}
+				}
+				Err(_) => failed.push(i),
+			}
+		}
+		(corrected, failed)
+	}
+
+	/// Corrects the block in place by iterative row and column decoding,
+	/// at most max_passes of each, and returns the number of corrected
+	/// symbols. Fails if the block is still not valid in the end, leaving
+	/// it partly corrected.
+	pub fn decode(&self, block: &mut [u8], max_passes: usize) -> Result<usize, RsError> {
+		if block.len() != self.block_len() {
+			return Err(RsError::InvalidLength);
+		}
+		let mut total = 0;
+		let mut bad_rows = vec![];
+		for _ in 0..max_passes {
+			let (rc, rows) = self.pass(block, true);
+			let (cc, cols) = self.pass(block, false);
+			total += rc + cc;
+			bad_rows = rows;
+			if cols.is_empty() && self.is_valid(block) {
+				return Ok(total);
+			}
+			if rc + cc == 0 {
+				break;
+			}
+		}
+
+		// Erase the rows that failed last in every column
+		bad_rows.retain(|&r| !self.row.is_valid(&block[r * self.row.n()..][..self.row.n()]));
+		if bad_rows.is_empty() || bad_rows.len() > self.col.n() - self.col.k() {
+			return Err(RsError::TooManyErrors);
+		}
+		for c in 0..self.row.n() {
+			let mut col = self.column(block, c);
+			let before = col.clone();
+			self.col.decode_erasures(&mut col, &bad_rows)?;
+			total += col.iter().zip(&before).filter(|(a, b)| a != b).count();
+			self.set_column(block, c, &col);
+		}
+		if !self.is_valid(block) {
+			return Err(RsError::TooManyErrors);
+		}
+		Ok(total)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 73 + i / 9) as u8).collect()
+    }
+
+    #[test]
+    fn test_encode() {
+        let code = ProductCode::new(12, 8, 10, 6).unwrap();
+        let d = data(code.data_len());
+        let block = code.encode(&d).unwrap();
+        assert_eq!(block.len(), 120);
+        assert!(code.is_valid(&block));
+        assert_eq!(code.data(&block), d);
+        // The parity rows are row codewords too
+        for row in block.chunks_exact(12).skip(6) {
+            assert!(code.row_code().is_valid(row));
+        }
+        assert_eq!(code.encode(&d[1..]), Err(RsError::InvalidLength));
+    }
+
+    #[test]
+    fn test_decode() {
+        let code = ProductCode::new(15, 11, 15, 11).unwrap();
+        let block = code.encode(&data(code.data_len())).unwrap();
+
+        // Random errors: three in one row, which rows alone cannot fix
+        let mut r = block.clone();
+        for p in [0, 1, 2, 40, 100, 170, 222] {
+            r[p] ^= 0x5c;
+        }
+        assert_eq!(code.decode(&mut r, 4), Ok(7));
+        assert_eq!(r, block);
+
+        // A burst over four whole rows, beyond both codes alone
+        let mut r = block.clone();
+        for (p, s) in r.iter_mut().enumerate().take(90).skip(30) {
+            *s ^= (p as u8) | 1;
+        }
+        assert!(code.decode(&mut r, 4).is_ok());
+        assert_eq!(r, block);
+
+        let mut r = block.clone();
+        for s in &mut r[..100] {
+            *s ^= 0xff;
+        }
+        assert_eq!(code.decode(&mut r, 4), Err(RsError::TooManyErrors));
+        assert_eq!(code.decode(&mut r[1..], 4), Err(RsError::InvalidLength));
+    }
+}
@@ -0,0 +1,262 @@
+//! Background reconstruction of missing shards.
+//!
+//! A Repair rebuilds the missing shards of one stripe a chunk of byte
+//! positions at a time. run paces the chunks so that no more than
+//! bytes_per_second survivor bytes are read on average, and reports
+//! progress after every chunk to a callback, which may cancel. A
+//! cancelled repair keeps its state and continues where it stopped on
+//! the next call to run; the rebuilt shards are only stored into the
+//! shard slots once every chunk is done.
+//!
+//! Time is taken from a Clock, so daemons can share their own timer and
+//! tests need not sleep.
+
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use super::erasure::{ ErasureCode, ErasureError, Plan };
+
+/// Time source used for throttling
+pub trait Clock {
+	/// Time elapsed since an arbitrary fixed point
+	fn now(&self) -> Duration;
+	fn sleep(&self, d: Duration);
+}
+
+/// The system clock
+#[derive(Copy,Clone,Debug)]
+pub struct SystemClock {
+	start: Instant,
+}
+
+impl SystemClock {
+	pub fn new() -> SystemClock {
+		SystemClock { start: Instant::now() }
+	}
+}
+
+impl Default for SystemClock {
+	fn default() -> SystemClock {
+		SystemClock::new()
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&self) -> Duration {
+		self.start.elapsed()
+	}
+
+	fn sleep(&self, d: Duration) {
+		thread::sleep(d)
+	}
+}
+
+/// How a repair is paced
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct RepairOptions {
+	/// Read budget, None for as fast as possible
+	pub bytes_per_second: Option<u64>,
+	/// Byte positions rebuilt per step
+	pub chunk_size: usize,
+}
+
+impl Default for RepairOptions {
+	fn default() -> RepairOptions {
+		RepairOptions { bytes_per_second: None, chunk_size: 64 * 1024 }
+	}
+}
+
+/// Reported after every chunk
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct RepairProgress {
+	/// Byte positions rebuilt so far
+	pub done: usize,
+	/// Shard size
+	pub total: usize,
+	/// Survivor bytes read so far
+	pub bytes_read: u64,
+}
+
+/// Returned by the progress callback
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Control {
+	Continue,
+	Cancel,
+}
+
+/// Result of run
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum RepairStatus {
+	/// Every missing shard has been stored
+	Complete,
+	/// The callback cancelled; run again to resume
+	Cancelled(RepairProgress),
+}
+
+/// An incremental reconstruction of one stripe
+pub struct Repair<'a> {
+	plan: Plan,
+	shards: &'a mut [Option<Vec<u8>>],
+	size: usize,
+	/// Rebuilt shards, in the order of plan.targets()
+	rebuilt: Vec<Vec<u8>>,
+	done: usize,
+	bytes_read: u64,
+}
+
+impl<'a> Repair<'a> {
+	/// Prepares the reconstruction of every None shard
+	pub fn new(code: &ErasureCode, shards: &'a mut [Option<Vec<u8>>]) -> Result<Repair<'a>, ErasureError> {
+		if shards.len() != code.total_shards() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let present: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_some()).collect();
+		let missing: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_none()).collect();
+		let plan = code.plan(&present, &missing)?;
+		let size = shards[plan.survivors()[0]].as_ref().map_or(0, |s| s.len());
+		if plan.survivors().iter().any(|&s| shards[s].as_ref().is_some_and(|s| s.len() != size)) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		let rebuilt = vec![vec![0u8; size]; missing.len()];
+		Ok(Repair { plan, shards, size, rebuilt, done: 0, bytes_read: 0 })
+	}
+
+	pub fn progress(&self) -> RepairProgress {
+		RepairProgress { done: self.done, total: self.size, bytes_read: self.bytes_read }
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.done == self.size && self.rebuilt.is_empty()
+	}
+
+	/// Rebuilds the next up to len byte positions. Returns true once the
+	/// repair is complete and the shards are stored.
+	pub fn step(&mut self, len: usize) -> bool {
+		if self.is_complete() {
+			return true;
+		}
+		let (start, end) = (self.done, (self.done + len.max(1)).min(self.size));
+		{
+			let inputs: Vec<&[u8]> = self.plan.survivors().iter()
+				.map(|&s| &self.shards[s].as_ref().expect("Survivor present")[start..end])
+				.collect();
+			for (t, out) in self.rebuilt.iter_mut().enumerate() {
+				self.plan.apply(t, &inputs, &mut out[start..end]);
+			}
+		}
+		self.bytes_read += ((end - start) * self.plan.survivors().len()) as u64;
+		self.done = end;
+		if self.done == self.size {
+			for (&t, shard) in self.plan.targets().iter().zip(self.rebuilt.drain(..)) {
+				self.shards[t] = Some(shard);
+			}
+			return true;
+		}
+		false
+	}
+
+	/// Runs chunks until complete or cancelled, sleeping on clock to keep
+	/// within the read budget. The budget is measured from the start of
+	/// this call.
+	pub fn run<C, F>(&mut self, opts: RepairOptions, clock: &C, mut progress: F) -> RepairStatus
+		where C: Clock + ?Sized, F: FnMut(RepairProgress) -> Control {
+		let (start, read_before) = (clock.now(), self.bytes_read);
+		while !self.step(opts.chunk_size) {
+			if let Some(rate) = opts.bytes_per_second.filter(|&r| r > 0) {
+				let read = self.bytes_read - read_before;
+				let due = Duration::from_secs_f64(read as f64 / rate as f64);
+				let elapsed = clock.now().saturating_sub(start);
+				if due > elapsed {
+					clock.sleep(due - elapsed);
+				}
+			}
+			if progress(self.progress()) == Control::Cancel {
+				return RepairStatus::Cancelled(self.progress());
+			}
+		}
+		progress(self.progress());
+		RepairStatus::Complete
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Advances only when slept on
+    struct FakeClock {
+        now: Cell<Duration>,
+        slept: Cell<Duration>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+
+        fn sleep(&self, d: Duration) {
+            self.now.set(self.now.get() + d);
+            self.slept.set(self.slept.get() + d);
+        }
+    }
+
+    fn stripe(code: &ErasureCode, size: usize) -> Vec<Option<Vec<u8>>> {
+        let data: Vec<Vec<u8>> = (0..code.data_shards())
+            .map(|i| (0..size).map(|j| (i * 31 + j * 7) as u8).collect())
+            .collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let mut parity = vec![vec![0u8; size]; code.parity_shards()];
+        let mut out: Vec<&mut [u8]> = parity.iter_mut().map(|p| &mut p[..]).collect();
+        code.encode(&refs, &mut out).unwrap();
+        data.into_iter().chain(parity).map(Some).collect()
+    }
+
+    #[test]
+    fn test_throttled() {
+        let code = ErasureCode::new(4, 2).unwrap();
+        let original = stripe(&code, 1000);
+        let mut shards = original.clone();
+        shards[1] = None;
+        shards[4] = None;
+        let clock = FakeClock { now: Cell::new(Duration::ZERO), slept: Cell::new(Duration::ZERO) };
+        let opts = RepairOptions { bytes_per_second: Some(2000), chunk_size: 100 };
+        let mut reports = Vec::new();
+        let mut repair = Repair::new(&code, &mut shards).unwrap();
+        let status = repair.run(opts, &clock, |p| { reports.push(p.done); Control::Continue });
+        assert_eq!(status, RepairStatus::Complete);
+        assert_eq!(reports, (1..=10).map(|i| i * 100).collect::<Vec<_>>());
+        // 4000 survivor bytes at 2000 per second; the last chunk needs no wait
+        assert_eq!(clock.slept.get(), Duration::from_millis(1800));
+        assert_eq!(shards, original);
+    }
+
+    #[test]
+    fn test_cancel_and_resume() {
+        let code = ErasureCode::new(3, 3).unwrap();
+        let original = stripe(&code, 50);
+        let mut shards = original.clone();
+        shards[0] = None;
+        let clock = SystemClock::new();
+        let opts = RepairOptions { bytes_per_second: None, chunk_size: 20 };
+        {
+            let mut repair = Repair::new(&code, &mut shards).unwrap();
+            let status = repair.run(opts, &clock, |_| Control::Cancel);
+            assert_eq!(status, RepairStatus::Cancelled(RepairProgress { done: 20, total: 50, bytes_read: 60 }));
+            assert!(!repair.is_complete());
+            assert_eq!(repair.run(opts, &clock, |_| Control::Continue), RepairStatus::Complete);
+            assert!(repair.is_complete());
+        }
+        assert_eq!(shards, original);
+    }
+
+    #[test]
+    fn test_too_few() {
+        let code = ErasureCode::new(3, 1).unwrap();
+        let mut shards = stripe(&code, 10);
+        shards[0] = None;
+        shards[1] = None;
+        assert_eq!(Repair::new(&code, &mut shards).err(), Some(ErasureError::TooFewShards));
+    }
+}
@@ -0,0 +1,157 @@
+//! Shard oriented erasure coding.
+//!
+//! ReedSolomon follows the shard model common to erasure coding
+//! libraries: a code with d data and p parity shards works on a slice of
+//! d + p equally long shards, data first. encode fills in the parity
+//! shards in place, verify checks them and reconstruct fills in missing
+//! shards given as None.
+//!
+//! It is a thin layer over ErasureCode, so the parity is the same and the
+//! plans for recent loss patterns are cached the same way.
+
+use super::erasure::{ ErasureCode, ErasureError };
+
+/// A systematic Reed-Solomon code over whole shards
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ReedSolomon {
+	code: ErasureCode,
+}
+
+impl ReedSolomon {
+	/// data_shards must be positive and the total at most 256
+	pub fn new(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon, ErasureError> {
+		Ok(ReedSolomon { code: ErasureCode::new(data_shards, parity_shards)? })
+	}
+
+	pub fn data_shard_count(&self) -> usize {
+		self.code.data_shards()
+	}
+
+	pub fn parity_shard_count(&self) -> usize {
+		self.code.parity_shards()
+	}
+
+	pub fn total_shard_count(&self) -> usize {
+		self.code.total_shards()
+	}
+
+	/// The underlying ErasureCode
+	pub fn code(&self) -> &ErasureCode {
+		&self.code
+	}
+
+	fn check_sizes<T: AsRef<[u8]>>(&self, shards: &[T]) -> Result<usize, ErasureError> {
+		if shards.len() != self.total_shard_count() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let size = shards[0].as_ref().len();
+		if shards.iter().any(|s| s.as_ref().len() != size) {
+			return Err(ErasureError::ShardSizeMismatch);
+		}
+		Ok(size)
+	}
+
+	/// Overwrites the parity shards with the parity of the data shards
+	pub fn encode<T: AsRef<[u8]> + AsMut<[u8]>>(&self, shards: &mut [T]) -> Result<(), ErasureError> {
+		self.check_sizes(shards)?;
+		let (data, parity) = shards.split_at_mut(self.data_shard_count());
+		let data: Vec<&[u8]> = data.iter().map(|s| s.as_ref()).collect();
+		let mut parity: Vec<&mut [u8]> = parity.iter_mut().map(|s| s.as_mut()).collect();
+		self.code.encode(&data, &mut parity)
+	}
+
+	/// Whether the parity shards match the data shards
+	pub fn verify<T: AsRef<[u8]>>(&self, shards: &[T]) -> Result<bool, ErasureError> {
+		let size = self.check_sizes(shards)?;
+		let (data, parity) = shards.split_at(self.data_shard_count());
+		let data: Vec<&[u8]> = data.iter().map(|s| s.as_ref()).collect();
+		let mut expected = vec![vec![0u8; size]; self.parity_shard_count()];
+		{
+			let mut refs: Vec<&mut [u8]> = expected.iter_mut().map(|p| &mut p[..]).collect();
+			self.code.encode(&data, &mut refs)?;
+		}
+		Ok(expected.iter().zip(parity).all(|(e, p)| e[..] == *p.as_ref()))
+	}
+
+	/// Fills in every missing shard, data and parity, given at least
+	/// data_shard_count present shards
+	pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ErasureError> {
+		self.code.reconstruct(shards)
+	}
+
+	/// Like reconstruct, but only fills in the data shards; missing parity
+	/// shards stay None
+	pub fn reconstruct_data(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ErasureError> {
+		if shards.len() != self.total_shard_count() {
+			return Err(ErasureError::ShardCountMismatch);
+		}
+		let present: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_some()).collect();
+		let missing: Vec<usize> = (0..self.data_shard_count()).filter(|&i| shards[i].is_none()).collect();
+		if missing.is_empty() {
+			return Ok(());
+		}
+		let plan = self.code.plan(&present, &missing)?;
+		self.code.reconstruct_with(&plan, shards)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shards(rs: &ReedSolomon, size: usize) -> Vec<Vec<u8>> {
+        let mut shards: Vec<Vec<u8>> = (0..rs.total_shard_count())
+            .map(|i| (0..size).map(|j| (i * 17 + j * 5) as u8).collect())
+            .collect();
+        rs.encode(&mut shards).unwrap();
+        shards
+    }
+
+    #[test]
+    fn test_encode_verify() {
+        let rs = ReedSolomon::new(4, 2).unwrap();
+        let mut all = shards(&rs, 16);
+        assert_eq!(rs.verify(&all), Ok(true));
+        // Encoding through mutable slices gives the same parity
+        let mut copy = all.clone();
+        {
+            let mut refs: Vec<&mut [u8]> = copy.iter_mut().map(|s| &mut s[..]).collect();
+            for p in &mut refs[4..] {
+                p.fill(0);
+            }
+            rs.encode(&mut refs).unwrap();
+        }
+        assert_eq!(copy, all);
+        all[1][3] ^= 1;
+        assert_eq!(rs.verify(&all), Ok(false));
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let rs = ReedSolomon::new(4, 3).unwrap();
+        let all = shards(&rs, 8);
+        let mut damaged: Vec<Option<Vec<u8>>> = all.iter().cloned().map(Some).collect();
+        damaged[0] = None;
+        damaged[2] = None;
+        damaged[5] = None;
+        let mut data_only = damaged.clone();
+        rs.reconstruct(&mut damaged).unwrap();
+        assert!(damaged.iter().zip(&all).all(|(d, a)| d.as_ref() == Some(a)));
+        rs.reconstruct_data(&mut data_only).unwrap();
+        assert_eq!(data_only[0].as_ref(), Some(&all[0]));
+        assert_eq!(data_only[2].as_ref(), Some(&all[2]));
+        assert_eq!(data_only[5], None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(ReedSolomon::new(0, 2), Err(ErasureError::InvalidParameters));
+        let rs = ReedSolomon::new(2, 1).unwrap();
+        let mut uneven = vec![vec![0u8; 4], vec![0u8; 4], vec![0u8; 3]];
+        assert_eq!(rs.encode(&mut uneven), Err(ErasureError::ShardSizeMismatch));
+        assert_eq!(rs.verify(&uneven[..2]), Err(ErasureError::ShardCountMismatch));
+        let mut lost = vec![None, None, Some(vec![0u8; 4])];
+        assert_eq!(rs.reconstruct(&mut lost), Err(ErasureError::TooFewShards));
+    }
+}
@@ -0,0 +1,227 @@
+//! Reed-Solomon protected byte streams.
+//!
+//! RsWriter cuts the data written to it into blocks of k bytes and writes
+//! each block followed by its n - k parity bytes. The last block may be
+//! shorter; it is encoded as a shortened codeword (the missing leading
+//! symbols are taken as zero), so no length field or padding is needed.
+//! RsReader reads blocks of n bytes, corrects them and returns the data.
+//! A block shorter than n can therefore only be the last one.
+//!
+//! Uncorrectable blocks surface as io::ErrorKind::InvalidData with the
+//! RsError as the inner error.
+
+use std::io::{ self, Read, Write };
+
+use super::RsError;
+use super::encoder::RsEncoder;
+
+fn invalid(e: RsError) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Adds parity to everything written through it.
+///
+/// The final partial block is written by finish, or on drop with errors
+/// ignored, like BufWriter does. flush only flushes the inner writer,
+/// since writing a partial block would end the stream.
+#[derive(Debug)]
+pub struct RsWriter<W: Write> {
+	inner: Option<W>,
+	code: RsEncoder,
+	/// Data of the current block, fewer than k bytes
+	pending: Vec<u8>,
+}
+
+impl<W: Write> RsWriter<W> {
+	pub fn new(inner: W, code: RsEncoder) -> RsWriter<W> {
+		let pending = Vec::with_capacity(code.k());
+		RsWriter { inner: Some(inner), code, pending }
+	}
+
+	pub fn get_ref(&self) -> &W {
+		self.inner.as_ref().expect("Writer is present until finish")
+	}
+
+	fn write_block(&mut self) -> io::Result<()> {
+		if self.pending.is_empty() {
+			return Ok(());
+		}
+		let (k, nsym) = (self.code.k(), self.code.parity_len());
+		// Leading zero symbols do not change the parity
+		let mut msg = vec![0u8; k - self.pending.len()];
+		msg.extend_from_slice(&self.pending);
+		let mut parity = vec![0u8; nsym];
+		self.code.encode_into(&msg, &mut parity);
+		let inner = self.inner.as_mut().expect("Writer is present until finish");
+		inner.write_all(&self.pending)?;
+		inner.write_all(&parity)?;
+		self.pending.clear();
+		Ok(())
+	}
+
+	/// Writes the last (partial) block and returns the inner writer
+	pub fn finish(mut self) -> io::Result<W> {
+		self.write_block()?;
+		let mut inner = self.inner.take().expect("Writer is present until finish");
+		inner.flush()?;
+		Ok(inner)
+	}
+}
+
+impl<W: Write> Write for RsWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let take = buf.len().min(self.code.k() - self.pending.len());
+		self.pending.extend_from_slice(&buf[..take]);
+		if self.pending.len() == self.code.k() {
+			self.write_block()?;
+		}
+		Ok(take)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.as_mut().expect("Writer is present until finish").flush()
+	}
+}
+
+impl<W: Write> Drop for RsWriter<W> {
+	fn drop(&mut self) {
+		if self.inner.is_some() {
+			let _ = self.write_block();
+		}
+	}
+}
+
+/// Corrects and strips the parity of a stream written by RsWriter
+#[derive(Debug)]
+pub struct RsReader<R: Read> {
+	inner: R,
+	code: RsEncoder,
+	/// Decoded data of the current block and the read position in it
+	block: Vec<u8>,
+	pos: usize,
+	corrected: usize,
+}
+
+impl<R: Read> RsReader<R> {
+	pub fn new(inner: R, code: RsEncoder) -> RsReader<R> {
+		RsReader { inner, code, block: Vec::new(), pos: 0, corrected: 0 }
+	}
+
+	/// Total number of symbols corrected so far
+	pub fn corrected(&self) -> usize {
+		self.corrected
+	}
+
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+
+	/// Reads and decodes the next block, returning false at the end
+	fn next_block(&mut self) -> io::Result<bool> {
+		let (n, k) = (self.code.n(), self.code.k());
+		let mut codeword = vec![0u8; n];
+		let mut len = 0;
+		while len < n {
+			match self.inner.read(&mut codeword[len..]) {
+				Ok(0) => break,
+				Ok(m) => len += m,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+				Err(e) => return Err(e),
+			}
+		}
+		if len == 0 {
+			return Ok(false);
+		}
+		if len <= self.code.parity_len() {
+			return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated block"));
+		}
+		// Move a shortened block behind its implicit leading zeros
+		let zeros = n - len;
+		codeword.rotate_right(zeros);
+		self.corrected += self.code.decode(&mut codeword).map_err(invalid)?;
+		// A correction in the implicit zeros means the block was
+		// miscorrected
+		if codeword[..zeros].iter().any(|&b| b != 0) {
+			return Err(invalid(RsError::TooManyErrors));
+		}
+		self.block.clear();
+		self.block.extend_from_slice(&codeword[zeros..k]);
+		self.pos = 0;
+		Ok(true)
+	}
+}
+
+impl<R: Read> Read for RsReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.pos == self.block.len() && !self.next_block()? {
+			return Ok(0);
+		}
+		let m = buf.len().min(self.block.len() - self.pos);
+		buf[..m].copy_from_slice(&self.block[self.pos..self.pos + m]);
+		self.pos += m;
+		Ok(m)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> RsEncoder {
+        RsEncoder::new(20, 16).unwrap()
+    }
+
+    fn protect(data: &[u8]) -> Vec<u8> {
+        let mut w = RsWriter::new(Vec::new(), code());
+        // Odd write sizes straddle block boundaries
+        for chunk in data.chunks(7) {
+            w.write_all(chunk).unwrap();
+        }
+        w.finish().unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for len in [0usize, 1, 15, 16, 17, 100] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 31 + 7) as u8).collect();
+            let stream = protect(&data);
+            assert_eq!(stream.len(), len + len.div_ceil(16) * 4);
+            let mut out = Vec::new();
+            RsReader::new(&stream[..], code()).read_to_end(&mut out).unwrap();
+            assert_eq!(out, data);
+        }
+    }
+
+    #[test]
+    fn test_corrects() {
+        let data: Vec<u8> = (0..50).collect();
+        let mut stream = protect(&data);
+        // Up to two errors per block, including the short last one
+        for p in [0, 19, 20, 33, 40, 59, 61, stream.len() - 1] {
+            stream[p] ^= 0x5a;
+        }
+        let mut r = RsReader::new(&stream[..], code());
+        let mut out = Vec::new();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert_eq!(r.corrected(), 8);
+
+        for p in [1, 2, 3] {
+            stream[p] ^= 0x01;
+        }
+        let err = RsReader::new(&stream[..], code()).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_drop_writes_last_block() {
+        let mut out = Vec::new();
+        {
+            let mut w = RsWriter::new(&mut out, code());
+            w.write_all(b"abc").unwrap();
+        }
+        assert_eq!(out.len(), 3 + 4);
+        assert_eq!(&out[..3], b"abc");
+    }
+}
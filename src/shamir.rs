@@ -0,0 +1,831 @@
+//! Shamir secret sharing over GF(256).
+//!
+//! Every byte of the secret is the constant term of its own random
+//! polynomial of degree threshold - 1. Share i holds the evaluations of
+//! all those polynomials at x = i. Any threshold shares determine the
+//! polynomials (and hence the secret) by Lagrange interpolation, fewer
+//! shares reveal nothing about it.
+//!
+//! The functions in this module do not allocate: shares have a fixed
+//! capacity given as a const generic parameter, which makes them usable on
+//! microcontrollers without a heap. With the alloc feature, split and
+//! recover work on OwnedShare instead, which holds any length.
+//!
+//! Long-lived shares can be refreshed: refresh adds a sharing of zero,
+//! after which old and new shares no longer combine. reshare changes the
+//! threshold and number of shares. Every old holder splits its own share
+//! among the new holders, and each new holder takes the Lagrange
+//! combination of what it received; the secret is never put together.
+//!
+//! For more than MAX_SHARES holders, the wide submodule shares over
+//! GF(2^16) instead.
+
+use core::fmt;
+use core::error;
+#[cfg(feature = "alloc")]
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+#[cfg(feature = "alloc")]
+use crate::bytes;
+
+#[cfg(feature = "alloc")]
+pub mod wide;
+
+/// Maximum number of shares. Share x coordinates have to be distinct and
+/// nonzero elements of the field.
+pub const MAX_SHARES: usize = 255;
+
+/// A single share holding up to MAX_LEN bytes
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Share<const MAX_LEN: usize> {
+	/// x coordinate of the share. Never zero for a valid share.
+	pub x: u8,
+	/// Number of shares needed to recover the secret
+	pub threshold: u8,
+	len: usize,
+	data: [u8; MAX_LEN]
+}
+
+impl<const MAX_LEN: usize> Share<MAX_LEN> {
+	/// Returns an empty share, to be filled by split_into
+	pub const fn empty() -> Share<MAX_LEN> {
+		Share { x: 0, threshold: 0, len: 0, data: [0; MAX_LEN] }
+	}
+
+	/// Builds a share from its parts, e.g. after reading it from storage
+	pub fn from_parts(x: u8, threshold: u8, data: &[u8])
+		-> Result<Share<MAX_LEN>, ShamirError> {
+		if data.len() > MAX_LEN {
+			return Err(ShamirError::SecretTooLong);
+		}
+		let mut share = Share::empty();
+		share.x = x;
+		share.threshold = threshold;
+		share.len = data.len();
+		share.data[..data.len()].copy_from_slice(data);
+		Ok(share)
+	}
+
+	/// Returns the share data (one byte per secret byte)
+	pub fn data(&self) -> &[u8] {
+		&self.data[..self.len]
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+impl<const MAX_LEN: usize> Default for Share<MAX_LEN> {
+	fn default() -> Share<MAX_LEN> {
+		Share::empty()
+	}
+}
+
+/// Errors produced while splitting or recovering a secret
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum ShamirError {
+	/// The threshold is zero or larger than the number of shares
+	InvalidThreshold,
+	/// More than MAX_SHARES shares were requested
+	TooManyShares,
+	/// The secret does not fit into a share, or the output buffer is too
+	/// small for the recovered secret
+	SecretTooLong,
+	/// Fewer shares than the threshold were given
+	NotEnoughShares,
+	/// The shares disagree on threshold or length
+	InconsistentShares,
+	/// Two shares have the same x coordinate, or a share has x = 0
+	InvalidShareIndex,
+}
+
+impl fmt::Display for ShamirError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			ShamirError::InvalidThreshold => "invalid threshold",
+			ShamirError::TooManyShares => "too many shares",
+			ShamirError::SecretTooLong => "secret does not fit into the buffer",
+			ShamirError::NotEnoughShares => "not enough shares",
+			ShamirError::InconsistentShares => "shares are inconsistent",
+			ShamirError::InvalidShareIndex => "invalid or duplicate share index",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for ShamirError {}
+
+/// Horner evaluation of the polynomial with the given coefficients
+/// (constant term first) at x
+fn eval(coeffs: &[Gf256], x: Gf256) -> Gf256 {
+	coeffs.iter().rev().fold(Gf256::zero(), |acc, &c| acc * x + c)
+}
+
+/// The Lagrange basis polynomial of xj evaluated at zero. In
+/// characteristic 2 subtraction is addition, so l_j(0) = prod x_m / (x_m + x_j)
+/// over the other coordinates x_m.
+fn weight_at_zero(xj: u8, xs: impl Iterator<Item = u8>) -> Gf256 {
+	let xj = Gf256::from_byte(xj);
+	xs.map(Gf256::from_byte).filter(|&xm| xm != xj)
+		.fold(Gf256::one(), |w, xm| w * xm / (xm + xj))
+}
+
+/// Checks that shares agree on threshold and length and have distinct,
+/// nonzero x coordinates
+fn check_shares<const MAX_LEN: usize>(shares: &[Share<MAX_LEN>]) -> Result<(), ShamirError> {
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	for (i, share) in shares.iter().enumerate() {
+		if share.threshold != first.threshold || share.len != first.len {
+			return Err(ShamirError::InconsistentShares);
+		}
+		if share.x == 0 || shares[..i].iter().any(|s| s.x == share.x) {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+	}
+	Ok(())
+}
+
+/// Splits secret into N shares, any threshold of which recover it.
+///
+/// rng has to fill the given buffer with uniformly random bytes from a
+/// cryptographically secure source.
+pub fn split_into<const N: usize, const MAX_LEN: usize, R>(secret: &[u8], threshold: u8,
+	shares: &mut [Share<MAX_LEN>; N], mut rng: R) -> Result<(), ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	if N > MAX_SHARES {
+		return Err(ShamirError::TooManyShares);
+	}
+	if threshold == 0 || threshold as usize > N {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if secret.len() > MAX_LEN {
+		return Err(ShamirError::SecretTooLong);
+	}
+
+	for (i, share) in shares.iter_mut().enumerate() {
+		share.x = (i + 1) as u8;
+		share.threshold = threshold;
+		share.len = secret.len();
+	}
+
+	let t = threshold as usize;
+	let mut random = [0u8; MAX_SHARES];
+	let mut coeffs = [Gf256::zero(); MAX_SHARES];
+	for (pos, &byte) in secret.iter().enumerate() {
+		rng(&mut random[1..t]);
+		coeffs[0] = Gf256::from_byte(byte);
+		for j in 1..t {
+			coeffs[j] = Gf256::from_byte(random[j]);
+		}
+		for share in shares.iter_mut() {
+			share.data[pos] = eval(&coeffs[..t], Gf256::from_byte(share.x)).to_byte();
+		}
+	}
+	Ok(())
+}
+
+/// Recovers the secret from shares into out and returns its length.
+///
+/// Only the first threshold shares are used.
+pub fn recover_into<const MAX_LEN: usize>(shares: &[Share<MAX_LEN>], out: &mut [u8])
+	-> Result<usize, ShamirError> {
+
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	let t = first.threshold as usize;
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if shares.len() < t {
+		return Err(ShamirError::NotEnoughShares);
+	}
+	let shares = &shares[..t];
+	let len = first.len;
+	if len > out.len() {
+		return Err(ShamirError::SecretTooLong);
+	}
+	check_shares(shares)?;
+
+	let mut weights = [Gf256::zero(); MAX_SHARES];
+	for (w, sj) in weights.iter_mut().zip(shares) {
+		*w = weight_at_zero(sj.x, shares.iter().map(|s| s.x));
+	}
+
+	for (pos, byte) in out[..len].iter_mut().enumerate() {
+		let mut acc = Gf256::zero();
+		for (share, &w) in shares.iter().zip(weights.iter()) {
+			acc += w * Gf256::from_byte(share.data[pos]);
+		}
+		*byte = acc.to_byte();
+	}
+	Ok(len)
+}
+
+/// Re-randomizes shares in place without changing the secret.
+///
+/// A random polynomial with zero constant term is added to the sharing
+/// polynomials, so the refreshed shares recover the same secret but are
+/// useless together with shares from before the refresh. Shares not
+/// passed in are left on the old polynomials and have to be discarded.
+/// rng is used as in split_into.
+pub fn refresh_into<const MAX_LEN: usize, R>(shares: &mut [Share<MAX_LEN>], mut rng: R)
+	-> Result<(), ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	check_shares(shares)?;
+	let first = shares[0];
+	let t = first.threshold as usize;
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	let mut random = [0u8; MAX_SHARES];
+	let mut coeffs = [Gf256::zero(); MAX_SHARES];
+	for pos in 0..first.len {
+		rng(&mut random[1..t]);
+		for j in 1..t {
+			coeffs[j] = Gf256::from_byte(random[j]);
+		}
+		for share in shares.iter_mut() {
+			share.data[pos] ^= eval(&coeffs[..t], Gf256::from_byte(share.x)).to_byte();
+		}
+	}
+	Ok(())
+}
+
+/// A share with heap storage, as produced by split
+#[cfg(feature = "alloc")]
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct OwnedShare {
+	/// x coordinate of the share. Never zero for a valid share.
+	pub x: u8,
+	/// Number of shares needed to recover the secret
+	pub threshold: u8,
+	/// One byte per secret byte
+	pub data: Vec<u8>,
+}
+
+/// Splits secret into the given number of shares, any threshold of which
+/// recover it. rng is used as in split_into.
+#[cfg(feature = "alloc")]
+pub fn split<R>(secret: &[u8], threshold: u8, shares: u8, mut rng: R)
+	-> Result<Vec<OwnedShare>, ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	if threshold == 0 || threshold > shares {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	let mut out: Vec<OwnedShare> = (1..=shares)
+		.map(|x| OwnedShare { x, threshold, data: vec![0; secret.len()] })
+		.collect();
+	let t = threshold as usize;
+	let mut random = vec![0u8; t];
+	let mut coeffs = vec![Gf256::zero(); t];
+	for (pos, &byte) in secret.iter().enumerate() {
+		rng(&mut random[1..]);
+		coeffs[0] = Gf256::from_byte(byte);
+		for (c, &r) in coeffs[1..].iter_mut().zip(&random[1..]) {
+			*c = Gf256::from_byte(r);
+		}
+		for share in out.iter_mut() {
+			share.data[pos] = eval(&coeffs, Gf256::from_byte(share.x)).to_byte();
+		}
+	}
+	Ok(out)
+}
+
+/// check_shares for OwnedShare
+#[cfg(feature = "alloc")]
+fn check_owned_shares(shares: &[OwnedShare]) -> Result<(), ShamirError> {
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	for (i, share) in shares.iter().enumerate() {
+		if share.threshold != first.threshold || share.data.len() != first.data.len() {
+			return Err(ShamirError::InconsistentShares);
+		}
+		if share.x == 0 || shares[..i].iter().any(|s| s.x == share.x) {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+	}
+	Ok(())
+}
+
+/// Recovers the secret from shares made by split. Only the first
+/// threshold shares are used, as in recover_into.
+#[cfg(feature = "alloc")]
+pub fn recover(shares: &[OwnedShare]) -> Result<Vec<u8>, ShamirError> {
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	let t = first.threshold as usize;
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if shares.len() < t {
+		return Err(ShamirError::NotEnoughShares);
+	}
+	let shares = &shares[..t];
+	check_owned_shares(shares)?;
+
+	let mut secret = vec![0u8; first.data.len()];
+	for sj in shares {
+		let w = weight_at_zero(sj.x, shares.iter().map(|s| s.x));
+		bytes::mul_add(&mut secret, &sj.data, w.to_byte());
+	}
+	Ok(secret)
+}
+
+/// Returns the shares re-randomized as by refresh_into
+#[cfg(feature = "alloc")]
+pub fn refresh<R>(shares: &[OwnedShare], mut rng: R) -> Result<Vec<OwnedShare>, ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	check_owned_shares(shares)?;
+	let (t, len) = (shares[0].threshold as usize, shares[0].data.len());
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	let mut out = shares.to_vec();
+	let mut random = vec![0u8; t];
+	let mut coeffs = vec![Gf256::zero(); t];
+	for pos in 0..len {
+		rng(&mut random[1..]);
+		for (c, &r) in coeffs[1..].iter_mut().zip(&random[1..]) {
+			*c = Gf256::from_byte(r);
+		}
+		for share in out.iter_mut() {
+			share.data[pos] ^= eval(&coeffs, Gf256::from_byte(share.x)).to_byte();
+		}
+	}
+	Ok(out)
+}
+
+/// First step of resharing, run by the holder of an old share: splits
+/// the share itself into subshares for the new holders 1 to new_count,
+/// any new_threshold of which recover it. Subshare i goes to new holder i.
+#[cfg(feature = "alloc")]
+pub fn reshare_deal<R>(share: &OwnedShare, new_threshold: u8, new_count: u8, rng: R)
+	-> Result<Vec<OwnedShare>, ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	if share.x == 0 {
+		return Err(ShamirError::InvalidShareIndex);
+	}
+	split(&share.data, new_threshold, new_count, rng)
+}
+
+/// Second step of resharing, run by a new holder: combines the subshares
+/// it received, subshares[j] dealt from the old share with x coordinate
+/// old_xs[j], into its new share.
+///
+/// The new share is the Lagrange combination at zero of the subshares,
+/// so the new shares lie on a polynomial of degree new_threshold - 1
+/// with the old secret as constant term. At least the old threshold of
+/// old shares have to deal, and every new holder has to combine
+/// subshares from the same ones.
+#[cfg(feature = "alloc")]
+pub fn reshare_combine(old_xs: &[u8], subshares: &[OwnedShare]) -> Result<OwnedShare, ShamirError> {
+	if old_xs.len() != subshares.len() {
+		return Err(ShamirError::InconsistentShares);
+	}
+	let first = subshares.first().ok_or(ShamirError::NotEnoughShares)?;
+	for (i, (&x, sub)) in old_xs.iter().zip(subshares).enumerate() {
+		if x == 0 || old_xs[..i].contains(&x) {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+		if sub.x != first.x || sub.threshold != first.threshold || sub.data.len() != first.data.len() {
+			return Err(ShamirError::InconsistentShares);
+		}
+	}
+
+	let mut data = vec![0u8; first.data.len()];
+	for (&xj, sub) in old_xs.iter().zip(subshares) {
+		let w = weight_at_zero(xj, old_xs.iter().copied());
+		bytes::mul_add(&mut data, &sub.data, w.to_byte());
+	}
+	Ok(OwnedShare { x: first.x, threshold: first.threshold, data })
+}
+
+/// Moves the secret behind shares to new_count new shares with threshold
+/// new_threshold, by running reshare_deal for the first threshold shares
+/// and reshare_combine for every new holder. The secret itself is never
+/// formed; in a real deployment each step runs at a different holder.
+#[cfg(feature = "alloc")]
+pub fn reshare<R>(shares: &[OwnedShare], new_threshold: u8, new_count: u8, mut rng: R)
+	-> Result<Vec<OwnedShare>, ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	let t = first.threshold as usize;
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if shares.len() < t {
+		return Err(ShamirError::NotEnoughShares);
+	}
+	let shares = &shares[..t];
+	check_owned_shares(shares)?;
+
+	let dealt = shares.iter()
+		.map(|s| reshare_deal(s, new_threshold, new_count, &mut rng))
+		.collect::<Result<Vec<_>, _>>()?;
+	let old_xs: Vec<u8> = shares.iter().map(|s| s.x).collect();
+	(0..new_count as usize)
+		.map(|i| {
+			let received: Vec<OwnedShare> = dealt.iter().map(|d| d[i].clone()).collect();
+			reshare_combine(&old_xs, &received)
+		})
+		.collect()
+}
+
+/// The sharing polynomials, as interpolated by recover_polynomial
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct SecretPolynomial<const T: usize, const MAX_LEN: usize> {
+	threshold: usize,
+	len: usize,
+	/// coeffs[j][pos]: coefficient of x^j of the polynomial of byte pos
+	coeffs: [[u8; MAX_LEN]; T],
+}
+
+impl<const T: usize, const MAX_LEN: usize> SecretPolynomial<T, MAX_LEN> {
+	pub fn threshold(&self) -> usize {
+		self.threshold
+	}
+
+	/// Returns the secret, i.e. the constant coefficients
+	pub fn secret(&self) -> &[u8] {
+		&self.coeffs[0][..self.len]
+	}
+
+	/// Returns the coefficients of x^j of all byte polynomials
+	pub fn coefficient(&self, j: usize) -> &[u8] {
+		&self.coeffs[j][..self.len]
+	}
+
+	/// Computes the share with the given x coordinate, e.g. to replace a
+	/// lost one
+	pub fn share(&self, x: u8) -> Result<Share<MAX_LEN>, ShamirError> {
+		if x == 0 {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+		let mut share = Share::empty();
+		share.x = x;
+		share.threshold = self.threshold as u8;
+		share.len = self.len;
+		let x = Gf256::from_byte(x);
+		for (pos, byte) in share.data[..self.len].iter_mut().enumerate() {
+			*byte = self.coeffs[..self.threshold].iter().rev()
+				.fold(Gf256::zero(), |acc, c| acc * x + Gf256::from_byte(c[pos]))
+				.to_byte();
+		}
+		Ok(share)
+	}
+}
+
+/// Interpolates the sharing polynomials through all given shares.
+///
+/// Unlike recover_into, every share is used. If the shares do not all lie
+/// on polynomials of degree threshold - 1, they cannot stem from the same
+/// split and InconsistentShares is returned. T bounds the threshold which
+/// can be handled.
+pub fn recover_polynomial<const T: usize, const MAX_LEN: usize>(shares: &[Share<MAX_LEN>])
+	-> Result<SecretPolynomial<T, MAX_LEN>, ShamirError> {
+
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	let t = first.threshold as usize;
+	if t == 0 || t > T {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if shares.len() < t {
+		return Err(ShamirError::NotEnoughShares);
+	}
+	if shares.len() > MAX_SHARES {
+		return Err(ShamirError::TooManyShares);
+	}
+	check_shares(shares)?;
+
+	let m = shares.len();
+	let mut poly = SecretPolynomial { threshold: t, len: first.len, coeffs: [[0; MAX_LEN]; T] };
+	let mut newton = [Gf256::zero(); MAX_SHARES];
+	let mut mono = [Gf256::zero(); MAX_SHARES];
+	let x = |i: usize| Gf256::from_byte(shares[i].x);
+	for pos in 0..first.len {
+		// Divided differences give the Newton form
+		// c_0 + c_1 (x - x_0) + c_2 (x - x_0)(x - x_1) + ...
+		for (c, share) in newton.iter_mut().zip(shares) {
+			*c = Gf256::from_byte(share.data[pos]);
+		}
+		for j in 1..m {
+			for i in (j..m).rev() {
+				newton[i] = (newton[i] - newton[i - 1]) / (x(i) - x(i - j));
+			}
+		}
+		// Expanded from the innermost term outwards
+		mono[0] = newton[m - 1];
+		for i in (0..m - 1).rev() {
+			let deg = m - 1 - i;
+			mono[deg] = mono[deg - 1];
+			for k in (1..deg).rev() {
+				mono[k] = mono[k - 1] - x(i) * mono[k];
+			}
+			mono[0] = newton[i] - x(i) * mono[0];
+		}
+		if mono[t..m].iter().any(|&c| c != Gf256::zero()) {
+			return Err(ShamirError::InconsistentShares);
+		}
+		for (coeff, &c) in poly.coeffs.iter_mut().zip(&mono[..t]) {
+			coeff[pos] = c.to_byte();
+		}
+	}
+	Ok(poly)
+}
+
+/// State of a Reconstructor after adding a share
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Progress<'a> {
+	/// This many more shares are needed
+	Missing(usize),
+	/// The threshold is met; this is the secret
+	Complete(&'a [u8]),
+}
+
+/// Recovers a secret from shares arriving one at a time.
+///
+/// Up to T shares are buffered, so T bounds the threshold which can be
+/// handled. Shares beyond the threshold are ignored.
+pub struct Reconstructor<const T: usize, const MAX_LEN: usize> {
+	shares: [Share<MAX_LEN>; T],
+	count: usize,
+	secret: [u8; MAX_LEN],
+}
+
+impl<const T: usize, const MAX_LEN: usize> Reconstructor<T, MAX_LEN> {
+	pub const fn new() -> Reconstructor<T, MAX_LEN> {
+		Reconstructor { shares: [Share::empty(); T], count: 0, secret: [0; MAX_LEN] }
+	}
+
+	/// Number of shares accepted so far
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	/// Whether the secret has been recovered
+	pub fn is_complete(&self) -> bool {
+		self.count > 0 && self.count == self.shares[0].threshold as usize
+	}
+
+	/// Adds a share. A share is rejected if it disagrees with the previous
+	/// ones on threshold or length, or reuses an x coordinate with other
+	/// data; resending an accepted share is harmless.
+	pub fn add(&mut self, share: &Share<MAX_LEN>) -> Result<Progress<'_>, ShamirError> {
+		if !self.is_complete() {
+			self.accept(share)?;
+		}
+		Ok(self.progress())
+	}
+
+	fn accept(&mut self, share: &Share<MAX_LEN>) -> Result<(), ShamirError> {
+		if share.x == 0 {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+		if share.threshold == 0 || share.threshold as usize > T {
+			return Err(ShamirError::InvalidThreshold);
+		}
+		let known = &self.shares[..self.count];
+		if let Some(first) = known.first() {
+			if share.threshold != first.threshold || share.len != first.len {
+				return Err(ShamirError::InconsistentShares);
+			}
+		}
+		if let Some(old) = known.iter().find(|s| s.x == share.x) {
+			return if old.data() == share.data() {
+				Ok(())
+			} else {
+				Err(ShamirError::InvalidShareIndex)
+			};
+		}
+
+		self.shares[self.count] = *share;
+		self.count += 1;
+		if self.is_complete() {
+			recover_into(&self.shares[..self.count], &mut self.secret)?;
+		}
+		Ok(())
+	}
+
+	/// Returns the current state without adding anything
+	pub fn progress(&self) -> Progress<'_> {
+		match self.shares[..self.count].first() {
+			None => Progress::Missing(1),
+			Some(first) if self.is_complete() => Progress::Complete(&self.secret[..first.len]),
+			Some(first) => Progress::Missing(first.threshold as usize - self.count),
+		}
+	}
+
+	/// Forgets all shares and the secret
+	pub fn reset(&mut self) {
+		*self = Reconstructor::new();
+	}
+}
+
+impl<const T: usize, const MAX_LEN: usize> Default for Reconstructor<T, MAX_LEN> {
+	fn default() -> Reconstructor<T, MAX_LEN> {
+		Reconstructor::new()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+
+    #[test]
+    fn test_split_recover() {
+        let secret = b"device key";
+        let mut shares = [Share::<16>::empty(); 5];
+        split_into(secret, 3, &mut shares, test_rng(1)).unwrap();
+
+        // Any 3 shares recover the secret
+        for a in 0..5 {
+            for b in 0..5 {
+                for c in 0..5 {
+                    if a == b || b == c || a == c {
+                        continue;
+                    }
+                    let subset = [shares[a], shares[b], shares[c]];
+                    let mut out = [0u8; 16];
+                    let len = recover_into(&subset, &mut out).unwrap();
+                    assert_eq!(&out[..len], &secret[..]);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_split_recover_owned() {
+        let secret: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let shares = split(&secret, 4, 7, test_rng(8)).unwrap();
+        assert_eq!(shares.len(), 7);
+        assert_eq!(recover(&shares[3..]), Ok(secret.clone()));
+        let mixed = [shares[6].clone(), shares[0].clone(), shares[4].clone(), shares[2].clone()];
+        assert_eq!(recover(&mixed), Ok(secret.clone()));
+        assert_eq!(recover(&shares[..3]), Err(ShamirError::NotEnoughShares));
+        // Matches the fixed-capacity variant for the same randomness
+        let mut fixed = [Share::<300>::empty(); 7];
+        split_into(&secret, 4, &mut fixed, test_rng(8)).unwrap();
+        assert_eq!(fixed[5].data(), &shares[5].data[..]);
+        assert_eq!(split(b"x", 3, 2, test_rng(9)), Err(ShamirError::InvalidThreshold));
+    }
+
+    #[test]
+    fn test_not_enough_shares() {
+        let mut shares = [Share::<4>::empty(); 4];
+        split_into(b"abcd", 3, &mut shares, test_rng(2)).unwrap();
+        let mut out = [0u8; 4];
+        assert_eq!(recover_into(&shares[..2], &mut out), Err(ShamirError::NotEnoughShares));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let mut shares = [Share::<2>::empty(); 3];
+        assert_eq!(split_into(b"ab", 0, &mut shares, test_rng(3)),
+            Err(ShamirError::InvalidThreshold));
+        assert_eq!(split_into(b"ab", 4, &mut shares, test_rng(3)),
+            Err(ShamirError::InvalidThreshold));
+        assert_eq!(split_into(b"abc", 2, &mut shares, test_rng(3)),
+            Err(ShamirError::SecretTooLong));
+    }
+
+    #[test]
+    fn test_duplicate_share() {
+        let mut shares = [Share::<4>::empty(); 3];
+        split_into(b"abcd", 2, &mut shares, test_rng(4)).unwrap();
+        let subset = [shares[1], shares[1]];
+        let mut out = [0u8; 4];
+        assert_eq!(recover_into(&subset, &mut out), Err(ShamirError::InvalidShareIndex));
+    }
+
+    #[test]
+    fn test_threshold_one() {
+        let mut shares = [Share::<3>::empty(); 2];
+        split_into(b"xyz", 1, &mut shares, test_rng(5)).unwrap();
+        assert_eq!(shares[0].data(), b"xyz");
+        assert_eq!(shares[1].data(), b"xyz");
+    }
+
+    #[test]
+    fn test_reconstructor() {
+        let secret = b"ceremony";
+        let mut shares = [Share::<8>::empty(); 5];
+        split_into(secret, 3, &mut shares, test_rng(6)).unwrap();
+
+        let mut rec = Reconstructor::<4, 8>::new();
+        assert_eq!(rec.progress(), Progress::Missing(1));
+        assert_eq!(rec.add(&shares[4]), Ok(Progress::Missing(2)));
+        // Resending a share changes nothing
+        assert_eq!(rec.add(&shares[4]), Ok(Progress::Missing(2)));
+        assert_eq!(rec.add(&shares[1]), Ok(Progress::Missing(1)));
+        assert_eq!(rec.add(&shares[2]), Ok(Progress::Complete(&secret[..])));
+        assert_eq!(rec.add(&shares[0]), Ok(Progress::Complete(&secret[..])));
+        assert_eq!(rec.count(), 3);
+
+        rec.reset();
+        assert_eq!(rec.progress(), Progress::Missing(1));
+    }
+
+    #[test]
+    fn test_reconstructor_rejects() {
+        let mut shares = [Share::<4>::empty(); 3];
+        split_into(b"abcd", 2, &mut shares, test_rng(7)).unwrap();
+        let mut rec = Reconstructor::<2, 4>::new();
+        rec.add(&shares[0]).unwrap();
+
+        let mut forged = shares[0];
+        forged.data[0] ^= 1;
+        assert_eq!(rec.add(&forged), Err(ShamirError::InvalidShareIndex));
+        let short = Share::from_parts(3, 2, b"abc").unwrap();
+        assert_eq!(rec.add(&short), Err(ShamirError::InconsistentShares));
+        let big = Share::from_parts(3, 3, b"abcd").unwrap();
+        assert_eq!(Reconstructor::<2, 4>::new().add(&big), Err(ShamirError::InvalidThreshold));
+        assert_eq!(rec.progress(), Progress::Missing(1));
+    }
+
+    #[test]
+    fn test_recover_polynomial() {
+        let secret = b"repair";
+        let mut shares = [Share::<6>::empty(); 6];
+        split_into(secret, 3, &mut shares, test_rng(8)).unwrap();
+
+        let poly = recover_polynomial::<4, 6>(&shares[1..5]).unwrap();
+        assert_eq!(poly.threshold(), 3);
+        assert_eq!(poly.secret(), secret);
+        // Lost shares can be regenerated
+        assert_eq!(poly.share(1).unwrap(), shares[0]);
+        assert_eq!(poly.share(6).unwrap(), shares[5]);
+
+        let mut bad = shares;
+        bad[3].data[2] ^= 0x40;
+        assert_eq!(recover_polynomial::<4, 6>(&bad[..4]).err(), Some(ShamirError::InconsistentShares));
+        // With exactly threshold shares there is nothing to cross-check
+        assert!(recover_polynomial::<4, 6>(&bad[1..4]).is_ok());
+        assert_eq!(recover_polynomial::<2, 6>(&shares).err(), Some(ShamirError::InvalidThreshold));
+    }
+
+    #[test]
+    fn test_refresh() {
+        let secret = b"long lived";
+        let mut shares = [Share::<16>::empty(); 5];
+        split_into(secret, 3, &mut shares, test_rng(10)).unwrap();
+        let old = shares;
+        refresh_into(&mut shares, test_rng(11)).unwrap();
+        assert_ne!(shares, old);
+
+        let mut out = [0u8; 16];
+        let len = recover_into(&shares[2..], &mut out).unwrap();
+        assert_eq!(&out[..len], secret);
+        // Old and new shares lie on different polynomials
+        let mixed = [old[0], shares[1], shares[2], shares[3]];
+        assert_eq!(recover_polynomial::<3, 16>(&mixed).err(), Some(ShamirError::InconsistentShares));
+
+        shares[1].x = shares[0].x;
+        assert_eq!(refresh_into(&mut shares, test_rng(12)), Err(ShamirError::InvalidShareIndex));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_reshare() {
+        let secret: Vec<u8> = (0..40).map(|i| (i * 29 + 3) as u8).collect();
+        let shares = split(&secret, 3, 5, test_rng(13)).unwrap();
+
+        let fresh = refresh(&shares, test_rng(14)).unwrap();
+        assert_eq!(recover(&fresh[1..4]), Ok(secret.clone()));
+        assert_ne!(fresh, shares);
+
+        // 3 of 5 to 4 of 6, dealt by three old holders
+        let dealers = [shares[4].clone(), shares[1].clone(), shares[2].clone()];
+        let new = reshare(&dealers, 4, 6, test_rng(15)).unwrap();
+        assert_eq!(new.len(), 6);
+        assert!(new.iter().all(|s| s.threshold == 4));
+        assert_eq!(recover(&new[2..]), Ok(secret.clone()));
+        let three = [new[0].clone(), new[1].clone(), new[5].clone()];
+        assert_eq!(recover(&three), Err(ShamirError::NotEnoughShares));
+        assert_eq!(reshare(&shares, 1, 1, test_rng(16)).map(|s| recover(&s)), Ok(Ok(secret.clone())));
+
+        // More dealers than the old threshold work as well
+        let dealt: Vec<Vec<OwnedShare>> = shares.iter()
+            .map(|s| reshare_deal(s, 2, 3, test_rng(s.x)).unwrap())
+            .collect();
+        let xs: Vec<u8> = shares.iter().map(|s| s.x).collect();
+        let new: Vec<OwnedShare> = (0..3)
+            .map(|i| reshare_combine(&xs, &dealt.iter().map(|d| d[i].clone()).collect::<Vec<_>>()).unwrap())
+            .collect();
+        assert_eq!(recover(&new[1..]), Ok(secret));
+
+        assert_eq!(reshare(&shares[..2], 2, 3, test_rng(17)), Err(ShamirError::NotEnoughShares));
+        assert_eq!(reshare(&shares, 4, 3, test_rng(17)), Err(ShamirError::InvalidThreshold));
+    }
+}
@@ -0,0 +1,143 @@
+//! Shamir secret sharing over GF(2^16).
+//!
+//! GF(256) has only 255 nonzero x coordinates, which caps a split at 255
+//! shares. Here the secret is cut into big endian pairs of bytes, each an
+//! element of Gf65536 and the constant term of its own polynomial, so up
+//! to 65535 shares can be made and x coordinates can be derived from
+//! 16-bit holder ids. An odd secret is padded with a zero byte; shares
+//! record the unpadded length. shares::EncodedShare stores these shares
+//! with their own field id.
+
+use alloc::{ vec, vec::Vec };
+
+use crate::wide::Gf65536;
+use super::ShamirError;
+
+/// Most shares one split can have
+pub const MAX_SHARES: usize = 65535;
+
+/// A share of a secret split over GF(2^16)
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct WideShare {
+	/// x coordinate of the share. Never zero for a valid share.
+	pub x: u16,
+	/// Number of shares needed to recover the secret
+	pub threshold: u16,
+	/// Length of the secret in bytes
+	pub len: usize,
+	/// One big endian symbol per two secret bytes
+	pub data: Vec<u8>,
+}
+
+fn symbol(data: &[u8], i: usize) -> Gf65536 {
+	Gf65536::from_u16(u16::from_be_bytes([data[2 * i], data[2 * i + 1]]))
+}
+
+fn eval(coeffs: &[Gf65536], x: Gf65536) -> Gf65536 {
+	coeffs.iter().rev().fold(Gf65536::zero(), |acc, &c| acc * x + c)
+}
+
+/// Splits secret into shares with x = 1, ..., shares, any threshold of
+/// which recover it. rng is used as in shamir::split_into.
+pub fn split<R>(secret: &[u8], threshold: u16, shares: u16, mut rng: R)
+	-> Result<Vec<WideShare>, ShamirError>
+	where R: FnMut(&mut [u8]) {
+
+	if threshold == 0 || threshold > shares {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	let mut padded = secret.to_vec();
+	padded.resize(secret.len().div_ceil(2) * 2, 0);
+	let mut out: Vec<WideShare> = (1..=shares)
+		.map(|x| WideShare { x, threshold, len: secret.len(), data: vec![0; padded.len()] })
+		.collect();
+	let t = threshold as usize;
+	let mut random = vec![0u8; 2 * t];
+	let mut coeffs = vec![Gf65536::zero(); t];
+	for i in 0..padded.len() / 2 {
+		rng(&mut random[2..]);
+		coeffs[0] = symbol(&padded, i);
+		for (j, c) in coeffs.iter_mut().enumerate().skip(1) {
+			*c = symbol(&random, j);
+		}
+		for share in out.iter_mut() {
+			let y = eval(&coeffs, Gf65536::from_u16(share.x)).to_u16();
+			share.data[2 * i..2 * i + 2].copy_from_slice(&y.to_be_bytes());
+		}
+	}
+	Ok(out)
+}
+
+/// Recovers the secret from shares made by split. Only the first
+/// threshold shares are used.
+pub fn recover(shares: &[WideShare]) -> Result<Vec<u8>, ShamirError> {
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	let t = first.threshold as usize;
+	if t == 0 {
+		return Err(ShamirError::InvalidThreshold);
+	}
+	if shares.len() < t {
+		return Err(ShamirError::NotEnoughShares);
+	}
+	let shares = &shares[..t];
+	for (i, share) in shares.iter().enumerate() {
+		if share.threshold != first.threshold || share.len != first.len
+			|| share.data.len() != first.len.div_ceil(2) * 2 {
+			return Err(ShamirError::InconsistentShares);
+		}
+		if share.x == 0 || shares[..i].iter().any(|s| s.x == share.x) {
+			return Err(ShamirError::InvalidShareIndex);
+		}
+	}
+
+	// l_j(0) = prod x_m / (x_m + x_j), as for GF(256)
+	let weights: Vec<Gf65536> = shares.iter().map(|sj| {
+		let xj = Gf65536::from_u16(sj.x);
+		shares.iter().map(|s| Gf65536::from_u16(s.x)).filter(|&xm| xm != xj)
+			.fold(Gf65536::one(), |w, xm| w * xm / (xm + xj))
+	}).collect();
+	let mut secret = Vec::with_capacity(first.data.len());
+	for i in 0..first.data.len() / 2 {
+		let y = shares.iter().zip(&weights)
+			.fold(Gf65536::zero(), |acc, (s, &w)| acc + w * symbol(&s.data, i));
+		secret.extend_from_slice(&y.to_u16().to_be_bytes());
+	}
+	secret.truncate(first.len);
+	Ok(secret)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+
+    #[test]
+    fn test_split_recover() {
+        let secret = b"odd length";
+        let shares = split(&secret[..9], 3, 5, test_rng(1)).unwrap();
+        assert_eq!(shares[0].data.len(), 10);
+        assert_eq!(recover(&shares[2..]), Ok(secret[..9].to_vec()));
+        let mixed = [shares[4].clone(), shares[0].clone(), shares[2].clone()];
+        assert_eq!(recover(&mixed), Ok(secret[..9].to_vec()));
+        assert_eq!(recover(&shares[..2]), Err(ShamirError::NotEnoughShares));
+        assert_eq!(split(b"x", 3, 2, test_rng(2)), Err(ShamirError::InvalidThreshold));
+    }
+
+    #[test]
+    fn test_many_shares() {
+        // More holders than GF(256) has x coordinates
+        let secret = b"vault";
+        let shares = split(secret, 4, 1000, test_rng(3)).unwrap();
+        assert_eq!(shares[999].x, 1000);
+        let picked: Vec<WideShare> = [999, 300, 256, 17].iter().map(|&i| shares[i].clone()).collect();
+        assert_eq!(recover(&picked), Ok(secret.to_vec()));
+
+        let mut bad = picked.clone();
+        bad[1].x = bad[0].x;
+        assert_eq!(recover(&bad), Err(ShamirError::InvalidShareIndex));
+        bad[1] = picked[1].clone();
+        bad[1].len = 4;
+        assert_eq!(recover(&bad), Err(ShamirError::InconsistentShares));
+    }
+}
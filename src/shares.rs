@@ -0,0 +1,439 @@
+//! A storage format for Shamir shares.
+//!
+//! A share is written as a header, its data and a CRC, all integers big
+//! endian:
+//!
+//! ```text
+//! 0       magic "GFSS"
+//! 4       version (2)
+//! 5       field id
+//! 6       threshold (u16)
+//! 8       x coordinate (u16)
+//! 10      reserved, zero (u16)
+//! 12      set id (u32)
+//! 16      secret length (u32)
+//! 20      data
+//! 20+len  CRC-32 (IEEE) of everything before it
+//! ```
+//!
+//! The field id is FIELD_GF256 for shares made by shamir::split, with one
+//! data byte per secret byte, and FIELD_GF65536 for those of
+//! shamir::wide::split, whose data is the secret length rounded up to
+//! even. Version 1 shares, which only existed over GF(256), had a 16 byte
+//! header with 8-bit threshold and x at offsets 5 and 6, a reserved byte
+//! and the set id and length at 8 and 12; from_bytes still reads them.
+//!
+//! The set id is drawn at random when a split is encoded and is the same
+//! for all of its shares. recover refuses to combine shares from
+//! different splits, which would otherwise yield a plausible looking but
+//! wrong secret. For paper backups or copy and paste, to_text and
+//! from_text wrap the binary form in standard base64. to_armored adds
+//! BEGIN and END lines and breaks the base64 into lines of 64 characters,
+//! which prints well and fits a QR code.
+
+use std::fmt;
+use std::error;
+
+use crate::recovery::crc32;
+use crate::shamir::{ self, OwnedShare, ShamirError };
+use crate::shamir::wide::{ self, WideShare };
+
+pub const MAGIC: [u8; 4] = *b"GFSS";
+pub const VERSION: u8 = 2;
+pub const HEADER_LEN: usize = 20;
+
+/// Field id of shares over GF(256)
+pub const FIELD_GF256: u8 = 0;
+/// Field id of shares over GF(2^16)
+pub const FIELD_GF65536: u8 = 1;
+
+/// Header length of version 1 shares
+const V1_HEADER_LEN: usize = 16;
+
+const ARMOR_BEGIN: &str = "-----BEGIN GF256 SHARE-----";
+const ARMOR_END: &str = "-----END GF256 SHARE-----";
+
+/// Problems found while decoding or combining encoded shares
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum ShareFormatError {
+	/// Fewer bytes than the header announces
+	Truncated,
+	BadMagic,
+	UnsupportedVersion(u8),
+	UnsupportedField(u8),
+	/// Reserved bytes are set, or threshold or x coordinate do not fit
+	/// the field
+	InvalidHeader,
+	/// The share is damaged
+	ChecksumMismatch,
+	/// The text is not valid base64
+	InvalidText,
+	/// The shares stem from different splits or fields
+	MismatchedSets,
+	/// The shares decoded fine, but do not recover a secret
+	Shamir(ShamirError),
+}
+
+impl fmt::Display for ShareFormatError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ShareFormatError::Truncated => f.write_str("truncated share"),
+			ShareFormatError::BadMagic => f.write_str("not an encoded share"),
+			ShareFormatError::UnsupportedVersion(v) => write!(f, "unsupported share version {}", v),
+			ShareFormatError::UnsupportedField(id) => write!(f, "unsupported share field {}", id),
+			ShareFormatError::InvalidHeader => f.write_str("invalid share header"),
+			ShareFormatError::ChecksumMismatch => f.write_str("share checksum mismatch"),
+			ShareFormatError::InvalidText => f.write_str("invalid share text"),
+			ShareFormatError::MismatchedSets => f.write_str("shares belong to different splits"),
+			ShareFormatError::Shamir(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl error::Error for ShareFormatError {}
+
+impl From<ShamirError> for ShareFormatError {
+	fn from(e: ShamirError) -> ShareFormatError {
+		ShareFormatError::Shamir(e)
+	}
+}
+
+/// A share over one of the supported fields
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub enum FieldShare {
+	Gf256(OwnedShare),
+	Gf65536(WideShare),
+}
+
+impl FieldShare {
+	pub fn field_id(&self) -> u8 {
+		match self {
+			FieldShare::Gf256(_) => FIELD_GF256,
+			FieldShare::Gf65536(_) => FIELD_GF65536,
+		}
+	}
+
+	pub fn x(&self) -> u16 {
+		match self {
+			FieldShare::Gf256(s) => s.x.into(),
+			FieldShare::Gf65536(s) => s.x,
+		}
+	}
+}
+
+impl From<OwnedShare> for FieldShare {
+	fn from(share: OwnedShare) -> FieldShare {
+		FieldShare::Gf256(share)
+	}
+}
+
+impl From<WideShare> for FieldShare {
+	fn from(share: WideShare) -> FieldShare {
+		FieldShare::Gf65536(share)
+	}
+}
+
+/// A decoded share and the id of the split it belongs to
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct EncodedShare {
+	pub set_id: u32,
+	pub share: FieldShare,
+}
+
+impl EncodedShare {
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let (threshold, x, len, data) = match &self.share {
+			FieldShare::Gf256(s) => (s.threshold.into(), s.x.into(), s.data.len(), &s.data),
+			FieldShare::Gf65536(s) => (s.threshold, s.x, s.len, &s.data),
+		};
+		let mut out = Vec::with_capacity(HEADER_LEN + data.len() + 4);
+		out.extend_from_slice(&MAGIC);
+		out.extend_from_slice(&[VERSION, self.share.field_id()]);
+		out.extend_from_slice(&threshold.to_be_bytes());
+		out.extend_from_slice(&x.to_be_bytes());
+		out.extend_from_slice(&[0, 0]);
+		out.extend_from_slice(&self.set_id.to_be_bytes());
+		out.extend_from_slice(&(len as u32).to_be_bytes());
+		out.extend_from_slice(data);
+		let crc = crc32(&out);
+		out.extend_from_slice(&crc.to_be_bytes());
+		out
+	}
+
+	/// Parses and checks a share of either version. Bytes after the CRC
+	/// are ignored.
+	pub fn from_bytes(bytes: &[u8]) -> Result<EncodedShare, ShareFormatError> {
+		if bytes.len() < 5 {
+			return Err(ShareFormatError::Truncated);
+		}
+		if bytes[..4] != MAGIC {
+			return Err(ShareFormatError::BadMagic);
+		}
+		let header = match bytes[4] {
+			1 => V1_HEADER_LEN,
+			VERSION => HEADER_LEN,
+			v => return Err(ShareFormatError::UnsupportedVersion(v)),
+		};
+		if bytes.len() < header {
+			return Err(ShareFormatError::Truncated);
+		}
+		let be16 = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+		let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+		let (field, threshold, x, reserved, set_id) = if header == V1_HEADER_LEN {
+			(FIELD_GF256, bytes[5].into(), bytes[6].into(), bytes[7] != 0, be32(&bytes[8..]))
+		} else {
+			(bytes[5], be16(&bytes[6..]), be16(&bytes[8..]), bytes[10..12] != [0, 0], be32(&bytes[12..]))
+		};
+		let len = be32(&bytes[header - 4..]) as usize;
+		let data_len = match field {
+			FIELD_GF256 => len,
+			FIELD_GF65536 => len.div_ceil(2) * 2,
+			_ => return Err(ShareFormatError::UnsupportedField(field)),
+		};
+		let end = header.checked_add(data_len)
+			.filter(|&end| end.checked_add(4).is_some_and(|e| e <= bytes.len()))
+			.ok_or(ShareFormatError::Truncated)?;
+		if crc32(&bytes[..end]) != be32(&bytes[end..]) {
+			return Err(ShareFormatError::ChecksumMismatch);
+		}
+		if reserved {
+			return Err(ShareFormatError::InvalidHeader);
+		}
+		let data = bytes[header..end].to_vec();
+		let share = if field == FIELD_GF256 {
+			let threshold = u8::try_from(threshold).map_err(|_| ShareFormatError::InvalidHeader)?;
+			let x = u8::try_from(x).map_err(|_| ShareFormatError::InvalidHeader)?;
+			FieldShare::Gf256(OwnedShare { x, threshold, data })
+		} else {
+			FieldShare::Gf65536(WideShare { x, threshold, len, data })
+		};
+		Ok(EncodedShare { set_id, share })
+	}
+
+	/// The binary form in base64
+	pub fn to_text(&self) -> String {
+		base64_encode(&self.to_bytes())
+	}
+
+	/// Parses the output of to_text, ignoring surrounding whitespace
+	pub fn from_text(text: &str) -> Result<EncodedShare, ShareFormatError> {
+		let bytes = base64_decode(text.trim()).ok_or(ShareFormatError::InvalidText)?;
+		EncodedShare::from_bytes(&bytes)
+	}
+
+	/// to_text between BEGIN and END lines, 64 characters per line
+	pub fn to_armored(&self) -> String {
+		let text = self.to_text();
+		let mut out = String::with_capacity(text.len() + text.len() / 64 + 60);
+		out.push_str(ARMOR_BEGIN);
+		out.push('\n');
+		for line in text.as_bytes().chunks(64) {
+			out.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+			out.push('\n');
+		}
+		out.push_str(ARMOR_END);
+		out.push('\n');
+		out
+	}
+
+	/// Parses the output of to_armored. The BEGIN and END lines are
+	/// optional; whitespace is ignored.
+	pub fn from_armored(text: &str) -> Result<EncodedShare, ShareFormatError> {
+		let mut body = text.trim();
+		if let Some(rest) = body.strip_prefix(ARMOR_BEGIN) {
+			body = rest.strip_suffix(ARMOR_END).ok_or(ShareFormatError::InvalidText)?;
+		}
+		let compact: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+		EncodedShare::from_text(&compact)
+	}
+}
+
+/// Tags the shares of one split with a set id drawn from rng
+pub fn encode_set<S, R>(shares: Vec<S>, mut rng: R) -> Vec<EncodedShare>
+	where S: Into<FieldShare>, R: FnMut(&mut [u8]) {
+	let mut id = [0u8; 4];
+	rng(&mut id);
+	let set_id = u32::from_be_bytes(id);
+	shares.into_iter().map(|share| EncodedShare { set_id, share: share.into() }).collect()
+}
+
+/// Recovers the secret after checking that all shares belong to the same
+/// split
+pub fn recover(shares: &[EncodedShare]) -> Result<Vec<u8>, ShareFormatError> {
+	let first = shares.first().ok_or(ShamirError::NotEnoughShares)?;
+	if shares.iter().any(|s| s.set_id != first.set_id || s.share.field_id() != first.share.field_id()) {
+		return Err(ShareFormatError::MismatchedSets);
+	}
+	let secret = match first.share {
+		FieldShare::Gf256(_) => {
+			let plain: Vec<OwnedShare> = shares.iter().filter_map(|s| match &s.share {
+				FieldShare::Gf256(s) => Some(s.clone()),
+				FieldShare::Gf65536(_) => None,
+			}).collect();
+			shamir::recover(&plain)?
+		}
+		FieldShare::Gf65536(_) => {
+			let plain: Vec<WideShare> = shares.iter().filter_map(|s| match &s.share {
+				FieldShare::Gf65536(s) => Some(s.clone()),
+				FieldShare::Gf256(_) => None,
+			}).collect();
+			wide::recover(&plain)?
+		}
+	};
+	Ok(secret)
+}
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for chunk in bytes.chunks(3) {
+		let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+		let v = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(ALPHABET[(v >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+	let text = text.as_bytes();
+	if !text.len().is_multiple_of(4) {
+		return None;
+	}
+	let mut out = Vec::with_capacity(text.len() / 4 * 3);
+	for (i, chunk) in text.chunks(4).enumerate() {
+		let last = i == text.len() / 4 - 1;
+		let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+		if pad > 2 || (pad > 0 && !last) {
+			return None;
+		}
+		let mut v = 0u32;
+		for &c in &chunk[..4 - pad] {
+			let d = ALPHABET.iter().position(|&a| a == c)? as u32;
+			v = v << 6 | d;
+		}
+		v <<= 6 * pad as u32;
+		out.extend_from_slice(&v.to_be_bytes()[1..4 - pad]);
+	}
+	Some(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+
+    fn set(secret: &[u8], seed: u8) -> Vec<EncodedShare> {
+        let shares = shamir::split(secret, 2, 3, test_rng(seed)).unwrap();
+        encode_set(shares, test_rng(seed.wrapping_add(100)))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let shares = set(b"launch code", 1);
+        for s in &shares {
+            assert_eq!(EncodedShare::from_bytes(&s.to_bytes()).as_ref(), Ok(s));
+            let text = s.to_text();
+            assert_eq!(EncodedShare::from_text(&format!(" {}\n", text)).as_ref(), Ok(s));
+        }
+        assert_eq!(recover(&shares[1..]), Ok(b"launch code".to_vec()));
+    }
+
+    #[test]
+    fn test_armored() {
+        let share = &set(&[0x5a; 100], 5)[1];
+        let armored = share.to_armored();
+        assert!(armored.starts_with("-----BEGIN GF256 SHARE-----\n"));
+        assert!(armored.lines().all(|l| l.len() <= 64 || l.starts_with("-----")));
+        assert_eq!(EncodedShare::from_armored(&armored).as_ref(), Ok(share));
+        assert_eq!(EncodedShare::from_armored(&share.to_text()).as_ref(), Ok(share));
+        let cut = &armored[..armored.len() - 10];
+        assert_eq!(EncodedShare::from_armored(cut), Err(ShareFormatError::InvalidText));
+    }
+
+    #[test]
+    fn test_rejects_damage() {
+        let share = &set(b"abc", 2)[0];
+        let bytes = share.to_bytes();
+        for i in 5..bytes.len() {
+            let mut bad = bytes.clone();
+            bad[i] ^= 0x10;
+            assert!(EncodedShare::from_bytes(&bad).is_err(), "byte {}", i);
+        }
+        assert_eq!(EncodedShare::from_bytes(&bytes[..bytes.len() - 1]), Err(ShareFormatError::Truncated));
+        let mut v3 = bytes.clone();
+        v3[4] = 3;
+        assert_eq!(EncodedShare::from_bytes(&v3), Err(ShareFormatError::UnsupportedVersion(3)));
+        let mut field = bytes.clone();
+        field[5] = 7;
+        assert_eq!(EncodedShare::from_bytes(&field), Err(ShareFormatError::UnsupportedField(7)));
+        assert_eq!(EncodedShare::from_bytes(b"GFSH\x01"), Err(ShareFormatError::BadMagic));
+        assert_eq!(EncodedShare::from_text("not base64!"), Err(ShareFormatError::InvalidText));
+    }
+
+    #[test]
+    fn test_mismatched_sets() {
+        let (a, b) = (set(b"abc", 3), set(b"abc", 4));
+        assert_ne!(a[0].set_id, b[0].set_id);
+        let mixed = [a[0].clone(), b[1].clone()];
+        assert_eq!(recover(&mixed), Err(ShareFormatError::MismatchedSets));
+        assert_eq!(recover(&a[..1]), Err(ShareFormatError::Shamir(ShamirError::NotEnoughShares)));
+    }
+
+    #[test]
+    fn test_wide() {
+        let secret = b"shared by a crowd";
+        let split = wide::split(secret, 3, 400, test_rng(6)).unwrap();
+        let shares = encode_set(split, test_rng(7));
+        let picked: Vec<EncodedShare> = [399, 255, 0].iter().map(|&i| shares[i].clone()).collect();
+        for s in &picked {
+            let bytes = s.to_bytes();
+            assert_eq!(bytes[5], FIELD_GF65536);
+            assert_eq!(bytes.len(), HEADER_LEN + 18 + 4);
+            assert_eq!(EncodedShare::from_bytes(&bytes).as_ref(), Ok(s));
+            assert_eq!(EncodedShare::from_armored(&s.to_armored()).as_ref(), Ok(s));
+        }
+        assert_eq!(picked[0].share.x(), 400);
+        assert_eq!(recover(&picked), Ok(secret.to_vec()));
+
+        // Shares of different fields never combine
+        let mut mixed = set(b"abc", 8);
+        mixed[0].set_id = picked[0].set_id;
+        mixed.push(picked[0].clone());
+        assert_eq!(recover(&mixed[..]), Err(ShareFormatError::MismatchedSets));
+    }
+
+    #[test]
+    fn test_version_1() {
+        // The 16 byte header of version 1: threshold 2, x 3, set id 0x01020304
+        let mut v1 = b"GFSS\x01\x02\x03\x00\x01\x02\x03\x04\x00\x00\x00\x02\xab\xcd".to_vec();
+        let crc = crc32(&v1);
+        v1.extend_from_slice(&crc.to_be_bytes());
+        let share = EncodedShare::from_bytes(&v1).unwrap();
+        let expected = OwnedShare { x: 3, threshold: 2, data: vec![0xab, 0xcd] };
+        assert_eq!(share, EncodedShare { set_id: 0x01020304, share: FieldShare::Gf256(expected) });
+        assert_eq!(share.to_bytes()[4], VERSION);
+
+        v1[7] = 1;
+        let crc = crc32(&v1[..18]);
+        v1[18..].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(EncodedShare::from_bytes(&v1), Err(ShareFormatError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_base64() {
+        for (plain, text) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(base64_encode(plain.as_bytes()), text);
+            assert_eq!(base64_decode(text), Some(plain.as_bytes().to_vec()));
+        }
+        assert_eq!(base64_decode("Zg=a"), None);
+        assert_eq!(base64_decode("Zg==Zg=="), None);
+    }
+}
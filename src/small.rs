@@ -0,0 +1,240 @@
+//! The small binary fields GF(2^K), K = 1 to 8.
+//!
+//! Gf2k<K> holds its element in the low K bits of a byte and uses the
+//! primitive polynomial listed in POLYNOMIALS, with x as generator (1 for
+//! GF(2)). The log and exp tables are built at compile time by the tables
+//! module. Gf2k<8> is the field of Gf256.
+//!
+//! Gf16 elements are nibbles; pack and unpack store two of them per byte,
+//! the first in the high nibble.
+
+use core::fmt;
+use core::ops::{ Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign };
+
+use crate::tables;
+
+/// Reduction polynomial of GF(2^K), at index K - 1
+pub const POLYNOMIALS: [u16; 8] = [0x3, 0x7, 0xb, 0x13, 0x25, 0x43, 0x83, 0x11d];
+
+pub type Gf2 = Gf2k<1>;
+pub type Gf4 = Gf2k<2>;
+pub type Gf8 = Gf2k<3>;
+pub type Gf16 = Gf2k<4>;
+
+/// An element of GF(2^K)
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+#[repr(transparent)]
+pub struct Gf2k<const K: usize> {
+	poly: u8
+}
+
+impl<const K: usize> Gf2k<K> {
+	const VALID: () = assert!(K >= 1 && K <= 8, "K must be between 1 and 8");
+	const GENERATOR: u8 = if K == 1 { 1 } else { 2 };
+	const EXP: &'static [u8; 256] = &tables::small_exp_table(POLYNOMIALS[K - 1], K as u32, Self::GENERATOR);
+	const LOG: &'static [u8; 256] = &tables::small_log_table(POLYNOMIALS[K - 1], K as u32, Self::GENERATOR);
+
+	/// Number of elements
+	pub const SIZE: usize = 1 << K;
+	/// Order of the multiplicative group
+	pub const ORDER: usize = Self::SIZE - 1;
+
+	#[inline]
+	pub const fn zero() -> Self {
+		Gf2k { poly: 0 }
+	}
+
+	#[inline]
+	pub const fn one() -> Self {
+		Gf2k { poly: 1 }
+	}
+
+	/// The element with the given bits, None if b does not fit into K bits
+	pub const fn new(b: u8) -> Option<Self> {
+		let () = Self::VALID;
+		if (b as usize) < Self::SIZE {
+			Some(Gf2k { poly: b })
+		} else {
+			None
+		}
+	}
+
+	/// Like new, but panics if b does not fit into K bits
+	pub const fn from_byte(b: u8) -> Self {
+		match Self::new(b) {
+			Some(x) => x,
+			None => panic!("Value does not fit into the field"),
+		}
+	}
+
+	#[inline]
+	pub const fn to_byte(&self) -> u8 {
+		self.poly
+	}
+
+	/// Logarithm to the base x, None for zero
+	pub const fn log(&self) -> Option<u8> {
+		if self.poly == 0 {
+			None
+		} else {
+			Some(Self::LOG[self.poly as usize])
+		}
+	}
+
+	/// x ^ power
+	pub const fn xexp(power: usize) -> Self {
+		Gf2k { poly: Self::EXP[power % Self::ORDER] }
+	}
+
+	pub const fn exp(&self, power: usize) -> Self {
+		match self.log() {
+			None => Self::zero(),
+			Some(i) => Self::xexp((i as usize * (power % Self::ORDER)) % Self::ORDER),
+		}
+	}
+
+	/// None for zero
+	pub const fn inv(&self) -> Option<Self> {
+		match self.log() {
+			Some(l) => Some(Self::xexp(Self::ORDER - l as usize)),
+			None => None,
+		}
+	}
+
+	pub const fn const_mul(self, rhs: Self) -> Self {
+		match (self.log(), rhs.log()) {
+			(Some(l1), Some(l2)) => Self::xexp(l1 as usize + l2 as usize),
+			_ => Self::zero(),
+		}
+	}
+
+	/// self / rhs, or None if rhs is zero
+	pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+		match rhs.inv() {
+			Some(r) => Some(self.const_mul(r)),
+			None => None,
+		}
+	}
+}
+
+impl Gf16 {
+	/// Packs hi and lo into one byte, hi in the upper nibble
+	#[inline]
+	pub const fn pack(hi: Gf16, lo: Gf16) -> u8 {
+		hi.poly << 4 | lo.poly
+	}
+
+	#[inline]
+	pub const fn unpack(b: u8) -> (Gf16, Gf16) {
+		(Gf2k { poly: b >> 4 }, Gf2k { poly: b & 0xf })
+	}
+}
+
+impl<const K: usize> fmt::Display for Gf2k<K> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(&self.poly, f)
+	}
+}
+
+impl<const K: usize> Add for Gf2k<K> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Self) -> Self {
+		Gf2k { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const K: usize> Sub for Gf2k<K> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: Self) -> Self {
+		Gf2k { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const K: usize> Mul for Gf2k<K> {
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self {
+		self.const_mul(rhs)
+	}
+}
+
+impl<const K: usize> Div for Gf2k<K> {
+	type Output = Self;
+	/// Panics if rhs is zero
+	fn div(self, rhs: Self) -> Self {
+		self.checked_div(rhs).expect("Division by zero")
+	}
+}
+
+macro_rules! impl_assign_op {
+	($tr:ident, $method:ident, $op:ident, $op_method:ident) => {
+		impl<const K: usize> $tr for Gf2k<K> {
+			#[inline]
+			fn $method(&mut self, rhs: Self) {
+				*self = $op::$op_method(*self, rhs);
+			}
+		}
+	};
+}
+
+impl_assign_op!(AddAssign, add_assign, Add, add);
+impl_assign_op!(SubAssign, sub_assign, Sub, sub);
+impl_assign_op!(MulAssign, mul_assign, Mul, mul);
+impl_assign_op!(DivAssign, div_assign, Div, div);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+
+    fn check_field<const K: usize>() {
+        for a in 0..Gf2k::<K>::SIZE as u8 {
+            let a = Gf2k::<K>::from_byte(a);
+            if a != Gf2k::zero() {
+                assert_eq!(a * a.inv().unwrap(), Gf2k::one());
+            }
+            for b in 0..Gf2k::<K>::SIZE as u8 {
+                let b = Gf2k::<K>::from_byte(b);
+                assert!((a * b).to_byte() < Gf2k::<K>::SIZE as u8);
+                assert_eq!(a * b, b * a);
+                if b != Gf2k::zero() {
+                    assert_eq!(a / b * b, a);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fields() {
+        check_field::<1>();
+        check_field::<2>();
+        check_field::<3>();
+        check_field::<4>();
+        check_field::<5>();
+        check_field::<7>();
+        assert_eq!(Gf4::from_byte(2) * Gf4::from_byte(2), Gf4::from_byte(3));
+        assert_eq!(Gf16::from_byte(8) * Gf16::from_byte(2), Gf16::from_byte(3));
+        assert_eq!(Gf16::new(16), None);
+    }
+
+    #[test]
+    fn test_gf256() {
+        for a in (0..=255u8).step_by(11) {
+            for b in 0..=255u8 {
+                let p = Gf2k::<8>::from_byte(a) * Gf2k::<8>::from_byte(b);
+                assert_eq!(p.to_byte(), (Gf256::from_byte(a) * Gf256::from_byte(b)).to_byte());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        let (hi, lo) = (Gf16::from_byte(0xa), Gf16::from_byte(0x3));
+        assert_eq!(Gf16::pack(hi, lo), 0xa3);
+        assert_eq!(Gf16::unpack(0xa3), (hi, lo));
+    }
+}
@@ -0,0 +1,287 @@
+//! Two-dimensional RS extension, as used for data availability sampling.
+//!
+//! A k x k matrix of equally sized chunks is extended to a 2k x 2k
+//! square: every row of the original is extended to 2k chunks with the
+//! erasure code, then every one of the 2k columns. Because the code is
+//! linear, the rows of the bottom half are codewords as well, so each row
+//! and each column of the square can be rebuilt from any k of its chunks.
+//!
+//! A PartialSquare collects sampled chunks and fills in the rest by
+//! alternately decoding rows and columns until nothing changes.
+
+use std::fmt;
+use std::error;
+
+use crate::rs::erasure::ErasureCode;
+
+/// Errors produced while extending or reconstructing squares
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum SquareError {
+	/// k must be between 1 and 128 and match the number of chunks
+	InvalidSize,
+	/// A chunk has the wrong length
+	ChunkSizeMismatch,
+	/// Coordinates outside the square
+	OutOfRange,
+	/// The known chunks are not enough to rebuild the square
+	Unrecoverable,
+	/// The chunks do not form a valid extended square
+	Inconsistent,
+}
+
+impl fmt::Display for SquareError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			SquareError::InvalidSize => "invalid square size",
+			SquareError::ChunkSizeMismatch => "chunk has the wrong size",
+			SquareError::OutOfRange => "coordinates outside the square",
+			SquareError::Unrecoverable => "not enough chunks to reconstruct",
+			SquareError::Inconsistent => "chunks are not a valid extended square",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for SquareError {}
+
+/// Extends line[..k] to line[k..] in place
+fn extend_line(code: &ErasureCode, line: &mut [Vec<u8>]) {
+	let k = code.data_shards();
+	let (data, parity) = line.split_at_mut(k);
+	let data_refs: Vec<&[u8]> = data.iter().map(|c| &c[..]).collect();
+	let mut parity_refs: Vec<&mut [u8]> = parity.iter_mut().map(|c| &mut c[..]).collect();
+	code.encode(&data_refs, &mut parity_refs).expect("Chunks have equal sizes");
+}
+
+/// A complete 2k x 2k extended square
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct ExtendedSquare {
+	k: usize,
+	chunk_size: usize,
+	/// Row major, 4 k^2 chunks
+	cells: Vec<Vec<u8>>,
+}
+
+impl ExtendedSquare {
+	/// Extends the k x k chunks, given row by row
+	pub fn extend(chunks: &[Vec<u8>], k: usize) -> Result<ExtendedSquare, SquareError> {
+		if k == 0 || k > 128 || chunks.len() != k * k {
+			return Err(SquareError::InvalidSize);
+		}
+		let chunk_size = chunks[0].len();
+		if chunks.iter().any(|c| c.len() != chunk_size) {
+			return Err(SquareError::ChunkSizeMismatch);
+		}
+		let code = ErasureCode::new(k, k).map_err(|_| SquareError::InvalidSize)?;
+		let w = 2 * k;
+		let mut cells = vec![vec![0u8; chunk_size]; w * w];
+		for r in 0..k {
+			cells[r * w..r * w + k].clone_from_slice(&chunks[r * k..(r + 1) * k]);
+			extend_line(&code, &mut cells[r * w..(r + 1) * w]);
+		}
+		for c in 0..w {
+			let mut column: Vec<Vec<u8>> = (0..w).map(|r| cells[r * w + c].clone()).collect();
+			extend_line(&code, &mut column);
+			for (r, chunk) in column.into_iter().enumerate().skip(k) {
+				cells[r * w + c] = chunk;
+			}
+		}
+		Ok(ExtendedSquare { k, chunk_size, cells })
+	}
+
+	/// Side length of the original data
+	pub fn k(&self) -> usize {
+		self.k
+	}
+
+	/// Side length of the square, 2k
+	pub fn width(&self) -> usize {
+		2 * self.k
+	}
+
+	pub fn chunk_size(&self) -> usize {
+		self.chunk_size
+	}
+
+	pub fn get(&self, row: usize, col: usize) -> &[u8] {
+		&self.cells[row * self.width() + col]
+	}
+
+	/// Returns row r, 2k chunks
+	pub fn row(&self, r: usize) -> &[Vec<u8>] {
+		let w = self.width();
+		&self.cells[r * w..(r + 1) * w]
+	}
+
+	/// Returns column c, 2k chunks
+	pub fn column(&self, c: usize) -> Vec<&[u8]> {
+		(0..self.width()).map(|r| self.get(r, c)).collect()
+	}
+
+	/// Returns the original k x k chunks, row by row
+	pub fn data(&self) -> Vec<Vec<u8>> {
+		(0..self.k).flat_map(|r| self.row(r)[..self.k].to_vec()).collect()
+	}
+
+	/// Whether a sampled chunk matches the square
+	pub fn check_sample(&self, row: usize, col: usize, chunk: &[u8]) -> bool {
+		row < self.width() && col < self.width() && self.get(row, col) == chunk
+	}
+
+	/// Checks that every row and column is a codeword
+	pub fn verify(&self) -> bool {
+		let code = ErasureCode::new(self.k, self.k).expect("Valid size");
+		let w = self.width();
+		let line_ok = |line: Vec<Vec<u8>>| {
+			let mut extended = line.clone();
+			extend_line(&code, &mut extended);
+			extended == line
+		};
+		(0..w).all(|r| line_ok(self.row(r).to_vec()))
+			&& (0..w).all(|c| line_ok(self.column(c).into_iter().map(|s| s.to_vec()).collect()))
+	}
+}
+
+/// An extended square of which only some chunks are known
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct PartialSquare {
+	k: usize,
+	chunk_size: usize,
+	cells: Vec<Option<Vec<u8>>>,
+}
+
+impl PartialSquare {
+	pub fn new(k: usize, chunk_size: usize) -> Result<PartialSquare, SquareError> {
+		if k == 0 || k > 128 {
+			return Err(SquareError::InvalidSize);
+		}
+		Ok(PartialSquare { k, chunk_size, cells: vec![None; 4 * k * k] })
+	}
+
+	pub fn width(&self) -> usize {
+		2 * self.k
+	}
+
+	/// Adds a sampled chunk
+	pub fn insert(&mut self, row: usize, col: usize, chunk: &[u8]) -> Result<(), SquareError> {
+		let w = self.width();
+		if row >= w || col >= w {
+			return Err(SquareError::OutOfRange);
+		}
+		if chunk.len() != self.chunk_size {
+			return Err(SquareError::ChunkSizeMismatch);
+		}
+		self.cells[row * w + col] = Some(chunk.to_vec());
+		Ok(())
+	}
+
+	/// Number of known chunks
+	pub fn known(&self) -> usize {
+		self.cells.iter().filter(|c| c.is_some()).count()
+	}
+
+	/// Decodes every line with at least k known chunks; returns whether
+	/// anything was filled in.
+	fn sweep(&mut self, code: &ErasureCode, rows: bool) -> bool {
+		let w = self.width();
+		let index = |line: usize, i: usize| if rows { line * w + i } else { i * w + line };
+		let mut progress = false;
+		for line in 0..w {
+			let mut shards: Vec<Option<Vec<u8>>> = (0..w).map(|i| self.cells[index(line, i)].clone()).collect();
+			let known = shards.iter().filter(|s| s.is_some()).count();
+			if known < self.k || known == w {
+				continue;
+			}
+			code.reconstruct(&mut shards).expect("Enough chunks are known");
+			for (i, shard) in shards.into_iter().enumerate() {
+				self.cells[index(line, i)] = shard;
+			}
+			progress = true;
+		}
+		progress
+	}
+
+	/// Rebuilds the whole square from the known chunks
+	pub fn solve(mut self) -> Result<ExtendedSquare, SquareError> {
+		let code = ErasureCode::new(self.k, self.k).map_err(|_| SquareError::InvalidSize)?;
+		while self.known() < self.cells.len() {
+			let rows = self.sweep(&code, true);
+			let cols = self.sweep(&code, false);
+			if !rows && !cols {
+				return Err(SquareError::Unrecoverable);
+			}
+		}
+		let square = ExtendedSquare {
+			k: self.k,
+			chunk_size: self.chunk_size,
+			cells: self.cells.into_iter().map(|c| c.expect("Complete")).collect(),
+		};
+		if !square.verify() {
+			return Err(SquareError::Inconsistent);
+		}
+		Ok(square)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corrupt::Corruptor;
+
+    fn chunks(k: usize, size: usize) -> Vec<Vec<u8>> {
+        (0..k * k).map(|i| (0..size).map(|j| (i * 41 + j * 3) as u8).collect()).collect()
+    }
+
+    #[test]
+    fn test_extend() {
+        let data = chunks(4, 8);
+        let square = ExtendedSquare::extend(&data, 4).unwrap();
+        assert_eq!(square.width(), 8);
+        assert!(square.verify());
+        assert_eq!(square.data(), data);
+        assert!(square.check_sample(7, 7, square.get(7, 7)));
+        assert!(!square.check_sample(7, 7, &[0; 8]));
+    }
+
+    #[test]
+    fn test_reconstruct_from_samples() {
+        let square = ExtendedSquare::extend(&chunks(4, 8), 4).unwrap();
+        let mut rng = Corruptor::new(3);
+        // Any single quadrant is enough: its rows decode first, then every
+        // column has k known chunks.
+        let mut partial = PartialSquare::new(4, 8).unwrap();
+        for r in 0..4 {
+            for c in 0..4 {
+                partial.insert(r + 4, c + 4, square.get(r + 4, c + 4)).unwrap();
+            }
+        }
+        assert_eq!(partial.clone().solve().unwrap(), square);
+
+        let mut partial = PartialSquare::new(4, 8).unwrap();
+        for cell in rng.choose(64, 36) {
+            partial.insert(cell / 8, cell % 8, square.get(cell / 8, cell % 8)).unwrap();
+        }
+        assert_eq!(partial.solve().unwrap(), square);
+    }
+
+    #[test]
+    fn test_unrecoverable_and_inconsistent() {
+        let square = ExtendedSquare::extend(&chunks(2, 4), 2).unwrap();
+        // Three chunks of one row and nothing else
+        let mut partial = PartialSquare::new(2, 4).unwrap();
+        for c in 0..3 {
+            partial.insert(0, c, square.get(0, c)).unwrap();
+        }
+        assert_eq!(partial.solve(), Err(SquareError::Unrecoverable));
+
+        let mut partial = PartialSquare::new(2, 4).unwrap();
+        for r in 0..4 {
+            for c in 0..4 {
+                partial.insert(r, c, square.get(r, c)).unwrap();
+            }
+        }
+        partial.insert(3, 3, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(partial.solve(), Err(SquareError::Inconsistent));
+    }
+}
@@ -0,0 +1,254 @@
+//! Table generation in const contexts.
+//!
+//! The functions here build exp, log, inverse and full multiplication
+//! tables for any degree 8 reduction polynomial at compile time, so
+//! embedded users can keep exactly the tables they need in flash:
+//!
+//!     static EXP: [u8; 256] = gf256::tables::exp_table(0x11b, 0x03);
+//!
+//! gf256_tables! expands all of them as const items of a module in the
+//! caller's crate. Unused consts take no space in the binary.
+//!
+//! Invalid input (a reducible polynomial, a generator which is not
+//! primitive) fails const evaluation, i.e. the build.
+
+use crate::ctx::Tables;
+
+/// Carry-less product of a and b reduced modulo poly (x^8 + ...)
+pub const fn mul_mod(a: u8, b: u8, poly: u16) -> u8 {
+	let low = (poly & 0xff) as u8;
+	let (mut a, mut b, mut acc) = (a, b, 0u8);
+	while b != 0 {
+		if b & 1 != 0 {
+			acc ^= a;
+		}
+		let carry = a & 0x80 != 0;
+		a <<= 1;
+		if carry {
+			a ^= low;
+		}
+		b >>= 1;
+	}
+	acc
+}
+
+/// mul_mod for fields of 2^degree elements, degree 1 to 8: a and b are
+/// below 2^degree and poly has degree `degree`
+pub const fn mul_mod_degree(a: u8, b: u8, poly: u16, degree: u32) -> u8 {
+	assert!(degree >= 1 && degree <= 8 && poly >> degree == 1, "Reduction polynomial has the wrong degree");
+	let mask = (1u16 << degree) - 1;
+	let low = poly & mask;
+	let (mut a, mut b, mut acc) = (a as u16, b as u16, 0u16);
+	while b != 0 {
+		if b & 1 != 0 {
+			acc ^= a;
+		}
+		a <<= 1;
+		if a >> degree != 0 {
+			a = (a & mask) ^ low;
+		}
+		b >>= 1;
+	}
+	acc as u8
+}
+
+/// exp table of GF(2^degree): exp[i] = generator^i for i below the group
+/// order 2^degree - 1, zero above
+pub const fn small_exp_table(poly: u16, degree: u32, generator: u8) -> [u8; 256] {
+	let order = (1usize << degree) - 1;
+	let mut exp = [0u8; 256];
+	let mut tmp = 1u8;
+	let mut i = 0;
+	while i < order {
+		assert!(i == 0 || tmp != 1, "Generator is not primitive");
+		exp[i] = tmp;
+		tmp = mul_mod_degree(tmp, generator, poly, degree);
+		i += 1;
+	}
+	assert!(tmp == 1, "Generator is not primitive");
+	exp
+}
+
+/// log table matching small_exp_table, log[0] = 0
+pub const fn small_log_table(poly: u16, degree: u32, generator: u8) -> [u8; 256] {
+	let exp = small_exp_table(poly, degree, generator);
+	let order = (1usize << degree) - 1;
+	let mut log = [0u8; 256];
+	let mut i = 0;
+	while i < order {
+		log[exp[i] as usize] = i as u8;
+		i += 1;
+	}
+	log
+}
+
+const fn check_poly(poly: u16) {
+	assert!(poly >> 8 == 1, "Reduction polynomial must have degree 8");
+}
+
+/// exp[i] = generator^i, with exp[255] = 1 as in ctx::Tables
+pub const fn exp_table(poly: u16, generator: u8) -> [u8; 256] {
+	check_poly(poly);
+	let mut exp = [0u8; 256];
+	let mut tmp = 1u8;
+	let mut i = 0;
+	while i < 255 {
+		assert!(i == 0 || tmp != 1, "Generator is not primitive");
+		exp[i] = tmp;
+		tmp = mul_mod(tmp, generator, poly);
+		i += 1;
+	}
+	assert!(tmp == 1, "Generator is not primitive");
+	exp[255] = 1;
+	exp
+}
+
+/// exp_table repeated twice: exp[i] = generator^(i mod 255) for every
+/// i below 510, so the sum of two logarithms indexes it directly
+pub const fn exp_table_doubled(poly: u16, generator: u8) -> [u8; 512] {
+	let exp = exp_table(poly, generator);
+	let mut out = [0u8; 512];
+	let mut i = 0;
+	while i < 512 {
+		out[i] = exp[i % 255];
+		i += 1;
+	}
+	out
+}
+
+/// log[generator^i] = i, with log[0] = 0
+pub const fn log_table(poly: u16, generator: u8) -> [u8; 256] {
+	let exp = exp_table(poly, generator);
+	let mut log = [0u8; 256];
+	let mut i = 0;
+	while i < 255 {
+		log[exp[i] as usize] = i as u8;
+		i += 1;
+	}
+	log
+}
+
+/// inv[x] = x^-1, with inv[0] = 0
+pub const fn inv_table(poly: u16) -> [u8; 256] {
+	check_poly(poly);
+	let mut inv = [0u8; 256];
+	let mut x = 1;
+	while x < 256 {
+		// x^254 by square and multiply
+		let (mut base, mut e, mut acc) = (x as u8, 254u8, 1u8);
+		while e != 0 {
+			if e & 1 != 0 {
+				acc = mul_mod(acc, base, poly);
+			}
+			base = mul_mod(base, base, poly);
+			e >>= 1;
+		}
+		assert!(mul_mod(acc, x as u8, poly) == 1, "Reduction polynomial is reducible");
+		inv[x] = acc;
+		x += 1;
+	}
+	inv
+}
+
+/// mul[a][b] = a * b, 64 KiB
+pub const fn mul_table(poly: u16) -> [[u8; 256]; 256] {
+	check_poly(poly);
+	let mut mul = [[0u8; 256]; 256];
+	let mut a = 0;
+	while a < 256 {
+		let mut b = 0;
+		while b < 256 {
+			mul[a][b] = mul_mod(a as u8, b as u8, poly);
+			b += 1;
+		}
+		a += 1;
+	}
+	mul
+}
+
+/// Tables in the layout of ctx::Tables. FieldCtx only accepts the ones
+/// of this crate's field, tables(0x11d, 0x02).
+pub const fn tables(poly: u16, generator: u8) -> Tables {
+	Tables {
+		exp: exp_table(poly, generator),
+		log: log_table(poly, generator),
+		inv: inv_table(poly),
+	}
+}
+
+/// Expands the tables of a field as const items of a new module:
+///
+///     gf256::gf256_tables!(pub mod aes { poly: 0x11b, generator: 0x03 });
+///
+/// defines aes::POLY, aes::GENERATOR, aes::EXP, aes::LOG, aes::INV and
+/// aes::MUL.
+#[macro_export]
+macro_rules! gf256_tables {
+	($vis:vis mod $name:ident { poly: $poly:expr, generator: $gen:expr $(,)? }) => {
+		$vis mod $name {
+			#[allow(unused_imports)]
+			use super::*;
+
+			pub const POLY: u16 = $poly;
+			pub const GENERATOR: u8 = $gen;
+			pub const EXP: [u8; 256] = $crate::tables::exp_table(POLY, GENERATOR);
+			pub const LOG: [u8; 256] = $crate::tables::log_table(POLY, GENERATOR);
+			pub const INV: [u8; 256] = $crate::tables::inv_table(POLY);
+			pub const MUL: [[u8; 256]; 256] = $crate::tables::mul_table(POLY);
+		}
+	};
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gf256;
+    use crate::ctx::FieldCtx;
+
+    crate::gf256_tables!(mod aes { poly: 0x11b, generator: 0x03 });
+
+    static BUILTIN: Tables = tables(0x11d, 0x02);
+
+    #[test]
+    fn test_builtin_field() {
+        assert!(FieldCtx::new(&BUILTIN).is_ok());
+        static MUL: [[u8; 256]; 256] = mul_table(0x11d);
+        for (a, row) in MUL.iter().enumerate().step_by(5) {
+            for (b, &p) in row.iter().enumerate() {
+                assert_eq!(Gf256::from_byte(a as u8) * Gf256::from_byte(b as u8), Gf256::from_byte(p));
+                if b != 0 {
+                    assert_eq!(Gf256::from_byte(p) / Gf256::from_byte(b as u8), Gf256::from_byte(a as u8));
+                }
+            }
+        }
+        let exp2 = exp_table_doubled(0x11d, 2);
+        assert_eq!(exp2[..255], exp2[255..510]);
+        assert_eq!(exp2[..255], exp_table(0x11d, 2)[..255]);
+    }
+
+    #[test]
+    fn test_small_tables() {
+        // GF(16) with x^4 + x + 1
+        let exp = small_exp_table(0x13, 4, 2);
+        assert_eq!(exp[..4], [1, 2, 4, 8]);
+        assert_eq!(exp[4], 3);
+        assert_eq!(exp[15], 0);
+        assert_eq!(mul_mod_degree(0x57, 0x83, 0x11b, 8), mul_mod(0x57, 0x83, 0x11b));
+        let log = small_log_table(0x13, 4, 2);
+        assert!((1..16).all(|x| exp[log[x] as usize] as usize == x));
+    }
+
+    #[test]
+    fn test_aes_field() {
+        // Known values from FIPS 197
+        assert_eq!(aes::MUL[0x57][0x83], 0xc1);
+        assert_eq!(aes::MUL[0x57][0x13], 0xfe);
+        assert_eq!(aes::INV[0x53], 0xca);
+        assert_eq!(aes::EXP[1], 0x03);
+        for x in 1..256 {
+            assert_eq!(aes::EXP[aes::LOG[x] as usize] as usize, x);
+            assert_eq!(aes::MUL[x][aes::INV[x] as usize], 1);
+        }
+    }
+}
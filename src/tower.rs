@@ -0,0 +1,243 @@
+//! GF(256) as the composite field GF((2^4)^2).
+//!
+//! A Tower element is hi * y + lo with hi and lo in Gf16 and y a root of
+//! y^2 + y + LAMBDA, the irreducible quadratic over GF(16) with the
+//! smallest constant. Multiplication and inversion then only need GF(16)
+//! arithmetic: the inverse of hi * y + lo is
+//!
+//! ```text
+//! (hi * y + hi + lo) / (hi^2 * LAMBDA + hi * lo + lo^2)
+//! ```
+//!
+//! with a single GF(16) inversion, which is why hardware and masked AES
+//! implementations compute the S-box this way.
+//!
+//! The two representations are isomorphic, and the isomorphism is linear
+//! over GF(2): x of Gf16 maps to OMEGA, a root of x^4 + x + 1 in GF(256),
+//! and y to Y. TO_GF256 and FROM_GF256 hold the basis-change matrices, as
+//! the images of the eight basis bits. Tower bytes put hi in the high
+//! nibble, as Gf16::pack does.
+
+use core::ops::{ Add, Sub, Mul };
+
+use crate::Gf256;
+use crate::small::Gf16;
+
+/// The constant term of the defining polynomial y^2 + y + LAMBDA
+pub const LAMBDA: Gf16 = {
+	// Irreducible exactly when z^2 + z never reaches lambda
+	let mut l = 1;
+	'search: loop {
+		let mut z = 0;
+		while z < 16 {
+			let z2 = Gf16::from_byte(z);
+			if z2.const_mul(z2).to_byte() ^ z == l {
+				l += 1;
+				continue 'search;
+			}
+			z += 1;
+		}
+		break Gf16::from_byte(l);
+	}
+};
+
+/// The smallest root of x^4 + x + 1 in GF(256), the image of x of Gf16
+pub const OMEGA: Gf256 = {
+	let mut a = 2;
+	while Gf256::from_byte(a).pow(4).poly ^ a ^ 1 != 0 {
+		a += 1;
+	}
+	Gf256::from_byte(a)
+};
+
+/// The smallest root of y^2 + y + LAMBDA in GF(256), the image of y
+pub const Y: Gf256 = {
+	let mut l = 0;
+	let mut i = 0;
+	while i < 4 {
+		if LAMBDA.to_byte() >> i & 1 == 1 {
+			l ^= OMEGA.pow(i).poly;
+		}
+		i += 1;
+	}
+	let mut a = 2;
+	while Gf256::from_byte(a).square().poly ^ a ^ l != 0 {
+		a += 1;
+	}
+	Gf256::from_byte(a)
+};
+
+/// TO_GF256[i] is the Gf256 image of the tower byte 1 << i
+pub const TO_GF256: [u8; 8] = {
+	let mut out = [0u8; 8];
+	let mut i = 0;
+	while i < 4 {
+		let w = OMEGA.pow(i as u64);
+		out[i] = w.poly;
+		out[i + 4] = w.const_mul(Y).poly;
+		i += 1;
+	}
+	out
+};
+
+/// FROM_GF256[i] is the tower byte of the Gf256 element 1 << i
+pub const FROM_GF256: [u8; 8] = {
+	let mut out = [0u8; 8];
+	let mut t = 0;
+	while t < 256 {
+		let g = apply(&TO_GF256, t as u8);
+		if g.count_ones() == 1 {
+			out[g.trailing_zeros() as usize] = t as u8;
+		}
+		t += 1;
+	}
+	out
+};
+
+/// The GF(2)-linear map with the given column images, applied to x
+const fn apply(columns: &[u8; 8], x: u8) -> u8 {
+	let mut acc = 0;
+	let mut i = 0;
+	while i < 8 {
+		acc ^= columns[i] & 0u8.wrapping_sub(x >> i & 1);
+		i += 1;
+	}
+	acc
+}
+
+/// The Gf256 counterpart of a Gf16 element
+pub const fn embed(x: Gf16) -> Gf256 {
+	Gf256::from_byte(apply(&TO_GF256, x.to_byte()))
+}
+
+/// The Gf16 element a stands for, if a lies in the subfield
+pub const fn restrict(a: Gf256) -> Option<Gf16> {
+	let t = Tower::from_gf256(a);
+	if t.hi.to_byte() == 0 {
+		Some(t.lo)
+	} else {
+		None
+	}
+}
+
+/// An element of GF((2^4)^2), see the module documentation
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Tower {
+	pub hi: Gf16,
+	pub lo: Gf16,
+}
+
+impl Tower {
+	pub const fn zero() -> Tower {
+		Tower { hi: Gf16::zero(), lo: Gf16::zero() }
+	}
+
+	pub const fn one() -> Tower {
+		Tower { hi: Gf16::zero(), lo: Gf16::one() }
+	}
+
+	/// hi in the high nibble
+	pub const fn from_byte(b: u8) -> Tower {
+		let (hi, lo) = Gf16::unpack(b);
+		Tower { hi, lo }
+	}
+
+	pub const fn to_byte(&self) -> u8 {
+		Gf16::pack(self.hi, self.lo)
+	}
+
+	pub const fn from_gf256(a: Gf256) -> Tower {
+		Tower::from_byte(apply(&FROM_GF256, a.poly))
+	}
+
+	pub const fn to_gf256(&self) -> Gf256 {
+		Gf256::from_byte(apply(&TO_GF256, self.to_byte()))
+	}
+
+	pub const fn const_mul(self, rhs: Tower) -> Tower {
+		let hh = self.hi.const_mul(rhs.hi);
+		let cross = self.hi.const_mul(rhs.lo).to_byte() ^ self.lo.const_mul(rhs.hi).to_byte();
+		let ll = self.lo.const_mul(rhs.lo);
+		// y^2 = y + LAMBDA
+		Tower {
+			hi: Gf16::from_byte(hh.to_byte() ^ cross),
+			lo: Gf16::from_byte(hh.const_mul(LAMBDA).to_byte() ^ ll.to_byte()),
+		}
+	}
+
+	/// None for zero
+	pub const fn inv(&self) -> Option<Tower> {
+		let (hi, lo) = (self.hi, self.lo);
+		let delta = hi.const_mul(hi).const_mul(LAMBDA).to_byte()
+			^ hi.const_mul(lo).to_byte() ^ lo.const_mul(lo).to_byte();
+		match Gf16::from_byte(delta).inv() {
+			None => None,
+			Some(d) => Some(Tower {
+				hi: hi.const_mul(d),
+				lo: Gf16::from_byte(hi.to_byte() ^ lo.to_byte()).const_mul(d),
+			}),
+		}
+	}
+}
+
+impl Add for Tower {
+	type Output = Tower;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Tower) -> Tower {
+		Tower { hi: self.hi + rhs.hi, lo: self.lo + rhs.lo }
+	}
+}
+
+impl Sub for Tower {
+	type Output = Tower;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: Tower) -> Tower {
+		Tower { hi: self.hi - rhs.hi, lo: self.lo - rhs.lo }
+	}
+}
+
+impl Mul for Tower {
+	type Output = Tower;
+	fn mul(self, rhs: Tower) -> Tower {
+		self.const_mul(rhs)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isomorphism() {
+        for a in Gf256::all() {
+            let t = Tower::from_gf256(a);
+            assert_eq!(t.to_gf256(), a);
+            for b in (0..=255u8).step_by(7).map(Gf256::from_byte) {
+                let u = Tower::from_gf256(b);
+                assert_eq!((t * u).to_gf256(), a * b);
+                assert_eq!((t + u).to_gf256(), a + b);
+            }
+            assert_eq!(t.inv().map(|i| i.to_gf256()), a.inv());
+        }
+        assert_eq!(apply(&FROM_GF256, apply(&TO_GF256, 0x5c)), 0x5c);
+    }
+
+    #[test]
+    fn test_subfield() {
+        let mut count = 0;
+        for a in Gf256::all() {
+            assert_eq!(a.is_in_subfield_gf16(), restrict(a).is_some());
+            count += a.is_in_subfield_gf16() as usize;
+        }
+        assert_eq!(count, 16);
+        for x in (0..16).map(Gf16::from_byte) {
+            assert_eq!(restrict(embed(x)), Some(x));
+            for y in (0..16).map(Gf16::from_byte) {
+                assert_eq!(embed(x * y), embed(x) * embed(y));
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Step by step explanations of arithmetic and decoding.
+//!
+//! Traced operations report what they do to a Sink as Events: the table
+//! lookups behind a multiplication, every Berlekamp-Massey iteration of a
+//! decode, the roots found by the Chien search and so on. Nothing is
+//! traced unless one of the traced entry points below (or
+//! RsCodec::decode_traced) is used, so the normal paths pay nothing.
+//!
+//! Events implement Display with a one line explanation; a Vec<String>
+//! is a Sink collecting exactly those lines (with the alloc feature).
+
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::{ string::{ String, ToString }, vec::Vec };
+
+use crate::Gf256;
+
+/// One step of a traced computation
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Event<'a> {
+	/// a * b through the log and exp tables. The logs are None for zero.
+	Mul { a: Gf256, b: Gf256, log_a: Option<u8>, log_b: Option<u8>, result: Gf256 },
+	/// a / b through the log and exp tables
+	Div { a: Gf256, b: Gf256, log_a: Option<u8>, log_b: u8, result: Gf256 },
+	/// The syndromes of a received word, S_0 first
+	Syndromes(&'a [Gf256]),
+	/// Berlekamp-Massey discrepancy at the given step
+	Discrepancy { step: usize, value: Gf256 },
+	/// Error locator after a step, lowest degree first
+	Locator { step: usize, lambda: &'a [Gf256] },
+	/// The Chien search found an error at the given position
+	Root { position: usize },
+	/// The error evaluator, lowest degree first
+	Evaluator(&'a [Gf256]),
+	/// Forney's formula gave the error value at a position
+	Magnitude { position: usize, value: Gf256 },
+	/// The decode ended, correcting the given number of symbols
+	Corrected(usize),
+	/// The decode gave up
+	Failed,
+}
+
+/// Receives trace events
+pub trait Sink {
+	fn event(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> Sink for F {
+	fn event(&mut self, event: &Event) {
+		self(event)
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for Vec<String> {
+	fn event(&mut self, event: &Event) {
+		self.push(event.to_string());
+	}
+}
+
+/// Discards every event; used by the untraced paths
+pub(crate) struct NoTrace;
+
+impl Sink for NoTrace {
+	#[inline]
+	fn event(&mut self, _event: &Event) {}
+}
+
+/// Writes a polynomial given lowest degree first, e.g. "1 + 0x0e x"
+fn write_poly(f: &mut fmt::Formatter, coeffs: &[Gf256]) -> fmt::Result {
+	let mut first = true;
+	for (i, c) in coeffs.iter().enumerate() {
+		if c.to_byte() == 0 {
+			continue;
+		}
+		if !first {
+			f.write_str(" + ")?;
+		}
+		first = false;
+		match i {
+			0 => write!(f, "{:#04x}", c.to_byte())?,
+			1 => write!(f, "{:#04x} x", c.to_byte())?,
+			_ => write!(f, "{:#04x} x^{}", c.to_byte(), i)?,
+		}
+	}
+	if first {
+		f.write_str("0")?;
+	}
+	Ok(())
+}
+
+impl fmt::Display for Event<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Event::Mul { a, b, log_a: Some(la), log_b: Some(lb), result } =>
+				write!(f, "{:#04x} * {:#04x}: log {} + log {} = {} (mod 255), exp gives {:#04x}",
+					a.to_byte(), b.to_byte(), la, lb, (la as u16 + lb as u16) % 255, result.to_byte()),
+			Event::Mul { a, b, .. } =>
+				write!(f, "{:#04x} * {:#04x}: a factor is zero, so the product is 0", a.to_byte(), b.to_byte()),
+			Event::Div { a, b, log_a: Some(la), log_b, result } =>
+				write!(f, "{:#04x} / {:#04x}: log {} - log {} = {} (mod 255), exp gives {:#04x}",
+					a.to_byte(), b.to_byte(), la, log_b, (la as u16 + 255 - log_b as u16) % 255, result.to_byte()),
+			Event::Div { a, b, .. } =>
+				write!(f, "{:#04x} / {:#04x}: the dividend is zero, so the quotient is 0", a.to_byte(), b.to_byte()),
+			Event::Syndromes(syn) => {
+				f.write_str("syndromes:")?;
+				for s in syn {
+					write!(f, " {:#04x}", s.to_byte())?;
+				}
+				Ok(())
+			},
+			Event::Discrepancy { step, value } =>
+				write!(f, "step {}: discrepancy {:#04x}", step, value.to_byte()),
+			Event::Locator { step, lambda } => {
+				write!(f, "step {}: locator ", step)?;
+				write_poly(f, lambda)
+			},
+			Event::Root { position } => write!(f, "locator root: error at position {}", position),
+			Event::Evaluator(omega) => {
+				f.write_str("evaluator ")?;
+				write_poly(f, omega)
+			},
+			Event::Magnitude { position, value } =>
+				write!(f, "position {}: error value {:#04x}", position, value.to_byte()),
+			Event::Corrected(count) => write!(f, "corrected {} symbols", count),
+			Event::Failed => f.write_str("too many errors"),
+		}
+	}
+}
+
+/// Multiplies a and b, reporting the table lookups
+pub fn mul(a: Gf256, b: Gf256, sink: &mut dyn Sink) -> Gf256 {
+	let result = a * b;
+	sink.event(&Event::Mul { a, b, log_a: a.log(), log_b: b.log(), result });
+	result
+}
+
+/// Divides a by b, reporting the table lookups. Panics if b is zero.
+pub fn div(a: Gf256, b: Gf256, sink: &mut dyn Sink) -> Gf256 {
+	let log_b = b.log().expect("Division by zero");
+	let result = a / b;
+	sink.event(&Event::Div { a, b, log_a: a.log(), log_b, result });
+	result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::{ RsCodec, Scratch };
+
+    #[test]
+    fn test_arithmetic() {
+        let mut lines: Vec<String> = Vec::new();
+        let p = mul(Gf256::from_byte(2), Gf256::from_byte(0x80), &mut lines);
+        assert_eq!(p, Gf256::from_byte(0x1d));
+        div(p, Gf256::from_byte(2), &mut lines);
+        mul(Gf256::zero(), p, &mut lines);
+        assert_eq!(lines, [
+            "0x02 * 0x80: log 1 + log 7 = 8 (mod 255), exp gives 0x1d",
+            "0x1d / 0x02: log 8 - log 1 = 7 (mod 255), exp gives 0x80",
+            "0x00 * 0x1d: a factor is zero, so the product is 0",
+        ]);
+    }
+
+    #[test]
+    fn test_decode_trace() {
+        let codec = RsCodec::<15, 11>::new();
+        let mut scratch = Scratch::new();
+        let mut cw = [0u8; 15];
+        cw[..11].copy_from_slice(b"hello world");
+        codec.encode(&mut cw);
+        let clean = cw;
+        cw[3] ^= 0x21;
+        cw[12] ^= 0x07;
+
+        let mut events = Vec::new();
+        let mut sink = |e: &Event| events.push(*e == Event::Root { position: 3 });
+        assert_eq!(codec.decode_traced(&mut cw, &mut scratch, &mut sink), Ok(2));
+        assert_eq!(cw, clean);
+        assert_eq!(events.iter().filter(|&&found| found).count(), 1);
+
+        // Four locator updates, one per syndrome
+        cw[3] ^= 0x21;
+        cw[12] ^= 0x07;
+        let mut lines: Vec<String> = Vec::new();
+        codec.decode_traced(&mut cw, &mut scratch, &mut lines).unwrap();
+        assert!(lines[0].starts_with("syndromes:"));
+        assert_eq!(lines.iter().filter(|l| l.contains(": locator")).count(), 4);
+        assert!(lines.contains(&"position 12: error value 0x07".to_string()));
+        assert_eq!(lines.last().unwrap(), "corrected 2 symbols");
+
+        let mut lines: Vec<String> = Vec::new();
+        assert_eq!(codec.decode_traced(&mut cw, &mut scratch, &mut lines), Ok(0));
+        assert_eq!(lines, ["syndromes: 0x00 0x00 0x00 0x00", "corrected 0 symbols"]);
+    }
+}
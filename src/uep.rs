@@ -0,0 +1,288 @@
+//! Unequal error protection.
+//!
+//! A Profile splits the input into consecutive regions and gives each its
+//! own number of RS parity bytes per block, so a header or keyframe can
+//! survive channel conditions that destroy the payload. Every region is
+//! cut into blocks of at most 255 - parity data bytes which are encoded
+//! as (shortened) RS codewords and concatenated in input order.
+//!
+//! Profiles can be written as text: a comma separated list of
+//! `length:parity` entries, where the length of the last entry may be `*`
+//! for "everything else". `"64:32,*:8"` protects the first 64 bytes with
+//! 32 parity bytes (16 correctable errors) per block and the rest with 8.
+
+use core::fmt;
+use core::error;
+use core::str::FromStr;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+use crate::rs::{ self, Buffers };
+
+/// Errors produced while parsing profiles or coding with them
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum UepError {
+	/// The profile description could not be parsed
+	Syntax,
+	/// Parity must be between 1 and 254 bytes per block
+	InvalidParity(usize),
+	/// Only the last region may extend to the end of the input
+	MisplacedRest,
+	/// The input does not match the lengths of the profile
+	LengthMismatch,
+	/// A block of the given region had too many errors
+	Uncorrectable { region: usize },
+}
+
+impl fmt::Display for UepError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			UepError::Syntax => f.write_str("invalid profile syntax"),
+			UepError::InvalidParity(p) => write!(f, "invalid parity of {} bytes", p),
+			UepError::MisplacedRest => f.write_str("only the last region may be open ended"),
+			UepError::LengthMismatch => f.write_str("input length does not match the profile"),
+			UepError::Uncorrectable { region } => write!(f, "uncorrectable block in region {}", region),
+		}
+	}
+}
+
+impl error::Error for UepError {}
+
+/// One region of a profile
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Region {
+	/// Number of input bytes, None for the rest of the input
+	pub len: Option<usize>,
+	/// Parity bytes per block
+	pub parity: usize,
+}
+
+impl Region {
+	/// Data bytes per block
+	fn block_data(&self) -> usize {
+		255 - self.parity
+	}
+
+	fn encoded_len(&self, len: usize) -> usize {
+		len + len.div_ceil(self.block_data()) * self.parity
+	}
+
+	/// Inverts encoded_len, if possible
+	fn decoded_len(&self, encoded: usize) -> Option<usize> {
+		let (full, rest) = (encoded / 255, encoded % 255);
+		let tail = match rest {
+			0 => 0,
+			r if r > self.parity => r - self.parity,
+			_ => return None,
+		};
+		Some(full * self.block_data() + tail)
+	}
+}
+
+/// A list of regions with their protection levels
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Profile {
+	regions: Vec<Region>,
+	/// Generator polynomial for every region
+	gens: Vec<Vec<Gf256>>,
+}
+
+impl Profile {
+	pub fn new(regions: Vec<Region>) -> Result<Profile, UepError> {
+		for (i, r) in regions.iter().enumerate() {
+			if r.parity == 0 || r.parity > 254 {
+				return Err(UepError::InvalidParity(r.parity));
+			}
+			if r.len.is_none() && i + 1 != regions.len() {
+				return Err(UepError::MisplacedRest);
+			}
+		}
+		let gens = regions.iter().map(|r| {
+			let mut gen = vec![Gf256::zero(); r.parity + 1];
+			rs::generator_into(r.parity, 0, &mut gen);
+			gen
+		}).collect();
+		Ok(Profile { regions, gens })
+	}
+
+	pub fn regions(&self) -> &[Region] {
+		&self.regions
+	}
+
+	/// Splits an input length into the lengths of every region
+	fn split(&self, total: usize) -> Result<Vec<usize>, UepError> {
+		let mut left = total;
+		let mut lens = Vec::with_capacity(self.regions.len());
+		for r in &self.regions {
+			let len = match r.len {
+				Some(l) => l.min(left),
+				None => left,
+			};
+			lens.push(len);
+			left -= len;
+		}
+		if left != 0 {
+			return Err(UepError::LengthMismatch);
+		}
+		Ok(lens)
+	}
+
+	/// Length of the encoding of an input of len bytes. Inputs shorter
+	/// than the fixed regions just leave the later regions empty.
+	pub fn encoded_len(&self, len: usize) -> Result<usize, UepError> {
+		let lens = self.split(len)?;
+		Ok(self.regions.iter().zip(lens).map(|(r, l)| r.encoded_len(l)).sum())
+	}
+
+	/// Encodes data according to the profile
+	pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, UepError> {
+		let lens = self.split(data.len())?;
+		let mut out = Vec::with_capacity(self.encoded_len(data.len())?);
+		let mut pos = 0;
+		for ((r, gen), len) in self.regions.iter().zip(&self.gens).zip(lens) {
+			for block in data[pos..pos + len].chunks(r.block_data()) {
+				out.extend_from_slice(block);
+				let start = out.len();
+				out.resize(start + r.parity, 0);
+				rs::encode_parity(gen, block, &mut out[start..]);
+			}
+			pos += len;
+		}
+		Ok(out)
+	}
+
+	/// Corrects and decodes an encoding produced by encode. Returns the
+	/// data and the number of corrected bytes.
+	pub fn decode(&self, encoded: &[u8]) -> Result<(Vec<u8>, usize), UepError> {
+		let lens = self.encoded_lens(encoded.len())?;
+		let mut bufs = vec![Gf256::zero(); 5 * 256];
+		let mut positions = [0usize; 128];
+		let mut out = Vec::with_capacity(encoded.len());
+		let mut corrected = 0;
+		let mut pos = 0;
+		for (i, (r, len)) in self.regions.iter().zip(lens).enumerate() {
+			for block in encoded[pos..pos + len].chunks(255) {
+				let mut cw = block.to_vec();
+				let (syn, rest) = bufs.split_at_mut(256);
+				let (lambda, rest) = rest.split_at_mut(256);
+				let (prev, rest) = rest.split_at_mut(256);
+				let (tmp, omega) = rest.split_at_mut(256);
+				let b = Buffers { syn, lambda, prev, tmp, omega, positions: &mut positions };
+				corrected += rs::decode_with(&mut cw, r.parity, 0, b)
+					.map_err(|_| UepError::Uncorrectable { region: i })?;
+				out.extend_from_slice(&cw[..cw.len() - r.parity]);
+			}
+			pos += len;
+		}
+		Ok((out, corrected))
+	}
+
+	/// Splits an encoded length into the encoded lengths of every region
+	fn encoded_lens(&self, total: usize) -> Result<Vec<usize>, UepError> {
+		let mut left = total;
+		let mut lens = Vec::with_capacity(self.regions.len());
+		for r in &self.regions {
+			let len = match r.len {
+				Some(l) => r.encoded_len(l).min(left),
+				None => left,
+			};
+			r.decoded_len(len).ok_or(UepError::LengthMismatch)?;
+			lens.push(len);
+			left -= len;
+		}
+		if left != 0 {
+			return Err(UepError::LengthMismatch);
+		}
+		Ok(lens)
+	}
+}
+
+impl FromStr for Profile {
+	type Err = UepError;
+
+	fn from_str(s: &str) -> Result<Profile, UepError> {
+		let regions = s.split(',').map(|entry| {
+			let (len, parity) = entry.trim().split_once(':').ok_or(UepError::Syntax)?;
+			let len = match len.trim() {
+				"*" => None,
+				l => Some(l.parse().map_err(|_| UepError::Syntax)?),
+			};
+			let parity = parity.trim().parse().map_err(|_| UepError::Syntax)?;
+			Ok(Region { len, parity })
+		}).collect::<Result<Vec<Region>, UepError>>()?;
+		Profile::new(regions)
+	}
+}
+
+impl fmt::Display for Profile {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, r) in self.regions.iter().enumerate() {
+			if i > 0 {
+				f.write_str(",")?;
+			}
+			match r.len {
+				Some(l) => write!(f, "{}:{}", l, r.parity)?,
+				None => write!(f, "*:{}", r.parity)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 131 + 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let profile: Profile = "64:32, *:8".parse().unwrap();
+        assert_eq!(profile.regions(), &[
+            Region { len: Some(64), parity: 32 },
+            Region { len: None, parity: 8 },
+        ]);
+        assert_eq!(profile.to_string(), "64:32,*:8");
+        assert_eq!("*:8,64:32".parse::<Profile>(), Err(UepError::MisplacedRest));
+        assert_eq!("64:0".parse::<Profile>(), Err(UepError::InvalidParity(0)));
+        assert_eq!("64".parse::<Profile>(), Err(UepError::Syntax));
+    }
+
+    #[test]
+    fn test_unequal_protection() {
+        let profile: Profile = "64:32,*:8".parse().unwrap();
+        let input = data(1000);
+        let mut encoded = profile.encode(&input).unwrap();
+        assert_eq!(encoded.len(), profile.encoded_len(1000).unwrap());
+        // 96 bytes of header codeword, then 936 bytes in 4 blocks of 247
+        assert_eq!(encoded.len(), 64 + 32 + 936 + 4 * 8);
+
+        // 16 errors in the header are fine
+        for i in 0..16 {
+            encoded[i * 5] ^= 0x33;
+        }
+        let (decoded, corrected) = profile.decode(&encoded).unwrap();
+        assert_eq!(decoded, input);
+        assert_eq!(corrected, 16);
+
+        // The same damage in the payload is not
+        let mut encoded = profile.encode(&input).unwrap();
+        for i in 0..16 {
+            encoded[200 + i * 5] ^= 0x33;
+        }
+        assert_eq!(profile.decode(&encoded), Err(UepError::Uncorrectable { region: 1 }));
+    }
+
+    #[test]
+    fn test_short_input() {
+        let profile: Profile = "100:10,50:20".parse().unwrap();
+        let input = data(30);
+        let encoded = profile.encode(&input).unwrap();
+        assert_eq!(encoded.len(), 40);
+        assert_eq!(profile.decode(&encoded).unwrap().0, input);
+        assert_eq!(profile.encode(&data(151)), Err(UepError::LengthMismatch));
+    }
+}
@@ -0,0 +1,442 @@
+//! Vectors of field elements stored as plain bytes.
+//!
+//! GfVec owns its storage, GfSlice is a borrowed view. Multiplying a view
+//! by a scalar does not compute anything by itself; it produces a Scaled
+//! expression which is consumed by the assignment operators, so
+//! `y += a * x.view()` runs a single fused multiply-accumulate pass over
+//! the bytes without a temporary vector.
+//!
+//! GfSlice and GfSliceMut also index, slice and iterate in terms of
+//! Gf256. This is free because Gf256 has the layout of a u8.
+
+use core::ops::{ Add, AddAssign, Bound, Index, IndexMut, Mul, MulAssign, RangeBounds };
+use core::iter::Copied;
+use core::slice;
+use alloc::{ vec, vec::Vec };
+
+use crate::{ Gf256, as_gf_slice, as_gf_slice_mut };
+use crate::bytes;
+
+/// An owned vector of field elements
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct GfVec {
+	bytes: Vec<u8>
+}
+
+/// A borrowed vector of field elements
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct GfSlice<'a> {
+	bytes: &'a [u8]
+}
+
+/// A mutably borrowed vector of field elements
+#[derive(PartialEq,Eq,Debug)]
+pub struct GfSliceMut<'a> {
+	bytes: &'a mut [u8]
+}
+
+/// The product of a scalar and a vector, waiting to be added somewhere
+#[derive(Copy,Clone,Debug)]
+pub struct Scaled<'a> {
+	scalar: Gf256,
+	vec: GfSlice<'a>
+}
+
+fn bounds<R: RangeBounds<usize>>(range: R) -> (Bound<usize>, Bound<usize>) {
+	(range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+impl GfVec {
+	pub fn new() -> GfVec {
+		GfVec { bytes: Vec::new() }
+	}
+
+	/// Returns the zero vector of length len
+	pub fn zeros(len: usize) -> GfVec {
+		GfVec { bytes: vec![0; len] }
+	}
+
+	pub fn from_bytes(bytes: Vec<u8>) -> GfVec {
+		GfVec { bytes }
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+		&mut self.bytes
+	}
+
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes
+	}
+
+	pub fn view(&self) -> GfSlice<'_> {
+		GfSlice { bytes: &self.bytes }
+	}
+
+	pub fn view_mut(&mut self) -> GfSliceMut<'_> {
+		GfSliceMut { bytes: &mut self.bytes }
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	pub fn get(&self, i: usize) -> Gf256 {
+		Gf256::from_byte(self.bytes[i])
+	}
+
+	pub fn set(&mut self, i: usize, value: Gf256) {
+		self.bytes[i] = value.to_byte();
+	}
+
+	/// self = self + a * x
+	pub fn axpy(&mut self, a: Gf256, x: GfSlice) {
+		bytes::mul_add(&mut self.bytes, x.bytes, a.to_byte());
+	}
+
+	/// self = a * self + x
+	pub fn scale_add(&mut self, a: Gf256, x: GfSlice) {
+		bytes::mul_scalar(&mut self.bytes, a.to_byte());
+		bytes::add_assign(&mut self.bytes, x.bytes);
+	}
+}
+
+impl<'a> GfSlice<'a> {
+	pub fn new(bytes: &'a [u8]) -> GfSlice<'a> {
+		GfSlice { bytes }
+	}
+
+	pub fn as_bytes(&self) -> &'a [u8] {
+		self.bytes
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	pub fn get(&self, i: usize) -> Gf256 {
+		Gf256::from_byte(self.bytes[i])
+	}
+
+	pub fn to_vec(&self) -> GfVec {
+		GfVec { bytes: self.bytes.to_vec() }
+	}
+
+	/// Returns the elements as a slice of Gf256
+	pub fn as_elements(&self) -> &'a [Gf256] {
+		as_gf_slice(self.bytes)
+	}
+
+	/// Returns the view of the given range of elements
+	pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> GfSlice<'a> {
+		GfSlice { bytes: &self.bytes[bounds(range)] }
+	}
+
+	pub fn iter(&self) -> Copied<slice::Iter<'a, Gf256>> {
+		self.as_elements().iter().copied()
+	}
+}
+
+impl<'a> GfSliceMut<'a> {
+	pub fn new(bytes: &'a mut [u8]) -> GfSliceMut<'a> {
+		GfSliceMut { bytes }
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		self.bytes
+	}
+
+	pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+		self.bytes
+	}
+
+	/// Reborrows as an immutable view
+	pub fn view(&self) -> GfSlice<'_> {
+		GfSlice { bytes: self.bytes }
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	pub fn get(&self, i: usize) -> Gf256 {
+		Gf256::from_byte(self.bytes[i])
+	}
+
+	pub fn set(&mut self, i: usize, value: Gf256) {
+		self.bytes[i] = value.to_byte();
+	}
+
+	pub fn as_elements(&self) -> &[Gf256] {
+		as_gf_slice(self.bytes)
+	}
+
+	pub fn as_elements_mut(&mut self) -> &mut [Gf256] {
+		as_gf_slice_mut(self.bytes)
+	}
+
+	/// Returns the mutable view of the given range of elements
+	pub fn slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> GfSliceMut<'_> {
+		GfSliceMut { bytes: &mut self.bytes[bounds(range)] }
+	}
+
+	pub fn iter(&self) -> Copied<slice::Iter<'_, Gf256>> {
+		self.as_elements().iter().copied()
+	}
+
+	pub fn iter_mut(&mut self) -> slice::IterMut<'_, Gf256> {
+		self.as_elements_mut().iter_mut()
+	}
+
+	/// self = self + a * x
+	pub fn axpy(&mut self, a: Gf256, x: GfSlice) {
+		bytes::mul_add(self.bytes, x.bytes, a.to_byte());
+	}
+}
+
+impl Index<usize> for GfVec {
+	type Output = Gf256;
+	fn index(&self, i: usize) -> &Gf256 {
+		&as_gf_slice(&self.bytes)[i]
+	}
+}
+
+impl IndexMut<usize> for GfVec {
+	fn index_mut(&mut self, i: usize) -> &mut Gf256 {
+		&mut as_gf_slice_mut(&mut self.bytes)[i]
+	}
+}
+
+impl Index<usize> for GfSlice<'_> {
+	type Output = Gf256;
+	fn index(&self, i: usize) -> &Gf256 {
+		&self.as_elements()[i]
+	}
+}
+
+impl Index<usize> for GfSliceMut<'_> {
+	type Output = Gf256;
+	fn index(&self, i: usize) -> &Gf256 {
+		&self.as_elements()[i]
+	}
+}
+
+impl IndexMut<usize> for GfSliceMut<'_> {
+	fn index_mut(&mut self, i: usize) -> &mut Gf256 {
+		&mut self.as_elements_mut()[i]
+	}
+}
+
+impl<'a> IntoIterator for GfSlice<'a> {
+	type Item = Gf256;
+	type IntoIter = Copied<slice::Iter<'a, Gf256>>;
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<'a> IntoIterator for GfSliceMut<'a> {
+	type Item = &'a mut Gf256;
+	type IntoIter = slice::IterMut<'a, Gf256>;
+	fn into_iter(self) -> Self::IntoIter {
+		as_gf_slice_mut(self.bytes).iter_mut()
+	}
+}
+
+impl From<Vec<u8>> for GfVec {
+	fn from(bytes: Vec<u8>) -> GfVec {
+		GfVec { bytes }
+	}
+}
+
+impl<'a> From<&'a [u8]> for GfSlice<'a> {
+	fn from(bytes: &'a [u8]) -> GfSlice<'a> {
+		GfSlice { bytes }
+	}
+}
+
+impl<'a> From<&'a GfVec> for GfSlice<'a> {
+	fn from(vec: &'a GfVec) -> GfSlice<'a> {
+		vec.view()
+	}
+}
+
+impl<'a> Mul<GfSlice<'a>> for Gf256 {
+	type Output = Scaled<'a>;
+	fn mul(self, vec: GfSlice<'a>) -> Scaled<'a> {
+		Scaled { scalar: self, vec }
+	}
+}
+
+impl<'a> Mul<&'a GfVec> for Gf256 {
+	type Output = Scaled<'a>;
+	fn mul(self, vec: &'a GfVec) -> Scaled<'a> {
+		Scaled { scalar: self, vec: vec.view() }
+	}
+}
+
+impl<'a> AddAssign<Scaled<'a>> for GfVec {
+	fn add_assign(&mut self, rhs: Scaled<'a>) {
+		self.axpy(rhs.scalar, rhs.vec);
+	}
+}
+
+impl<'a> AddAssign<GfSlice<'a>> for GfVec {
+	fn add_assign(&mut self, rhs: GfSlice<'a>) {
+		bytes::add_assign(&mut self.bytes, rhs.bytes);
+	}
+}
+
+impl<'a> AddAssign<&'a GfVec> for GfVec {
+	fn add_assign(&mut self, rhs: &'a GfVec) {
+		bytes::add_assign(&mut self.bytes, &rhs.bytes);
+	}
+}
+
+impl<'a> Add<&'a GfVec> for &'a GfVec {
+	type Output = GfVec;
+	fn add(self, rhs: &'a GfVec) -> GfVec {
+		let mut res = self.clone();
+		res += rhs;
+		res
+	}
+}
+
+impl<'a> AddAssign<Scaled<'a>> for GfSliceMut<'_> {
+	fn add_assign(&mut self, rhs: Scaled<'a>) {
+		self.axpy(rhs.scalar, rhs.vec);
+	}
+}
+
+impl<'a> AddAssign<GfSlice<'a>> for GfSliceMut<'_> {
+	fn add_assign(&mut self, rhs: GfSlice<'a>) {
+		bytes::add_assign(self.bytes, rhs.bytes);
+	}
+}
+
+impl MulAssign<Gf256> for GfSliceMut<'_> {
+	fn mul_assign(&mut self, rhs: Gf256) {
+		bytes::mul_scalar(self.bytes, rhs.to_byte());
+	}
+}
+
+impl MulAssign<Gf256> for GfVec {
+	fn mul_assign(&mut self, rhs: Gf256) {
+		bytes::mul_scalar(&mut self.bytes, rhs.to_byte());
+	}
+}
+
+impl Mul<Gf256> for &GfVec {
+	type Output = GfVec;
+	fn mul(self, rhs: Gf256) -> GfVec {
+		let mut res = self.clone();
+		res *= rhs;
+		res
+	}
+}
+
+impl<'a> From<Scaled<'a>> for GfVec {
+	fn from(scaled: Scaled<'a>) -> GfVec {
+		let mut res = scaled.vec.to_vec();
+		res *= scaled.scalar;
+		res
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elementwise(x: &[u8], a: Gf256, y: &[u8]) -> Vec<u8> {
+        x.iter().zip(y)
+            .map(|(&x, &y)| (a * Gf256::from_byte(x) + Gf256::from_byte(y)).to_byte())
+            .collect()
+    }
+
+    #[test]
+    fn test_axpy_operator() {
+        let x = GfVec::from_bytes((0..=255u8).collect());
+        let mut y = GfVec::from_bytes((0..=255u8).rev().collect());
+        let a = Gf256::from_byte(0x53);
+
+        let expected = elementwise(x.as_bytes(), a, y.as_bytes());
+        y += a * &x;
+        assert_eq!(y.as_bytes(), &expected[..]);
+    }
+
+    #[test]
+    fn test_scalar_special_cases() {
+        let x = GfVec::from_bytes(vec![1, 2, 3]);
+        assert_eq!(&x * Gf256::zero(), GfVec::zeros(3));
+        assert_eq!(&x * Gf256::one(), x);
+        assert_eq!(&x + &x, GfVec::zeros(3));
+    }
+
+    #[test]
+    fn test_scaled_into_vec() {
+        let bytes = [7u8, 0, 200];
+        let a = Gf256::from_byte(0x1d);
+        let v: GfVec = (a * GfSlice::new(&bytes)).into();
+        for (i, &b) in bytes.iter().enumerate() {
+            assert_eq!(v.get(i), a * Gf256::from_byte(b));
+        }
+    }
+
+    #[test]
+    fn test_views() {
+        let mut buf = [1u8, 2, 3, 4, 5, 6];
+        let x = GfSlice::new(&[7u8, 7]);
+        {
+            let mut view = GfSliceMut::new(&mut buf);
+            assert_eq!(view[2], Gf256::from_byte(3));
+            view[0] = Gf256::from_byte(9);
+            let mut tail = view.slice_mut(4..);
+            tail += Gf256::from_byte(2) * x;
+            for e in view.slice_mut(..2) {
+                *e *= Gf256::from_byte(2);
+            }
+        }
+        let expected = [
+            (Gf256::from_byte(9) * 2).to_byte(), 4, 3, 4,
+            (5 ^ (Gf256::from_byte(7) * 2).to_byte()), (6 ^ (Gf256::from_byte(7) * 2).to_byte()),
+        ];
+        assert_eq!(buf, expected);
+
+        let view = GfSlice::new(&buf);
+        let sum = view.slice(1..=3).iter().fold(Gf256::zero(), |a, b| a + b);
+        assert_eq!(sum, Gf256::from_byte(4 ^ 3 ^ 4));
+        assert_eq!(view.into_iter().count(), 6);
+    }
+
+    #[test]
+    fn test_vec_indexing() {
+        let mut v = GfVec::zeros(3);
+        v[1] = Gf256::from_byte(0xaa);
+        v.view_mut()[2] = Gf256::one();
+        assert_eq!(v.as_bytes(), &[0, 0xaa, 1]);
+        assert_eq!(v[1], Gf256::from_byte(0xaa));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_mismatch() {
+        let mut y = GfVec::zeros(3);
+        y += &GfVec::zeros(4);
+    }
+}
@@ -0,0 +1,324 @@
+//! Deterministic golden test vectors.
+//!
+//! generate derives inputs from a seed with splitmix64 and runs them
+//! through a codec, so the same seed and Params always produce the same
+//! vectors. Implementations in other languages can pin their conformance
+//! tests to the output. Vector::to_json writes one line of JSON per
+//! vector with byte strings in lowercase hex; the `gf256 vectors`
+//! subcommand prints them for all codecs.
+//!
+//! The vectors are:
+//! - rs: input is a k byte message, output the n = k + parity codeword
+//!   (message first), as produced by rs::RsCodec.
+//! - shamir: input is the secret, random the coefficient bytes handed to
+//!   split_into in the order it asked for them, output the data of the
+//!   shares with x = 1, 2, ... concatenated.
+//! - crc32: the IEEE CRC-32 of the input, big endian.
+//! - scrambler: input is one group of 8 transport stream packets, output
+//!   the same packets after DVB energy dispersal.
+
+use std::fmt;
+use std::error;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::Gf256;
+use crate::dvb::{ Randomizer, SYNC, TS_PACKET_LEN };
+use crate::recovery::crc32;
+use crate::rs;
+use crate::shamir::{ self, Share, MAX_SHARES };
+
+/// Longest secret in a shamir vector
+const MAX_SECRET: usize = 64;
+
+/// Errors produced by generate
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum VectorError {
+	/// The parameters do not fit the codec
+	InvalidParameters,
+	/// Unknown codec name
+	UnknownCodec,
+}
+
+impl fmt::Display for VectorError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			VectorError::InvalidParameters => "invalid vector parameters",
+			VectorError::UnknownCodec => "unknown codec",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for VectorError {}
+
+/// The codecs vectors can be generated for
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Codec {
+	Rs,
+	Shamir,
+	Crc32,
+	Scrambler,
+}
+
+impl Codec {
+	pub const ALL: [Codec; 4] = [Codec::Rs, Codec::Shamir, Codec::Crc32, Codec::Scrambler];
+
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Codec::Rs => "rs",
+			Codec::Shamir => "shamir",
+			Codec::Crc32 => "crc32",
+			Codec::Scrambler => "scrambler",
+		}
+	}
+}
+
+impl fmt::Display for Codec {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+impl FromStr for Codec {
+	type Err = VectorError;
+
+	fn from_str(s: &str) -> Result<Codec, VectorError> {
+		Codec::ALL.iter().copied().find(|c| c.name() == s).ok_or(VectorError::UnknownCodec)
+	}
+}
+
+/// Parameters of a vector set
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct Params {
+	/// Number of vectors per codec
+	pub count: usize,
+	/// Message, secret and CRC input length
+	pub len: usize,
+	/// RS parity symbols
+	pub rs_parity: usize,
+	pub shamir_threshold: u8,
+	pub shamir_shares: u8,
+}
+
+impl Default for Params {
+	fn default() -> Params {
+		Params { count: 4, len: 16, rs_parity: 8, shamir_threshold: 3, shamir_shares: 5 }
+	}
+}
+
+/// One test vector
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Vector {
+	pub codec: Codec,
+	pub index: usize,
+	/// Codec parameters, by name
+	pub params: Vec<(&'static str, usize)>,
+	/// Byte strings (input, output, ...), by name
+	pub fields: Vec<(&'static str, Vec<u8>)>,
+}
+
+impl Vector {
+	/// Returns the field of the given name
+	pub fn field(&self, name: &str) -> Option<&[u8]> {
+		self.fields.iter().find(|(n, _)| *n == name).map(|(_, v)| &v[..])
+	}
+
+	/// One line of JSON, without a trailing newline
+	pub fn to_json(&self) -> String {
+		let mut out = String::new();
+		write!(out, "{{\"codec\":\"{}\",\"index\":{},\"params\":{{", self.codec, self.index).unwrap();
+		for (i, (name, value)) in self.params.iter().enumerate() {
+			let sep = if i == 0 { "" } else { "," };
+			write!(out, "{}\"{}\":{}", sep, name, value).unwrap();
+		}
+		out.push('}');
+		for (name, bytes) in &self.fields {
+			write!(out, ",\"{}\":\"", name).unwrap();
+			for b in bytes {
+				write!(out, "{:02x}", b).unwrap();
+			}
+			out.push('"');
+		}
+		out.push('}');
+		out
+	}
+}
+
+/// splitmix64
+struct Source {
+	state: u64,
+}
+
+impl Source {
+	fn new(seed: u64, codec: Codec, index: usize) -> Source {
+		let mut s = Source { state: seed };
+		s.state ^= s.next() ^ ((codec as u64) << 32 | index as u64);
+		Source { state: s.next() }
+	}
+
+	fn next(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	fn fill(&mut self, buf: &mut [u8]) {
+		for b in buf.iter_mut() {
+			*b = self.next() as u8;
+		}
+	}
+
+	fn bytes(&mut self, len: usize) -> Vec<u8> {
+		let mut v = vec![0u8; len];
+		self.fill(&mut v);
+		v
+	}
+}
+
+fn rs_vector(src: &mut Source, p: &Params) -> Result<Vector, VectorError> {
+	let (k, nsym) = (p.len, p.rs_parity);
+	if k == 0 || nsym == 0 || k + nsym > 255 {
+		return Err(VectorError::InvalidParameters);
+	}
+	let mut gen = vec![Gf256::zero(); nsym + 1];
+	rs::generator_into(nsym, 0, &mut gen);
+	let input = src.bytes(k);
+	let mut output = input.clone();
+	output.resize(k + nsym, 0);
+	rs::encode_parity(&gen, &input, &mut output[k..]);
+	Ok(Vector {
+		codec: Codec::Rs,
+		index: 0,
+		params: vec![("n", k + nsym), ("k", k)],
+		fields: vec![("input", input), ("output", output)],
+	})
+}
+
+fn shamir_vector(src: &mut Source, p: &Params) -> Result<Vector, VectorError> {
+	let (t, n) = (p.shamir_threshold, p.shamir_shares as usize);
+	if p.len > MAX_SECRET || t == 0 || t as usize > n {
+		return Err(VectorError::InvalidParameters);
+	}
+	let input = src.bytes(p.len);
+	let mut random = Vec::new();
+	// Shares are evaluated at x = 1, 2, ... regardless of their number,
+	// so the first n of MAX_SHARES are the shares of an n share split.
+	let mut shares = Box::new([Share::<MAX_SECRET>::empty(); MAX_SHARES]);
+	shamir::split_into(&input, t, &mut shares, |buf: &mut [u8]| {
+		src.fill(buf);
+		random.extend_from_slice(buf);
+	}).map_err(|_| VectorError::InvalidParameters)?;
+	let output = shares[..n].iter().flat_map(|s| s.data().to_vec()).collect();
+	Ok(Vector {
+		codec: Codec::Shamir,
+		index: 0,
+		params: vec![("threshold", t as usize), ("shares", n)],
+		fields: vec![("input", input), ("random", random), ("output", output)],
+	})
+}
+
+fn crc_vector(src: &mut Source, p: &Params) -> Vector {
+	let input = src.bytes(p.len);
+	let output = crc32(&input).to_be_bytes().to_vec();
+	Vector {
+		codec: Codec::Crc32,
+		index: 0,
+		params: vec![("len", p.len)],
+		fields: vec![("input", input), ("output", output)],
+	}
+}
+
+fn scrambler_vector(src: &mut Source) -> Vector {
+	let mut randomizer = Randomizer::new();
+	let mut input = Vec::with_capacity(8 * TS_PACKET_LEN);
+	let mut output = Vec::with_capacity(8 * TS_PACKET_LEN);
+	for _ in 0..8 {
+		let mut packet = [0u8; TS_PACKET_LEN];
+		src.fill(&mut packet);
+		packet[0] = SYNC;
+		input.extend_from_slice(&packet);
+		randomizer.randomize(&mut packet);
+		output.extend_from_slice(&packet);
+	}
+	Vector {
+		codec: Codec::Scrambler,
+		index: 0,
+		params: vec![("packets", 8)],
+		fields: vec![("input", input), ("output", output)],
+	}
+}
+
+/// Generates params.count vectors for codec
+pub fn generate(codec: Codec, params: &Params, seed: u64) -> Result<Vec<Vector>, VectorError> {
+	(0..params.count).map(|index| {
+		let mut src = Source::new(seed, codec, index);
+		let mut v = match codec {
+			Codec::Rs => rs_vector(&mut src, params)?,
+			Codec::Shamir => shamir_vector(&mut src, params)?,
+			Codec::Crc32 => crc_vector(&mut src, params),
+			Codec::Scrambler => scrambler_vector(&mut src),
+		};
+		v.index = index;
+		Ok(v)
+	}).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rs::{ RsCodec, Scratch };
+    use crate::shamir::recover_into;
+
+    #[test]
+    fn test_deterministic() {
+        let params = Params::default();
+        for codec in Codec::ALL {
+            let a = generate(codec, &params, 42).unwrap();
+            assert_eq!(a, generate(codec, &params, 42).unwrap());
+            assert_ne!(a, generate(codec, &params, 43).unwrap());
+            assert_ne!(a[0].field("input"), a[1].field("input"));
+        }
+        assert_eq!("crc32".parse(), Ok(Codec::Crc32));
+        assert_eq!("md5".parse::<Codec>(), Err(VectorError::UnknownCodec));
+    }
+
+    #[test]
+    fn test_vectors_check_out() {
+        let params = Params { count: 2, len: 15, rs_parity: 4, ..Params::default() };
+        for v in generate(Codec::Rs, &params, 1).unwrap() {
+            let mut cw: [u8; 19] = v.field("output").unwrap().try_into().unwrap();
+            assert_eq!(&cw[..15], v.field("input").unwrap());
+            assert!(RsCodec::<19, 15>::new().is_valid(&cw, &mut Scratch::new()));
+            cw[0] ^= 1;
+            assert!(!RsCodec::<19, 15>::new().is_valid(&cw, &mut Scratch::new()));
+        }
+        for v in generate(Codec::Shamir, &params, 1).unwrap() {
+            let out = v.field("output").unwrap();
+            let shares: Vec<Share<64>> = out.chunks(15).take(3).enumerate()
+                .map(|(i, d)| Share::from_parts(i as u8 + 1, 3, d).unwrap())
+                .collect();
+            let mut secret = [0u8; 64];
+            let len = recover_into(&shares, &mut secret).unwrap();
+            assert_eq!(&secret[..len], v.field("input").unwrap());
+            assert_eq!(v.field("random").unwrap().len(), 15 * 2);
+        }
+        let bad = Params { len: 252, ..params };
+        assert_eq!(generate(Codec::Rs, &bad, 1), Err(VectorError::InvalidParameters));
+    }
+
+    #[test]
+    fn test_json() {
+        let v = Vector {
+            codec: Codec::Crc32,
+            index: 3,
+            params: vec![("len", 9)],
+            fields: vec![("input", b"123456789".to_vec()), ("output", crc32(b"123456789").to_be_bytes().to_vec())],
+        };
+        assert_eq!(v.to_json(), "{\"codec\":\"crc32\",\"index\":3,\"params\":{\"len\":9},\
+            \"input\":\"313233343536373839\",\"output\":\"cbf43926\"}");
+    }
+}
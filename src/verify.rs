@@ -0,0 +1,311 @@
+//! Checks whether a reduction polynomial defines GF(256).
+//!
+//! The arithmetic here is done from scratch with carry-less
+//! multiplication, so it does not depend on the tables of the built-in
+//! field and can vet a polynomial before any tables are made from it.
+//!
+//! verify_field spot checks the axioms. check_field goes all the way for
+//! a polynomial and generator: it checks the tables built from them entry
+//! by entry against the carry-less arithmetic, and then all axioms for
+//! every pair and triple of elements with table multiplication. That is
+//! 2^24 triples, a fraction of a second in an optimized build, so it can
+//! run at startup.
+
+use core::fmt;
+use core::error;
+
+use crate::ctx::{ Tables, TableError };
+use crate::tables;
+
+/// The field axiom which failed a spot check
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum Axiom {
+	Commutativity,
+	Associativity,
+	Distributivity,
+}
+
+/// Why a polynomial does not give a field
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum FieldDefect {
+	/// The polynomial is not of degree 8
+	WrongDegree(u16),
+	/// The polynomial is the product of factor and another polynomial
+	Reducible { factor: u16 },
+	/// This element has no multiplicative inverse
+	NoInverse(u8),
+	/// The axiom failed for these elements
+	AxiomViolation { axiom: Axiom, a: u8, b: u8, c: u8 },
+}
+
+impl fmt::Display for FieldDefect {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			FieldDefect::WrongDegree(p) => write!(f, "polynomial {:#x} is not of degree 8", p),
+			FieldDefect::Reducible { factor } => write!(f, "polynomial has the factor {:#x}", factor),
+			FieldDefect::NoInverse(a) => write!(f, "element {:#x} has no inverse", a),
+			FieldDefect::AxiomViolation { axiom, a, b, c } =>
+				write!(f, "{:?} fails for {:#x}, {:#x}, {:#x}", axiom, a, b, c),
+		}
+	}
+}
+
+impl error::Error for FieldDefect {}
+
+/// Properties of a valid field polynomial
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct FieldReport {
+	/// The polynomial which was checked, including the x^8 term
+	pub poly: u16,
+	/// Whether x generates the multiplicative group. Log tables with base
+	/// x (as this crate uses) need a primitive polynomial.
+	pub primitive: bool,
+	/// The smallest element generating the multiplicative group
+	pub generator: u8,
+}
+
+/// A reduction polynomial and the generator of its log tables
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub struct FieldConfig {
+	/// Including the x^8 term
+	pub poly: u16,
+	pub generator: u8,
+}
+
+impl FieldConfig {
+	/// The configuration of Gf256
+	pub const BUILTIN: FieldConfig = FieldConfig { poly: crate::POLYNOMIAL, generator: 2 };
+
+	pub const fn new(poly: u16, generator: u8) -> FieldConfig {
+		FieldConfig { poly, generator }
+	}
+}
+
+/// Why check_field rejected a configuration
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum FieldError {
+	/// The polynomial does not give a field
+	Defect(FieldDefect),
+	/// The generator does not generate the multiplicative group
+	NotAGenerator(u8),
+	/// A table entry disagrees with the arithmetic
+	Table(TableError),
+	/// Table multiplication of a and b disagrees with the arithmetic
+	Product { a: u8, b: u8 },
+}
+
+impl fmt::Display for FieldError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			FieldError::Defect(d) => write!(f, "{}", d),
+			FieldError::NotAGenerator(g) => write!(f, "{:#x} does not generate the multiplicative group", g),
+			FieldError::Table(e) => write!(f, "{}", e),
+			FieldError::Product { a, b } => write!(f, "table product of {:#x} and {:#x} is wrong", a, b),
+		}
+	}
+}
+
+impl error::Error for FieldError {}
+
+impl From<FieldDefect> for FieldError {
+	fn from(d: FieldDefect) -> FieldError {
+		FieldError::Defect(d)
+	}
+}
+
+/// Multiplies a and b modulo poly
+fn mul(a: u8, b: u8, poly: u16) -> u8 {
+	let mut acc: u16 = 0;
+	for bit in 0..8 {
+		if b >> bit & 1 == 1 {
+			acc ^= (a as u16) << bit;
+		}
+	}
+	for bit in (8..16).rev() {
+		if acc >> bit & 1 == 1 {
+			acc ^= poly << (bit - 8);
+		}
+	}
+	acc as u8
+}
+
+/// Remainder of a divided by b, both polynomials over GF(2)
+fn rem(mut a: u16, b: u16) -> u16 {
+	let db = 15 - b.leading_zeros();
+	while a != 0 && 15 - a.leading_zeros() >= db {
+		a ^= b << (15 - a.leading_zeros() - db);
+	}
+	a
+}
+
+/// Multiplicative order of a nonzero element
+fn order(a: u8, poly: u16) -> usize {
+	let mut x = a;
+	let mut n = 1;
+	while x != 1 {
+		x = mul(x, a, poly);
+		n += 1;
+		if n > 255 {
+			break;
+		}
+	}
+	n
+}
+
+/// Checks that poly (e.g. 0x11D) is irreducible of degree 8 and that the
+/// resulting arithmetic behaves like a field.
+pub fn verify_field(poly: u16) -> Result<FieldReport, FieldDefect> {
+	if poly >> 8 != 1 {
+		return Err(FieldDefect::WrongDegree(poly));
+	}
+	// A reducible polynomial of degree 8 has a factor of degree at most 4
+	if let Some(factor) = (2u16..32).find(|&d| rem(poly, d) == 0) {
+		return Err(FieldDefect::Reducible { factor });
+	}
+
+	for a in 1..=255u8 {
+		if !(1..=255u8).any(|b| mul(a, b, poly) == 1) {
+			return Err(FieldDefect::NoInverse(a));
+		}
+	}
+
+	// Spot checks over a spread of triples. The inverse check above
+	// already implies a field, this catches broken multiplication code.
+	let samples = (0..=255u8).step_by(17).chain([0x80, 0xff, 0x1d]);
+	for a in samples.clone() {
+		for b in samples.clone() {
+			if mul(a, b, poly) != mul(b, a, poly) {
+				return Err(FieldDefect::AxiomViolation { axiom: Axiom::Commutativity, a, b, c: 0 });
+			}
+			for c in samples.clone() {
+				if mul(mul(a, b, poly), c, poly) != mul(a, mul(b, c, poly), poly) {
+					return Err(FieldDefect::AxiomViolation { axiom: Axiom::Associativity, a, b, c });
+				}
+				if mul(a, b ^ c, poly) != mul(a, b, poly) ^ mul(a, c, poly) {
+					return Err(FieldDefect::AxiomViolation { axiom: Axiom::Distributivity, a, b, c });
+				}
+			}
+		}
+	}
+
+	let generator = (2..=255u8).find(|&g| order(g, poly) == 255)
+		.expect("The multiplicative group of a field is cyclic");
+	Ok(FieldReport { poly, primitive: generator == 2, generator })
+}
+
+
+/// Verifies config exhaustively, see the module documentation
+pub fn check_field(config: FieldConfig) -> Result<(), FieldError> {
+	verify_field(config.poly)?;
+	// tables() panics on a generator of lower order
+	if config.generator == 0 || order(config.generator, config.poly) != 255 {
+		return Err(FieldError::NotAGenerator(config.generator));
+	}
+	check_tables(config, &tables::tables(config.poly, config.generator))
+}
+
+/// Checks that tables are the exp, log and inverse tables of config and
+/// that multiplying through them satisfies the field axioms. config.poly
+/// has to be irreducible, which check_field verifies first.
+pub fn check_tables(config: FieldConfig, t: &Tables) -> Result<(), FieldError> {
+	let FieldConfig { poly, generator } = config;
+	if generator == 0 || order(generator, poly) != 255 {
+		return Err(FieldError::NotAGenerator(generator));
+	}
+	let mut x = 1u8;
+	for i in 0..255 {
+		if t.exp[i] != x {
+			return Err(FieldError::Table(TableError::Exp(i)));
+		}
+		if t.log[x as usize] as usize != i {
+			return Err(FieldError::Table(TableError::Log(x as usize)));
+		}
+		x = mul(x, generator, poly);
+	}
+	if t.exp[255] != 1 {
+		return Err(FieldError::Table(TableError::Exp(255)));
+	}
+	if t.inv[0] != 0 {
+		return Err(FieldError::Table(TableError::Inv(0)));
+	}
+	if let Some(a) = (1..256).find(|&a| mul(a as u8, t.inv[a], poly) != 1) {
+		return Err(FieldError::Table(TableError::Inv(a)));
+	}
+
+	let tmul = |a: u8, b: u8| -> u8 {
+		if a == 0 || b == 0 {
+			0
+		} else {
+			t.exp[(t.log[a as usize] as usize + t.log[b as usize] as usize) % 255]
+		}
+	};
+	for a in 0..=255u8 {
+		for b in 0..=255u8 {
+			if tmul(a, b) != mul(a, b, poly) {
+				return Err(FieldError::Product { a, b });
+			}
+		}
+	}
+	let violation = |axiom, a, b, c| FieldError::Defect(FieldDefect::AxiomViolation { axiom, a, b, c });
+	for a in 0..=255u8 {
+		for b in 0..=255u8 {
+			let ab = tmul(a, b);
+			if ab != tmul(b, a) {
+				return Err(violation(Axiom::Commutativity, a, b, 0));
+			}
+			for c in 0..=255u8 {
+				if tmul(ab, c) != tmul(a, tmul(b, c)) {
+					return Err(violation(Axiom::Associativity, a, b, c));
+				}
+				if tmul(a, b ^ c) != ab ^ tmul(a, c) {
+					return Err(violation(Axiom::Distributivity, a, b, c));
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_poly() {
+        let report = verify_field(crate::POLYNOMIAL).unwrap();
+        assert!(report.primitive);
+        assert_eq!(report.generator, 2);
+    }
+
+    #[test]
+    fn test_aes_poly() {
+        // x^8 + x^4 + x^3 + x + 1 is irreducible but x has order 51
+        let report = verify_field(0x11B).unwrap();
+        assert!(!report.primitive);
+        assert_eq!(report.generator, 3);
+    }
+
+    #[test]
+    fn test_defects() {
+        assert_eq!(verify_field(0x1D), Err(FieldDefect::WrongDegree(0x1D)));
+        // x^8 + 1 = (x + 1)^8
+        assert_eq!(verify_field(0x101), Err(FieldDefect::Reducible { factor: 3 }));
+        // x^8 + x^4 + x^3 + x^2 = x^2 (x^6 + x^2 + x + 1)
+        assert_eq!(verify_field(0x11C), Err(FieldDefect::Reducible { factor: 2 }));
+    }
+
+    #[test]
+    fn test_check_field() {
+        assert_eq!(check_field(FieldConfig::BUILTIN), Ok(()));
+        assert_eq!(check_field(FieldConfig::new(0x101, 2)), Err(FieldError::Defect(FieldDefect::Reducible { factor: 3 })));
+        // The AES polynomial needs another generator than x
+        assert_eq!(check_field(FieldConfig::new(0x11b, 2)), Err(FieldError::NotAGenerator(2)));
+        let mut t = tables::tables(0x11d, 2);
+        t.exp[7] ^= 1;
+        assert_eq!(check_tables(FieldConfig::BUILTIN, &t), Err(FieldError::Table(TableError::Exp(7))));
+        let mut t = tables::tables(0x11d, 2);
+        t.inv[9] = 1;
+        assert_eq!(check_tables(FieldConfig::BUILTIN, &t), Err(FieldError::Table(TableError::Inv(9))));
+    }
+}
@@ -0,0 +1,259 @@
+//! Weight distributions of linear codes and the MacWilliams identity.
+//!
+//! A_w is the number of codewords of Hamming weight w. For codes small
+//! enough to list, enumerate walks all q^k combinations of the rows of a
+//! generator matrix (see CyclicCode::generator_matrix). For MDS codes,
+//! which includes every RS code, mds gives the closed form instead.
+//!
+//! The MacWilliams identity turns the distribution of a code into the one
+//! of its dual: B_j = 1/|C| sum_i A_i K_j(i), where K_j is the Krawtchouk
+//! polynomial for length n and alphabet size q. Counts are exact u128
+//! values; distributions that do not fit report Overflow.
+
+use core::fmt;
+use core::error;
+use alloc::{ vec, vec::Vec };
+
+use crate::Gf256;
+
+/// Field size of Gf256
+const Q: u128 = 256;
+
+/// Most codewords enumerate will list
+pub const MAX_ENUMERATED: u128 = 1 << 24;
+
+/// Errors produced by the weight analysis
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum WeightError {
+	/// Code parameters are inconsistent (e.g. k > n or ragged rows)
+	InvalidParameters,
+	/// The code has more than MAX_ENUMERATED codewords
+	TooLarge,
+	/// An intermediate value does not fit 128 bits
+	Overflow,
+}
+
+impl fmt::Display for WeightError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match *self {
+			WeightError::InvalidParameters => "invalid code parameters",
+			WeightError::TooLarge => "too many codewords to enumerate",
+			WeightError::Overflow => "weight counts overflow",
+		};
+		f.write_str(msg)
+	}
+}
+
+impl error::Error for WeightError {}
+
+fn binomial(n: usize, k: usize) -> Option<i128> {
+	if k > n {
+		return Some(0);
+	}
+	let k = k.min(n - k);
+	let mut acc: i128 = 1;
+	for i in 0..k {
+		acc = acc.checked_mul((n - i) as i128)? / (i as i128 + 1);
+	}
+	Some(acc)
+}
+
+fn power(q: u128, e: usize) -> Option<i128> {
+	(q as i128).checked_pow(e as u32)
+}
+
+/// Number of codewords of every weight 0..=n of a linear code
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct WeightDistribution {
+	/// Alphabet size
+	q: u128,
+	counts: Vec<u128>,
+}
+
+impl WeightDistribution {
+	/// Wraps counts[w] = A_w for a code over an alphabet of q symbols
+	pub fn from_counts(q: u128, counts: Vec<u128>) -> WeightDistribution {
+		assert!(q >= 2 && !counts.is_empty(), "Invalid distribution");
+		WeightDistribution { q, counts }
+	}
+
+	/// Code length
+	pub fn n(&self) -> usize {
+		self.counts.len() - 1
+	}
+
+	pub fn counts(&self) -> &[u128] {
+		&self.counts
+	}
+
+	/// A_w
+	pub fn count(&self, w: usize) -> u128 {
+		self.counts[w]
+	}
+
+	/// Total number of codewords
+	pub fn size(&self) -> u128 {
+		self.counts.iter().sum()
+	}
+
+	/// Smallest nonzero weight, None for the zero code
+	pub fn min_distance(&self) -> Option<usize> {
+		(1..self.counts.len()).find(|&w| self.counts[w] != 0)
+	}
+
+	/// Whether the minimum distance meets the Singleton bound n - k + 1
+	pub fn is_mds(&self) -> bool {
+		let (mut size, mut k) = (self.size(), 0);
+		while size > 1 && size.is_multiple_of(self.q) {
+			size /= self.q;
+			k += 1;
+		}
+		size == 1 && self.min_distance().is_some_and(|d| d == self.n() + 1 - k)
+	}
+
+	/// Distribution of the dual code by the MacWilliams identity
+	pub fn dual(&self) -> Result<WeightDistribution, WeightError> {
+		let n = self.n();
+		let size = i128::try_from(self.size()).map_err(|_| WeightError::Overflow)?;
+		let counts = (0..=n).map(|j| {
+			let mut sum: i128 = 0;
+			for (i, &a) in self.counts.iter().enumerate() {
+				if a == 0 {
+					continue;
+				}
+				let a = i128::try_from(a).ok()?;
+				sum = sum.checked_add(a.checked_mul(self.krawtchouk(j, i)?)?)?;
+			}
+			if sum < 0 || sum % size != 0 {
+				return None;
+			}
+			Some((sum / size) as u128)
+		}).collect::<Option<Vec<u128>>>().ok_or(WeightError::Overflow)?;
+		Ok(WeightDistribution { q: self.q, counts })
+	}
+
+	/// K_j(i) = sum_s (-1)^s (q-1)^(j-s) C(i, s) C(n-i, j-s)
+	fn krawtchouk(&self, j: usize, i: usize) -> Option<i128> {
+		let n = self.n();
+		let mut acc: i128 = 0;
+		for s in 0..=j {
+			let term = power(self.q - 1, j - s)?
+				.checked_mul(binomial(i, s)?)?
+				.checked_mul(binomial(n - i, j - s)?)?;
+			acc = if s % 2 == 0 { acc.checked_add(term)? } else { acc.checked_sub(term)? };
+		}
+		Some(acc)
+	}
+}
+
+/// Counts the weights of all combinations of the generator matrix rows
+pub fn enumerate(generator: &[Vec<Gf256>]) -> Result<WeightDistribution, WeightError> {
+	let k = generator.len();
+	let n = generator.first().map_or(0, |r| r.len());
+	if n == 0 || generator.iter().any(|r| r.len() != n) {
+		return Err(WeightError::InvalidParameters);
+	}
+	if Q.checked_pow(k as u32).is_none_or(|size| size > MAX_ENUMERATED) {
+		return Err(WeightError::TooLarge);
+	}
+	let mut counts = vec![0u128; n + 1];
+	let mut msg = vec![0u8; k];
+	let mut word = vec![Gf256::zero(); n];
+	loop {
+		for w in word.iter_mut() {
+			*w = Gf256::zero();
+		}
+		for (&m, row) in msg.iter().zip(generator) {
+			for (w, &g) in word.iter_mut().zip(row) {
+				*w += m * g;
+			}
+		}
+		counts[word.iter().filter(|w| w.to_byte() != 0).count()] += 1;
+		// Next message, as a base 256 counter
+		let Some(i) = msg.iter().position(|&m| m != 255) else {
+			break;
+		};
+		msg[i] += 1;
+		for m in &mut msg[..i] {
+			*m = 0;
+		}
+	}
+	Ok(WeightDistribution { q: Q, counts })
+}
+
+/// Weight distribution of any [n, k] MDS code over Gf256:
+/// A_w = C(n, w) (q - 1) sum_j (-1)^j C(w - 1, j) q^(w - d - j) for w >= d
+pub fn mds(n: usize, k: usize) -> Result<WeightDistribution, WeightError> {
+	if k == 0 || k > n {
+		return Err(WeightError::InvalidParameters);
+	}
+	let d = n - k + 1;
+	let mut counts = vec![0u128; n + 1];
+	counts[0] = 1;
+	for (w, c) in counts.iter_mut().enumerate().skip(d) {
+		let value = (|| {
+			let mut sum: i128 = 0;
+			for j in 0..=w - d {
+				let term = binomial(w - 1, j)?.checked_mul(power(Q, w - d - j)?)?;
+				sum = if j % 2 == 0 { sum.checked_add(term)? } else { sum.checked_sub(term)? };
+			}
+			binomial(n, w)?.checked_mul(Q as i128 - 1)?.checked_mul(sum)
+		})().ok_or(WeightError::Overflow)?;
+		*c = value as u128;
+	}
+	Ok(WeightDistribution { q: Q, counts })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cyclic::CyclicCode;
+
+    /// RS code of length 15 with 2 message symbols, using b = a^17 of order 15
+    fn rs15() -> CyclicCode {
+        let b = Gf256::xexp(17);
+        let mut gen = vec![Gf256::one()];
+        for i in 0..13u8 {
+            let root = b.exp(i);
+            let mut next = vec![Gf256::zero(); gen.len() + 1];
+            for (j, &g) in gen.iter().enumerate() {
+                next[j + 1] += g;
+                next[j] += g * root;
+            }
+            gen = next;
+        }
+        CyclicCode::new(15, gen).unwrap()
+    }
+
+    #[test]
+    fn test_enumerate_matches_mds() {
+        let dist = enumerate(&rs15().generator_matrix()).unwrap();
+        assert_eq!(dist, mds(15, 2).unwrap());
+        assert_eq!(dist.size(), 65536);
+        assert_eq!(dist.min_distance(), Some(14));
+        assert_eq!(dist.count(14), 15 * 255);
+        assert!(dist.is_mds());
+    }
+
+    #[test]
+    fn test_macwilliams() {
+        // The dual of an MDS code is MDS
+        let dist = mds(6, 2).unwrap();
+        assert_eq!(dist.dual().unwrap(), mds(6, 4).unwrap());
+        assert_eq!(mds(6, 4).unwrap().dual().unwrap(), dist);
+
+        // Binary repetition code of length 3 and its dual, the even weight code
+        let rep = WeightDistribution::from_counts(2, vec![1, 0, 0, 1]);
+        assert_eq!(rep.dual().unwrap().counts(), &[1, 0, 3, 0]);
+        assert!(rep.is_mds());
+    }
+
+    #[test]
+    fn test_limits() {
+        let code = CyclicCode::reed_solomon(4).unwrap();
+        assert_eq!(enumerate(&code.generator_matrix()), Err(WeightError::TooLarge));
+        assert_eq!(mds(255, 223).unwrap_err(), WeightError::Overflow);
+        assert_eq!(mds(3, 4), Err(WeightError::InvalidParameters));
+    }
+}
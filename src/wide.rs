@@ -0,0 +1,230 @@
+//! GF(2^16).
+//!
+//! Gf65536 uses x^16 + x^12 + x^3 + x + 1 (0x1100b) with generator x, the
+//! field of PAR2 recovery files. Other polynomials and generators are
+//! selected through the const parameters, e.g. Gf65536<0x1002d>.
+//!
+//! Multiplication goes through log and exp tables of 65536 u16 entries
+//! each (256 KiB per field), computed at compile time like the tables of
+//! Gf256. With 65535 nonzero elements a Reed-Solomon or Vandermonde code
+//! over this field can have far more than 255 shards.
+
+use core::fmt;
+use core::ops::{ Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign };
+
+/// The PAR2 polynomial
+pub const PAR2_POLY: u32 = 0x1100b;
+
+/// Log and exp tables of GF(2^16). exp[65535] = 1 and log[0] = 0, as for
+/// the tables of Gf256.
+pub struct WideTables {
+	pub exp: [u16; 65536],
+	pub log: [u16; 65536],
+}
+
+/// Carry-less product of a and b reduced modulo poly (x^16 + ...)
+pub const fn mul_mod16(a: u16, b: u16, poly: u32) -> u16 {
+	let low = (poly & 0xffff) as u16;
+	let (mut a, mut b, mut acc) = (a, b, 0u16);
+	while b != 0 {
+		if b & 1 != 0 {
+			acc ^= a;
+		}
+		let carry = a & 0x8000 != 0;
+		a <<= 1;
+		if carry {
+			a ^= low;
+		}
+		b >>= 1;
+	}
+	acc
+}
+
+/// Builds the tables, failing const evaluation if the polynomial does not
+/// have degree 16 or generator is not primitive for it
+pub const fn wide_tables(poly: u32, generator: u16) -> WideTables {
+	assert!(poly >> 16 == 1, "Reduction polynomial must have degree 16");
+	let mut t = WideTables { exp: [0; 65536], log: [0; 65536] };
+	let mut tmp = 1u16;
+	let mut i = 0;
+	while i < 65535 {
+		assert!(i == 0 || tmp != 1, "Generator is not primitive");
+		t.exp[i] = tmp;
+		t.log[tmp as usize] = i as u16;
+		tmp = mul_mod16(tmp, generator, poly);
+		i += 1;
+	}
+	assert!(tmp == 1, "Generator is not primitive");
+	t.exp[65535] = 1;
+	t
+}
+
+/// An element of GF(2^16)
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+#[repr(transparent)]
+pub struct Gf65536<const POLY: u32 = PAR2_POLY, const GENERATOR: u16 = 2> {
+	pub poly: u16
+}
+
+impl<const POLY: u32, const GENERATOR: u16> Gf65536<POLY, GENERATOR> {
+	const TABLES: &'static WideTables = &wide_tables(POLY, GENERATOR);
+
+	#[inline]
+	pub const fn zero() -> Self {
+		Gf65536 { poly: 0 }
+	}
+
+	#[inline]
+	pub const fn one() -> Self {
+		Gf65536 { poly: 1 }
+	}
+
+	#[inline]
+	pub const fn from_u16(v: u16) -> Self {
+		Gf65536 { poly: v }
+	}
+
+	#[inline]
+	pub const fn to_u16(&self) -> u16 {
+		self.poly
+	}
+
+	/// Logarithm to the base GENERATOR, None for zero
+	pub const fn log(&self) -> Option<u16> {
+		if self.poly == 0 {
+			None
+		} else {
+			Some(Self::TABLES.log[self.poly as usize])
+		}
+	}
+
+	/// GENERATOR ^ power
+	pub const fn xexp(power: u32) -> Self {
+		Gf65536 { poly: Self::TABLES.exp[(power % 65535) as usize] }
+	}
+
+	pub const fn exp(&self, power: u32) -> Self {
+		match self.log() {
+			None => Self::zero(),
+			Some(i) => Self::xexp(((i as u64 * power as u64) % 65535) as u32),
+		}
+	}
+
+	/// None for zero
+	pub const fn inv(&self) -> Option<Self> {
+		match self.log() {
+			Some(l) => Some(Self::xexp(65535 - l as u32)),
+			None => None,
+		}
+	}
+
+	pub const fn const_mul(self, rhs: Self) -> Self {
+		match (self.log(), rhs.log()) {
+			(Some(l1), Some(l2)) => Self::xexp(l1 as u32 + l2 as u32),
+			_ => Self::zero(),
+		}
+	}
+
+	/// self / rhs, or None if rhs is zero
+	pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+		match rhs.inv() {
+			Some(r) => Some(self.const_mul(r)),
+			None => None,
+		}
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> fmt::Display for Gf65536<POLY, GENERATOR> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(&self.poly, f)
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> fmt::LowerHex for Gf65536<POLY, GENERATOR> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::LowerHex::fmt(&self.poly, f)
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> Add for Gf65536<POLY, GENERATOR> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Self) -> Self {
+		Gf65536 { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> Sub for Gf65536<POLY, GENERATOR> {
+	type Output = Self;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: Self) -> Self {
+		Gf65536 { poly: self.poly ^ rhs.poly }
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> Mul for Gf65536<POLY, GENERATOR> {
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self {
+		self.const_mul(rhs)
+	}
+}
+
+impl<const POLY: u32, const GENERATOR: u16> Div for Gf65536<POLY, GENERATOR> {
+	type Output = Self;
+	/// Panics if rhs is zero
+	fn div(self, rhs: Self) -> Self {
+		self.checked_div(rhs).expect("Division by zero")
+	}
+}
+
+macro_rules! impl_assign_op {
+	($tr:ident, $method:ident, $op:ident, $op_method:ident) => {
+		impl<const POLY: u32, const GENERATOR: u16> $tr for Gf65536<POLY, GENERATOR> {
+			#[inline]
+			fn $method(&mut self, rhs: Self) {
+				*self = $op::$op_method(*self, rhs);
+			}
+		}
+	};
+}
+
+impl_assign_op!(AddAssign, add_assign, Add, add);
+impl_assign_op!(SubAssign, sub_assign, Sub, sub);
+impl_assign_op!(MulAssign, mul_assign, Mul, mul);
+impl_assign_op!(DivAssign, div_assign, Div, div);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Gf = Gf65536;
+
+    #[test]
+    fn test_tables_match_clmul() {
+        let poly = PAR2_POLY;
+        for a in (0..=u16::MAX).step_by(997) {
+            for b in (0..=u16::MAX).step_by(1009) {
+                let p = Gf::from_u16(a) * Gf::from_u16(b);
+                assert_eq!(p.to_u16(), mul_mod16(a, b, poly));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        for a in (1..=u16::MAX).step_by(257) {
+            let a = Gf::from_u16(a);
+            assert_eq!(a * a.inv().unwrap(), Gf::one());
+            let mut b = Gf::from_u16(0x1234);
+            b /= a;
+            assert_eq!(b * a, Gf::from_u16(0x1234));
+        }
+        assert_eq!(Gf::zero().inv(), None);
+        // x^16 = x^12 + x^3 + x + 1
+        assert_eq!(Gf::xexp(16), Gf::from_u16(0x100b));
+        assert_eq!(Gf::from_u16(2).exp(65535), Gf::one());
+    }
+}